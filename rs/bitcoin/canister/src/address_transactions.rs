@@ -0,0 +1,290 @@
+//! An index of the transactions that spend from or pay to an address.
+//!
+//! The index maps (address, height, txid) to the net amount that the
+//! transaction moved into the address. Entries are sorted by address and then
+//! by descending height, so the history of an address can be read with a
+//! single prefix scan.
+//!
+//! Only the transactions of stable blocks are indexed. A chain that was synced
+//! before the index was introduced only has the transactions of the blocks
+//! that became stable since.
+use crate::{
+    state::{UtxoSet, MAX_ADDRESS_SIZE},
+    types::Storable,
+    utxos::UtxosTrait,
+    PageMapMemory,
+};
+use bitcoin::{Address, Network, Script, Transaction, Txid};
+use ic_btc_types::{AddressTransaction, Height};
+use stable_structures::StableBTreeMap;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::str::FromStr;
+
+/// The index, along with the height of the first block that it holds.
+pub struct AddressTransactions {
+    // The height of the first block whose transactions are indexed.
+    pub start_height: Height,
+
+    // The net amount of each (address, height, txid).
+    pub entries: StableBTreeMap<PageMapMemory, Vec<u8>, Vec<u8>>,
+}
+
+// The size of a transaction ID.
+const TXID_SIZE: u32 = 32;
+
+// The height is a `u32`, which is 4 bytes.
+const HEIGHT_SIZE: u32 = 4;
+
+// A key is the address prefixed with its length, followed by the height and
+// the txid.
+const MAX_KEY_SIZE: u32 = 1 + MAX_ADDRESS_SIZE + HEIGHT_SIZE + TXID_SIZE;
+
+// The net amount is an `i64`, which is 8 bytes.
+const VALUE_SIZE: u32 = 8;
+
+/// Returns true if the index is maintained for `network`.
+///
+/// The index grows with every transaction of the chain. It is disabled on
+/// mainnet, where it would not fit into the memory of the canister.
+pub fn is_enabled(network: Network) -> bool {
+    network != Network::Bitcoin
+}
+
+/// Creates an empty index that starts with the block at `start_height`.
+pub fn new(start_height: Height) -> AddressTransactions {
+    AddressTransactions {
+        start_height,
+        entries: StableBTreeMap::new(PageMapMemory::default(), MAX_KEY_SIZE, VALUE_SIZE),
+    }
+}
+
+/// Loads the index stored in `memory`, or creates an empty index if the
+/// memory is empty.
+pub fn init(start_height: Height, memory: PageMapMemory) -> AddressTransactions {
+    AddressTransactions {
+        start_height,
+        entries: StableBTreeMap::init(memory, MAX_KEY_SIZE, VALUE_SIZE),
+    }
+}
+
+/// Adds the transaction `tx` of the block at `height` to the index.
+///
+/// Must be called before `tx` is inserted into `utxo_set`, as the outputs
+/// spent by `tx` are looked up in `utxo_set`.
+pub fn insert_tx(
+    index: &mut AddressTransactions,
+    utxo_set: &UtxoSet,
+    tx: &Transaction,
+    height: Height,
+) {
+    // NOTE: The amounts cannot overflow, as the maximum number of satoshis
+    // is 2.1 * 10^15, which is well below the max value of an `i64`.
+    let mut net_amounts: BTreeMap<String, i64> = BTreeMap::new();
+
+    if !tx.is_coin_base() {
+        for input in &tx.input {
+            let (txout, _) = utxo_set
+                .utxos
+                .get(&input.previous_output)
+                .unwrap_or_else(|| panic!("Cannot find outpoint: {}", &input.previous_output));
+
+            if let Some(address) = to_address(&txout.script_pubkey, utxo_set.network) {
+                *net_amounts.entry(address).or_default() -= txout.value as i64;
+            }
+        }
+    }
+
+    for output in &tx.output {
+        if let Some(address) = to_address(&output.script_pubkey, utxo_set.network) {
+            *net_amounts.entry(address).or_default() += output.value as i64;
+        }
+    }
+
+    let txid = tx.txid();
+    for (address, net_amount) in net_amounts {
+        index
+            .entries
+            .insert(
+                (address, height, txid).to_bytes(),
+                net_amount.to_le_bytes().to_vec(),
+            )
+            .expect("insertion must succeed");
+    }
+}
+
+/// Returns the transactions of `address` in descending order of height.
+///
+/// If `offset` is set, the transactions start with the transaction at that
+/// (height, txid). At most `limit` transactions are returned, along with the
+/// offset of the next transaction if there are more.
+pub fn get_transactions(
+    index: &AddressTransactions,
+    address: &str,
+    offset: Option<(Height, Txid)>,
+    limit: usize,
+) -> (Vec<AddressTransaction>, Option<(Height, Txid)>) {
+    let mut entries = index
+        .entries
+        .range_with_prefix(
+            address.to_string().to_bytes(),
+            offset.map(|offset| offset.to_bytes()),
+        )
+        .map(|(key, value)| {
+            let (_, height, txid) = <(String, Height, Txid)>::from_bytes(key);
+            let net_amount = i64::from_le_bytes(
+                value
+                    .try_into()
+                    .expect("the net amount must be 8 bytes long"),
+            );
+            (height, txid, net_amount)
+        });
+
+    let transactions = entries
+        .by_ref()
+        .take(limit)
+        .map(|(height, txid, net_amount)| AddressTransaction {
+            txid: txid.to_vec(),
+            height,
+            net_amount,
+        })
+        .collect();
+    let next = entries.next().map(|(height, txid, _)| (height, txid));
+
+    (transactions, next)
+}
+
+// Returns the address that `script` pays to, if any.
+//
+// Like the UTXO index, only addresses that can be parsed back from their
+// string representation are indexed, see `utxoset::insert_utxo`.
+fn to_address(script: &Script, network: Network) -> Option<String> {
+    let address = Address::from_script(script, network)?.to_string();
+    Address::from_str(&address).ok()?;
+    Some(address)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ic_btc_test_utils::{random_p2pkh_address, TransactionBuilder};
+
+    fn insert_txs(
+        index: &mut AddressTransactions,
+        utxo_set: &mut UtxoSet,
+        txs: &[Transaction],
+        height: Height,
+    ) {
+        for tx in txs {
+            insert_tx(index, utxo_set, tx, height);
+            crate::utxoset::insert_tx(utxo_set, tx, height);
+        }
+    }
+
+    #[test]
+    fn records_net_amounts_of_inputs_and_outputs() {
+        let network = Network::Regtest;
+        let address_1 = random_p2pkh_address(network);
+        let address_2 = random_p2pkh_address(network);
+        let mut utxo_set = UtxoSet::new(network);
+        let mut index = new(0);
+
+        let coinbase_tx = TransactionBuilder::coinbase()
+            .with_output(&address_1, 1000)
+            .build();
+        // Spends the coinbase output in the same block, sending change back.
+        let tx = TransactionBuilder::new()
+            .with_input(bitcoin::OutPoint::new(coinbase_tx.txid(), 0))
+            .with_output(&address_2, 600)
+            .with_output(&address_1, 300)
+            .build();
+        insert_txs(
+            &mut index,
+            &mut utxo_set,
+            &[coinbase_tx.clone(), tx.clone()],
+            0,
+        );
+
+        let tx_2 = TransactionBuilder::new()
+            .with_input(bitcoin::OutPoint::new(tx.txid(), 0))
+            .with_output(&address_1, 500)
+            .build();
+        insert_txs(&mut index, &mut utxo_set, &[tx_2.clone()], 1);
+
+        let (transactions, next) =
+            get_transactions(&index, &address_1.to_string(), None, usize::MAX);
+        assert_eq!(next, None);
+        assert_eq!(transactions.len(), 3);
+        // The most recent transaction comes first.
+        assert_eq!(
+            transactions[0],
+            AddressTransaction {
+                txid: tx_2.txid().to_vec(),
+                height: 1,
+                net_amount: 500,
+            }
+        );
+        let mut block_0_amounts: Vec<_> = transactions[1..]
+            .iter()
+            .map(|transaction| {
+                (
+                    transaction.txid.clone(),
+                    transaction.height,
+                    transaction.net_amount,
+                )
+            })
+            .collect();
+        block_0_amounts.sort();
+        let mut expected = vec![
+            (coinbase_tx.txid().to_vec(), 0, 1000),
+            (tx.txid().to_vec(), 0, -700),
+        ];
+        expected.sort();
+        assert_eq!(block_0_amounts, expected);
+
+        let (transactions, _) = get_transactions(&index, &address_2.to_string(), None, usize::MAX);
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|transaction| transaction.net_amount)
+                .collect::<Vec<_>>(),
+            vec![-600, 600]
+        );
+    }
+
+    #[test]
+    fn paginates_transactions() {
+        let network = Network::Regtest;
+        let address = random_p2pkh_address(network);
+        let mut utxo_set = UtxoSet::new(network);
+        let mut index = new(0);
+
+        for height in 0..5 {
+            // Vary the value so that the coinbase transactions are distinct.
+            let coinbase_tx = TransactionBuilder::coinbase()
+                .with_output(&address, 1000 + height as u64)
+                .build();
+            insert_txs(&mut index, &mut utxo_set, &[coinbase_tx], height);
+        }
+
+        let mut heights = vec![];
+        let mut offset = None;
+        loop {
+            let (transactions, next) = get_transactions(&index, &address.to_string(), offset, 2);
+            assert!(transactions.len() <= 2);
+            heights.extend(transactions.iter().map(|transaction| transaction.height));
+            match next {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(heights, vec![4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn disabled_on_mainnet() {
+        assert!(!is_enabled(Network::Bitcoin));
+        assert!(is_enabled(Network::Testnet));
+        assert!(is_enabled(Network::Regtest));
+    }
+}
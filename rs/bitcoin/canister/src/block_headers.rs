@@ -0,0 +1,99 @@
+//! The headers of the stable blocks, indexed by height.
+//!
+//! Stable blocks are removed from the unstable blocks once their transactions
+//! are applied to the UTXO set, so their headers are kept here to serve
+//! `get_block_headers` requests for heights below the anchor.
+use crate::PageMapMemory;
+use bitcoin::{consensus::serialize, BlockHeader};
+use ic_btc_types::Height;
+use stable_structures::StableBTreeMap;
+
+pub type BlockHeaders = StableBTreeMap<PageMapMemory, Vec<u8>, Vec<u8>>;
+
+// The height is a `u32`, which is 4 bytes. It is stored in big-endian so that
+// the keys are sorted by height.
+const KEY_SIZE: u32 = 4;
+
+// A serialized block header is 80 bytes.
+const BLOCK_HEADER_SIZE: u32 = 80;
+
+/// Creates an empty map of headers.
+pub fn new() -> BlockHeaders {
+    StableBTreeMap::new(PageMapMemory::default(), KEY_SIZE, BLOCK_HEADER_SIZE)
+}
+
+/// Loads the headers stored in `memory`, or creates an empty map if the
+/// memory is empty.
+pub fn init(memory: PageMapMemory) -> BlockHeaders {
+    StableBTreeMap::init(memory, KEY_SIZE, BLOCK_HEADER_SIZE)
+}
+
+/// Stores the header of the stable block at `height`.
+pub fn insert(headers: &mut BlockHeaders, height: Height, header: &BlockHeader) {
+    headers
+        .insert(height.to_be_bytes().to_vec(), serialize(header))
+        .expect("insertion must succeed");
+}
+
+/// Returns the lowest height for which a header is stored, if any.
+///
+/// States created before the headers were kept only hold the headers of the
+/// blocks that became stable since.
+pub fn first_height(headers: &BlockHeaders) -> Option<Height> {
+    headers
+        .first_key_value()
+        .map(|(key, _)| height_from_bytes(key))
+}
+
+/// Returns the serialized headers starting at `start_height`, in ascending
+/// order of height. At most `limit` headers are returned.
+pub fn get(headers: &BlockHeaders, start_height: Height, limit: usize) -> Vec<Vec<u8>> {
+    headers
+        .range(start_height.to_be_bytes().to_vec()..)
+        .take(limit)
+        .map(|(_, header)| header)
+        .collect()
+}
+
+fn height_from_bytes(bytes: Vec<u8>) -> Height {
+    let mut height = [0; 4];
+    height.copy_from_slice(&bytes);
+    Height::from_be_bytes(height)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ic_btc_test_utils::BlockBuilder;
+
+    #[test]
+    fn returns_headers_in_order_of_height() {
+        let mut blocks = vec![BlockBuilder::genesis().build()];
+        for _ in 1..300 {
+            let block = BlockBuilder::with_prev_header(blocks.last().unwrap().header).build();
+            blocks.push(block);
+        }
+
+        let mut headers = new();
+        // Heights above 255 check that the keys are sorted numerically.
+        for (height, block) in blocks.iter().enumerate().skip(10) {
+            insert(&mut headers, height as Height, &block.header);
+        }
+
+        assert_eq!(first_height(&headers), Some(10));
+        assert_eq!(
+            get(&headers, 250, 10),
+            blocks[250..260]
+                .iter()
+                .map(|block| serialize(&block.header))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(get(&headers, 295, 10).len(), 5);
+        assert_eq!(get(&headers, 300, 10), Vec::<Vec<u8>>::new());
+    }
+
+    #[test]
+    fn empty_headers_have_no_first_height() {
+        assert_eq!(first_height(&new()), None);
+    }
+}
@@ -2,8 +2,9 @@ pub use crate::fees::get_current_fee_percentiles;
 use crate::{metrics::BitcoinCanisterMetrics, state::State, store};
 use bitcoin::{util::psbt::serialize::Deserialize, Transaction};
 use ic_btc_types::{
-    GetBalanceError, GetUtxosError, GetUtxosResponse, SendTransactionError, SendTransactionRequest,
-    UtxosFilter,
+    BlockchainInfo, GetBalanceError, GetBlockHeadersError, GetBlockHeadersResponse,
+    GetTransactionsError, GetTransactionsResponse, GetUtxosError, GetUtxosResponse, Height, Page,
+    SendTransactionError, SendTransactionRequest, UtxosFilter,
 };
 use ic_btc_types_internal::{
     BitcoinAdapterRequestWrapper, SendTransactionRequest as InternalSendTransactionRequest,
@@ -24,6 +25,16 @@ use ic_replicated_state::bitcoin_state::BitcoinStateError;
 // than 100_000 `Utxo`s are returned in a single response.
 const MAX_UTXOS_PER_RESPONSE: usize = 10_000;
 
+// The maximum number of block headers that are allowed to be included in a
+// single `GetBlockHeadersResponse`. A header is 80 bytes, so a response is
+// well below the max response payload size.
+const MAX_BLOCK_HEADERS_PER_RESPONSE: usize = 1_000;
+
+// The maximum number of transactions that are allowed to be included in a
+// single `GetTransactionsResponse`. An `AddressTransaction` takes less than
+// 50 bytes, so a response is well below the max response payload size.
+const MAX_TRANSACTIONS_PER_RESPONSE: usize = 10_000;
+
 /// The Bitcoin Canister component.
 ///
 /// Maintains information that is needed to be accessed at the bitcoin canister's
@@ -83,6 +94,49 @@ pub fn get_utxos(
     }
 }
 
+/// Retrieves the headers of the main chain blocks with heights in
+/// `start_height..=end_height`, or up to the tip if `end_height` is not set.
+///
+/// If the range holds too many blocks, only the headers of the first
+/// `MAX_BLOCK_HEADERS_PER_RESPONSE` blocks are returned.
+pub fn get_block_headers(
+    state: &State,
+    start_height: Height,
+    end_height: Option<Height>,
+) -> Result<GetBlockHeadersResponse, GetBlockHeadersError> {
+    store::get_block_headers(
+        state,
+        start_height,
+        end_height,
+        MAX_BLOCK_HEADERS_PER_RESPONSE,
+    )
+}
+
+/// Retrieves the height, hash and difficulty of the tip of the main chain and
+/// the height of the latest stable block.
+pub fn get_blockchain_info(state: &State) -> BlockchainInfo {
+    store::get_blockchain_info(state)
+}
+
+/// Retrieves the transactions that spend from or pay to the given Bitcoin
+/// address in stable blocks, most recent first.
+///
+/// If the address has more than `MAX_TRANSACTIONS_PER_RESPONSE` transactions,
+/// the response contains a `next_page` that can be passed to a subsequent
+/// request to retrieve the remaining transactions.
+pub fn get_transactions(
+    state: &State,
+    address: &str,
+    page: Option<Page>,
+) -> Result<GetTransactionsResponse, GetTransactionsError> {
+    store::get_transactions(
+        state,
+        address,
+        page.map(|page| page.to_vec()),
+        MAX_TRANSACTIONS_PER_RESPONSE,
+    )
+}
+
 pub fn send_transaction(
     state: &mut State,
     request: SendTransactionRequest,
@@ -119,7 +173,10 @@ mod test {
     use bitcoin::util::psbt::serialize::Serialize;
     use bitcoin::{blockdata::constants::genesis_block, Address, Block, Network, PublicKey};
     use ic_btc_test_utils::{random_p2tr_address, BlockBuilder, TransactionBuilder};
-    use ic_btc_types::{Network as BtcTypesNetwork, OutPoint, Utxo};
+    use ic_btc_types::{AddressTransaction, Network as BtcTypesNetwork, OutPoint, Utxo};
+    use ic_replicated_state::{
+        bitcoin_state::BitcoinState as ReplicatedBitcoinState, page_map::PageMap,
+    };
 
     // A default state to use for tests.
     fn default_state() -> State {
@@ -509,6 +566,178 @@ mod test {
         }
     }
 
+    // Builds a chain of `num_blocks` blocks and returns its blocks and a state
+    // with a stability threshold of 2 that has processed all of them.
+    fn chain_state(network: Network, num_blocks: usize) -> (Vec<Block>, State) {
+        let mut blocks = vec![BlockBuilder::genesis().build()];
+        for _ in 1..num_blocks {
+            let block = BlockBuilder::with_prev_header(blocks.last().unwrap().header).build();
+            blocks.push(block);
+        }
+
+        let mut state = State::new(2, network, blocks[0].clone());
+        for block in blocks[1..].iter() {
+            store::insert_block(&mut state, block.clone()).unwrap();
+        }
+        (blocks, state)
+    }
+
+    #[test]
+    fn get_block_headers_of_main_chain() {
+        let (blocks, state) = chain_state(Network::Regtest, 6);
+        let stable_height = state.height;
+        assert!(stable_height > 0);
+
+        let headers = |range: std::ops::RangeInclusive<usize>| -> Vec<Vec<u8>> {
+            blocks[range]
+                .iter()
+                .map(|block| bitcoin::consensus::serialize(&block.header))
+                .collect()
+        };
+
+        assert_eq!(
+            get_block_headers(&state, stable_height, None),
+            Ok(GetBlockHeadersResponse {
+                tip_height: 5,
+                block_headers: headers(stable_height as usize..=5),
+            })
+        );
+        assert_eq!(
+            get_block_headers(&state, 5, Some(5)),
+            Ok(GetBlockHeadersResponse {
+                tip_height: 5,
+                block_headers: headers(5..=5),
+            })
+        );
+        assert_eq!(
+            get_block_headers(&state, stable_height, Some(stable_height)),
+            Ok(GetBlockHeadersResponse {
+                tip_height: 5,
+                block_headers: headers(stable_height as usize..=stable_height as usize),
+            })
+        );
+    }
+
+    #[test]
+    fn get_block_headers_rejects_invalid_ranges() {
+        let (_, state) = chain_state(Network::Regtest, 6);
+
+        assert_eq!(
+            get_block_headers(&state, 6, None),
+            Err(GetBlockHeadersError::StartHeightDoesNotExist {
+                requested: 6,
+                chain_height: 5,
+            })
+        );
+        assert_eq!(
+            get_block_headers(&state, 5, Some(6)),
+            Err(GetBlockHeadersError::EndHeightDoesNotExist {
+                requested: 6,
+                chain_height: 5,
+            })
+        );
+        assert_eq!(
+            get_block_headers(&state, 5, Some(4)),
+            Err(GetBlockHeadersError::StartHeightLargerThanEndHeight {
+                start_height: 5,
+                end_height: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn get_block_headers_of_stable_blocks() {
+        let (blocks, state) = chain_state(Network::Regtest, 6);
+        let stable_height = state.height;
+        assert!(stable_height > 1);
+
+        let headers = |range: std::ops::RangeInclusive<usize>| -> Vec<Vec<u8>> {
+            blocks[range]
+                .iter()
+                .map(|block| bitcoin::consensus::serialize(&block.header))
+                .collect()
+        };
+
+        // All headers since genesis are available.
+        assert_eq!(
+            get_block_headers(&state, 0, None),
+            Ok(GetBlockHeadersResponse {
+                tip_height: 5,
+                block_headers: headers(0..=5),
+            })
+        );
+        assert_eq!(
+            get_block_headers(&state, 0, Some(stable_height - 1)),
+            Ok(GetBlockHeadersResponse {
+                tip_height: 5,
+                block_headers: headers(0..=stable_height as usize - 1),
+            })
+        );
+
+        // The response is limited across stable and unstable headers.
+        let response = store::get_block_headers(&state, stable_height - 1, None, 2).unwrap();
+        assert_eq!(
+            response.block_headers,
+            headers(stable_height as usize - 1..=stable_height as usize)
+        );
+    }
+
+    #[test]
+    fn get_block_headers_below_first_stored_header() {
+        let (_, mut state) = chain_state(Network::Regtest, 6);
+        let stable_height = state.height;
+
+        // Simulates a state that started to store headers at the anchor.
+        state.block_headers = crate::block_headers::new();
+
+        assert_eq!(
+            get_block_headers(&state, stable_height - 1, None),
+            Err(GetBlockHeadersError::HeadersNotAvailable {
+                requested: stable_height - 1,
+                min_available_height: stable_height,
+            })
+        );
+    }
+
+    #[test]
+    fn get_block_headers_limits_response_size() {
+        let (blocks, state) = chain_state(Network::Regtest, 10);
+
+        // Only the first of the unstable headers fits in the response.
+        let response = store::get_block_headers(&state, state.height, None, 1).unwrap();
+        assert_eq!(
+            response.block_headers,
+            vec![bitcoin::consensus::serialize(
+                &blocks[state.height as usize].header
+            )]
+        );
+        assert_eq!(response.tip_height, 9);
+    }
+
+    #[test]
+    fn get_blockchain_info_returns_tip() {
+        for network in [
+            Network::Bitcoin,
+            Network::Regtest,
+            Network::Testnet,
+            Network::Signet,
+        ]
+        .iter()
+        {
+            let (blocks, state) = chain_state(*network, 6);
+
+            assert_eq!(
+                get_blockchain_info(&state),
+                BlockchainInfo {
+                    tip_height: 5,
+                    tip_block_hash: blocks[5].block_hash().to_vec(),
+                    difficulty: blocks[5].header.difficulty(*network),
+                    stable_height: state.height,
+                }
+            );
+        }
+    }
+
     #[test]
     fn send_transaction_malformed_transaction() {
         assert_eq!(
@@ -545,6 +774,160 @@ mod test {
         assert_eq!(state.adapter_queues.num_requests(), 1);
     }
 
+    #[test]
+    fn get_transactions_of_stable_blocks() {
+        let network = Network::Regtest;
+        let address_1 = random_p2tr_address(network);
+        let address_2 = random_p2tr_address(network);
+
+        // Create a genesis block where 1000 satoshis are given to address_1, followed
+        // by a block where address_1 gives 800 satoshis to address_2.
+        let coinbase_tx = TransactionBuilder::coinbase()
+            .with_output(&address_1, 1000)
+            .build();
+        let block_0 = BlockBuilder::genesis()
+            .with_transaction(coinbase_tx.clone())
+            .build();
+        let tx = TransactionBuilder::new()
+            .with_input(bitcoin::OutPoint::new(coinbase_tx.txid(), 0))
+            .with_output(&address_2, 800)
+            .build();
+        let block_1 = BlockBuilder::with_prev_header(block_0.header)
+            .with_transaction(tx.clone())
+            .build();
+        let block_2 = BlockBuilder::with_prev_header(block_1.header).build();
+        let block_3 = BlockBuilder::with_prev_header(block_2.header).build();
+
+        let mut state = State::new(1, network, block_0);
+        for block in [block_1, block_2, block_3] {
+            store::insert_block(&mut state, block).unwrap();
+        }
+        assert!(state.height >= 2);
+
+        assert_eq!(
+            get_transactions(&state, &address_1.to_string(), None),
+            Ok(GetTransactionsResponse {
+                transactions: vec![
+                    AddressTransaction {
+                        txid: tx.txid().to_vec(),
+                        height: 1,
+                        net_amount: -1000,
+                    },
+                    AddressTransaction {
+                        txid: coinbase_tx.txid().to_vec(),
+                        height: 0,
+                        net_amount: 1000,
+                    },
+                ],
+                stable_height: state.height,
+                first_indexed_height: 0,
+                next_page: None,
+            })
+        );
+        assert_eq!(
+            get_transactions(&state, &address_2.to_string(), None),
+            Ok(GetTransactionsResponse {
+                transactions: vec![AddressTransaction {
+                    txid: tx.txid().to_vec(),
+                    height: 1,
+                    net_amount: 800,
+                }],
+                stable_height: state.height,
+                first_indexed_height: 0,
+                next_page: None,
+            })
+        );
+
+        // The second page of a paginated response only has the older transaction.
+        let response = store::get_transactions(&state, &address_1.to_string(), None, 1).unwrap();
+        assert_eq!(response.transactions.len(), 1);
+        let response =
+            get_transactions(&state, &address_1.to_string(), response.next_page).unwrap();
+        assert_eq!(
+            response.transactions,
+            vec![AddressTransaction {
+                txid: coinbase_tx.txid().to_vec(),
+                height: 0,
+                net_amount: 1000,
+            }]
+        );
+    }
+
+    #[test]
+    fn get_transactions_errors() {
+        let state = default_state();
+
+        assert_eq!(
+            get_transactions(&state, "not an address", None),
+            Err(GetTransactionsError::MalformedAddress)
+        );
+
+        let address = random_p2tr_address(Network::Regtest).to_string();
+        assert!(matches!(
+            get_transactions(&state, &address, Some(Page::from(vec![1, 2, 3]))),
+            Err(GetTransactionsError::MalformedPage { .. })
+        ));
+
+        // The index is not maintained on mainnet.
+        let state = State::new(1, Network::Bitcoin, genesis_block(Network::Bitcoin));
+        assert!(state.address_transactions.is_none());
+        assert_eq!(
+            get_transactions(
+                &state,
+                &random_p2tr_address(Network::Bitcoin).to_string(),
+                None
+            ),
+            Err(GetTransactionsError::TransactionIndexDisabled)
+        );
+    }
+
+    #[test]
+    fn get_transactions_reports_first_indexed_height() {
+        let network = Network::Regtest;
+        let address = random_p2tr_address(network);
+
+        let mut blocks = vec![BlockBuilder::genesis().build()];
+        for _ in 0..4 {
+            let coinbase_tx = TransactionBuilder::coinbase()
+                .with_output(&address, 1000 + blocks.len() as u64)
+                .build();
+            let block = BlockBuilder::with_prev_header(blocks.last().unwrap().header)
+                .with_transaction(coinbase_tx)
+                .build();
+            blocks.push(block);
+        }
+
+        // The first blocks became stable before the index was introduced.
+        let mut state = State::new(1, network, blocks[0].clone());
+        for block in blocks[1..3].iter() {
+            store::insert_block(&mut state, block.clone()).unwrap();
+        }
+        let first_indexed_height = state.height;
+        assert!(first_indexed_height > 1);
+        let mut replicated_state = ReplicatedBitcoinState::from(state);
+        replicated_state.address_transactions = PageMap::default();
+        replicated_state.address_transactions_start_height = None;
+
+        // The index starts with the next block that becomes stable.
+        let mut state = State::from(replicated_state);
+        for block in blocks[3..].iter() {
+            store::insert_block(&mut state, block.clone()).unwrap();
+        }
+
+        let response = get_transactions(&state, &address.to_string(), None).unwrap();
+        assert_eq!(response.first_indexed_height, first_indexed_height);
+        assert_eq!(
+            response
+                .transactions
+                .iter()
+                .map(|transaction| transaction.height)
+                .collect::<Vec<_>>(),
+            (first_indexed_height..state.height)
+                .rev()
+                .collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn support_taproot_addresses() {
         for network in [
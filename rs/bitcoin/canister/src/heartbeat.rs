@@ -35,6 +35,7 @@ impl BitcoinCanister {
         }
 
         let mut state: State = State::from(bitcoin_state);
+        let network_label = state.utxos.network.to_string();
 
         // Process all incoming responses from the adapter.
         let previous_height = store::main_chain_height(&state);
@@ -45,7 +46,6 @@ impl BitcoinCanister {
 
         match bitcoin_feature.status {
             BitcoinFeatureStatus::Enabled | BitcoinFeatureStatus::Syncing => {
-                let network_label = state.utxos.network.to_string();
                 self.metrics.observe_chain_height(height, &network_label);
                 self.metrics
                     .observe_utxos_length(state.utxos.utxos.len(), &network_label);
@@ -53,6 +53,12 @@ impl BitcoinCanister {
                     state.utxos.address_to_outpoints.len(),
                     &network_label,
                 );
+                if let Some(address_transactions) = &state.address_transactions {
+                    self.metrics.observe_address_transactions_length(
+                        address_transactions.entries.len(),
+                        &network_label,
+                    );
+                }
 
                 if !state.adapter_queues.has_in_flight_get_successors_requests() {
                     let request = get_successors_request(&mut state);
@@ -77,7 +83,14 @@ impl BitcoinCanister {
             }
         }
 
-        state.into()
+        let bitcoin_state = ReplicatedBitcoinState::from(state);
+        self.metrics.observe_address_transactions_size(
+            bitcoin_state.address_transactions_memory_usage().get(),
+            &network_label,
+        );
+        self.metrics
+            .observe_memory_usage(bitcoin_state.memory_usage().get(), &network_label);
+        bitcoin_state
     }
 }
 
@@ -335,4 +348,31 @@ mod tests {
             State::from(ReplicatedBitcoinState::new(BitcoinNetwork::Testnet)).into()
         );
     }
+
+    #[test]
+    fn address_transactions_start_at_the_stable_height_except_on_mainnet() {
+        let bitcoin_canister = BitcoinCanister::new(&MetricsRegistry::new(), no_op_logger());
+
+        // A testnet state synced before the index was introduced.
+        let mut state = ReplicatedBitcoinState::new(BitcoinNetwork::Testnet);
+        state.stable_height = 17;
+        let state = bitcoin_canister.heartbeat(
+            state,
+            BitcoinFeature {
+                network: BitcoinNetwork::Testnet,
+                status: BitcoinFeatureStatus::Enabled,
+            },
+        );
+        // The index starts with the next block that becomes stable.
+        assert_eq!(state.address_transactions_start_height, Some(17));
+
+        let state = bitcoin_canister.heartbeat(
+            ReplicatedBitcoinState::new(BitcoinNetwork::Mainnet),
+            BitcoinFeature {
+                network: BitcoinNetwork::Mainnet,
+                status: BitcoinFeatureStatus::Enabled,
+            },
+        );
+        assert_eq!(state.address_transactions_start_height, None);
+    }
 }
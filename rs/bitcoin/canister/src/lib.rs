@@ -1,4 +1,6 @@
+mod address_transactions;
 mod address_utxoset;
+mod block_headers;
 mod blocktree;
 mod canister;
 mod heartbeat;
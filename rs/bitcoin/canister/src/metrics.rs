@@ -5,6 +5,9 @@ pub struct BitcoinCanisterMetrics {
     pub chain_height: IntGaugeVec,
     pub utxos_length: IntGaugeVec,
     pub address_to_outpoints_length: IntGaugeVec,
+    pub address_transactions_length: IntGaugeVec,
+    pub address_transactions_size: IntGaugeVec,
+    pub memory_usage: IntGaugeVec,
 }
 
 impl BitcoinCanisterMetrics {
@@ -25,6 +28,21 @@ impl BitcoinCanisterMetrics {
                 "The size of address to outpoints map stored by the bitcoin canister.",
                 &["network"],
             ),
+            address_transactions_length: metrics_registry.int_gauge_vec(
+                "bitcoin_canister_address_transactions_length",
+                "The size of the address transaction history index stored by the bitcoin canister.",
+                &["network"],
+            ),
+            address_transactions_size: metrics_registry.int_gauge_vec(
+                "bitcoin_canister_address_transactions_size_bytes",
+                "The memory taken by the address transaction history index in bytes. Not charged to any canister.",
+                &["network"],
+            ),
+            memory_usage: metrics_registry.int_gauge_vec(
+                "bitcoin_canister_memory_usage_bytes",
+                "The memory taken by the page maps of the bitcoin canister in bytes.",
+                &["network"],
+            ),
         }
     }
 
@@ -49,4 +67,30 @@ impl BitcoinCanisterMetrics {
             .with_label_values(&[network_label])
             .set(address_to_outpoints_length as i64);
     }
+
+    pub fn observe_address_transactions_length(
+        &self,
+        address_transactions_length: u64,
+        network_label: &str,
+    ) {
+        self.address_transactions_length
+            .with_label_values(&[network_label])
+            .set(address_transactions_length as i64);
+    }
+
+    pub fn observe_address_transactions_size(
+        &self,
+        address_transactions_size: u64,
+        network_label: &str,
+    ) {
+        self.address_transactions_size
+            .with_label_values(&[network_label])
+            .set(address_transactions_size as i64);
+    }
+
+    pub fn observe_memory_usage(&self, memory_usage: u64, network_label: &str) {
+        self.memory_usage
+            .with_label_values(&[network_label])
+            .set(memory_usage as i64);
+    }
 }
@@ -1,4 +1,8 @@
-use crate::{proto, PageMapMemory};
+use crate::{
+    address_transactions::{self, AddressTransactions},
+    block_headers::{self, BlockHeaders},
+    proto, PageMapMemory,
+};
 use bitcoin::{hashes::Hash, Block, Network, OutPoint, Script, TxOut, Txid};
 use ic_btc_types::Height;
 use ic_protobuf::bitcoin::v1;
@@ -6,7 +10,7 @@ use ic_replicated_state::bitcoin_state::{
     AdapterQueues, BitcoinState as ReplicatedBitcoinState, FeePercentilesCache, UnstableBlocks,
     UtxoSet as ReplicatedUtxoSet,
 };
-use ic_replicated_state::page_map::PersistenceError;
+use ic_replicated_state::page_map::{PageMap, PersistenceError};
 use ic_state_layout::{AccessPolicy, ProtoFileWith, RwPolicy};
 use stable_structures::StableBTreeMap;
 use std::collections::BTreeMap;
@@ -20,6 +24,9 @@ pub struct State {
     // The UTXOs of all stable blocks since genesis.
     pub utxos: UtxoSet,
 
+    // The headers of the stable blocks, indexed by height.
+    pub block_headers: BlockHeaders,
+
     // Blocks inserted, but are not considered stable yet.
     pub unstable_blocks: UnstableBlocks,
 
@@ -28,6 +35,10 @@ pub struct State {
 
     // Cache for the current fee percentiles.
     pub fee_percentiles_cache: Option<FeePercentilesCache>,
+
+    // The transactions of each address in stable blocks, if the index is
+    // enabled for the network.
+    pub address_transactions: Option<AddressTransactions>,
 }
 
 impl State {
@@ -40,9 +51,12 @@ impl State {
         Self {
             height: 0,
             utxos: UtxoSet::new(network),
+            block_headers: block_headers::new(),
             unstable_blocks: UnstableBlocks::new(stability_threshold, genesis_block),
             adapter_queues: AdapterQueues::default(),
             fee_percentiles_cache: None,
+            address_transactions: address_transactions::is_enabled(network)
+                .then(|| address_transactions::new(0)),
         }
     }
 
@@ -73,7 +87,20 @@ impl State {
             .utxos
             .medium_utxos
             .get_memory()
-            .persist_and_sync_delta(&root.join("medium_utxos.bin"))
+            .persist_and_sync_delta(&root.join("medium_utxos.bin"))?;
+
+        self.block_headers
+            .get_memory()
+            .persist_and_sync_delta(&root.join("block_headers.bin"))?;
+
+        if let Some(address_transactions) = &self.address_transactions {
+            address_transactions
+                .entries
+                .get_memory()
+                .persist_and_sync_delta(&root.join("address_transactions.bin"))?;
+        }
+
+        Ok(())
     }
 
     // TODO(EXC-1093): Guard this function with a rust feature. It's only needed in local scripts.
@@ -85,18 +112,43 @@ impl State {
         let state_file: ProtoFileWith<proto::State, RwPolicy> = root.join("state.pbuf").into();
         let proto_state = state_file.deserialize_opt().unwrap().unwrap();
 
+        let utxos = UtxoSet::from_proto(
+            proto_state.utxos.unwrap(),
+            small_utxos_memory,
+            medium_utxos_memory,
+            address_to_outpoints_memory,
+        );
+
+        // States serialized before the headers were kept don't have them.
+        let block_headers_path = root.join("block_headers.bin");
+        let block_headers = if block_headers_path.exists() {
+            block_headers::init(PageMapMemory::open(&block_headers_path)?)
+        } else {
+            block_headers::new()
+        };
+
+        // States serialized before the index was introduced don't have it.
+        // Their index starts with the next block that becomes stable.
+        let address_transactions = if !address_transactions::is_enabled(utxos.network) {
+            None
+        } else if let Some(start_height) = proto_state.address_transactions_start_height {
+            Some(address_transactions::init(
+                start_height,
+                PageMapMemory::open(&root.join("address_transactions.bin"))?,
+            ))
+        } else {
+            Some(address_transactions::new(proto_state.height))
+        };
+
         Ok(Self {
             adapter_queues: AdapterQueues::default(),
             height: proto_state.height,
-            utxos: UtxoSet::from_proto(
-                proto_state.utxos.unwrap(),
-                small_utxos_memory,
-                medium_utxos_memory,
-                address_to_outpoints_memory,
-            ),
+            utxos,
+            block_headers,
             unstable_blocks: UnstableBlocks::try_from(proto_state.unstable_blocks.unwrap())
                 .unwrap(),
             fee_percentiles_cache: None,
+            address_transactions,
         })
     }
 }
@@ -106,6 +158,19 @@ impl From<ReplicatedBitcoinState> for State {
         let utxos_small = state.utxo_set.utxos_small;
         let utxos_medium = state.utxo_set.utxos_medium;
         let address_outpoints = state.utxo_set.address_outpoints;
+        let block_headers_page_map = state.block_headers;
+        let address_transactions_page_map = state.address_transactions;
+        // States synced before the index was introduced don't have it. Their
+        // index starts with the next block that becomes stable.
+        let address_transactions =
+            address_transactions::is_enabled(state.utxo_set.network).then(|| {
+                address_transactions::init(
+                    state
+                        .address_transactions_start_height
+                        .unwrap_or(state.stable_height),
+                    PageMapMemory::new(address_transactions_page_map),
+                )
+            });
 
         Self {
             adapter_queues: state.adapter_queues,
@@ -132,7 +197,9 @@ impl From<ReplicatedBitcoinState> for State {
                     0,
                 ),
             },
+            block_headers: block_headers::init(PageMapMemory::new(block_headers_page_map)),
             fee_percentiles_cache: state.fee_percentiles_cache,
+            address_transactions,
         }
     }
 }
@@ -155,6 +222,16 @@ impl From<State> for ReplicatedBitcoinState {
                 network: state.utxos.network,
             },
             fee_percentiles_cache: state.fee_percentiles_cache,
+            block_headers: state.block_headers.get_memory().into_page_map(),
+            address_transactions_start_height: state
+                .address_transactions
+                .as_ref()
+                .map(|address_transactions| address_transactions.start_height),
+            address_transactions: state
+                .address_transactions
+                .map_or_else(PageMap::default, |address_transactions| {
+                    address_transactions.entries.get_memory().into_page_map()
+                }),
         }
     }
 }
@@ -165,6 +242,10 @@ impl From<&State> for proto::State {
             height: state.height,
             utxos: Some(state.utxos.to_proto()),
             unstable_blocks: Some(v1::UnstableBlocks::from(&state.unstable_blocks)),
+            address_transactions_start_height: state
+                .address_transactions
+                .as_ref()
+                .map(|address_transactions| address_transactions.start_height),
         }
     }
 }
@@ -247,7 +328,7 @@ pub const UTXO_VALUE_MAX_SIZE_MEDIUM: u32 = TX_OUT_MAX_SIZE_MEDIUM + HEIGHT_SIZE
 
 // The longest addresses are bech32 addresses, and a bech32 string can be at most 90 chars.
 // See https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
-const MAX_ADDRESS_SIZE: u32 = 90;
+pub(crate) const MAX_ADDRESS_SIZE: u32 = 90;
 const MAX_ADDRESS_OUTPOINT_SIZE: u32 = MAX_ADDRESS_SIZE + OUTPOINT_SIZE;
 
 impl Default for Utxos {
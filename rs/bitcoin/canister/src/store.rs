@@ -1,11 +1,16 @@
 use crate::{
+    address_transactions, block_headers,
     blocktree::{BlockChain, BlockDoesNotExtendTree},
     state::State,
-    types::Page,
+    types::{Page, TransactionsPage},
     unstable_blocks, utxoset,
 };
-use bitcoin::{hashes::Hash, Address, Block, OutPoint, Txid};
-use ic_btc_types::{GetBalanceError, GetUtxosError, GetUtxosResponse, Height, Satoshi};
+use bitcoin::{consensus::serialize, hashes::Hash, Address, Block, OutPoint, Txid};
+use ic_btc_types::{
+    BlockchainInfo, GetBalanceError, GetBlockHeadersError, GetBlockHeadersResponse,
+    GetTransactionsError, GetTransactionsResponse, GetUtxosError, GetUtxosResponse, Height,
+    Satoshi,
+};
 use lazy_static::lazy_static;
 use serde_bytes::ByteBuf;
 use std::str::FromStr;
@@ -189,19 +194,158 @@ pub fn insert_block(state: &mut State, block: Block) -> Result<(), BlockDoesNotE
     // TODO(EXC-932): Process all stable blocks, not just one.
     if let Some(new_stable_block) = unstable_blocks::pop(&mut state.unstable_blocks) {
         for tx in &new_stable_block.txdata {
+            // The transaction is indexed first, as indexing looks up the
+            // outputs that it spends in the UTXO set.
+            if let Some(index) = &mut state.address_transactions {
+                address_transactions::insert_tx(index, &state.utxos, tx, state.height);
+            }
             utxoset::insert_tx(&mut state.utxos, tx, state.height);
         }
 
+        block_headers::insert(
+            &mut state.block_headers,
+            state.height,
+            &new_stable_block.header,
+        );
         state.height += 1;
     }
 
     Ok(())
 }
 
+/// Returns the transactions that spend from or pay to a bitcoin address, in
+/// descending order of height.
+///
+/// Only the transactions of stable blocks are indexed. If the optional `page`
+/// is set, the transactions start from that page reference. At most
+/// `transaction_limit` transactions are returned, along with a page reference
+/// to the remaining transactions if there are more.
+pub fn get_transactions(
+    state: &State,
+    address: &str,
+    page: Option<Vec<u8>>,
+    transaction_limit: usize,
+) -> Result<GetTransactionsResponse, GetTransactionsError> {
+    let index = state
+        .address_transactions
+        .as_ref()
+        .ok_or(GetTransactionsError::TransactionIndexDisabled)?;
+
+    if Address::from_str(address).is_err() {
+        return Err(GetTransactionsError::MalformedAddress);
+    }
+
+    let offset = page
+        .map(TransactionsPage::from_bytes)
+        .transpose()
+        .map_err(|err| GetTransactionsError::MalformedPage { err })?
+        .map(|page| (page.height, page.txid));
+
+    let (transactions, next) =
+        address_transactions::get_transactions(index, address, offset, transaction_limit);
+
+    Ok(GetTransactionsResponse {
+        transactions,
+        stable_height: state.height,
+        first_indexed_height: index.start_height,
+        next_page: next
+            .map(|(height, txid)| ByteBuf::from(TransactionsPage { height, txid }.to_bytes())),
+    })
+}
+
 pub fn main_chain_height(state: &State) -> Height {
     unstable_blocks::get_main_chain(&state.unstable_blocks).len() as u32 + state.height - 1
 }
 
+/// Returns the headers of the main chain blocks with heights in
+/// `start_height..=end_height`. `end_height` defaults to the height of the tip.
+///
+/// The headers of stable blocks are available from the height at which the
+/// state started to keep them. At most `header_limit` headers are returned,
+/// starting with the header at `start_height`.
+pub fn get_block_headers(
+    state: &State,
+    start_height: Height,
+    end_height: Option<Height>,
+    header_limit: usize,
+) -> Result<GetBlockHeadersResponse, GetBlockHeadersError> {
+    let chain = unstable_blocks::get_main_chain(&state.unstable_blocks);
+    let tip_height = state.height + (chain.len() as u32) - 1;
+    let end_height = end_height.unwrap_or(tip_height);
+
+    if start_height > tip_height {
+        return Err(GetBlockHeadersError::StartHeightDoesNotExist {
+            requested: start_height,
+            chain_height: tip_height,
+        });
+    }
+
+    if end_height > tip_height {
+        return Err(GetBlockHeadersError::EndHeightDoesNotExist {
+            requested: end_height,
+            chain_height: tip_height,
+        });
+    }
+
+    if start_height > end_height {
+        return Err(GetBlockHeadersError::StartHeightLargerThanEndHeight {
+            start_height,
+            end_height,
+        });
+    }
+
+    // The headers of stable blocks are only kept from the height at which
+    // the state started to store them.
+    let min_available_height =
+        block_headers::first_height(&state.block_headers).unwrap_or(state.height);
+    if start_height < min_available_height {
+        return Err(GetBlockHeadersError::HeadersNotAvailable {
+            requested: start_height,
+            min_available_height,
+        });
+    }
+
+    let num_headers = ((end_height - start_height) as usize + 1).min(header_limit);
+
+    // The headers of stable blocks come first, followed by the headers of the
+    // main chain starting at the anchor.
+    let mut headers = if start_height < state.height {
+        block_headers::get(
+            &state.block_headers,
+            start_height,
+            num_headers.min((state.height - start_height) as usize),
+        )
+    } else {
+        vec![]
+    };
+
+    let unstable_start_height = start_height.max(state.height);
+    headers.extend(
+        chain
+            .into_chain()
+            .into_iter()
+            .skip((unstable_start_height - state.height) as usize)
+            .take(num_headers - headers.len())
+            .map(|block| serialize(&block.header)),
+    );
+
+    Ok(GetBlockHeadersResponse {
+        tip_height,
+        block_headers: headers,
+    })
+}
+
+/// Returns information about the tip of the main chain.
+pub fn get_blockchain_info(state: &State) -> BlockchainInfo {
+    let tip = unstable_blocks::get_main_chain(&state.unstable_blocks).tip();
+    BlockchainInfo {
+        tip_height: main_chain_height(state),
+        tip_block_hash: tip.block_hash().to_vec(),
+        difficulty: tip.header.difficulty(state.utxos.network),
+        stable_height: state.height,
+    }
+}
+
 pub fn get_unstable_blocks(state: &State) -> Vec<&Block> {
     unstable_blocks::get_blocks(&state.unstable_blocks)
 }
@@ -61,6 +61,32 @@ impl Page {
     }
 }
 
+/// Used to signal the cut-off point for returning chunked transaction
+/// history results.
+pub struct TransactionsPage {
+    pub height: Height,
+    pub txid: Txid,
+}
+
+impl TransactionsPage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        (self.height, self.txid).to_bytes()
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, String> {
+        // The page consists of 36 bytes and is the concatenation of the following:
+        //
+        //   1) A `Height` (4 bytes)
+        //   2) A `Txid` (32 bytes)
+        if bytes.len() != 36 {
+            return Err(format!("Invalid length {} != 36 for page", bytes.len()));
+        }
+
+        let (height, txid) = <(Height, Txid)>::from_bytes(bytes);
+        Ok(TransactionsPage { height, txid })
+    }
+}
+
 fn outpoint_from_bytes(bytes: Vec<u8>) -> Result<OutPoint, String> {
     if bytes.len() != 36 {
         return Err(format!("Invalid length {} != 36 for outpoint", bytes.len()));
@@ -209,6 +235,53 @@ impl Storable for (Height, OutPoint) {
     }
 }
 
+impl Storable for Txid {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Self {
+        assert_eq!(bytes.len(), 32);
+        Txid::from_hash(Hash::from_slice(&bytes).unwrap())
+    }
+}
+
+impl Storable for (Height, Txid) {
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![self.0.to_bytes(), Txid::to_bytes(&self.1)]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    fn from_bytes(mut bytes: Vec<u8>) -> Self {
+        let txid_offset = 4;
+        let txid_bytes = bytes.split_off(txid_offset);
+
+        (Height::from_bytes(bytes), Txid::from_bytes(txid_bytes))
+    }
+}
+
+impl Storable for (Address, Height, Txid) {
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![
+            Address::to_bytes(&self.0),
+            <(Height, Txid)>::to_bytes(&(self.1, self.2)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn from_bytes(mut bytes: Vec<u8>) -> Self {
+        let address_len = bytes[0] as usize;
+        let height_txid_bytes = bytes.split_off(address_len + 1);
+        let (height, txid) = <(Height, Txid)>::from_bytes(height_txid_bytes);
+
+        (Address::from_bytes(bytes), height, txid)
+    }
+}
+
 #[test]
 fn parsing_empty_page_fails() {
     assert!(Page::from_bytes(vec![]).is_err());
@@ -225,3 +298,11 @@ fn parsing_page_with_invalid_length_fails() {
 fn parsing_page_with_exact_length_succeeds() {
     assert!(Page::from_bytes(vec![0; 72]).is_ok());
 }
+
+#[test]
+fn parsing_transactions_page_with_invalid_length_fails() {
+    assert!(TransactionsPage::from_bytes(vec![]).is_err());
+    assert!(TransactionsPage::from_bytes(vec![0; 35]).is_err());
+    assert!(TransactionsPage::from_bytes(vec![0; 37]).is_err());
+    assert!(TransactionsPage::from_bytes(vec![0; 36]).is_ok());
+}
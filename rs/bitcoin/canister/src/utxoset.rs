@@ -281,7 +281,7 @@ mod test {
         // Verify that the entries returned are sorted in descending height.
         assert_eq!(
             utxo.address_to_outpoints
-                .range(address.to_bytes(), None)
+                .range_with_prefix(address.to_bytes(), None)
                 .map(|(k, _)| {
                     let (_, height, _) = <(AddressStr, Height, OutPoint)>::from_bytes(k);
                     height
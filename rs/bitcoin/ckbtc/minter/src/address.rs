@@ -0,0 +1,75 @@
+///! Conversion between Bitcoin addresses and the scripts of the outputs
+///! paying to them.
+use crate::tx::hash160;
+use bitcoin::util::address::{Address, Payload, WitnessVersion};
+use ic_btc_types::Network;
+use std::str::FromStr;
+
+fn to_bitcoin_network(network: Network) -> bitcoin::Network {
+    match network {
+        Network::Mainnet => bitcoin::Network::Bitcoin,
+        Network::Testnet => bitcoin::Network::Testnet,
+        Network::Regtest => bitcoin::Network::Regtest,
+    }
+}
+
+/// Returns the script_pubkey of the outputs paying to `address`.
+///
+/// Returns an error if the address is malformed or if it does not belong to
+/// `network`.
+pub fn script_pubkey(address: &str, network: Network) -> Result<Vec<u8>, String> {
+    let address = Address::from_str(address).map_err(|err| err.to_string())?;
+    let expected_network = to_bitcoin_network(network);
+    // Legacy regtest addresses use the testnet prefixes.
+    let is_valid_network = address.network == expected_network
+        || (expected_network == bitcoin::Network::Regtest
+            && address.network == bitcoin::Network::Testnet);
+    if !is_valid_network {
+        return Err(format!(
+            "address {} is not valid on network {}",
+            address, network
+        ));
+    }
+    Ok(address.script_pubkey().into_bytes())
+}
+
+/// Returns the P2WPKH address of the compressed public key `pubkey`.
+pub fn p2wpkh_address(pubkey: &[u8], network: Network) -> String {
+    Address {
+        network: to_bitcoin_network(network),
+        payload: Payload::WitnessProgram {
+            version: WitnessVersion::V0,
+            program: hash160(pubkey).to_vec(),
+        },
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx::p2wpkh_script_pubkey;
+
+    // The P2WPKH example of BIP-173.
+    const PUBKEY: [u8; 33] = [
+        0x02, 0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce, 0x87,
+        0x0b, 0x07, 0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81, 0x5b, 0x16,
+        0xf8, 0x17, 0x98,
+    ];
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    #[test]
+    fn test_p2wpkh_address() {
+        assert_eq!(p2wpkh_address(&PUBKEY, Network::Mainnet), ADDRESS);
+    }
+
+    #[test]
+    fn test_script_pubkey() {
+        assert_eq!(
+            script_pubkey(ADDRESS, Network::Mainnet),
+            Ok(p2wpkh_script_pubkey(&hash160(&PUBKEY)))
+        );
+        assert!(script_pubkey(ADDRESS, Network::Testnet).is_err());
+        assert!(script_pubkey("not an address", Network::Mainnet).is_err());
+    }
+}
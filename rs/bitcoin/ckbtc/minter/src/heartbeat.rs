@@ -0,0 +1,764 @@
+///! Processing of the retrieve_btc requests.
+///!
+///! Every `PROCESSING_INTERVAL_NANOS` a heartbeat of the minter:
+///! - pays out pending requests with a new transaction,
+///! - finalizes the requests whose transaction has enough confirmations,
+///! - replaces the transactions that take too long to be confirmed by
+///!   transactions paying a higher fee.
+use crate::updates::get_btc_address::account_derivation_path;
+use ic_btc_types::{MillisatoshiPerByte, Network, Utxo};
+use ic_ckbtc_minter::address::{p2wpkh_address, script_pubkey};
+use ic_ckbtc_minter::runtime::{CallError, Runtime};
+use ic_ckbtc_minter::state::{
+    audit, mutate_state, read_state, Account, RetrieveBtcRequest, SubmittedBtcTransaction,
+};
+use ic_ckbtc_minter::tx::{
+    encode_signature, hash160, p2wpkh_script_pubkey, SignedTransaction, TxOut, UnsignedInput,
+    UnsignedTransaction, Witness, RBF_SEQUENCE,
+};
+use std::collections::BTreeMap;
+
+/// The maximum number of requests paid out by a single transaction.
+const MAX_REQUESTS_PER_TX: usize = 100;
+
+/// The fee rate used when the Bitcoin canister has not observed enough
+/// transactions to compute fee percentiles, e.g. on regtest.
+const DEFAULT_FEE_PER_VBYTE: MillisatoshiPerByte = 2_000;
+
+/// The minimum increase of the fee rate of a replacement transaction. BIP-125
+/// requires replacements to pay for their own relay.
+const MIN_RELAY_FEE_PER_VBYTE: MillisatoshiPerByte = 1_000;
+
+/// The time after which an unconfirmed transaction is replaced.
+const RESUBMIT_AFTER_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// The minimum value of the outputs of the minter's transactions, in
+/// satoshi. Outputs with a lower value are not relayed by Bitcoin nodes.
+const MIN_OUTPUT_VALUE: u64 = 1_000;
+
+/// The minimum time between two heartbeats processing the withdrawals.
+/// Processing makes paid calls to the Bitcoin canister, and a new block
+/// is only mined every ten minutes on average.
+const PROCESSING_INTERVAL_NANOS: u64 = 5 * 60 * 1_000_000_000;
+
+#[derive(Debug, PartialEq, Eq)]
+enum BuildTxError {
+    /// The share of the fee of some request is not covered by its amount.
+    AmountTooLow {
+        block_index: u64,
+    },
+    MalformedAddress(String),
+}
+
+/// A scope guard for the processing of the withdrawals.
+/// It sets the `is_heartbeat_running` flag when constructed and clears the
+/// flag when dropped, including when a heartbeat stops at an early return.
+struct HeartbeatGuard(());
+
+impl HeartbeatGuard {
+    /// Returns `None` if there is already one active `HeartbeatGuard`.
+    fn new() -> Option<Self> {
+        mutate_state(|s| {
+            if s.is_heartbeat_running {
+                return None;
+            }
+            s.is_heartbeat_running = true;
+            Some(HeartbeatGuard(()))
+        })
+    }
+}
+
+impl Drop for HeartbeatGuard {
+    fn drop(&mut self) {
+        mutate_state(|s| s.is_heartbeat_running = false);
+    }
+}
+
+pub async fn heartbeat(runtime: &dyn Runtime) {
+    let _guard = match HeartbeatGuard::new() {
+        Some(guard) => guard,
+        None => return,
+    };
+
+    let now = runtime.time();
+    let is_due = read_state(|s| {
+        s.last_processing_time.map_or(true, |last| {
+            now.saturating_sub(last) >= PROCESSING_INTERVAL_NANOS
+        })
+    });
+    if !is_due {
+        return;
+    }
+    mutate_state(|s| s.last_processing_time = Some(now));
+
+    submit_pending_requests(runtime).await;
+    finalize_requests(runtime).await;
+    resubmit_transactions(runtime).await;
+}
+
+/// The account controlling the minter's change outputs.
+fn main_account(runtime: &dyn Runtime) -> Account {
+    Account {
+        owner: runtime.id(),
+        subaccount: None,
+    }
+}
+
+/// Returns the public key of the main account, fetching it on first use.
+async fn main_public_key(runtime: &dyn Runtime) -> Result<Vec<u8>, CallError> {
+    if let Some(main_pubkey) = read_state(|s| s.main_public_key.clone()) {
+        return Ok(main_pubkey);
+    }
+    let account = main_account(runtime);
+    let key_name = read_state(|s| s.ecdsa_key_name.clone());
+    let main_pubkey = runtime
+        .ecdsa_public_key(
+            key_name,
+            account_derivation_path(account.owner, account.subaccount),
+        )
+        .await?;
+    mutate_state(|s| s.main_public_key = Some(main_pubkey.clone()));
+    Ok(main_pubkey)
+}
+
+/// Returns the median fee rate of the recent transactions.
+async fn estimate_fee_per_vbyte(
+    runtime: &dyn Runtime,
+    network: Network,
+) -> Result<MillisatoshiPerByte, CallError> {
+    let percentiles = runtime.get_current_fee_percentiles(network).await?;
+    Ok(percentiles
+        .get(percentiles.len() / 2)
+        .copied()
+        .unwrap_or(DEFAULT_FEE_PER_VBYTE))
+}
+
+/// Removes from `available_utxos` and returns UTXOs worth at least `target`
+/// satoshi, preferring large UTXOs to keep the transactions small.
+///
+/// Returns `None` and leaves `available_utxos` untouched if the available
+/// UTXOs are not worth enough.
+fn select_utxos(
+    available_utxos: &mut BTreeMap<Utxo, Account>,
+    target: u64,
+) -> Option<Vec<(Utxo, Account)>> {
+    let mut candidates: Vec<&Utxo> = available_utxos.keys().collect();
+    candidates.sort_by(|a, b| b.value.cmp(&a.value));
+
+    let mut selected = Vec::new();
+    let mut total = 0_u64;
+    for utxo in candidates {
+        if total >= target {
+            break;
+        }
+        total = total.saturating_add(utxo.value);
+        selected.push(utxo.clone());
+    }
+    if total < target {
+        return None;
+    }
+
+    Some(
+        selected
+            .into_iter()
+            .map(|utxo| {
+                let account = available_utxos.remove(&utxo).unwrap();
+                (utxo, account)
+            })
+            .collect(),
+    )
+}
+
+/// Builds a transaction spending `utxos` that pays out `requests` and sends
+/// the remaining value back to the key with hash `main_pubkey_hash`.
+///
+/// The fee is split evenly among the requests and deducted from their
+/// amounts.
+fn build_unsigned_transaction(
+    requests: &[RetrieveBtcRequest],
+    utxos: &[(Utxo, Account)],
+    main_pubkey_hash: &[u8; 20],
+    fee_per_vbyte: MillisatoshiPerByte,
+    network: Network,
+) -> Result<UnsignedTransaction, BuildTxError> {
+    let inputs = utxos
+        .iter()
+        .map(|(utxo, _)| UnsignedInput {
+            previous_output: utxo.outpoint.clone(),
+            value: utxo.value,
+            sequence: RBF_SEQUENCE,
+        })
+        .collect();
+
+    let mut outputs = Vec::with_capacity(requests.len() + 1);
+    for request in requests {
+        outputs.push(TxOut {
+            value: request.amount,
+            script_pubkey: script_pubkey(&request.address, network)
+                .map_err(BuildTxError::MalformedAddress)?,
+        });
+    }
+    let inputs_value: u64 = utxos.iter().map(|(utxo, _)| utxo.value).sum();
+    let requests_value: u64 = requests.iter().map(|request| request.amount).sum();
+    outputs.push(TxOut {
+        value: inputs_value - requests_value,
+        script_pubkey: p2wpkh_script_pubkey(main_pubkey_hash),
+    });
+
+    let mut tx = UnsignedTransaction {
+        inputs,
+        outputs,
+        lock_time: 0,
+    };
+
+    let fee = (tx.estimate_vsize() * fee_per_vbyte + 999) / 1000;
+    let fee_share = (fee + requests.len() as u64 - 1) / requests.len() as u64;
+    for (output, request) in tx.outputs.iter_mut().zip(requests) {
+        if output.value < fee_share + MIN_OUTPUT_VALUE {
+            return Err(BuildTxError::AmountTooLow {
+                block_index: request.block_index,
+            });
+        }
+        output.value -= fee_share;
+    }
+    Ok(tx)
+}
+
+/// Removes from `requests` and returns the requests whose amount does not
+/// cover their share of the fee of a transaction spending `utxos`.
+fn remove_requests_below_fee_share(
+    requests: &mut Vec<RetrieveBtcRequest>,
+    utxos: &[(Utxo, Account)],
+    main_pubkey_hash: &[u8; 20],
+    fee_per_vbyte: MillisatoshiPerByte,
+    network: Network,
+) -> Vec<RetrieveBtcRequest> {
+    let mut removed = Vec::new();
+    // The share of the fee grows as requests are removed.
+    while !requests.is_empty() {
+        match build_unsigned_transaction(requests, utxos, main_pubkey_hash, fee_per_vbyte, network)
+        {
+            Err(BuildTxError::AmountTooLow { block_index }) => {
+                let position = requests
+                    .iter()
+                    .position(|request| request.block_index == block_index)
+                    .expect("the request must be part of the batch");
+                removed.push(requests.remove(position));
+            }
+            _ => break,
+        }
+    }
+    removed
+}
+
+/// Signs all the inputs of `unsigned_tx`; the input at index `i` spends a
+/// UTXO controlled by `accounts[i]`.
+async fn sign_transaction(
+    runtime: &dyn Runtime,
+    key_name: &str,
+    unsigned_tx: UnsignedTransaction,
+    accounts: &[Account],
+) -> Result<SignedTransaction, CallError> {
+    let mut public_keys: BTreeMap<Vec<Vec<u8>>, Vec<u8>> = BTreeMap::new();
+    let mut witnesses = Vec::with_capacity(accounts.len());
+    for (index, account) in accounts.iter().enumerate() {
+        let derivation_path = account_derivation_path(account.owner, account.subaccount);
+        let pubkey = match public_keys.get(&derivation_path) {
+            Some(pubkey) => pubkey.clone(),
+            None => {
+                let pubkey = runtime
+                    .ecdsa_public_key(key_name.to_string(), derivation_path.clone())
+                    .await?;
+                public_keys.insert(derivation_path.clone(), pubkey.clone());
+                pubkey
+            }
+        };
+        let sighash = unsigned_tx.sighash(index, &hash160(&pubkey));
+        let signature = runtime
+            .sign_with_ecdsa(key_name.to_string(), derivation_path, sighash)
+            .await?;
+        witnesses.push(Witness {
+            signature: encode_signature(&signature),
+            pubkey,
+        });
+    }
+    Ok(SignedTransaction {
+        unsigned_tx,
+        witnesses,
+    })
+}
+
+/// Builds, signs and sends a transaction. Returns the id of the transaction.
+async fn sign_and_send(
+    runtime: &dyn Runtime,
+    requests: &[RetrieveBtcRequest],
+    utxos: &[(Utxo, Account)],
+    main_pubkey: &[u8],
+    fee_per_vbyte: MillisatoshiPerByte,
+) -> Result<Vec<u8>, String> {
+    let (network, key_name) = read_state(|s| (s.btc_network, s.ecdsa_key_name.clone()));
+    let unsigned_tx = build_unsigned_transaction(
+        requests,
+        utxos,
+        &hash160(main_pubkey),
+        fee_per_vbyte,
+        network,
+    )
+    .map_err(|err| format!("failed to build the transaction: {:?}", err))?;
+    let accounts: Vec<Account> = utxos.iter().map(|(_, account)| account.clone()).collect();
+    let signed_tx = sign_transaction(runtime, &key_name, unsigned_tx, &accounts)
+        .await
+        .map_err(|err| format!("{} failed: {}", err.method, err.reason))?;
+    runtime
+        .send_transaction(network, signed_tx.serialize())
+        .await
+        .map_err(|err| format!("{} failed: {}", err.method, err.reason))?;
+    Ok(signed_tx.txid())
+}
+
+/// Pays out the oldest pending requests with a new transaction.
+async fn submit_pending_requests(runtime: &dyn Runtime) {
+    // Avoid the paid calls below if the available UTXOs cannot pay out the
+    // oldest request.
+    let can_pay_out = read_state(|s| {
+        let available_value: u64 = s.available_utxos.keys().map(|utxo| utxo.value).sum();
+        s.pending_retrieve_btc_requests
+            .first()
+            .map_or(false, |request| {
+                request.amount + MIN_OUTPUT_VALUE <= available_value
+            })
+    });
+    if !can_pay_out {
+        return;
+    }
+    let network = read_state(|s| s.btc_network);
+    let (fee_per_vbyte, main_pubkey) = match (
+        estimate_fee_per_vbyte(runtime, network).await,
+        main_public_key(runtime).await,
+    ) {
+        (Ok(fee_per_vbyte), Ok(main_pubkey)) => (fee_per_vbyte, main_pubkey),
+        (Err(err), _) | (_, Err(err)) => {
+            ic_cdk::println!("{} failed: {}", err.method, err.reason);
+            return;
+        }
+    };
+
+    // Take the requests and the UTXOs out of the state so that they are not
+    // used concurrently while the transaction is signed.
+    let batch = mutate_state(|s| {
+        let available_value: u64 = s.available_utxos.keys().map(|utxo| utxo.value).sum();
+        let mut requests_value = 0;
+        let mut num_requests = 0;
+        for request in s.pending_retrieve_btc_requests.iter() {
+            if num_requests == MAX_REQUESTS_PER_TX
+                || requests_value + request.amount + MIN_OUTPUT_VALUE > available_value
+            {
+                break;
+            }
+            requests_value += request.amount;
+            num_requests += 1;
+        }
+        if num_requests == 0 {
+            return None;
+        }
+        let utxos = select_utxos(&mut s.available_utxos, requests_value + MIN_OUTPUT_VALUE)?;
+        let requests: Vec<RetrieveBtcRequest> = s
+            .pending_retrieve_btc_requests
+            .drain(..num_requests)
+            .collect();
+        for request in &requests {
+            s.requests_in_flight.insert(request.block_index);
+        }
+        Some((requests, utxos))
+    });
+    let (mut requests, utxos) = match batch {
+        Some(batch) => batch,
+        None => return,
+    };
+
+    // The requests that cannot cover their share of the fee are dropped
+    // instead of holding up the other requests. Putting them back into the
+    // queue would retry them on every heartbeat without ever paying them out.
+    let dropped_requests = remove_requests_below_fee_share(
+        &mut requests,
+        &utxos,
+        &hash160(&main_pubkey),
+        fee_per_vbyte,
+        network,
+    );
+    if !dropped_requests.is_empty() {
+        mutate_state(|s| {
+            for request in &dropped_requests {
+                s.requests_in_flight.remove(&request.block_index);
+                audit::remove_retrieve_btc_request(s, request.block_index);
+            }
+        });
+    }
+    if requests.is_empty() {
+        mutate_state(|s| s.available_utxos.extend(utxos));
+        return;
+    }
+
+    let result = sign_and_send(runtime, &requests, &utxos, &main_pubkey, fee_per_vbyte).await;
+    mutate_state(|s| {
+        for request in &requests {
+            s.requests_in_flight.remove(&request.block_index);
+        }
+    });
+    match result {
+        Ok(txid) => mutate_state(|s| {
+            audit::sent_transaction(
+                s,
+                SubmittedBtcTransaction {
+                    requests,
+                    txid,
+                    replaced_txids: vec![],
+                    used_utxos: utxos,
+                    submitted_at: runtime.time(),
+                    fee_per_vbyte,
+                },
+            )
+        }),
+        Err(err) => {
+            ic_cdk::println!("failed to submit a transaction: {}", err);
+            mutate_state(|s| {
+                s.available_utxos.extend(utxos);
+                s.pending_retrieve_btc_requests.splice(0..0, requests);
+            });
+        }
+    }
+}
+
+/// Finalizes the requests paid out by transactions with enough confirmations.
+///
+/// A transaction is confirmed once its change output shows up among the
+/// UTXOs of the minter's main address. The change output becomes available
+/// for new transactions.
+async fn finalize_requests(runtime: &dyn Runtime) {
+    if read_state(|s| s.submitted_transactions.is_empty()) {
+        return;
+    }
+    let (network, min_confirmations) = read_state(|s| (s.btc_network, s.min_confirmations));
+    let main_pubkey = match main_public_key(runtime).await {
+        Ok(main_pubkey) => main_pubkey,
+        Err(err) => {
+            ic_cdk::println!("{} failed: {}", err.method, err.reason);
+            return;
+        }
+    };
+    let utxos = match runtime
+        .get_utxos(
+            network,
+            p2wpkh_address(&main_pubkey, network),
+            min_confirmations,
+        )
+        .await
+    {
+        Ok(utxos) => utxos,
+        Err(err) => {
+            ic_cdk::println!("{} failed: {}", err.method, err.reason);
+            return;
+        }
+    };
+
+    let main_account = main_account(runtime);
+    mutate_state(|s| {
+        for utxo in utxos {
+            // Most UTXOs of the main address are not change outputs of
+            // submitted transactions and are skipped.
+            audit::confirmed_transaction(s, utxo.outpoint.txid.clone(), utxo, main_account.clone());
+        }
+    });
+}
+
+/// Replaces the transactions that were not confirmed in time by transactions
+/// spending the same UTXOs with a higher fee.
+async fn resubmit_transactions(runtime: &dyn Runtime) {
+    let now = runtime.time();
+    let stuck_transactions: Vec<SubmittedBtcTransaction> = read_state(|s| {
+        s.submitted_transactions
+            .iter()
+            .filter(|tx| now.saturating_sub(tx.submitted_at) >= RESUBMIT_AFTER_NANOS)
+            .cloned()
+            .collect()
+    });
+    if stuck_transactions.is_empty() {
+        return;
+    }
+    let network = read_state(|s| s.btc_network);
+    let (current_fee_per_vbyte, main_pubkey) = match (
+        estimate_fee_per_vbyte(runtime, network).await,
+        main_public_key(runtime).await,
+    ) {
+        (Ok(fee_per_vbyte), Ok(main_pubkey)) => (fee_per_vbyte, main_pubkey),
+        (Err(err), _) | (_, Err(err)) => {
+            ic_cdk::println!("{} failed: {}", err.method, err.reason);
+            return;
+        }
+    };
+
+    for tx in stuck_transactions {
+        let fee_per_vbyte = current_fee_per_vbyte.max(tx.fee_per_vbyte + MIN_RELAY_FEE_PER_VBYTE);
+        match sign_and_send(
+            runtime,
+            &tx.requests,
+            &tx.used_utxos,
+            &main_pubkey,
+            fee_per_vbyte,
+        )
+        .await
+        {
+            Ok(new_txid) => mutate_state(|s| {
+                // The transaction may have been finalized in the meantime.
+                audit::replaced_transaction(
+                    s,
+                    tx.txid.clone(),
+                    new_txid,
+                    runtime.time(),
+                    fee_per_vbyte,
+                );
+            }),
+            Err(err) => ic_cdk::println!("failed to resubmit a transaction: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+    use ic_btc_types::OutPoint;
+    use ic_ckbtc_minter::runtime::MockRuntime;
+    use ic_ckbtc_minter::state::{replace_state, CkBtcMinterState, RetrieveBtcStatus};
+
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const MAIN_PUBKEY: [u8; 33] = [2; 33];
+
+    fn init_state() {
+        let mut state = CkBtcMinterState::new(
+            Network::Mainnet,
+            Principal::from_text("2chl6-4hpzw-vqaaa-aaaaa-c").unwrap(),
+            "key_1".to_string(),
+            6,
+            1_000,
+            10_000,
+        );
+        state.available_utxos.insert(
+            utxo(vec![1; 32], 0, 100_000),
+            Account {
+                owner: Principal::anonymous(),
+                subaccount: None,
+            },
+        );
+        state
+            .pending_retrieve_btc_requests
+            .push(RetrieveBtcRequest {
+                amount: 50_000,
+                address: ADDRESS.to_string(),
+                block_index: 3,
+                received_at: 0,
+            });
+        replace_state(state);
+    }
+
+    fn utxo(txid: Vec<u8>, vout: u32, value: u64) -> Utxo {
+        Utxo {
+            outpoint: OutPoint { txid, vout },
+            value,
+            height: 10,
+        }
+    }
+
+    fn runtime() -> MockRuntime {
+        MockRuntime::new()
+            .set_id_result(Principal::management_canister())
+            .set_time_result(0)
+            .set_fee_percentiles_result(Ok(vec![]))
+            .set_ecdsa_public_key_result(Ok(MAIN_PUBKEY.to_vec()))
+            .set_sign_with_ecdsa_result(Ok(vec![1; 64]))
+            .set_send_transaction_result(Ok(()))
+            .set_get_utxos_result(Ok(vec![]))
+    }
+
+    fn submitted_txid() -> Vec<u8> {
+        match read_state(|s| s.retrieve_btc_status(3)) {
+            RetrieveBtcStatus::Submitted { txid } => txid,
+            status => panic!("unexpected status {:?}", status),
+        }
+    }
+
+    #[test]
+    fn test_heartbeat_submits_and_finalizes_requests() {
+        init_state();
+        let runtime = runtime();
+
+        tokio_test::block_on(heartbeat(&runtime));
+        assert_eq!(runtime.sent_transactions().len(), 1);
+        let txid = submitted_txid();
+        read_state(|s| {
+            assert!(s.available_utxos.is_empty());
+            assert!(s.pending_retrieve_btc_requests.is_empty());
+            assert!(s.requests_in_flight.is_empty());
+            assert!(!s.is_heartbeat_running);
+            assert_eq!(s.main_public_key, Some(MAIN_PUBKEY.to_vec()));
+            let tx = &s.submitted_transactions[0];
+            assert_eq!(tx.fee_per_vbyte, DEFAULT_FEE_PER_VBYTE);
+        });
+
+        // The change output gets enough confirmations.
+        let change = utxo(txid.clone(), 1, 50_000);
+        let runtime = runtime
+            .set_get_utxos_result(Ok(vec![change.clone()]))
+            .set_time_result(PROCESSING_INTERVAL_NANOS);
+        tokio_test::block_on(heartbeat(&runtime));
+        read_state(|s| {
+            assert_eq!(
+                s.retrieve_btc_status(3),
+                RetrieveBtcStatus::Confirmed { txid }
+            );
+            assert!(s.submitted_transactions.is_empty());
+            assert_eq!(
+                s.available_utxos.get(&change),
+                Some(&main_account(&runtime))
+            );
+        });
+    }
+
+    #[test]
+    fn test_heartbeat_keeps_requests_pending_without_enough_utxos() {
+        init_state();
+        mutate_state(|s| s.available_utxos.clear());
+        // The mocked calls are not set, so the heartbeat must not make any.
+        let runtime = MockRuntime::new()
+            .set_id_result(Principal::management_canister())
+            .set_time_result(0);
+
+        tokio_test::block_on(heartbeat(&runtime));
+        read_state(|s| {
+            assert_eq!(s.retrieve_btc_status(3), RetrieveBtcStatus::Pending);
+            assert!(!s.is_heartbeat_running);
+        });
+    }
+
+    #[test]
+    fn test_heartbeat_processes_withdrawals_once_per_interval() {
+        init_state();
+        let runtime = runtime();
+        tokio_test::block_on(heartbeat(&runtime));
+        let txid = submitted_txid();
+
+        // The change output is confirmed, but it is too early to check.
+        let runtime = runtime
+            .set_get_utxos_result(Ok(vec![utxo(txid.clone(), 1, 50_000)]))
+            .set_time_result(PROCESSING_INTERVAL_NANOS - 1);
+        tokio_test::block_on(heartbeat(&runtime));
+        read_state(|s| {
+            assert_eq!(
+                s.retrieve_btc_status(3),
+                RetrieveBtcStatus::Submitted { txid: txid.clone() }
+            );
+            assert!(!s.is_heartbeat_running);
+        });
+
+        let runtime = runtime.set_time_result(PROCESSING_INTERVAL_NANOS);
+        tokio_test::block_on(heartbeat(&runtime));
+        read_state(|s| {
+            assert_eq!(
+                s.retrieve_btc_status(3),
+                RetrieveBtcStatus::Confirmed { txid }
+            )
+        });
+    }
+
+    #[test]
+    fn test_heartbeat_drops_requests_that_cannot_pay_their_fee() {
+        init_state();
+        mutate_state(|s| {
+            s.pending_retrieve_btc_requests.insert(
+                0,
+                RetrieveBtcRequest {
+                    amount: MIN_OUTPUT_VALUE,
+                    address: ADDRESS.to_string(),
+                    block_index: 2,
+                    received_at: 0,
+                },
+            )
+        });
+        let runtime = runtime();
+
+        tokio_test::block_on(heartbeat(&runtime));
+        assert_eq!(runtime.sent_transactions().len(), 1);
+        submitted_txid();
+        read_state(|s| {
+            assert_eq!(s.retrieve_btc_status(2), RetrieveBtcStatus::AmountTooLow);
+            assert!(s.pending_retrieve_btc_requests.is_empty());
+            assert!(s.requests_in_flight.is_empty());
+            let tx = &s.submitted_transactions[0];
+            assert_eq!(tx.requests.len(), 1);
+            assert_eq!(tx.requests[0].block_index, 3);
+        });
+    }
+
+    #[test]
+    fn test_heartbeat_replaces_stuck_transactions() {
+        init_state();
+        let runtime = runtime();
+        tokio_test::block_on(heartbeat(&runtime));
+        let txid = submitted_txid();
+
+        let runtime = runtime.set_time_result(RESUBMIT_AFTER_NANOS);
+        tokio_test::block_on(heartbeat(&runtime));
+        assert_eq!(runtime.sent_transactions().len(), 2);
+        let new_txid = submitted_txid();
+        assert_ne!(new_txid, txid);
+        read_state(|s| {
+            let tx = &s.submitted_transactions[0];
+            assert_eq!(tx.replaced_txids, vec![txid.clone()]);
+            assert_eq!(
+                tx.fee_per_vbyte,
+                DEFAULT_FEE_PER_VBYTE + MIN_RELAY_FEE_PER_VBYTE
+            );
+            assert_eq!(tx.submitted_at, RESUBMIT_AFTER_NANOS);
+        });
+
+        // The replaced transaction may still be the one that gets confirmed.
+        let runtime = runtime
+            .set_get_utxos_result(Ok(vec![utxo(txid.clone(), 1, 50_000)]))
+            .set_time_result(RESUBMIT_AFTER_NANOS + PROCESSING_INTERVAL_NANOS);
+        tokio_test::block_on(heartbeat(&runtime));
+        read_state(|s| {
+            assert_eq!(
+                s.retrieve_btc_status(3),
+                RetrieveBtcStatus::Confirmed { txid }
+            )
+        });
+    }
+
+    #[test]
+    fn test_fee_is_deducted_from_the_requests() {
+        let requests = vec![RetrieveBtcRequest {
+            amount: 50_000,
+            address: ADDRESS.to_string(),
+            block_index: 3,
+            received_at: 0,
+        }];
+        let utxos = vec![(
+            utxo(vec![1; 32], 0, 100_000),
+            Account {
+                owner: Principal::anonymous(),
+                subaccount: None,
+            },
+        )];
+
+        let tx = build_unsigned_transaction(&requests, &utxos, &[4; 20], 2_000, Network::Mainnet)
+            .unwrap();
+        let fee = (tx.estimate_vsize() * 2_000 + 999) / 1000;
+        assert_eq!(tx.outputs[0].value, 50_000 - fee);
+        assert_eq!(tx.outputs[1].value, 50_000);
+
+        assert_eq!(
+            build_unsigned_transaction(&requests, &utxos, &[4; 20], 400_000, Network::Mainnet),
+            Err(BuildTxError::AmountTooLow { block_index: 3 })
+        );
+    }
+}
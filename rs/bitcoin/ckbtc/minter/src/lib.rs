@@ -1,2 +1,5 @@
+pub mod address;
 pub mod runtime;
 pub mod state;
+pub mod storage;
+pub mod tx;
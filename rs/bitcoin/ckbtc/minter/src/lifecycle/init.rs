@@ -1,8 +1,41 @@
-use candid::{CandidType, Deserialize};
+use candid::{CandidType, Deserialize, Principal};
+use ic_btc_types::Network;
 use ic_ckbtc_minter::runtime::Runtime;
+use ic_ckbtc_minter::state::{audit, replace_state, CkBtcMinterState};
 use serde::Serialize;
 
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
-pub struct InitArgs {}
+pub struct InitArgs {
+    /// The Bitcoin network that the minter will connect to
+    pub btc_network: Network,
 
-pub fn init(_args: InitArgs, _runtime: &mut dyn Runtime) {}
+    /// The principal of the ckBTC ledger that the minter mints on
+    pub ledger_id: Principal,
+
+    /// The name of the threshold ECDSA key controlling the minter's
+    /// Bitcoin addresses
+    pub ecdsa_key_name: String,
+
+    /// The minimum number of confirmations required for the minter to
+    /// accept a Bitcoin transaction
+    pub min_confirmations: u32,
+
+    /// The fee in satoshi deducted from every deposit to pay for KYT checks
+    pub kyt_fee: u64,
+
+    /// The minimum amount in satoshi of a retrieve_btc request
+    pub retrieve_btc_min_amount: u64,
+}
+
+pub fn init(args: InitArgs, _runtime: &mut dyn Runtime) {
+    let state = CkBtcMinterState::new(
+        args.btc_network,
+        args.ledger_id,
+        args.ecdsa_key_name,
+        args.min_confirmations,
+        args.kyt_fee,
+        args.retrieve_btc_min_amount,
+    );
+    audit::init(&state);
+    replace_state(state);
+}
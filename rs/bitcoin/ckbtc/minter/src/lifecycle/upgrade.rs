@@ -1,14 +1,21 @@
 use candid::{CandidType, Deserialize};
 use ic_ckbtc_minter::runtime::Runtime;
+use ic_ckbtc_minter::state::{eventlog::replay, replace_state};
+use ic_ckbtc_minter::storage::events;
 use serde::Serialize;
 
 #[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct UpgradeArgs {}
 
 pub fn pre_upgrade(_runtime: &mut dyn Runtime) {
+    // The state is reconstructed from the event log, which already lives in
+    // stable memory.
     ic_cdk::println!("Executing pre upgrade");
 }
 
 pub fn post_upgrade(_args: UpgradeArgs, _runtime: &mut dyn Runtime) {
     ic_cdk::println!("Executing post upgrade");
+    let state = replay(events())
+        .unwrap_or_else(|err| ic_cdk::trap(&format!("failed to replay the event log: {:?}", err)));
+    replace_state(state);
 }
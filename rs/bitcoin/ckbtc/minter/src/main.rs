@@ -1,16 +1,22 @@
 use crate::metrics::encode_metrics;
+use crate::queries::{get_events::GetEventsArgs, retrieve_btc_status::RetrieveBtcStatusArgs};
 use crate::updates::{
     get_btc_address::{GetBtcAddressArgs, GetBtcAddressResult},
     get_withdrawal_account::GetWithdrawalAccountResult,
+    retrieve_btc::{RetrieveBtcArgs, RetrieveBtcError, RetrieveBtcOk},
+    update_balance::{UpdateBalanceArgs, UpdateBalanceError, UpdateBalanceResult},
 };
 use candid::candid_method;
-use ic_cdk_macros::{init, post_upgrade, pre_upgrade, update};
+use ic_cdk_macros::{heartbeat, init, post_upgrade, pre_upgrade, query, update};
 use ic_ckbtc_minter::runtime::CanisterRuntime;
+use ic_ckbtc_minter::state::{eventlog::Event, RetrieveBtcStatus};
 use lifecycle::init::InitArgs;
 use lifecycle::upgrade::UpgradeArgs;
 
+mod heartbeat;
 mod lifecycle;
 mod metrics;
+mod queries;
 mod updates;
 
 #[init]
@@ -28,10 +34,17 @@ fn post_upgrade(args: UpgradeArgs) {
     lifecycle::post_upgrade(args, &mut CanisterRuntime {})
 }
 
+#[heartbeat]
+async fn heartbeat() {
+    heartbeat::heartbeat(&CanisterRuntime {}).await
+}
+
 #[candid_method(update)]
 #[update]
-fn get_btc_address(args: GetBtcAddressArgs) -> GetBtcAddressResult {
+async fn get_btc_address(args: GetBtcAddressArgs) -> GetBtcAddressResult {
     updates::get_btc_address(args, &CanisterRuntime {})
+        .await
+        .unwrap_or_else(|err| ic_cdk::trap(&format!("{} failed: {}", err.method, err.reason)))
 }
 
 #[candid_method(update)]
@@ -40,6 +53,32 @@ fn get_withdrawal_account() -> GetWithdrawalAccountResult {
     updates::get_withdrawal_account(&CanisterRuntime {})
 }
 
+#[candid_method(update)]
+#[update]
+async fn update_balance(
+    args: UpdateBalanceArgs,
+) -> Result<UpdateBalanceResult, UpdateBalanceError> {
+    updates::update_balance(args, &CanisterRuntime {}).await
+}
+
+#[candid_method(update)]
+#[update]
+async fn retrieve_btc(args: RetrieveBtcArgs) -> Result<RetrieveBtcOk, RetrieveBtcError> {
+    updates::retrieve_btc(args, &CanisterRuntime {}).await
+}
+
+#[candid_method(query)]
+#[query]
+fn retrieve_btc_status(args: RetrieveBtcStatusArgs) -> RetrieveBtcStatus {
+    queries::retrieve_btc_status(args)
+}
+
+#[candid_method(query)]
+#[query]
+fn get_events(args: GetEventsArgs) -> Vec<Event> {
+    queries::get_events(args)
+}
+
 #[export_name = "canister_query http_request"]
 fn http_request() {
     dfn_http_metrics::serve_metrics(encode_metrics);
@@ -1,3 +1,5 @@
+use ic_ckbtc_minter::storage::count_events;
+
 pub fn encode_metrics(
     metrics: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>,
 ) -> std::io::Result<()> {
@@ -6,5 +8,10 @@ pub fn encode_metrics(
         ic_cdk::api::stable::stable_size() as f64,
         "Size of the stable memory allocated by this canister.",
     )?;
+    metrics.encode_gauge(
+        "ckbtc_minter_event_count",
+        count_events() as f64,
+        "Number of events in the event log, which is replayed on upgrade.",
+    )?;
     Ok(())
 }
@@ -0,0 +1,5 @@
+pub mod get_events;
+pub mod retrieve_btc_status;
+
+pub use get_events::get_events;
+pub use retrieve_btc_status::retrieve_btc_status;
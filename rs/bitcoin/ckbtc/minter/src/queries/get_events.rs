@@ -0,0 +1,20 @@
+use candid::{CandidType, Deserialize};
+use ic_ckbtc_minter::state::eventlog::Event;
+use serde::Serialize;
+
+/// The maximum number of events returned by a single `get_events` call.
+const MAX_EVENTS_PER_QUERY: u64 = 2000;
+
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct GetEventsArgs {
+    /// The index of the first event to return.
+    pub start: u64,
+    /// The maximum number of events to return.
+    pub length: u64,
+}
+
+/// Returns the events of the event log starting at index `start`, so that
+/// auditors can replay the state transitions of the minter.
+pub fn get_events(args: GetEventsArgs) -> Vec<Event> {
+    ic_ckbtc_minter::storage::get_events(args.start, args.length.min(MAX_EVENTS_PER_QUERY))
+}
@@ -0,0 +1,15 @@
+use candid::{CandidType, Deserialize};
+use ic_ckbtc_minter::state::{read_state, RetrieveBtcStatus};
+use serde::Serialize;
+
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RetrieveBtcStatusArgs {
+    /// The block index returned by `retrieve_btc`.
+    pub block_index: u64,
+}
+
+/// Returns the status of the retrieve_btc request identified by the index of
+/// the ledger block that burned the ckBTC.
+pub fn retrieve_btc_status(args: RetrieveBtcStatusArgs) -> RetrieveBtcStatus {
+    read_state(|s| s.retrieve_btc_status(args.block_index))
+}
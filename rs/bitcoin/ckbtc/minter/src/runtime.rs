@@ -3,9 +3,84 @@
 ///! The [`Runtime`] trait is the abstraction and has two implementations:
 ///! - [`MockRuntime`] provides a mocked implementation of the runtime
 ///! - [`CanisterRuntime`] provides the real implementation of the runtime
+use crate::address::p2wpkh_address;
 use async_trait::async_trait;
-use bitcoin::AddressType;
-use candid::Principal;
+use candid::{CandidType, Deserialize, Principal};
+use ic_btc_types::{
+    GetCurrentFeePercentilesRequest, GetUtxosRequest, GetUtxosResponse, MillisatoshiPerByte,
+    Network, SendTransactionRequest, Utxo, UtxosFilter,
+};
+use ic_ledger_types::{
+    AccountIdentifier, Memo, Subaccount, Tokens, TransferArgs, DEFAULT_SUBACCOUNT,
+};
+use std::sync::{Arc, Mutex};
+
+/// The cycles attached to every `bitcoin_get_utxos` call.
+const GET_UTXOS_COST_CYCLES: u64 = 100_000_000;
+
+/// The cycles attached to every `bitcoin_get_current_fee_percentiles` call.
+const GET_CURRENT_FEE_PERCENTILES_COST_CYCLES: u64 = 100_000_000;
+
+/// The cycles attached to every `bitcoin_send_transaction` call, in addition
+/// to the cycles charged per byte of the transaction.
+const SEND_TRANSACTION_BASE_COST_CYCLES: u64 = 5_000_000_000;
+const SEND_TRANSACTION_COST_CYCLES_PER_BYTE: u64 = 20_000_000;
+
+/// The cycles attached to every `sign_with_ecdsa` call.
+const SIGN_WITH_ECDSA_COST_CYCLES: u64 = 10_000_000_000;
+
+#[derive(CandidType, Deserialize)]
+enum EcdsaCurve {
+    #[serde(rename = "secp256k1")]
+    Secp256k1,
+}
+
+#[derive(CandidType, Deserialize)]
+struct EcdsaKeyId {
+    curve: EcdsaCurve,
+    name: String,
+}
+
+#[derive(CandidType, Deserialize)]
+struct EcdsaPublicKeyArgs {
+    canister_id: Option<Principal>,
+    derivation_path: Vec<Vec<u8>>,
+    key_id: EcdsaKeyId,
+}
+
+#[derive(CandidType, Deserialize)]
+struct EcdsaPublicKeyResponse {
+    public_key: Vec<u8>,
+    chain_code: Vec<u8>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct SignWithEcdsaArgs {
+    message_hash: Vec<u8>,
+    derivation_path: Vec<Vec<u8>>,
+    key_id: EcdsaKeyId,
+}
+
+#[derive(CandidType, Deserialize)]
+struct SignWithEcdsaResponse {
+    signature: Vec<u8>,
+}
+
+/// The error returned when a call to another canister fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallError {
+    pub method: String,
+    pub reason: String,
+}
+
+impl CallError {
+    fn new(method: &str, reason: String) -> Self {
+        Self {
+            method: method.to_string(),
+            reason,
+        }
+    }
+}
 
 /// Represents all the dependencies of the ckBTC Minter.
 #[async_trait]
@@ -16,8 +91,74 @@ pub trait Runtime {
     /// The principal of the caller
     fn caller(&self) -> Principal;
 
-    /// Return the Bitcoin address for the given path and type
-    fn address(&self, derivation_path: Vec<Vec<u8>>, address_type: &AddressType) -> String;
+    /// The current time in nanoseconds since the epoch
+    fn time(&self) -> u64;
+
+    /// Return the P2WPKH address on `network` of the threshold ECDSA key
+    /// `key_name` derived with `derivation_path`
+    async fn address(
+        &self,
+        key_name: String,
+        network: Network,
+        derivation_path: Vec<Vec<u8>>,
+    ) -> Result<String, CallError>;
+
+    /// Return all the UTXOs of the given address that have at least
+    /// `min_confirmations` confirmations
+    async fn get_utxos(
+        &self,
+        network: Network,
+        address: String,
+        min_confirmations: u32,
+    ) -> Result<Vec<Utxo>, CallError>;
+
+    /// Mint `amount` ckBTC on the ledger `ledger_id` to the account `to` and
+    /// return the index of the ledger block containing the transaction
+    async fn mint(
+        &self,
+        ledger_id: Principal,
+        to: AccountIdentifier,
+        amount: u64,
+    ) -> Result<u64, CallError>;
+
+    /// Burn `amount` ckBTC held by the minter in `from_subaccount` and
+    /// return the index of the ledger block containing the transaction
+    async fn burn(
+        &self,
+        ledger_id: Principal,
+        from_subaccount: Subaccount,
+        amount: u64,
+    ) -> Result<u64, CallError>;
+
+    /// Return the SEC1 compressed public key of the threshold ECDSA key
+    /// `key_name` derived with `derivation_path`
+    async fn ecdsa_public_key(
+        &self,
+        key_name: String,
+        derivation_path: Vec<Vec<u8>>,
+    ) -> Result<Vec<u8>, CallError>;
+
+    /// Sign `message_hash` with the threshold ECDSA key `key_name` derived
+    /// with `derivation_path` and return the 64-byte signature
+    async fn sign_with_ecdsa(
+        &self,
+        key_name: String,
+        derivation_path: Vec<Vec<u8>>,
+        message_hash: [u8; 32],
+    ) -> Result<Vec<u8>, CallError>;
+
+    /// Send the serialized `transaction` to the Bitcoin network
+    async fn send_transaction(
+        &self,
+        network: Network,
+        transaction: Vec<u8>,
+    ) -> Result<(), CallError>;
+
+    /// Return the percentiles of the fees of the recent transactions
+    async fn get_current_fee_percentiles(
+        &self,
+        network: Network,
+    ) -> Result<Vec<MillisatoshiPerByte>, CallError>;
 }
 
 /// [`Runtime`] implementation calling the real ic primitives.
@@ -33,8 +174,179 @@ impl Runtime for CanisterRuntime {
         ic_cdk::caller()
     }
 
-    fn address(&self, _derivation_path: Vec<Vec<u8>>, _address_type: &AddressType) -> String {
-        todo!()
+    fn time(&self) -> u64 {
+        ic_cdk::api::time()
+    }
+
+    async fn address(
+        &self,
+        key_name: String,
+        network: Network,
+        derivation_path: Vec<Vec<u8>>,
+    ) -> Result<String, CallError> {
+        let public_key = self.ecdsa_public_key(key_name, derivation_path).await?;
+        Ok(p2wpkh_address(&public_key, network))
+    }
+
+    async fn get_utxos(
+        &self,
+        network: Network,
+        address: String,
+        min_confirmations: u32,
+    ) -> Result<Vec<Utxo>, CallError> {
+        const METHOD: &str = "bitcoin_get_utxos";
+        let mut utxos = Vec::new();
+        let mut filter = Some(UtxosFilter::MinConfirmations(min_confirmations));
+        loop {
+            let request = GetUtxosRequest {
+                address: address.clone(),
+                network,
+                filter,
+            };
+            let (response,): (GetUtxosResponse,) = ic_cdk::api::call::call_with_payment(
+                Principal::management_canister(),
+                METHOD,
+                (request,),
+                GET_UTXOS_COST_CYCLES,
+            )
+            .await
+            .map_err(|(code, msg)| CallError::new(METHOD, format!("{:?}: {}", code, msg)))?;
+            utxos.extend(response.utxos);
+            match response.next_page {
+                Some(page) => filter = Some(UtxosFilter::Page(page)),
+                None => return Ok(utxos),
+            }
+        }
+    }
+
+    async fn mint(
+        &self,
+        ledger_id: Principal,
+        to: AccountIdentifier,
+        amount: u64,
+    ) -> Result<u64, CallError> {
+        const METHOD: &str = "transfer";
+        // The minter owns the minting account of the ledger, so transfers
+        // from its default subaccount are mints and are not charged a fee.
+        let args = TransferArgs {
+            memo: Memo(0),
+            amount: Tokens::from_e8s(amount),
+            fee: Tokens::from_e8s(0),
+            from_subaccount: None,
+            to,
+            created_at_time: None,
+        };
+        ic_ledger_types::transfer(ledger_id, args)
+            .await
+            .map_err(|(code, msg)| CallError::new(METHOD, format!("{:?}: {}", code, msg)))?
+            .map_err(|err| CallError::new(METHOD, format!("{:?}", err)))
+    }
+
+    async fn burn(
+        &self,
+        ledger_id: Principal,
+        from_subaccount: Subaccount,
+        amount: u64,
+    ) -> Result<u64, CallError> {
+        const METHOD: &str = "transfer";
+        // Transfers to the minting account are burns and are not charged a
+        // fee.
+        let args = TransferArgs {
+            memo: Memo(0),
+            amount: Tokens::from_e8s(amount),
+            fee: Tokens::from_e8s(0),
+            from_subaccount: Some(from_subaccount),
+            to: AccountIdentifier::new(&self.id(), &DEFAULT_SUBACCOUNT),
+            created_at_time: None,
+        };
+        ic_ledger_types::transfer(ledger_id, args)
+            .await
+            .map_err(|(code, msg)| CallError::new(METHOD, format!("{:?}: {}", code, msg)))?
+            .map_err(|err| CallError::new(METHOD, format!("{:?}", err)))
+    }
+
+    async fn ecdsa_public_key(
+        &self,
+        key_name: String,
+        derivation_path: Vec<Vec<u8>>,
+    ) -> Result<Vec<u8>, CallError> {
+        const METHOD: &str = "ecdsa_public_key";
+        let args = EcdsaPublicKeyArgs {
+            canister_id: None,
+            derivation_path,
+            key_id: EcdsaKeyId {
+                curve: EcdsaCurve::Secp256k1,
+                name: key_name,
+            },
+        };
+        let (response,): (EcdsaPublicKeyResponse,) =
+            ic_cdk::call(Principal::management_canister(), METHOD, (args,))
+                .await
+                .map_err(|(code, msg)| CallError::new(METHOD, format!("{:?}: {}", code, msg)))?;
+        Ok(response.public_key)
+    }
+
+    async fn sign_with_ecdsa(
+        &self,
+        key_name: String,
+        derivation_path: Vec<Vec<u8>>,
+        message_hash: [u8; 32],
+    ) -> Result<Vec<u8>, CallError> {
+        const METHOD: &str = "sign_with_ecdsa";
+        let args = SignWithEcdsaArgs {
+            message_hash: message_hash.to_vec(),
+            derivation_path,
+            key_id: EcdsaKeyId {
+                curve: EcdsaCurve::Secp256k1,
+                name: key_name,
+            },
+        };
+        let (response,): (SignWithEcdsaResponse,) = ic_cdk::api::call::call_with_payment(
+            Principal::management_canister(),
+            METHOD,
+            (args,),
+            SIGN_WITH_ECDSA_COST_CYCLES,
+        )
+        .await
+        .map_err(|(code, msg)| CallError::new(METHOD, format!("{:?}: {}", code, msg)))?;
+        Ok(response.signature)
+    }
+
+    async fn send_transaction(
+        &self,
+        network: Network,
+        transaction: Vec<u8>,
+    ) -> Result<(), CallError> {
+        const METHOD: &str = "bitcoin_send_transaction";
+        let cycles = SEND_TRANSACTION_BASE_COST_CYCLES
+            + SEND_TRANSACTION_COST_CYCLES_PER_BYTE * transaction.len() as u64;
+        ic_cdk::api::call::call_with_payment(
+            Principal::management_canister(),
+            METHOD,
+            (SendTransactionRequest {
+                transaction,
+                network,
+            },),
+            cycles,
+        )
+        .await
+        .map_err(|(code, msg)| CallError::new(METHOD, format!("{:?}: {}", code, msg)))
+    }
+
+    async fn get_current_fee_percentiles(
+        &self,
+        network: Network,
+    ) -> Result<Vec<MillisatoshiPerByte>, CallError> {
+        const METHOD: &str = "bitcoin_get_current_fee_percentiles";
+        let (percentiles,): (Vec<MillisatoshiPerByte>,) = ic_cdk::api::call::call_with_payment(
+            Principal::management_canister(),
+            METHOD,
+            (GetCurrentFeePercentilesRequest { network },),
+            GET_CURRENT_FEE_PERCENTILES_COST_CYCLES,
+        )
+        .await
+        .map_err(|(code, msg)| CallError::new(METHOD, format!("{:?}: {}", code, msg)))?;
+        Ok(percentiles)
     }
 }
 
@@ -43,6 +355,16 @@ pub struct MockRuntime {
     pub id_result: Option<Principal>,
     pub caller_result: Option<Principal>,
     pub address_result: Option<String>,
+    pub get_utxos_result: Option<Result<Vec<Utxo>, CallError>>,
+    pub mint_result: Option<Result<u64, CallError>>,
+    pub time_result: Option<u64>,
+    pub burn_result: Option<Result<u64, CallError>>,
+    pub ecdsa_public_key_result: Option<Result<Vec<u8>, CallError>>,
+    pub sign_with_ecdsa_result: Option<Result<Vec<u8>, CallError>>,
+    pub send_transaction_result: Option<Result<(), CallError>>,
+    pub fee_percentiles_result: Option<Result<Vec<MillisatoshiPerByte>, CallError>>,
+    /// The transactions passed to `send_transaction`, in order.
+    pub sent_transactions: Arc<Mutex<Vec<Vec<u8>>>>,
 }
 
 /// [`Runtime`] mocked implementation.
@@ -54,6 +376,15 @@ impl MockRuntime {
             id_result: None,
             caller_result: None,
             address_result: None,
+            get_utxos_result: None,
+            mint_result: None,
+            time_result: None,
+            burn_result: None,
+            ecdsa_public_key_result: None,
+            sign_with_ecdsa_result: None,
+            send_transaction_result: None,
+            fee_percentiles_result: None,
+            sent_transactions: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -71,6 +402,54 @@ impl MockRuntime {
         self.address_result = Some(address);
         self
     }
+
+    pub fn set_get_utxos_result(mut self, result: Result<Vec<Utxo>, CallError>) -> Self {
+        self.get_utxos_result = Some(result);
+        self
+    }
+
+    pub fn set_mint_result(mut self, result: Result<u64, CallError>) -> Self {
+        self.mint_result = Some(result);
+        self
+    }
+
+    pub fn set_time_result(mut self, time: u64) -> Self {
+        self.time_result = Some(time);
+        self
+    }
+
+    pub fn set_burn_result(mut self, result: Result<u64, CallError>) -> Self {
+        self.burn_result = Some(result);
+        self
+    }
+
+    pub fn set_ecdsa_public_key_result(mut self, result: Result<Vec<u8>, CallError>) -> Self {
+        self.ecdsa_public_key_result = Some(result);
+        self
+    }
+
+    pub fn set_sign_with_ecdsa_result(mut self, result: Result<Vec<u8>, CallError>) -> Self {
+        self.sign_with_ecdsa_result = Some(result);
+        self
+    }
+
+    pub fn set_send_transaction_result(mut self, result: Result<(), CallError>) -> Self {
+        self.send_transaction_result = Some(result);
+        self
+    }
+
+    pub fn set_fee_percentiles_result(
+        mut self,
+        result: Result<Vec<MillisatoshiPerByte>, CallError>,
+    ) -> Self {
+        self.fee_percentiles_result = Some(result);
+        self
+    }
+
+    /// Returns the transactions passed to `send_transaction` so far.
+    pub fn sent_transactions(&self) -> Vec<Vec<u8>> {
+        self.sent_transactions.lock().unwrap().clone()
+    }
 }
 
 impl Default for MockRuntime {
@@ -89,7 +468,90 @@ impl Runtime for MockRuntime {
         self.caller_result.expect("caller result not set")
     }
 
-    fn address(&self, _derivation_path: Vec<Vec<u8>>, _address_type: &AddressType) -> String {
-        self.address_result.clone().expect("address not set")
+    fn time(&self) -> u64 {
+        self.time_result.expect("time result not set")
+    }
+
+    async fn address(
+        &self,
+        _key_name: String,
+        _network: Network,
+        _derivation_path: Vec<Vec<u8>>,
+    ) -> Result<String, CallError> {
+        Ok(self.address_result.clone().expect("address not set"))
+    }
+
+    async fn get_utxos(
+        &self,
+        _network: Network,
+        _address: String,
+        _min_confirmations: u32,
+    ) -> Result<Vec<Utxo>, CallError> {
+        self.get_utxos_result
+            .clone()
+            .expect("get_utxos result not set")
+    }
+
+    async fn mint(
+        &self,
+        _ledger_id: Principal,
+        _to: AccountIdentifier,
+        _amount: u64,
+    ) -> Result<u64, CallError> {
+        self.mint_result.clone().expect("mint result not set")
+    }
+
+    async fn burn(
+        &self,
+        _ledger_id: Principal,
+        _from_subaccount: Subaccount,
+        _amount: u64,
+    ) -> Result<u64, CallError> {
+        self.burn_result.clone().expect("burn result not set")
+    }
+
+    async fn ecdsa_public_key(
+        &self,
+        _key_name: String,
+        _derivation_path: Vec<Vec<u8>>,
+    ) -> Result<Vec<u8>, CallError> {
+        self.ecdsa_public_key_result
+            .clone()
+            .expect("ecdsa_public_key result not set")
+    }
+
+    async fn sign_with_ecdsa(
+        &self,
+        _key_name: String,
+        _derivation_path: Vec<Vec<u8>>,
+        _message_hash: [u8; 32],
+    ) -> Result<Vec<u8>, CallError> {
+        self.sign_with_ecdsa_result
+            .clone()
+            .expect("sign_with_ecdsa result not set")
+    }
+
+    async fn send_transaction(
+        &self,
+        _network: Network,
+        transaction: Vec<u8>,
+    ) -> Result<(), CallError> {
+        let result = self
+            .send_transaction_result
+            .clone()
+            .expect("send_transaction result not set");
+        if result.is_ok() {
+            self.sent_transactions.lock().unwrap().push(transaction);
+        }
+        result
+    }
+
+    async fn get_current_fee_percentiles(
+        &self,
+        _network: Network,
+    ) -> Result<Vec<MillisatoshiPerByte>, CallError> {
+        self.fee_percentiles_result
+            .clone()
+            .expect("fee percentiles result not set")
     }
 }
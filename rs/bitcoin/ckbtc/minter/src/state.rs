@@ -3,17 +3,310 @@
 ///! The state is stored in the global thread-level variable `__STATE`.
 ///! This module provides utility functions to manage the state. Most
 ///! code should use those functions instead of touching `__STATE` directly.
+use candid::{CandidType, Deserialize, Principal};
+use ic_btc_types::{Network, Utxo};
+use ic_ledger_types::Subaccount;
+use serde::Serialize;
 use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub mod audit;
+pub mod eventlog;
 
 thread_local! {
     static __STATE: RefCell<Option<CkBtcMinterState>> = RefCell::default();
 }
 
+/// A ckBTC ledger account, which also identifies a Bitcoin deposit address.
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct Account {
+    pub owner: Principal,
+    pub subaccount: Option<Subaccount>,
+}
+
+/// A request to withdraw BTC whose ckBTC have already been burned.
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct RetrieveBtcRequest {
+    /// The amount to withdraw, in satoshi.
+    pub amount: u64,
+    /// The destination Bitcoin address.
+    pub address: String,
+    /// The index of the ledger block that burned the ckBTC. It identifies the
+    /// request.
+    pub block_index: u64,
+    /// The time at which the minter accepted the request.
+    pub received_at: u64,
+}
+
+/// A transaction that the minter sent to the Bitcoin network and that is not
+/// confirmed yet.
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct SubmittedBtcTransaction {
+    /// The requests paid out by this transaction.
+    pub requests: Vec<RetrieveBtcRequest>,
+    /// The id of the last version of the transaction sent to the network.
+    pub txid: Vec<u8>,
+    /// The ids of the earlier versions of the transaction that were replaced
+    /// because they did not get confirmed in time. Any of them may still end
+    /// up being confirmed instead of `txid`.
+    pub replaced_txids: Vec<Vec<u8>>,
+    /// The UTXOs spent by the transaction and the accounts controlling them.
+    pub used_utxos: Vec<(Utxo, Account)>,
+    /// The time at which the last version of the transaction was sent.
+    pub submitted_at: u64,
+    /// The fee rate of the last version of the transaction, in millisatoshi
+    /// per virtual byte.
+    pub fee_per_vbyte: u64,
+}
+
+/// The status of a retrieve_btc request that the minter no longer processes.
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq)]
+pub enum FinalizedStatus {
+    /// The amount of the request did not cover its share of the transaction
+    /// fee, so the request was dropped.
+    AmountTooLow,
+    /// The transaction paying out the request has enough confirmations.
+    Confirmed { txid: Vec<u8> },
+}
+
+/// The status of a retrieve_btc request.
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum RetrieveBtcStatus {
+    /// The minter does not know about the request.
+    Unknown,
+    /// The request waits to be included in a transaction.
+    Pending,
+    /// The transaction paying out the request is being signed and sent.
+    Signing,
+    /// The transaction paying out the request was sent to the network.
+    Submitted { txid: Vec<u8> },
+    /// The transaction paying out the request has enough confirmations.
+    Confirmed { txid: Vec<u8> },
+    /// The amount of the request does not cover its share of the transaction
+    /// fee. The request was dropped.
+    AmountTooLow,
+}
+
 /// The state of the ckBTC Minter.
 ///
 /// Every piece of state of the Minter should be stored as field of this struct.
-#[derive(Clone, Debug)]
-pub struct CkBtcMinterState {}
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq)]
+pub struct CkBtcMinterState {
+    /// The Bitcoin network that the minter connects to.
+    pub btc_network: Network,
+
+    /// The principal of the ckBTC ledger that the minter mints on.
+    pub ledger_id: Principal,
+
+    /// The name of the threshold ECDSA key that controls the minter's
+    /// Bitcoin addresses.
+    pub ecdsa_key_name: String,
+
+    /// The minimum number of confirmations a UTXO needs before the minter
+    /// mints ckBTC for it.
+    pub min_confirmations: u32,
+
+    /// The fee in satoshi that the minter deducts from every deposit to
+    /// cover the KYT checks of the deposited UTXOs.
+    pub kyt_fee: u64,
+
+    /// The minimum amount in satoshi of a retrieve_btc request. It has to
+    /// cover the share of the request in the transaction fee.
+    pub retrieve_btc_min_amount: u64,
+
+    /// The UTXOs for which the minter already minted ckBTC.
+    ///
+    /// UTXOs are added before minting to prevent concurrent calls from
+    /// minting twice, and removed again if minting fails.
+    pub processed_utxos: BTreeSet<Utxo>,
+
+    /// The UTXOs that the minter can spend, with the accounts controlling
+    /// them.
+    pub available_utxos: BTreeMap<Utxo, Account>,
+
+    /// The retrieve_btc requests that wait to be included in a transaction.
+    pub pending_retrieve_btc_requests: Vec<RetrieveBtcRequest>,
+
+    /// The block indices of the requests whose transaction is being signed
+    /// and sent.
+    pub requests_in_flight: BTreeSet<u64>,
+
+    /// The transactions sent to the Bitcoin network that are not confirmed
+    /// yet.
+    pub submitted_transactions: Vec<SubmittedBtcTransaction>,
+
+    /// The final statuses of the requests that were paid out by a confirmed
+    /// transaction or dropped, indexed by their block indices.
+    pub finalized_requests: BTreeMap<u64, FinalizedStatus>,
+
+    /// Whether a heartbeat is processing the withdrawals. Prevents
+    /// concurrent heartbeats from spending the same UTXOs.
+    pub is_heartbeat_running: bool,
+
+    /// The time at which a heartbeat last processed the withdrawals.
+    pub last_processing_time: Option<u64>,
+
+    /// The public key of the minter's main account, which receives the
+    /// change outputs. Fetched from the management canister on first use.
+    pub main_public_key: Option<Vec<u8>>,
+}
+
+impl CkBtcMinterState {
+    pub fn new(
+        btc_network: Network,
+        ledger_id: Principal,
+        ecdsa_key_name: String,
+        min_confirmations: u32,
+        kyt_fee: u64,
+        retrieve_btc_min_amount: u64,
+    ) -> Self {
+        Self {
+            btc_network,
+            ledger_id,
+            ecdsa_key_name,
+            min_confirmations,
+            kyt_fee,
+            retrieve_btc_min_amount,
+            processed_utxos: BTreeSet::new(),
+            available_utxos: BTreeMap::new(),
+            pending_retrieve_btc_requests: Vec::new(),
+            requests_in_flight: BTreeSet::new(),
+            submitted_transactions: Vec::new(),
+            finalized_requests: BTreeMap::new(),
+            is_heartbeat_running: false,
+            last_processing_time: None,
+            main_public_key: None,
+        }
+    }
+
+    /// Marks `utxos` as processed and makes them available for new
+    /// transactions.
+    pub(crate) fn add_utxos(&mut self, account: Account, utxos: Vec<Utxo>) {
+        for utxo in utxos {
+            self.processed_utxos.insert(utxo.clone());
+            self.available_utxos.insert(utxo, account.clone());
+        }
+    }
+
+    pub(crate) fn push_retrieve_btc_request(&mut self, request: RetrieveBtcRequest) {
+        self.pending_retrieve_btc_requests.push(request);
+    }
+
+    /// Drops the request burning ckBTC in the ledger block `block_index`
+    /// because its amount does not cover its share of the transaction fee.
+    pub(crate) fn remove_retrieve_btc_request(&mut self, block_index: u64) {
+        self.pending_retrieve_btc_requests
+            .retain(|request| request.block_index != block_index);
+        self.finalized_requests
+            .insert(block_index, FinalizedStatus::AmountTooLow);
+    }
+
+    /// Records a transaction sent to the network. The requests it pays out
+    /// and the UTXOs it spends are no longer pending or available.
+    pub(crate) fn push_submitted_transaction(&mut self, tx: SubmittedBtcTransaction) {
+        self.pending_retrieve_btc_requests.retain(|pending| {
+            !tx.requests
+                .iter()
+                .any(|request| request.block_index == pending.block_index)
+        });
+        for (utxo, _) in &tx.used_utxos {
+            self.available_utxos.remove(utxo);
+        }
+        self.submitted_transactions.push(tx);
+    }
+
+    /// Replaces the submitted transaction `old_txid` by `new_txid`.
+    ///
+    /// Returns false if there is no submitted transaction `old_txid`.
+    pub(crate) fn replace_transaction(
+        &mut self,
+        old_txid: &[u8],
+        new_txid: Vec<u8>,
+        submitted_at: u64,
+        fee_per_vbyte: u64,
+    ) -> bool {
+        match self
+            .submitted_transactions
+            .iter_mut()
+            .find(|tx| tx.txid == old_txid)
+        {
+            Some(tx) => {
+                tx.replaced_txids.push(old_txid.to_vec());
+                tx.txid = new_txid;
+                tx.submitted_at = submitted_at;
+                tx.fee_per_vbyte = fee_per_vbyte;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the position of the submitted transaction that has the id
+    /// `txid` or that replaced a transaction with the id `txid`.
+    pub(crate) fn find_submitted_transaction(&self, txid: &[u8]) -> Option<usize> {
+        self.submitted_transactions.iter().position(|tx| {
+            tx.txid == txid || tx.replaced_txids.iter().any(|replaced| replaced == txid)
+        })
+    }
+
+    /// Finalizes the requests paid out by the confirmed transaction `txid`
+    /// and makes its change output available.
+    ///
+    /// Returns false if there is no submitted transaction `txid`.
+    pub(crate) fn finalize_transaction(
+        &mut self,
+        txid: &[u8],
+        change_utxo: Utxo,
+        change_account: Account,
+    ) -> bool {
+        let position = match self.find_submitted_transaction(txid) {
+            Some(position) => position,
+            None => return false,
+        };
+        let tx = self.submitted_transactions.remove(position);
+        for request in tx.requests {
+            self.finalized_requests.insert(
+                request.block_index,
+                FinalizedStatus::Confirmed {
+                    txid: txid.to_vec(),
+                },
+            );
+        }
+        self.available_utxos.insert(change_utxo, change_account);
+        true
+    }
+
+    /// Returns the status of the retrieve_btc request burning ckBTC in the
+    /// ledger block `block_index`.
+    pub fn retrieve_btc_status(&self, block_index: u64) -> RetrieveBtcStatus {
+        if self
+            .pending_retrieve_btc_requests
+            .iter()
+            .any(|request| request.block_index == block_index)
+        {
+            return RetrieveBtcStatus::Pending;
+        }
+        if self.requests_in_flight.contains(&block_index) {
+            return RetrieveBtcStatus::Signing;
+        }
+        if let Some(tx) = self.submitted_transactions.iter().find(|tx| {
+            tx.requests
+                .iter()
+                .any(|request| request.block_index == block_index)
+        }) {
+            return RetrieveBtcStatus::Submitted {
+                txid: tx.txid.clone(),
+            };
+        }
+        match self.finalized_requests.get(&block_index) {
+            Some(FinalizedStatus::Confirmed { txid }) => {
+                RetrieveBtcStatus::Confirmed { txid: txid.clone() }
+            }
+            Some(FinalizedStatus::AmountTooLow) => RetrieveBtcStatus::AmountTooLow,
+            None => RetrieveBtcStatus::Unknown,
+        }
+    }
+}
 
 /// Take the current state.
 ///
@@ -0,0 +1,92 @@
+///! State modifications that must be recorded in the event log.
+///!
+///! Every function records the event describing the modification before
+///! applying it, so that replaying the event log yields the same state.
+use super::eventlog::Event;
+use super::{Account, CkBtcMinterState, RetrieveBtcRequest, SubmittedBtcTransaction};
+use crate::storage::record_event;
+use ic_btc_types::Utxo;
+
+pub fn init(state: &CkBtcMinterState) {
+    record_event(&Event::Init {
+        btc_network: state.btc_network,
+        ledger_id: state.ledger_id,
+        ecdsa_key_name: state.ecdsa_key_name.clone(),
+        min_confirmations: state.min_confirmations,
+        kyt_fee: state.kyt_fee,
+        retrieve_btc_min_amount: state.retrieve_btc_min_amount,
+    });
+}
+
+pub fn add_utxos(state: &mut CkBtcMinterState, account: Account, utxos: Vec<Utxo>) {
+    record_event(&Event::ReceivedUtxos {
+        account: account.clone(),
+        utxos: utxos.clone(),
+    });
+    state.add_utxos(account, utxos);
+}
+
+pub fn accept_retrieve_btc_request(state: &mut CkBtcMinterState, request: RetrieveBtcRequest) {
+    record_event(&Event::AcceptedRetrieveBtcRequest(request.clone()));
+    state.push_retrieve_btc_request(request);
+}
+
+pub fn remove_retrieve_btc_request(state: &mut CkBtcMinterState, block_index: u64) {
+    record_event(&Event::RemovedRetrieveBtcRequest { block_index });
+    state.remove_retrieve_btc_request(block_index);
+}
+
+pub fn sent_transaction(state: &mut CkBtcMinterState, tx: SubmittedBtcTransaction) {
+    record_event(&Event::SentTransaction {
+        requests: tx.requests.clone(),
+        txid: tx.txid.clone(),
+        used_utxos: tx.used_utxos.clone(),
+        submitted_at: tx.submitted_at,
+        fee_per_vbyte: tx.fee_per_vbyte,
+    });
+    state.push_submitted_transaction(tx);
+}
+
+/// Does nothing and returns false if there is no submitted transaction
+/// `old_txid`, e.g. because it was confirmed in the meantime.
+pub fn replaced_transaction(
+    state: &mut CkBtcMinterState,
+    old_txid: Vec<u8>,
+    new_txid: Vec<u8>,
+    submitted_at: u64,
+    fee_per_vbyte: u64,
+) -> bool {
+    if !state
+        .submitted_transactions
+        .iter()
+        .any(|tx| tx.txid == old_txid)
+    {
+        return false;
+    }
+    record_event(&Event::ReplacedTransaction {
+        old_txid: old_txid.clone(),
+        new_txid: new_txid.clone(),
+        submitted_at,
+        fee_per_vbyte,
+    });
+    state.replace_transaction(&old_txid, new_txid, submitted_at, fee_per_vbyte)
+}
+
+/// Does nothing and returns false if no submitted transaction has the id
+/// `txid`, including the ids of the replaced versions.
+pub fn confirmed_transaction(
+    state: &mut CkBtcMinterState,
+    txid: Vec<u8>,
+    change_utxo: Utxo,
+    change_account: Account,
+) -> bool {
+    if state.find_submitted_transaction(&txid).is_none() {
+        return false;
+    }
+    record_event(&Event::ConfirmedTransaction {
+        txid: txid.clone(),
+        change_utxo: change_utxo.clone(),
+        change_account: change_account.clone(),
+    });
+    state.finalize_transaction(&txid, change_utxo, change_account)
+}
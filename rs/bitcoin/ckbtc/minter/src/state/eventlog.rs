@@ -0,0 +1,259 @@
+///! The events describing the state transitions of the minter.
+///!
+///! Replaying the events in order reconstructs the minter state, see
+///! [`replay`].
+use crate::state::{Account, CkBtcMinterState, RetrieveBtcRequest, SubmittedBtcTransaction};
+use candid::{CandidType, Deserialize, Principal};
+use ic_btc_types::{Network, Utxo};
+
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq)]
+pub enum Event {
+    /// The minter was initialized. This is the first event of the log.
+    Init {
+        btc_network: Network,
+        ledger_id: Principal,
+        ecdsa_key_name: String,
+        min_confirmations: u32,
+        kyt_fee: u64,
+        retrieve_btc_min_amount: u64,
+    },
+
+    /// The minter minted ckBTC for the `utxos` deposited to the address of
+    /// `account`.
+    ReceivedUtxos { account: Account, utxos: Vec<Utxo> },
+
+    /// The minter burned ckBTC and accepted a request to withdraw BTC.
+    AcceptedRetrieveBtcRequest(RetrieveBtcRequest),
+
+    /// The minter dropped the request burning ckBTC in the ledger block
+    /// `block_index` because its amount does not cover its share of the
+    /// transaction fee.
+    RemovedRetrieveBtcRequest { block_index: u64 },
+
+    /// The minter sent a transaction paying out `requests`. The UTXOs spent
+    /// by the transaction are not available anymore.
+    SentTransaction {
+        requests: Vec<RetrieveBtcRequest>,
+        txid: Vec<u8>,
+        used_utxos: Vec<(Utxo, Account)>,
+        submitted_at: u64,
+        fee_per_vbyte: u64,
+    },
+
+    /// The minter replaced the transaction `old_txid` by the transaction
+    /// `new_txid` paying a higher fee.
+    ReplacedTransaction {
+        old_txid: Vec<u8>,
+        new_txid: Vec<u8>,
+        submitted_at: u64,
+        fee_per_vbyte: u64,
+    },
+
+    /// The transaction `txid` got enough confirmations. Its change output
+    /// is available for new transactions.
+    ConfirmedTransaction {
+        txid: Vec<u8>,
+        change_utxo: Utxo,
+        change_account: Account,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayLogError {
+    /// There are no events in the event log.
+    EmptyLog,
+    /// The event log is inconsistent.
+    InconsistentLog(String),
+}
+
+/// Reconstructs the minter state from the events of the event log.
+pub fn replay(mut events: impl Iterator<Item = Event>) -> Result<CkBtcMinterState, ReplayLogError> {
+    let mut state = match events.next() {
+        Some(Event::Init {
+            btc_network,
+            ledger_id,
+            ecdsa_key_name,
+            min_confirmations,
+            kyt_fee,
+            retrieve_btc_min_amount,
+        }) => CkBtcMinterState::new(
+            btc_network,
+            ledger_id,
+            ecdsa_key_name,
+            min_confirmations,
+            kyt_fee,
+            retrieve_btc_min_amount,
+        ),
+        Some(event) => {
+            return Err(ReplayLogError::InconsistentLog(format!(
+                "The first event is not Init: {:?}",
+                event
+            )))
+        }
+        None => return Err(ReplayLogError::EmptyLog),
+    };
+
+    for event in events {
+        match event {
+            Event::Init { .. } => {
+                return Err(ReplayLogError::InconsistentLog(
+                    "Init event in the middle of the log".to_string(),
+                ))
+            }
+            Event::ReceivedUtxos { account, utxos } => state.add_utxos(account, utxos),
+            Event::AcceptedRetrieveBtcRequest(request) => state.push_retrieve_btc_request(request),
+            Event::RemovedRetrieveBtcRequest { block_index } => {
+                state.remove_retrieve_btc_request(block_index)
+            }
+            Event::SentTransaction {
+                requests,
+                txid,
+                used_utxos,
+                submitted_at,
+                fee_per_vbyte,
+            } => state.push_submitted_transaction(SubmittedBtcTransaction {
+                requests,
+                txid,
+                replaced_txids: vec![],
+                used_utxos,
+                submitted_at,
+                fee_per_vbyte,
+            }),
+            Event::ReplacedTransaction {
+                old_txid,
+                new_txid,
+                submitted_at,
+                fee_per_vbyte,
+            } => {
+                if !state.replace_transaction(&old_txid, new_txid, submitted_at, fee_per_vbyte) {
+                    return Err(ReplayLogError::InconsistentLog(format!(
+                        "Cannot replace unknown transaction {:?}",
+                        old_txid
+                    )));
+                }
+            }
+            Event::ConfirmedTransaction {
+                txid,
+                change_utxo,
+                change_account,
+            } => {
+                if !state.finalize_transaction(&txid, change_utxo, change_account) {
+                    return Err(ReplayLogError::InconsistentLog(format!(
+                        "Cannot confirm unknown transaction {:?}",
+                        txid
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::audit;
+    use crate::storage::{events, get_events};
+    use ic_btc_types::OutPoint;
+
+    fn utxo(txid: Vec<u8>, value: u64) -> Utxo {
+        Utxo {
+            outpoint: OutPoint { txid, vout: 0 },
+            value,
+            height: 10,
+        }
+    }
+
+    fn account() -> Account {
+        Account {
+            owner: Principal::anonymous(),
+            subaccount: None,
+        }
+    }
+
+    fn request(block_index: u64) -> RetrieveBtcRequest {
+        RetrieveBtcRequest {
+            amount: 10_000,
+            address: "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_string(),
+            block_index,
+            received_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_replay_reconstructs_state() {
+        let mut state = CkBtcMinterState::new(
+            Network::Mainnet,
+            Principal::from_text("2chl6-4hpzw-vqaaa-aaaaa-c").unwrap(),
+            "key_1".to_string(),
+            6,
+            1_000,
+            10_000,
+        );
+        audit::init(&state);
+        audit::add_utxos(
+            &mut state,
+            account(),
+            vec![utxo(vec![1; 32], 50_000), utxo(vec![2; 32], 20_000)],
+        );
+        audit::accept_retrieve_btc_request(&mut state, request(3));
+        audit::accept_retrieve_btc_request(&mut state, request(4));
+        audit::accept_retrieve_btc_request(&mut state, request(5));
+        audit::remove_retrieve_btc_request(&mut state, 5);
+        audit::sent_transaction(
+            &mut state,
+            SubmittedBtcTransaction {
+                requests: vec![request(3)],
+                txid: vec![5; 32],
+                replaced_txids: vec![],
+                used_utxos: vec![(utxo(vec![1; 32], 50_000), account())],
+                submitted_at: 1,
+                fee_per_vbyte: 2_000,
+            },
+        );
+        assert!(audit::replaced_transaction(
+            &mut state,
+            vec![5; 32],
+            vec![6; 32],
+            2,
+            3_000
+        ));
+        assert!(audit::confirmed_transaction(
+            &mut state,
+            vec![5; 32],
+            utxo(vec![5; 32], 39_000),
+            account(),
+        ));
+        // Unknown transactions are neither replaced nor confirmed.
+        assert!(!audit::replaced_transaction(
+            &mut state,
+            vec![7; 32],
+            vec![8; 32],
+            3,
+            4_000
+        ));
+        assert!(!audit::confirmed_transaction(
+            &mut state,
+            vec![7; 32],
+            utxo(vec![7; 32], 1_000),
+            account(),
+        ));
+
+        assert_eq!(get_events(0, 100).len(), 9);
+        assert_eq!(get_events(7, 100).len(), 2);
+        assert_eq!(get_events(1, 1), vec![events().nth(1).unwrap()]);
+        assert_eq!(replay(events()), Ok(state));
+    }
+
+    #[test]
+    fn test_replay_rejects_inconsistent_logs() {
+        assert_eq!(replay(std::iter::empty()), Err(ReplayLogError::EmptyLog));
+        assert!(matches!(
+            replay(std::iter::once(Event::AcceptedRetrieveBtcRequest(request(
+                3
+            )))),
+            Err(ReplayLogError::InconsistentLog(_))
+        ));
+    }
+}
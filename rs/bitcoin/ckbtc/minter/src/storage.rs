@@ -0,0 +1,75 @@
+///! Persistence of the minter events in stable memory.
+///!
+///! The events are kept in an append-only log that survives upgrades; the
+///! minter state is reconstructed from them in `post_upgrade`.
+use crate::state::eventlog::Event;
+use stable_structures::{log::Log as StableLog, DefaultMemoryImpl};
+use std::cell::RefCell;
+
+/// The maximum number of events that the log can hold. The index of the log
+/// takes 8 bytes of stable memory per event.
+const MAX_EVENTS: u32 = 10_000_000;
+
+type EventLog = StableLog<DefaultMemoryImpl>;
+
+thread_local! {
+    /// Append-only list of the candid-encoded minter events.
+    static EVENTS: RefCell<EventLog> = RefCell::new(EventLog::init(
+        DefaultMemoryImpl::default(),
+        MAX_EVENTS,
+    ).expect("failed to initialize the event log"));
+}
+
+fn encode_event(event: &Event) -> Vec<u8> {
+    candid::encode_one(event).expect("failed to encode a minter event")
+}
+
+fn decode_event(bytes: &[u8]) -> Event {
+    candid::decode_one(bytes).expect("failed to decode a minter event")
+}
+
+/// Appends `event` to the event log.
+///
+/// Panics if the log is full or stable memory cannot grow. The panic rolls
+/// back the state change that the event describes.
+pub fn record_event(event: &Event) {
+    EVENTS.with(|events| {
+        events
+            .borrow()
+            .append(&encode_event(event))
+            .expect("failed to append an entry to the event log")
+    });
+}
+
+/// Returns the number of events in the log.
+pub fn count_events() -> u64 {
+    EVENTS.with(|events| events.borrow().len() as u64)
+}
+
+/// Returns at most `length` events, starting with the event at index
+/// `start`.
+pub fn get_events(start: u64, length: u64) -> Vec<Event> {
+    EVENTS.with(|events| {
+        let events = events.borrow();
+        let mut buf = vec![];
+        (start..start.saturating_add(length))
+            .map_while(|index| {
+                events.read_entry(index as usize, &mut buf).ok()?;
+                Some(decode_event(&buf))
+            })
+            .collect()
+    })
+}
+
+/// Returns an iterator over all the events in the log.
+pub fn events() -> impl Iterator<Item = Event> {
+    (0..count_events()).map(|index| {
+        EVENTS.with(|events| {
+            let bytes = events
+                .borrow()
+                .get(index as usize)
+                .expect("the event log shrunk");
+            decode_event(&bytes)
+        })
+    })
+}
@@ -0,0 +1,327 @@
+///! Construction, signing and serialization of the segwit (P2WPKH) Bitcoin
+///! transactions that pay out retrieve_btc requests.
+///!
+///! See BIP-141 for the serialization format and BIP-143 for the signature
+///! hash algorithm.
+use bitcoin::hashes::{hash160, Hash};
+use ic_btc_types::OutPoint;
+use ic_crypto_sha::Sha256;
+
+/// The version of the transactions built by the minter.
+const TX_VERSION: u32 = 2;
+
+/// The sequence number of the inputs; it signals replaceability (BIP-125).
+pub const RBF_SEQUENCE: u32 = 0xffff_fffd;
+
+/// The sighash type committing to all inputs and outputs.
+const SIGHASH_ALL: u32 = 1;
+
+/// An upper bound of the size of a DER-encoded signature with its sighash
+/// type byte.
+const MAX_SIGNATURE_LEN: usize = 73;
+
+/// The length of a compressed SEC1 public key.
+const PUBKEY_LEN: usize = 33;
+
+/// An input that spends a P2WPKH output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsignedInput {
+    pub previous_output: OutPoint,
+    /// The value of the spent output, in satoshi.
+    pub value: u64,
+    pub sequence: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxOut {
+    /// The value of the output, in satoshi.
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsignedTransaction {
+    pub inputs: Vec<UnsignedInput>,
+    pub outputs: Vec<TxOut>,
+    pub lock_time: u32,
+}
+
+/// The witness of an input that spends a P2WPKH output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Witness {
+    /// The DER-encoded signature followed by the sighash type byte.
+    pub signature: Vec<u8>,
+    /// The compressed SEC1 public key.
+    pub pubkey: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedTransaction {
+    pub unsigned_tx: UnsignedTransaction,
+    /// One witness per input, in the order of the inputs.
+    pub witnesses: Vec<Witness>,
+}
+
+impl UnsignedTransaction {
+    /// Returns the transaction id, in the internal byte order used by
+    /// outpoints. Signatures are not part of the id of segwit transactions.
+    pub fn txid(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_without_witness(&mut buf);
+        double_sha256(&buf).to_vec()
+    }
+
+    /// Returns the BIP-143 SIGHASH_ALL signature hash of the input at
+    /// `input_index`, which spends a P2WPKH output of the key with hash
+    /// `pubkey_hash`.
+    ///
+    /// Panics if `input_index` is out of bounds.
+    pub fn sighash(&self, input_index: usize, pubkey_hash: &[u8; 20]) -> [u8; 32] {
+        let input = &self.inputs[input_index];
+
+        let mut prevouts = Vec::new();
+        let mut sequences = Vec::new();
+        for input in &self.inputs {
+            encode_outpoint(&input.previous_output, &mut prevouts);
+            sequences.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+        let mut outputs = Vec::new();
+        for output in &self.outputs {
+            encode_txout(output, &mut outputs);
+        }
+
+        let mut preimage = Vec::new();
+        preimage.extend_from_slice(&TX_VERSION.to_le_bytes());
+        preimage.extend_from_slice(&double_sha256(&prevouts));
+        preimage.extend_from_slice(&double_sha256(&sequences));
+        encode_outpoint(&input.previous_output, &mut preimage);
+        // The script code of P2WPKH inputs is the matching P2PKH script.
+        preimage.extend_from_slice(&[0x19, 0x76, 0xa9, 0x14]);
+        preimage.extend_from_slice(pubkey_hash);
+        preimage.extend_from_slice(&[0x88, 0xac]);
+        preimage.extend_from_slice(&input.value.to_le_bytes());
+        preimage.extend_from_slice(&input.sequence.to_le_bytes());
+        preimage.extend_from_slice(&double_sha256(&outputs));
+        preimage.extend_from_slice(&self.lock_time.to_le_bytes());
+        preimage.extend_from_slice(&SIGHASH_ALL.to_le_bytes());
+        double_sha256(&preimage)
+    }
+
+    /// Returns an upper bound of the virtual size of the transaction once
+    /// all the inputs are signed.
+    pub fn estimate_vsize(&self) -> u64 {
+        let mut base = Vec::new();
+        self.encode_without_witness(&mut base);
+        // Marker, flag and for every input the number of witness items and
+        // the length prefixed signature and public key.
+        let witness_size = 2 + self.inputs.len() * (1 + 1 + MAX_SIGNATURE_LEN + 1 + PUBKEY_LEN);
+        (base.len() + (witness_size + 3) / 4) as u64
+    }
+
+    fn encode_without_witness(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&TX_VERSION.to_le_bytes());
+        self.encode_inputs_and_outputs(buf);
+        buf.extend_from_slice(&self.lock_time.to_le_bytes());
+    }
+
+    fn encode_inputs_and_outputs(&self, buf: &mut Vec<u8>) {
+        encode_varint(self.inputs.len() as u64, buf);
+        for input in &self.inputs {
+            encode_outpoint(&input.previous_output, buf);
+            // The script_sig of segwit inputs is empty.
+            encode_varint(0, buf);
+            buf.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+        encode_varint(self.outputs.len() as u64, buf);
+        for output in &self.outputs {
+            encode_txout(output, buf);
+        }
+    }
+}
+
+impl SignedTransaction {
+    /// Returns the id of the transaction.
+    pub fn txid(&self) -> Vec<u8> {
+        self.unsigned_tx.txid()
+    }
+
+    /// Serializes the transaction in the BIP-144 format expected by
+    /// `bitcoin_send_transaction`.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&TX_VERSION.to_le_bytes());
+        // Segwit marker and flag.
+        buf.extend_from_slice(&[0x00, 0x01]);
+        self.unsigned_tx.encode_inputs_and_outputs(&mut buf);
+        for witness in &self.witnesses {
+            encode_varint(2, &mut buf);
+            encode_varint(witness.signature.len() as u64, &mut buf);
+            buf.extend_from_slice(&witness.signature);
+            encode_varint(witness.pubkey.len() as u64, &mut buf);
+            buf.extend_from_slice(&witness.pubkey);
+        }
+        buf.extend_from_slice(&self.unsigned_tx.lock_time.to_le_bytes());
+        buf
+    }
+}
+
+/// Returns the HASH160 of a public key.
+pub fn hash160(pubkey: &[u8]) -> [u8; 20] {
+    hash160::Hash::hash(pubkey).into_inner()
+}
+
+/// Returns the script_pubkey of the P2WPKH output of the key with hash
+/// `pubkey_hash`.
+pub fn p2wpkh_script_pubkey(pubkey_hash: &[u8; 20]) -> Vec<u8> {
+    let mut script = vec![0x00, 0x14];
+    script.extend_from_slice(pubkey_hash);
+    script
+}
+
+/// Encodes a 64-byte `r || s` signature, as returned by `sign_with_ecdsa`,
+/// in DER and appends the SIGHASH_ALL type byte.
+///
+/// Panics if the signature is not 64 bytes long.
+pub fn encode_signature(signature: &[u8]) -> Vec<u8> {
+    assert_eq!(signature.len(), 64, "invalid signature length");
+
+    fn encode_integer(bytes: &[u8], buf: &mut Vec<u8>) {
+        // Strip the leading zeros and prepend a zero if the integer would
+        // otherwise be negative.
+        let start = bytes
+            .iter()
+            .position(|b| *b != 0)
+            .unwrap_or(bytes.len() - 1);
+        let bytes = &bytes[start..];
+        let pad = bytes[0] & 0x80 != 0;
+        buf.push(0x02);
+        buf.push((bytes.len() + pad as usize) as u8);
+        if pad {
+            buf.push(0x00);
+        }
+        buf.extend_from_slice(bytes);
+    }
+
+    let mut integers = Vec::with_capacity(70);
+    encode_integer(&signature[..32], &mut integers);
+    encode_integer(&signature[32..], &mut integers);
+
+    let mut der = Vec::with_capacity(MAX_SIGNATURE_LEN);
+    der.push(0x30);
+    der.push(integers.len() as u8);
+    der.extend_from_slice(&integers);
+    der.push(SIGHASH_ALL as u8);
+    der
+}
+
+fn double_sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::hash(&Sha256::hash(data))
+}
+
+fn encode_outpoint(outpoint: &OutPoint, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&outpoint.txid);
+    buf.extend_from_slice(&outpoint.vout.to_le_bytes());
+}
+
+fn encode_txout(output: &TxOut, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&output.value.to_le_bytes());
+    encode_varint(output.script_pubkey.len() as u64, buf);
+    buf.extend_from_slice(&output.script_pubkey);
+}
+
+fn encode_varint(n: u64, buf: &mut Vec<u8>) {
+    match n {
+        0..=0xfc => buf.push(n as u8),
+        0xfd..=0xffff => {
+            buf.push(0xfd);
+            buf.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            buf.push(0xfe);
+            buf.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        _ => {
+            buf.push(0xff);
+            buf.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn transaction() -> UnsignedTransaction {
+        UnsignedTransaction {
+            inputs: vec![UnsignedInput {
+                previous_output: OutPoint {
+                    txid: vec![1; 32],
+                    vout: 0,
+                },
+                value: 100_000,
+                sequence: RBF_SEQUENCE,
+            }],
+            outputs: vec![
+                TxOut {
+                    value: 60_000,
+                    script_pubkey: p2wpkh_script_pubkey(&[2; 20]),
+                },
+                TxOut {
+                    value: 39_000,
+                    script_pubkey: p2wpkh_script_pubkey(&[3; 20]),
+                },
+            ],
+            lock_time: 0,
+        }
+    }
+
+    #[test]
+    fn test_txid_and_sighash() {
+        let tx = transaction();
+        assert_eq!(
+            tx.txid(),
+            from_hex("9210347f348c27b5ecd3ded6bd0493bb1695ddc201722bc951618d6bfadbe3da")
+        );
+        assert_eq!(
+            tx.sighash(0, &[4; 20]).to_vec(),
+            from_hex("e2f410970e024b3f8864d3de9d10f7b64f0df27e47fead52e5d5fd0194d4d39f")
+        );
+    }
+
+    #[test]
+    fn test_signed_transaction_size_is_within_estimate() {
+        let unsigned_tx = transaction();
+        let estimate = unsigned_tx.estimate_vsize();
+        let signed_tx = SignedTransaction {
+            unsigned_tx,
+            witnesses: vec![Witness {
+                signature: encode_signature(&[0x80; 64]),
+                pubkey: vec![2; PUBKEY_LEN],
+            }],
+        };
+        let size = signed_tx.serialize().len() as u64;
+        // The witness makes up for 111 of the serialized bytes and is
+        // discounted by a factor of 4.
+        assert_eq!(estimate, size - 111 + 28);
+        assert_eq!(signed_tx.txid(), signed_tx.unsigned_tx.txid());
+    }
+
+    #[test]
+    fn test_encode_signature() {
+        let mut signature = [0x80; 64];
+        signature[32..].copy_from_slice(&[0; 32]);
+        signature[63] = 1;
+
+        let mut expected = vec![0x30, 0x26, 0x02, 0x21, 0x00];
+        expected.extend_from_slice(&[0x80; 32]);
+        expected.extend_from_slice(&[0x02, 0x01, 0x01, 0x01]);
+        assert_eq!(encode_signature(&signature), expected);
+    }
+}
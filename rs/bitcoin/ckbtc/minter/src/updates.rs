@@ -1,5 +1,9 @@
 pub mod get_btc_address;
 pub mod get_withdrawal_account;
+pub mod retrieve_btc;
+pub mod update_balance;
 
 pub use get_btc_address::get_btc_address;
 pub use get_withdrawal_account::get_withdrawal_account;
+pub use retrieve_btc::retrieve_btc;
+pub use update_balance::update_balance;
@@ -1,7 +1,7 @@
-use bitcoin::AddressType;
 use candid::{CandidType, Deserialize};
 use ic_base_types::ic_types::Principal;
-use ic_ckbtc_minter::runtime::Runtime;
+use ic_ckbtc_minter::runtime::{CallError, Runtime};
+use ic_ckbtc_minter::state::read_state;
 use ic_ledger_types::{Subaccount, DEFAULT_SUBACCOUNT};
 use serde::Serialize;
 
@@ -19,11 +19,13 @@ pub struct GetBtcAddressResult {
 
 /// Return a valid BIP-32 derivation path from an account id (Principal + subaccount)
 ///
-/// See [`derivation_path_schema()`] for the possible panics.
-fn account_derivation_path(principal: Principal, subaccount: Option<Subaccount>) -> Vec<Vec<u8>> {
-    let _bytes = derivation_path_schema(principal, subaccount);
-    //ic_btc_library::address_management::get_derivation_path(&bytes)
-    todo!()
+/// The path consists of a single index encoding the account.
+/// See [`derivation_path_schema()`] for the encoding and the possible panics.
+pub fn account_derivation_path(
+    principal: Principal,
+    subaccount: Option<Subaccount>,
+) -> Vec<Vec<u8>> {
+    vec![derivation_path_schema(principal, subaccount)]
 }
 
 /// Return a blob containing principal and subaccount.
@@ -51,9 +53,14 @@ fn derivation_path_schema(principal: Principal, subaccount: Option<Subaccount>)
     bytes
 }
 
-pub fn get_btc_address(args: GetBtcAddressArgs, runtime: &dyn Runtime) -> GetBtcAddressResult {
+/// Returns the deposit address of the caller's account.
+pub async fn get_btc_address(
+    args: GetBtcAddressArgs,
+    runtime: &dyn Runtime,
+) -> Result<GetBtcAddressResult, CallError> {
     let caller = runtime.caller();
     let derivation_path = account_derivation_path(caller, args.subaccount);
-    let address = runtime.address(derivation_path, &AddressType::P2pkh);
-    GetBtcAddressResult { address }
+    let (key_name, network) = read_state(|s| (s.ecdsa_key_name.clone(), s.btc_network));
+    let address = runtime.address(key_name, network, derivation_path).await?;
+    Ok(GetBtcAddressResult { address })
 }
@@ -27,7 +27,7 @@ pub fn get_withdrawal_account(runtime: &dyn Runtime) -> GetWithdrawalAccountResu
 }
 
 /// Compute the subaccount of a principal based on a given nonce.
-fn compute_subaccount(controller: PrincipalId, nonce: u64) -> Subaccount {
+pub fn compute_subaccount(controller: PrincipalId, nonce: u64) -> Subaccount {
     const DOMAIN: &[u8] = b"ckbtc";
     const DOMAIN_LENGTH: [u8; 1] = [0x05];
     Subaccount({
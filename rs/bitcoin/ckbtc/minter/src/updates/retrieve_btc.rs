@@ -0,0 +1,175 @@
+use crate::updates::get_withdrawal_account::compute_subaccount;
+use candid::{CandidType, Deserialize};
+use ic_base_types::PrincipalId;
+use ic_ckbtc_minter::address::script_pubkey;
+use ic_ckbtc_minter::runtime::Runtime;
+use ic_ckbtc_minter::state::{audit, mutate_state, read_state, RetrieveBtcRequest};
+use serde::Serialize;
+
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RetrieveBtcArgs {
+    /// The amount to withdraw, in satoshi.
+    pub amount: u64,
+    /// The destination Bitcoin address.
+    pub address: String,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RetrieveBtcOk {
+    /// The index of the ledger block that burned the ckBTC. It identifies the
+    /// request in `retrieve_btc_status`.
+    pub block_index: u64,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum RetrieveBtcError {
+    /// The destination address is malformed or belongs to another network.
+    MalformedAddress(String),
+    /// The amount is below the minimum withdrawal amount.
+    AmountTooLow(u64),
+    /// Burning the ckBTC of the withdrawal account failed, e.g. because the
+    /// balance of the account is too low.
+    LedgerError(String),
+}
+
+/// Burns `amount` ckBTC from the caller's withdrawal account and queues the
+/// withdrawal of the same amount of BTC to `address`.
+///
+/// The heartbeat pays out queued withdrawals, see
+/// [`crate::heartbeat::heartbeat`]. The fee of the Bitcoin transaction is
+/// deducted from the withdrawn amount.
+pub async fn retrieve_btc(
+    args: RetrieveBtcArgs,
+    runtime: &dyn Runtime,
+) -> Result<RetrieveBtcOk, RetrieveBtcError> {
+    let (btc_network, ledger_id, min_amount) =
+        read_state(|s| (s.btc_network, s.ledger_id, s.retrieve_btc_min_amount));
+
+    script_pubkey(&args.address, btc_network).map_err(RetrieveBtcError::MalformedAddress)?;
+    if args.amount < min_amount {
+        return Err(RetrieveBtcError::AmountTooLow(min_amount));
+    }
+
+    let caller = runtime.caller();
+    let from_subaccount = compute_subaccount(PrincipalId(caller), 0);
+    let block_index = runtime
+        .burn(ledger_id, from_subaccount, args.amount)
+        .await
+        .map_err(|err| {
+            RetrieveBtcError::LedgerError(format!("{} failed: {}", err.method, err.reason))
+        })?;
+
+    let request = RetrieveBtcRequest {
+        amount: args.amount,
+        address: args.address,
+        block_index,
+        received_at: runtime.time(),
+    };
+    mutate_state(|s| audit::accept_retrieve_btc_request(s, request));
+    Ok(RetrieveBtcOk { block_index })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+    use ic_btc_types::Network;
+    use ic_ckbtc_minter::runtime::{CallError, MockRuntime};
+    use ic_ckbtc_minter::state::{replace_state, CkBtcMinterState, RetrieveBtcStatus};
+
+    const MIN_AMOUNT: u64 = 10_000;
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn init_state() {
+        replace_state(CkBtcMinterState::new(
+            Network::Mainnet,
+            Principal::from_text("2chl6-4hpzw-vqaaa-aaaaa-c").unwrap(),
+            "key_1".to_string(),
+            6,
+            1_000,
+            MIN_AMOUNT,
+        ));
+    }
+
+    fn runtime(burn_result: Result<u64, CallError>) -> MockRuntime {
+        MockRuntime::new()
+            .set_caller_result(Principal::anonymous())
+            .set_time_result(42)
+            .set_burn_result(burn_result)
+    }
+
+    fn retrieve_btc(
+        runtime: &MockRuntime,
+        amount: u64,
+        address: &str,
+    ) -> Result<RetrieveBtcOk, RetrieveBtcError> {
+        tokio_test::block_on(super::retrieve_btc(
+            RetrieveBtcArgs {
+                amount,
+                address: address.to_string(),
+            },
+            runtime,
+        ))
+    }
+
+    #[test]
+    fn test_retrieve_btc_queues_request() {
+        init_state();
+        let runtime = runtime(Ok(3));
+
+        assert_eq!(
+            retrieve_btc(&runtime, MIN_AMOUNT, ADDRESS),
+            Ok(RetrieveBtcOk { block_index: 3 })
+        );
+        read_state(|s| {
+            assert_eq!(
+                s.pending_retrieve_btc_requests,
+                vec![RetrieveBtcRequest {
+                    amount: MIN_AMOUNT,
+                    address: ADDRESS.to_string(),
+                    block_index: 3,
+                    received_at: 42,
+                }]
+            );
+            assert_eq!(s.retrieve_btc_status(3), RetrieveBtcStatus::Pending);
+            assert_eq!(s.retrieve_btc_status(4), RetrieveBtcStatus::Unknown);
+        });
+    }
+
+    #[test]
+    fn test_retrieve_btc_rejects_invalid_requests() {
+        init_state();
+        let runtime = runtime(Ok(3));
+
+        assert!(matches!(
+            retrieve_btc(
+                &runtime,
+                MIN_AMOUNT,
+                "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"
+            ),
+            Err(RetrieveBtcError::MalformedAddress(_))
+        ));
+        assert_eq!(
+            retrieve_btc(&runtime, MIN_AMOUNT - 1, ADDRESS),
+            Err(RetrieveBtcError::AmountTooLow(MIN_AMOUNT))
+        );
+        read_state(|s| assert!(s.pending_retrieve_btc_requests.is_empty()));
+    }
+
+    #[test]
+    fn test_retrieve_btc_does_not_queue_request_if_burn_fails() {
+        init_state();
+        let runtime = runtime(Err(CallError {
+            method: "transfer".to_string(),
+            reason: "InsufficientFunds".to_string(),
+        }));
+
+        assert_eq!(
+            retrieve_btc(&runtime, MIN_AMOUNT, ADDRESS),
+            Err(RetrieveBtcError::LedgerError(
+                "transfer failed: InsufficientFunds".to_string()
+            ))
+        );
+        read_state(|s| assert!(s.pending_retrieve_btc_requests.is_empty()));
+    }
+}
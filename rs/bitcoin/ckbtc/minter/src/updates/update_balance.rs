@@ -0,0 +1,230 @@
+use crate::updates::get_btc_address::{get_btc_address, GetBtcAddressArgs};
+use candid::{CandidType, Deserialize};
+use ic_btc_types::Utxo;
+use ic_ckbtc_minter::runtime::{CallError, Runtime};
+use ic_ckbtc_minter::state::{audit, mutate_state, read_state, Account};
+use ic_ledger_types::{AccountIdentifier, Subaccount, DEFAULT_SUBACCOUNT};
+use serde::Serialize;
+
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct UpdateBalanceArgs {
+    pub subaccount: Option<Subaccount>,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct UpdateBalanceResult {
+    /// The amount of ckBTC minted, in satoshi.
+    pub amount: u64,
+    /// The index of the ledger block containing the mint transaction.
+    pub block_index: u64,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum UpdateBalanceError {
+    /// There are no new UTXOs with enough confirmations at the deposit address.
+    NoNewUtxos,
+    /// The value of the new UTXOs does not cover the KYT fee.
+    AmountTooLow { amount: u64, kyt_fee: u64 },
+    /// A call to the Bitcoin canister or to the ledger failed, the caller
+    /// can try again later.
+    TemporarilyUnavailable(String),
+}
+
+impl From<CallError> for UpdateBalanceError {
+    fn from(err: CallError) -> Self {
+        Self::TemporarilyUnavailable(format!("{} failed: {}", err.method, err.reason))
+    }
+}
+
+/// Mints ckBTC for the UTXOs at the caller's deposit address that have enough
+/// confirmations and for which no ckBTC was minted yet.
+///
+/// The KYT fee is deducted from the total value of the new UTXOs.
+pub async fn update_balance(
+    args: UpdateBalanceArgs,
+    runtime: &dyn Runtime,
+) -> Result<UpdateBalanceResult, UpdateBalanceError> {
+    let caller = runtime.caller();
+    let address = get_btc_address(
+        GetBtcAddressArgs {
+            subaccount: args.subaccount,
+        },
+        runtime,
+    )
+    .await?
+    .address;
+    let (btc_network, ledger_id, min_confirmations, kyt_fee) =
+        read_state(|s| (s.btc_network, s.ledger_id, s.min_confirmations, s.kyt_fee));
+
+    let utxos = runtime
+        .get_utxos(btc_network, address, min_confirmations)
+        .await?;
+
+    let new_utxos: Vec<Utxo> = read_state(|s| {
+        utxos
+            .into_iter()
+            .filter(|utxo| !s.processed_utxos.contains(utxo))
+            .collect()
+    });
+    if new_utxos.is_empty() {
+        return Err(UpdateBalanceError::NoNewUtxos);
+    }
+    let amount = new_utxos
+        .iter()
+        .fold(0_u64, |sum, utxo| sum.saturating_add(utxo.value));
+    if amount <= kyt_fee {
+        return Err(UpdateBalanceError::AmountTooLow { amount, kyt_fee });
+    }
+
+    // Mark the UTXOs as processed before calling the ledger so that
+    // concurrent calls cannot mint for them again.
+    mutate_state(|s| s.processed_utxos.extend(new_utxos.iter().cloned()));
+
+    let to = AccountIdentifier::new(&caller, &args.subaccount.unwrap_or(DEFAULT_SUBACCOUNT));
+    let minted_amount = amount - kyt_fee;
+    match runtime.mint(ledger_id, to, minted_amount).await {
+        Ok(block_index) => {
+            // The minted UTXOs can now be spent to pay out withdrawals.
+            let account = Account {
+                owner: caller,
+                subaccount: args.subaccount,
+            };
+            mutate_state(|s| audit::add_utxos(s, account, new_utxos));
+            Ok(UpdateBalanceResult {
+                amount: minted_amount,
+                block_index,
+            })
+        }
+        Err(err) => {
+            mutate_state(|s| {
+                for utxo in &new_utxos {
+                    s.processed_utxos.remove(utxo);
+                }
+            });
+            Err(err.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candid::Principal;
+    use ic_btc_types::{Network, OutPoint};
+    use ic_ckbtc_minter::runtime::MockRuntime;
+    use ic_ckbtc_minter::state::{replace_state, CkBtcMinterState};
+
+    const KYT_FEE: u64 = 1_000;
+
+    fn init_state() {
+        replace_state(CkBtcMinterState::new(
+            Network::Regtest,
+            Principal::from_text("2chl6-4hpzw-vqaaa-aaaaa-c").unwrap(),
+            "key_1".to_string(),
+            6,
+            KYT_FEE,
+            10_000,
+        ));
+    }
+
+    fn utxo(vout: u32, value: u64) -> Utxo {
+        Utxo {
+            outpoint: OutPoint {
+                txid: vec![1; 32],
+                vout,
+            },
+            value,
+            height: 10,
+        }
+    }
+
+    fn runtime(utxos: Vec<Utxo>, mint_result: Result<u64, CallError>) -> MockRuntime {
+        MockRuntime::new()
+            .set_caller_result(Principal::anonymous())
+            .set_address_result("bcrt1qdeposit".to_string())
+            .set_get_utxos_result(Ok(utxos))
+            .set_mint_result(mint_result)
+    }
+
+    fn update_balance(runtime: &MockRuntime) -> Result<UpdateBalanceResult, UpdateBalanceError> {
+        tokio_test::block_on(super::update_balance(
+            UpdateBalanceArgs { subaccount: None },
+            runtime,
+        ))
+    }
+
+    #[test]
+    fn test_update_balance_mints_new_utxos_once() {
+        init_state();
+        let runtime = runtime(vec![utxo(0, 10_000), utxo(1, 5_000)], Ok(7));
+
+        assert_eq!(
+            update_balance(&runtime),
+            Ok(UpdateBalanceResult {
+                amount: 15_000 - KYT_FEE,
+                block_index: 7,
+            })
+        );
+        read_state(|s| assert_eq!(s.available_utxos.len(), 2));
+        assert_eq!(
+            update_balance(&runtime),
+            Err(UpdateBalanceError::NoNewUtxos)
+        );
+
+        // Only the newly received UTXO is minted.
+        let runtime =
+            runtime.set_get_utxos_result(Ok(vec![utxo(0, 10_000), utxo(1, 5_000), utxo(2, 3_000)]));
+        assert_eq!(
+            update_balance(&runtime),
+            Ok(UpdateBalanceResult {
+                amount: 3_000 - KYT_FEE,
+                block_index: 7,
+            })
+        );
+    }
+
+    #[test]
+    fn test_update_balance_rejects_amount_below_kyt_fee() {
+        init_state();
+        let runtime = runtime(vec![utxo(0, KYT_FEE)], Ok(7));
+
+        assert_eq!(
+            update_balance(&runtime),
+            Err(UpdateBalanceError::AmountTooLow {
+                amount: KYT_FEE,
+                kyt_fee: KYT_FEE,
+            })
+        );
+        read_state(|s| assert!(s.processed_utxos.is_empty()));
+    }
+
+    #[test]
+    fn test_update_balance_releases_utxos_if_minting_fails() {
+        init_state();
+        let error = CallError {
+            method: "transfer".to_string(),
+            reason: "ledger is stopped".to_string(),
+        };
+        let runtime = runtime(vec![utxo(0, 10_000)], Err(error));
+
+        assert_eq!(
+            update_balance(&runtime),
+            Err(UpdateBalanceError::TemporarilyUnavailable(
+                "transfer failed: ledger is stopped".to_string()
+            ))
+        );
+        read_state(|s| {
+            assert!(s.processed_utxos.is_empty());
+            assert!(s.available_utxos.is_empty());
+        });
+
+        let runtime = runtime.set_mint_result(Ok(8));
+        assert_eq!(
+            update_balance(&runtime),
+            Ok(UpdateBalanceResult {
+                amount: 10_000 - KYT_FEE,
+                block_index: 8,
+            })
+        );
+    }
+}
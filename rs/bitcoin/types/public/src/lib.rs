@@ -8,6 +8,7 @@ pub type Address = String;
 pub type Satoshi = u64;
 pub type MillisatoshiPerByte = u64;
 pub type BlockHash = Vec<u8>;
+pub type BlockHeader = Vec<u8>;
 pub type Height = u32;
 pub type Page = ByteBuf;
 
@@ -29,7 +30,7 @@ impl std::fmt::Display for Network {
 }
 
 /// A reference to a transaction output.
-#[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq, Hash)]
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct OutPoint {
     #[serde(with = "serde_bytes")]
     pub txid: Vec<u8>,
@@ -37,7 +38,7 @@ pub struct OutPoint {
 }
 
 /// An unspent transaction output.
-#[derive(CandidType, Debug, Deserialize, PartialEq, Clone, Hash, Eq)]
+#[derive(CandidType, Debug, Deserialize, PartialEq, Clone, Hash, Eq, PartialOrd, Ord)]
 pub struct Utxo {
     pub outpoint: OutPoint,
     pub value: Satoshi,
@@ -140,6 +141,176 @@ impl std::fmt::Display for GetBalanceError {
     }
 }
 
+/// A request for getting the headers of the main chain blocks with heights in
+/// `start_height..=end_height`, or up to the tip if `end_height` is not set.
+#[derive(CandidType, Debug, Deserialize, PartialEq)]
+pub struct GetBlockHeadersRequest {
+    pub start_height: Height,
+    pub end_height: Option<Height>,
+    pub network: Network,
+}
+
+/// The response returned for a request to get the headers of a range of
+/// blocks of the main chain.
+#[derive(CandidType, Debug, Deserialize, PartialEq, Clone)]
+pub struct GetBlockHeadersResponse {
+    pub tip_height: Height,
+    /// The consensus-encoded headers in ascending order of height.
+    pub block_headers: Vec<BlockHeader>,
+}
+
+/// Errors when processing a `get_block_headers` request.
+#[derive(CandidType, Debug, Deserialize, PartialEq, Clone)]
+pub enum GetBlockHeadersError {
+    StartHeightDoesNotExist {
+        requested: Height,
+        chain_height: Height,
+    },
+    EndHeightDoesNotExist {
+        requested: Height,
+        chain_height: Height,
+    },
+    StartHeightLargerThanEndHeight {
+        start_height: Height,
+        end_height: Height,
+    },
+    /// The headers of blocks below `min_available_height` are no longer
+    /// available.
+    HeadersNotAvailable {
+        requested: Height,
+        min_available_height: Height,
+    },
+}
+
+impl std::fmt::Display for GetBlockHeadersError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StartHeightDoesNotExist {
+                requested,
+                chain_height,
+            } => {
+                write!(
+                    f,
+                    "The requested start_height is larger than the height of the chain. Given: {}, height of chain: {}",
+                    requested, chain_height
+                )
+            }
+            Self::EndHeightDoesNotExist {
+                requested,
+                chain_height,
+            } => {
+                write!(
+                    f,
+                    "The requested end_height is larger than the height of the chain. Given: {}, height of chain: {}",
+                    requested, chain_height
+                )
+            }
+            Self::StartHeightLargerThanEndHeight {
+                start_height,
+                end_height,
+            } => {
+                write!(
+                    f,
+                    "The requested start_height is larger than the requested end_height. start_height: {}, end_height: {}",
+                    start_height, end_height
+                )
+            }
+            Self::HeadersNotAvailable {
+                requested,
+                min_available_height,
+            } => {
+                write!(
+                    f,
+                    "The headers of the blocks below height {} are not available. Given: {}",
+                    min_available_height, requested
+                )
+            }
+        }
+    }
+}
+
+/// A request for getting information about the main chain.
+#[derive(CandidType, Debug, Deserialize, PartialEq)]
+pub struct GetBlockchainInfoRequest {
+    pub network: Network,
+}
+
+/// Information about the main chain.
+#[derive(CandidType, Debug, Deserialize, PartialEq, Clone)]
+pub struct BlockchainInfo {
+    pub tip_height: Height,
+    pub tip_block_hash: BlockHash,
+    /// The difficulty of the tip block, relative to the minimum difficulty
+    /// of the network.
+    pub difficulty: u64,
+    /// The height of the latest block that is considered stable.
+    pub stable_height: Height,
+}
+
+/// A transaction that spends from or pays to an address.
+#[derive(CandidType, Debug, Deserialize, PartialEq, Clone)]
+pub struct AddressTransaction {
+    #[serde(with = "serde_bytes")]
+    pub txid: Vec<u8>,
+    pub height: Height,
+    /// The value received by the address minus the value spent by it, in
+    /// satoshis.
+    pub net_amount: i64,
+}
+
+/// A request for getting the transaction history of an address.
+#[derive(CandidType, Debug, Deserialize, PartialEq)]
+pub struct GetTransactionsRequest {
+    pub address: Address,
+    pub network: Network,
+    pub page: Option<Page>,
+}
+
+/// The response returned for a request to get the transaction history of an
+/// address.
+#[derive(CandidType, Debug, Deserialize, PartialEq, Clone)]
+pub struct GetTransactionsResponse {
+    /// The transactions in descending order of height.
+    pub transactions: Vec<AddressTransaction>,
+    /// Only the transactions of the blocks below this height are indexed.
+    pub stable_height: Height,
+    /// Only the transactions of the blocks at or above this height are
+    /// indexed. The history below it is missing if the chain was synced
+    /// before the index was introduced.
+    pub first_indexed_height: Height,
+    pub next_page: Option<Page>,
+}
+
+/// Errors when processing a `get_transactions` request.
+#[derive(CandidType, Debug, Deserialize, PartialEq, Clone)]
+pub enum GetTransactionsError {
+    MalformedAddress,
+    MalformedPage {
+        err: String,
+    },
+    /// The transaction index is not maintained for this network.
+    TransactionIndexDisabled,
+}
+
+impl std::fmt::Display for GetTransactionsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MalformedAddress => {
+                write!(f, "Malformed address.")
+            }
+            Self::MalformedPage { err } => {
+                write!(f, "The provided page is malformed {}", err)
+            }
+            Self::TransactionIndexDisabled => {
+                write!(
+                    f,
+                    "The transaction history of addresses is not available on this network."
+                )
+            }
+        }
+    }
+}
+
 #[derive(CandidType, Debug, Deserialize, PartialEq)]
 pub struct SendTransactionRequest {
     #[serde(with = "serde_bytes")]
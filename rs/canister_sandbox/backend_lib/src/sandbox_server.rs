@@ -151,7 +151,7 @@ mod tests {
     use ic_interfaces::execution_environment::{AvailableMemory, ExecutionMode, HypervisorError};
     use ic_logger::replica_logger::no_op_logger;
     use ic_registry_subnet_type::SubnetType;
-    use ic_replicated_state::{Global, NumWasmPages, PageIndex, PageMap};
+    use ic_replicated_state::{CanisterTimer, Global, NumWasmPages, PageIndex, PageMap};
     use ic_system_api::{
         sandbox_safe_system_state::{CanisterStatusView, SandboxSafeSystemState},
         ApiType, ExecutionParameters, InstructionLimits,
@@ -206,6 +206,10 @@ mod tests {
             0,
             ic00_aliases,
             SMALL_APP_SUBNET_MAX_SIZE,
+            CanisterTimer::Inactive,
+            0,
+            BTreeSet::from([user_test_id(0).get()]),
+            None,
         )
     }
 
@@ -516,6 +516,7 @@ impl WasmExecutor for SandboxedExecutionController {
                                 accessed_pages: 0,
                                 dirty_pages: 0,
                             },
+                            canister_log: Default::default(),
                         },
                         None,
                     ),
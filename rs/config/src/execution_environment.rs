@@ -1,7 +1,7 @@
 use crate::{
     embedders::{self, QUERY_EXECUTION_THREADS},
     flag_status::FlagStatus,
-    subnet_config::MAX_INSTRUCTIONS_PER_MESSAGE,
+    subnet_config::MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS,
 };
 use ic_base_types::NumSeconds;
 use ic_types::{
@@ -51,6 +51,15 @@ const INGRESS_HISTORY_MEMORY_CAPACITY: NumBytes = NumBytes::new(10 * GB);
 /// memory can succeed.
 pub(crate) const SUBNET_HEAP_DELTA_CAPACITY: NumBytes = NumBytes::new(150 * GB);
 
+/// The maximum depth of a query call graph. The user query is at depth one,
+/// so this allows a chain of five nested inter-canister query calls.
+const MAX_QUERY_CALL_GRAPH_DEPTH: usize = 6;
+
+/// The upper limit on the total number of instructions that all executions in
+/// a single query call graph can use. Each individual execution is still
+/// bounded by the per-message instruction limit.
+const MAX_QUERY_CALL_GRAPH_INSTRUCTIONS: NumInstructions = NumInstructions::new(10_000_000_000);
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 #[serde(default)]
 pub struct Config {
@@ -118,13 +127,21 @@ pub struct Config {
     /// Compiling a single WASM instruction should cost as much as executing
     /// this many instructions.
     pub cost_to_compile_wasm_instruction: NumInstructions,
+
+    /// The maximum depth of the call graph of a query that calls other
+    /// canisters, e.g. via composite queries.
+    pub max_query_call_graph_depth: usize,
+
+    /// The maximum total number of instructions that can be executed by all
+    /// messages in the call graph of a query that calls other canisters.
+    pub max_query_call_graph_instructions: NumInstructions,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             create_funds_whitelist: String::default(),
-            max_instructions_for_message_acceptance_calls: MAX_INSTRUCTIONS_PER_MESSAGE,
+            max_instructions_for_message_acceptance_calls: MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS,
             subnet_memory_capacity: SUBNET_MEMORY_CAPACITY,
             subnet_message_memory_capacity: SUBNET_MESSAGE_MEMORY_CAPACITY,
             ingress_history_memory_capacity: INGRESS_HISTORY_MEMORY_CAPACITY,
@@ -144,9 +161,11 @@ impl Default for Config {
             rate_limiting_of_instructions: FlagStatus::Enabled,
             // TODO(RUN-211): Increase the allocatable capacity.
             allocatable_compute_capacity_in_percent: 50,
-            deterministic_time_slicing: FlagStatus::Disabled,
+            deterministic_time_slicing: FlagStatus::Enabled,
             module_sharing: FlagStatus::Enabled,
             cost_to_compile_wasm_instruction: embedders::DEFAULT_COST_TO_COMPILE_WASM_INSTRUCTION,
+            max_query_call_graph_depth: MAX_QUERY_CALL_GRAPH_DEPTH,
+            max_query_call_graph_instructions: MAX_QUERY_CALL_GRAPH_INSTRUCTIONS,
         }
     }
 }
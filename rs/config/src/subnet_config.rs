@@ -14,16 +14,27 @@ const M: u64 = 1_000_000;
 
 // The limit on the number of instructions a message is allowed to executed.
 // Going above the limit results in an `InstructionLimitExceeded` error.
-pub(crate) const MAX_INSTRUCTIONS_PER_MESSAGE: NumInstructions = NumInstructions::new(5 * B);
+// With deterministic time slicing the message may span multiple rounds.
+const MAX_INSTRUCTIONS_PER_MESSAGE: NumInstructions = NumInstructions::new(20 * B);
+
+// The limit on the number of instructions a message that cannot be paused is
+// allowed to execute. This applies to queries, inspect messages, and to update
+// messages if deterministic time slicing is disabled.
+// We assume 1 cycles unit ≅ 1 CPU cycle, so on a 2 GHz CPU one such message
+// has approximately 2.5 seconds to be processed.
+pub(crate) const MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS: NumInstructions =
+    NumInstructions::new(5 * B);
 
 // The limit on the number of instructions a slice is allowed to executed.
 // If deterministic time slicing is enabled, then going above this limit
 // causes the Wasm execution to pause until the next slice.
 // If deterministic time slicing is disabled, then this limit is ignored and
-// `MAX_INSTRUCTIONS_PER_MESSAGE` is used for execution of the single slice.
-// We assume 1 cycles unit ≅ 1 CPU cycle, so on a 2 GHz CPU one message has
-// approximately 2.5 seconds to be processed.
-const MAX_INSTRUCTIONS_PER_SLICE: NumInstructions = NumInstructions::new(5 * B);
+// `MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS` is used for execution of the
+// single slice.
+//
+// System tasks such as heartbeats and timers cannot be paused, so they must
+// finish within a single slice of this size.
+const MAX_INSTRUCTIONS_PER_SLICE: NumInstructions = NumInstructions::new(2 * B);
 
 // We assume 1 cycles unit ≅ 1 CPU cycle, so on a 2 GHz CPU it takes
 // at most 1ms to enter and exit the Wasm engine.
@@ -36,12 +47,12 @@ const INSTRUCTION_OVERHEAD_PER_MESSAGE: NumInstructions = NumInstructions::new(2
 const INSTRUCTION_OVERHEAD_PER_CANISTER_FOR_FINALIZATION: NumInstructions =
     NumInstructions::new(12_000);
 
-// If messages are short, then we expect about 2B=(7B - 5B) instructions to run
-// in a round in about 1 second. Short messages followed by one long message
+// If messages are short, then we expect about 5B=(7B - 2B) instructions to run
+// in a round in about 2.5 seconds. Short messages followed by one long slice
 // would cause the longest possible round of 7B instructions or 3.5 seconds.
 //
 // In general, the round limit should be close to
-// `message_limit + 2B * (1 / finalization_rate)` which ensures that
+// `slice_limit + 5B * (1 / finalization_rate)` which ensures that
 // 1) execution does not slow down finalization.
 // 2) execution does not waste the time available per round.
 const MAX_INSTRUCTIONS_PER_ROUND: NumInstructions = NumInstructions::new(7 * B);
@@ -52,10 +63,15 @@ const MAX_INSTRUCTIONS_PER_ROUND: NumInstructions = NumInstructions::new(7 * B);
 // limitations with the current upgrade process is implemented.
 //
 // The value is picked to allow roughly for 4GB of state to be stored to stable
-// memory during upgrade. We know that we hit `MAX_INSTRUCTIONS_PER_MESSAGE`
-// with roughly 100MB of state, so we set the limit to 40x.
+// memory during upgrade. We know that we hit 5B instructions with roughly
+// 100MB of state, so we set the limit to 40x.
 const MAX_INSTRUCTIONS_PER_INSTALL_CODE: NumInstructions = NumInstructions::new(40 * 5 * B);
 
+// The limit on the number of instructions a slice of an `install_code` message
+// is allowed to execute. It plays the same role as `MAX_INSTRUCTIONS_PER_SLICE`
+// for `canister_init()`, `canister_pre_upgrade()`, and `canister_post_upgrade()`.
+const MAX_INSTRUCTIONS_PER_INSTALL_CODE_SLICE: NumInstructions = NumInstructions::new(2 * B);
+
 // The factor to bump the instruction limit for system subnets.
 const SYSTEM_SUBNET_FACTOR: u64 = 10;
 
@@ -106,6 +122,10 @@ pub struct SchedulerConfig {
     /// Maximum amount of instructions a single message execution can consume.
     pub max_instructions_per_message: NumInstructions,
 
+    /// Maximum amount of instructions a single message execution can consume
+    /// if the execution cannot be paused, e.g. queries and system tasks.
+    pub max_instructions_per_message_without_dts: NumInstructions,
+
     /// Maximum amount of instructions a single slice of execution can consume.
     /// This should not exceed `max_instructions_per_round`.
     pub max_instructions_per_slice: NumInstructions,
@@ -125,6 +145,10 @@ pub struct SchedulerConfig {
     /// Maximum number of instructions an `install_code` message can consume.
     pub max_instructions_per_install_code: NumInstructions,
 
+    /// Maximum number of instructions a single slice of `install_code`
+    /// execution can consume. This should not exceed `max_instructions_per_round`.
+    pub max_instructions_per_install_code_slice: NumInstructions,
+
     /// This specifies the upper limit on how much heap delta all the canisters
     /// together on the subnet can produce in between checkpoints. This is a
     /// soft limit in the sense, that we will continue to execute canisters as
@@ -165,16 +189,18 @@ impl SchedulerConfig {
             subnet_heap_delta_capacity: SUBNET_HEAP_DELTA_CAPACITY,
             max_instructions_per_round: MAX_INSTRUCTIONS_PER_ROUND,
             max_instructions_per_message: MAX_INSTRUCTIONS_PER_MESSAGE,
+            max_instructions_per_message_without_dts: MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS,
             max_instructions_per_slice: MAX_INSTRUCTIONS_PER_SLICE,
             instruction_overhead_per_message: INSTRUCTION_OVERHEAD_PER_MESSAGE,
             instruction_overhead_per_canister_for_finalization:
                 INSTRUCTION_OVERHEAD_PER_CANISTER_FOR_FINALIZATION,
             max_instructions_per_install_code: MAX_INSTRUCTIONS_PER_INSTALL_CODE,
+            max_instructions_per_install_code_slice: MAX_INSTRUCTIONS_PER_INSTALL_CODE_SLICE,
             max_heap_delta_per_iteration: MAX_HEAP_DELTA_PER_ITERATION,
             max_message_duration_before_warn_in_seconds:
                 MAX_MESSAGE_DURATION_BEFORE_WARN_IN_SECONDS,
             heap_delta_rate_limit: NumBytes::from(75 * 1024 * 1024),
-            install_code_rate_limit: MAX_INSTRUCTIONS_PER_MESSAGE,
+            install_code_rate_limit: MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS,
         }
     }
 
@@ -185,11 +211,15 @@ impl SchedulerConfig {
             subnet_heap_delta_capacity: SUBNET_HEAP_DELTA_CAPACITY,
             max_instructions_per_round: MAX_INSTRUCTIONS_PER_ROUND * SYSTEM_SUBNET_FACTOR,
             max_instructions_per_message: MAX_INSTRUCTIONS_PER_MESSAGE * SYSTEM_SUBNET_FACTOR,
+            max_instructions_per_message_without_dts: MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS
+                * SYSTEM_SUBNET_FACTOR,
             max_instructions_per_slice: MAX_INSTRUCTIONS_PER_SLICE * SYSTEM_SUBNET_FACTOR,
             instruction_overhead_per_message: INSTRUCTION_OVERHEAD_PER_MESSAGE,
             instruction_overhead_per_canister_for_finalization:
                 INSTRUCTION_OVERHEAD_PER_CANISTER_FOR_FINALIZATION,
             max_instructions_per_install_code,
+            max_instructions_per_install_code_slice: MAX_INSTRUCTIONS_PER_INSTALL_CODE_SLICE
+                * SYSTEM_SUBNET_FACTOR,
             max_heap_delta_per_iteration: MAX_HEAP_DELTA_PER_ITERATION * SYSTEM_SUBNET_FACTOR,
             max_message_duration_before_warn_in_seconds:
                 MAX_MESSAGE_DURATION_BEFORE_WARN_IN_SECONDS,
@@ -208,16 +238,18 @@ impl SchedulerConfig {
             subnet_heap_delta_capacity: SUBNET_HEAP_DELTA_CAPACITY,
             max_instructions_per_round: MAX_INSTRUCTIONS_PER_ROUND,
             max_instructions_per_message: MAX_INSTRUCTIONS_PER_MESSAGE,
+            max_instructions_per_message_without_dts: MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS,
             max_instructions_per_slice: MAX_INSTRUCTIONS_PER_SLICE,
             instruction_overhead_per_message: INSTRUCTION_OVERHEAD_PER_MESSAGE,
             instruction_overhead_per_canister_for_finalization:
                 INSTRUCTION_OVERHEAD_PER_CANISTER_FOR_FINALIZATION,
             max_instructions_per_install_code: MAX_INSTRUCTIONS_PER_INSTALL_CODE,
+            max_instructions_per_install_code_slice: MAX_INSTRUCTIONS_PER_INSTALL_CODE_SLICE,
             max_heap_delta_per_iteration: MAX_HEAP_DELTA_PER_ITERATION,
             max_message_duration_before_warn_in_seconds:
                 MAX_MESSAGE_DURATION_BEFORE_WARN_IN_SECONDS,
             heap_delta_rate_limit: NumBytes::from(75 * 1024 * 1024),
-            install_code_rate_limit: MAX_INSTRUCTIONS_PER_MESSAGE,
+            install_code_rate_limit: MAX_INSTRUCTIONS_PER_MESSAGE_WITHOUT_DTS,
         }
     }
 
@@ -1,7 +1,7 @@
 use ic_interfaces::{
     canister_http::CanisterHttpPayloadBuilder, consensus::PayloadValidationError,
     ingress_manager::IngressSelector, messaging::XNetPayloadBuilder,
-    self_validating_payload::SelfValidatingPayloadBuilder,
+    query_stats::QueryStatsPayloadBuilder, self_validating_payload::SelfValidatingPayloadBuilder,
 };
 use ic_logger::{error, warn, ReplicaLogger};
 use ic_types::{
@@ -9,7 +9,7 @@ use ic_types::{
         BatchPayload, CanisterHttpPayload, IngressPayload, SelfValidatingPayload, ValidationContext,
     },
     consensus::Payload,
-    CountBytes, Height, NumBytes, Time,
+    CountBytes, Height, NodeId, NumBytes, Time,
 };
 use std::sync::Arc;
 
@@ -37,6 +37,7 @@ pub(crate) enum BatchPayloadSectionBuilder {
     XNet(Arc<dyn XNetPayloadBuilder>),
     SelfValidating(Arc<dyn SelfValidatingPayloadBuilder>),
     CanisterHttp(Arc<dyn CanisterHttpPayloadBuilder>),
+    QueryStats(Arc<dyn QueryStatsPayloadBuilder>),
 }
 
 impl BatchPayloadSectionBuilder {
@@ -164,12 +165,51 @@ impl BatchPayloadSectionBuilder {
                     }
                 }
             }
+            Self::QueryStats(builder) => {
+                let past_payloads = builder.filter_past_payloads(past_payloads);
+                let query_stats = match builder.get_query_stats_payload(
+                    validation_context,
+                    &past_payloads,
+                    max_size,
+                ) {
+                    Some(query_stats) => query_stats,
+                    None => return NumBytes::new(0),
+                };
+                let size = NumBytes::new(query_stats.count_bytes() as u64);
+
+                // Check that the size limit is respected
+                if size > max_size {
+                    error!(
+                        logger,
+                        "QueryStatsPayload is larger than byte_limit. This is a bug."
+                    );
+                    return NumBytes::new(0);
+                }
+
+                // Check validation as safety measure
+                if let Err(err) = builder.validate_query_stats_payload(
+                    &query_stats,
+                    query_stats.proposer,
+                    validation_context,
+                    &past_payloads,
+                ) {
+                    error!(
+                        logger,
+                        "QueryStats payload did not pass validation, this is a bug, {:?}", err
+                    );
+                    return NumBytes::new(0);
+                }
+
+                payload.query_stats = Some(query_stats);
+                size
+            }
         }
     }
 
     /// Called to validate the payload.
     ///
     /// # Argument:
+    /// - `block_maker`: The [`NodeId`] of the node that made the block.
     /// - `payload`: The payload to verify.
     /// - `validation_context`: The [`ValidationContext`], under which to validate the payload.
     /// - `past_payloads`: All [`Payload`]s from the certified height to the tip.
@@ -180,6 +220,7 @@ impl BatchPayloadSectionBuilder {
     pub(crate) fn validate_payload(
         &self,
         height: Height,
+        block_maker: NodeId,
         payload: &BatchPayload,
         validation_context: &ValidationContext,
         past_payloads: &[(Height, Time, Payload)],
@@ -219,6 +260,18 @@ impl BatchPayloadSectionBuilder {
                     &past_payloads,
                 )?)
             }
+            BatchPayloadSectionBuilder::QueryStats(builder) => match &payload.query_stats {
+                Some(query_stats) => {
+                    let past_payloads = builder.filter_past_payloads(past_payloads);
+                    Ok(builder.validate_query_stats_payload(
+                        query_stats,
+                        block_maker,
+                        validation_context,
+                        &past_payloads,
+                    )?)
+                }
+                None => Ok(NumBytes::new(0)),
+            },
         }
     }
 }
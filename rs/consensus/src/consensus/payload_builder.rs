@@ -8,6 +8,7 @@ use ic_interfaces::{
     consensus::{PayloadPermanentError, PayloadTransientError, PayloadValidationError},
     ingress_manager::IngressSelector,
     messaging::XNetPayloadBuilder,
+    query_stats::QueryStatsPayloadBuilder,
     registry::RegistryClient,
     self_validating_payload::SelfValidatingPayloadBuilder,
     validation::{ValidationError, ValidationResult},
@@ -20,7 +21,7 @@ use ic_types::{
     batch::{BatchPayload, ValidationContext, MAX_BITCOIN_BLOCK_SIZE},
     consensus::Payload,
     messages::MAX_XNET_PAYLOAD_IN_BYTES,
-    Height, NumBytes, SubnetId, Time,
+    Height, NodeId, NumBytes, SubnetId, Time,
 };
 use std::sync::Arc;
 
@@ -40,8 +41,8 @@ pub trait PayloadBuilder: Send + Sync {
         subnet_records: &SubnetRecords,
     ) -> BatchPayload;
 
-    /// Checks whether the provided `payload` is valid given `past_payloads` and
-    /// `context`.
+    /// Checks whether the provided `payload` of the block made by `block_maker`
+    /// is valid given `past_payloads` and `context`.
     ///
     /// `past_payloads` contains the `Payloads` from all blocks above the
     /// certified height provided in `context`, in descending block height
@@ -49,6 +50,7 @@ pub trait PayloadBuilder: Send + Sync {
     fn validate_payload(
         &self,
         height: Height,
+        block_maker: NodeId,
         payload: &Payload,
         past_payloads: &[(Height, Time, Payload)],
         context: &ValidationContext,
@@ -73,6 +75,7 @@ impl PayloadBuilderImpl {
         xnet_payload_builder: Arc<dyn XNetPayloadBuilder>,
         self_validating_payload_builder: Arc<dyn SelfValidatingPayloadBuilder>,
         canister_http_payload_builder: Arc<dyn CanisterHttpPayloadBuilder>,
+        query_stats_payload_builder: Arc<dyn QueryStatsPayloadBuilder>,
         metrics: MetricsRegistry,
         logger: ReplicaLogger,
     ) -> Self {
@@ -81,6 +84,7 @@ impl PayloadBuilderImpl {
             BatchPayloadSectionBuilder::SelfValidating(self_validating_payload_builder),
             BatchPayloadSectionBuilder::XNet(xnet_payload_builder),
             BatchPayloadSectionBuilder::CanisterHttp(canister_http_payload_builder),
+            BatchPayloadSectionBuilder::QueryStats(query_stats_payload_builder),
         ];
 
         Self {
@@ -141,6 +145,7 @@ impl PayloadBuilder for PayloadBuilderImpl {
     fn validate_payload(
         &self,
         height: Height,
+        block_maker: NodeId,
         payload: &Payload,
         past_payloads: &[(Height, Time, Payload)],
         context: &ValidationContext,
@@ -176,8 +181,13 @@ impl PayloadBuilder for PayloadBuilderImpl {
 
         let mut accumulated_size = NumBytes::new(0);
         for builder in &self.section_builder {
-            accumulated_size +=
-                builder.validate_payload(height, batch_payload, context, past_payloads)?;
+            accumulated_size += builder.validate_payload(
+                height,
+                block_maker,
+                batch_payload,
+                context,
+                past_payloads,
+            )?;
             if accumulated_size > max_block_payload_size {
                 return Err(ValidationError::Permanent(
                     PayloadPermanentError::PayloadTooBig {
@@ -228,6 +238,7 @@ mod test {
         consensus::fake::Fake,
         ingress_selector::FakeIngressSelector,
         mock_time,
+        query_stats_payload_builder::FakeQueryStatsPayloadBuilder,
         self_validating_payload_builder::FakeSelfValidatingPayloadBuilder,
         types::ids::{node_test_id, subnet_test_id},
         types::messages::SignedIngressBuilder,
@@ -275,6 +286,7 @@ mod test {
             Arc::new(xnet_payload_builder),
             Arc::new(self_validating_payload_builder),
             Arc::new(canister_http_payload_builder),
+            Arc::new(FakeQueryStatsPayloadBuilder::new()),
             MetricsRegistry::new(),
             no_op_logger(),
         )
@@ -444,7 +456,13 @@ mod test {
             let wrapped_payload0 = wrap_batch_payload(0, payload0);
 
             payload_builder
-                .validate_payload(Height::from(0), &wrapped_payload0, &[], &context)
+                .validate_payload(
+                    Height::from(0),
+                    node_test_id(0),
+                    &wrapped_payload0,
+                    &[],
+                    &context,
+                )
                 .unwrap();
 
             // Build second payload and validate it
@@ -459,7 +477,13 @@ mod test {
             let wrapped_payload1 = wrap_batch_payload(0, payload1);
 
             payload_builder
-                .validate_payload(Height::from(1), &wrapped_payload1, &past_payload0, &context)
+                .validate_payload(
+                    Height::from(1),
+                    node_test_id(0),
+                    &wrapped_payload1,
+                    &past_payload0,
+                    &context,
+                )
                 .unwrap();
 
             // Build third payload and validate it
@@ -475,7 +499,13 @@ mod test {
             let wrapped_payload2 = wrap_batch_payload(1, payload2);
 
             payload_builder
-                .validate_payload(Height::from(2), &wrapped_payload2, &past_payload1, &context)
+                .validate_payload(
+                    Height::from(2),
+                    node_test_id(0),
+                    &wrapped_payload2,
+                    &past_payload1,
+                    &context,
+                )
                 .unwrap();
         });
     }
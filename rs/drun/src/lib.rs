@@ -1,12 +1,18 @@
 //! Standalone interface for testing application canisters.
 
-use crate::message::{msg_stream_from_file, Message};
+use crate::message::{msg_stream_from_file, ExpectedOutcome, Message};
+use candid::IDLArgs;
 use hex::encode;
-use ic_config::{subnet_config::SubnetConfigs, Config};
+use ic_config::{
+    state_manager::Config as StateManagerConfig, subnet_config::SubnetConfigs, Config,
+};
 use ic_cycles_account_manager::CyclesAccountManager;
-use ic_error_types::{ErrorCode, UserError};
+use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_execution_environment::ExecutionServices;
-use ic_interfaces::{execution_environment::IngressHistoryReader, messaging::MessageRouting};
+use ic_interfaces::{
+    execution_environment::{IngressHistoryReader, QueryHandler},
+    messaging::MessageRouting,
+};
 use ic_interfaces_state_manager::StateReader;
 use ic_messaging::MessageRoutingImpl;
 use ic_metrics::MetricsRegistry;
@@ -25,6 +31,7 @@ use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_routing_table::{routing_table_insert_subnet, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::ReplicatedState;
 use ic_state_manager::StateManagerImpl;
 use ic_test_utilities::consensus::fake::FakeVerifier;
 use ic_test_utilities_registry::{
@@ -35,15 +42,18 @@ use ic_types::{
     ingress::{IngressState, IngressStatus, WasmResult},
     messages::{MessageId, SignedIngress},
     replica_config::ReplicaConfig,
-    time::UNIX_EPOCH,
+    time::{Time, UNIX_EPOCH},
     CanisterId, NodeId, PrincipalId, Randomness, RegistryVersion, SubnetId,
 };
+use serde_json::json;
 use slog::{Drain, Logger};
 use std::collections::BTreeMap;
-use std::fs::OpenOptions;
-use std::path::PathBuf;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::{thread::sleep, time::Duration};
+use tempfile::TempDir;
 
 mod message;
 
@@ -53,27 +63,65 @@ const MAX_BATCHES_UNTIL_RESPONSE: u64 = 10000;
 // how long to wait between batches
 const WAIT_PER_BATCH: Duration = Duration::from_millis(5);
 
+// the directory holding the checkpoints in a state root, see `StateLayout`
+const CHECKPOINTS_DIR: &str = "checkpoints";
+
+// the histograms tracking the instructions executed by update and query calls
+const INSTRUCTIONS_METRICS: [&str; 2] = [
+    "scheduler_instructions_consumed_per_round",
+    "execution_query_instructions",
+];
+
+/// The format in which the result of each message is printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One line of free-form text per message.
+    Text,
+    /// One JSON record per message.
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!(
+                "Unknown output format {}, expected one of: text, json.",
+                s
+            )),
+        }
+    }
+}
+
 pub struct DrunOptions {
     pub msg_filename: String,
     pub cfg: Config,
     pub extra_batches: u64,
     pub log_file: Option<PathBuf>,
+    pub output_format: OutputFormat,
+    pub load_state: Option<PathBuf>,
 }
 
-/// Deliver a single message to the Message Routing layer
+/// Deliver a single message to the Message Routing layer and return its
+/// result.
 fn deliver_message(
     msg: SignedIngress,
     message_routing: &dyn MessageRouting,
     ingress_hist_reader: &dyn IngressHistoryReader,
     extra_batches: u64,
-) {
+    time: Time,
+) -> Result<WasmResult, UserError> {
     let message_id = msg.id();
 
-    let _ = execute_ingress_message(message_routing, msg, &message_id, ingress_hist_reader);
-    // print result after waiting, to not interleave the result
+    let result =
+        execute_ingress_message(message_routing, msg, &message_id, ingress_hist_reader, time);
+    // the result is printed after waiting, to not interleave the result
     // with debug.print messages from subsequent calls. revise after DFN-1269.
-    wait_extra_batches(message_routing, extra_batches);
-    print_ingress_result(&message_id, ingress_hist_reader);
+    wait_extra_batches(message_routing, extra_batches, time);
+    result
 }
 
 fn setup_logger(log_file: PathBuf) -> Logger {
@@ -149,131 +197,395 @@ fn get_registry(
     registry_client
 }
 
+/// The components needed to execute messages on top of the replicated state
+/// stored in a single state directory.
+struct Services {
+    message_routing: MessageRoutingImpl,
+    ingress_hist_reader: Box<dyn IngressHistoryReader>,
+    query_handler: Arc<dyn QueryHandler<State = ReplicatedState>>,
+    state_manager: Arc<StateManagerImpl>,
+    metrics_registry: MetricsRegistry,
+    // The state directory of a restored checkpoint, removed when dropped.
+    _state_dir: Option<TempDir>,
+}
+
+impl Services {
+    /// Sets up the services on top of the latest checkpoint in `state_root`,
+    /// or on top of an empty state if there is none.
+    fn new(
+        cfg: &Config,
+        state_root: PathBuf,
+        metrics_registry: MetricsRegistry,
+        log: &Logger,
+    ) -> Self {
+        // Hardcoded magic values to create a ReplicaConfig that parses.
+        let subnet_type = SubnetType::System;
+        let subnet_config = SubnetConfigs::default().own_subnet_config(subnet_type);
+        let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(0));
+        let root_subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(1));
+        let replica_config = ReplicaConfig {
+            node_id: NodeId::from(PrincipalId::new_node_test_id(27)),
+            subnet_id,
+        };
+
+        let registry = get_registry(
+            &metrics_registry,
+            subnet_id,
+            root_subnet_id,
+            subnet_type,
+            &[replica_config.node_id],
+        );
+
+        let cycles_account_manager = Arc::new(CyclesAccountManager::new(
+            subnet_config.scheduler_config.max_instructions_per_message,
+            subnet_type,
+            subnet_id,
+            subnet_config.cycles_account_manager_config,
+        ));
+
+        let state_manager = Arc::new(StateManagerImpl::new(
+            Arc::new(FakeVerifier::new()),
+            replica_config.subnet_id,
+            subnet_type,
+            log.clone().into(),
+            &metrics_registry,
+            &StateManagerConfig::new(state_root),
+            None,
+            ic_types::malicious_flags::MaliciousFlags::default(),
+        ));
+        let (_, ingress_history_writer, ingress_hist_reader, query_handler, _, _, scheduler) =
+            ExecutionServices::setup_execution(
+                log.clone().into(),
+                &metrics_registry,
+                replica_config.subnet_id,
+                subnet_type,
+                subnet_config.scheduler_config,
+                cfg.hypervisor.clone(),
+                Arc::clone(&cycles_account_manager),
+                Arc::clone(&state_manager) as Arc<_>,
+            )
+            .into_parts();
+
+        let message_routing = MessageRoutingImpl::new(
+            Arc::clone(&state_manager) as _,
+            Arc::clone(&state_manager) as _,
+            Arc::clone(&ingress_history_writer) as _,
+            scheduler,
+            cfg.hypervisor.clone(),
+            cycles_account_manager,
+            replica_config.subnet_id,
+            &metrics_registry,
+            log.clone().into(),
+            Arc::clone(&registry) as _,
+        );
+
+        Self {
+            message_routing,
+            ingress_hist_reader,
+            query_handler,
+            state_manager,
+            metrics_registry,
+            _state_dir: None,
+        }
+    }
+
+    /// Sets up the services on top of a checkpoint previously saved to `dir`
+    /// with [`Services::checkpoint`].
+    ///
+    /// The checkpoint is copied into a fresh state directory, so that it
+    /// cannot collide with the checkpoints of any other state root.
+    fn restore(
+        cfg: &Config,
+        dir: &Path,
+        metrics_registry: MetricsRegistry,
+        log: &Logger,
+    ) -> Result<Self, String> {
+        let state_dir = tempfile::Builder::new()
+            .prefix("drun_state")
+            .tempdir()
+            .map_err(|e| format!("Failed to create a state directory: {}", e))?;
+        copy_checkpoints(dir, state_dir.path())?;
+
+        let mut services = Self::new(cfg, state_dir.path().to_path_buf(), metrics_registry, log);
+        services._state_dir = Some(state_dir);
+        Ok(services)
+    }
+
+    /// Executes an empty round that creates a checkpoint of the replicated
+    /// state, and saves the checkpoint to `dir`, replacing its contents.
+    fn checkpoint(&self, dir: &Path, time: Time) -> Result<(), String> {
+        let mut batch = build_batch(&self.message_routing, vec![], time);
+        batch.requires_full_state_hash = true;
+        let height = batch.batch_number;
+        while self.message_routing.deliver_batch(batch.clone()).is_err() {
+            sleep(WAIT_PER_BATCH);
+        }
+
+        // The batch is executed asynchronously, so wait for the state manager
+        // to write the checkpoint.
+        let mut retries = 0;
+        while !self.state_manager.checkpoint_heights().contains(&height) {
+            retries += 1;
+            if retries > MAX_BATCHES_UNTIL_RESPONSE {
+                panic!(
+                    "Checkpoint at height {} was not created within {} batches, panicking",
+                    height, MAX_BATCHES_UNTIL_RESPONSE
+                );
+            }
+            sleep(WAIT_PER_BATCH);
+        }
+
+        let checkpoint = self
+            .state_manager
+            .state_layout()
+            .checkpoint(height)
+            .map_err(|e| format!("Failed to open checkpoint at height {}: {}", height, e))?;
+        if dir.exists() {
+            fs::remove_dir_all(dir)
+                .map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))?;
+        }
+        let name = checkpoint
+            .raw_path()
+            .file_name()
+            .expect("checkpoint path must have a name");
+        copy_dir(checkpoint.raw_path(), &dir.join(CHECKPOINTS_DIR).join(name))
+    }
+
+    /// Returns the time of the latest replicated state.
+    fn batch_time(&self) -> Time {
+        self.state_manager
+            .get_latest_state()
+            .take()
+            .metadata
+            .batch_time
+    }
+
+    fn measure_usage(&self) -> ResourceUsage {
+        ResourceUsage::measure(self.state_manager.as_ref(), &self.metrics_registry)
+    }
+}
+
+/// Copies the checkpoints saved to `dir` into the given state root.
+fn copy_checkpoints(dir: &Path, state_root: &Path) -> Result<(), String> {
+    let checkpoints = dir.join(CHECKPOINTS_DIR);
+    if !checkpoints.is_dir() {
+        return Err(format!(
+            "{} does not contain a checkpoint saved by drun",
+            dir.display()
+        ));
+    }
+    copy_dir(&checkpoints, &state_root.join(CHECKPOINTS_DIR))
+}
+
+/// Recursively copies the directory `from` to `to`.
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    let copy_err = |e: std::io::Error| {
+        format!(
+            "Failed to copy {} to {}: {}",
+            from.display(),
+            to.display(),
+            e
+        )
+    };
+    fs::create_dir_all(to).map_err(copy_err)?;
+    for entry in fs::read_dir(from).map_err(copy_err)? {
+        let entry = entry.map_err(copy_err)?;
+        let target = to.join(entry.file_name());
+        if entry.file_type().map_err(copy_err)?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target).map_err(copy_err)?;
+        }
+    }
+    Ok(())
+}
+
 pub fn run_drun(uo: DrunOptions) -> Result<(), String> {
     let DrunOptions {
         msg_filename,
         cfg,
         extra_batches,
         log_file,
+        output_format,
+        load_state,
     } = uo;
-    // Hardcoded magic values to create a ReplicaConfig that parses.
-    let subnet_type = SubnetType::System;
-    let subnet_config = SubnetConfigs::default().own_subnet_config(subnet_type);
-    let subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(0));
-    let root_subnet_id = SubnetId::from(PrincipalId::new_subnet_test_id(1));
-    let replica_config = ReplicaConfig {
-        node_id: NodeId::from(PrincipalId::new_node_test_id(27)),
-        subnet_id,
-    };
 
-    let mut msg_stream = msg_stream_from_file(&msg_filename)?;
+    let msg_stream = msg_stream_from_file(&msg_filename)?;
     let log = match log_file {
         Some(log_file) => setup_logger(log_file),
         None => slog::Logger::root(slog::Discard, slog::o!()),
     };
 
     let metrics_registry = MetricsRegistry::global();
-    let registry = get_registry(
-        &metrics_registry,
-        subnet_id,
-        root_subnet_id,
-        subnet_type,
-        &[replica_config.node_id],
-    );
-
-    let cycles_account_manager = Arc::new(CyclesAccountManager::new(
-        subnet_config.scheduler_config.max_instructions_per_message,
-        subnet_type,
-        subnet_id,
-        subnet_config.cycles_account_manager_config,
-    ));
-
-    let state_manager = Arc::new(StateManagerImpl::new(
-        Arc::new(FakeVerifier::new()),
-        replica_config.subnet_id,
-        subnet_type,
-        log.clone().into(),
-        &metrics_registry,
-        &cfg.state_manager,
-        None,
-        ic_types::malicious_flags::MaliciousFlags::default(),
-    ));
-    let (_, ingress_history_writer, ingress_hist_reader, query_handler, _, _, scheduler) =
-        ExecutionServices::setup_execution(
-            log.clone().into(),
-            &metrics_registry,
-            replica_config.subnet_id,
-            subnet_type,
-            subnet_config.scheduler_config,
-            cfg.hypervisor.clone(),
-            Arc::clone(&cycles_account_manager),
-            Arc::clone(&state_manager) as Arc<_>,
-        )
-        .into_parts();
-
     let _metrics_runtime = MetricsRuntimeImpl::new_insecure(
         tokio::runtime::Handle::current(),
-        cfg.metrics,
+        cfg.metrics.clone(),
         metrics_registry.clone(),
         &log,
     );
+    let mut services = match load_state {
+        Some(dir) => Services::restore(&cfg, &dir, metrics_registry, &log)?,
+        None => Services::new(&cfg, cfg.state_manager.state_root(), metrics_registry, &log),
+    };
 
-    let message_routing = MessageRoutingImpl::new(
-        Arc::clone(&state_manager) as _,
-        Arc::clone(&state_manager) as _,
-        Arc::clone(&ingress_history_writer) as _,
-        scheduler,
-        cfg.hypervisor,
-        cycles_account_manager,
-        replica_config.subnet_id,
-        &metrics_registry,
-        log.clone().into(),
-        Arc::clone(&registry) as _,
-    );
+    let deliver = |services: &Services, kind: &str, msg: SignedIngress, time: Time| {
+        let message_id = msg.id();
+        let usage_before = services.measure_usage();
+        let result = deliver_message(
+            msg,
+            &services.message_routing,
+            services.ingress_hist_reader.as_ref(),
+            extra_batches,
+            time,
+        );
+        let usage = services.measure_usage().since(&usage_before);
+        print_result(output_format, kind, Some(&message_id), &result, usage);
+        result
+    };
 
-    msg_stream.try_for_each(|parse_result| {
-        parse_result.map(|msg| match msg {
-            Message::Install(msg) => {
-                deliver_message(
-                    msg,
-                    &message_routing,
-                    ingress_hist_reader.as_ref(),
-                    extra_batches,
-                );
-            }
+    // the time of the delivered batches, advanced by `advance-time` directives
+    // and never earlier than the time of a loaded state
+    let mut time = services.batch_time();
+    let mut last_result = None;
+    let mut failed_expectations = 0;
+    for parse_result in msg_stream {
+        let result = match parse_result? {
+            Message::Install(msg) => deliver(&services, "install", msg, time),
 
             Message::Query(q) => {
+                let usage_before = services.measure_usage();
                 // NOTE: Data certificates aren't supported in drun yet.
                 // To support them, we'd need to do something similar to
                 // http_handler::get_latest_certified_state_and_data_certificate
-                print_query_result(query_handler.query(
+                let result = services.query_handler.query(
                     q,
-                    state_manager.get_latest_state().take(),
+                    services.state_manager.get_latest_state().take(),
                     Vec::new(),
-                ));
+                );
+                let usage = services.measure_usage().since(&usage_before);
+                print_result(output_format, "query", None, &result, usage);
+                result
             }
 
-            Message::Ingress(msg) => {
-                deliver_message(
-                    msg,
-                    &message_routing,
-                    ingress_hist_reader.as_ref(),
-                    extra_batches,
-                );
+            Message::Ingress(msg) => deliver(&services, "ingress", msg, time),
+            Message::Create(msg) => deliver(&services, "create", msg, time),
+
+            Message::Expect(expectation) => {
+                if let Err(err) = check_expectation(&expectation.outcome, last_result.as_ref()) {
+                    eprintln!("Line {}: {}", expectation.line, err);
+                    failed_expectations += 1;
+                }
+                continue;
             }
-            Message::Create(msg) => {
-                deliver_message(
-                    msg,
-                    &message_routing,
-                    ingress_hist_reader.as_ref(),
-                    extra_batches,
-                );
+
+            Message::AdvanceTime(duration) => {
+                time += duration;
+                continue;
             }
-        })
-    })
+
+            Message::Tick(rounds) => {
+                wait_extra_batches(&services.message_routing, rounds, time);
+                continue;
+            }
+
+            Message::Checkpoint(dir) => {
+                services.checkpoint(&dir, time)?;
+                continue;
+            }
+
+            Message::Restore(dir) => {
+                // The metrics of the current services are already registered in
+                // the global registry, so the restored services use a fresh one.
+                services = Services::restore(&cfg, &dir, MetricsRegistry::new(), &log)?;
+                time = std::cmp::max(time, services.batch_time());
+                continue;
+            }
+        };
+        last_result = Some(result);
+    }
+
+    if failed_expectations > 0 {
+        return Err(format!("{} expectation(s) failed.", failed_expectations));
+    }
+    Ok(())
+}
+
+/// Cycles and instructions consumed on the subnet, used to attribute resource
+/// usage to individual messages.
+#[derive(Clone, Copy, Debug, Default)]
+struct ResourceUsage {
+    cycles: u128,
+    instructions: u64,
+}
+
+impl ResourceUsage {
+    /// Returns the resources consumed since the replica started.
+    ///
+    /// Cycles are read from the canister metrics in the latest state, while
+    /// instructions are read from the execution metrics, as the state does not
+    /// track them.
+    fn measure(
+        state_reader: &dyn StateReader<State = ReplicatedState>,
+        metrics_registry: &MetricsRegistry,
+    ) -> Self {
+        let cycles = state_reader
+            .get_latest_state()
+            .take()
+            .canisters_iter()
+            .map(|canister| {
+                canister
+                    .system_state
+                    .canister_metrics
+                    .consumed_cycles_since_replica_started
+                    .get()
+            })
+            .sum();
+
+        let instructions = metrics_registry
+            .prometheus_registry()
+            .gather()
+            .iter()
+            .filter(|family| {
+                INSTRUCTIONS_METRICS
+                    .iter()
+                    .any(|name| *name == family.get_name())
+            })
+            .flat_map(|family| family.get_metric())
+            .map(|metric| metric.get_histogram().get_sample_sum())
+            .sum::<f64>() as u64;
+
+        Self {
+            cycles,
+            instructions,
+        }
+    }
+
+    /// Returns the resources consumed between `earlier` and `self`.
+    fn since(&self, earlier: &Self) -> Self {
+        Self {
+            cycles: self.cycles.saturating_sub(earlier.cycles),
+            instructions: self.instructions.saturating_sub(earlier.instructions),
+        }
+    }
 }
 
-fn print_query_result(res: Result<WasmResult, UserError>) {
+fn print_result(
+    output_format: OutputFormat,
+    kind: &str,
+    message_id: Option<&MessageId>,
+    result: &Result<WasmResult, UserError>,
+    usage: ResourceUsage,
+) {
+    match output_format {
+        OutputFormat::Text => match message_id {
+            Some(_) => print_ingress_result(result),
+            None => print_query_result(result),
+        },
+        OutputFormat::Json => println!("{}", json_record(kind, message_id, result, usage)),
+    }
+}
+
+fn print_query_result(res: &Result<WasmResult, UserError>) {
     match res {
         Ok(payload) => {
             print!("Ok: ");
@@ -283,33 +595,123 @@ fn print_query_result(res: Result<WasmResult, UserError>) {
     }
 }
 
-fn print_ingress_result(message_id: &MessageId, ingress_hist_reader: &dyn IngressHistoryReader) {
-    let status = (ingress_hist_reader.get_latest_status())(message_id);
+fn print_ingress_result(res: &Result<WasmResult, UserError>) {
     print!("ingress ");
-    match status {
-        IngressStatus::Known {
-            state: IngressState::Completed(result),
-            ..
-        } => {
+    match res {
+        Ok(result) => {
             print!("Completed: ");
             print_wasm_result(result)
         }
-        IngressStatus::Known {
-            state: IngressState::Failed(error),
-            ..
-        } => println!("Err: {}", error),
-        _ => panic!("Ingress message has not finished processing."),
+        Err(error) => println!("Err: {}", error),
     };
 }
 
-fn print_wasm_result(wasm_result: WasmResult) {
+fn print_wasm_result(wasm_result: &WasmResult) {
     match wasm_result {
         WasmResult::Reply(v) => println!("Reply: 0x{}", encode(v)),
         WasmResult::Reject(e) => println!("Reject: {}", e),
     }
 }
 
-fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -> Batch {
+/// Builds the JSON record describing the result of a single message.
+fn json_record(
+    kind: &str,
+    message_id: Option<&MessageId>,
+    result: &Result<WasmResult, UserError>,
+    usage: ResourceUsage,
+) -> serde_json::Value {
+    let mut record = json!({
+        "type": kind,
+        // Cycles may exceed the range of JSON numbers supported by most parsers.
+        "cycles_used": usage.cycles.to_string(),
+        "instructions_used": usage.instructions,
+    });
+    if let Some(message_id) = message_id {
+        record["message_id"] = json!(message_id.to_string());
+    }
+
+    match result {
+        Ok(WasmResult::Reply(payload)) => {
+            record["status"] = json!("replied");
+            record["reply"] = json!(format!("0x{}", encode(payload)));
+            record["reply_candid"] = match IDLArgs::from_bytes(payload) {
+                Ok(args) => json!(args.to_string()),
+                Err(_) => serde_json::Value::Null,
+            };
+        }
+        Ok(WasmResult::Reject(message)) => {
+            record["status"] = json!("rejected");
+            record["reject_code"] = json!(RejectCode::CanisterReject as u64);
+            record["reject_message"] = json!(message);
+        }
+        Err(error) => {
+            record["status"] = json!("rejected");
+            record["reject_code"] = json!(error.reject_code() as u64);
+            record["reject_message"] = json!(error.description());
+            record["error_code"] = json!(error.code().to_string());
+        }
+    }
+    record
+}
+
+/// Checks the result of the last message against an `expect-*` directive.
+fn check_expectation(
+    expected: &ExpectedOutcome,
+    actual: Option<&Result<WasmResult, UserError>>,
+) -> Result<(), String> {
+    let actual =
+        actual.ok_or_else(|| "No message to check the expectation against.".to_string())?;
+    match (expected, actual) {
+        (ExpectedOutcome::Reply(None), Ok(WasmResult::Reply(_))) => Ok(()),
+        (ExpectedOutcome::Reply(Some(expected)), Ok(WasmResult::Reply(payload))) => {
+            if expected == payload {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Expected reply 0x{}, got reply 0x{}.",
+                    encode(expected),
+                    encode(payload)
+                ))
+            }
+        }
+        (ExpectedOutcome::Reply(_), Ok(WasmResult::Reject(message))) => Err(format!(
+            "Expected a reply, got reject {} ({}).",
+            RejectCode::CanisterReject as u64,
+            message
+        )),
+        (ExpectedOutcome::Reply(_), Err(error)) => Err(format!(
+            "Expected a reply, got reject {} ({}).",
+            error.reject_code() as u64,
+            error
+        )),
+        (ExpectedOutcome::Reject(expected), Ok(WasmResult::Reject(_))) => {
+            check_reject_code(*expected, RejectCode::CanisterReject)
+        }
+        (ExpectedOutcome::Reject(expected), Err(error)) => {
+            check_reject_code(*expected, error.reject_code())
+        }
+        (ExpectedOutcome::Reject(_), Ok(WasmResult::Reply(payload))) => Err(format!(
+            "Expected a reject, got reply 0x{}.",
+            encode(payload)
+        )),
+    }
+}
+
+fn check_reject_code(expected: Option<RejectCode>, actual: RejectCode) -> Result<(), String> {
+    match expected {
+        Some(expected) if expected != actual => Err(format!(
+            "Expected reject code {}, got reject code {}.",
+            expected as u64, actual as u64
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn build_batch(
+    message_routing: &dyn MessageRouting,
+    msgs: Vec<SignedIngress>,
+    time: Time,
+) -> Batch {
     Batch {
         batch_number: message_routing.expected_batch_height(),
         requires_full_state_hash: !msgs.is_empty(),
@@ -320,7 +722,7 @@ fn build_batch(message_routing: &dyn MessageRouting, msgs: Vec<SignedIngress>) -
         randomness: Randomness::from([0; 32]),
         ecdsa_subnet_public_keys: BTreeMap::new(),
         registry_version: RegistryVersion::from(1),
-        time: UNIX_EPOCH,
+        time,
         consensus_responses: vec![],
     }
 }
@@ -333,8 +735,9 @@ fn execute_ingress_message(
     msg: SignedIngress,
     msg_id: &MessageId,
     ingress_history: &dyn IngressHistoryReader,
+    time: Time,
 ) -> Result<WasmResult, UserError> {
-    let mut batch = build_batch(message_routing, vec![msg]);
+    let mut batch = build_batch(message_routing, vec![msg], time);
     for _ in 0..MAX_BATCHES_UNTIL_RESPONSE {
         // In the first batch we try to send the ingress message itself. If it fails, we
         // repeat with the same batch.
@@ -345,7 +748,7 @@ fn execute_ingress_message(
         // potential inter-canister messages that the ingress message may have
         // triggered.
         if message_routing.deliver_batch(batch.clone()).is_ok() {
-            batch = build_batch(message_routing, vec![])
+            batch = build_batch(message_routing, vec![], time)
         }
         sleep(WAIT_PER_BATCH);
 
@@ -391,10 +794,10 @@ fn execute_ingress_message(
 ///
 /// This is a temporary measure until DFN-1269 is resolved. In that ticket, we
 /// will actually try to wait until all messages have been executed.
-fn wait_extra_batches(message_routing: &dyn MessageRouting, extra_batches: u64) {
+fn wait_extra_batches(message_routing: &dyn MessageRouting, extra_batches: u64, time: Time) {
     for _ in 0..extra_batches {
         loop {
-            let batch = build_batch(message_routing, vec![]);
+            let batch = build_batch(message_routing, vec![], time);
             let ok = message_routing.deliver_batch(batch).is_ok();
             sleep(WAIT_PER_BATCH);
             if ok {
@@ -403,3 +806,51 @@ fn wait_extra_batches(message_routing: &dyn MessageRouting, extra_batches: u64)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_state_can_be_loaded() {
+        // Setting up the execution services requires a tokio runtime context.
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        Config::run_with_temp_config(|cfg| {
+            let log = slog::Logger::root(slog::Discard, slog::o!());
+            let services = Services::new(
+                &cfg,
+                cfg.state_manager.state_root(),
+                MetricsRegistry::new(),
+                &log,
+            );
+            let time = services.batch_time();
+            let msg = match message::parse_message("create", 0).unwrap() {
+                Message::Create(msg) => msg,
+                _ => panic!("expected a create message"),
+            };
+            deliver_message(
+                msg,
+                &services.message_routing,
+                services.ingress_hist_reader.as_ref(),
+                0,
+                time,
+            )
+            .unwrap();
+
+            let saved = tempfile::tempdir().unwrap();
+            services.checkpoint(saved.path(), time).unwrap();
+
+            let restored =
+                Services::restore(&cfg, saved.path(), MetricsRegistry::new(), &log).unwrap();
+            // The restored services use their own state root.
+            assert_ne!(
+                restored.state_manager.state_layout().raw_path(),
+                services.state_manager.state_layout().raw_path()
+            );
+            let state = restored.state_manager.get_latest_state().take();
+            assert_eq!(state.canister_states.len(), 1);
+            assert!(restored.batch_time() >= time);
+        })
+    }
+}
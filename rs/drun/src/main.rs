@@ -4,7 +4,7 @@ use ic_canister_sandbox_backend_lib::{
 };
 use ic_canister_sandbox_launcher::sandbox_launcher_main;
 use ic_config::{Config, ConfigSource};
-use ic_drun::{run_drun, DrunOptions};
+use ic_drun::{run_drun, DrunOptions, OutputFormat};
 use std::path::PathBuf;
 
 const DEFAULT_CONFIG_FILE: &str = "ic.toml";
@@ -13,6 +13,8 @@ const ARG_CONF: &str = "config";
 const ARG_LOG_FILE: &str = "log-file";
 const ARG_MESSAGES: &str = "messages";
 const ARG_EXTRA_BATCHES: &str = "extra-batches";
+const ARG_OUTPUT: &str = "output";
+const ARG_LOAD_STATE: &str = "load-state";
 
 fn main() -> Result<(), String> {
     // Check if `drun` is running in the canister sandbox mode where it waits
@@ -55,11 +57,23 @@ async fn drun_main() -> Result<(), String> {
             })
             .unwrap_or(DEFAULT_EXTRA_BATCHES);
 
+        let output_format = matches
+            .value_of(ARG_OUTPUT)
+            .map(|arg| {
+                arg.parse().unwrap_or_else(|err| {
+                    eprintln!("Failed to parse ARG_OUTPUT\n  {}", err);
+                    std::process::exit(1);
+                })
+            })
+            .unwrap_or(OutputFormat::Text);
+
         let uo = DrunOptions {
             msg_filename: matches.value_of(ARG_MESSAGES).unwrap().to_string(),
             cfg,
             extra_batches,
             log_file,
+            output_format,
+            load_state: matches.value_of(ARG_LOAD_STATE).map(PathBuf::from),
         };
         run_drun(uo)
     })
@@ -101,6 +115,21 @@ fn get_arg_matches() -> ArgMatches {
                 .value_name("Query/Ingress Messages")
                 .help("Text file containing one message per line."),
         )
+        .arg(
+            Arg::new(ARG_OUTPUT)
+                .long(ARG_OUTPUT)
+                .value_name("text|json")
+                .help("Format of the result printed for each message (default: text).")
+                .possible_values(["text", "json"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new(ARG_LOAD_STATE)
+                .long(ARG_LOAD_STATE)
+                .value_name("dir")
+                .help("Directory with a checkpoint saved by a `checkpoint` message to start from (default: None).")
+                .takes_value(true),
+        )
         .arg(
             Arg::new(ARG_LOG_FILE)
                 .long(ARG_LOG_FILE)
@@ -1,6 +1,7 @@
 use super::CanisterId;
 
 use hex::decode;
+use ic_error_types::RejectCode;
 use ic_ic00_types::{self as ic00, CanisterInstallMode, Payload};
 use ic_types::{
     messages::{SignedIngress, UserQuery},
@@ -13,8 +14,10 @@ use std::{
     fmt,
     fs::File,
     io::{self, Read},
+    path::PathBuf,
     str::Chars,
     string::FromUtf8Error,
+    time::Duration,
 };
 
 #[derive(Debug, PartialEq)]
@@ -23,6 +26,32 @@ pub(crate) enum Message {
     Query(UserQuery),
     Install(SignedIngress),
     Create(SignedIngress),
+    Expect(Expectation),
+    /// Advances the time of subsequent batches by the given duration.
+    AdvanceTime(Duration),
+    /// Executes the given number of rounds without delivering any message.
+    Tick(u64),
+    /// Saves a checkpoint of the replicated state to the given directory.
+    Checkpoint(PathBuf),
+    /// Replaces the replicated state with a checkpoint saved to the given
+    /// directory.
+    Restore(PathBuf),
+}
+
+/// An assertion about the result of the preceding message.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Expectation {
+    /// The line of the message file the assertion was read from.
+    pub line: u64,
+    pub outcome: ExpectedOutcome,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum ExpectedOutcome {
+    /// The message was replied to, optionally with the given payload.
+    Reply(Option<Vec<u8>>),
+    /// The message was rejected, optionally with the given reject code.
+    Reject(Option<RejectCode>),
 }
 
 #[derive(Debug)]
@@ -144,7 +173,7 @@ pub(crate) fn msg_stream_from_file(
         }))
 }
 
-fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
+pub(crate) fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
     let s = s.trim_end();
     let tokens: Vec<&str> = s.splitn(4, char::is_whitespace).collect();
 
@@ -185,6 +214,50 @@ fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
         ["upgrade", canister_id, wasm_file, payload] => {
             parse_install(nonce, canister_id, payload, wasm_file, "upgrade")
         }
+        ["update-settings", _, _, ..] => {
+            // Settings are whitespace-separated, so take all the remaining words.
+            let words: Vec<&str> = s.split_whitespace().collect();
+            match &words[..] {
+                [_, canister_id, settings @ ..] if !settings.is_empty() => {
+                    parse_update_settings(nonce, canister_id, settings)
+                }
+                _ => Err("Too few arguments.".to_string()),
+            }
+        }
+        ["top-up", canister_id, cycles] => parse_top_up(nonce, canister_id, cycles),
+        ["stop", canister_id] => {
+            parse_canister_record(nonce, canister_id, ic00::Method::StopCanister)
+        }
+        ["start", canister_id] => {
+            parse_canister_record(nonce, canister_id, ic00::Method::StartCanister)
+        }
+        ["delete", canister_id] => {
+            parse_canister_record(nonce, canister_id, ic00::Method::DeleteCanister)
+        }
+        ["advance-time", duration] => Ok(Message::AdvanceTime(parse_duration(duration)?)),
+        ["tick"] => Ok(Message::Tick(1)),
+        ["tick", rounds] => rounds
+            .parse()
+            .map(Message::Tick)
+            .map_err(|e| format!("Failed to parse number of rounds {}: {}", rounds, e)),
+        ["checkpoint", dir] => Ok(Message::Checkpoint(PathBuf::from(dir))),
+        ["restore", dir] => Ok(Message::Restore(PathBuf::from(dir))),
+        ["expect-reply"] => Ok(expect(nonce, ExpectedOutcome::Reply(None))),
+        ["expect-reply", ..] => {
+            // The payload may contain whitespace, so take the rest of the line.
+            let payload = s
+                .splitn(2, char::is_whitespace)
+                .nth(1)
+                .unwrap()
+                .trim_start();
+            let payload = parse_octet_string(payload)?;
+            Ok(expect(nonce, ExpectedOutcome::Reply(Some(payload))))
+        }
+        ["expect-reject"] => Ok(expect(nonce, ExpectedOutcome::Reject(None))),
+        ["expect-reject", code] => {
+            let code = parse_reject_code(code)?;
+            Ok(expect(nonce, ExpectedOutcome::Reject(Some(code))))
+        }
         _ => Err(format!(
             "Failed to parse line {}, don't have a pattern to match this with",
             s
@@ -192,6 +265,22 @@ fn parse_message(s: &str, nonce: u64) -> Result<Message, String> {
     }
 }
 
+// The nonce of a message is the (zero-based) index of its line in the message file.
+fn expect(nonce: u64, outcome: ExpectedOutcome) -> Message {
+    Message::Expect(Expectation {
+        line: nonce + 1,
+        outcome,
+    })
+}
+
+fn parse_reject_code(code: &str) -> Result<RejectCode, String> {
+    code.parse::<u64>()
+        .map_err(|e| format!("Failed to parse reject code {}: {}", code, e))
+        .and_then(|code| {
+            RejectCode::try_from(code).map_err(|_| format!("Unknown reject code {}.", code))
+        })
+}
+
 fn parse_canister_id(canister_id: &str) -> Result<CanisterId, String> {
     use std::str::FromStr;
     match PrincipalId::from_str(canister_id) {
@@ -263,6 +352,132 @@ fn parse_install(
     Ok(Message::Install(signed_ingress))
 }
 
+/// Builds an ingress message to the management canister.
+fn management_ingress(nonce: u64, method: ic00::Method, payload: Vec<u8>) -> SignedIngress {
+    use ic_test_utilities::types::messages::SignedIngressBuilder;
+
+    SignedIngressBuilder::new()
+        .canister_id(ic00::IC_00)
+        .method_name(method)
+        .method_payload(payload)
+        .nonce(nonce)
+        .build()
+}
+
+fn parse_canister_record(
+    nonce: u64,
+    canister_id: &str,
+    method: ic00::Method,
+) -> Result<Message, String> {
+    let canister_id = parse_canister_id(canister_id)?;
+    let payload = ic00::CanisterIdRecord::from(canister_id).encode();
+    Ok(Message::Ingress(management_ingress(nonce, method, payload)))
+}
+
+fn parse_top_up(nonce: u64, canister_id: &str, cycles: &str) -> Result<Message, String> {
+    let canister_id = parse_canister_id(canister_id)?;
+    let cycles = cycles
+        .parse::<u128>()
+        .map_err(|e| format!("Failed to parse cycles {}: {}", cycles, e))?;
+    let payload = ic00::ProvisionalTopUpCanisterArgs::new(canister_id, cycles).encode();
+    Ok(Message::Ingress(management_ingress(
+        nonce,
+        ic00::Method::ProvisionalTopUpCanister,
+        payload,
+    )))
+}
+
+/// Parses settings of the form `<name>=<value>`.
+/// Controllers are given as a comma-separated list of principals.
+fn parse_update_settings(
+    nonce: u64,
+    canister_id: &str,
+    settings: &[&str],
+) -> Result<Message, String> {
+    use std::str::FromStr;
+
+    fn parse_u64(name: &str, value: &str) -> Result<Option<u64>, String> {
+        value
+            .parse()
+            .map(Some)
+            .map_err(|e| format!("Failed to parse {} {}: {}", name, value, e))
+    }
+
+    let canister_id = parse_canister_id(canister_id)?;
+    let mut controllers = None;
+    let mut compute_allocation = None;
+    let mut memory_allocation = None;
+    let mut freezing_threshold = None;
+
+    for setting in settings {
+        let (name, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("Setting {} must have the form <name>=<value>.", setting))?;
+        match name {
+            "controllers" => {
+                controllers = Some(
+                    value
+                        .split(',')
+                        .filter(|p| !p.is_empty())
+                        .map(|p| {
+                            PrincipalId::from_str(p).map_err(|e| {
+                                format!("Failed to convert {} to principal id with {}", p, e)
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?,
+                )
+            }
+            "compute_allocation" => compute_allocation = parse_u64(name, value)?,
+            "memory_allocation" => memory_allocation = parse_u64(name, value)?,
+            "freezing_threshold" => freezing_threshold = parse_u64(name, value)?,
+            _ => return Err(format!("Unknown canister setting {}.", name)),
+        }
+    }
+
+    let payload = ic00::UpdateSettingsArgs {
+        canister_id: canister_id.get(),
+        settings: ic00::CanisterSettingsArgs::new(
+            None,
+            controllers,
+            compute_allocation,
+            memory_allocation,
+            freezing_threshold,
+        ),
+    }
+    .encode();
+    Ok(Message::Ingress(management_ingress(
+        nonce,
+        ic00::Method::UpdateSettings,
+        payload,
+    )))
+}
+
+/// Parses a duration of the form `<integer><unit>`, where the unit is one of
+/// `ns`, `us`, `ms`, `s`, `m`, `h` or `d`.
+fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("Duration {} is missing a unit.", duration))?;
+    let (value, unit) = duration.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|e| format!("Failed to parse duration {}: {}", duration, e))?;
+    let nanos_per_unit: u64 = match unit {
+        "ns" => 1,
+        "us" => 1_000,
+        "ms" => 1_000_000,
+        "s" => 1_000_000_000,
+        "m" => 60 * 1_000_000_000,
+        "h" => 60 * 60 * 1_000_000_000,
+        "d" => 24 * 60 * 60 * 1_000_000_000,
+        _ => return Err(format!("Unknown unit of duration {}.", duration)),
+    };
+    value
+        .checked_mul(nanos_per_unit)
+        .map(Duration::from_nanos)
+        .ok_or_else(|| format!("Duration {} is too large.", duration))
+}
+
 fn validate_method_name(method_name: &str) -> Result<String, String> {
     fn is_ident_start(c: char) -> bool {
         c.is_ascii() && (c.is_alphabetic() || c == '_')
@@ -470,6 +685,160 @@ mod tests {
         assert!(parse_message(s, 0).is_err());
     }
 
+    #[test]
+    fn test_parse_expectations() {
+        assert_eq!(
+            parse_message("expect-reply", 3).unwrap(),
+            Message::Expect(Expectation {
+                line: 4,
+                outcome: ExpectedOutcome::Reply(None)
+            })
+        );
+        assert_eq!(
+            parse_message("expect-reply \"hello world\"", 0).unwrap(),
+            Message::Expect(Expectation {
+                line: 1,
+                outcome: ExpectedOutcome::Reply(Some(b"hello world".to_vec()))
+            })
+        );
+        assert_eq!(
+            parse_message("expect-reply 0x0102", 0).unwrap(),
+            Message::Expect(Expectation {
+                line: 1,
+                outcome: ExpectedOutcome::Reply(Some(vec![1, 2]))
+            })
+        );
+        assert_eq!(
+            parse_message("expect-reject", 0).unwrap(),
+            Message::Expect(Expectation {
+                line: 1,
+                outcome: ExpectedOutcome::Reject(None)
+            })
+        );
+        assert_eq!(
+            parse_message("expect-reject 4", 0).unwrap(),
+            Message::Expect(Expectation {
+                line: 1,
+                outcome: ExpectedOutcome::Reject(Some(RejectCode::CanisterReject))
+            })
+        );
+        assert!(parse_message("expect-reject 42", 0).is_err());
+        assert!(parse_message("expect-reject four", 0).is_err());
+        assert!(parse_message("expect-reply zz", 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_time_and_rounds() {
+        assert_eq!(
+            parse_message("advance-time 10s", 0).unwrap(),
+            Message::AdvanceTime(Duration::from_secs(10))
+        );
+        assert_eq!(
+            parse_message("advance-time 250ms", 0).unwrap(),
+            Message::AdvanceTime(Duration::from_millis(250))
+        );
+        assert_eq!(
+            parse_message("advance-time 2d", 0).unwrap(),
+            Message::AdvanceTime(Duration::from_secs(2 * 24 * 60 * 60))
+        );
+        assert!(parse_message("advance-time 10", 0).is_err());
+        assert!(parse_message("advance-time 10y", 0).is_err());
+        assert!(parse_message("advance-time s", 0).is_err());
+
+        assert_eq!(parse_message("tick", 0).unwrap(), Message::Tick(1));
+        assert_eq!(parse_message("tick 5", 0).unwrap(), Message::Tick(5));
+        assert!(parse_message("tick -1", 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_checkpoint_and_restore() {
+        assert_eq!(
+            parse_message("checkpoint setup", 0).unwrap(),
+            Message::Checkpoint(PathBuf::from("setup"))
+        );
+        assert_eq!(
+            parse_message("restore /tmp/setup", 0).unwrap(),
+            Message::Restore(PathBuf::from("/tmp/setup"))
+        );
+        assert!(parse_message("checkpoint", 0).is_err());
+        assert!(parse_message("restore", 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_management_messages() {
+        fn payload(message: Message) -> (String, Vec<u8>) {
+            match message {
+                Message::Ingress(signed_ingress) => {
+                    let content = signed_ingress.content();
+                    assert_eq!(content.canister_id(), ic00::IC_00);
+                    (content.method_name().to_string(), content.arg().to_vec())
+                }
+                m => panic!("unexpected message type: {:?}", m),
+            }
+        }
+
+        let canister_id = canister_test_id(APP_CANISTER_ID);
+        for (directive, method) in [
+            ("stop", ic00::Method::StopCanister),
+            ("start", ic00::Method::StartCanister),
+            ("delete", ic00::Method::DeleteCanister),
+        ] {
+            let s = format!("{} {}", directive, APP_CANISTER_URL);
+            assert_eq!(
+                payload(parse_message(&s, 0).unwrap()),
+                (
+                    method.to_string(),
+                    ic00::CanisterIdRecord::from(canister_id).encode()
+                )
+            );
+        }
+
+        let s = format!("top-up {} 1000000", APP_CANISTER_URL);
+        let (method, arg) = payload(parse_message(&s, 0).unwrap());
+        assert_eq!(method, ic00::Method::ProvisionalTopUpCanister.to_string());
+        let args = ic00::ProvisionalTopUpCanisterArgs::decode(&arg).unwrap();
+        assert_eq!(args.get_canister_id(), canister_id);
+        assert_eq!(args.to_u128(), Some(1_000_000));
+
+        let s = format!(
+            "update-settings {} controllers={},{} freezing_threshold=100 compute_allocation=10",
+            APP_CANISTER_URL,
+            PrincipalId::new_anonymous(),
+            APP_CANISTER_URL
+        );
+        let (method, arg) = payload(parse_message(&s, 0).unwrap());
+        assert_eq!(method, ic00::Method::UpdateSettings.to_string());
+        let args = ic00::UpdateSettingsArgs::decode(&arg).unwrap();
+        assert_eq!(args.get_canister_id(), canister_id);
+        assert_eq!(
+            args.settings.controllers,
+            Some(vec![PrincipalId::new_anonymous(), canister_id.get()])
+        );
+        assert_eq!(args.settings.freezing_threshold, Some(100u64.into()));
+        assert_eq!(args.settings.compute_allocation, Some(10u64.into()));
+        assert_eq!(args.settings.memory_allocation, None);
+
+        // Fields may be separated by several spaces.
+        let s = format!(
+            "update-settings  {}   freezing_threshold=100  memory_allocation=1000",
+            APP_CANISTER_URL
+        );
+        let (_, arg) = payload(parse_message(&s, 0).unwrap());
+        let args = ic00::UpdateSettingsArgs::decode(&arg).unwrap();
+        assert_eq!(args.get_canister_id(), canister_id);
+        assert_eq!(args.settings.freezing_threshold, Some(100u64.into()));
+        assert_eq!(args.settings.memory_allocation, Some(1000u64.into()));
+
+        let s = format!("update-settings  {}", APP_CANISTER_URL);
+        assert!(parse_message(&s, 0).is_err());
+        let s = format!("update-settings {} freezing_threshold", APP_CANISTER_URL);
+        assert!(parse_message(&s, 0).is_err());
+        let s = format!("update-settings {} cycles=100", APP_CANISTER_URL);
+        assert!(parse_message(&s, 0).is_err());
+        let s = format!("top-up {} lots", APP_CANISTER_URL);
+        assert!(parse_message(&s, 0).is_err());
+    }
+
     #[test]
     fn test_line_iterator() {
         let text = Cursor::new(
@@ -214,6 +214,7 @@ impl WasmExecutor for WasmExecutorImpl {
                                 accessed_pages: 0,
                                 dirty_pages: 0,
                             },
+                            canister_log: Default::default(),
                         },
                         None,
                     ),
@@ -601,6 +602,7 @@ pub fn process(
                         accessed_pages: 0,
                         dirty_pages: 0,
                     },
+                    canister_log: Default::default(),
                 },
                 None,
                 Err(system_api),
@@ -642,6 +644,10 @@ pub fn process(
         .store_data_mut()
         .system_api
         .take_execution_result(run_result.as_ref().err());
+    let canister_log = instance
+        .store_data_mut()
+        .system_api
+        .take_canister_log(wasm_result.as_ref().err());
 
     let wasm_heap_size_after = instance.heap_size();
     let wasm_heap_limit =
@@ -700,6 +706,7 @@ pub fn process(
             allocated_bytes,
             allocated_message_bytes,
             instance_stats,
+            canister_log,
         },
         wasm_state_changes,
         Ok(instance),
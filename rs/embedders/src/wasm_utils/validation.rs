@@ -410,6 +410,16 @@ fn get_valid_system_apis() -> HashMap<String, HashMap<String, FunctionSignature>
                 },
             )],
         ),
+        (
+            "global_timer_set",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValueType::I64],
+                    return_type: vec![ValueType::I64],
+                },
+            )],
+        ),
         (
             "performance_counter",
             vec![(
@@ -520,6 +530,26 @@ fn get_valid_system_apis() -> HashMap<String, HashMap<String, FunctionSignature>
                 },
             )],
         ),
+        (
+            "canister_version",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![],
+                    return_type: vec![ValueType::I64],
+                },
+            )],
+        ),
+        (
+            "is_controller",
+            vec![(
+                API_VERSION_IC0,
+                FunctionSignature {
+                    param_types: vec![ValueType::I32, ValueType::I32],
+                    return_type: vec![ValueType::I32],
+                },
+            )],
+        ),
         (
             "mint_cycles",
             vec![(
@@ -621,6 +651,13 @@ fn get_valid_exported_functions() -> HashMap<String, FunctionSignature> {
                 return_type: vec![],
             },
         ),
+        (
+            "canister_composite_query",
+            FunctionSignature {
+                param_types: vec![],
+                return_type: vec![],
+            },
+        ),
         (
             "canister_pre_upgrade",
             FunctionSignature {
@@ -649,6 +686,13 @@ fn get_valid_exported_functions() -> HashMap<String, FunctionSignature> {
                 return_type: vec![],
             },
         ),
+        (
+            "canister_global_timer",
+            FunctionSignature {
+                param_types: vec![],
+                return_type: vec![],
+            },
+        ),
     ];
 
     valid_exported_functions
@@ -770,8 +814,8 @@ fn validate_import_section(module: &Module) -> Result<WasmImportsDetails, WasmVa
 }
 
 // Performs the following checks:
-// * Validates signatures of exported canister_update and canister_query
-//   methods.
+// * Validates signatures of exported canister_update, canister_query and
+//   canister_composite_query methods.
 // * Validates the signatures of other allowed exported functions (like
 //   `canister_init` or `canister_pre_upgrade`) if present.
 // * Validates that the canister doesn't export any reserved symbols
@@ -795,15 +839,17 @@ fn validate_export_section(module: &Module) -> Result<usize, WasmValidationError
                 let mut func_name = export.field();
                 // func_name holds either:
                 // - the entire exported non-IC function names, or
-                // - canister_query or canister_update part in case of the IC functions.
+                // - canister_query, canister_composite_query or canister_update part in
+                //   case of the IC functions.
                 if func_name.starts_with("canister_query ")
+                    || func_name.starts_with("canister_composite_query ")
                     || func_name.starts_with("canister_update ")
                 {
                     let parts: Vec<&str> = func_name.splitn(2, ' ').collect();
                     let unmangled_func_name = parts[1];
                     if seen_funcs.contains(unmangled_func_name) {
                         return Err(WasmValidationError::InvalidExportSection(format!(
-                            "Duplicate function '{}' exported for both update calls and queries.",
+                            "Duplicate function '{}' exported with multiple call types.",
                             unmangled_func_name
                         )));
                     }
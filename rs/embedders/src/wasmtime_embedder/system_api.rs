@@ -6,7 +6,7 @@ use ic_interfaces::execution_environment::{
 };
 use ic_logger::{error, ReplicaLogger};
 use ic_registry_subnet_type::SubnetType;
-use ic_types::{CanisterId, Cycles, NumBytes, NumInstructions};
+use ic_types::{CanisterId, Cycles, NumBytes, NumInstructions, Time};
 
 use wasmtime::{AsContextMut, Caller, Global, Linker, Store, Trap, Val};
 
@@ -625,21 +625,21 @@ pub(crate) fn syscalls<S: SystemApi>(
                         network: (length as u64).into(),
                     },
                 )?;
-                match (
-                    caller.data().system_api.subnet_type(),
-                    rate_limiting_of_debug_prints,
-                ) {
-                    // Debug print is a no-op on non-system subnets with rate limiting.
-                    (SubnetType::Application, FlagStatus::Enabled) => Ok(()),
-                    (SubnetType::VerifiedApplication, FlagStatus::Enabled) => Ok(()),
-                    // If rate limiting is disabled or the subnet is a system subnet, then
-                    // debug print produces output.
-                    (_, FlagStatus::Disabled) | (SubnetType::System, FlagStatus::Enabled) => {
-                        with_memory_and_system_api(caller, |system_api, memory| {
+                with_memory_and_system_api(caller, |system_api, memory| {
+                    // The message always goes to the canister log, regardless
+                    // of the rate limiting of the replica log output.
+                    system_api.save_log_message(offset as u32, length as u32, memory);
+                    match (system_api.subnet_type(), rate_limiting_of_debug_prints) {
+                        // Debug print is a no-op on non-system subnets with rate limiting.
+                        (SubnetType::Application, FlagStatus::Enabled) => Ok(()),
+                        (SubnetType::VerifiedApplication, FlagStatus::Enabled) => Ok(()),
+                        // If rate limiting is disabled or the subnet is a system subnet, then
+                        // debug print produces output.
+                        (_, FlagStatus::Disabled) | (SubnetType::System, FlagStatus::Enabled) => {
                             system_api.ic0_debug_print(offset as u32, length as u32, memory)
-                        })
+                        }
                     }
-                }
+                })
             }
         })
         .unwrap();
@@ -1013,6 +1013,18 @@ pub(crate) fn syscalls<S: SystemApi>(
         })
         .unwrap();
 
+    linker
+        .func_wrap("ic0", "global_timer_set", {
+            move |mut caller: Caller<'_, StoreData<S>>, time: i64| {
+                with_system_api(&mut caller, |s| {
+                    s.ic0_global_timer_set(Time::from_nanos_since_unix_epoch(time as u64))
+                })
+                .map_err(|e| process_err(caller, e))
+                .map(|s| s.as_nanos_since_unix_epoch())
+            }
+        })
+        .unwrap();
+
     linker
         .func_wrap("ic0", "performance_counter", {
             let log = log.clone();
@@ -1217,6 +1229,57 @@ pub(crate) fn syscalls<S: SystemApi>(
         })
         .unwrap();
 
+    linker
+        .func_wrap("ic0", "canister_version", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>| {
+                observe_execution_complexity(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    &ExecutionComplexity {
+                        cpu: system_api_complexity::cpu::CANISTER_VERSION,
+                        memory: 0.into(),
+                        disk: 0.into(),
+                        network: 0.into(),
+                    },
+                )?;
+                with_system_api(&mut caller, |s| s.ic0_canister_version())
+                    .map_err(|e| process_err(caller, e))
+                    .and_then(|s| {
+                        i64::try_from(s).map_err(|e| {
+                            wasmtime::Trap::new(format!("ic0_canister_version failed: {}", e))
+                        })
+                    })
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "is_controller", {
+            let log = log.clone();
+            move |mut caller: Caller<'_, StoreData<S>>, src: u32, size: u32| {
+                charge_for_system_api_call(
+                    &log,
+                    canister_id,
+                    &mut caller,
+                    system_api_complexity::overhead::IS_CONTROLLER,
+                    size,
+                    &ExecutionComplexity {
+                        cpu: system_api_complexity::cpu::IS_CONTROLLER,
+                        memory: (size as u64).into(),
+                        disk: 0.into(),
+                        network: 0.into(),
+                    },
+                )?;
+                with_memory_and_system_api(caller, |system_api, memory| {
+                    system_api.ic0_is_controller(src, size, memory)
+                })
+                .map(|result| result as i32)
+            }
+        })
+        .unwrap();
+
     linker
         .func_wrap("ic0", "certified_data_set", {
             let log = log.clone();
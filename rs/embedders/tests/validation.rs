@@ -88,9 +88,11 @@ fn can_validate_valid_export_section() {
                   (func $x)
                   (export "canister_init" (func $x))
                   (export "canister_heartbeat" (func $x))
+                  (export "canister_global_timer" (func $x))
                   (export "canister_pre_upgrade" (func $x))
                   (export "canister_post_upgrade" (func $x))
-                  (export "canister_query read" (func $x)))"#,
+                  (export "canister_query read" (func $x))
+                  (export "canister_composite_query query_all" (func $x)))"#,
     )
     .unwrap();
 
@@ -184,6 +186,34 @@ fn can_validate_canister_heartbeat_with_invalid_params() {
     );
 }
 
+#[test]
+fn can_validate_canister_global_timer_with_invalid_return() {
+    let wasm = wat2wasm(
+        r#"(module
+                  (func $x (result i32) (i32.const 0))
+                  (export "canister_global_timer" (func $x)))"#,
+    )
+    .unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Err(WasmValidationError::InvalidFunctionSignature(_))
+    );
+}
+
+#[test]
+fn can_validate_canister_global_timer_with_invalid_params() {
+    let wasm = wat2wasm(
+        r#"(module
+                  (func $x (param $y i32))
+                  (export "canister_global_timer" (func $x)))"#,
+    )
+    .unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Err(WasmValidationError::InvalidFunctionSignature(_))
+    );
+}
+
 #[test]
 fn can_validate_canister_pre_upgrade_with_invalid_return() {
     let wasm = wat2wasm(
@@ -269,6 +299,35 @@ fn can_validate_duplicate_method_for_canister_query_and_canister_update() {
     );
 }
 
+#[test]
+fn can_validate_invalid_canister_composite_query() {
+    let wasm = wat2wasm(
+        r#"(module
+                    (func $read (param i64 i32) (result i32) (local.get 1))
+                    (export "canister_composite_query read" (func $read)))"#,
+    )
+    .unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Err(WasmValidationError::InvalidFunctionSignature(_))
+    );
+}
+
+#[test]
+fn can_validate_duplicate_method_for_canister_query_and_canister_composite_query() {
+    let wasm = wat2wasm(
+        r#"(module
+                    (func $read)
+                    (export "canister_query read" (func $read))
+                    (export "canister_composite_query read" (func $read)))"#,
+    )
+    .unwrap();
+    assert_matches!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Err(WasmValidationError::InvalidExportSection(_))
+    );
+}
+
 #[test]
 fn can_validate_canister_query_update_method_name_with_whitespace() {
     let wasm = wat2wasm(
@@ -754,6 +813,48 @@ fn can_validate_performance_counter_import() {
     );
 }
 
+#[test]
+fn can_validate_global_timer_set_import() {
+    let wasm = wat2wasm(
+        r#"(module
+        (import "ic0" "global_timer_set" (func $ic0_global_timer_set (param i64) (result i64)))
+    )"#,
+    )
+    .unwrap();
+    assert_eq!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Ok(WasmValidationDetails::default())
+    );
+}
+
+#[test]
+fn can_validate_canister_version_import() {
+    let wasm = wat2wasm(
+        r#"(module
+        (import "ic0" "canister_version" (func $ic0_canister_version (result i64)))
+    )"#,
+    )
+    .unwrap();
+    assert_eq!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Ok(WasmValidationDetails::default())
+    );
+}
+
+#[test]
+fn can_validate_is_controller_import() {
+    let wasm = wat2wasm(
+        r#"(module
+        (import "ic0" "is_controller" (func $ic0_is_controller (param i32 i32) (result i32)))
+    )"#,
+    )
+    .unwrap();
+    assert_eq!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Ok(WasmValidationDetails::default())
+    );
+}
+
 /// The spec doesn't allow exported functions to have results.
 #[test]
 fn function_with_result_is_invalid() {
@@ -3,9 +3,13 @@ use bitcoin::{
 };
 use candid::Encode;
 use ic_btc_test_utils::{random_p2pkh_address, BlockBuilder, TransactionBuilder};
-use ic_btc_types::{GetUtxosResponse, OutPoint, Satoshi, Utxo, UtxosFilter};
+use ic_btc_types::{
+    AddressTransaction, BlockchainInfo, GetBlockHeadersResponse, GetTransactionsResponse,
+    GetUtxosResponse, OutPoint, Satoshi, Utxo, UtxosFilter,
+};
 use ic_ic00_types::{
-    BitcoinGetBalanceArgs, BitcoinGetCurrentFeePercentilesArgs, BitcoinGetUtxosArgs,
+    BitcoinGetBalanceArgs, BitcoinGetBlockHeadersArgs, BitcoinGetBlockchainInfoArgs,
+    BitcoinGetCurrentFeePercentilesArgs, BitcoinGetTransactionsArgs, BitcoinGetUtxosArgs,
     BitcoinNetwork, BitcoinSendTransactionArgs, EmptyBlob, Method, Payload as Ic00Payload,
 };
 use ic_interfaces::execution_environment::AvailableMemory;
@@ -729,6 +733,185 @@ fn get_current_fee_percentiles_cache() {
     test.state_mut().put_bitcoin_state(bitcoin_state);
 }
 
+fn fake_get_block_headers_args() -> BitcoinGetBlockHeadersArgs {
+    BitcoinGetBlockHeadersArgs {
+        start_height: 0,
+        end_height: None,
+        network: BitcoinNetwork::Testnet,
+    }
+}
+
+#[test]
+fn get_block_headers_rejects_feature_not_enabled() {
+    reject_feature_not_enabled(
+        Method::BitcoinGetBlockHeaders,
+        fake_get_block_headers_args().encode(),
+    );
+}
+
+#[test]
+fn get_block_headers_not_enough_cycles() {
+    reject_and_check_refund(
+        fake_state(),
+        Method::BitcoinGetBlockHeaders,
+        fake_get_block_headers_args().encode(),
+        Cycles::new(100_000_000 - 1), // Not enough cycles given.
+        Cycles::new(100_000_000 - 1), // Refund all.
+        "Received 99999999 cycles. 100000000 cycles are required.",
+    );
+}
+
+#[test]
+fn get_block_headers_rejects_start_height_above_tip() {
+    reject_and_check_refund(
+        fake_state(),
+        Method::BitcoinGetBlockHeaders,
+        BitcoinGetBlockHeadersArgs {
+            start_height: 2,
+            ..fake_get_block_headers_args()
+        }
+        .encode(),
+        Cycles::new(100_000_000),
+        Cycles::zero(),
+        "bitcoin_get_block_headers failed: The requested start_height is larger than the height of the chain. Given: 2, height of chain: 1",
+    );
+}
+
+#[test]
+fn get_block_headers_succeeds() {
+    let block_0 = BlockBuilder::genesis().build();
+    let block_1 = BlockBuilder::with_prev_header(block_0.header).build();
+    let mut state = ic_btc_canister::state::State::new(2, Network::Testnet, block_0.clone());
+    ic_btc_canister::store::insert_block(&mut state, block_1.clone()).unwrap();
+
+    execute_check_payload_and_refund(
+        BitcoinState::from(state),
+        Method::BitcoinGetBlockHeaders,
+        fake_get_block_headers_args().encode(),
+        Cycles::new(100_000_000),
+        Cycles::zero(),
+        Payload::Data(
+            Encode!(&GetBlockHeadersResponse {
+                tip_height: 1,
+                block_headers: vec![
+                    bitcoin::consensus::serialize(&block_0.header),
+                    bitcoin::consensus::serialize(&block_1.header),
+                ],
+            })
+            .unwrap(),
+        ),
+    );
+}
+
+#[test]
+fn get_blockchain_info_rejects_feature_not_enabled() {
+    reject_feature_not_enabled(
+        Method::BitcoinGetBlockchainInfo,
+        BitcoinGetBlockchainInfoArgs {
+            network: BitcoinNetwork::Testnet,
+        }
+        .encode(),
+    );
+}
+
+#[test]
+fn get_blockchain_info_succeeds() {
+    let block_0 = BlockBuilder::genesis().build();
+
+    execute_check_payload_and_refund(
+        BitcoinState::from(ic_btc_canister::state::State::new(
+            2,
+            Network::Testnet,
+            block_0.clone(),
+        )),
+        Method::BitcoinGetBlockchainInfo,
+        BitcoinGetBlockchainInfoArgs {
+            network: BitcoinNetwork::Testnet,
+        }
+        .encode(),
+        Cycles::new(100_000_000),
+        Cycles::zero(),
+        Payload::Data(
+            Encode!(&BlockchainInfo {
+                tip_height: 0,
+                tip_block_hash: block_0.block_hash().to_vec(),
+                difficulty: block_0.header.difficulty(Network::Testnet),
+                stable_height: 0,
+            })
+            .unwrap(),
+        ),
+    );
+}
+
+fn fake_get_transactions_args() -> BitcoinGetTransactionsArgs {
+    BitcoinGetTransactionsArgs {
+        address: random_p2pkh_address(Network::Testnet).to_string(),
+        network: BitcoinNetwork::Testnet,
+        page: None,
+    }
+}
+
+#[test]
+fn get_transactions_rejects_feature_not_enabled() {
+    reject_feature_not_enabled(
+        Method::BitcoinGetTransactions,
+        fake_get_transactions_args().encode(),
+    );
+}
+
+#[test]
+fn get_transactions_not_enough_cycles() {
+    reject_and_check_refund(
+        fake_state(),
+        Method::BitcoinGetTransactions,
+        fake_get_transactions_args().encode(),
+        Cycles::new(100_000_000 - 1), // Not enough cycles given.
+        Cycles::new(100_000_000 - 1), // Refund all.
+        "Received 99999999 cycles. 100000000 cycles are required.",
+    );
+}
+
+#[test]
+fn get_transactions_succeeds() {
+    let address = random_p2pkh_address(Network::Testnet);
+    let coinbase_tx = TransactionBuilder::coinbase()
+        .with_output(&address, 1000)
+        .build();
+    let block_0 = BlockBuilder::genesis()
+        .with_transaction(coinbase_tx.clone())
+        .build();
+    let block_1 = BlockBuilder::with_prev_header(block_0.header).build();
+
+    // The genesis block becomes stable once the next block is inserted.
+    let mut state = ic_btc_canister::state::State::new(1, Network::Testnet, block_0);
+    ic_btc_canister::store::insert_block(&mut state, block_1).unwrap();
+
+    execute_check_payload_and_refund(
+        BitcoinState::from(state),
+        Method::BitcoinGetTransactions,
+        BitcoinGetTransactionsArgs {
+            address: address.to_string(),
+            ..fake_get_transactions_args()
+        }
+        .encode(),
+        Cycles::new(100_000_000),
+        Cycles::zero(),
+        Payload::Data(
+            Encode!(&GetTransactionsResponse {
+                transactions: vec![AddressTransaction {
+                    txid: coinbase_tx.txid().to_vec(),
+                    height: 0,
+                    net_amount: 1000,
+                }],
+                stable_height: 1,
+                first_indexed_height: 0,
+                next_page: None,
+            })
+            .unwrap(),
+        ),
+    );
+}
+
 #[test]
 fn send_transaction_rejects_if_feature_not_enabled() {
     reject_feature_not_enabled(
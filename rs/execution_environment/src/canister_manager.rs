@@ -12,8 +12,10 @@ use ic_config::flag_status::FlagStatus;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    CanisterInstallMode, CanisterStatusResultV2, CanisterStatusType, InstallCodeArgs,
-    Method as Ic00Method,
+    CanisterChangeDetails, CanisterChangeOrigin, CanisterInfoResponse, CanisterInstallMode,
+    CanisterSnapshotResponse, CanisterStatusResultV2, CanisterStatusType, ChunkHash,
+    InstallChunkedCodeArgs, InstallCodeArgs, ListCanisterSnapshotsResponse, LogVisibility,
+    Method as Ic00Method, QueryStatsRecord, StoredChunksReply,
 };
 use ic_interfaces::execution_environment::{
     CanisterOutOfCyclesError, HypervisorError, IngressHistoryWriter,
@@ -23,14 +25,19 @@ use ic_logger::{error, fatal, info, ReplicaLogger};
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    CallOrigin, CanisterState, CanisterStatus, NetworkTopology, ReplicatedState, SchedulerState,
-    SystemState,
+    canister_snapshots::MAX_SNAPSHOTS_PER_CANISTER,
+    canister_state::system_state::{
+        WasmChunkHash, CHUNK_SIZE, DEFAULT_MAX_CHUNKS, MAX_CANISTER_HISTORY_CHANGES,
+    },
+    CallOrigin, CanisterSnapshot, CanisterState, CanisterStatus, CanisterTimer, NetworkTopology,
+    ReplicatedState, SchedulerState, SnapshotId, SystemState, WasmChunkStore,
 };
 use ic_state_layout::{CanisterLayout, CheckpointLayout, RwPolicy};
 use ic_system_api::ExecutionParameters;
 use ic_types::messages::SignedIngressContent;
 use ic_types::nominal_cycles::NominalCycles;
 use ic_types::{
+    canister_log::CanisterLog,
     ingress::{IngressState, IngressStatus},
     messages::{Payload, RejectContext, Response as CanisterResponse, StopCanisterContext},
     CanisterId, ComputeAllocation, Cycles, Height, InvalidComputeAllocationError,
@@ -248,6 +255,64 @@ impl TryFrom<(PrincipalId, InstallCodeArgs)> for InstallCodeContext {
     }
 }
 
+impl InstallCodeContext {
+    /// Builds the context of an `install_chunked_code` call by assembling the
+    /// Wasm module from the chunk store of the target canister.
+    ///
+    /// Only controllers may read the chunk store, so the sender is checked
+    /// before the module is assembled.
+    pub(crate) fn from_chunked_code(
+        sender: PrincipalId,
+        args: InstallChunkedCodeArgs,
+        canister: &CanisterState,
+    ) -> Result<Self, CanisterManagerError> {
+        let canister_id = canister.canister_id();
+        if !canister.controllers().contains(&sender) {
+            return Err(CanisterManagerError::CanisterInvalidController {
+                canister_id,
+                controllers_expected: canister.system_state.controllers.clone(),
+                controller_provided: sender,
+            });
+        }
+        let wasm_chunk_store = &canister.system_state.wasm_chunk_store;
+        let mut wasm_module = Vec::new();
+        for chunk_hash in &args.chunk_hashes_list {
+            let chunk = WasmChunkHash::try_from(chunk_hash.hash.as_slice())
+                .ok()
+                .and_then(|hash| wasm_chunk_store.get_chunk_data(&hash))
+                .ok_or_else(|| CanisterManagerError::WasmChunkStoreError {
+                    message: format!(
+                        "Chunk with hash {} is not in the chunk store of canister {}",
+                        hex::encode(&chunk_hash.hash),
+                        canister_id
+                    ),
+                })?;
+            wasm_module.extend(chunk);
+        }
+        let wasm_module = CanisterModule::new(wasm_module);
+        if wasm_module.module_hash()[..] != args.wasm_module_hash[..] {
+            return Err(CanisterManagerError::WasmChunkStoreError {
+                message: format!(
+                    "Wasm module hash {} does not match the expected hash {}",
+                    hex::encode(wasm_module.module_hash()),
+                    hex::encode(&args.wasm_module_hash)
+                ),
+            });
+        }
+
+        Ok(InstallCodeContext {
+            sender,
+            mode: args.mode,
+            canister_id,
+            wasm_module,
+            arg: args.arg,
+            compute_allocation: None,
+            memory_allocation: None,
+            query_allocation: QueryAllocation::default(),
+        })
+    }
+}
+
 /// The entity responsible for managing canisters (creation, installing, etc.)
 pub(crate) struct CanisterManager {
     hypervisor: Arc<Hypervisor>,
@@ -303,15 +368,25 @@ impl CanisterManager {
             | Ok(Ic00Method::HttpRequest)
             // Nobody pays for `raw_rand`, so this cannot be used via ingress messages
             | Ok(Ic00Method::RawRand)
+            // The canister history is meant to be checked by other canisters.
+            | Ok(Ic00Method::CanisterInfo)
             // Bitcoin messages require cycles, so we reject all ingress messages.
             | Ok(Ic00Method::BitcoinGetBalance)
             | Ok(Ic00Method::BitcoinGetUtxos)
             | Ok(Ic00Method::BitcoinSendTransaction)
-            | Ok(Ic00Method::BitcoinGetCurrentFeePercentiles) => Err(UserError::new(
+            | Ok(Ic00Method::BitcoinGetCurrentFeePercentiles)
+            | Ok(Ic00Method::BitcoinGetBlockHeaders)
+            | Ok(Ic00Method::BitcoinGetBlockchainInfo)
+            | Ok(Ic00Method::BitcoinGetTransactions) => Err(UserError::new(
                 ErrorCode::CanisterRejectedMessage,
                 format!("Only canisters can call ic00 method {}", method_name),
             )),
 
+            // Canister logs can only be fetched with a query call.
+            Ok(Ic00Method::FetchCanisterLogs) => Err(UserError::new(
+                ErrorCode::CanisterRejectedMessage,
+                format!("ic00 method {} can only be called as a query", method_name),
+            )),
 
             // These methods are only valid if they are sent by the controller
             // of the canister. We assume that the canister always wants to
@@ -323,7 +398,15 @@ impl CanisterManager {
             | Ok(Ic00Method::DeleteCanister) |
             Ok(Ic00Method::UpdateSettings)|
             Ok(Ic00Method::InstallCode) |
-            Ok(Ic00Method::SetController) => {
+            Ok(Ic00Method::SetController) |
+            Ok(Ic00Method::TakeCanisterSnapshot) |
+            Ok(Ic00Method::LoadCanisterSnapshot) |
+            Ok(Ic00Method::ListCanisterSnapshots) |
+            Ok(Ic00Method::DeleteCanisterSnapshot) |
+            Ok(Ic00Method::UploadChunk) |
+            Ok(Ic00Method::ClearChunkStore) |
+            Ok(Ic00Method::StoredChunks) |
+            Ok(Ic00Method::InstallChunkedCode) => {
                 match effective_canister_id {
                     Some(canister_id) => {
                         let canister = state.canister_state(&canister_id).ok_or_else(|| UserError::new(
@@ -444,20 +527,31 @@ impl CanisterManager {
         if let Some(freezing_threshold) = settings.freezing_threshold {
             canister.system_state.freeze_threshold = freezing_threshold;
         }
+        if let Some(log_visibility) = settings.log_visibility {
+            canister.system_state.log_visibility = log_visibility;
+        }
+        if let Some(wasm_memory_limit) = settings.wasm_memory_limit {
+            // A limit of zero removes the limit.
+            canister.system_state.wasm_memory_limit = match wasm_memory_limit.get() {
+                0 => None,
+                _ => Some(wasm_memory_limit),
+            };
+        }
     }
 
     /// Tries to apply the requested settings on the canister identified by
     /// `canister_id`.
     pub(crate) fn update_settings(
         &self,
-        sender: PrincipalId,
+        time: Time,
+        origin: CanisterChangeOrigin,
         settings: CanisterSettings,
         canister: &mut CanisterState,
         total_subnet_compute_allocation_used: u64,
         total_subnet_memory_taken: NumBytes,
     ) -> Result<(), CanisterManagerError> {
         // Verify controller.
-        self.validate_controller(canister, &sender)?;
+        self.validate_controller(canister, &origin.origin())?;
         self.validate_compute_allocation(
             total_subnet_compute_allocation_used,
             canister,
@@ -471,7 +565,18 @@ impl CanisterManager {
 
         let validated_settings =
             ValidatedCanisterSettings::try_from((settings, self.config.max_controllers))?;
+        let controllers_changed =
+            validated_settings.controller.is_some() || validated_settings.controllers.is_some();
         self.do_update_settings(validated_settings, canister);
+        canister.system_state.bump_canister_version();
+        if controllers_changed {
+            let new_controllers = canister.controllers().iter().copied().collect();
+            canister.system_state.add_canister_change(
+                time,
+                origin,
+                CanisterChangeDetails::controllers_change(new_controllers),
+            );
+        }
 
         Ok(())
     }
@@ -523,8 +628,9 @@ impl CanisterManager {
         ) {
             Err(err) => (Err(err), cycles),
             Ok(validate_settings) => {
+                // Only canisters can call `create_canister`.
                 let canister_id = match self.create_canister_helper(
-                    sender,
+                    CanisterChangeOrigin::from_canister(sender, None),
                     cycles,
                     fee,
                     validate_settings,
@@ -596,7 +702,7 @@ impl CanisterManager {
                 paused_execution,
             } => {
                 unreachable!(
-                    "Unexpected paused execution: {:?}. DTS is not enabled yet",
+                    "Unexpected paused execution: {:?}. Use `install_code_dts()` for DTS",
                     paused_execution
                 );
             }
@@ -760,12 +866,14 @@ impl CanisterManager {
             InstallCodeRoutineResult::Finished {
                 instructions_left,
                 result,
+                canister_log,
             } => finish_install_code(
                 canister,
                 message,
                 message_instruction_limit,
                 instructions_left,
                 result,
+                canister_log,
                 mode,
                 canister_layout_path,
                 &self.config,
@@ -794,9 +902,10 @@ impl CanisterManager {
     pub(crate) fn uninstall_code(
         &self,
         canister_id: CanisterId,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         state: &mut ReplicatedState,
     ) -> Result<(), CanisterManagerError> {
+        let sender = origin.origin();
         let time = state.time();
         let path = state.path().to_owned();
         let canister = match state.canister_state_mut(&canister_id) {
@@ -813,7 +922,7 @@ impl CanisterManager {
             }
         }
 
-        let rejects = uninstall_canister(&self.log, canister, &path, time);
+        let rejects = uninstall_canister(&self.log, canister, &path, time, Some(origin));
         crate::util::process_responses(
             rejects,
             state,
@@ -954,6 +1063,7 @@ impl CanisterManager {
         let compute_allocation = canister.scheduler_state.compute_allocation;
         let memory_allocation = canister.memory_allocation();
         let freeze_threshold = canister.system_state.freeze_threshold;
+        let query_stats = &canister.system_state.total_query_stats;
 
         Ok(CanisterStatusResultV2::new(
             canister.status(),
@@ -976,6 +1086,16 @@ impl CanisterManager {
                     subnet_size,
                 )
                 .get(),
+            canister
+                .system_state
+                .wasm_memory_limit
+                .map(|limit| limit.get()),
+            QueryStatsRecord::new(
+                query_stats.num_calls,
+                query_stats.num_instructions,
+                query_stats.ingress_payload_size,
+                query_stats.egress_payload_size,
+            ),
         ))
     }
 
@@ -983,20 +1103,23 @@ impl CanisterManager {
     /// the canister is able to run this, otherwise an error is returned.
     pub(crate) fn set_controller(
         &self,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         canister_id: CanisterId,
         new_controller: PrincipalId,
         state: &mut ReplicatedState,
     ) -> Result<(), CanisterManagerError> {
+        let time = state.time();
         let compute_allocation_used = state.total_compute_allocation();
         let memory_taken = state.total_memory_taken();
         let canister = state
             .canister_state_mut(&canister_id)
             .ok_or(CanisterManagerError::CanisterNotFound(canister_id))?;
 
-        let settings = CanisterSettings::new(Some(new_controller), None, None, None, None);
+        let settings =
+            CanisterSettings::new(Some(new_controller), None, None, None, None, None, None);
         self.update_settings(
-            sender,
+            time,
+            origin,
             settings,
             canister,
             compute_allocation_used,
@@ -1004,6 +1127,251 @@ impl CanisterManager {
         )
     }
 
+    /// Takes a snapshot of the Wasm module, memories, globals and certified
+    /// data of a canister. If `replace_snapshot` is given, the new snapshot
+    /// replaces the existing snapshot with that id.
+    ///
+    /// Snapshots count towards the memory allocation of the canister or, if
+    /// the canister has no memory allocation, towards the memory of the
+    /// subnet.
+    pub(crate) fn take_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        replace_snapshot: Option<&[u8]>,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<CanisterSnapshotResponse, CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        self.validate_controller(canister, &sender)?;
+
+        let replaced_snapshot = match replace_snapshot {
+            Some(snapshot_id) => Some(self.validate_snapshot(state, canister_id, snapshot_id)?),
+            None => {
+                if state.canister_snapshots.count(canister_id) >= MAX_SNAPSHOTS_PER_CANISTER {
+                    return Err(CanisterManagerError::CanisterSnapshotLimitExceeded {
+                        canister_id,
+                        limit: MAX_SNAPSHOTS_PER_CANISTER,
+                    });
+                }
+                None
+            }
+        };
+
+        let execution_state = canister.execution_state.as_ref().ok_or_else(|| {
+            CanisterManagerError::Hypervisor(canister_id, HypervisorError::WasmModuleNotFound)
+        })?;
+        let snapshot = CanisterSnapshot::from_execution_state(
+            canister_id,
+            state.time(),
+            execution_state,
+            canister.system_state.certified_data.clone(),
+        );
+        self.validate_memory_increase(
+            canister,
+            snapshot.size(),
+            replaced_snapshot
+                .as_ref()
+                .map_or(NumBytes::from(0), |(_, replaced)| replaced.size()),
+            round_limits,
+        )?;
+
+        if let Some((replaced_id, _)) = replaced_snapshot {
+            state.remove_canister_snapshot(replaced_id);
+        }
+        let snapshot_id = SnapshotId::new(state.metadata.next_snapshot_id);
+        state.metadata.next_snapshot_id += 1;
+        let response = snapshot_response(snapshot_id, &snapshot);
+        state.put_canister_snapshot(snapshot_id, snapshot);
+        Ok(response)
+    }
+
+    /// Replaces the Wasm module, memories, globals and certified data of a
+    /// canister with the ones stored in the given snapshot.
+    ///
+    /// The canister must be stopped, so that no call context or paused
+    /// execution refers to the replaced execution state.
+    pub(crate) fn load_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        snapshot_id: &[u8],
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<(), CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        self.validate_controller(canister, &sender)?;
+        self.validate_canister_is_stopped(
+            canister,
+            CanisterManagerError::LoadCanisterSnapshotNotStopped,
+        )?;
+        let (_, snapshot) = self.validate_snapshot(state, canister_id, snapshot_id)?;
+
+        // The memory of the current execution state is released and replaced
+        // with a copy of the snapshot.
+        self.validate_memory_increase(
+            canister,
+            snapshot.size(),
+            canister
+                .execution_state
+                .as_ref()
+                .map_or(NumBytes::from(0), |es| es.memory_usage()),
+            round_limits,
+        )?;
+
+        let path = state.path().to_owned();
+        let layout = canister_layout(&path, &canister_id);
+        let canister = state.canister_state_mut(&canister_id).unwrap();
+        canister.execution_state = Some(snapshot.to_execution_state(layout.raw_path()));
+        canister.system_state.certified_data = snapshot.certified_data().to_vec();
+        canister.system_state.bump_canister_version();
+
+        // All pages of the loaded memories are part of the round delta, so
+        // the files of the previous memories must not be reused.
+        truncate_canister_heap(&self.log, &path, canister_id);
+        truncate_canister_stable_memory(&self.log, &path, canister_id);
+        Ok(())
+    }
+
+    /// Returns the snapshots of a canister.
+    pub(crate) fn list_canister_snapshots(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        state: &ReplicatedState,
+    ) -> Result<ListCanisterSnapshotsResponse, CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        self.validate_controller(canister, &sender)?;
+
+        Ok(ListCanisterSnapshotsResponse(
+            state
+                .canister_snapshots
+                .list(canister_id)
+                .map(|(snapshot_id, snapshot)| snapshot_response(*snapshot_id, snapshot))
+                .collect(),
+        ))
+    }
+
+    /// Deletes a snapshot of a canister, releasing the memory it takes.
+    pub(crate) fn delete_canister_snapshot(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        snapshot_id: &[u8],
+        state: &mut ReplicatedState,
+    ) -> Result<(), CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        self.validate_controller(canister, &sender)?;
+        let (snapshot_id, _) = self.validate_snapshot(state, canister_id, snapshot_id)?;
+
+        state.remove_canister_snapshot(snapshot_id);
+        Ok(())
+    }
+
+    /// Adds a chunk to the Wasm chunk store of a canister and returns its
+    /// hash. Uploading a chunk that is already stored is a no-op.
+    ///
+    /// Every chunk counts as `CHUNK_SIZE` bytes towards the memory usage of
+    /// the canister, regardless of its actual length.
+    pub(crate) fn upload_chunk(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        chunk: &[u8],
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> Result<ChunkHash, CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        self.validate_controller(canister, &sender)?;
+
+        let wasm_chunk_store = &canister.system_state.wasm_chunk_store;
+        if !wasm_chunk_store.contains_chunk(chunk) {
+            wasm_chunk_store
+                .can_insert_chunk(DEFAULT_MAX_CHUNKS, chunk)
+                .map_err(|message| CanisterManagerError::WasmChunkStoreError { message })?;
+            self.validate_memory_increase(
+                canister,
+                NumBytes::from(CHUNK_SIZE),
+                NumBytes::from(0),
+                round_limits,
+            )?;
+        }
+
+        let canister = state.canister_state_mut(&canister_id).unwrap();
+        let hash = canister.system_state.wasm_chunk_store.insert_chunk(chunk);
+        Ok(ChunkHash {
+            hash: hash.to_vec(),
+        })
+    }
+
+    /// Removes all chunks from the Wasm chunk store of a canister.
+    pub(crate) fn clear_chunk_store(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        state: &mut ReplicatedState,
+    ) -> Result<(), CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        self.validate_controller(canister, &sender)?;
+
+        let path = state.path().to_owned();
+        let canister = state.canister_state_mut(&canister_id).unwrap();
+        canister.system_state.wasm_chunk_store = WasmChunkStore::default();
+        truncate_canister_wasm_chunk_store(&self.log, &path, canister_id);
+        Ok(())
+    }
+
+    /// Returns the hashes of the chunks in the Wasm chunk store of a canister.
+    pub(crate) fn stored_chunks(
+        &self,
+        sender: PrincipalId,
+        canister_id: CanisterId,
+        state: &ReplicatedState,
+    ) -> Result<StoredChunksReply, CanisterManagerError> {
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        self.validate_controller(canister, &sender)?;
+
+        Ok(StoredChunksReply(
+            canister
+                .system_state
+                .wasm_chunk_store
+                .keys()
+                .map(|hash| ChunkHash {
+                    hash: hash.to_vec(),
+                })
+                .collect(),
+        ))
+    }
+
+    /// Returns the module hash, the controllers and the most recent changes
+    /// of a canister. Unlike `canister_status`, it can be called by anyone.
+    pub(crate) fn get_canister_info(
+        &self,
+        canister_id: CanisterId,
+        num_requested_changes: Option<u64>,
+        state: &ReplicatedState,
+    ) -> Result<CanisterInfoResponse, CanisterManagerError> {
+        let num_requested_changes = num_requested_changes.unwrap_or(0);
+        if num_requested_changes > MAX_CANISTER_HISTORY_CHANGES as u64 {
+            return Err(CanisterManagerError::CanisterInfoTooManyChanges {
+                requested: num_requested_changes,
+                limit: MAX_CANISTER_HISTORY_CHANGES,
+            });
+        }
+        let canister = self.validate_canister_exists(state, canister_id)?;
+        let canister_history = &canister.system_state.canister_history;
+
+        Ok(CanisterInfoResponse {
+            total_num_changes: canister_history.get_total_num_changes(),
+            recent_changes: canister_history
+                .get_changes(num_requested_changes as usize)
+                .cloned()
+                .collect(),
+            module_hash: get_wasm_hash(canister).map(|hash| hash.to_vec()),
+            controllers: canister.controllers().iter().copied().collect(),
+        })
+    }
+
     /// Permanently deletes a canister from `ReplicatedState`.
     ///
     /// The canister must be `Stopped` and only the controller of the canister
@@ -1033,7 +1401,10 @@ impl CanisterManager {
         // Validate the request is from the controller.
         self.validate_controller(canister_to_delete, &sender)?;
 
-        self.validate_canister_is_stopped(canister_to_delete)?;
+        self.validate_canister_is_stopped(
+            canister_to_delete,
+            CanisterManagerError::DeleteCanisterNotStopped,
+        )?;
 
         // Once a canister is stopped, it stops accepting new messages, so this should
         // never happen.
@@ -1070,8 +1441,9 @@ impl CanisterManager {
         // - its state is permanently deleted, and
         // - its cycles are discarded.
 
-        // Take out the canister from `ReplicatedState`.
+        // Take out the canister and its snapshots from `ReplicatedState`.
         let canister_to_delete = state.take_canister_state(&canister_id_to_delete).unwrap();
+        state.canister_snapshots.remove_all(canister_id_to_delete);
         // Leftover cycles in the balance are considered `consumed`.
         let consumed_cycles_by_canister_to_delete =
             NominalCycles::from(canister_to_delete.system_state.balance())
@@ -1104,13 +1476,14 @@ impl CanisterManager {
     /// Returns the auto-generated id the new canister that has been created.
     pub(crate) fn create_canister_with_cycles(
         &self,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         cycles_amount: Option<u128>,
         settings: CanisterSettings,
         state: &mut ReplicatedState,
         provisional_whitelist: &ProvisionalWhitelist,
         max_number_of_canisters: u64,
     ) -> Result<CanisterId, CanisterManagerError> {
+        let sender = origin.origin();
         if !provisional_whitelist.contains(&sender) {
             return Err(CanisterManagerError::SenderNotInWhitelist(sender));
         }
@@ -1129,7 +1502,7 @@ impl CanisterManager {
         ) {
             Err(err) => Err(err),
             Ok(validated_settings) => self.create_canister_helper(
-                sender,
+                origin,
                 cycles,
                 Cycles::new(0),
                 validated_settings,
@@ -1141,7 +1514,7 @@ impl CanisterManager {
 
     fn create_canister_helper(
         &self,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         cycles: Cycles,
         creation_fee: Cycles,
         settings: ValidatedCanisterSettings,
@@ -1166,7 +1539,7 @@ impl CanisterManager {
         // Canister id available. Create the new canister.
         let mut system_state = SystemState::new_running(
             new_canister_id,
-            sender,
+            origin.origin(),
             cycles,
             self.config.default_freeze_threshold,
         );
@@ -1177,6 +1550,12 @@ impl CanisterManager {
         let mut new_canister = CanisterState::new(system_state, None, scheduler_state);
 
         self.do_update_settings(settings, &mut new_canister);
+        let controllers = new_canister.controllers().iter().copied().collect();
+        new_canister.system_state.add_canister_change(
+            state.time(),
+            origin,
+            CanisterChangeDetails::canister_creation(controllers),
+        );
 
         // Add new canister to the replicated state.
         state.put_canister_state(new_canister);
@@ -1300,14 +1679,83 @@ impl CanisterManager {
         Ok(())
     }
 
+    // Ensures that the canister has enough memory allocation, or the subnet
+    // has enough memory, to replace `freed_bytes` of the canister's memory
+    // with `new_bytes`.
+    fn validate_memory_increase(
+        &self,
+        canister: &CanisterState,
+        new_bytes: NumBytes,
+        freed_bytes: NumBytes,
+        round_limits: &mut RoundLimits,
+    ) -> Result<(), CanisterManagerError> {
+        match canister.memory_allocation() {
+            MemoryAllocation::Reserved(allocated_bytes) => {
+                let memory_usage_needed =
+                    canister.memory_usage(self.config.own_subnet_type) + new_bytes - freed_bytes;
+                if memory_usage_needed > allocated_bytes {
+                    return Err(CanisterManagerError::NotEnoughMemoryAllocationGiven {
+                        canister_id: canister.canister_id(),
+                        memory_allocation_given: canister.memory_allocation(),
+                        memory_usage_needed,
+                    });
+                }
+            }
+            MemoryAllocation::BestEffort => {
+                if new_bytes > freed_bytes {
+                    let requested = new_bytes - freed_bytes;
+                    if round_limits
+                        .subnet_available_memory
+                        .try_decrement(requested, NumBytes::from(0))
+                        .is_err()
+                    {
+                        return Err(CanisterManagerError::SubnetMemoryCapacityOverSubscribed {
+                            requested,
+                            available: NumBytes::from(
+                                round_limits
+                                    .subnet_available_memory
+                                    .get_total_memory()
+                                    .max(0) as u64,
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the snapshot with the given id if it exists and belongs to the
+    // given canister.
+    fn validate_snapshot(
+        &self,
+        state: &ReplicatedState,
+        canister_id: CanisterId,
+        snapshot_id: &[u8],
+    ) -> Result<(SnapshotId, Arc<CanisterSnapshot>), CanisterManagerError> {
+        SnapshotId::from_bytes(snapshot_id)
+            .and_then(|id| {
+                state
+                    .canister_snapshots
+                    .get(id)
+                    .map(|snapshot| (id, Arc::clone(snapshot)))
+            })
+            .filter(|(_, snapshot)| snapshot.canister_id() == canister_id)
+            .ok_or_else(|| CanisterManagerError::CanisterSnapshotNotFound {
+                canister_id,
+                snapshot_id: snapshot_id.to_vec(),
+            })
+    }
+
+    // Ensures that the canister is stopped, returning the error built by
+    // `not_stopped` otherwise.
     fn validate_canister_is_stopped(
         &self,
         canister: &CanisterState,
+        not_stopped: fn(CanisterId) -> CanisterManagerError,
     ) -> Result<(), CanisterManagerError> {
         if canister.status() != CanisterStatusType::Stopped {
-            return Err(CanisterManagerError::DeleteCanisterNotStopped(
-                canister.canister_id(),
-            ));
+            return Err(not_stopped(canister.canister_id()));
         }
         Ok(())
     }
@@ -1348,6 +1796,17 @@ impl CanisterManager {
     }
 }
 
+fn snapshot_response(
+    snapshot_id: SnapshotId,
+    snapshot: &CanisterSnapshot,
+) -> CanisterSnapshotResponse {
+    CanisterSnapshotResponse {
+        id: snapshot_id.to_bytes(),
+        taken_at_timestamp: snapshot.taken_at_timestamp().as_nanos_since_unix_epoch(),
+        total_size: snapshot.size().get(),
+    }
+}
+
 pub(crate) fn get_wasm_hash(canister: &CanisterState) -> Option<[u8; 32]> {
     canister
         .execution_state
@@ -1387,6 +1846,7 @@ pub(crate) enum CanisterManagerError {
     Hypervisor(CanisterId, HypervisorError),
     DeleteCanisterNotStopped(CanisterId),
     DeleteCanisterSelf(CanisterId),
+    LoadCanisterSnapshotNotStopped(CanisterId),
     SenderNotInWhitelist(PrincipalId),
     NotEnoughMemoryAllocationGiven {
         canister_id: CanisterId,
@@ -1408,6 +1868,21 @@ pub(crate) enum CanisterManagerError {
         subnet_id: SubnetId,
         max_number_of_canisters: u64,
     },
+    CanisterSnapshotNotFound {
+        canister_id: CanisterId,
+        snapshot_id: Vec<u8>,
+    },
+    CanisterSnapshotLimitExceeded {
+        canister_id: CanisterId,
+        limit: usize,
+    },
+    WasmChunkStoreError {
+        message: String,
+    },
+    CanisterInfoTooManyChanges {
+        requested: u64,
+        limit: usize,
+    },
 }
 
 impl From<CanisterManagerError> for UserError {
@@ -1477,6 +1952,15 @@ impl From<CanisterManagerError> for UserError {
                     )
                 )
             }
+            LoadCanisterSnapshotNotStopped(canister_id) => {
+                Self::new(
+                    ErrorCode::CanisterNotStopped,
+                    format!(
+                        "Canister {} must be stopped before a snapshot is loaded into it.",
+                        canister_id,
+                    )
+                )
+            }
             DeleteCanisterSelf(canister_id) => {
                 Self::new(
                     ErrorCode::CanisterInvalidController,
@@ -1548,6 +2032,33 @@ impl From<CanisterManagerError> for UserError {
                     format!("Subnet {} has reached the allowed canister limit of {} canisters. Retry creating the canister.", subnet_id, max_number_of_canisters),
                 )
             }
+            CanisterSnapshotNotFound { canister_id, snapshot_id } => {
+                Self::new(
+                    ErrorCode::CanisterSnapshotNotFound,
+                    format!("Could not find the snapshot 0x{} of canister {}.", hex::encode(snapshot_id), canister_id),
+                )
+            }
+            CanisterSnapshotLimitExceeded { canister_id, limit } => {
+                Self::new(
+                    ErrorCode::CanisterSnapshotLimitExceeded,
+                    format!("Canister {} has reached the limit of {} snapshots. Delete or replace an existing snapshot.", canister_id, limit),
+                )
+            }
+            WasmChunkStoreError { message } => {
+                Self::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!("Error from Wasm chunk store: {}", message),
+                )
+            }
+            CanisterInfoTooManyChanges { requested, limit } => {
+                Self::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!(
+                        "canister_info can return at most {} changes, but {} were requested",
+                        limit, requested
+                    ),
+                )
+            }
         }
     }
 }
@@ -1607,10 +2118,34 @@ pub(crate) fn truncate_canister_stable_memory(
     }
 }
 
+pub(crate) fn truncate_canister_wasm_chunk_store(
+    log: &ReplicaLogger,
+    state_path: &Path,
+    canister_id: CanisterId,
+) {
+    let layout = canister_layout(state_path, &canister_id);
+    let wasm_chunk_store_file = layout.wasm_chunk_store();
+    if let Err(err) = nix::unistd::truncate(&wasm_chunk_store_file, 0) {
+        // It's OK if the file doesn't exist, everything else is a fatal error.
+        if err != nix::errno::Errno::ENOENT {
+            fatal!(
+                log,
+                "failed to truncate Wasm chunk store of canister {} stored at {}: {}",
+                canister_id,
+                wasm_chunk_store_file.display(),
+                err
+            )
+        }
+    }
+}
+
 /// Uninstalls a canister.
 ///
 /// See https://sdk.dfinity.org/docs/interface-spec/index.html#ic-uninstall_code
 ///
+/// The uninstallation is recorded in the canister history if `origin` is
+/// given, i.e. if it was requested via `uninstall_code`.
+///
 /// Returns a list of rejects that need to be sent out to their callers.
 #[doc(hidden)]
 pub fn uninstall_canister(
@@ -1618,6 +2153,7 @@ pub fn uninstall_canister(
     canister: &mut CanisterState,
     state_path: &Path,
     time: Time,
+    origin: Option<CanisterChangeOrigin>,
 ) -> Vec<Response> {
     // Drop the canister's execution state.
     canister.execution_state = None;
@@ -1625,9 +2161,28 @@ pub fn uninstall_canister(
     // Drop its certified data.
     canister.system_state.certified_data = Vec::new();
 
+    // Deactivate its global timer.
+    canister.system_state.global_timer = CanisterTimer::Inactive;
+
+    // Clear its log.
+    canister.system_state.canister_log = CanisterLog::default();
+
+    canister.system_state.bump_canister_version();
+    if let Some(origin) = origin {
+        canister.system_state.add_canister_change(
+            time,
+            origin,
+            CanisterChangeDetails::CodeUninstall,
+        );
+    }
+
     truncate_canister_heap(log, state_path, canister.canister_id());
     truncate_canister_stable_memory(log, state_path, canister.canister_id());
 
+    // Drop the chunks uploaded for installing a new module.
+    canister.system_state.wasm_chunk_store = WasmChunkStore::default();
+    truncate_canister_wasm_chunk_store(log, state_path, canister.canister_id());
+
     let mut rejects = Vec::new();
     let canister_id = canister.canister_id();
     if let Some(call_context_manager) = canister.system_state.call_context_manager_mut() {
@@ -1675,8 +2230,8 @@ pub fn uninstall_canister(
                     log,
                     "No callbacks with a query origin should be found when uninstalling"
                 ),
-                CallOrigin::Heartbeat => {
-                    // Cannot respond to heartbeat messages. Nothing to do.
+                CallOrigin::SystemTask => {
+                    // Cannot respond to system tasks. Nothing to do.
                 }
             }
 
@@ -1694,6 +2249,8 @@ struct ValidatedCanisterSettings {
     pub compute_allocation: Option<ComputeAllocation>,
     pub memory_allocation: Option<MemoryAllocation>,
     pub freezing_threshold: Option<NumSeconds>,
+    pub log_visibility: Option<LogVisibility>,
+    pub wasm_memory_limit: Option<NumBytes>,
 }
 
 impl TryFrom<(CanisterSettings, usize)> for ValidatedCanisterSettings {
@@ -1729,6 +2286,8 @@ impl TryFrom<(CanisterSettings, usize)> for ValidatedCanisterSettings {
             compute_allocation: settings.compute_allocation(),
             memory_allocation: settings.memory_allocation(),
             freezing_threshold: settings.freezing_threshold(),
+            log_visibility: settings.log_visibility(),
+            wasm_memory_limit: settings.wasm_memory_limit(),
         })
     }
 }
@@ -1742,6 +2301,7 @@ fn finish_install_code(
     instruction_limit: NumInstructions,
     instructions_left: NumInstructions,
     result: Result<(CanisterState, NumBytes), CanisterManagerError>,
+    canister_log: Option<CanisterLog>,
     mode: CanisterInstallMode,
     canister_layout_path: PathBuf,
     config: &CanisterMgrConfig,
@@ -1786,6 +2346,13 @@ fn finish_install_code(
             if config.rate_limiting_of_instructions == FlagStatus::Enabled {
                 new_canister.scheduler_state.install_code_debit += instructions_consumed;
             }
+            if let Some(module_hash) = new_wasm_hash {
+                new_canister.system_state.add_canister_change(
+                    round.time,
+                    message.canister_change_origin(),
+                    CanisterChangeDetails::code_deployment(mode, module_hash),
+                );
+            }
 
             // We managed to create a new canister and will be dropping the
             // older one. So we get rid of the previous heap to make sure it
@@ -1823,6 +2390,11 @@ fn finish_install_code(
                 instructions_left,
                 instruction_limit,
             );
+            // The log records of the failed execution are kept, so that the
+            // controllers can see why the install / upgrade failed.
+            if let Some(canister_log) = canister_log {
+                old_canister.system_state.canister_log = canister_log;
+            }
             DtsInstallCodeResult::Finished {
                 canister: old_canister,
                 message,
@@ -1860,12 +2432,14 @@ impl PausedInstallCodeExecution {
             InstallCodeRoutineResult::Finished {
                 instructions_left,
                 result,
+                canister_log,
             } => finish_install_code(
                 canister,
                 self.message,
                 self.message_instruction_limit,
                 instructions_left,
                 result,
+                canister_log,
                 self.mode,
                 self.canister_layout_path,
                 &self.config,
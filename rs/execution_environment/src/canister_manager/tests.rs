@@ -18,8 +18,9 @@ use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, UserError};
 use ic_ic00_types::{
-    CanisterIdRecord, CanisterInstallMode, CanisterSettingsArgs, CanisterStatusType,
-    CreateCanisterArgs, EmptyBlob, InstallCodeArgs, Method, Payload, UpdateSettingsArgs,
+    CanisterChangeOrigin, CanisterIdRecord, CanisterInstallMode, CanisterSettingsArgs,
+    CanisterStatusType, CreateCanisterArgs, EmptyBlob, InstallCodeArgs, Method, Payload,
+    UpdateSettingsArgs,
 };
 use ic_interfaces::{
     execution_environment::{AvailableMemory, ExecutionMode, HypervisorError},
@@ -1179,7 +1180,7 @@ fn provisional_create_canister_has_no_creation_fee() {
     with_setup(|canister_manager, mut state, _| {
         let canister_id = canister_manager
             .create_canister_with_cycles(
-                canister_test_id(1).get(),
+                CanisterChangeOrigin::from_canister(canister_test_id(1).get(), None),
                 Some(INITIAL_CYCLES.get()),
                 CanisterSettings::default(),
                 &mut state,
@@ -1867,7 +1868,7 @@ fn set_controller_with_incorrect_controller() {
         // Set the controller from the wrong controller. Should fail.
         assert_eq!(
             canister_manager.set_controller(
-                wrong_controller,
+                CanisterChangeOrigin::from_user(wrong_controller),
                 canister_id,
                 new_controller,
                 &mut state
@@ -1903,7 +1904,12 @@ fn set_controller_with_correct_controller() {
 
         // Set the controller from the correct controller. Should succeed.
         assert!(canister_manager
-            .set_controller(controller, canister_id, new_controller, &mut state,)
+            .set_controller(
+                CanisterChangeOrigin::from_user(controller),
+                canister_id,
+                new_controller,
+                &mut state,
+            )
             .is_ok());
 
         // Controller is now the new controller.
@@ -2105,7 +2111,7 @@ fn create_canister_with_cycles_sender_in_whitelist() {
     let sender = canister_test_id(1).get();
     let canister_id = canister_manager
         .create_canister_with_cycles(
-            sender,
+            CanisterChangeOrigin::from_canister(sender, None),
             Some(123),
             CanisterSettings::default(),
             &mut state,
@@ -2387,6 +2393,7 @@ fn uninstall_canister_doesnt_respond_to_responded_call_contexts() {
                 .build(),
             Path::new(""),
             mock_time(),
+            None,
         ),
         Vec::new()
     );
@@ -2411,6 +2418,7 @@ fn uninstall_canister_responds_to_unresponded_call_contexts() {
                 .build(),
             Path::new(""),
             mock_time(),
+            None,
         )[0],
         Response::Ingress(IngressResponse {
             message_id: message_test_id(456),
@@ -2967,6 +2975,8 @@ fn lower_memory_allocation_than_usage_fails() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(2)).unwrap()),
             None,
+            None,
+            None,
         );
 
         let compute_allocation_used = state.total_compute_allocation();
@@ -2975,7 +2985,8 @@ fn lower_memory_allocation_than_usage_fails() {
 
         assert_matches!(
             canister_manager.update_settings(
-                sender,
+                mock_time(),
+                CanisterChangeOrigin::from_canister(sender, None),
                 settings,
                 canister,
                 compute_allocation_used,
@@ -2998,6 +3009,8 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(2)).unwrap()),
             None,
+            None,
+            None,
         );
         let canister_id = canister_manager
             .create_canister(
@@ -3041,6 +3054,8 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(MEMORY_CAPACITY.get() / 2)).unwrap()),
             None,
+            None,
+            None,
         );
 
         let compute_allocation_used = state.total_compute_allocation();
@@ -3049,7 +3064,8 @@ fn test_install_when_updating_memory_allocation_via_canister_settings() {
 
         canister_manager
             .update_settings(
-                sender,
+                mock_time(),
+                CanisterChangeOrigin::from_canister(sender, None),
                 settings,
                 canister,
                 compute_allocation_used,
@@ -3088,6 +3104,8 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
                 MemoryAllocation::try_from(NumBytes::from(WASM_PAGE_SIZE_IN_BYTES + 100)).unwrap(),
             ),
             None,
+            None,
+            None,
         );
         let wat = r#"
         (module
@@ -3163,6 +3181,8 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
                     .unwrap(),
             ),
             None,
+            None,
+            None,
         );
 
         let compute_allocation_used = state.total_compute_allocation();
@@ -3171,7 +3191,8 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
 
         canister_manager
             .update_settings(
-                sender,
+                mock_time(),
+                CanisterChangeOrigin::from_canister(sender, None),
                 settings,
                 canister,
                 compute_allocation_used,
@@ -3223,7 +3244,7 @@ fn uninstall_code_can_be_invoked_by_governance_canister() {
     canister_manager
         .uninstall_code(
             canister_test_id(0),
-            GOVERNANCE_CANISTER_ID.get(),
+            CanisterChangeOrigin::from_canister(GOVERNANCE_CANISTER_ID.get(), None),
             &mut state,
         )
         .unwrap();
@@ -3244,7 +3265,7 @@ fn test_install_when_setting_memory_allocation_to_zero() {
         let wasm = ic_test_utilities::universal_canister::UNIVERSAL_CANISTER_WASM.to_vec();
 
         let sender = canister_test_id(100).get();
-        let settings = CanisterSettings::new(None, None, None, None, None);
+        let settings = CanisterSettings::new(None, None, None, None, None, None, None);
         let canister_id = canister_manager
             .create_canister(
                 sender,
@@ -3265,6 +3286,8 @@ fn test_install_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(0)).unwrap()),
             None,
+            None,
+            None,
         );
 
         let compute_allocation_used = state.total_compute_allocation();
@@ -3273,7 +3296,8 @@ fn test_install_when_setting_memory_allocation_to_zero() {
 
         canister_manager
             .update_settings(
-                sender,
+                mock_time(),
+                CanisterChangeOrigin::from_canister(sender, None),
                 settings,
                 canister,
                 compute_allocation_used,
@@ -3312,6 +3336,8 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(MEMORY_CAPACITY.get() / 2)).unwrap()),
             None,
+            None,
+            None,
         );
         let canister_id = canister_manager
             .create_canister(
@@ -3350,6 +3376,8 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
             None,
             Some(MemoryAllocation::try_from(NumBytes::from(0)).unwrap()),
             None,
+            None,
+            None,
         );
 
         let compute_allocation_used = state.total_compute_allocation();
@@ -3358,7 +3386,8 @@ fn test_upgrade_when_setting_memory_allocation_to_zero() {
 
         canister_manager
             .update_settings(
-                sender,
+                mock_time(),
+                CanisterChangeOrigin::from_canister(sender, None),
                 settings,
                 canister,
                 compute_allocation_used,
@@ -81,6 +81,19 @@ fn validate_message(
         }
     }
 
+    // Composite queries can call other canisters and are therefore only
+    // supported in non-replicated mode.
+    if canister.exports_composite_query_method(req.method_name().to_string()) {
+        return Err(UserError::new(
+            ErrorCode::CompositeQueryCalledInReplicatedMode,
+            format!(
+                "Composite query method {} of canister {} cannot be called in replicated mode",
+                req.method_name(),
+                canister.canister_id()
+            ),
+        ));
+    }
+
     let query = WasmMethod::Query(req.method_name().to_string());
     if validate_method(&query, canister).is_err() {
         let update = WasmMethod::Update(req.method_name().to_string());
@@ -56,10 +56,10 @@ pub(crate) fn action_to_response(
             log,
             "The update path should not have created a callback with a query origin",
         ),
-        CallOrigin::Heartbeat => {
-            // Since heartbeat messages are invoked by the system as opposed
-            // to a principal, they cannot respond since there's no one to
-            // respond to. Do nothing.
+        CallOrigin::SystemTask => {
+            // Since system tasks are invoked by the system as opposed to a
+            // principal, they cannot respond since there's no one to respond
+            // to. Do nothing.
             ExecutionResponse::Empty
         }
     }
@@ -211,10 +211,10 @@ pub(crate) fn wasm_result_to_query_response(
         CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => {
             fatal!(log, "The update path should not have a query origin",)
         }
-        CallOrigin::Heartbeat => {
-            // Since heartbeat messages are invoked by the system as opposed
-            // to a principal, they cannot respond since there's no one to
-            // respond to. Do nothing.
+        CallOrigin::SystemTask => {
+            // Since system tasks are invoked by the system as opposed to a
+            // principal, they cannot respond since there's no one to respond
+            // to. Do nothing.
             ExecutionResponse::Empty
         }
     }
@@ -389,4 +389,7 @@ pub fn apply_canister_state_changes(
         execution_state.stable_memory = stable_memory;
         execution_state.exported_globals = globals;
     }
+    // The log records are kept even if the execution failed, so that the
+    // controllers can see the trap messages.
+    system_state.canister_log.append(&mut output.canister_log);
 }
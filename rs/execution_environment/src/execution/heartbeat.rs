@@ -1,5 +1,6 @@
 use crate::execution_environment::RoundLimits;
-// This module defines how `canister_heartbeat` messages are executed.
+// This module defines how system tasks, i.e. `canister_heartbeat` and
+// `canister_global_timer` messages, are executed.
 // See https://smartcontracts.org/docs/interface-spec/index.html#_heartbeat.
 use crate::{CanisterHeartbeatError, Hypervisor};
 use ic_cycles_account_manager::CyclesAccountManager;
@@ -14,9 +15,9 @@ use ic_types::methods::{FuncRef, SystemMethod, WasmMethod};
 use ic_types::{Cycles, NumBytes, Time};
 use std::sync::Arc;
 
-/// Holds the result of heartbeat execution.
+/// Holds the result of a system task execution.
 pub struct HeartbeatResult {
-    /// The canister state resulted from the heartbeat execution.
+    /// The canister state resulted from the system task execution.
     pub canister_state: CanisterState,
     /// The size of the heap delta change, if execution is successful
     /// or the relevant error in case of failure.
@@ -39,7 +40,7 @@ impl HeartbeatResult {
     }
 }
 
-// Validates a canister before executing the heartbeat.
+// Validates a canister before executing a system task.
 //
 // Returns the canister split in parts if successful,
 // otherwise `HeartbeatResult` which contains the error.
@@ -83,17 +84,18 @@ fn validate_canister(
     Ok((execution_state, old_system_state, scheduler_state))
 }
 
-/// Executes a heartbeat of a given canister.
+/// Executes a system task (`canister_heartbeat` or `canister_global_timer`)
+/// of a given canister.
 ///
-/// Before executing the heartbeat, the canister is validated to meet the following
-/// conditions:
+/// Before executing the system task, the canister is validated to meet the
+/// following conditions:
 ///     - The status of the canister is Running.
 ///     Otherwise, `CanisterHeartbeatError::CanisterNotRunning` error is returned.
 ///     - Wasm module is present.
 ///     Otherwise, `CanisterHeartbeatError::CanisterExecutionFailed` error is returned.
-///     - Wasm module exports the heartbeat method.
-///    
-/// When the heartbeat method is not exported, the execution succeeds as a no-op operation.
+///     - Wasm module exports the system task method.
+///
+/// When the system task method is not exported, the execution succeeds as a no-op operation.
 /// No changes are applied to the canister state if the canister cannot be validated.
 ///
 /// Returns:
@@ -107,6 +109,7 @@ fn validate_canister(
 /// execution was successful or the relevant `CanisterHeartbeatError` error if execution fails.
 #[allow(clippy::too_many_arguments)]
 pub fn execute_heartbeat(
+    system_task: SystemMethod,
     canister: CanisterState,
     network_topology: Arc<NetworkTopology>,
     execution_parameters: ExecutionParameters,
@@ -117,7 +120,7 @@ pub fn execute_heartbeat(
     round_limits: &mut RoundLimits,
     subnet_size: usize,
 ) -> HeartbeatResult {
-    let method = WasmMethod::System(SystemMethod::CanisterHeartbeat);
+    let method = WasmMethod::System(system_task.clone());
     let memory_usage = canister.memory_usage(own_subnet_type);
     let compute_allocation = canister.scheduler_state.compute_allocation;
     let message_instruction_limit = execution_parameters.instruction_limits.message();
@@ -131,7 +134,7 @@ pub fn execute_heartbeat(
             Err(err) => return err,
         };
 
-    // Charge for heartbeat execution.
+    // Charge for system task execution.
     if let Err(err) = cycles_account_manager.withdraw_execution_cycles(
         &mut system_state,
         memory_usage,
@@ -145,12 +148,12 @@ pub fn execute_heartbeat(
         );
     }
 
-    // Execute canister heartbeat.
+    // Execute the system task.
     let call_context_id = system_state
         .call_context_manager_mut()
         .unwrap()
-        .new_call_context(CallOrigin::Heartbeat, Cycles::new(0), time);
-    let api_type = ApiType::heartbeat(time, call_context_id);
+        .new_call_context(CallOrigin::SystemTask, Cycles::new(0), time);
+    let api_type = ApiType::system_task(system_task, time, call_context_id);
     let (output, output_execution_state, output_system_state) = hypervisor.execute(
         api_type,
         time,
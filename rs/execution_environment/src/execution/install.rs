@@ -9,7 +9,7 @@ use ic_base_types::{NumBytes, PrincipalId};
 use ic_embedders::wasm_executor::{CanisterStateChanges, PausedWasmExecution, WasmExecutionResult};
 use ic_interfaces::execution_environment::WasmExecutionOutput;
 use ic_logger::{fatal, info};
-use ic_replicated_state::{CanisterState, SystemState};
+use ic_replicated_state::{CanisterState, CanisterTimer, SystemState};
 use ic_sys::PAGE_SIZE;
 use ic_system_api::{ApiType, ExecutionParameters};
 use ic_types::methods::{FuncRef, SystemMethod, WasmMethod};
@@ -85,6 +85,7 @@ pub(crate) fn execute_install(
             return InstallCodeRoutineResult::Finished {
                 instructions_left: execution_parameters.instruction_limits.message(),
                 result: Err((canister_id, err).into()),
+                canister_log: None,
             };
         }
     };
@@ -93,6 +94,13 @@ pub(crate) fn execute_install(
     let scheduler_state = old_canister.scheduler_state.clone();
     let mut new_canister = CanisterState::new(system_state, Some(execution_state), scheduler_state);
 
+    // The global timer is deactivated when the Wasm module changes.
+    new_canister.system_state.global_timer = CanisterTimer::Inactive;
+
+    // `start()` and `canister_init()` already see the new canister version.
+    // It is dropped together with the new canister if the installation fails.
+    new_canister.system_state.bump_canister_version();
+
     // Update allocations.  This must happen after we have created the new
     // execution state so that we fairly account for the memory requirements
     // of the new wasm module.
@@ -118,6 +126,7 @@ pub(crate) fn execute_install(
                     memory_allocation_given: desired_memory_allocation,
                     memory_usage_needed: new_canister.memory_usage(subnet_type),
                 }),
+                canister_log: None,
             };
         }
         execution_parameters.canister_memory_limit = bytes;
@@ -240,6 +249,7 @@ fn install_stage_2a_process_start_result(
             return InstallCodeRoutineResult::Finished {
                 instructions_left,
                 result: Err((canister_id, err).into()),
+                canister_log: Some(new_canister.system_state.canister_log),
             }
         }
     };
@@ -303,6 +313,7 @@ fn install_stage_2b_continue_install_after_start(
         return InstallCodeRoutineResult::Finished {
             instructions_left,
             result: Ok((new_canister, total_heap_delta)),
+            canister_log: None,
         };
     }
 
@@ -385,11 +396,13 @@ fn install_stage_3_process_init_result(
             InstallCodeRoutineResult::Finished {
                 instructions_left: output.num_instructions_left,
                 result: Ok((new_canister, total_heap_delta)),
+                canister_log: None,
             }
         }
         Err(err) => InstallCodeRoutineResult::Finished {
             instructions_left: output.num_instructions_left,
             result: Err((canister_id, err).into()),
+            canister_log: Some(new_canister.system_state.canister_log),
         },
     }
 }
@@ -3,7 +3,7 @@
 
 use ic_base_types::NumBytes;
 use ic_replicated_state::CanisterState;
-use ic_types::NumInstructions;
+use ic_types::{canister_log::CanisterLog, NumInstructions};
 
 use crate::{
     canister_manager::CanisterManagerError, execution_environment::RoundContext, RoundLimits,
@@ -14,12 +14,17 @@ use crate::{
 /// canister state with all changes. If the routine has failed, then there is no
 /// new canister state and the caller should use the old state after refunding
 /// the remaining instructions.
+///
+/// The log of a canister is not rolled back: if the routine fails after
+/// executing Wasm code, then `canister_log` holds the log of the canister
+/// including the records of that execution.
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub(crate) enum InstallCodeRoutineResult {
     Finished {
         instructions_left: NumInstructions,
         result: Result<(CanisterState, NumBytes), CanisterManagerError>,
+        canister_log: Option<CanisterLog>,
     },
     Paused {
         paused_execution: Box<dyn PausedInstallCodeRoutine>,
@@ -42,7 +42,11 @@ pub fn execute_non_replicated_query(
         );
     }
 
-    let method = WasmMethod::Query(method.to_string());
+    let method = if canister.exports_composite_query_method(method.to_string()) {
+        WasmMethod::CompositeQuery(method.to_string())
+    } else {
+        WasmMethod::Query(method.to_string())
+    };
     let memory_usage = canister.memory_usage(hypervisor.subnet_type());
 
     // Validate that the Wasm module is present and exports the method
@@ -10,7 +10,7 @@ use ic_base_types::{NumBytes, PrincipalId};
 use ic_embedders::wasm_executor::{CanisterStateChanges, PausedWasmExecution, WasmExecutionResult};
 use ic_interfaces::execution_environment::{HypervisorError, WasmExecutionOutput};
 use ic_logger::{fatal, info};
-use ic_replicated_state::{CanisterState, Memory, SystemState};
+use ic_replicated_state::{CanisterState, CanisterTimer, Memory, SystemState};
 use ic_sys::PAGE_SIZE;
 use ic_system_api::{ApiType, ExecutionParameters};
 use ic_types::methods::{FuncRef, SystemMethod, WasmMethod};
@@ -89,6 +89,7 @@ pub(crate) fn execute_upgrade(
             return InstallCodeRoutineResult::Finished {
                 instructions_left: execution_parameters.instruction_limits.message(),
                 result: Err((canister_id, HypervisorError::WasmModuleNotFound).into()),
+                canister_log: None,
             }
         }
         Some(es) => es,
@@ -207,6 +208,7 @@ fn upgrade_stage_1_process_pre_upgrade_result(
         Err(err) => InstallCodeRoutineResult::Finished {
             instructions_left,
             result: Err((canister_id, err).into()),
+            canister_log: Some(new_canister.system_state.canister_log),
         },
     }
 }
@@ -256,6 +258,7 @@ fn upgrade_stage_2_and_3a_create_execution_state_and_call_start(
             return InstallCodeRoutineResult::Finished {
                 instructions_left: execution_parameters.instruction_limits.message(),
                 result: Err((canister_id, err).into()),
+                canister_log: Some(new_canister.system_state.canister_log),
             };
         }
         Ok(mut execution_state) => {
@@ -269,6 +272,14 @@ fn upgrade_stage_2_and_3a_create_execution_state_and_call_start(
 
     new_canister.execution_state = Some(execution_state);
 
+    // The global timer is deactivated when the Wasm module changes.
+    new_canister.system_state.global_timer = CanisterTimer::Inactive;
+
+    // `start()` and `canister_post_upgrade()` already see the new canister
+    // version, while `canister_pre_upgrade()` saw the old one. It is dropped
+    // together with the new canister if the upgrade fails.
+    new_canister.system_state.bump_canister_version();
+
     let instructions_left = execution_parameters.instruction_limits.message();
 
     // Update allocations.  This must happen after we have created the new
@@ -296,6 +307,7 @@ fn upgrade_stage_2_and_3a_create_execution_state_and_call_start(
                     memory_allocation_given: desired_memory_allocation,
                     memory_usage_needed: new_canister.memory_usage(subnet_type),
                 }),
+                canister_log: Some(new_canister.system_state.canister_log),
             };
         }
         execution_parameters.canister_memory_limit = bytes;
@@ -419,6 +431,7 @@ fn upgrade_stage_3b_process_start_result(
         Err(err) => InstallCodeRoutineResult::Finished {
             instructions_left,
             result: Err((canister_id, err).into()),
+            canister_log: Some(new_canister.system_state.canister_log),
         },
     }
 }
@@ -547,6 +560,7 @@ fn upgrade_stage_4b_process_post_upgrade_result(
         Err(err) => InstallCodeRoutineResult::Finished {
             instructions_left,
             result: Err((canister_id, err).into()),
+            canister_log: Some(new_canister.system_state.canister_log),
         },
     }
 }
@@ -571,6 +585,7 @@ fn upgrade_stage_4c_finish_upgrade(
     InstallCodeRoutineResult::Finished {
         instructions_left,
         result: Ok((new_canister, total_heap_delta)),
+        canister_log: None,
     }
 }
 
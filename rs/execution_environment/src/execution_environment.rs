@@ -22,11 +22,13 @@ use ic_crypto::derive_tecdsa_public_key;
 use ic_cycles_account_manager::{CyclesAccountManager, IngressInductionCost};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    CanisterHttpRequestArgs, CanisterIdRecord, CanisterSettingsArgs, CanisterStatusType,
+    CanisterChangeOrigin, CanisterHttpRequestArgs, CanisterIdRecord, CanisterInfoRequest,
+    CanisterSettingsArgs, CanisterSnapshotArgs, CanisterStatusType,
     ComputeInitialEcdsaDealingsArgs, CreateCanisterArgs, ECDSAPublicKeyArgs,
-    ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob, InstallCodeArgs, Method as Ic00Method,
-    Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
-    SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs, UpdateSettingsArgs, IC_00,
+    ECDSAPublicKeyResponse, EcdsaKeyId, EmptyBlob, InstallChunkedCodeArgs, InstallCodeArgs,
+    Method as Ic00Method, Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs,
+    ProvisionalTopUpCanisterArgs, SetControllerArgs, SetupInitialDKGArgs, SignWithECDSAArgs,
+    TakeCanisterSnapshotArgs, UpdateSettingsArgs, UploadChunkArgs, IC_00,
 };
 use ic_interfaces::execution_environment::{
     AvailableMemory, CanisterOutOfCyclesError, RegistryExecutionSettings,
@@ -43,13 +45,13 @@ use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::canister_state::system_state::PausedExecutionId;
 use ic_replicated_state::canister_state::NextExecution;
-use ic_replicated_state::ExecutionTask;
 use ic_replicated_state::{
     metadata_state::subnet_call_context_manager::{
         EcdsaDealingsContext, SetupInitialDkgContext, SignWithEcdsaContext,
     },
     CanisterState, NetworkTopology, ReplicatedState,
 };
+use ic_replicated_state::{CanisterTimer, ExecutionTask};
 use ic_system_api::{ExecutionParameters, InstructionLimits};
 use ic_types::messages::MessageId;
 use ic_types::{
@@ -61,6 +63,7 @@ use ic_types::{
         extract_effective_canister_id, AnonymousQuery, Payload, RejectContext, Request, Response,
         SignedIngressContent, StopCanisterContext,
     },
+    methods::SystemMethod,
     CanisterId, ComputeAllocation, Cycles, NumBytes, NumInstructions, SubnetId, Time,
 };
 use ic_wasm_types::WasmHash;
@@ -75,8 +78,8 @@ use std::{convert::Into, convert::TryFrom, sync::Arc};
 use strum::ParseError;
 
 lazy_static! {
-    /// Track how many heartbeat errors have been encountered so that we can
-    /// restrict logging to a sample of them.
+    /// Track how many system task (heartbeat and global timer) errors have
+    /// been encountered so that we can restrict logging to a sample of them.
     static ref HEARTBEAT_ERROR_COUNT: AtomicU64 = AtomicU64::new(0);
 }
 
@@ -245,7 +248,7 @@ pub struct ExecutionEnvironment {
     paused_execution_registry: Arc<Mutex<PausedExecutionRegistry>>,
 }
 
-/// Errors when executing `canister_heartbeat`.
+/// Errors when executing `canister_heartbeat` or `canister_global_timer`.
 #[derive(Debug, Eq, PartialEq)]
 pub enum CanisterHeartbeatError {
     /// The canister isn't running.
@@ -255,7 +258,8 @@ pub enum CanisterHeartbeatError {
 
     OutOfCycles(CanisterOutOfCyclesError),
 
-    /// Execution failed while executing the `canister_heartbeat`.
+    /// Execution failed while executing the `canister_heartbeat` or
+    /// `canister_global_timer`.
     CanisterExecutionFailed(HypervisorError),
 }
 
@@ -429,7 +433,7 @@ impl ExecutionEnvironment {
                 }
             }
 
-            Ok(Ic00Method::InstallCode) => {
+            Ok(Ic00Method::InstallCode) | Ok(Ic00Method::InstallChunkedCode) => {
                 // Tail call is needed for deterministic time slicing here to
                 // properly handle the case of a paused execution.
                 return self.execute_install_code(msg, state, instruction_limits, round_limits, registry_settings.subnet_size);
@@ -440,13 +444,160 @@ impl ExecutionEnvironment {
                     Err(err) => Err(candid_error_to_user_error(err)),
                     Ok(args) => self
                         .canister_manager
-                        .uninstall_code(args.get_canister_id(), *msg.sender(), &mut state)
+                        .uninstall_code(
+                            args.get_canister_id(),
+                            msg.canister_change_origin(),
+                            &mut state,
+                        )
+                        .map(|()| EmptyBlob::encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::TakeCanisterSnapshot) => {
+                let res = match TakeCanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .take_canister_snapshot(
+                            *msg.sender(),
+                            args.get_canister_id(),
+                            args.replace_snapshot(),
+                            &mut state,
+                            round_limits,
+                        )
+                        .map(|response| response.encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::LoadCanisterSnapshot) => {
+                let res = match CanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .load_canister_snapshot(
+                            *msg.sender(),
+                            args.get_canister_id(),
+                            args.snapshot_id(),
+                            &mut state,
+                            round_limits,
+                        )
+                        .map(|()| EmptyBlob::encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::ListCanisterSnapshots) => {
+                let res = match CanisterIdRecord::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .list_canister_snapshots(*msg.sender(), args.get_canister_id(), &state)
+                        .map(|response| response.encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::DeleteCanisterSnapshot) => {
+                let res = match CanisterSnapshotArgs::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .delete_canister_snapshot(
+                            *msg.sender(),
+                            args.get_canister_id(),
+                            args.snapshot_id(),
+                            &mut state,
+                        )
+                        .map(|()| EmptyBlob::encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::UploadChunk) => {
+                let res = match UploadChunkArgs::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .upload_chunk(
+                            *msg.sender(),
+                            args.get_canister_id(),
+                            args.chunk(),
+                            &mut state,
+                            round_limits,
+                        )
+                        .map(|response| response.encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::ClearChunkStore) => {
+                let res = match CanisterIdRecord::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .clear_chunk_store(*msg.sender(), args.get_canister_id(), &mut state)
                         .map(|()| EmptyBlob::encode())
                         .map_err(|err| err.into()),
                 };
                 Some((res, msg.take_cycles()))
             }
 
+            Ok(Ic00Method::StoredChunks) => {
+                let res = match CanisterIdRecord::decode(payload) {
+                    Err(err) => Err(candid_error_to_user_error(err)),
+                    Ok(args) => self
+                        .canister_manager
+                        .stored_chunks(*msg.sender(), args.get_canister_id(), &state)
+                        .map(|response| response.encode())
+                        .map_err(|err| err.into()),
+                };
+                Some((res, msg.take_cycles()))
+            }
+
+            Ok(Ic00Method::CanisterInfo) => match &msg {
+                RequestOrIngress::Ingress(_) => Some((
+                    Err(UserError::new(
+                        ErrorCode::CanisterMethodNotFound,
+                        "canister_info can only be called by other canisters, not via ingress messages.",
+                    )),
+                    Cycles::zero(),
+                )),
+                RequestOrIngress::Request(_) => {
+                    let res = match CanisterInfoRequest::decode(payload) {
+                        Err(err) => Err(candid_error_to_user_error(err)),
+                        Ok(args) => self
+                            .canister_manager
+                            .get_canister_info(
+                                args.canister_id(),
+                                args.num_requested_changes(),
+                                &state,
+                            )
+                            .map(|response| response.encode())
+                            .map_err(|err| err.into()),
+                    };
+                    Some((res, msg.take_cycles()))
+                }
+            },
+
+            Ok(Ic00Method::FetchCanisterLogs) => Some((
+                Err(UserError::new(
+                    ErrorCode::CanisterRejectedMessage,
+                    format!(
+                        "{} can only be called as a query",
+                        Ic00Method::FetchCanisterLogs
+                    ),
+                )),
+                msg.take_cycles(),
+            )),
+
             Ok(Ic00Method::UpdateSettings) => {
                 let res = match UpdateSettingsArgs::decode(payload) {
                     Err(err) => Err(candid_error_to_user_error(err)),
@@ -458,7 +609,7 @@ impl ExecutionEnvironment {
                         let result = match CanisterSettings::try_from(args.settings) {
                             Err(err) => Err(err.into()),
                             Ok(settings) => self.update_settings(
-                                *msg.sender(),
+                                msg.canister_change_origin(),
                                 settings,
                                 canister_id,
                                 &mut state,
@@ -509,7 +660,7 @@ impl ExecutionEnvironment {
                     Ok(args) => self
                         .canister_manager
                         .set_controller(
-                            *msg.sender(),
+                            msg.canister_change_origin(),
                             args.get_canister_id(),
                             args.get_new_controller(),
                             &mut state,
@@ -808,7 +959,7 @@ impl ExecutionEnvironment {
                             Ok(settings) => self
                                 .canister_manager
                                 .create_canister_with_cycles(
-                                    *msg.sender(),
+                                    msg.canister_change_origin(),
                                     cycles_amount,
                                     settings,
                                     &mut state,
@@ -860,6 +1011,27 @@ impl ExecutionEnvironment {
                 Some(res)
             }
 
+            Ok(Ic00Method::BitcoinGetBlockHeaders) => {
+                let cycles = msg.take_cycles();
+                let res =
+                    crate::bitcoin::get_block_headers(msg.method_payload(), &mut state, cycles);
+                Some(res)
+            }
+
+            Ok(Ic00Method::BitcoinGetBlockchainInfo) => {
+                let cycles = msg.take_cycles();
+                let res =
+                    crate::bitcoin::get_blockchain_info(msg.method_payload(), &mut state, cycles);
+                Some(res)
+            }
+
+            Ok(Ic00Method::BitcoinGetTransactions) => {
+                let cycles = msg.take_cycles();
+                let res =
+                    crate::bitcoin::get_transactions(msg.method_payload(), &mut state, cycles);
+                Some(res)
+            }
+
             Ok(Ic00Method::BitcoinSendTransaction) => {
                 let cycles = msg.take_cycles();
                 let res =
@@ -967,17 +1139,27 @@ impl ExecutionEnvironment {
         )
     }
 
-    /// Executes a heartbeat of a given canister.
-    pub fn execute_canister_heartbeat(
+    /// Executes a system task (`canister_heartbeat` or `canister_global_timer`)
+    /// of a given canister.
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_canister_system_task(
         &self,
-        canister: CanisterState,
+        system_task: SystemMethod,
+        mut canister: CanisterState,
         instruction_limits: InstructionLimits,
         network_topology: Arc<NetworkTopology>,
         time: Time,
         round_limits: &mut RoundLimits,
         subnet_size: usize,
     ) -> (CanisterState, Result<NumBytes, CanisterHeartbeatError>) {
-        // A heartbeat is expected to finish quickly, so DTS is not supported for it.
+        if system_task == SystemMethod::CanisterGlobalTimer {
+            // The timer is deactivated before the execution, so that the
+            // canister can set a new timer in `canister_global_timer`.
+            canister.system_state.global_timer = CanisterTimer::Inactive;
+        }
+        // A system task is expected to finish quickly, so DTS is not supported
+        // for it. With DTS enabled, it must finish within a single slice, e.g.
+        // 2B instructions on application subnets.
         let instruction_limits = InstructionLimits::new(
             FlagStatus::Disabled,
             instruction_limits.slice(),
@@ -986,6 +1168,7 @@ impl ExecutionEnvironment {
         let execution_parameters =
             self.execution_parameters(&canister, instruction_limits, ExecutionMode::Replicated);
         let (canister, result) = execute_heartbeat(
+            system_task.clone(),
             canister,
             network_topology,
             execution_parameters,
@@ -1006,15 +1189,23 @@ impl ExecutionEnvironment {
                 if log_count < LOG_FIRST_N_HEARTBEAT || log_count % LOG_ONE_HEARTBEAT_OUT_OF == 0 {
                     warn!(
                         self.log,
-                        "Error executing heartbeat on canister {} with failure `{}`",
+                        "Error executing {} on canister {} with failure `{}`",
+                        system_task,
                         canister.canister_id(),
                         err;
                         messaging.canister_id => canister.canister_id().to_string(),
                     );
                 }
-                self.metrics
-                    .execution_round_failed_heartbeat_executions
-                    .inc();
+                match system_task {
+                    SystemMethod::CanisterGlobalTimer => self
+                        .metrics
+                        .execution_round_failed_global_timer_executions
+                        .inc(),
+                    _ => self
+                        .metrics
+                        .execution_round_failed_heartbeat_executions
+                        .inc(),
+                }
             }
         }
         (canister, result)
@@ -1080,18 +1271,20 @@ impl ExecutionEnvironment {
 
     fn update_settings(
         &self,
-        sender: PrincipalId,
+        origin: CanisterChangeOrigin,
         settings: CanisterSettings,
         canister_id: CanisterId,
         state: &mut ReplicatedState,
     ) -> Result<Vec<u8>, UserError> {
+        let time = state.time();
         let compute_allocation_used = state.total_compute_allocation();
         let memory_allocation_used = state.total_memory_taken();
 
         let canister = get_canister_mut(canister_id, state)?;
         self.canister_manager
             .update_settings(
-                sender,
+                time,
+                origin,
                 settings,
                 canister,
                 compute_allocation_used,
@@ -1689,6 +1882,21 @@ impl ExecutionEnvironment {
             state: &mut ReplicatedState,
         ) -> Result<(InstallCodeContext, CanisterState), UserError> {
             let payload = msg.method_payload();
+            if let Ok(Ic00Method::InstallChunkedCode) = Ic00Method::from_str(msg.method_name()) {
+                // The module is assembled from the chunk store of the target
+                // canister.
+                let args =
+                    InstallChunkedCodeArgs::decode(payload).map_err(candid_error_to_user_error)?;
+                let canister_id = args.get_canister_id();
+                let install_context = match state.canister_state(&canister_id) {
+                    Some(canister) => {
+                        InstallCodeContext::from_chunked_code(*msg.sender(), args, canister)?
+                    }
+                    None => return Err(CanisterManagerError::CanisterNotFound(canister_id).into()),
+                };
+                let canister = state.take_canister_state(&canister_id).unwrap();
+                return Ok((install_context, canister));
+            }
             let args = InstallCodeArgs::decode(payload).map_err(candid_error_to_user_error)?;
             let install_context = InstallCodeContext::try_from((*msg.sender(), args))?;
             let canister = state
@@ -1861,6 +2069,7 @@ impl ExecutionEnvironment {
             .unwrap();
         match task {
             ExecutionTask::Heartbeat
+            | ExecutionTask::GlobalTimer
             | ExecutionTask::PausedExecution(_)
             | ExecutionTask::AbortedExecution(_) => {
                 panic!(
@@ -1934,7 +2143,8 @@ impl ExecutionEnvironment {
                     .map(|task| match task {
                         ExecutionTask::AbortedExecution(..)
                         | ExecutionTask::AbortedInstallCode(..)
-                        | ExecutionTask::Heartbeat => task,
+                        | ExecutionTask::Heartbeat
+                        | ExecutionTask::GlobalTimer => task,
                         ExecutionTask::PausedExecution(id) => {
                             let paused = self.take_paused_execution(id).unwrap();
                             let message = paused.abort();
@@ -2122,7 +2332,8 @@ pub fn execute_canister(
     match canister.system_state.task_queue.pop_front() {
         Some(task) => match task {
             ExecutionTask::Heartbeat => {
-                let (canister, result) = exec_env.execute_canister_heartbeat(
+                let (canister, result) = exec_env.execute_canister_system_task(
+                    SystemMethod::CanisterHeartbeat,
                     canister,
                     instruction_limits,
                     network_topology,
@@ -2138,6 +2349,24 @@ pub fn execute_canister(
                     description: Some("heartbeat".to_string()),
                 }
             }
+            ExecutionTask::GlobalTimer => {
+                let (canister, result) = exec_env.execute_canister_system_task(
+                    SystemMethod::CanisterGlobalTimer,
+                    canister,
+                    instruction_limits,
+                    network_topology,
+                    time,
+                    round_limits,
+                    subnet_size,
+                );
+                let heap_delta = result.unwrap_or_else(|_| NumBytes::from(0));
+                ExecuteCanisterResult {
+                    canister,
+                    heap_delta,
+                    ingress_status: None,
+                    description: Some("global timer".to_string()),
+                }
+            }
             ExecutionTask::PausedExecution(id) => {
                 let paused = exec_env.take_paused_execution(id).unwrap();
                 let round_context = RoundContext {
@@ -14,6 +14,8 @@ pub(crate) struct ExecutionEnvironmentMetrics {
     response_cycles_refund_error: IntCounter,
 
     pub execution_round_failed_heartbeat_executions: IntCounter,
+
+    pub execution_round_failed_global_timer_executions: IntCounter,
 }
 
 impl ExecutionEnvironmentMetrics {
@@ -35,6 +37,10 @@ impl ExecutionEnvironmentMetrics {
                 "execution_round_failed_heartbeat_executions",
                 "Total number of heartbeat executions that resulted in an error",
             ),
+            execution_round_failed_global_timer_executions: metrics_registry.int_counter(
+                "execution_round_failed_global_timer_executions",
+                "Total number of global timer executions that resulted in an error",
+            ),
         }
     }
 
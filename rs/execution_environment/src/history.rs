@@ -258,6 +258,7 @@ fn dashboard_label_value_from(code: ErrorCode) -> &'static str {
         CanisterWasmModuleNotFound => "Canister WASM Module Not Found",
         CanisterNonEmpty => "Canister Non-Empty",
         CanisterEmpty => "Canister Empty",
+        CanisterSnapshotNotFound => "Canister Snapshot Not Found",
         CanisterOutOfCycles => "Canister Out Of Cycles",
         CanisterTrapped => "Canister Trapped",
         CanisterCalledTrap => "Canister Called Trap",
@@ -288,5 +289,13 @@ fn dashboard_label_value_from(code: ErrorCode) -> &'static str {
         CanisterInstallCodeRateLimited => {
             "Canister is rate limited because it executed too many instructions in the previous install_code messages"
         }
+        CompositeQueryCalledInReplicatedMode => {
+            "Composite query cannot be called in replicated mode"
+        }
+        QueryCallGraphTooDeep => "Composite query call graph is too deep",
+        QueryCallGraphTotalInstructionLimitExceeded => {
+            "Composite query call graph exceeded the instruction limit"
+        }
+        CanisterSnapshotLimitExceeded => "Canister has reached the maximum number of snapshots",
     }
 }
@@ -325,7 +325,7 @@ impl Hypervisor {
                 (slice, output, system_state_changes)
             }
             WasmExecutionResult::Paused(_, _) => {
-                unreachable!("DTS is not supported");
+                unreachable!("DTS is not supported by `execute()`");
             }
         };
         update_round_limits(round_limits, &slice);
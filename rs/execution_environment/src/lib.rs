@@ -39,7 +39,9 @@ use ic_replicated_state::{CallOrigin, NetworkTopology, ReplicatedState};
 use ic_types::{messages::CallContextId, SubnetId};
 use ingress_filter::IngressFilter;
 use query_handler::HttpQueryHandler;
-pub use query_handler::InternalHttpQueryHandler;
+pub use query_handler::{
+    InternalHttpQueryHandler, QueryStatsCollector, QueryStatsPayloadBuilderImpl,
+};
 use scheduler::SchedulerImpl;
 use std::sync::{Arc, Mutex};
 use tower::limit::GlobalConcurrencyLimitLayer;
@@ -83,6 +85,7 @@ pub struct ExecutionServices {
     pub ingress_history_writer: Arc<dyn IngressHistoryWriter<State = ReplicatedState>>,
     pub ingress_history_reader: Box<dyn IngressHistoryReader>,
     pub sync_query_handler: Arc<dyn QueryHandler<State = ReplicatedState>>,
+    pub query_stats_collector: Arc<QueryStatsCollector>,
     pub async_query_handler: QueryExecutionService,
     pub anonymous_query_handler: AnonymousQueryService,
     pub scheduler: Box<dyn Scheduler<State = ReplicatedState>>,
@@ -130,14 +133,16 @@ impl ExecutionServices {
             config.clone(),
             Arc::clone(&cycles_account_manager),
         ));
+        let query_stats_collector = Arc::new(QueryStatsCollector::new());
         let sync_query_handler = Arc::new(InternalHttpQueryHandler::new(
             logger.clone(),
             hypervisor,
             own_subnet_type,
             config.clone(),
             metrics_registry,
-            scheduler_config.max_instructions_per_message,
+            scheduler_config.max_instructions_per_message_without_dts,
             Arc::clone(&cycles_account_manager),
+            Arc::clone(&query_stats_collector),
         ));
         let threadpool = threadpool::Builder::new()
             .num_threads(config.query_execution_threads)
@@ -157,6 +162,7 @@ impl ExecutionServices {
             Arc::clone(&sync_query_handler) as Arc<_>,
             Arc::clone(&threadpool),
             Arc::clone(&state_reader),
+            Arc::clone(&query_stats_collector),
         );
         let ingress_filter = IngressFilter::new_service(
             concurrency_buffer.clone(),
@@ -169,7 +175,7 @@ impl ExecutionServices {
             threadpool,
             Arc::clone(&state_reader),
             Arc::clone(&exec_env),
-            scheduler_config.max_instructions_per_message,
+            scheduler_config.max_instructions_per_message_without_dts,
         );
 
         let bitcoin_canister = Arc::new(BitcoinCanister::new(metrics_registry, logger.clone()));
@@ -193,6 +199,7 @@ impl ExecutionServices {
             ingress_history_writer,
             ingress_history_reader,
             sync_query_handler,
+            query_stats_collector,
             async_query_handler,
             anonymous_query_handler,
             scheduler,
@@ -207,8 +207,11 @@ pub fn duration_histogram<S: Into<String>>(
 fn instructions_buckets() -> Vec<f64> {
     fn add_limits(buckets: &mut Vec<NumInstructions>, config: SchedulerConfig) {
         buckets.push(config.max_instructions_per_message);
+        buckets.push(config.max_instructions_per_message_without_dts);
+        buckets.push(config.max_instructions_per_slice);
         buckets.push(config.max_instructions_per_round);
         buckets.push(config.max_instructions_per_install_code);
+        buckets.push(config.max_instructions_per_install_code_slice);
     }
     let mut buckets: Vec<NumInstructions> = decimal_buckets_with_zero(4, 10)
         .into_iter()
@@ -3,10 +3,12 @@
 
 mod query_allocations;
 mod query_context;
+mod query_stats;
 #[cfg(test)]
 mod tests;
 
 use crate::execution_environment::subnet_memory_capacity;
+use crate::util::candid_error_to_user_error;
 use crate::{
     hypervisor::Hypervisor,
     metrics::{MeasurementScope, QueryHandlerMetrics},
@@ -15,6 +17,10 @@ use ic_config::execution_environment::Config;
 use ic_crypto_tree_hash::{flatmap, Label, LabeledTree, LabeledTree::SubTree};
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, RejectCode, UserError};
+use ic_ic00_types::{
+    CanisterIdRecord, FetchCanisterLogsResponse, LogVisibility, Method as Ic00Method, Payload,
+    IC_00,
+};
 use ic_interfaces::execution_environment::{QueryExecutionService, QueryHandler};
 use ic_interfaces_state_manager::StateReader;
 use ic_logger::ReplicaLogger;
@@ -22,19 +28,22 @@ use ic_metrics::MetricsRegistry;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::ReplicatedState;
 use ic_types::{
+    batch::QueryStats,
     ingress::WasmResult,
     messages::{
         Blob, Certificate, CertificateDelegation, HttpQueryResponse, HttpQueryResponseReply,
         UserQuery,
     },
-    CanisterId, NumInstructions,
+    CanisterId, Height, NumInstructions, PrincipalId,
 };
 use query_allocations::QueryAllocationsUsed;
+pub use query_stats::{QueryStatsCollector, QueryStatsPayloadBuilderImpl};
 use serde::Serialize;
 use std::{
     convert::Infallible,
     future::Future,
     pin::Pin,
+    str::FromStr,
     sync::{Arc, Mutex, RwLock},
     task::{Context, Poll},
 };
@@ -49,11 +58,13 @@ fn into_cbor<R: Serialize>(r: &R) -> Vec<u8> {
     ser.into_inner()
 }
 
+/// Returns the latest certified state along with its height and the data
+/// certificate of `canister_id`.
 fn get_latest_certified_state_and_data_certificate(
     state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
     certificate_delegation: Option<CertificateDelegation>,
     canister_id: CanisterId,
-) -> Option<(Arc<ReplicatedState>, Vec<u8>)> {
+) -> Option<(Arc<ReplicatedState>, Height, Vec<u8>)> {
     // The path to fetch the data certificate for the canister.
     let path = SubTree(flatmap! {
         label("canister") => SubTree(
@@ -73,6 +84,7 @@ fn get_latest_certified_state_and_data_certificate(
         .map(|(state, tree, cert)| {
             (
                 state,
+                cert.height,
                 into_cbor(&Certificate {
                     tree,
                     signature: Blob(cert.signed.signature.signature.get().0),
@@ -95,6 +107,7 @@ pub struct InternalHttpQueryHandler {
     metrics: QueryHandlerMetrics,
     max_instructions_per_message: NumInstructions,
     cycles_account_manager: Arc<CyclesAccountManager>,
+    query_stats_collector: Arc<QueryStatsCollector>,
 }
 
 #[derive(Clone)]
@@ -103,6 +116,7 @@ pub(crate) struct HttpQueryHandler {
     internal: Arc<dyn QueryHandler<State = ReplicatedState>>,
     state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
     threadpool: Arc<Mutex<threadpool::ThreadPool>>,
+    query_stats_collector: Arc<QueryStatsCollector>,
 }
 
 impl InternalHttpQueryHandler {
@@ -114,6 +128,7 @@ impl InternalHttpQueryHandler {
         metrics_registry: &MetricsRegistry,
         max_instructions_per_message: NumInstructions,
         cycles_account_manager: Arc<CyclesAccountManager>,
+        query_stats_collector: Arc<QueryStatsCollector>,
     ) -> Self {
         Self {
             log,
@@ -124,6 +139,7 @@ impl InternalHttpQueryHandler {
             metrics: QueryHandlerMetrics::new(metrics_registry),
             max_instructions_per_message,
             cycles_account_manager,
+            query_stats_collector,
         }
     }
 }
@@ -138,6 +154,10 @@ impl QueryHandler for InternalHttpQueryHandler {
         data_certificate: Vec<u8>,
     ) -> Result<WasmResult, UserError> {
         let measurement_scope = MeasurementScope::root(&self.metrics.query);
+        if query.receiver == IC_00 {
+            return query_management_canister(query, &state);
+        }
+
         // Note that This assumes that the QueryHandler is always called with the
         // "latest" state.  If and when we start supporting queries against older
         // versions of the state, we will need the caller of the QueryHandler to
@@ -163,14 +183,103 @@ impl QueryHandler for InternalHttpQueryHandler {
             subnet_available_memory,
             max_canister_memory_size,
             self.max_instructions_per_message,
+            self.config.max_query_call_graph_depth,
+            self.config.max_query_call_graph_instructions,
         );
-        context.run(
+        let canister_id = query.receiver;
+        let ingress_payload_size = query.method_payload.len() as u64;
+        let result = context.run(
             query,
             &self.metrics,
             Arc::clone(&self.cycles_account_manager),
             &measurement_scope,
+        );
+
+        // Only the queries that were executed by the canister are counted. A
+        // query that failed, e.g. because the canister does not exist, trapped
+        // or ran out of cycles, returns an error.
+        if let Ok(wasm_result) = &result {
+            let egress_payload_size = match wasm_result {
+                WasmResult::Reply(reply) => reply.len(),
+                WasmResult::Reject(message) => message.len(),
+            } as u64;
+            self.query_stats_collector.register_query_statistics(
+                canister_id,
+                &QueryStats {
+                    num_calls: 1,
+                    num_instructions: context.instructions_executed().get(),
+                    ingress_payload_size,
+                    egress_payload_size,
+                },
+            );
+        }
+        result
+    }
+}
+
+/// Executes a query call to the management canister. Only the methods that
+/// read the replicated state without executing any canister code can be
+/// called this way.
+fn query_management_canister(
+    query: UserQuery,
+    state: &ReplicatedState,
+) -> Result<WasmResult, UserError> {
+    match Ic00Method::from_str(&query.method_name) {
+        Ok(Ic00Method::FetchCanisterLogs) => {
+            let args = CanisterIdRecord::decode(&query.method_payload)
+                .map_err(candid_error_to_user_error)?;
+            let response = fetch_canister_logs(query.source.get(), state, args.get_canister_id())?;
+            Ok(WasmResult::Reply(response.encode()))
+        }
+        _ => Err(UserError::new(
+            ErrorCode::CanisterMethodNotFound,
+            format!(
+                "Query method {} not found in the management canister.",
+                query.method_name
+            ),
+        )),
+    }
+}
+
+/// Returns the log records of the given canister if `sender` is allowed to
+/// see them according to the log visibility of the canister.
+fn fetch_canister_logs(
+    sender: PrincipalId,
+    state: &ReplicatedState,
+    canister_id: CanisterId,
+) -> Result<FetchCanisterLogsResponse, UserError> {
+    let canister = state.canister_state(&canister_id).ok_or_else(|| {
+        UserError::new(
+            ErrorCode::CanisterNotFound,
+            format!("Canister {} not found", canister_id),
         )
+    })?;
+
+    match canister.system_state.log_visibility {
+        LogVisibility::Public => {}
+        LogVisibility::Controllers => {
+            if !canister.controllers().contains(&sender) {
+                return Err(UserError::new(
+                    ErrorCode::CanisterInvalidController,
+                    format!(
+                        "Caller {} is not allowed to query ic00 method {}",
+                        sender,
+                        Ic00Method::FetchCanisterLogs
+                    ),
+                ));
+            }
+        }
     }
+
+    Ok(FetchCanisterLogsResponse {
+        canister_log_records: canister
+            .system_state
+            .canister_log
+            .records()
+            .iter()
+            .cloned()
+            .collect(),
+    })
 }
 
 impl HttpQueryHandler {
@@ -179,11 +288,13 @@ impl HttpQueryHandler {
         internal: Arc<dyn QueryHandler<State = ReplicatedState>>,
         threadpool: Arc<Mutex<threadpool::ThreadPool>>,
         state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+        query_stats_collector: Arc<QueryStatsCollector>,
     ) -> QueryExecutionService {
         let base_service = BoxCloneService::new(Self {
             internal,
             state_reader,
             threadpool,
+            query_stats_collector,
         });
         ServiceBuilder::new()
             .layer(concurrency_buffer)
@@ -220,6 +331,7 @@ impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
     ) -> Self::Future {
         let internal = Arc::clone(&self.internal);
         let state_reader = Arc::clone(&self.state_reader);
+        let query_stats_collector = Arc::clone(&self.query_stats_collector);
         let (tx, rx) = oneshot::channel();
         let threadpool = self.threadpool.lock().unwrap().clone();
         threadpool.execute(move || {
@@ -232,7 +344,12 @@ impl Service<(UserQuery, Option<CertificateDelegation>)> for HttpQueryHandler {
                     certificate_delegation,
                     query.receiver,
                 ) {
-                    Some((state, cert)) => internal.query(query, state, cert),
+                    Some((state, height, cert)) => {
+                        // The statistics of the query belong to the epoch of
+                        // the certified state it executes against.
+                        query_stats_collector.set_epoch_from_height(height);
+                        internal.query(query, state, cert)
+                    }
                     None => Err(UserError::new(
                         ErrorCode::CertifiedStateUnavailable,
                         "Certified state is not available yet. Please try again...",
@@ -1,5 +1,8 @@
-//! This module implements an MVP version of inter-canister queries. This
-//! implementation has the following restrictions:
+//! This module implements inter-canister queries. A canister can call other
+//! canisters from a `canister_composite_query` method. On system and verified
+//! application subnets, plain `canister_query` methods are also allowed to
+//! call other canisters for backwards compatibility. This implementation has
+//! the following restrictions:
 //!
 //! - A canister can only query other canisters on the same subnet.
 //!
@@ -10,6 +13,12 @@
 //! - Loops are not allowed. E.g. call graphs like A -> B -> C -> A are not
 //! supported.
 //!
+//! - The depth of the call graph is limited. A call that would exceed the
+//! limit is rejected and the caller can handle the reject.
+//!
+//! - The total number of instructions executed by all messages in the call
+//! graph is limited. Exceeding the limit fails the whole query.
+//!
 //! Some interesting factoids about inter-canister query execution to keep in
 //! mind:
 //!
@@ -34,12 +43,17 @@
 //!
 //! - For a lack of a better strategy, always prioritise responses over
 //! requests.
+//!
+//! - Due to the points above, the canisters stored in the query context are
+//! exactly the callers on the path from the user query to the request that is
+//! being executed. This is used both for loop detection and for computing the
+//! depth of the call graph.
 
 use super::query_allocations::QueryAllocationsUsed;
 use crate::{
     execution::common,
     execution::nonreplicated_query::execute_non_replicated_query,
-    execution_environment::{as_round_instructions, RoundLimits},
+    execution_environment::{as_num_instructions, as_round_instructions, RoundLimits},
     hypervisor::Hypervisor,
     metrics::{MeasurementScope, QueryHandlerMetrics},
     NonReplicatedQueryKind,
@@ -74,6 +88,11 @@ const ENABLE_QUERY_OPTIMIZATION: bool = true;
 const LOOP_DETECTED_ERROR_MSG: &str =
     "Loop detected.  MVP inter-canister queries do not support loops.";
 
+const CALL_GRAPH_TOO_DEEP_ERROR_MSG: &str = "Composite query call graph depth exceeded";
+
+const CALL_GRAPH_INSTRUCTION_LIMIT_EXCEEDED_ERROR_MSG: &str =
+    "Composite query call graph exceeded the total instruction limit";
+
 /// A simple enum representing the different things that
 /// QueryContext::enqueue_requests() can return.
 enum EnqueueRequestsResult {
@@ -116,6 +135,10 @@ pub(super) struct QueryContext<'a> {
     query_allocations_used: Arc<RwLock<QueryAllocationsUsed>>,
     max_canister_memory_size: NumBytes,
     max_instructions_per_message: NumInstructions,
+    max_query_call_graph_depth: usize,
+    max_query_call_graph_instructions: NumInstructions,
+    // The remaining instructions of the whole call graph are tracked in
+    // `round_limits.instructions`.
     round_limits: RoundLimits,
 }
 
@@ -131,10 +154,12 @@ impl<'a> QueryContext<'a> {
         subnet_available_memory: SubnetAvailableMemory,
         max_canister_memory_size: NumBytes,
         max_instructions_per_message: NumInstructions,
+        max_query_call_graph_depth: usize,
+        max_query_call_graph_instructions: NumInstructions,
     ) -> Self {
         let network_topology = Arc::new(state.metadata.network_topology.clone());
         let round_limits = RoundLimits {
-            instructions: as_round_instructions(max_instructions_per_message),
+            instructions: as_round_instructions(max_query_call_graph_instructions),
             subnet_available_memory,
         };
         Self {
@@ -150,10 +175,18 @@ impl<'a> QueryContext<'a> {
             network_topology,
             max_canister_memory_size,
             max_instructions_per_message,
+            max_query_call_graph_depth,
+            max_query_call_graph_instructions,
             round_limits,
         }
     }
 
+    /// Returns the number of instructions executed by all messages in the
+    /// call graph so far.
+    pub(super) fn instructions_executed(&self) -> NumInstructions {
+        self.max_query_call_graph_instructions - self.call_graph_instructions_left()
+    }
+
     /// Executes the given Query sent by an end user.
     ///
     /// - If it produces a response return the response.
@@ -194,12 +227,14 @@ impl<'a> QueryContext<'a> {
         }
 
         let call_origin = CallOrigin::Query(query.source);
-        // EXC-500: Contain the usage of inter-canister query calls to the subnets
-        // that currently use it until we decide on the future of this feature and
-        // get a proper spec for it.
-        let cross_canister_query_calls_enabled = self.own_subnet_type == SubnetType::System
-            || self.own_subnet_type == SubnetType::VerifiedApplication;
-        let try_pure_query = ENABLE_QUERY_OPTIMIZATION || !cross_canister_query_calls_enabled;
+        // Composite queries always call other canisters, so there is no point
+        // in trying to execute them as `Pure` first.
+        let is_composite_query =
+            old_canister.exports_composite_query_method(query.method_name.clone());
+        let cross_canister_query_calls_enabled =
+            is_composite_query || self.query_method_calls_enabled();
+        let try_pure_query = !is_composite_query
+            && (ENABLE_QUERY_OPTIMIZATION || !cross_canister_query_calls_enabled);
         let query_kind = if try_pure_query {
             NonReplicatedQueryKind::Pure {
                 caller: query.source.get(),
@@ -289,6 +324,15 @@ impl<'a> QueryContext<'a> {
         let measurement_scope =
             MeasurementScope::nested(&metrics.query_spawned_calls, measurement_scope);
         loop {
+            // All messages in the call graph share the same instruction
+            // budget. Once it is used up, the whole query fails.
+            if self.call_graph_instructions_left() == NumInstructions::from(0) {
+                return Err(UserError::new(
+                    ErrorCode::QueryCallGraphTotalInstructionLimitExceeded,
+                    CALL_GRAPH_INSTRUCTION_LIMIT_EXCEEDED_ERROR_MSG.to_string(),
+                ));
+            }
+
             if let Some(response) = self.outstanding_response.take() {
                 debug!(self.log, "Executing response for {}", response.originator);
                 // Any result returned by `handle_response` is a query context
@@ -364,7 +408,7 @@ impl<'a> QueryContext<'a> {
                         // module so must have existed on the canister's output
                         // queue from before.
                         CallOrigin::CanisterUpdate(_, _)
-                        | CallOrigin::Heartbeat
+                        | CallOrigin::SystemTask
                         | CallOrigin::Ingress(_, _) => continue,
 
                         // We never serialize messages of such types in the
@@ -407,13 +451,7 @@ impl<'a> QueryContext<'a> {
         query_kind: NonReplicatedQueryKind,
         measurement_scope: &MeasurementScope,
     ) -> (CanisterState, Result<Option<WasmResult>, UserError>) {
-        let instruction_limit = self.max_instructions_per_message.min(
-            self.query_allocations_used
-                .write()
-                .unwrap()
-                .allocation_before_execution(&canister.canister_id())
-                .into(),
-        );
+        let instruction_limit = self.instruction_limit(&canister.canister_id());
         let instruction_limits =
             InstructionLimits::new(FlagStatus::Disabled, instruction_limit, instruction_limit);
         let execution_parameters = self.execution_parameters(&canister, instruction_limits);
@@ -481,7 +519,7 @@ impl<'a> QueryContext<'a> {
         let func_ref = match call_origin {
             CallOrigin::Ingress(_, _)
             | CallOrigin::CanisterUpdate(_, _)
-            | CallOrigin::Heartbeat => unreachable!("Unreachable in the QueryContext."),
+            | CallOrigin::SystemTask => unreachable!("Unreachable in the QueryContext."),
             CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => {
                 FuncRef::QueryClosure(closure)
             }
@@ -491,13 +529,7 @@ impl<'a> QueryContext<'a> {
         // No cycles are refunded in a response to a query call.
         let incoming_cycles = Cycles::zero();
 
-        let instruction_limit = self.max_instructions_per_message.min(
-            self.query_allocations_used
-                .write()
-                .unwrap()
-                .allocation_before_execution(&canister_id)
-                .into(),
-        );
+        let instruction_limit = self.instruction_limit(&canister_id);
         let instruction_limits =
             InstructionLimits::new(FlagStatus::Disabled, instruction_limit, instruction_limit);
         let mut execution_parameters = self.execution_parameters(&canister, instruction_limits);
@@ -602,7 +634,7 @@ impl<'a> QueryContext<'a> {
         let func_ref = match call_origin {
             CallOrigin::Ingress(_, _)
             | CallOrigin::CanisterUpdate(_, _)
-            | CallOrigin::Heartbeat => unreachable!("Unreachable in the QueryContext."),
+            | CallOrigin::SystemTask => unreachable!("Unreachable in the QueryContext."),
             CallOrigin::CanisterQuery(_, _) | CallOrigin::Query(_) => {
                 FuncRef::QueryClosure(cleanup_closure)
             }
@@ -675,12 +707,39 @@ impl<'a> QueryContext<'a> {
             }
         };
 
-        let call_origin = CallOrigin::CanisterQuery(request.sender, request.sender_reply_callback);
+        // The canisters in the query context are the callers on the path to
+        // this request, so the callee would be one level deeper.
+        if self.canisters.len() + 1 > self.max_query_call_graph_depth {
+            let error = UserError::new(
+                ErrorCode::QueryCallGraphTooDeep,
+                CALL_GRAPH_TOO_DEEP_ERROR_MSG.to_string(),
+            );
+            let payload = Payload::Reject(RejectContext::from(error));
+            self.outstanding_response = Some(generate_response(request, payload));
+            return None;
+        }
+
+        // Only composite queries (and plain queries on subnets that allow
+        // them to call other canisters) need to keep track of the state.
+        let query_kind = if canister.exports_composite_query_method(request.method_name.clone())
+            || self.query_method_calls_enabled()
+        {
+            NonReplicatedQueryKind::Stateful {
+                call_origin: CallOrigin::CanisterQuery(
+                    request.sender,
+                    request.sender_reply_callback,
+                ),
+            }
+        } else {
+            NonReplicatedQueryKind::Pure {
+                caller: request.sender.get(),
+            }
+        };
         let (mut canister, result) = self.execute_query(
             canister,
             request.method_name.as_str(),
             request.method_payload.as_slice(),
-            NonReplicatedQueryKind::Stateful { call_origin },
+            query_kind,
             measurement_scope,
         );
 
@@ -971,7 +1030,7 @@ impl<'a> QueryContext<'a> {
 
             CallOrigin::CanisterUpdate(_, _)
             | CallOrigin::Ingress(_, _)
-            | CallOrigin::Heartbeat => fatal!(
+            | CallOrigin::SystemTask => fatal!(
                 self.log,
                 "Canister {}: query path should not have created a callback with an update origin",
                 canister_id
@@ -983,6 +1042,35 @@ impl<'a> QueryContext<'a> {
         }
     }
 
+    // EXC-500: Contain the usage of inter-canister query calls from plain
+    // query methods to the subnets that currently use it. Composite queries
+    // are allowed to call other canisters on all subnets.
+    fn query_method_calls_enabled(&self) -> bool {
+        self.own_subnet_type == SubnetType::System
+            || self.own_subnet_type == SubnetType::VerifiedApplication
+    }
+
+    // The number of instructions that all remaining executions in the call
+    // graph may use.
+    fn call_graph_instructions_left(&self) -> NumInstructions {
+        as_num_instructions(self.round_limits.instructions)
+    }
+
+    // Returns the instruction limit for a single execution on the given
+    // canister: the per-message limit capped by the query allocation of the
+    // canister and by the remaining instructions of the call graph.
+    fn instruction_limit(&self, canister_id: &CanisterId) -> NumInstructions {
+        let query_allocation: NumInstructions = self
+            .query_allocations_used
+            .write()
+            .unwrap()
+            .allocation_before_execution(canister_id)
+            .into();
+        self.max_instructions_per_message
+            .min(query_allocation)
+            .min(self.call_graph_instructions_left())
+    }
+
     fn execution_parameters(
         &self,
         canister: &CanisterState,
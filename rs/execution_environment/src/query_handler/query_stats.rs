@@ -0,0 +1,316 @@
+//! Collection of the statistics of non-replicated queries and the payload
+//! builder that gossips them to the other replicas of the subnet.
+//!
+//! Every replica keeps per-canister statistics of the queries it executes
+//! during a query stats epoch. Once the epoch is over, the statistics are
+//! proposed for inclusion in a block and are aggregated in the replicated
+//! state, see `ReplicatedState::apply_query_stats()`.
+
+use ic_interfaces::{
+    query_stats::{
+        InvalidQueryStatsPayload, QueryStatsPayloadBuilder, QueryStatsPayloadValidationError,
+        QueryStatsTransientValidationError,
+    },
+    validation::ValidationError,
+};
+use ic_interfaces_state_manager::StateReader;
+use ic_logger::{warn, ReplicaLogger};
+use ic_replicated_state::ReplicatedState;
+use ic_types::{
+    batch::{
+        epoch_from_height, CanisterQueryStats, QueryStats, QueryStatsEpoch, QueryStatsPayload,
+        ValidationContext, MAX_QUERY_STATS_PAYLOAD_SIZE,
+    },
+    CanisterId, CountBytes, Height, NodeId, NumBytes,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+#[derive(Default)]
+struct CollectorState {
+    /// The epoch that newly registered statistics belong to.
+    current_epoch: Option<QueryStatsEpoch>,
+    current_stats: BTreeMap<CanisterId, QueryStats>,
+    /// The statistics of the last completed epoch.
+    completed: Option<(QueryStatsEpoch, BTreeMap<CanisterId, QueryStats>)>,
+}
+
+/// Collects the statistics of the non-replicated queries executed by this
+/// replica.
+#[derive(Default)]
+pub struct QueryStatsCollector {
+    state: Mutex<CollectorState>,
+}
+
+impl QueryStatsCollector {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Moves the collector to the epoch of the given certified height. The
+    /// statistics of the current epoch become the completed ones if the epoch
+    /// changes.
+    pub fn set_epoch_from_height(&self, height: Height) {
+        let epoch = epoch_from_height(height);
+        let mut state = self.state.lock().unwrap();
+        match state.current_epoch {
+            Some(current_epoch) if epoch <= current_epoch => {}
+            Some(current_epoch) => {
+                let stats = std::mem::take(&mut state.current_stats);
+                state.completed = Some((current_epoch, stats));
+                state.current_epoch = Some(epoch);
+            }
+            None => state.current_epoch = Some(epoch),
+        }
+    }
+
+    /// Adds the statistics of a single query executed on `canister_id`.
+    pub fn register_query_statistics(&self, canister_id: CanisterId, stats: &QueryStats) {
+        self.state
+            .lock()
+            .unwrap()
+            .current_stats
+            .entry(canister_id)
+            .or_default()
+            .saturating_accumulate(stats);
+    }
+
+    /// Returns the statistics of the last completed epoch.
+    fn completed_epoch(&self) -> Option<(QueryStatsEpoch, Vec<CanisterQueryStats>)> {
+        let state = self.state.lock().unwrap();
+        state.completed.as_ref().map(|(epoch, stats)| {
+            (
+                *epoch,
+                stats
+                    .iter()
+                    .map(|(canister_id, stats)| CanisterQueryStats {
+                        canister_id: *canister_id,
+                        stats: *stats,
+                    })
+                    .collect(),
+            )
+        })
+    }
+}
+
+/// Builds and validates the `QueryStatsPayload` section of blocks.
+pub struct QueryStatsPayloadBuilderImpl {
+    collector: Arc<QueryStatsCollector>,
+    state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+    node_id: NodeId,
+    log: ReplicaLogger,
+}
+
+impl QueryStatsPayloadBuilderImpl {
+    pub fn new(
+        collector: Arc<QueryStatsCollector>,
+        state_reader: Arc<dyn StateReader<State = ReplicatedState>>,
+        node_id: NodeId,
+        log: ReplicaLogger,
+    ) -> Self {
+        Self {
+            collector,
+            state_reader,
+            node_id,
+            log,
+        }
+    }
+
+    /// Returns true if statistics for an epoch later than `epoch` are already
+    /// part of the given state or of `past_payloads`. The aggregation ignores
+    /// the statistics of `epoch` in that case.
+    fn is_stale(
+        state: &ReplicatedState,
+        past_payloads: &[&QueryStatsPayload],
+        epoch: QueryStatsEpoch,
+    ) -> bool {
+        past_payloads
+            .iter()
+            .map(|payload| payload.epoch)
+            .chain(state.metadata.epoch_query_stats.epoch)
+            .any(|delivered_epoch| delivered_epoch > epoch)
+    }
+
+    /// Returns true if the statistics of `proposer` for `epoch` are already
+    /// part of the given state or of `past_payloads`.
+    fn is_delivered(
+        state: &ReplicatedState,
+        past_payloads: &[&QueryStatsPayload],
+        proposer: NodeId,
+        epoch: QueryStatsEpoch,
+    ) -> bool {
+        let epoch_query_stats = &state.metadata.epoch_query_stats;
+        let in_state = epoch_query_stats.epoch == Some(epoch)
+            && epoch_query_stats.proposers.contains(&proposer);
+        in_state
+            || past_payloads
+                .iter()
+                .any(|payload| payload.proposer == proposer && payload.epoch == epoch)
+    }
+}
+
+impl QueryStatsPayloadBuilder for QueryStatsPayloadBuilderImpl {
+    fn get_query_stats_payload(
+        &self,
+        validation_context: &ValidationContext,
+        past_payloads: &[&QueryStatsPayload],
+        byte_limit: NumBytes,
+    ) -> Option<QueryStatsPayload> {
+        self.collector
+            .set_epoch_from_height(validation_context.certified_height);
+        let (epoch, stats) = self.collector.completed_epoch()?;
+        if stats.is_empty() {
+            return None;
+        }
+
+        let state = match self
+            .state_reader
+            .get_state_at(validation_context.certified_height)
+        {
+            Ok(state) => state,
+            Err(err) => {
+                warn!(
+                    self.log,
+                    "Failed to get state at height {} for the query stats payload: {:?}",
+                    validation_context.certified_height,
+                    err
+                );
+                return None;
+            }
+        };
+        if Self::is_stale(state.get_ref(), past_payloads, epoch)
+            || Self::is_delivered(state.get_ref(), past_payloads, self.node_id, epoch)
+        {
+            return None;
+        }
+
+        // Statistics that do not fit into the payload are dropped.
+        let byte_limit = byte_limit.get().min(MAX_QUERY_STATS_PAYLOAD_SIZE as u64) as usize;
+        let mut payload = QueryStatsPayload {
+            proposer: self.node_id,
+            epoch,
+            stats: Vec::new(),
+        };
+        for canister_stats in stats {
+            payload.stats.push(canister_stats);
+            if payload.count_bytes() > byte_limit {
+                payload.stats.pop();
+                break;
+            }
+        }
+
+        if payload.stats.is_empty() {
+            None
+        } else {
+            Some(payload)
+        }
+    }
+
+    fn validate_query_stats_payload(
+        &self,
+        payload: &QueryStatsPayload,
+        block_maker: NodeId,
+        validation_context: &ValidationContext,
+        past_payloads: &[&QueryStatsPayload],
+    ) -> Result<NumBytes, QueryStatsPayloadValidationError> {
+        if payload.proposer != block_maker {
+            return Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::InvalidProposer {
+                    block_maker,
+                    payload_proposer: payload.proposer,
+                },
+            ));
+        }
+
+        let size = payload.count_bytes();
+        if size > MAX_QUERY_STATS_PAYLOAD_SIZE {
+            return Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::PayloadTooBig,
+            ));
+        }
+
+        if payload.epoch >= epoch_from_height(validation_context.certified_height) {
+            return Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::EpochNotCompleted(payload.epoch),
+            ));
+        }
+
+        let mut canister_ids = BTreeSet::new();
+        for canister_stats in &payload.stats {
+            if !canister_ids.insert(canister_stats.canister_id) {
+                return Err(ValidationError::Permanent(
+                    InvalidQueryStatsPayload::DuplicateCanister(canister_stats.canister_id),
+                ));
+            }
+        }
+
+        let state = self
+            .state_reader
+            .get_state_at(validation_context.certified_height)
+            .map_err(|err| {
+                ValidationError::Transient(QueryStatsTransientValidationError::GetStateFailed(
+                    validation_context.certified_height,
+                    err,
+                ))
+            })?;
+        if Self::is_stale(state.get_ref(), past_payloads, payload.epoch) {
+            return Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::StaleEpoch(payload.epoch),
+            ));
+        }
+        if Self::is_delivered(
+            state.get_ref(),
+            past_payloads,
+            payload.proposer,
+            payload.epoch,
+        ) {
+            return Err(ValidationError::Permanent(
+                InvalidQueryStatsPayload::DuplicateProposer(payload.proposer, payload.epoch),
+            ));
+        }
+
+        Ok(NumBytes::new(size as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_types::batch::QUERY_STATS_EPOCH_LENGTH;
+
+    fn stats(num_calls: u64) -> QueryStats {
+        QueryStats {
+            num_calls,
+            ..QueryStats::default()
+        }
+    }
+
+    #[test]
+    fn collector_completes_epoch_when_height_advances() {
+        let collector = QueryStatsCollector::new();
+        let canister_id = CanisterId::from_u64(1);
+
+        collector.set_epoch_from_height(Height::from(1));
+        collector.register_query_statistics(canister_id, &stats(1));
+        collector.register_query_statistics(canister_id, &stats(2));
+        assert!(collector.completed_epoch().is_none());
+
+        // Heights within the same epoch do not complete it.
+        collector.set_epoch_from_height(Height::from(QUERY_STATS_EPOCH_LENGTH - 1));
+        assert!(collector.completed_epoch().is_none());
+
+        collector.set_epoch_from_height(Height::from(QUERY_STATS_EPOCH_LENGTH));
+        collector.register_query_statistics(canister_id, &stats(5));
+        let (epoch, completed) = collector.completed_epoch().unwrap();
+        assert_eq!(epoch, QueryStatsEpoch::from(0));
+        assert_eq!(
+            completed,
+            vec![CanisterQueryStats {
+                canister_id,
+                stats: stats(3),
+            }]
+        );
+    }
+}
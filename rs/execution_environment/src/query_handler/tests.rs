@@ -1,13 +1,32 @@
-use crate::InternalHttpQueryHandler;
+use crate::{InternalHttpQueryHandler, QueryStatsCollector, QueryStatsPayloadBuilderImpl};
+use assert_matches::assert_matches;
 use ic_base_types::NumSeconds;
 use ic_error_types::{ErrorCode, UserError};
+use ic_ic00_types::{CanisterStatusResultV2, Payload};
+use ic_interfaces::{
+    query_stats::{InvalidQueryStatsPayload, QueryStatsPayloadBuilder},
+    validation::ValidationError,
+};
+use ic_interfaces_state_manager::Labeled;
+use ic_logger::replica_logger::no_op_logger;
 use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::{metadata_state::NodeTopology, ReplicatedState};
 use ic_test_utilities::{
     execution_environment::ExecutionTestBuilder,
-    types::ids::user_test_id,
+    mock_time,
+    state_manager::MockStateManager,
+    types::ids::{node_test_id, user_test_id},
     universal_canister::{call_args, wasm},
 };
-use ic_types::{ingress::WasmResult, messages::UserQuery, Cycles};
+use ic_types::{
+    batch::{
+        CanisterQueryStats, QueryStats, QueryStatsEpoch, QueryStatsPayload, ValidationContext,
+        QUERY_STATS_EPOCH_LENGTH,
+    },
+    ingress::WasmResult,
+    messages::UserQuery,
+    CanisterId, Cycles, Height, NodeId, NumBytes, RegistryVersion,
+};
 use std::sync::Arc;
 
 const CYCLES_BALANCE: Cycles = Cycles::new(100_000_000_000_000);
@@ -390,3 +409,363 @@ fn queries_to_frozen_canisters_are_rejected() {
     );
     assert!(result.is_ok());
 }
+
+// A canister with a composite query method `forward` that calls the `pong`
+// query method of the canister whose id is passed as the argument. It replies
+// with the reply of the callee or with the reject message if the call fails.
+const COMPOSITE_QUERY_WAT: &str = r#"
+    (module
+        (import "ic0" "msg_arg_data_size" (func $msg_arg_data_size (result i32)))
+        (import "ic0" "msg_arg_data_copy"
+            (func $msg_arg_data_copy (param i32 i32 i32))
+        )
+        (import "ic0" "msg_reject_msg_size" (func $msg_reject_msg_size (result i32)))
+        (import "ic0" "msg_reject_msg_copy"
+            (func $msg_reject_msg_copy (param i32 i32 i32))
+        )
+        (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32))
+        )
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (import "ic0" "call_new"
+            (func $call_new
+                (param i32 i32)
+                (param $method_name_src i32) (param $method_name_len i32)
+                (param $reply_fun i32)       (param $reply_env i32)
+                (param $reject_fun i32)      (param $reject_env i32)
+            )
+        )
+        (import "ic0" "call_perform" (func $call_perform (result i32)))
+        (func $on_reply (param $env i32)
+            (call $msg_arg_data_copy (i32.const 100) (i32.const 0) (call $msg_arg_data_size))
+            (call $msg_reply_data_append (i32.const 100) (call $msg_arg_data_size))
+            (call $msg_reply)
+        )
+        (func $on_reject (param $env i32)
+            (call $msg_reject_msg_copy (i32.const 100) (i32.const 0) (call $msg_reject_msg_size))
+            (call $msg_reply_data_append (i32.const 100) (call $msg_reject_msg_size))
+            (call $msg_reply)
+        )
+        (table funcref (elem $on_reply $on_reject))
+        (func (export "canister_composite_query forward")
+            ;; The argument is the id of the callee.
+            (call $msg_arg_data_copy (i32.const 100) (i32.const 0) (call $msg_arg_data_size))
+            (call $call_new
+                (i32.const 100) (call $msg_arg_data_size) ;; callee canister id
+                (i32.const 0) (i32.const 4)               ;; refers to "pong" on the heap
+                (i32.const 0) (i32.const 0)               ;; on_reply closure
+                (i32.const 1) (i32.const 0)               ;; on_reject closure
+            )
+            (drop (call $call_perform))
+        )
+        (memory (export "memory") 1)
+        (data (i32.const 0) "pong")
+    )"#;
+
+// A canister with a query method `pong` that replies with "pong".
+const PONG_WAT: &str = r#"
+    (module
+        (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32))
+        )
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (func (export "canister_query pong")
+            (call $msg_reply_data_append (i32.const 0) (i32.const 4))
+            (call $msg_reply)
+        )
+        (memory (export "memory") 1)
+        (data (i32.const 0) "pong")
+    )"#;
+
+fn forward_query(receiver: CanisterId, callee: CanisterId) -> UserQuery {
+    UserQuery {
+        source: user_test_id(2),
+        receiver,
+        method_name: "forward".to_string(),
+        method_payload: callee.get().to_vec(),
+        ingress_expiry: 0,
+        nonce: None,
+    }
+}
+
+#[test]
+fn composite_query_calls_query_on_application_subnet() {
+    // In this test we have two canisters A and B on an application subnet.
+    // Canister A handles the user query in a composite query by calling
+    // canister B.
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_a = test.canister_from_wat(COMPOSITE_QUERY_WAT).unwrap();
+    let canister_b = test.canister_from_wat(PONG_WAT).unwrap();
+
+    let output = test.query(
+        forward_query(canister_a, canister_b),
+        Arc::new(test.state().clone()),
+        vec![],
+    );
+    assert_eq!(output, Ok(WasmResult::Reply(b"pong".to_vec())));
+}
+
+#[test]
+fn composite_query_cannot_be_called_in_replicated_mode() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_a = test.canister_from_wat(COMPOSITE_QUERY_WAT).unwrap();
+    let canister_b = test.canister_from_wat(PONG_WAT).unwrap();
+
+    let err = test
+        .ingress(canister_a, "forward", canister_b.get().to_vec())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CompositeQueryCalledInReplicatedMode);
+}
+
+#[test]
+fn composite_query_call_exceeding_depth_limit_is_rejected() {
+    // The call graph depth limit only allows the user query itself, so the
+    // call from canister A to canister B is rejected and canister A replies
+    // with the reject message.
+    let mut test = ExecutionTestBuilder::new()
+        .with_max_query_call_graph_depth(1)
+        .build();
+    let canister_a = test.canister_from_wat(COMPOSITE_QUERY_WAT).unwrap();
+    let canister_b = test.canister_from_wat(PONG_WAT).unwrap();
+
+    let output = test.query(
+        forward_query(canister_a, canister_b),
+        Arc::new(test.state().clone()),
+        vec![],
+    );
+    match output {
+        Ok(WasmResult::Reply(reply)) => {
+            let reject_message = String::from_utf8(reply).unwrap();
+            assert!(
+                reject_message.contains("call graph depth exceeded"),
+                "Unexpected reject message: {}",
+                reject_message
+            );
+        }
+        _ => unreachable!("Unexpected query result: {:?}", output),
+    }
+}
+
+#[test]
+fn composite_query_fails_when_call_graph_instruction_limit_is_exceeded() {
+    // Canister B runs a long loop that uses up the instructions of the
+    // whole call graph, so the query fails without executing the callback
+    // of canister A.
+    let mut test = ExecutionTestBuilder::new()
+        .with_max_query_call_graph_instructions(1_000_000)
+        .build();
+    let canister_a = test.canister_from_wat(COMPOSITE_QUERY_WAT).unwrap();
+    let canister_b = test
+        .canister_from_wat(
+            r#"
+            (module
+                (func (export "canister_query pong")
+                    (local $i i32)
+                    (loop $loop
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br_if $loop (i32.lt_u (local.get $i) (i32.const 100000000)))
+                    )
+                )
+                (memory (export "memory") 1)
+            )"#,
+        )
+        .unwrap();
+
+    let output = test.query(
+        forward_query(canister_a, canister_b),
+        Arc::new(test.state().clone()),
+        vec![],
+    );
+    match output {
+        Ok(_) => unreachable!("The query was expected to fail, but it succeeded."),
+        Err(err) => assert_eq!(
+            err.code(),
+            ErrorCode::QueryCallGraphTotalInstructionLimitExceeded
+        ),
+    }
+}
+
+// Returns a payload builder of `node_id` that reads `state` at every height.
+fn query_stats_payload_builder(
+    collector: Arc<QueryStatsCollector>,
+    state: ReplicatedState,
+    node_id: NodeId,
+) -> QueryStatsPayloadBuilderImpl {
+    let state = Arc::new(state);
+    let mut state_reader = MockStateManager::new();
+    state_reader
+        .expect_get_state_at()
+        .returning(move |height| Ok(Labeled::new(height, Arc::clone(&state))));
+    QueryStatsPayloadBuilderImpl::new(collector, Arc::new(state_reader), node_id, no_op_logger())
+}
+
+// A validation context whose certified height completes the first epoch.
+fn second_epoch_validation_context() -> ValidationContext {
+    ValidationContext {
+        registry_version: RegistryVersion::from(1),
+        certified_height: Height::from(QUERY_STATS_EPOCH_LENGTH),
+        time: mock_time(),
+    }
+}
+
+#[test]
+fn query_stats_are_aggregated_and_reported_in_canister_status() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister_with_cycles(CYCLES_BALANCE).unwrap();
+    let node_id = node_test_id(1);
+    let own_subnet_id = test.state().metadata.own_subnet_id;
+    test.state_mut()
+        .metadata
+        .network_topology
+        .subnets
+        .get_mut(&own_subnet_id)
+        .unwrap()
+        .nodes
+        .insert(node_id, NodeTopology::default());
+
+    // The query executes against a certified state of the first epoch.
+    let collector = Arc::clone(&downcast_query_handler(test.query_handler()).query_stats_collector);
+    collector.set_epoch_from_height(Height::from(1));
+    let method_payload = wasm().reply_data(b"pong".as_ref()).build();
+    let ingress_payload_size = method_payload.len() as u64;
+    let output = test.query(
+        UserQuery {
+            source: user_test_id(2),
+            receiver: canister_id,
+            method_name: "query".to_string(),
+            method_payload,
+            ingress_expiry: 0,
+            nonce: None,
+        },
+        Arc::new(test.state().clone()),
+        vec![],
+    );
+    assert_eq!(output, Ok(WasmResult::Reply(b"pong".to_vec())));
+
+    let payload_builder = query_stats_payload_builder(collector, test.state().clone(), node_id);
+    let validation_context = second_epoch_validation_context();
+    let payload = payload_builder
+        .get_query_stats_payload(&validation_context, &[], NumBytes::new(1024 * 1024))
+        .unwrap();
+    assert_eq!(payload.proposer, node_id);
+    assert_eq!(payload.epoch, QueryStatsEpoch::from(0));
+    payload_builder
+        .validate_query_stats_payload(&payload, node_id, &validation_context, &[])
+        .unwrap();
+    // A block made by another node cannot carry the statistics of `node_id`.
+    assert!(payload_builder
+        .validate_query_stats_payload(&payload, node_test_id(2), &validation_context, &[])
+        .is_err());
+
+    // All nodes of the subnet delivered their statistics.
+    test.state_mut().apply_query_stats(payload);
+
+    let result = test.canister_status(canister_id).unwrap();
+    let status = CanisterStatusResultV2::decode(&result.bytes()).unwrap();
+    let query_stats = status.query_stats();
+    assert_eq!(query_stats.num_calls_total(), 1);
+    assert!(query_stats.num_instructions_total() > 0);
+    assert_eq!(
+        query_stats.request_payload_bytes_total(),
+        ingress_payload_size
+    );
+    assert_eq!(query_stats.response_payload_bytes_total(), 4);
+}
+
+#[test]
+fn query_stats_are_not_collected_for_failed_queries() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister_with_cycles(CYCLES_BALANCE).unwrap();
+    let collector = Arc::clone(&downcast_query_handler(test.query_handler()).query_stats_collector);
+    collector.set_epoch_from_height(Height::from(1));
+
+    for (receiver, method_payload) in [
+        (canister_id, wasm().trap().build()),
+        (CanisterId::from_u64(1_000), wasm().reply().build()),
+    ] {
+        let output = test.query(
+            UserQuery {
+                source: user_test_id(2),
+                receiver,
+                method_name: "query".to_string(),
+                method_payload,
+                ingress_expiry: 0,
+                nonce: None,
+            },
+            Arc::new(test.state().clone()),
+            vec![],
+        );
+        assert!(output.is_err());
+    }
+
+    let payload_builder =
+        query_stats_payload_builder(collector, test.state().clone(), node_test_id(1));
+    assert_eq!(
+        payload_builder.get_query_stats_payload(
+            &second_epoch_validation_context(),
+            &[],
+            NumBytes::new(1024 * 1024)
+        ),
+        None
+    );
+}
+
+#[test]
+fn query_stats_of_stale_epochs_are_rejected() {
+    let node_id = node_test_id(1);
+    let payload = |epoch: u64| QueryStatsPayload {
+        proposer: node_id,
+        epoch: QueryStatsEpoch::from(epoch),
+        stats: vec![CanisterQueryStats {
+            canister_id: CanisterId::from_u64(1),
+            stats: QueryStats {
+                num_calls: 1,
+                ..QueryStats::default()
+            },
+        }],
+    };
+    let validation_context = ValidationContext {
+        certified_height: Height::from(2 * QUERY_STATS_EPOCH_LENGTH),
+        ..second_epoch_validation_context()
+    };
+
+    // The certified state already aggregates a later epoch.
+    let mut state = ExecutionTestBuilder::new().build().state().clone();
+    state.metadata.epoch_query_stats.epoch = Some(QueryStatsEpoch::from(1));
+    let payload_builder =
+        query_stats_payload_builder(Arc::new(QueryStatsCollector::new()), state, node_id);
+    assert_matches!(
+        payload_builder.validate_query_stats_payload(
+            &payload(0),
+            node_id,
+            &validation_context,
+            &[]
+        ),
+        Err(ValidationError::Permanent(
+            InvalidQueryStatsPayload::StaleEpoch(_)
+        ))
+    );
+    payload_builder
+        .validate_query_stats_payload(&payload(1), node_id, &validation_context, &[])
+        .unwrap();
+
+    // A later epoch is delivered in a block above the certified height.
+    let state = ExecutionTestBuilder::new().build().state().clone();
+    let payload_builder =
+        query_stats_payload_builder(Arc::new(QueryStatsCollector::new()), state, node_id);
+    let later = QueryStatsPayload {
+        proposer: node_test_id(2),
+        ..payload(1)
+    };
+    assert_matches!(
+        payload_builder.validate_query_stats_payload(
+            &payload(0),
+            node_id,
+            &validation_context,
+            &[&later]
+        ),
+        Err(ValidationError::Permanent(
+            InvalidQueryStatsPayload::StaleEpoch(_)
+        ))
+    );
+}
@@ -14,7 +14,8 @@ use ic_crypto::prng::{Csprng, RandomnessPurpose::ExecutionThread};
 use ic_cycles_account_manager::CyclesAccountManager;
 use ic_error_types::{ErrorCode, UserError};
 use ic_ic00_types::{
-    CanisterStatusType, EcdsaKeyId, InstallCodeArgs, Method as Ic00Method, Payload as _,
+    CanisterStatusType, EcdsaKeyId, InstallChunkedCodeArgs, InstallCodeArgs, Method as Ic00Method,
+    Payload as _,
 };
 use ic_interfaces::execution_environment::{ExecutionRoundType, RegistryExecutionSettings};
 use ic_interfaces::{
@@ -317,7 +318,7 @@ impl SchedulerImpl {
             let instruction_limits = InstructionLimits::new(
                 self.deterministic_time_slicing,
                 self.config.max_instructions_per_install_code,
-                self.config.max_instructions_per_slice,
+                self.config.max_instructions_per_install_code_slice,
             );
             let instructions_before = round_limits.instructions;
             state = self.exec_env.resume_install_code(
@@ -368,12 +369,10 @@ impl SchedulerImpl {
                 break;
             }
             if let Some(msg) = state.pop_subnet_input() {
-                let max_instructions_per_message =
-                    get_instructions_limit_for_subnet_message(&self.config, &msg);
-                let instruction_limits = InstructionLimits::new(
+                let instruction_limits = get_instruction_limits_for_subnet_message(
                     self.deterministic_time_slicing,
-                    max_instructions_per_message,
-                    self.config.max_instructions_per_slice,
+                    &self.config,
+                    &msg,
                 );
 
                 if is_bitcoin_request(&msg) {
@@ -436,12 +435,14 @@ impl SchedulerImpl {
 
         let mut total_heap_delta = NumBytes::from(0);
 
-        // Add `Heartbeat` tasks to be executed before input messages.
+        // Add `Heartbeat` and `GlobalTimer` tasks to be executed before input
+        // messages.
         {
             let _timer = self
                 .metrics
                 .round_inner_heartbeat_overhead_duration
                 .start_timer();
+            let now = state.time();
             for canister in state.canisters_iter_mut() {
                 if canister.exports_heartbeat_method() {
                     canister
@@ -449,6 +450,14 @@ impl SchedulerImpl {
                         .task_queue
                         .push_front(ExecutionTask::Heartbeat);
                 }
+                if canister.exports_global_timer_method()
+                    && canister.system_state.global_timer.has_reached_deadline(now)
+                {
+                    canister
+                        .system_state
+                        .task_queue
+                        .push_front(ExecutionTask::GlobalTimer);
+                }
             }
         }
 
@@ -550,11 +559,12 @@ impl SchedulerImpl {
                 .metrics
                 .round_inner_heartbeat_overhead_duration
                 .start_timer();
-            // Remove all remaining `Heartbeat` tasks because they will be added
-            // again in the next round.
+            // Remove all remaining `Heartbeat` and `GlobalTimer` tasks because
+            // they will be added again in the next round. A `GlobalTimer` task
+            // that was not executed leaves the timer active.
             for canister in state.canisters_iter_mut() {
                 canister.system_state.task_queue.retain(|task| match task {
-                    ExecutionTask::Heartbeat => false,
+                    ExecutionTask::Heartbeat | ExecutionTask::GlobalTimer => false,
                     ExecutionTask::PausedExecution(..)
                     | ExecutionTask::PausedInstallCode(..)
                     | ExecutionTask::AbortedExecution(..)
@@ -843,11 +853,14 @@ impl SchedulerImpl {
                 )
                 .is_err()
             {
+                // Running out of cycles is not a change requested by anyone,
+                // so it is not recorded in the canister history.
                 all_rejects.push(uninstall_canister(
                     &self.log,
                     canister,
                     &state_path,
                     state_time,
+                    None,
                 ));
                 canister.scheduler_state.compute_allocation = ComputeAllocation::zero();
                 canister.system_state.memory_allocation = MemoryAllocation::BestEffort;
@@ -1024,10 +1037,10 @@ impl SchedulerImpl {
                             id
                         );
                     }
-                    ExecutionTask::Heartbeat => {
+                    ExecutionTask::Heartbeat | ExecutionTask::GlobalTimer => {
                         panic!(
-                            "Unexpected heartbeat task after a round in canister {:?}",
-                            id
+                            "Unexpected system task {:?} after a round in canister {:?}",
+                            task, id
                         );
                     }
                     ExecutionTask::PausedExecution(_) | ExecutionTask::PausedInstallCode(_) => {
@@ -1178,10 +1191,9 @@ impl Scheduler for SchedulerImpl {
             // For now, we assume all subnet messages need the entire replicated
             // state. That can be changed in the future as we optimize scheduling.
             while let Some(response) = state.consensus_queue.pop() {
-                let instruction_limits = InstructionLimits::new(
+                let instruction_limits = get_instruction_limits_for_update_message(
                     self.deterministic_time_slicing,
-                    self.config.max_instructions_per_message,
-                    self.config.max_instructions_per_slice,
+                    &self.config,
                 );
                 let instructions_before = round_limits.instructions;
                 state = self.exec_env.execute_subnet_message(
@@ -1232,15 +1244,18 @@ impl Scheduler for SchedulerImpl {
         // The round will stop as soon as the counter reaches zero.
         // We can compute the initial value `X` of the counter based on:
         // - `R = max_instructions_per_round`,
-        // - `M = max_instructions_per_message`.
+        // - `S = the largest number of instructions of a single slice`.
         // In the worst case, we start a new Wasm execution when then counter
-        // reaches 1 and the execution uses the maximum `M` instructions. After
-        // the execution the counter will be set to `1 - M`.
+        // reaches 1 and the execution uses the maximum `S` instructions. After
+        // the execution the counter will be set to `1 - S`.
         //
         // We want the total number executed instructions to not exceed `R`,
-        // which gives us: `X - (1 - M) <= R` or `X <= R - M + 1`.
+        // which gives us: `X - (1 - S) <= R` or `X <= R - S + 1`.
         round_limits.instructions = as_round_instructions(self.config.max_instructions_per_round)
-            - as_round_instructions(self.config.max_instructions_per_message)
+            - as_round_instructions(get_max_instructions_per_slice(
+                self.deterministic_time_slicing,
+                &self.config,
+            ))
             + RoundInstructions::from(1);
 
         let ordered_canister_ids;
@@ -1448,11 +1463,8 @@ fn execute_canisters_on_thread(
     let mut total_messages_executed = NumMessages::from(0);
     let mut total_heap_delta = NumBytes::from(0);
 
-    let instruction_limits = InstructionLimits::new(
-        deterministic_time_slicing,
-        config.max_instructions_per_message,
-        config.max_instructions_per_slice,
-    );
+    let instruction_limits =
+        get_instruction_limits_for_update_message(deterministic_time_slicing, config);
 
     for (rank, mut canister) in canisters_to_execute.into_iter().enumerate() {
         // If no more instructions are left or if heap delta is already too
@@ -1695,17 +1707,61 @@ fn can_execute_msg(
         .unwrap_or(true)
 }
 
+/// Returns the message and slice instruction limits of update messages and
+/// responses.
+///
+/// Without deterministic time slicing the execution cannot be paused, so the
+/// message has to finish in a single slice within
+/// `max_instructions_per_message_without_dts`.
+fn get_instruction_limits_for_update_message(
+    dts: FlagStatus,
+    config: &SchedulerConfig,
+) -> InstructionLimits {
+    let max_instructions_per_message = match dts {
+        FlagStatus::Enabled => config.max_instructions_per_message,
+        FlagStatus::Disabled => config
+            .max_instructions_per_message
+            .min(config.max_instructions_per_message_without_dts),
+    };
+    InstructionLimits::new(
+        dts,
+        max_instructions_per_message,
+        config.max_instructions_per_slice,
+    )
+}
+
+/// Returns the largest number of instructions that a single slice of a
+/// canister execution can use in a round.
+///
+/// Note that system tasks such as heartbeats and timers cannot be paused and
+/// run within the slice limit of update messages.
+fn get_max_instructions_per_slice(dts: FlagStatus, config: &SchedulerConfig) -> NumInstructions {
+    match dts {
+        FlagStatus::Enabled => config
+            .max_instructions_per_slice
+            .max(config.max_instructions_per_install_code_slice),
+        FlagStatus::Disabled => get_instruction_limits_for_update_message(dts, config).slice(),
+    }
+}
+
 /// Based on the type of the subnet message to execute, figure out its
-/// instruction limit.
+/// message and slice instruction limits.
 ///
 /// This is primarily done because upgrading a canister might need to
 /// (de)-serialize a large state and thus consume a lot of instructions.
-fn get_instructions_limit_for_subnet_message(
+fn get_instruction_limits_for_subnet_message(
+    dts: FlagStatus,
     config: &SchedulerConfig,
     msg: &CanisterInputMessage,
-) -> NumInstructions {
+) -> InstructionLimits {
+    let default_limits = get_instruction_limits_for_update_message(dts, config);
+    let install_code_limits = InstructionLimits::new(
+        dts,
+        config.max_instructions_per_install_code,
+        config.max_instructions_per_install_code_slice,
+    );
     let (method_name, payload, sender) = match &msg {
-        CanisterInputMessage::Response(_) => return config.max_instructions_per_message,
+        CanisterInputMessage::Response(_) => return default_limits,
         CanisterInputMessage::Ingress(ingress) => (
             &ingress.method_name,
             &ingress.method_payload,
@@ -1736,21 +1792,37 @@ fn get_instructions_limit_for_subnet_message(
             | StopCanister
             | UninstallCode
             | UpdateSettings
+            | TakeCanisterSnapshot
+            | LoadCanisterSnapshot
+            | ListCanisterSnapshots
+            | DeleteCanisterSnapshot
+            | FetchCanisterLogs
+            | UploadChunk
+            | ClearChunkStore
+            | StoredChunks
+            | CanisterInfo
             | BitcoinGetBalance
             | BitcoinGetUtxos
             | BitcoinSendTransaction
             | BitcoinGetCurrentFeePercentiles
+            | BitcoinGetBlockHeaders
+            | BitcoinGetBlockchainInfo
+            | BitcoinGetTransactions
             | ProvisionalCreateCanisterWithCycles
-            | ProvisionalTopUpCanister => config.max_instructions_per_message,
+            | ProvisionalTopUpCanister => default_limits,
             InstallCode => match InstallCodeArgs::decode(payload) {
-                Err(_) => config.max_instructions_per_message,
+                Err(_) => default_limits,
                 Ok(args) => match InstallCodeContext::try_from((sender, args)) {
-                    Err(_) => config.max_instructions_per_message,
-                    Ok(_) => config.max_instructions_per_install_code,
+                    Err(_) => default_limits,
+                    Ok(_) => install_code_limits,
                 },
             },
+            InstallChunkedCode => match InstallChunkedCodeArgs::decode(payload) {
+                Err(_) => default_limits,
+                Ok(_) => install_code_limits,
+            },
         },
-        Err(_) => config.max_instructions_per_message,
+        Err(_) => default_limits,
     }
 }
 
@@ -1764,7 +1836,10 @@ fn is_bitcoin_request(msg: &CanisterInputMessage) -> bool {
                 BitcoinGetBalance
                 | BitcoinGetUtxos
                 | BitcoinSendTransaction
-                | BitcoinGetCurrentFeePercentiles => true,
+                | BitcoinGetCurrentFeePercentiles
+                | BitcoinGetBlockHeaders
+                | BitcoinGetBlockchainInfo
+                | BitcoinGetTransactions => true,
                 CanisterStatus
                 | CreateCanister
                 | DeleteCanister
@@ -1780,9 +1855,19 @@ fn is_bitcoin_request(msg: &CanisterInputMessage) -> bool {
                 | StopCanister
                 | UninstallCode
                 | UpdateSettings
+                | TakeCanisterSnapshot
+                | LoadCanisterSnapshot
+                | ListCanisterSnapshots
+                | DeleteCanisterSnapshot
+                | FetchCanisterLogs
+                | UploadChunk
+                | ClearChunkStore
+                | StoredChunks
+                | CanisterInfo
                 | ProvisionalCreateCanisterWithCycles
                 | ProvisionalTopUpCanister
-                | InstallCode => false,
+                | InstallCode
+                | InstallChunkedCode => false,
             },
             Err(_) => false,
         },
@@ -174,7 +174,9 @@ impl SchedulerTest {
     /// The `system_method` parameter can be used to optionally enable the
     /// heartbeat by passing `Some(SystemMethod::CanisterHeartbeat)`.
     /// In that case the heartbeat execution must be specified before each
-    /// round using `expect_heartbeat()`.
+    /// round using `expect_heartbeat()`. Similarly, passing
+    /// `Some(SystemMethod::CanisterGlobalTimer)` enables the global timer,
+    /// whose execution is specified using `expect_global_timer()`.
     pub fn create_canister_with(
         &mut self,
         cycles: Cycles,
@@ -372,7 +374,22 @@ impl SchedulerTest {
              `create_canister_with(.., Some(SystemMethod::Heartbeat))`"
         );
         let mut wasm_executor = self.wasm_executor.core.lock().unwrap();
-        wasm_executor.push_heartbeat(canister_id, heartbeat);
+        wasm_executor.push_system_task(canister_id, heartbeat);
+    }
+
+    /// Specifies global timer execution for the next round.
+    pub fn expect_global_timer(&mut self, canister_id: CanisterId, global_timer: TestMessage) {
+        assert!(
+            self.canister_state(canister_id)
+                .execution_state
+                .as_ref()
+                .unwrap()
+                .exports_method(&WasmMethod::System(SystemMethod::CanisterGlobalTimer)),
+            "The canister should be created with \
+             `create_canister_with(.., Some(SystemMethod::CanisterGlobalTimer))`"
+        );
+        let mut wasm_executor = self.wasm_executor.core.lock().unwrap();
+        wasm_executor.push_system_task(canister_id, global_timer);
     }
 
     pub fn execute_round(&mut self, round_type: ExecutionRoundType) {
@@ -791,11 +808,12 @@ impl WasmExecutor for TestWasmExecutor {
         input: WasmExecutionInput,
         execution_state: &ExecutionState,
     ) -> (Option<CompilationResult>, WasmExecutionResult) {
-        let (_message_id, message, call_context_id) = {
+        let (origin, message, call_context_id) = {
             let mut guard = self.core.lock().unwrap();
             guard.take_message(&input)
         };
         let execution = TestPausedWasmExecution {
+            origin,
             message,
             sandbox_safe_system_state: input.sandbox_safe_system_state,
             execution_parameters: input.execution_parameters,
@@ -833,7 +851,7 @@ struct TestWasmExecutorCore {
     messages: HashMap<u32, TestMessage>,
     install_code: HashMap<CanisterId, VecDeque<TestInstallCode>>,
     current_install_code: Option<TestInstallCode>,
-    heartbeat: HashMap<CanisterId, VecDeque<TestMessage>>,
+    system_tasks: HashMap<CanisterId, VecDeque<TestMessage>>,
     schedule: Vec<(ThreadId, ExecutionRound, CanisterId, NumInstructions)>,
     next_message_id: u32,
     round: ExecutionRound,
@@ -845,7 +863,7 @@ impl TestWasmExecutorCore {
             messages: HashMap::new(),
             install_code: HashMap::new(),
             current_install_code: None,
-            heartbeat: HashMap::new(),
+            system_tasks: HashMap::new(),
             schedule: vec![],
             next_message_id: 0,
             round: ExecutionRound::new(0),
@@ -892,6 +910,7 @@ impl TestWasmExecutorCore {
                     accessed_pages: 0,
                     dirty_pages: 0,
                 },
+                canister_log: Default::default(),
             };
             self.schedule
                 .push((thread_id, self.round, canister_id, instructions_to_execute));
@@ -930,6 +949,7 @@ impl TestWasmExecutorCore {
             allocated_message_bytes: NumBytes::from(0),
             num_instructions_left: instructions_left,
             instance_stats,
+            canister_log: Default::default(),
         };
         self.schedule
             .push((thread_id, self.round, canister_id, instructions_to_execute));
@@ -1038,11 +1058,13 @@ impl TestWasmExecutorCore {
         Ok(())
     }
 
-    // Returns the test message corresponding to the given input.
+    // Returns the test message corresponding to the given input together with
+    // its origin, which is needed to put the message back if the execution is
+    // aborted.
     fn take_message(
         &mut self,
         input: &WasmExecutionInput,
-    ) -> (u32, TestMessage, Option<CallContextId>) {
+    ) -> (TestMessageOrigin, TestMessage, Option<CallContextId>) {
         let canister_id = input.sandbox_safe_system_state.canister_id();
         match &input.api_type {
             ApiType::Update {
@@ -1052,7 +1074,11 @@ impl TestWasmExecutorCore {
             } => {
                 let message_id = decode_message_id_from_payload(incoming_payload.clone());
                 let message = self.messages.remove(&message_id).unwrap();
-                (message_id, message, Some(*call_context_id))
+                (
+                    TestMessageOrigin::Message(message_id),
+                    message,
+                    Some(*call_context_id),
+                )
             }
             ApiType::ReplyCallback {
                 call_context_id, ..
@@ -1065,24 +1091,30 @@ impl TestWasmExecutorCore {
                     FuncRef::UpdateClosure(closure) | FuncRef::QueryClosure(closure) => closure.env,
                 };
                 let message = self.messages.remove(&message_id).unwrap();
-                (message_id, message, Some(*call_context_id))
+                (
+                    TestMessageOrigin::Message(message_id),
+                    message,
+                    Some(*call_context_id),
+                )
             }
-            ApiType::Heartbeat {
+            ApiType::SystemTask {
                 call_context_id, ..
             } => {
-                let message_id = self.next_message_id();
                 let message = self
-                    .heartbeat
+                    .system_tasks
                     .get_mut(&canister_id)
                     .unwrap()
                     .pop_front()
                     .unwrap();
-                (message_id, message, Some(*call_context_id))
+                (
+                    TestMessageOrigin::SystemTask,
+                    message,
+                    Some(*call_context_id),
+                )
             }
             ApiType::Start => {
-                let message_id = self.next_message_id();
-                let message = match self.current_install_code.clone() {
-                    Some(TestInstallCode::Upgrade { start, .. }) => start,
+                let install_code = match self.current_install_code.clone() {
+                    Some(install_code @ TestInstallCode::Upgrade { .. }) => install_code,
                     _ => {
                         // Starting a new `install_code`, get it from the deque.
                         let install_code = self
@@ -1091,22 +1123,23 @@ impl TestWasmExecutorCore {
                             .unwrap()
                             .pop_front()
                             .unwrap();
-                        self.current_install_code = Some(install_code.clone());
-                        match install_code {
-                            TestInstallCode::Install { start, .. }
-                            | TestInstallCode::Reinstall { start, .. } => start,
-                            TestInstallCode::Upgrade { .. } => {
-                                unreachable!("Executing `start` before `pre_upgrade`")
-                            }
+                        if let TestInstallCode::Upgrade { .. } = install_code {
+                            unreachable!("Executing `start` before `pre_upgrade`")
                         }
+                        self.current_install_code = Some(install_code.clone());
+                        install_code
                     }
                 };
-                (message_id, message, None)
+                let message = match install_code.clone() {
+                    TestInstallCode::Install { start, .. }
+                    | TestInstallCode::Reinstall { start, .. }
+                    | TestInstallCode::Upgrade { start, .. } => start,
+                };
+                (TestMessageOrigin::InstallCode(install_code), message, None)
             }
             ApiType::Init { .. } => {
-                let message_id = self.next_message_id();
                 let install_code = self.current_install_code.take().unwrap();
-                let message = match install_code {
+                let message = match install_code.clone() {
                     TestInstallCode::Install { init, .. }
                     | TestInstallCode::Reinstall { init, .. } => init,
                     TestInstallCode::Upgrade { post_upgrade, .. } => {
@@ -1114,10 +1147,9 @@ impl TestWasmExecutorCore {
                         post_upgrade
                     }
                 };
-                (message_id, message, None)
+                (TestMessageOrigin::InstallCode(install_code), message, None)
             }
             ApiType::PreUpgrade { .. } => {
-                let message_id = self.next_message_id();
                 // Starting a new `install_code`, get it from the deque.
                 let install_code = self
                     .install_code
@@ -1126,13 +1158,13 @@ impl TestWasmExecutorCore {
                     .pop_front()
                     .unwrap();
                 self.current_install_code = Some(install_code.clone());
-                let message = match install_code {
+                let message = match install_code.clone() {
                     TestInstallCode::Install { .. } | TestInstallCode::Reinstall { .. } => {
                         unreachable!("Requested pre_upgrade for (re-)install")
                     }
                     TestInstallCode::Upgrade { pre_upgrade, .. } => pre_upgrade,
                 };
-                (message_id, message, None)
+                (TestMessageOrigin::InstallCode(install_code), message, None)
             }
             ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
@@ -1174,11 +1206,11 @@ impl TestWasmExecutorCore {
             .push_back(install_code);
     }
 
-    fn push_heartbeat(&mut self, canister_id: CanisterId, heartbeat: TestMessage) {
-        self.heartbeat
+    fn push_system_task(&mut self, canister_id: CanisterId, system_task: TestMessage) {
+        self.system_tasks
             .entry(canister_id)
             .or_default()
-            .push_back(heartbeat);
+            .push_back(system_task);
     }
 
     fn next_message_id(&mut self) -> u32 {
@@ -1186,10 +1218,49 @@ impl TestWasmExecutorCore {
         self.next_message_id += 1;
         result
     }
+
+    // Puts the message of an aborted execution back, so that the restarted
+    // execution finds it again.
+    fn restore_message(
+        &mut self,
+        canister_id: CanisterId,
+        origin: TestMessageOrigin,
+        message: TestMessage,
+    ) {
+        match origin {
+            TestMessageOrigin::Message(message_id) => {
+                self.messages.insert(message_id, message);
+            }
+            TestMessageOrigin::SystemTask => {
+                self.system_tasks
+                    .entry(canister_id)
+                    .or_default()
+                    .push_front(message);
+            }
+            TestMessageOrigin::InstallCode(install_code) => {
+                // An aborted `install_code` restarts from the beginning.
+                self.current_install_code = None;
+                self.install_code
+                    .entry(canister_id)
+                    .or_default()
+                    .push_front(install_code);
+            }
+        }
+    }
+}
+
+// Describes where a test message taken by the executor came from.
+enum TestMessageOrigin {
+    // A call or a response with the given test message id.
+    Message(u32),
+    SystemTask,
+    // A stage of the given `install_code`.
+    InstallCode(TestInstallCode),
 }
 
 /// Represent fake Wasm execution that can be paused and resumed.
 struct TestPausedWasmExecution {
+    origin: TestMessageOrigin,
     message: TestMessage,
     sandbox_safe_system_state: SandboxSafeSystemState,
     execution_parameters: ExecutionParameters,
@@ -1216,7 +1287,10 @@ impl PausedWasmExecution for TestPausedWasmExecution {
     }
 
     fn abort(self: Box<Self>) {
-        // Nothing to do.
+        let canister_id = self.sandbox_safe_system_state.canister_id();
+        let executor = Arc::clone(&self.executor);
+        let mut guard = executor.core.lock().unwrap();
+        guard.restore_message(canister_id, self.origin, self.message);
     }
 }
 
@@ -6,14 +6,14 @@ use super::{
 use crate::scheduler::test_utilities::{on_response, other_side};
 use candid::Encode;
 use ic_btc_types::Network;
-use ic_config::subnet_config::{CyclesAccountManagerConfig, SchedulerConfig};
+use ic_config::subnet_config::{CyclesAccountManagerConfig, SchedulerConfig, SubnetConfigs};
 use ic_ic00_types::{BitcoinGetBalanceArgs, CanisterIdRecord, EmptyBlob, Method};
 use ic_interfaces::execution_environment::AvailableMemory;
 use ic_logger::replica_logger::no_op_logger;
 use ic_registry_routing_table::CanisterIdRange;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::testing::CanisterQueuesTesting;
-use ic_replicated_state::CanisterStatus;
+use ic_replicated_state::{CanisterStatus, CanisterTimer};
 
 use ic_test_utilities::{
     mock_time,
@@ -1153,6 +1153,45 @@ fn execute_heartbeat_before_messages() {
     assert_eq!(test.ingress_queue_size(canister), 3);
 }
 
+#[test]
+fn execute_global_timer_when_deadline_is_reached() {
+    let mut test = SchedulerTestBuilder::new()
+        .with_scheduler_config(SchedulerConfig {
+            scheduler_cores: 1,
+            ..SchedulerConfig::application_subnet()
+        })
+        .build();
+    let canister = test.create_canister_with(
+        Cycles::new(1_000_000_000_000),
+        ComputeAllocation::zero(),
+        MemoryAllocation::BestEffort,
+        Some(SystemMethod::CanisterGlobalTimer),
+    );
+    let now = test.state().time();
+
+    // The timer is not executed before the deadline.
+    test.canister_state_mut(canister).system_state.global_timer =
+        CanisterTimer::Active(now + Duration::from_secs(1));
+    test.execute_round(ExecutionRoundType::OrdinaryRound);
+    let metrics = &test.scheduler().metrics;
+    assert_eq!(metrics.round_inner.messages.get_sample_sum(), 0.0);
+    assert_eq!(
+        test.canister_state(canister).system_state.global_timer,
+        CanisterTimer::Active(now + Duration::from_secs(1))
+    );
+
+    // The timer is executed once the deadline is reached and gets deactivated.
+    test.canister_state_mut(canister).system_state.global_timer = CanisterTimer::Active(now);
+    test.expect_global_timer(canister, instructions(1));
+    test.execute_round(ExecutionRoundType::OrdinaryRound);
+    let metrics = &test.scheduler().metrics;
+    assert_eq!(metrics.round_inner.messages.get_sample_sum(), 1.0);
+    assert_eq!(
+        test.canister_state(canister).system_state.global_timer,
+        CanisterTimer::Inactive
+    );
+}
+
 #[test]
 fn test_drain_subnet_messages_with_some_long_running_canisters() {
     let mut test = SchedulerTestBuilder::new()
@@ -2608,3 +2647,227 @@ fn dts_long_execution_runs_out_of_instructions() {
         ErrorCode::CanisterInstructionLimitExceeded,
     );
 }
+
+#[test]
+fn dts_long_execution_is_aborted_and_restarted_after_checkpoint() {
+    let mut test = SchedulerTestBuilder::new()
+        .with_scheduler_config(SchedulerConfig {
+            scheduler_cores: 1,
+            instruction_overhead_per_message: NumInstructions::from(0),
+            max_instructions_per_round: NumInstructions::from(1000),
+            max_instructions_per_message: NumInstructions::from(1000),
+            max_instructions_per_slice: NumInstructions::from(100),
+            ..SchedulerConfig::application_subnet()
+        })
+        .with_deterministic_time_slicing()
+        .build();
+
+    let canister = test.create_canister();
+    let message_id = test.send_ingress(canister, ingress(1000));
+    for _ in 0..3 {
+        test.execute_round(ExecutionRoundType::OrdinaryRound);
+    }
+    test.execute_round(ExecutionRoundType::CheckpointRound);
+    assert_eq!(test.ingress_status(&message_id), IngressStatus::Unknown);
+
+    // The aborted execution starts from scratch and needs all slices again.
+    for _ in 0..10 {
+        assert_eq!(test.ingress_status(&message_id), IngressStatus::Unknown);
+        test.execute_round(ExecutionRoundType::OrdinaryRound);
+    }
+    assert_eq!(
+        test.ingress_error(&message_id).code(),
+        ErrorCode::CanisterDidNotReply,
+    );
+}
+
+#[test]
+fn dts_long_upgrade_completes_over_multiple_rounds() {
+    let mut test = SchedulerTestBuilder::new()
+        .with_scheduler_config(SchedulerConfig {
+            scheduler_cores: 1,
+            instruction_overhead_per_message: NumInstructions::from(0),
+            max_instructions_per_round: NumInstructions::from(1000),
+            max_instructions_per_install_code: NumInstructions::from(1000),
+            max_instructions_per_install_code_slice: NumInstructions::from(100),
+            ..SchedulerConfig::application_subnet()
+        })
+        .with_deterministic_time_slicing()
+        .build();
+
+    let canister = test.create_canister();
+    let upgrade = TestInstallCode::Upgrade {
+        pre_upgrade: instructions(300),
+        start: instructions(100),
+        post_upgrade: instructions(300),
+    };
+    test.inject_install_code_call_to_ic00(canister, upgrade);
+
+    let mut rounds = 0;
+    let response = loop {
+        test.execute_round(ExecutionRoundType::OrdinaryRound);
+        rounds += 1;
+        if let Some(response) = test.get_responses_to_injected_calls().pop() {
+            break response;
+        }
+        assert!(rounds < 100, "The upgrade did not complete");
+    };
+    assert!(rounds > 1);
+    assert_eq!(
+        response.response_payload,
+        Payload::Data(EmptyBlob::encode())
+    );
+}
+
+#[test]
+fn dts_long_call_is_aborted_and_restarted_after_checkpoint() {
+    let mut test = SchedulerTestBuilder::new()
+        .with_scheduler_config(SchedulerConfig {
+            scheduler_cores: 1,
+            instruction_overhead_per_message: NumInstructions::from(0),
+            max_instructions_per_round: NumInstructions::from(1000),
+            max_instructions_per_message: NumInstructions::from(1000),
+            max_instructions_per_slice: NumInstructions::from(100),
+            ..SchedulerConfig::application_subnet()
+        })
+        .with_deterministic_time_slicing()
+        .build();
+
+    let caller = test.create_canister();
+    let callee = test.create_canister();
+    let message = ingress(10).call(other_side(callee, 1000), on_response(10));
+    let message_id = test.send_ingress(caller, message);
+    for _ in 0..3 {
+        test.execute_round(ExecutionRoundType::OrdinaryRound);
+    }
+    test.execute_round(ExecutionRoundType::CheckpointRound);
+
+    let mut rounds = 0;
+    while !matches!(
+        test.ingress_status(&message_id),
+        IngressStatus::Known {
+            state: IngressState::Failed(_),
+            ..
+        }
+    ) {
+        test.execute_round(ExecutionRoundType::OrdinaryRound);
+        rounds += 1;
+        assert!(rounds < 100, "The call did not complete");
+    }
+    assert_eq!(
+        test.ingress_error(&message_id).code(),
+        ErrorCode::CanisterDidNotReply,
+    );
+
+    // The aborted execution of the call starts from scratch, so the callee
+    // executes more instructions than the call needs.
+    let callee_instructions: u64 = test
+        .executed_schedule()
+        .into_iter()
+        .filter(|(_, _, canister, _)| *canister == callee)
+        .map(|(_, _, _, instructions)| instructions.get())
+        .sum();
+    assert!(callee_instructions > 1000);
+}
+
+#[test]
+fn dts_long_upgrade_is_aborted_and_restarted_after_checkpoint() {
+    let mut test = SchedulerTestBuilder::new()
+        .with_scheduler_config(SchedulerConfig {
+            scheduler_cores: 1,
+            instruction_overhead_per_message: NumInstructions::from(0),
+            max_instructions_per_round: NumInstructions::from(1000),
+            max_instructions_per_install_code: NumInstructions::from(1000),
+            max_instructions_per_install_code_slice: NumInstructions::from(100),
+            ..SchedulerConfig::application_subnet()
+        })
+        .with_deterministic_time_slicing()
+        .build();
+
+    let canister = test.create_canister();
+    let upgrade = TestInstallCode::Upgrade {
+        pre_upgrade: instructions(300),
+        start: instructions(100),
+        post_upgrade: instructions(300),
+    };
+    test.inject_install_code_call_to_ic00(canister, upgrade);
+    for _ in 0..2 {
+        test.execute_round(ExecutionRoundType::OrdinaryRound);
+    }
+    test.execute_round(ExecutionRoundType::CheckpointRound);
+    assert!(test.get_responses_to_injected_calls().is_empty());
+
+    let mut rounds = 0;
+    let response = loop {
+        test.execute_round(ExecutionRoundType::OrdinaryRound);
+        rounds += 1;
+        if let Some(response) = test.get_responses_to_injected_calls().pop() {
+            break response;
+        }
+        assert!(rounds < 100, "The upgrade did not complete");
+    };
+    assert_eq!(
+        response.response_payload,
+        Payload::Data(EmptyBlob::encode())
+    );
+
+    // The aborted upgrade starts from scratch with `pre_upgrade`.
+    let executed_instructions: u64 = test
+        .executed_schedule()
+        .into_iter()
+        .map(|(_, _, _, instructions)| instructions.get())
+        .sum();
+    assert!(executed_instructions > 700);
+}
+
+#[test]
+fn ingress_is_executed_with_default_limits() {
+    for subnet_type in &[SubnetType::Application, SubnetType::System] {
+        for dts in &[false, true] {
+            let builder = SchedulerTestBuilder::new().with_subnet_type(*subnet_type);
+            let mut test = if *dts {
+                builder.with_deterministic_time_slicing().build()
+            } else {
+                builder.build()
+            };
+
+            let canister = test.create_canister();
+            let message_id = test.send_ingress(canister, ingress(1000));
+            test.execute_round(ExecutionRoundType::OrdinaryRound);
+            assert_eq!(
+                test.ingress_error(&message_id).code(),
+                ErrorCode::CanisterDidNotReply,
+                "subnet type: {:?}, DTS: {}",
+                subnet_type,
+                dts
+            );
+        }
+    }
+}
+
+#[test]
+fn dts_long_execution_completes_with_default_limits() {
+    let scheduler_config = SubnetConfigs::default()
+        .own_subnet_config(SubnetType::Application)
+        .scheduler_config;
+    let mut test = SchedulerTestBuilder::new()
+        .with_deterministic_time_slicing()
+        .build();
+
+    // The message needs more instructions than a single slice or round.
+    let instructions = scheduler_config.max_instructions_per_round.get() * 2;
+    assert!(instructions < scheduler_config.max_instructions_per_message.get());
+    let canister = test.create_canister();
+    let message_id = test.send_ingress(canister, ingress(instructions));
+    let mut rounds = 0;
+    while test.ingress_status(&message_id) == IngressStatus::Unknown {
+        test.execute_round(ExecutionRoundType::OrdinaryRound);
+        rounds += 1;
+        assert!(rounds < 100, "The execution did not complete");
+    }
+    assert!(rounds > 1);
+    assert_eq!(
+        test.ingress_error(&message_id).code(),
+        ErrorCode::CanisterDidNotReply,
+    );
+}
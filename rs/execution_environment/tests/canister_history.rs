@@ -0,0 +1,156 @@
+use ic_error_types::ErrorCode;
+use ic_ic00_types::{
+    CanisterChange, CanisterChangeDetails, CanisterChangeOrigin, CanisterInfoRequest,
+    CanisterInfoResponse, CanisterInstallMode, Method, Payload as _,
+};
+use ic_replicated_state::canister_state::system_state::MAX_CANISTER_HISTORY_CHANGES;
+use ic_test_utilities::execution_environment::{ExecutionTest, ExecutionTestBuilder};
+use ic_test_utilities::types::ids::{canister_test_id, subnet_test_id, user_test_id};
+use ic_types::messages::{Payload, RequestOrResponse};
+use ic_types::{CanisterId, Cycles};
+use ic_wasm_types::CanisterModule;
+
+const EMPTY_WAT: &str = "(module)";
+
+fn test_with_caller() -> ExecutionTest {
+    ExecutionTestBuilder::new()
+        .with_caller(subnet_test_id(2), canister_test_id(1))
+        .build()
+}
+
+fn canister_info(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    num_requested_changes: Option<u64>,
+) -> Result<CanisterInfoResponse, String> {
+    let payload = CanisterInfoRequest::new(canister_id, num_requested_changes).encode();
+    test.inject_call_to_ic00(Method::CanisterInfo, payload, Cycles::zero());
+    test.execute_all();
+    match test.xnet_messages().last().unwrap() {
+        RequestOrResponse::Response(response) => match &response.response_payload {
+            Payload::Data(data) => Ok(CanisterInfoResponse::decode(data).unwrap()),
+            Payload::Reject(reject) => Err(reject.message.clone()),
+        },
+        RequestOrResponse::Request(_) => panic!("Expected a response"),
+    }
+}
+
+#[test]
+fn canister_history_records_code_and_controller_changes() {
+    let mut test = test_with_caller();
+    let user = test.user_id().get();
+    let wasm = wabt::wat2wasm(EMPTY_WAT).unwrap();
+    let module_hash = CanisterModule::new(wasm.clone()).module_hash();
+    let time = test.state().time().as_nanos_since_unix_epoch();
+
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    test.install_canister(canister_id, wasm.clone()).unwrap();
+    test.upgrade_canister(canister_id, wasm).unwrap();
+    test.uninstall_code(canister_id).unwrap();
+    let new_controller = user_test_id(42).get();
+    test.set_controller(canister_id, new_controller).unwrap();
+
+    let origin = CanisterChangeOrigin::from_user(user);
+    let expected_changes = vec![
+        CanisterChange::new(
+            time,
+            0,
+            origin.clone(),
+            CanisterChangeDetails::canister_creation(vec![user]),
+        ),
+        CanisterChange::new(
+            time,
+            1,
+            origin.clone(),
+            CanisterChangeDetails::code_deployment(CanisterInstallMode::Install, module_hash),
+        ),
+        CanisterChange::new(
+            time,
+            2,
+            origin.clone(),
+            CanisterChangeDetails::code_deployment(CanisterInstallMode::Upgrade, module_hash),
+        ),
+        CanisterChange::new(
+            time,
+            3,
+            origin.clone(),
+            CanisterChangeDetails::CodeUninstall,
+        ),
+        CanisterChange::new(
+            time,
+            4,
+            origin,
+            CanisterChangeDetails::controllers_change(vec![new_controller]),
+        ),
+    ];
+
+    let info = canister_info(&mut test, canister_id, Some(20)).unwrap();
+    assert_eq!(info.total_num_changes, 5);
+    assert_eq!(info.recent_changes, expected_changes);
+    assert_eq!(info.module_hash, None);
+    assert_eq!(info.controllers, vec![new_controller]);
+}
+
+#[test]
+fn canister_info_returns_the_requested_number_of_changes() {
+    let mut test = test_with_caller();
+    let canister_id = test.canister_from_wat(EMPTY_WAT).unwrap();
+    let module_hash = test
+        .execution_state(canister_id)
+        .wasm_binary
+        .binary
+        .module_hash();
+
+    let info = canister_info(&mut test, canister_id, None).unwrap();
+    assert_eq!(info.total_num_changes, 2);
+    assert!(info.recent_changes.is_empty());
+    assert_eq!(info.module_hash, Some(module_hash.to_vec()));
+    assert_eq!(info.controllers, vec![test.user_id().get()]);
+
+    let info = canister_info(&mut test, canister_id, Some(1)).unwrap();
+    assert_eq!(info.recent_changes.len(), 1);
+    assert_eq!(info.recent_changes[0].canister_version, 1);
+}
+
+#[test]
+fn canister_history_keeps_only_the_most_recent_changes() {
+    let mut test = test_with_caller();
+    let canister_id = test.canister_from_wat(EMPTY_WAT).unwrap();
+    let controller = test.user_id().get();
+    for _ in 0..MAX_CANISTER_HISTORY_CHANGES {
+        test.set_controller(canister_id, controller).unwrap();
+    }
+
+    let info = canister_info(&mut test, canister_id, Some(20)).unwrap();
+    let total_num_changes = MAX_CANISTER_HISTORY_CHANGES as u64 + 2;
+    assert_eq!(info.total_num_changes, total_num_changes);
+    assert_eq!(info.recent_changes.len(), MAX_CANISTER_HISTORY_CHANGES);
+    assert_eq!(
+        info.recent_changes.last().unwrap().canister_version,
+        total_num_changes - 1
+    );
+}
+
+#[test]
+fn canister_info_rejects_too_many_requested_changes() {
+    let mut test = test_with_caller();
+    let canister_id = test.canister_from_wat(EMPTY_WAT).unwrap();
+    let err = canister_info(&mut test, canister_id, Some(21)).unwrap_err();
+    assert!(err.contains("at most 20 changes"), "{}", err);
+}
+
+#[test]
+fn canister_info_cannot_be_called_via_ingress() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(EMPTY_WAT).unwrap();
+    let payload = CanisterInfoRequest::new(canister_id, None).encode();
+
+    let err = test
+        .should_accept_ingress_message(CanisterId::ic_00(), Method::CanisterInfo, payload.clone())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterRejectedMessage);
+    let err = test
+        .subnet_message(Method::CanisterInfo, payload)
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterMethodNotFound);
+}
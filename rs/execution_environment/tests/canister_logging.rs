@@ -0,0 +1,215 @@
+use ic_error_types::{ErrorCode, UserError};
+use ic_ic00_types::{
+    CanisterIdRecord, CanisterSettingsArgs, FetchCanisterLogsResponse, LogVisibility, Method,
+    Payload, UpdateSettingsArgs, IC_00,
+};
+use ic_test_utilities::execution_environment::{ExecutionTest, ExecutionTestBuilder};
+use ic_test_utilities::types::ids::user_test_id;
+use ic_types::{ingress::WasmResult, messages::UserQuery, CanisterId, Cycles, UserId};
+use std::sync::Arc;
+
+const LOGGING_WAT: &str = r#"
+    (module
+        (import "ic0" "debug_print" (func $debug_print (param i32 i32)))
+        (import "ic0" "trap" (func $trap (param i32 i32)))
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (func (export "canister_update print")
+            (call $debug_print (i32.const 0) (i32.const 5))
+            (call $msg_reply)
+        )
+        (func (export "canister_update fail")
+            (call $debug_print (i32.const 0) (i32.const 5))
+            (call $trap (i32.const 5) (i32.const 4))
+        )
+        (func (export "canister_pre_upgrade")
+            (call $debug_print (i32.const 0) (i32.const 5))
+        )
+        (memory (export "memory") 1)
+        (data (i32.const 0) "helloboom")
+    )"#;
+
+// A module whose `canister_init` and `canister_post_upgrade` print a message
+// and trap.
+const TRAPPING_INSTALL_WAT: &str = r#"
+    (module
+        (import "ic0" "debug_print" (func $debug_print (param i32 i32)))
+        (import "ic0" "trap" (func $trap (param i32 i32)))
+        (func $print_and_trap
+            (call $debug_print (i32.const 0) (i32.const 5))
+            (call $trap (i32.const 5) (i32.const 4))
+        )
+        (func (export "canister_init") (call $print_and_trap))
+        (func (export "canister_post_upgrade") (call $print_and_trap))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "helloboom")
+    )"#;
+
+fn fetch_canister_logs(
+    test: &ExecutionTest,
+    sender: UserId,
+    canister_id: CanisterId,
+) -> Result<FetchCanisterLogsResponse, UserError> {
+    let result = test.query(
+        UserQuery {
+            source: sender,
+            receiver: IC_00,
+            method_name: Method::FetchCanisterLogs.to_string(),
+            method_payload: CanisterIdRecord::from(canister_id).encode(),
+            ingress_expiry: 0,
+            nonce: None,
+        },
+        Arc::new(test.state().clone()),
+        vec![],
+    )?;
+    match result {
+        WasmResult::Reply(bytes) => Ok(FetchCanisterLogsResponse::decode(&bytes).unwrap()),
+        WasmResult::Reject(msg) => panic!("Unexpected reject: {}", msg),
+    }
+}
+
+fn log_contents(response: FetchCanisterLogsResponse) -> Vec<Vec<u8>> {
+    response
+        .canister_log_records
+        .into_iter()
+        .map(|record| record.content)
+        .collect()
+}
+
+fn set_log_visibility(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    visibility: LogVisibility,
+) {
+    let payload = UpdateSettingsArgs {
+        canister_id: canister_id.into(),
+        settings: CanisterSettingsArgs {
+            log_visibility: Some(visibility),
+            ..CanisterSettingsArgs::new(None, None, None, None, None)
+        },
+    }
+    .encode();
+    test.subnet_message(Method::UpdateSettings, payload)
+        .unwrap();
+}
+
+#[test]
+fn debug_print_is_recorded_in_canister_log() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(LOGGING_WAT).unwrap();
+    test.ingress(canister_id, "print", vec![]).unwrap();
+    test.ingress(canister_id, "print", vec![]).unwrap();
+
+    let response = fetch_canister_logs(&test, test.user_id(), canister_id).unwrap();
+    let records = &response.canister_log_records;
+    assert_eq!(records.len(), 2);
+    assert!(records[0].idx < records[1].idx);
+    assert_eq!(
+        records[0].timestamp_nanos,
+        test.state().time().as_nanos_since_unix_epoch()
+    );
+    assert_eq!(
+        log_contents(response),
+        vec![b"hello".to_vec(), b"hello".to_vec()]
+    );
+}
+
+#[test]
+fn trap_is_recorded_in_canister_log() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(LOGGING_WAT).unwrap();
+    let err = test.ingress(canister_id, "fail", vec![]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterCalledTrap);
+
+    // The messages printed before the trap are kept.
+    let response = fetch_canister_logs(&test, test.user_id(), canister_id).unwrap();
+    assert_eq!(
+        log_contents(response),
+        vec![b"hello".to_vec(), b"[TRAP]: boom".to_vec()]
+    );
+}
+
+#[test]
+fn canister_log_is_kept_when_upgrade_fails() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(LOGGING_WAT).unwrap();
+    let err = test
+        .upgrade_canister(canister_id, wabt::wat2wasm(TRAPPING_INSTALL_WAT).unwrap())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterCalledTrap);
+
+    // The upgrade is rolled back, but the records of `canister_pre_upgrade`
+    // and `canister_post_upgrade` are kept.
+    test.ingress(canister_id, "print", vec![]).unwrap();
+    let response = fetch_canister_logs(&test, test.user_id(), canister_id).unwrap();
+    assert_eq!(
+        log_contents(response),
+        vec![
+            b"hello".to_vec(),
+            b"hello".to_vec(),
+            b"[TRAP]: boom".to_vec(),
+            b"hello".to_vec()
+        ]
+    );
+}
+
+#[test]
+fn canister_log_is_kept_when_install_fails() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    let err = test
+        .install_canister(canister_id, wabt::wat2wasm(TRAPPING_INSTALL_WAT).unwrap())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterCalledTrap);
+    assert!(test.canister_state(canister_id).execution_state.is_none());
+
+    let response = fetch_canister_logs(&test, test.user_id(), canister_id).unwrap();
+    assert_eq!(
+        log_contents(response),
+        vec![b"hello".to_vec(), b"[TRAP]: boom".to_vec()]
+    );
+}
+
+#[test]
+fn uninstall_code_clears_canister_log() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(LOGGING_WAT).unwrap();
+    test.ingress(canister_id, "print", vec![]).unwrap();
+
+    test.uninstall_code(canister_id).unwrap();
+    let response = fetch_canister_logs(&test, test.user_id(), canister_id).unwrap();
+    assert_eq!(log_contents(response), Vec::<Vec<u8>>::new());
+}
+
+#[test]
+fn fetch_canister_logs_respects_log_visibility() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(LOGGING_WAT).unwrap();
+    test.ingress(canister_id, "print", vec![]).unwrap();
+
+    let err = fetch_canister_logs(&test, user_test_id(42), canister_id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+
+    set_log_visibility(&mut test, canister_id, LogVisibility::Public);
+    let response = fetch_canister_logs(&test, user_test_id(42), canister_id).unwrap();
+    assert_eq!(log_contents(response), vec![b"hello".to_vec()]);
+}
+
+#[test]
+fn fetch_canister_logs_cannot_be_called_as_update() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(LOGGING_WAT).unwrap();
+    let err = test
+        .subnet_message(
+            Method::FetchCanisterLogs,
+            CanisterIdRecord::from(canister_id).encode(),
+        )
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterRejectedMessage);
+}
+
+#[test]
+fn fetch_canister_logs_of_missing_canister_fails() {
+    let test = ExecutionTestBuilder::new().build();
+    let err = fetch_canister_logs(&test, test.user_id(), CanisterId::from_u64(42)).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterNotFound);
+}
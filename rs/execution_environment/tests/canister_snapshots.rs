@@ -0,0 +1,284 @@
+use ic_error_types::{ErrorCode, UserError};
+use ic_ic00_types::{
+    CanisterIdRecord, CanisterSnapshotArgs, CanisterSnapshotResponse,
+    ListCanisterSnapshotsResponse, Method, Payload, TakeCanisterSnapshotArgs,
+};
+use ic_replicated_state::SnapshotId;
+use ic_test_utilities::execution_environment::{get_reply, ExecutionTest, ExecutionTestBuilder};
+use ic_test_utilities::types::ids::user_test_id;
+use ic_types::{CanisterId, Cycles, MemoryAllocation, NumBytes};
+use std::convert::TryFrom;
+
+const COUNTER_WAT: &str = r#"
+    (module
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32))
+        )
+        (func (export "canister_update inc")
+            (i32.store (i32.const 0) (i32.add (i32.load (i32.const 0)) (i32.const 1)))
+            (call $msg_reply)
+        )
+        (func (export "canister_update read")
+            (call $msg_reply_data_append (i32.const 0) (i32.const 4))
+            (call $msg_reply)
+        )
+        (func (export "canister_update grow")
+            (drop (memory.grow (i32.const 1)))
+            (call $msg_reply)
+        )
+        (memory (export "memory") 1)
+    )"#;
+
+fn take_snapshot(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    replace_snapshot: Option<Vec<u8>>,
+) -> Result<CanisterSnapshotResponse, UserError> {
+    let payload = TakeCanisterSnapshotArgs::new(canister_id, replace_snapshot).encode();
+    let result = test.subnet_message(Method::TakeCanisterSnapshot, payload)?;
+    Ok(CanisterSnapshotResponse::decode(&get_reply(Ok(result))).unwrap())
+}
+
+fn load_snapshot(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    snapshot_id: Vec<u8>,
+) -> Result<(), UserError> {
+    let payload = CanisterSnapshotArgs::new(canister_id, snapshot_id).encode();
+    test.subnet_message(Method::LoadCanisterSnapshot, payload)
+        .map(|_| ())
+}
+
+fn list_snapshots(test: &mut ExecutionTest, canister_id: CanisterId) -> Vec<Vec<u8>> {
+    let payload = CanisterIdRecord::from(canister_id).encode();
+    let result = test.subnet_message(Method::ListCanisterSnapshots, payload);
+    ListCanisterSnapshotsResponse::decode(&get_reply(result))
+        .unwrap()
+        .0
+        .into_iter()
+        .map(|snapshot| snapshot.id)
+        .collect()
+}
+
+fn delete_snapshot(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    snapshot_id: Vec<u8>,
+) -> Result<(), UserError> {
+    let payload = CanisterSnapshotArgs::new(canister_id, snapshot_id).encode();
+    test.subnet_message(Method::DeleteCanisterSnapshot, payload)
+        .map(|_| ())
+}
+
+fn read_counter(test: &mut ExecutionTest, canister_id: CanisterId) -> Vec<u8> {
+    test.ingress(canister_id, "read", vec![]).unwrap().bytes()
+}
+
+// Loads the snapshot into the canister, stopping it before and starting it
+// again afterwards.
+fn stop_and_load_snapshot(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    snapshot_id: Vec<u8>,
+) -> Result<(), UserError> {
+    test.stop_canister(canister_id);
+    test.process_stopping_canisters();
+    let result = load_snapshot(test, canister_id, snapshot_id);
+    test.start_canister(canister_id).unwrap();
+    result
+}
+
+#[test]
+fn load_canister_snapshot_restores_memory() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(COUNTER_WAT).unwrap();
+    test.ingress(canister_id, "inc", vec![]).unwrap();
+
+    let snapshot = take_snapshot(&mut test, canister_id, None).unwrap();
+    assert_eq!(
+        snapshot.taken_at_timestamp,
+        test.state().time().as_nanos_since_unix_epoch()
+    );
+    assert!(snapshot.total_size > 0);
+
+    test.ingress(canister_id, "inc", vec![]).unwrap();
+    test.ingress(canister_id, "inc", vec![]).unwrap();
+    assert_eq!(read_counter(&mut test, canister_id), 3u32.to_le_bytes());
+
+    stop_and_load_snapshot(&mut test, canister_id, snapshot.id.clone()).unwrap();
+    assert_eq!(read_counter(&mut test, canister_id), 1u32.to_le_bytes());
+
+    // The snapshot is not affected by loading it and can be loaded again.
+    test.ingress(canister_id, "inc", vec![]).unwrap();
+    stop_and_load_snapshot(&mut test, canister_id, snapshot.id).unwrap();
+    assert_eq!(read_counter(&mut test, canister_id), 1u32.to_le_bytes());
+}
+
+#[test]
+fn load_canister_snapshot_requires_a_stopped_canister() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(COUNTER_WAT).unwrap();
+    let snapshot = take_snapshot(&mut test, canister_id, None).unwrap();
+    test.ingress(canister_id, "inc", vec![]).unwrap();
+
+    let err = load_snapshot(&mut test, canister_id, snapshot.id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterNotStopped);
+    assert_eq!(read_counter(&mut test, canister_id), 1u32.to_le_bytes());
+}
+
+#[test]
+fn list_and_delete_canister_snapshots() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(COUNTER_WAT).unwrap();
+    assert!(list_snapshots(&mut test, canister_id).is_empty());
+
+    let snapshot = take_snapshot(&mut test, canister_id, None).unwrap();
+    assert_eq!(
+        list_snapshots(&mut test, canister_id),
+        vec![snapshot.id.clone()]
+    );
+
+    delete_snapshot(&mut test, canister_id, snapshot.id.clone()).unwrap();
+    assert!(list_snapshots(&mut test, canister_id).is_empty());
+    assert!(test.state().canister_snapshots.is_empty());
+
+    let err = delete_snapshot(&mut test, canister_id, snapshot.id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterSnapshotNotFound);
+}
+
+#[test]
+fn take_canister_snapshot_fails_above_limit_unless_replacing() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(COUNTER_WAT).unwrap();
+    let first = take_snapshot(&mut test, canister_id, None).unwrap();
+
+    let err = take_snapshot(&mut test, canister_id, None).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterSnapshotLimitExceeded);
+
+    let second = take_snapshot(&mut test, canister_id, Some(first.id.clone())).unwrap();
+    assert_ne!(first.id, second.id);
+    assert_eq!(list_snapshots(&mut test, canister_id), vec![second.id]);
+
+    let err = load_snapshot(&mut test, canister_id, first.id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterSnapshotNotFound);
+}
+
+#[test]
+fn snapshots_of_other_canisters_are_not_found() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_a = test.canister_from_wat(COUNTER_WAT).unwrap();
+    let canister_b = test.canister_from_wat(COUNTER_WAT).unwrap();
+    let snapshot = take_snapshot(&mut test, canister_a, None).unwrap();
+
+    let err = load_snapshot(&mut test, canister_b, snapshot.id.clone()).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterSnapshotNotFound);
+    let err = take_snapshot(&mut test, canister_b, Some(snapshot.id.clone())).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterSnapshotNotFound);
+    let err = delete_snapshot(&mut test, canister_b, snapshot.id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterSnapshotNotFound);
+
+    // Malformed ids are not found either.
+    let err = load_snapshot(&mut test, canister_a, vec![1, 2, 3]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterSnapshotNotFound);
+}
+
+#[test]
+fn take_canister_snapshot_of_empty_canister_fails() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    let err = take_snapshot(&mut test, canister_id, None).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterWasmModuleNotFound);
+}
+
+#[test]
+fn canister_snapshots_require_a_controller() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(COUNTER_WAT).unwrap();
+    let snapshot = take_snapshot(&mut test, canister_id, None).unwrap();
+    test.set_controller(canister_id, user_test_id(42).get())
+        .unwrap();
+
+    let err = take_snapshot(&mut test, canister_id, None).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+    let err = load_snapshot(&mut test, canister_id, snapshot.id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+}
+
+#[test]
+fn canister_snapshots_count_towards_memory_allocation() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(COUNTER_WAT).unwrap();
+    let memory_usage = test
+        .canister_state(canister_id)
+        .execution_state
+        .as_ref()
+        .unwrap()
+        .memory_usage();
+
+    // There is enough memory allocation for the canister but not for a copy
+    // of it.
+    test.state_mut()
+        .canister_state_mut(&canister_id)
+        .unwrap()
+        .system_state
+        .memory_allocation =
+        MemoryAllocation::try_from(memory_usage + NumBytes::from(1024)).unwrap();
+    let err = take_snapshot(&mut test, canister_id, None).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InsufficientMemoryAllocation);
+    assert!(test.state().canister_snapshots.is_empty());
+
+    test.state_mut()
+        .canister_state_mut(&canister_id)
+        .unwrap()
+        .system_state
+        .memory_allocation = MemoryAllocation::try_from(memory_usage * 3).unwrap();
+    let snapshot = take_snapshot(&mut test, canister_id, None).unwrap();
+    assert_eq!(
+        test.state()
+            .canister_snapshots
+            .memory_taken(canister_id)
+            .get(),
+        snapshot.total_size
+    );
+    assert!(test
+        .state()
+        .canister_snapshots
+        .get(SnapshotId::from_bytes(&snapshot.id).unwrap())
+        .is_some());
+}
+
+#[test]
+fn canister_snapshots_count_towards_memory_allocation_when_growing_memory() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(COUNTER_WAT).unwrap();
+    let memory_usage = test
+        .canister_state(canister_id)
+        .execution_state
+        .as_ref()
+        .unwrap()
+        .memory_usage();
+    test.state_mut()
+        .canister_state_mut(&canister_id)
+        .unwrap()
+        .system_state
+        .memory_allocation = MemoryAllocation::try_from(memory_usage * 3).unwrap();
+    let snapshot = take_snapshot(&mut test, canister_id, None).unwrap();
+
+    // The allocation covers the canister and its snapshot, but not another
+    // Wasm page.
+    test.state_mut()
+        .canister_state_mut(&canister_id)
+        .unwrap()
+        .system_state
+        .memory_allocation = MemoryAllocation::try_from(
+        memory_usage + NumBytes::from(snapshot.total_size) + NumBytes::from(1024),
+    )
+    .unwrap();
+    let err = test.ingress(canister_id, "grow", vec![]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterOutOfMemory);
+
+    // Deleting the snapshot releases its memory.
+    delete_snapshot(&mut test, canister_id, snapshot.id).unwrap();
+    test.ingress(canister_id, "grow", vec![]).unwrap();
+}
@@ -0,0 +1,134 @@
+use ic_error_types::ErrorCode;
+use ic_test_utilities::execution_environment::{ExecutionTest, ExecutionTestBuilder};
+use ic_test_utilities::types::ids::user_test_id;
+use ic_types::{CanisterId, Cycles};
+
+const VERSION_WAT: &str = r#"
+    (module
+        (import "ic0" "canister_version" (func $canister_version (result i64)))
+        (import "ic0" "is_controller"
+            (func $is_controller (param i32 i32) (result i32))
+        )
+        (import "ic0" "msg_caller_size" (func $msg_caller_size (result i32)))
+        (import "ic0" "msg_caller_copy"
+            (func $msg_caller_copy (param i32 i32 i32))
+        )
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32))
+        )
+        (func (export "canister_query version")
+            (i64.store (i32.const 0) (call $canister_version))
+            (call $msg_reply_data_append (i32.const 0) (i32.const 8))
+            (call $msg_reply)
+        )
+        ;; Records the version seen by `canister_init` and
+        ;; `canister_post_upgrade` at address 200.
+        (func $record_installed_version
+            (i64.store (i32.const 200) (call $canister_version))
+        )
+        (func (export "canister_init") (call $record_installed_version))
+        (func (export "canister_post_upgrade") (call $record_installed_version))
+        (func (export "canister_query installed_version")
+            (call $msg_reply_data_append (i32.const 200) (i32.const 8))
+            (call $msg_reply)
+        )
+        (func (export "canister_query caller_is_controller")
+            ;; Copy the caller to address 100 and reply with the result at address 0.
+            (call $msg_caller_copy (i32.const 100) (i32.const 0) (call $msg_caller_size))
+            (i32.store
+                (i32.const 0)
+                (call $is_controller (i32.const 100) (call $msg_caller_size))
+            )
+            (call $msg_reply_data_append (i32.const 0) (i32.const 4))
+            (call $msg_reply)
+        )
+        (memory (export "memory") 1)
+    )"#;
+
+fn reply_as_u64(test: &mut ExecutionTest, canister_id: CanisterId, method: &str) -> u64 {
+    let result = test.ingress(canister_id, method, vec![]).unwrap();
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&result.bytes());
+    u64::from_le_bytes(bytes)
+}
+
+fn canister_version(test: &mut ExecutionTest, canister_id: CanisterId) -> u64 {
+    reply_as_u64(test, canister_id, "version")
+}
+
+// Returns the version seen by the last `canister_init` or
+// `canister_post_upgrade`.
+fn installed_version(test: &mut ExecutionTest, canister_id: CanisterId) -> u64 {
+    reply_as_u64(test, canister_id, "installed_version")
+}
+
+fn stored_canister_version(test: &ExecutionTest, canister_id: CanisterId) -> u64 {
+    test.canister_state(canister_id)
+        .system_state
+        .canister_version
+}
+
+fn caller_is_controller(test: &mut ExecutionTest, canister_id: CanisterId) -> bool {
+    let result = test
+        .ingress(canister_id, "caller_is_controller", vec![])
+        .unwrap();
+    result.bytes() == 1_u32.to_le_bytes().to_vec()
+}
+
+#[test]
+fn canister_version_is_bumped_on_code_and_settings_changes() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    assert_eq!(stored_canister_version(&test, canister_id), 0);
+
+    test.install_canister(canister_id, wabt::wat2wasm(VERSION_WAT).unwrap())
+        .unwrap();
+    assert_eq!(installed_version(&mut test, canister_id), 1);
+    assert_eq!(canister_version(&mut test, canister_id), 1);
+    // Executing messages does not change the version.
+    assert_eq!(canister_version(&mut test, canister_id), 1);
+
+    test.upgrade_canister(canister_id, wabt::wat2wasm(VERSION_WAT).unwrap())
+        .unwrap();
+    assert_eq!(installed_version(&mut test, canister_id), 2);
+    assert_eq!(canister_version(&mut test, canister_id), 2);
+
+    test.set_controller(canister_id, test.user_id().get())
+        .unwrap();
+    assert_eq!(canister_version(&mut test, canister_id), 3);
+    assert_eq!(stored_canister_version(&test, canister_id), 3);
+
+    test.uninstall_code(canister_id).unwrap();
+    assert_eq!(stored_canister_version(&test, canister_id), 4);
+}
+
+#[test]
+fn canister_version_is_not_bumped_by_failed_upgrade() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(VERSION_WAT).unwrap();
+
+    // The `canister_post_upgrade` of the new module traps, so the upgrade is
+    // rolled back.
+    let err = test
+        .upgrade_canister(
+            canister_id,
+            wabt::wat2wasm(r#"(module (func (export "canister_post_upgrade") unreachable))"#)
+                .unwrap(),
+        )
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterTrapped);
+    assert_eq!(canister_version(&mut test, canister_id), 1);
+    assert_eq!(stored_canister_version(&test, canister_id), 1);
+}
+
+#[test]
+fn is_controller_checks_the_given_principal() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(VERSION_WAT).unwrap();
+    assert!(caller_is_controller(&mut test, canister_id));
+
+    test.set_controller(canister_id, user_test_id(42).get())
+        .unwrap();
+    assert!(!caller_is_controller(&mut test, canister_id));
+}
@@ -0,0 +1,126 @@
+use ic_execution_environment::CanisterHeartbeatError;
+use ic_interfaces::execution_environment::{HypervisorError, TrapCode};
+use ic_replicated_state::CanisterTimer;
+use ic_test_utilities::execution_environment::{wat_compilation_cost, ExecutionTestBuilder};
+use ic_types::{NumBytes, Time};
+
+const TIMER_WAT: &str = r#"
+    (module
+        (import "ic0" "global_timer_set"
+            (func $global_timer_set (param i64) (result i64))
+        )
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32))
+        )
+        (func (export "canister_update set")
+            ;; Store the previous value of the timer at address 0 and reply with it.
+            (i64.store (i32.const 0) (call $global_timer_set (i64.const 1000)))
+            (call $msg_reply_data_append (i32.const 0) (i32.const 8))
+            (call $msg_reply)
+        )
+        (func (export "canister_global_timer")
+            (drop (call $global_timer_set (i64.const 2000)))
+        )
+        (memory (export "memory") 1)
+    )"#;
+
+#[test]
+fn global_timer_is_executed() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (func (export "canister_global_timer") unreachable)
+            (memory (export "memory") 1)
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    let err = test.global_timer(canister_id).unwrap_err();
+    assert_eq!(
+        err,
+        CanisterHeartbeatError::CanisterExecutionFailed(HypervisorError::Trapped(
+            TrapCode::Unreachable
+        ))
+    );
+}
+
+#[test]
+fn global_timer_fails_gracefully_if_not_exported() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = "(module)";
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    test.global_timer(canister_id).unwrap();
+    assert_eq!(NumBytes::from(0), test.state().metadata.heap_delta_estimate);
+    assert_eq!(wat_compilation_cost(wat), test.executed_instructions());
+}
+
+#[test]
+fn global_timer_set_is_persisted_and_returns_previous_value() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(TIMER_WAT).unwrap();
+    assert_eq!(
+        test.canister_state(canister_id).system_state.global_timer,
+        CanisterTimer::Inactive
+    );
+
+    let result = test.ingress(canister_id, "set", vec![]).unwrap();
+    assert_eq!(result.bytes(), 0u64.to_le_bytes().to_vec());
+    assert_eq!(
+        test.canister_state(canister_id).system_state.global_timer,
+        CanisterTimer::Active(Time::from_nanos_since_unix_epoch(1000))
+    );
+
+    let result = test.ingress(canister_id, "set", vec![]).unwrap();
+    assert_eq!(result.bytes(), 1000u64.to_le_bytes().to_vec());
+}
+
+#[test]
+fn global_timer_is_deactivated_before_execution() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (import "ic0" "global_timer_set"
+                (func $global_timer_set (param i64) (result i64))
+            )
+            (func (export "canister_global_timer")
+                ;; Trap unless the timer was deactivated before the execution.
+                (if (i64.ne (call $global_timer_set (i64.const 0)) (i64.const 0))
+                    (then unreachable)
+                )
+            )
+            (memory (export "memory") 1)
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    test.canister_state_mut(canister_id)
+        .system_state
+        .global_timer = CanisterTimer::Active(Time::from_nanos_since_unix_epoch(1000));
+    test.global_timer(canister_id).unwrap();
+    assert_eq!(
+        test.canister_state(canister_id).system_state.global_timer,
+        CanisterTimer::Inactive
+    );
+}
+
+#[test]
+fn global_timer_can_be_reactivated_in_canister_global_timer() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(TIMER_WAT).unwrap();
+    test.ingress(canister_id, "set", vec![]).unwrap();
+    test.global_timer(canister_id).unwrap();
+    assert_eq!(
+        test.canister_state(canister_id).system_state.global_timer,
+        CanisterTimer::Active(Time::from_nanos_since_unix_epoch(2000))
+    );
+}
+
+#[test]
+fn global_timer_is_deactivated_on_upgrade() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(TIMER_WAT).unwrap();
+    test.ingress(canister_id, "set", vec![]).unwrap();
+    test.upgrade_canister(canister_id, wabt::wat2wasm(TIMER_WAT).unwrap())
+        .unwrap();
+    assert_eq!(
+        test.canister_state(canister_id).system_state.global_timer,
+        CanisterTimer::Inactive
+    );
+}
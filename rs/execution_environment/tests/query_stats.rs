@@ -0,0 +1,35 @@
+use ic_ic00_types::{CanisterStatusResultV2, Payload, QueryStatsRecord};
+use ic_test_utilities::execution_environment::ExecutionTestBuilder;
+use ic_types::{batch::QueryStats, Cycles};
+
+#[test]
+fn canister_status_reports_zero_query_stats_for_new_canister() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+
+    let result = test.canister_status(canister_id).unwrap();
+    let status = CanisterStatusResultV2::decode(&result.bytes()).unwrap();
+    assert_eq!(status.query_stats(), &QueryStatsRecord::default());
+}
+
+#[test]
+fn canister_status_reports_total_query_stats() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    test.canister_state_mut(canister_id)
+        .system_state
+        .total_query_stats = QueryStats {
+        num_calls: 3,
+        num_instructions: 1_000,
+        ingress_payload_size: 30,
+        egress_payload_size: 60,
+    };
+
+    let result = test.canister_status(canister_id).unwrap();
+    let status = CanisterStatusResultV2::decode(&result.bytes()).unwrap();
+    let query_stats = status.query_stats();
+    assert_eq!(query_stats.num_calls_total(), 3);
+    assert_eq!(query_stats.num_instructions_total(), 1_000);
+    assert_eq!(query_stats.request_payload_bytes_total(), 30);
+    assert_eq!(query_stats.response_payload_bytes_total(), 60);
+}
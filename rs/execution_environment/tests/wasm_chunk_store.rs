@@ -0,0 +1,188 @@
+use ic_error_types::{ErrorCode, UserError};
+use ic_ic00_types::{
+    CanisterIdRecord, CanisterInstallMode, ChunkHash, InstallChunkedCodeArgs, Method, Payload,
+    StoredChunksReply, UploadChunkArgs,
+};
+use ic_replicated_state::canister_state::system_state::CHUNK_SIZE;
+use ic_test_utilities::execution_environment::{get_reply, ExecutionTest, ExecutionTestBuilder};
+use ic_test_utilities::types::ids::user_test_id;
+use ic_types::{CanisterId, Cycles, MemoryAllocation, NumBytes};
+use ic_wasm_types::CanisterModule;
+use std::convert::TryFrom;
+
+const HELLO_WAT: &str = r#"
+    (module
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32))
+        )
+        (func (export "canister_update hello")
+            (call $msg_reply_data_append (i32.const 0) (i32.const 5))
+            (call $msg_reply)
+        )
+        (memory (export "memory") 1)
+        (data (i32.const 0) "hello")
+    )"#;
+
+fn upload_chunk(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    chunk: Vec<u8>,
+) -> Result<Vec<u8>, UserError> {
+    let payload = UploadChunkArgs::new(canister_id, chunk).encode();
+    let result = test.subnet_message(Method::UploadChunk, payload)?;
+    Ok(ChunkHash::decode(&get_reply(Ok(result))).unwrap().hash)
+}
+
+fn stored_chunks(test: &mut ExecutionTest, canister_id: CanisterId) -> Vec<Vec<u8>> {
+    let payload = CanisterIdRecord::from(canister_id).encode();
+    let result = test.subnet_message(Method::StoredChunks, payload);
+    StoredChunksReply::decode(&get_reply(result))
+        .unwrap()
+        .0
+        .into_iter()
+        .map(|chunk| chunk.hash)
+        .collect()
+}
+
+fn clear_chunk_store(test: &mut ExecutionTest, canister_id: CanisterId) -> Result<(), UserError> {
+    let payload = CanisterIdRecord::from(canister_id).encode();
+    test.subnet_message(Method::ClearChunkStore, payload)
+        .map(|_| ())
+}
+
+fn install_chunked_code(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    chunk_hashes: Vec<Vec<u8>>,
+    wasm_module_hash: Vec<u8>,
+) -> Result<(), UserError> {
+    let payload = InstallChunkedCodeArgs::new(
+        CanisterInstallMode::Install,
+        canister_id,
+        chunk_hashes,
+        wasm_module_hash,
+        vec![],
+    )
+    .encode();
+    test.subnet_message(Method::InstallChunkedCode, payload)
+        .map(|_| ())
+}
+
+// Chunks and modules are both addressed by their SHA-256 hash.
+fn sha256(bytes: &[u8]) -> Vec<u8> {
+    CanisterModule::new(bytes.to_vec()).module_hash().to_vec()
+}
+
+#[test]
+fn upload_list_and_clear_chunks() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    assert!(stored_chunks(&mut test, canister_id).is_empty());
+
+    let first = upload_chunk(&mut test, canister_id, b"first".to_vec()).unwrap();
+    let second = upload_chunk(&mut test, canister_id, b"second".to_vec()).unwrap();
+    assert_eq!(first, sha256(b"first"));
+    // Uploading the same chunk again is a no-op.
+    assert_eq!(
+        upload_chunk(&mut test, canister_id, b"first".to_vec()).unwrap(),
+        first
+    );
+
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(stored_chunks(&mut test, canister_id), expected);
+
+    clear_chunk_store(&mut test, canister_id).unwrap();
+    assert!(stored_chunks(&mut test, canister_id).is_empty());
+}
+
+#[test]
+fn install_chunked_code_assembles_the_module() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    let wasm = wabt::wat2wasm(HELLO_WAT).unwrap();
+    let (head, tail) = wasm.split_at(wasm.len() / 2);
+
+    let head_hash = upload_chunk(&mut test, canister_id, head.to_vec()).unwrap();
+    let tail_hash = upload_chunk(&mut test, canister_id, tail.to_vec()).unwrap();
+    install_chunked_code(
+        &mut test,
+        canister_id,
+        vec![head_hash, tail_hash],
+        sha256(&wasm),
+    )
+    .unwrap();
+
+    let result = test.ingress(canister_id, "hello", vec![]).unwrap();
+    assert_eq!(result.bytes(), b"hello".to_vec());
+    // The chunks are kept after the installation.
+    assert_eq!(stored_chunks(&mut test, canister_id).len(), 2);
+}
+
+#[test]
+fn install_chunked_code_fails_on_hash_mismatch() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    let wasm = wabt::wat2wasm(HELLO_WAT).unwrap();
+    let hash = upload_chunk(&mut test, canister_id, wasm).unwrap();
+
+    let err = install_chunked_code(&mut test, canister_id, vec![hash], vec![0; 32]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+
+    let err =
+        install_chunked_code(&mut test, canister_id, vec![vec![1; 32]], vec![0; 32]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+    assert!(test.canister_state(canister_id).execution_state.is_none());
+}
+
+#[test]
+fn chunk_store_requires_a_controller() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    let wasm = wabt::wat2wasm(HELLO_WAT).unwrap();
+    let hash = upload_chunk(&mut test, canister_id, wasm.clone()).unwrap();
+    test.set_controller(canister_id, user_test_id(42).get())
+        .unwrap();
+
+    let err = upload_chunk(&mut test, canister_id, b"chunk".to_vec()).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+    let err = clear_chunk_store(&mut test, canister_id).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+    let err = install_chunked_code(&mut test, canister_id, vec![hash], sha256(&wasm)).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+}
+
+#[test]
+fn chunks_count_towards_memory_usage() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.create_canister(Cycles::new(1_000_000_000_000));
+    upload_chunk(&mut test, canister_id, b"chunk".to_vec()).unwrap();
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .wasm_chunk_store
+            .memory_usage(),
+        NumBytes::from(CHUNK_SIZE)
+    );
+
+    // There is no memory allocation left for another chunk.
+    test.state_mut()
+        .canister_state_mut(&canister_id)
+        .unwrap()
+        .system_state
+        .memory_allocation = MemoryAllocation::try_from(NumBytes::from(CHUNK_SIZE)).unwrap();
+    let err = upload_chunk(&mut test, canister_id, b"other".to_vec()).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InsufficientMemoryAllocation);
+    assert_eq!(stored_chunks(&mut test, canister_id).len(), 1);
+}
+
+#[test]
+fn uninstall_code_clears_the_chunk_store() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(HELLO_WAT).unwrap();
+    upload_chunk(&mut test, canister_id, b"chunk".to_vec()).unwrap();
+
+    test.uninstall_code(canister_id).unwrap();
+    assert!(stored_chunks(&mut test, canister_id).is_empty());
+}
@@ -0,0 +1,118 @@
+use ic_error_types::ErrorCode;
+use ic_ic00_types::{
+    CanisterSettingsArgs, CanisterStatusResultV2, Method, Payload, UpdateSettingsArgs,
+};
+use ic_test_utilities::execution_environment::{ExecutionTest, ExecutionTestBuilder};
+use ic_types::CanisterId;
+
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+const GROW_WAT: &str = r#"
+    (module
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (func $grow (param $pages i32)
+            (if (i32.eq (memory.grow (local.get $pages)) (i32.const -1))
+                (then unreachable)
+            )
+        )
+        (func (export "canister_update grow")
+            (call $grow (i32.const 1))
+            (call $msg_reply)
+        )
+        (func (export "canister_query grow_query")
+            (call $grow (i32.const 1))
+            (call $msg_reply)
+        )
+        (func (export "canister_post_upgrade")
+            (call $grow (i32.const 5))
+        )
+        (memory 1)
+    )"#;
+
+fn set_wasm_memory_limit(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    wasm_memory_limit: u64,
+) -> Result<(), String> {
+    let payload = UpdateSettingsArgs {
+        canister_id: canister_id.into(),
+        settings: CanisterSettingsArgs {
+            wasm_memory_limit: Some(candid::Nat::from(wasm_memory_limit)),
+            ..CanisterSettingsArgs::new(None, None, None, None, None)
+        },
+    }
+    .encode();
+    test.subnet_message(Method::UpdateSettings, payload)
+        .map(|_| ())
+        .map_err(|err| err.description().to_string())
+}
+
+fn wasm_memory_limit(test: &mut ExecutionTest, canister_id: CanisterId) -> Option<u64> {
+    let result = test.canister_status(canister_id).unwrap();
+    CanisterStatusResultV2::decode(&result.bytes())
+        .unwrap()
+        .wasm_memory_limit()
+}
+
+#[test]
+fn wasm_memory_limit_is_reported_in_canister_status() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    assert_eq!(wasm_memory_limit(&mut test, canister_id), None);
+
+    set_wasm_memory_limit(&mut test, canister_id, 2 * WASM_PAGE_SIZE).unwrap();
+    assert_eq!(
+        wasm_memory_limit(&mut test, canister_id),
+        Some(2 * WASM_PAGE_SIZE)
+    );
+
+    // A limit of zero removes the limit.
+    set_wasm_memory_limit(&mut test, canister_id, 0).unwrap();
+    assert_eq!(wasm_memory_limit(&mut test, canister_id), None);
+}
+
+#[test]
+fn update_call_traps_when_growing_above_wasm_memory_limit() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    set_wasm_memory_limit(&mut test, canister_id, 2 * WASM_PAGE_SIZE).unwrap();
+
+    // Growing up to the limit succeeds.
+    test.ingress(canister_id, "grow", vec![]).unwrap();
+
+    let err = test.ingress(canister_id, "grow", vec![]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterOutOfMemory);
+    assert!(
+        err.description().contains("exceeded its Wasm memory limit"),
+        "{}",
+        err.description()
+    );
+
+    // Queries are not affected by the limit.
+    test.ingress(canister_id, "grow_query", vec![]).unwrap();
+
+    set_wasm_memory_limit(&mut test, canister_id, 0).unwrap();
+    test.ingress(canister_id, "grow", vec![]).unwrap();
+}
+
+#[test]
+fn upgrade_succeeds_above_wasm_memory_limit() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    set_wasm_memory_limit(&mut test, canister_id, 2 * WASM_PAGE_SIZE).unwrap();
+
+    // `canister_post_upgrade` grows the memory to 6 pages.
+    test.upgrade_canister(canister_id, wabt::wat2wasm(GROW_WAT).unwrap())
+        .unwrap();
+
+    let err = test.ingress(canister_id, "grow", vec![]).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterOutOfMemory);
+}
+
+#[test]
+fn wasm_memory_limit_out_of_range_is_rejected() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.canister_from_wat(GROW_WAT).unwrap();
+    let err = set_wasm_memory_limit(&mut test, canister_id, (1 << 48) + 1).unwrap_err();
+    assert!(err.contains("Wasm memory limit expected"), "{}", err);
+}
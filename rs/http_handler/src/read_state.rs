@@ -359,7 +359,9 @@ mod test {
     use ic_crypto_tree_hash::{Digest, Label, MixedHashTree, Path};
     use ic_interfaces_state_manager::Labeled;
     use ic_registry_subnet_type::SubnetType;
-    use ic_replicated_state::{BitcoinState, CanisterQueues, ReplicatedState, SystemMetadata};
+    use ic_replicated_state::{
+        BitcoinState, CanisterQueues, CanisterSnapshots, ReplicatedState, SystemMetadata,
+    };
     use ic_test_utilities::{
         mock_time,
         state::insert_dummy_canister,
@@ -502,6 +504,7 @@ mod test {
                     Height::from(1),
                     Arc::new(ReplicatedState::new_from_checkpoint(
                         BTreeMap::new(),
+                        CanisterSnapshots::default(),
                         metadata,
                         CanisterQueues::default(),
                         Vec::new(),
@@ -68,7 +68,9 @@ mod tests {
     use super::*;
     use ic_crypto_tree_hash::{flatmap, Label, LabeledTree};
     use ic_registry_subnet_type::SubnetType;
-    use ic_replicated_state::{BitcoinState, CanisterQueues, ReplicatedState, SystemMetadata};
+    use ic_replicated_state::{
+        BitcoinState, CanisterQueues, CanisterSnapshots, ReplicatedState, SystemMetadata,
+    };
     use ic_test_utilities::{
         mock_time, state::ReplicatedStateBuilder, state_manager::MockStateManager,
         types::ids::subnet_test_id,
@@ -97,6 +99,7 @@ mod tests {
                     Height::from(1),
                     Arc::new(ReplicatedState::new_from_checkpoint(
                         BTreeMap::new(),
+                        CanisterSnapshots::default(),
                         metadata,
                         CanisterQueues::default(),
                         Vec::new(),
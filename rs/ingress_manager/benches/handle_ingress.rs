@@ -29,7 +29,9 @@ use ic_registry_client::client::RegistryClientImpl;
 use ic_registry_keys::make_subnet_record_key;
 use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::{BitcoinState, CanisterQueues, ReplicatedState, SystemMetadata};
+use ic_replicated_state::{
+    BitcoinState, CanisterQueues, CanisterSnapshots, ReplicatedState, SystemMetadata,
+};
 use ic_test_utilities::{
     consensus::MockConsensusCache,
     crypto::temp_crypto_component_with_fake_registry,
@@ -185,6 +187,7 @@ where
                     Height::from(1),
                     Arc::new(ReplicatedState::new_from_checkpoint(
                         BTreeMap::new(),
+                        CanisterSnapshots::default(),
                         metadata,
                         CanisterQueues::default(),
                         Vec::new(),
@@ -9,6 +9,7 @@ use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_sys::{PageBytes, PageIndex};
 use ic_types::{
+    canister_log::CanisterLog,
     crypto::canister_threshold_sig::MasterEcdsaPublicKey,
     ingress::{IngressStatus, WasmResult},
     messages::{
@@ -491,6 +492,10 @@ pub trait SystemApi {
     /// Outputs the specified bytes on the heap as a string on STDOUT.
     fn ic0_debug_print(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()>;
 
+    /// Appends the specified bytes on the heap to the canister log. Unlike
+    /// `ic0_debug_print()`, it is not subject to rate limiting.
+    fn save_log_message(&mut self, src: u32, size: u32, heap: &[u8]);
+
     /// Traps, with a possibly helpful message
     fn ic0_trap(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()>;
 
@@ -663,6 +668,14 @@ pub trait SystemApi {
 
     fn ic0_time(&self) -> HypervisorResult<Time>;
 
+    /// Sets the canister's global timer to the given time and returns the
+    /// previous value of the timer. Passing zero deactivates the timer.
+    ///
+    /// Once the batch time passes the timer, the timer is deactivated and
+    /// the `canister_global_timer` method of the canister is scheduled for
+    /// execution. A deactivated timer is reported as zero.
+    fn ic0_global_timer_set(&mut self, time: Time) -> HypervisorResult<Time>;
+
     /// The canister can query the "performance counter", which is
     /// a deterministic monotonically increasing integer approximating
     /// the amount of work the canister has done since the beginning of
@@ -824,6 +837,16 @@ pub trait SystemApi {
     /// running, `2` indicates stopping, and `3` indicates stopped.
     fn ic0_canister_status(&self) -> HypervisorResult<u32>;
 
+    /// Returns the version of the canister, which is incremented every time
+    /// the code or the settings of the canister change.
+    fn ic0_canister_version(&self) -> HypervisorResult<u64>;
+
+    /// Returns `1` if the principal stored at `heap[src..src+size]` is one
+    /// of the controllers of the canister and `0` otherwise.
+    ///
+    /// Traps if the bytes are not a valid principal.
+    fn ic0_is_controller(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<u32>;
+
     /// Mints the `amount` cycles
     /// Adds cycles to the canister's balance.
     ///
@@ -916,6 +939,9 @@ pub struct WasmExecutionOutput {
     pub allocated_bytes: NumBytes,
     pub allocated_message_bytes: NumBytes,
     pub instance_stats: InstanceStats,
+    /// The log records produced by the execution, including the ones of a
+    /// failed execution.
+    pub canister_log: CanisterLog,
 }
 
 impl fmt::Display for WasmExecutionOutput {
@@ -1,6 +1,6 @@
 use ic_base_types::{CanisterIdError, PrincipalIdBlobParseError};
 use ic_error_types::UserError;
-use ic_types::{methods::WasmMethod, CanisterId, Cycles};
+use ic_types::{methods::WasmMethod, CanisterId, Cycles, NumBytes};
 use ic_wasm_types::{WasmEngineError, WasmInstrumentationError, WasmValidationError};
 use serde::{Deserialize, Serialize};
 
@@ -121,6 +121,12 @@ pub enum HypervisorError {
     /// The canister is close to running out of Wasm memory and
     /// attempted to allocate reserved Wasm pages.
     WasmReservedPages,
+    /// The canister attempted to grow its Wasm memory above the
+    /// `wasm_memory_limit` setting.
+    WasmMemoryLimitExceeded {
+        bytes: NumBytes,
+        limit: NumBytes,
+    },
     /// The execution was aborted by deterministic time slicing. This error is
     /// not observable by the user and should be processed before leaving Wasm
     /// execution.
@@ -178,6 +184,7 @@ impl HypervisorError {
                 let kind = match wasm_method {
                     WasmMethod::Update(_) => "update",
                     WasmMethod::Query(_) => "query",
+                    WasmMethod::CompositeQuery(_) => "composite query",
                     WasmMethod::System(_) => "system",
                 };
 
@@ -238,6 +245,13 @@ impl HypervisorError {
                     canister_id
                 ),
             ),
+            Self::WasmMemoryLimitExceeded { bytes, limit } => UserError::new(
+                E::CanisterOutOfMemory,
+                format!(
+                    "Canister {} exceeded its Wasm memory limit of {} bytes by growing its Wasm memory to {} bytes",
+                    canister_id, limit, bytes
+                ),
+            ),
             Self::CanisterStopped => UserError::new(
                 E::CanisterStopped,
                 format!("Canister {} is stopped", canister_id,),
@@ -314,6 +328,7 @@ impl HypervisorError {
             HypervisorError::Cleanup { .. } => "Cleanup",
             HypervisorError::WasmEngineError(_) => "WasmEngineError",
             HypervisorError::WasmReservedPages => "WasmReservedPages",
+            HypervisorError::WasmMemoryLimitExceeded { .. } => "WasmMemoryLimitExceeded",
             HypervisorError::Aborted => "Aborted",
         }
     }
@@ -347,7 +362,8 @@ impl HypervisorError {
             | HypervisorError::InvalidCanisterId(_)
             | HypervisorError::MessageRejected
             | HypervisorError::InsufficientCyclesBalance(_)
-            | HypervisorError::WasmReservedPages => false,
+            | HypervisorError::WasmReservedPages
+            | HypervisorError::WasmMemoryLimitExceeded { .. } => false,
         }
     }
 }
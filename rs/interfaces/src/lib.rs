@@ -18,6 +18,7 @@ pub mod ingress_manager;
 pub mod ingress_pool;
 pub mod messages;
 pub mod messaging;
+pub mod query_stats;
 pub mod registry;
 pub mod replica_config;
 pub mod self_validating_payload;
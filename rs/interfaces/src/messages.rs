@@ -1,4 +1,5 @@
 //! Messages used in various components.
+use ic_ic00_types::CanisterChangeOrigin;
 use ic_types::{
     messages::{Ingress, Request, Response, StopCanisterContext},
     Cycles, PrincipalId,
@@ -58,6 +59,19 @@ impl RequestOrIngress {
         }
     }
 
+    /// Returns the origin to record in the canister history for changes
+    /// triggered by this message.
+    pub fn canister_change_origin(&self) -> CanisterChangeOrigin {
+        match self {
+            RequestOrIngress::Request(request) => {
+                CanisterChangeOrigin::from_canister(request.sender.get(), None)
+            }
+            RequestOrIngress::Ingress(ingress) => {
+                CanisterChangeOrigin::from_user(ingress.source.get())
+            }
+        }
+    }
+
     /// Extracts the cycles received with this message.
     pub fn take_cycles(&mut self) -> Cycles {
         match self {
@@ -0,0 +1,82 @@
+use crate::validation::ValidationError;
+use ic_interfaces_state_manager::StateManagerError;
+use ic_types::{
+    batch::{QueryStatsEpoch, QueryStatsPayload, ValidationContext},
+    consensus::Payload,
+    CanisterId, Height, NodeId, NumBytes, Time,
+};
+
+/// A QueryStatsPayload error from which it is not possible to recover.
+#[derive(Debug)]
+pub enum InvalidQueryStatsPayload {
+    PayloadTooBig,
+    /// The epoch of the payload has not been completed yet.
+    EpochNotCompleted(QueryStatsEpoch),
+    /// Statistics for a later epoch were already delivered, so the statistics
+    /// of the payload would be ignored.
+    StaleEpoch(QueryStatsEpoch),
+    /// The proposer already delivered its statistics for the epoch.
+    DuplicateProposer(NodeId, QueryStatsEpoch),
+    /// The payload contains multiple entries for the same canister.
+    DuplicateCanister(CanisterId),
+    /// The payload claims a different proposer than the maker of the block.
+    InvalidProposer {
+        block_maker: NodeId,
+        payload_proposer: NodeId,
+    },
+}
+
+/// A QueryStatsPayload error from which it may be possible to recover.
+#[derive(Debug)]
+pub enum QueryStatsTransientValidationError {
+    GetStateFailed(Height, StateManagerError),
+}
+
+/// A QueryStatsPayload error that results from payload validation.
+pub type QueryStatsPayloadValidationError =
+    ValidationError<InvalidQueryStatsPayload, QueryStatsTransientValidationError>;
+
+pub trait QueryStatsPayloadBuilder: Send + Sync {
+    /// Produces a `QueryStatsPayload` of maximum byte size `byte_limit`
+    /// containing the query statistics this replica collected during the last
+    /// completed epoch, if they have not been included in the certified
+    /// state or in `past_payloads` yet.
+    fn get_query_stats_payload(
+        &self,
+        validation_context: &ValidationContext,
+        past_payloads: &[&QueryStatsPayload],
+        byte_limit: NumBytes,
+    ) -> Option<QueryStatsPayload>;
+
+    /// Checks whether the provided `QueryStatsPayload` of a block made by
+    /// `block_maker` is valid given a `ValidationContext` and `past_payloads`
+    /// (the `QueryStatsPayloads` from all blocks above the certified height,
+    /// in descending block height order).
+    ///
+    /// If valid, returns the payload's byte size; else returns a permanent or
+    /// transient `ValidationError`.
+    fn validate_query_stats_payload(
+        &self,
+        payload: &QueryStatsPayload,
+        block_maker: NodeId,
+        validation_context: &ValidationContext,
+        past_payloads: &[&QueryStatsPayload],
+    ) -> Result<NumBytes, QueryStatsPayloadValidationError>;
+
+    /// Extracts the sequence of past `QueryStatsPayloads` from `past_payloads`.
+    fn filter_past_payloads<'a>(
+        &self,
+        past_payloads: &'a [(Height, Time, Payload)],
+    ) -> Vec<&'a QueryStatsPayload> {
+        past_payloads
+            .iter()
+            .filter_map(|(_, _, payload)| {
+                if payload.is_summary() {
+                    None
+                } else {
+                    payload.as_ref().as_data().batch.query_stats.as_ref()
+                }
+            })
+            .collect()
+    }
+}
@@ -196,6 +196,10 @@ pub struct BitcoinStateBits {
     pub network: i32,
     #[prost(message, repeated, tag = "5")]
     pub utxos_large: ::prost::alloc::vec::Vec<Utxo>,
+    /// The height of the first block indexed in the address transaction index.
+    /// Not set if the index is not maintained for the network.
+    #[prost(uint32, optional, tag = "6")]
+    pub address_transactions_start_height: ::core::option::Option<u32>,
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
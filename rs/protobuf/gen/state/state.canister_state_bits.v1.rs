@@ -27,6 +27,8 @@ pub mod call_context {
         #[prost(uint64, tag = "2")]
         pub callback_id: u64,
     }
+    /// Used for all system tasks (heartbeat and global timer); the name is kept
+    /// for backwards compatibility.
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct Heartbeat {}
     #[derive(Clone, PartialEq, ::prost::Oneof)]
@@ -119,7 +121,7 @@ pub mod global {
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct WasmMethod {
-    #[prost(oneof = "wasm_method::WasmMethod", tags = "1, 2, 3")]
+    #[prost(oneof = "wasm_method::WasmMethod", tags = "1, 2, 3, 4")]
     pub wasm_method: ::core::option::Option<wasm_method::WasmMethod>,
 }
 /// Nested message and enum types in `WasmMethod`.
@@ -135,6 +137,7 @@ pub mod wasm_method {
         CanisterInspectMessage = 5,
         CanisterHeartbeat = 6,
         Empty = 7,
+        CanisterGlobalTimer = 8,
     }
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum WasmMethod {
@@ -144,6 +147,8 @@ pub mod wasm_method {
         Query(::prost::alloc::string::String),
         #[prost(enumeration = "SystemMethod", tag = "3")]
         System(i32),
+        #[prost(string, tag = "4")]
+        CompositeQuery(::prost::alloc::string::String),
     }
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -160,6 +165,114 @@ pub struct WasmMetadata {
         ::prost::alloc::collections::BTreeMap<::prost::alloc::string::String, WasmCustomSection>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterLogRecord {
+    #[prost(uint64, tag = "1")]
+    pub idx: u64,
+    #[prost(uint64, tag = "2")]
+    pub timestamp_nanos: u64,
+    #[prost(bytes = "vec", tag = "3")]
+    pub content: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WasmChunkData {
+    #[prost(bytes = "vec", tag = "1")]
+    pub hash: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub index: u64,
+    #[prost(uint64, tag = "3")]
+    pub length: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WasmChunkStoreMetadata {
+    #[prost(message, repeated, tag = "1")]
+    pub chunks: ::prost::alloc::vec::Vec<WasmChunkData>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterChangeFromUser {
+    #[prost(message, optional, tag = "1")]
+    pub user_id: ::core::option::Option<super::super::super::types::v1::PrincipalId>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterChangeFromCanister {
+    #[prost(message, optional, tag = "1")]
+    pub canister_id: ::core::option::Option<super::super::super::types::v1::PrincipalId>,
+    #[prost(uint64, optional, tag = "2")]
+    pub canister_version: ::core::option::Option<u64>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterCreation {
+    #[prost(message, repeated, tag = "1")]
+    pub controllers: ::prost::alloc::vec::Vec<super::super::super::types::v1::PrincipalId>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterCodeUninstall {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterCodeDeployment {
+    #[prost(enumeration = "CanisterInstallMode", tag = "1")]
+    pub mode: i32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub module_hash: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterControllersChange {
+    #[prost(message, repeated, tag = "1")]
+    pub controllers: ::prost::alloc::vec::Vec<super::super::super::types::v1::PrincipalId>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterChange {
+    #[prost(uint64, tag = "1")]
+    pub timestamp_nanos: u64,
+    #[prost(uint64, tag = "2")]
+    pub canister_version: u64,
+    #[prost(oneof = "canister_change::ChangeOrigin", tags = "3, 4")]
+    pub change_origin: ::core::option::Option<canister_change::ChangeOrigin>,
+    #[prost(oneof = "canister_change::ChangeDetails", tags = "5, 6, 7, 8")]
+    pub change_details: ::core::option::Option<canister_change::ChangeDetails>,
+}
+/// Nested message and enum types in `CanisterChange`.
+pub mod canister_change {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum ChangeOrigin {
+        #[prost(message, tag = "3")]
+        CanisterChangeFromUser(super::CanisterChangeFromUser),
+        #[prost(message, tag = "4")]
+        CanisterChangeFromCanister(super::CanisterChangeFromCanister),
+    }
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum ChangeDetails {
+        #[prost(message, tag = "5")]
+        CanisterCreation(super::CanisterCreation),
+        #[prost(message, tag = "6")]
+        CanisterCodeUninstall(super::CanisterCodeUninstall),
+        #[prost(message, tag = "7")]
+        CanisterCodeDeployment(super::CanisterCodeDeployment),
+        #[prost(message, tag = "8")]
+        CanisterControllersChange(super::CanisterControllersChange),
+    }
+}
+/// Statistics about the non-replicated queries executed on a canister, summed
+/// over all replicas of the subnet.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TotalQueryStats {
+    #[prost(uint64, tag = "1")]
+    pub num_calls: u64,
+    #[prost(uint64, tag = "2")]
+    pub num_instructions: u64,
+    #[prost(uint64, tag = "3")]
+    pub ingress_payload_size: u64,
+    #[prost(uint64, tag = "4")]
+    pub egress_payload_size: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterHistory {
+    /// The most recent changes, from the oldest to the newest.
+    #[prost(message, repeated, tag = "1")]
+    pub changes: ::prost::alloc::vec::Vec<CanisterChange>,
+    /// The number of changes ever recorded, including the dropped ones.
+    #[prost(uint64, tag = "2")]
+    pub total_num_changes: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ExecutionStateBits {
     #[prost(message, repeated, tag = "1")]
     pub exported_globals: ::prost::alloc::vec::Vec<Global>,
@@ -317,6 +430,35 @@ pub struct CanisterStateBits {
     /// canister.
     #[prost(message, repeated, tag = "30")]
     pub task_queue: ::prost::alloc::vec::Vec<ExecutionTask>,
+    /// The time (in nanoseconds since the Unix epoch) at which the canister's
+    /// global timer expires. Unset if the global timer is not active.
+    #[prost(uint64, optional, tag = "31")]
+    pub global_timer_nanos: ::core::option::Option<u64>,
+    /// Who may fetch the canister log.
+    #[prost(enumeration = "LogVisibility", tag = "32")]
+    pub log_visibility: i32,
+    /// The log records of the canister, from the oldest to the newest.
+    #[prost(message, repeated, tag = "33")]
+    pub canister_log_records: ::prost::alloc::vec::Vec<CanisterLogRecord>,
+    /// The index that will be assigned to the next log record.
+    #[prost(uint64, tag = "34")]
+    pub next_canister_log_record_idx: u64,
+    /// The hashes and locations of the chunks in the Wasm chunk store.
+    #[prost(message, optional, tag = "35")]
+    pub wasm_chunk_store_metadata: ::core::option::Option<WasmChunkStoreMetadata>,
+    /// The version of the canister, see `ic0.canister_version`.
+    #[prost(uint64, tag = "36")]
+    pub canister_version: u64,
+    /// The most recent changes of the canister, see `canister_info`.
+    #[prost(message, optional, tag = "37")]
+    pub canister_history: ::core::option::Option<CanisterHistory>,
+    /// The upper bound on the Wasm heap of the canister in bytes. Unset if
+    /// there is no limit.
+    #[prost(uint64, optional, tag = "38")]
+    pub wasm_memory_limit: ::core::option::Option<u64>,
+    /// The query statistics of all completed epochs, see `canister_status`.
+    #[prost(message, optional, tag = "39")]
+    pub total_query_stats: ::core::option::Option<TotalQueryStats>,
     #[prost(oneof = "canister_state_bits::CanisterStatus", tags = "11, 12, 13")]
     pub canister_status: ::core::option::Option<canister_state_bits::CanisterStatus>,
 }
@@ -332,6 +474,31 @@ pub mod canister_state_bits {
         Stopped(super::CanisterStatusStopped),
     }
 }
+/// The bits of a canister snapshot that are not stored in separate files.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterSnapshotBits {
+    #[prost(message, optional, tag = "1")]
+    pub canister_id: ::core::option::Option<super::super::super::types::v1::CanisterId>,
+    /// The time (in nanoseconds since the Unix epoch) at which the snapshot was
+    /// taken.
+    #[prost(uint64, tag = "2")]
+    pub taken_at_timestamp_nanos: u64,
+    #[prost(message, repeated, tag = "3")]
+    pub exported_globals: ::prost::alloc::vec::Vec<Global>,
+    #[prost(uint32, tag = "4")]
+    pub heap_size: u32,
+    /// The size of the snapshot's stable memory in Wasm pages.
+    #[prost(uint64, tag = "5")]
+    pub stable_memory_size: u64,
+    #[prost(message, repeated, tag = "6")]
+    pub exports: ::prost::alloc::vec::Vec<WasmMethod>,
+    #[prost(message, optional, tag = "7")]
+    pub metadata: ::core::option::Option<WasmMetadata>,
+    #[prost(bytes = "vec", tag = "8")]
+    pub certified_data: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", optional, tag = "9")]
+    pub binary_hash: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum CustomSectionType {
@@ -339,3 +506,18 @@ pub enum CustomSectionType {
     Public = 1,
     Private = 2,
 }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum LogVisibility {
+    Unspecified = 0,
+    Controllers = 1,
+    Public = 2,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum CanisterInstallMode {
+    Unspecified = 0,
+    Install = 1,
+    Reinstall = 2,
+    Upgrade = 3,
+}
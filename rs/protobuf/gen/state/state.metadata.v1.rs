@@ -178,6 +178,33 @@ pub struct SubnetMetrics {
         ::core::option::Option<super::super::super::types::v1::NominalCycles>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EpochQueryStatsEntry {
+    #[prost(message, optional, tag = "1")]
+    pub canister_id: ::core::option::Option<super::super::super::types::v1::CanisterId>,
+    #[prost(uint64, tag = "2")]
+    pub num_calls: u64,
+    #[prost(uint64, tag = "3")]
+    pub num_instructions: u64,
+    #[prost(uint64, tag = "4")]
+    pub ingress_payload_size: u64,
+    #[prost(uint64, tag = "5")]
+    pub egress_payload_size: u64,
+    /// The replica that delivered these statistics.
+    #[prost(message, optional, tag = "6")]
+    pub proposer: ::core::option::Option<super::super::super::types::v1::NodeId>,
+}
+/// The query statistics delivered for the epoch that is being aggregated.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EpochQueryStats {
+    /// Unset if no query statistics have been delivered yet.
+    #[prost(uint64, optional, tag = "1")]
+    pub epoch: ::core::option::Option<u64>,
+    #[prost(message, repeated, tag = "2")]
+    pub proposers: ::prost::alloc::vec::Vec<super::super::super::types::v1::NodeId>,
+    #[prost(message, repeated, tag = "3")]
+    pub stats: ::prost::alloc::vec::Vec<EpochQueryStatsEntry>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SystemMetadata {
     #[prost(uint64, tag = "1")]
     pub generated_id_counter: u64,
@@ -225,6 +252,11 @@ pub struct SystemMetadata {
     pub time_of_last_allocation_charge_nanos: ::core::option::Option<TimeOfLastAllocationCharge>,
     #[prost(message, optional, tag = "15")]
     pub subnet_metrics: ::core::option::Option<SubnetMetrics>,
+    /// A counter used for generating new canister snapshot ids.
+    #[prost(uint64, tag = "18")]
+    pub next_snapshot_id: u64,
+    #[prost(message, optional, tag = "19")]
+    pub epoch_query_stats: ::core::option::Option<EpochQueryStats>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct StableMemory {
@@ -3,7 +3,7 @@ use ic_interfaces::{consensus::PayloadValidationError, validation::ValidationRes
 use ic_types::{
     batch::{BatchPayload, ValidationContext},
     consensus::Payload,
-    Height, Time,
+    Height, NodeId, Time,
 };
 
 /// A mock we're using to instantiate the consensus Validator. Since notarizations
@@ -24,6 +24,7 @@ impl PayloadBuilder for MockPayloadBuilder {
     fn validate_payload(
         &self,
         _height: Height,
+        _block_maker: NodeId,
         _payload: &Payload,
         _past_payloads: &[(Height, Time, Payload)],
         _context: &ValidationContext,
@@ -4,7 +4,7 @@ use ic_config::{artifact_pool::ArtifactPoolConfig, subnet_config::SubnetConfig,
 use ic_consensus::certification::VerifierImpl;
 use ic_crypto::CryptoComponent;
 use ic_cycles_account_manager::CyclesAccountManager;
-use ic_execution_environment::ExecutionServices;
+use ic_execution_environment::{ExecutionServices, QueryStatsPayloadBuilderImpl};
 use ic_interfaces::execution_environment::AnonymousQueryService;
 use ic_interfaces::{
     certified_stream_store::CertifiedStreamStore,
@@ -240,6 +240,13 @@ pub fn construct_ic_stack(
     );
     let self_validating_payload_builder = Arc::new(self_validating_payload_builder);
 
+    let query_stats_payload_builder = Arc::new(QueryStatsPayloadBuilderImpl::new(
+        execution_services.query_stats_collector,
+        Arc::clone(&state_manager) as Arc<_>,
+        node_id,
+        replica_logger.clone(),
+    ));
+
     let canister_http_adapter_client = ic_canister_http_adapter_client::setup_canister_http_client(
         rt_handle.clone(),
         &metrics_registry,
@@ -263,6 +270,7 @@ pub fn construct_ic_stack(
         P2PStateSyncClient::Client(Arc::clone(&state_manager) as Arc<_>),
         xnet_payload_builder as Arc<_>,
         self_validating_payload_builder as Arc<_>,
+        query_stats_payload_builder as Arc<_>,
         message_router as Arc<_>,
         // TODO(SCL-213)
         Arc::clone(&crypto) as Arc<_>,
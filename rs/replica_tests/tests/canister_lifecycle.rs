@@ -679,6 +679,8 @@ fn can_get_canister_information() {
                 None,
                 2592000,
                 0u128,
+                None,
+                Default::default(),
             )
         );
 
@@ -730,6 +732,8 @@ fn can_get_canister_information() {
                     None,
                     259200,
                     0u128,
+                    None,
+                    Default::default(),
                 ),
                 CanisterStatusResultV2::decode(&res).unwrap(),
                 2 * BALANCE_EPSILON,
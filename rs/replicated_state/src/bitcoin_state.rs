@@ -8,6 +8,8 @@ use ic_protobuf::{
     bitcoin::v1 as pb_bitcoin,
     proxy::{try_from_option_field, ProxyDecodeError},
 };
+use ic_sys::PAGE_SIZE;
+use ic_types::NumBytes;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{BTreeMap, VecDeque},
@@ -224,6 +226,11 @@ pub struct BitcoinState {
     pub unstable_blocks: UnstableBlocks,
     pub stable_height: u32,
     pub fee_percentiles_cache: Option<FeePercentilesCache>,
+    pub block_headers: PageMap,
+    pub address_transactions: PageMap,
+    /// The height of the first block indexed in `address_transactions`, or
+    /// `None` if the index is not maintained for the network.
+    pub address_transactions_start_height: Option<u32>,
 }
 
 impl Default for BitcoinState {
@@ -247,6 +254,9 @@ impl BitcoinState {
             ),
             stable_height: 0,
             fee_percentiles_cache: None,
+            block_headers: PageMap::default(),
+            address_transactions: PageMap::default(),
+            address_transactions_start_height: None,
         }
     }
 
@@ -258,6 +268,9 @@ impl BitcoinState {
             unstable_blocks: UnstableBlocks::default(),
             stable_height: 0,
             fee_percentiles_cache: None,
+            block_headers: PageMap::default(),
+            address_transactions: PageMap::default(),
+            address_transactions_start_height: None,
         }
     }
 
@@ -271,6 +284,28 @@ impl BitcoinState {
         }
     }
 
+    /// Returns the memory taken by the address transaction index in bytes.
+    ///
+    /// Only reported as a metric: the memory of the bitcoin state is neither
+    /// charged to a canister nor counted against the subnet memory capacity.
+    pub fn address_transactions_memory_usage(&self) -> NumBytes {
+        page_map_memory_usage(&self.address_transactions)
+    }
+
+    /// Returns the memory taken by the page maps of the bitcoin state in bytes.
+    pub fn memory_usage(&self) -> NumBytes {
+        [
+            &self.utxo_set.utxos_small,
+            &self.utxo_set.utxos_medium,
+            &self.utxo_set.address_outpoints,
+            &self.block_headers,
+            &self.address_transactions,
+        ]
+        .iter()
+        .map(|page_map| page_map_memory_usage(page_map))
+        .sum()
+    }
+
     /// Returns an iterator over the existing requests to the Bitcoin Adapter.
     pub fn adapter_requests_iter(
         &self,
@@ -405,5 +440,9 @@ pub struct FeePercentilesCache {
     pub fee_percentiles: Vec<MillisatoshiPerByte>,
 }
 
+fn page_map_memory_usage(page_map: &PageMap) -> NumBytes {
+    NumBytes::from((page_map.num_host_pages() * PAGE_SIZE) as u64)
+}
+
 #[cfg(test)]
 mod tests;
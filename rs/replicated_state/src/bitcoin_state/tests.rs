@@ -1,9 +1,11 @@
-use crate::{BitcoinState, BitcoinStateError};
+use crate::{page_map::PageIndex, BitcoinState, BitcoinStateError};
 use ic_btc_types::Network;
 use ic_btc_types_internal::{
     BitcoinAdapterRequestWrapper, BitcoinAdapterResponse, BitcoinAdapterResponseWrapper,
     GetSuccessorsRequest, GetSuccessorsResponse, SendTransactionRequest, SendTransactionResponse,
 };
+use ic_sys::PAGE_SIZE;
+use ic_types::NumBytes;
 
 #[test]
 fn can_push_requests_until_capacity_reached() {
@@ -160,3 +162,26 @@ fn can_pop_responses_in_the_correct_order() {
     }
     assert_eq!(bitcoin_state.adapter_queues.pop_response(), None);
 }
+
+#[test]
+fn memory_usage_includes_address_transactions() {
+    let mut bitcoin_state = BitcoinState::default();
+    assert_eq!(bitcoin_state.memory_usage(), NumBytes::from(0));
+
+    bitcoin_state
+        .utxo_set
+        .utxos_small
+        .update(&[(PageIndex::new(0), &[1u8; PAGE_SIZE])]);
+    bitcoin_state
+        .address_transactions
+        .update(&[(PageIndex::new(1), &[1u8; PAGE_SIZE])]);
+
+    assert_eq!(
+        bitcoin_state.address_transactions_memory_usage(),
+        NumBytes::from(2 * PAGE_SIZE as u64)
+    );
+    assert_eq!(
+        bitcoin_state.memory_usage(),
+        NumBytes::from(3 * PAGE_SIZE as u64)
+    );
+}
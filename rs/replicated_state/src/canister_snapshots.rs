@@ -0,0 +1,292 @@
+use crate::{
+    canister_state::execution_state::{WasmBinary, WasmMetadata},
+    num_bytes_try_from, ExecutionState, ExportedFunctions, Global, Memory,
+};
+use ic_types::{CanisterId, NumBytes, Time};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The maximum number of snapshots that a single canister can have.
+pub const MAX_SNAPSHOTS_PER_CANISTER: usize = 1;
+
+/// The id of a canister snapshot.
+///
+/// Snapshot ids are generated from a counter in `SystemMetadata` and are thus
+/// unique within a subnet. The management canister exposes them as opaque
+/// blobs, see `to_bytes()` and `from_bytes()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnapshotId(u64);
+
+impl SnapshotId {
+    pub fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns the blob representation of this id used by the management
+    /// canister.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_be_bytes().to_vec()
+    }
+
+    /// Parses a snapshot id from its blob representation. Returns `None` if
+    /// the blob does not have the expected length.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes
+            .try_into()
+            .ok()
+            .map(|bytes| Self(u64::from_be_bytes(bytes)))
+    }
+}
+
+impl std::fmt::Display for SnapshotId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A copy of the Wasm module, memories, globals and certified data of a
+/// canister, taken via the `take_canister_snapshot` method of the management
+/// canister.
+///
+/// Snapshots are immutable: the `PageMap`s of the memories are persistent
+/// data structures, so taking a snapshot is cheap and later changes to the
+/// canister do not affect it.
+#[derive(Clone, Debug)]
+pub struct CanisterSnapshot {
+    canister_id: CanisterId,
+    taken_at_timestamp: Time,
+    wasm_binary: Arc<WasmBinary>,
+    exports: ExportedFunctions,
+    metadata: WasmMetadata,
+    exported_globals: Vec<Global>,
+    wasm_memory: Memory,
+    stable_memory: Memory,
+    certified_data: Vec<u8>,
+}
+
+// We have to implement it by hand as the embedder cache of the Wasm binary
+// can not be compared for equality (and doesn't need to be).
+impl PartialEq for CanisterSnapshot {
+    fn eq(&self, rhs: &Self) -> bool {
+        (
+            &self.canister_id,
+            &self.taken_at_timestamp,
+            &self.wasm_binary.binary,
+            &self.exports,
+            &self.metadata,
+            &self.exported_globals,
+            &self.wasm_memory,
+            &self.stable_memory,
+            &self.certified_data,
+        ) == (
+            &rhs.canister_id,
+            &rhs.taken_at_timestamp,
+            &rhs.wasm_binary.binary,
+            &rhs.exports,
+            &rhs.metadata,
+            &rhs.exported_globals,
+            &rhs.wasm_memory,
+            &rhs.stable_memory,
+            &rhs.certified_data,
+        )
+    }
+}
+
+impl CanisterSnapshot {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        canister_id: CanisterId,
+        taken_at_timestamp: Time,
+        wasm_binary: Arc<WasmBinary>,
+        exports: ExportedFunctions,
+        metadata: WasmMetadata,
+        exported_globals: Vec<Global>,
+        wasm_memory: Memory,
+        stable_memory: Memory,
+        certified_data: Vec<u8>,
+    ) -> Self {
+        Self {
+            canister_id,
+            taken_at_timestamp,
+            wasm_binary,
+            exports,
+            metadata,
+            exported_globals,
+            wasm_memory,
+            stable_memory,
+            certified_data,
+        }
+    }
+
+    /// Takes a snapshot of the given execution state.
+    pub fn from_execution_state(
+        canister_id: CanisterId,
+        taken_at_timestamp: Time,
+        execution_state: &ExecutionState,
+        certified_data: Vec<u8>,
+    ) -> Self {
+        Self::new(
+            canister_id,
+            taken_at_timestamp,
+            Arc::clone(&execution_state.wasm_binary),
+            execution_state.exports.clone(),
+            execution_state.metadata.clone(),
+            execution_state.exported_globals.clone(),
+            // The sandbox memory is not shared with the canister, which keeps
+            // on executing after the snapshot is taken.
+            Memory::new(
+                execution_state.wasm_memory.page_map.clone(),
+                execution_state.wasm_memory.size,
+            ),
+            Memory::new(
+                execution_state.stable_memory.page_map.clone(),
+                execution_state.stable_memory.size,
+            ),
+            certified_data,
+        )
+    }
+
+    /// Builds the execution state that results from loading this snapshot.
+    ///
+    /// The Wasm binary (and thus its compilation cache) is shared with the
+    /// snapshot, so no recompilation is needed. All pages of the memories are
+    /// part of their round delta, so the caller must truncate the memory files
+    /// of the canister, as it is done when reinstalling a canister.
+    pub fn to_execution_state(&self, canister_root: PathBuf) -> ExecutionState {
+        ExecutionState::new(
+            canister_root,
+            Arc::clone(&self.wasm_binary),
+            self.exports.clone(),
+            Memory::new(
+                self.wasm_memory.page_map.copy_with_full_delta(),
+                self.wasm_memory.size,
+            ),
+            Memory::new(
+                self.stable_memory.page_map.copy_with_full_delta(),
+                self.stable_memory.size,
+            ),
+            self.exported_globals.clone(),
+            self.metadata.clone(),
+        )
+    }
+
+    pub fn canister_id(&self) -> CanisterId {
+        self.canister_id
+    }
+
+    pub fn taken_at_timestamp(&self) -> Time {
+        self.taken_at_timestamp
+    }
+
+    pub fn wasm_binary(&self) -> &Arc<WasmBinary> {
+        &self.wasm_binary
+    }
+
+    pub fn exports(&self) -> &ExportedFunctions {
+        &self.exports
+    }
+
+    pub fn metadata(&self) -> &WasmMetadata {
+        &self.metadata
+    }
+
+    pub fn exported_globals(&self) -> &[Global] {
+        &self.exported_globals
+    }
+
+    pub fn wasm_memory(&self) -> &Memory {
+        &self.wasm_memory
+    }
+
+    pub fn stable_memory(&self) -> &Memory {
+        &self.stable_memory
+    }
+
+    pub fn certified_data(&self) -> &[u8] {
+        &self.certified_data
+    }
+
+    /// Returns the memory taken by this snapshot. It is computed the same way
+    /// as the memory usage of an `ExecutionState`, plus the certified data.
+    pub fn size(&self) -> NumBytes {
+        // We use 8 bytes per global.
+        let globals_size_bytes = 8 * self.exported_globals.len() as u64;
+        let wasm_binary_size_bytes = self.wasm_binary.binary.len() as u64;
+        num_bytes_try_from(self.wasm_memory.size)
+            .expect("could not convert from wasm memory number of pages to bytes")
+            + num_bytes_try_from(self.stable_memory.size)
+                .expect("could not convert from stable memory number of pages to bytes")
+            + NumBytes::from(globals_size_bytes)
+            + NumBytes::from(wasm_binary_size_bytes)
+            + NumBytes::from(self.certified_data.len() as u64)
+    }
+}
+
+/// The canister snapshots of a subnet, indexed by snapshot id.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CanisterSnapshots {
+    snapshots: BTreeMap<SnapshotId, Arc<CanisterSnapshot>>,
+}
+
+impl CanisterSnapshots {
+    pub fn new(snapshots: BTreeMap<SnapshotId, Arc<CanisterSnapshot>>) -> Self {
+        Self { snapshots }
+    }
+
+    /// Adds a snapshot with the given id.
+    pub fn insert(&mut self, snapshot_id: SnapshotId, snapshot: CanisterSnapshot) {
+        self.snapshots.insert(snapshot_id, Arc::new(snapshot));
+    }
+
+    pub fn get(&self, snapshot_id: SnapshotId) -> Option<&Arc<CanisterSnapshot>> {
+        self.snapshots.get(&snapshot_id)
+    }
+
+    /// Removes the snapshot with the given id and returns it, if it exists.
+    pub fn remove(&mut self, snapshot_id: SnapshotId) -> Option<Arc<CanisterSnapshot>> {
+        self.snapshots.remove(&snapshot_id)
+    }
+
+    /// Removes all snapshots of the given canister.
+    pub fn remove_all(&mut self, canister_id: CanisterId) {
+        self.snapshots
+            .retain(|_, snapshot| snapshot.canister_id != canister_id);
+    }
+
+    /// Returns an iterator over all snapshots, ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = (&SnapshotId, &Arc<CanisterSnapshot>)> {
+        self.snapshots.iter()
+    }
+
+    /// Returns the snapshots of the given canister, ordered by id.
+    pub fn list(
+        &self,
+        canister_id: CanisterId,
+    ) -> impl Iterator<Item = (&SnapshotId, &Arc<CanisterSnapshot>)> {
+        self.snapshots
+            .iter()
+            .filter(move |(_, snapshot)| snapshot.canister_id == canister_id)
+    }
+
+    /// Returns the number of snapshots of the given canister.
+    pub fn count(&self, canister_id: CanisterId) -> usize {
+        self.list(canister_id).count()
+    }
+
+    /// Returns the memory taken by the snapshots of the given canister.
+    pub fn memory_taken(&self, canister_id: CanisterId) -> NumBytes {
+        self.list(canister_id)
+            .map(|(_, snapshot)| snapshot.size())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
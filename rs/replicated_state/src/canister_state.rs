@@ -189,7 +189,9 @@ impl CanisterState {
         match (next_task, self.has_input()) {
             (None, false) => NextExecution::None,
             (None, true) => NextExecution::StartNew,
-            (Some(ExecutionTask::Heartbeat), _) => NextExecution::StartNew,
+            (Some(ExecutionTask::Heartbeat), _) | (Some(ExecutionTask::GlobalTimer), _) => {
+                NextExecution::StartNew
+            }
             (Some(ExecutionTask::AbortedExecution(..)), _)
             | (Some(ExecutionTask::PausedExecution(..)), _) => NextExecution::ContinueLong,
             (Some(ExecutionTask::AbortedInstallCode(..)), _)
@@ -334,6 +336,8 @@ impl CanisterState {
         self.execution_state
             .as_ref()
             .map_or(NumBytes::from(0), |es| es.memory_usage())
+            + self.system_state.wasm_chunk_store.memory_usage()
+            + self.system_state.snapshots_memory_usage
             + message_memory_usage
     }
 
@@ -379,6 +383,16 @@ impl CanisterState {
         }
     }
 
+    /// Returns true if the canister exports the `canister_global_timer` system
+    /// method.
+    pub fn exports_global_timer_method(&self) -> bool {
+        match &self.execution_state {
+            Some(execution_state) => execution_state
+                .exports_method(&WasmMethod::System(SystemMethod::CanisterGlobalTimer)),
+            None => false,
+        }
+    }
+
     /// Returns true if the canister contains an exported query method with the
     /// name provided, false otherwise.
     pub fn exports_query_method(&self, method_name: String) -> bool {
@@ -390,6 +404,17 @@ impl CanisterState {
         }
     }
 
+    /// Returns true if the canister contains an exported composite query method
+    /// with the name provided, false otherwise.
+    pub fn exports_composite_query_method(&self, method_name: String) -> bool {
+        match &self.execution_state {
+            Some(execution_state) => {
+                execution_state.exports_method(&WasmMethod::CompositeQuery(method_name))
+            }
+            None => false,
+        }
+    }
+
     /// Returns the number of global variables in the Wasm module.
     pub fn num_wasm_globals(&self) -> usize {
         match &self.execution_state {
@@ -1,11 +1,15 @@
 mod call_context_manager;
+mod canister_history;
+mod wasm_chunk_store;
 
 use super::queues::can_push;
 pub use super::queues::memory_required_to_push_request;
 pub use crate::canister_state::queues::CanisterOutputQueuesIterator;
 use crate::{CanisterQueues, InputQueueType, StateError};
 pub use call_context_manager::{CallContext, CallContextAction, CallContextManager, CallOrigin};
+pub use canister_history::{CanisterHistory, MAX_CANISTER_HISTORY_CHANGES};
 use ic_base_types::NumSeconds;
+use ic_ic00_types::{CanisterChange, CanisterChangeDetails, CanisterChangeOrigin, LogVisibility};
 use ic_interfaces::messages::{CanisterInputMessage, RequestOrIngress};
 use ic_protobuf::{
     proxy::{try_from_option_field, ProxyDecodeError},
@@ -13,6 +17,8 @@ use ic_protobuf::{
 };
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{
+    batch::QueryStats,
+    canister_log::CanisterLog,
     messages::{Ingress, Request, RequestOrResponse, Response, StopCanisterContext},
     nominal_cycles::NominalCycles,
     CanisterId, Cycles, MemoryAllocation, NumBytes, PrincipalId, QueueIndex, Time,
@@ -26,6 +32,10 @@ use std::{
 };
 use std::{collections::BTreeSet, sync::Arc};
 use std::{collections::VecDeque, str::FromStr};
+pub use wasm_chunk_store::{
+    ChunkInfo, WasmChunkHash, WasmChunkStore, WasmChunkStoreMetadata, CHUNK_SIZE,
+    DEFAULT_MAX_CHUNKS,
+};
 
 lazy_static! {
     static ref DEFAULT_PRINCIPAL_MULTIPLE_CONTROLLERS: PrincipalId =
@@ -47,6 +57,54 @@ pub struct CanisterMetrics {
     pub consumed_cycles_since_replica_started: NominalCycles,
 }
 
+/// The state of a canister's global timer.
+///
+/// The timer is set by the canister by calling `ic0.global_timer_set`. Once
+/// the batch time passes the timer, the timer is deactivated and the
+/// `canister_global_timer` method of the canister is executed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CanisterTimer {
+    /// The global timer is deactivated.
+    Inactive,
+    /// The global timer is active and expires at the given time.
+    Active(Time),
+}
+
+impl CanisterTimer {
+    /// Converts the value passed to `ic0.global_timer_set` into a timer,
+    /// where zero deactivates the timer.
+    pub fn from_time(time: Time) -> Self {
+        if time == Time::from_nanos_since_unix_epoch(0) {
+            Self::Inactive
+        } else {
+            Self::Active(time)
+        }
+    }
+
+    /// Converts the timer into the value returned by `ic0.global_timer_set`,
+    /// where zero stands for an inactive timer.
+    pub fn to_time(&self) -> Time {
+        match self {
+            Self::Inactive => Time::from_nanos_since_unix_epoch(0),
+            Self::Active(time) => *time,
+        }
+    }
+
+    /// Returns true if the timer is active and expired at the given time.
+    pub fn has_reached_deadline(&self, now: Time) -> bool {
+        match self {
+            Self::Inactive => false,
+            Self::Active(deadline) => *deadline <= now,
+        }
+    }
+}
+
+impl Default for CanisterTimer {
+    fn default() -> Self {
+        Self::Inactive
+    }
+}
+
 /// State that is controlled and owned by the system (IC).
 ///
 /// Contains structs needed for running and maintaining the canister on the IC.
@@ -96,6 +154,43 @@ pub struct SystemState {
     /// Tasks to execute before processing input messages.
     /// Currently the task queue is empty outside of execution rounds.
     pub task_queue: VecDeque<ExecutionTask>,
+
+    /// The canister's global timer, set via `ic0.global_timer_set`.
+    pub global_timer: CanisterTimer,
+
+    /// Who may fetch the canister log via `fetch_canister_logs`.
+    pub log_visibility: LogVisibility,
+
+    /// The log records produced by `ic0.debug_print` and by traps.
+    pub canister_log: CanisterLog,
+
+    /// The chunks uploaded via `upload_chunk` for `install_chunked_code`.
+    pub wasm_chunk_store: WasmChunkStore,
+
+    /// The version of the canister, returned by `ic0.canister_version`.
+    ///
+    /// It starts at zero and is incremented every time the code or the
+    /// settings of the canister change, see `bump_canister_version()`.
+    pub canister_version: u64,
+
+    /// The most recent changes of the canister, returned by `canister_info`.
+    pub canister_history: CanisterHistory,
+
+    /// The upper bound on the Wasm heap of the canister. Update calls that
+    /// grow the heap beyond it trap. `None` means there is no such limit.
+    pub wasm_memory_limit: Option<NumBytes>,
+
+    /// The statistics of the non-replicated queries executed on the canister
+    /// by all replicas of the subnet during the completed query stats epochs.
+    /// It is an estimate derived from the median of the statistics reported by
+    /// the replicas, see `EpochQueryStats`.
+    pub total_query_stats: QueryStats,
+
+    /// The memory taken by the snapshots of the canister.
+    ///
+    /// Derived from `ReplicatedState::canister_snapshots`, which keeps it up
+    /// to date, so it is not persisted.
+    pub snapshots_memory_usage: NumBytes,
 }
 
 /// A wrapper around the different canister statuses.
@@ -191,6 +286,10 @@ pub enum ExecutionTask {
     // serialized.
     Heartbeat,
 
+    // A global timer task exists only within an execution round. It is never
+    // serialized.
+    GlobalTimer,
+
     // A paused execution task exists only within an epoch (between
     // checkpoints). It is never serialized and turns into `AbortedExecution`
     // before the checkpoint.
@@ -218,6 +317,7 @@ impl From<&ExecutionTask> for pb::ExecutionTask {
     fn from(item: &ExecutionTask) -> Self {
         match item {
             ExecutionTask::Heartbeat
+            | ExecutionTask::GlobalTimer
             | ExecutionTask::PausedExecution(_)
             | ExecutionTask::PausedInstallCode(_) => {
                 panic!("Attempt to serialize ephemeral task: {:?}.", item);
@@ -358,6 +458,15 @@ impl SystemState {
             certified_data: Default::default(),
             canister_metrics: CanisterMetrics::default(),
             task_queue: Default::default(),
+            global_timer: CanisterTimer::Inactive,
+            log_visibility: LogVisibility::default(),
+            canister_log: CanisterLog::default(),
+            wasm_chunk_store: WasmChunkStore::default(),
+            canister_version: 0,
+            canister_history: CanisterHistory::default(),
+            wasm_memory_limit: None,
+            total_query_stats: QueryStats::default(),
+            snapshots_memory_usage: NumBytes::from(0),
         }
     }
 
@@ -389,6 +498,14 @@ impl SystemState {
         canister_metrics: CanisterMetrics,
         cycles_balance: Cycles,
         task_queue: VecDeque<ExecutionTask>,
+        global_timer: CanisterTimer,
+        log_visibility: LogVisibility,
+        canister_log: CanisterLog,
+        wasm_chunk_store: WasmChunkStore,
+        canister_version: u64,
+        canister_history: CanisterHistory,
+        wasm_memory_limit: Option<NumBytes>,
+        total_query_stats: QueryStats,
     ) -> Self {
         Self {
             controllers,
@@ -401,6 +518,16 @@ impl SystemState {
             canister_metrics,
             cycles_balance,
             task_queue,
+            global_timer,
+            log_visibility,
+            canister_log,
+            wasm_chunk_store,
+            canister_version,
+            canister_history,
+            wasm_memory_limit,
+            total_query_stats,
+            // Set by `ReplicatedState::new_from_checkpoint()`.
+            snapshots_memory_usage: NumBytes::from(0),
         }
     }
 
@@ -408,6 +535,30 @@ impl SystemState {
         self.canister_id
     }
 
+    /// Increments the canister version. Must be called whenever the code,
+    /// the controllers or the other settings of the canister change.
+    pub fn bump_canister_version(&mut self) {
+        self.canister_version += 1;
+    }
+
+    /// Records a change of the canister in its history. The change is
+    /// tagged with the current canister version, so this must be called
+    /// after `bump_canister_version()`.
+    pub fn add_canister_change(
+        &mut self,
+        time: Time,
+        origin: CanisterChangeOrigin,
+        details: CanisterChangeDetails,
+    ) {
+        self.canister_history
+            .add_canister_change(CanisterChange::new(
+                time.as_nanos_since_unix_epoch(),
+                self.canister_version,
+                origin,
+                details,
+            ));
+    }
+
     /// Returns a mutable reference to the balance of the canister.
     pub fn balance_mut(&mut self) -> &mut Cycles {
         &mut self.cycles_balance
@@ -218,7 +218,9 @@ pub enum CallOrigin {
     CanisterUpdate(CanisterId, CallbackId),
     Query(UserId),
     CanisterQuery(CanisterId, CallbackId),
-    Heartbeat,
+    /// A call context created by the system for executing a system task, such
+    /// as `canister_heartbeat` or `canister_global_timer`.
+    SystemTask,
 }
 
 impl From<&CallOrigin> for pb::call_context::CallOrigin {
@@ -241,7 +243,7 @@ impl From<&CallOrigin> for pb::call_context::CallOrigin {
                     callback_id: callback_id.get(),
                 })
             }
-            CallOrigin::Heartbeat => Self::Heartbeat(pb::call_context::Heartbeat {}),
+            CallOrigin::SystemTask => Self::Heartbeat(pb::call_context::Heartbeat {}),
         }
     }
 }
@@ -281,7 +283,7 @@ impl TryFrom<pb::call_context::CallOrigin> for CallOrigin {
                 try_from_option_field(canister_id, "CallOrigin::CanisterQuery::canister_id")?,
                 callback_id.into(),
             ),
-            pb::call_context::CallOrigin::Heartbeat { .. } => Self::Heartbeat,
+            pb::call_context::CallOrigin::Heartbeat { .. } => Self::SystemTask,
         };
         Ok(call_origin)
     }
@@ -0,0 +1,146 @@
+use ic_ic00_types::CanisterChange;
+use ic_protobuf::{proxy::ProxyDecodeError, state::canister_state_bits::v1 as pb};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+/// The maximum number of changes kept in the history of a canister. When the
+/// limit is exceeded, the oldest changes are dropped.
+pub const MAX_CANISTER_HISTORY_CHANGES: usize = 20;
+
+/// A bounded list of the most recent changes of a canister, e.g. its
+/// creation, code deployments and controller changes. It is returned by
+/// `canister_info`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanisterHistory {
+    /// The most recent changes, from the oldest to the newest.
+    changes: VecDeque<CanisterChange>,
+
+    /// The number of changes ever recorded, including the dropped ones.
+    total_num_changes: u64,
+}
+
+impl CanisterHistory {
+    /// Restores a history from its changes, e.g. when loading a checkpoint.
+    pub fn new(total_num_changes: u64, changes: Vec<CanisterChange>) -> Self {
+        let mut history = Self {
+            changes: changes.into(),
+            total_num_changes,
+        };
+        while history.changes.len() > MAX_CANISTER_HISTORY_CHANGES {
+            history.changes.pop_front();
+        }
+        history
+    }
+
+    /// Records a new change, dropping the oldest one if the history is full.
+    pub fn add_canister_change(&mut self, change: CanisterChange) {
+        if self.changes.len() >= MAX_CANISTER_HISTORY_CHANGES {
+            self.changes.pop_front();
+        }
+        self.changes.push_back(change);
+        self.total_num_changes += 1;
+    }
+
+    /// Returns up to `num_requested_changes` most recent changes, from the
+    /// oldest to the newest.
+    pub fn get_changes(
+        &self,
+        num_requested_changes: usize,
+    ) -> impl Iterator<Item = &CanisterChange> {
+        let num_changes = num_requested_changes.min(self.changes.len());
+        self.changes.iter().skip(self.changes.len() - num_changes)
+    }
+
+    /// Returns the number of changes ever recorded, including the ones that
+    /// were dropped from the history.
+    pub fn get_total_num_changes(&self) -> u64 {
+        self.total_num_changes
+    }
+}
+
+impl From<&CanisterHistory> for pb::CanisterHistory {
+    fn from(item: &CanisterHistory) -> Self {
+        Self {
+            changes: item.changes.iter().map(|change| change.into()).collect(),
+            total_num_changes: item.total_num_changes,
+        }
+    }
+}
+
+impl TryFrom<pb::CanisterHistory> for CanisterHistory {
+    type Error = ProxyDecodeError;
+
+    fn try_from(value: pb::CanisterHistory) -> Result<Self, Self::Error> {
+        let changes = value
+            .changes
+            .into_iter()
+            .map(CanisterChange::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(value.total_num_changes, changes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_base_types::PrincipalId;
+    use ic_ic00_types::{CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallMode};
+
+    fn change(canister_version: u64) -> CanisterChange {
+        CanisterChange::new(
+            canister_version * 10,
+            canister_version,
+            CanisterChangeOrigin::from_user(PrincipalId::new_user_test_id(1)),
+            CanisterChangeDetails::CodeUninstall,
+        )
+    }
+
+    #[test]
+    fn get_changes_returns_the_most_recent_changes() {
+        let mut history = CanisterHistory::default();
+        for version in 0..3 {
+            history.add_canister_change(change(version));
+        }
+
+        let changes: Vec<_> = history.get_changes(2).cloned().collect();
+        assert_eq!(changes, vec![change(1), change(2)]);
+        assert_eq!(history.get_changes(10).count(), 3);
+        assert_eq!(history.get_changes(0).count(), 0);
+        assert_eq!(history.get_total_num_changes(), 3);
+    }
+
+    #[test]
+    fn oldest_changes_are_dropped_when_full() {
+        let mut history = CanisterHistory::default();
+        let total = MAX_CANISTER_HISTORY_CHANGES as u64 + 5;
+        for version in 0..total {
+            history.add_canister_change(change(version));
+        }
+
+        let changes: Vec<_> = history.get_changes(usize::MAX).collect();
+        assert_eq!(changes.len(), MAX_CANISTER_HISTORY_CHANGES);
+        assert_eq!(changes[0], &change(5));
+        assert_eq!(history.get_total_num_changes(), total);
+    }
+
+    #[test]
+    fn history_roundtrips_through_protobuf() {
+        let mut history = CanisterHistory::default();
+        history.add_canister_change(CanisterChange::new(
+            1,
+            0,
+            CanisterChangeOrigin::from_canister(PrincipalId::new_user_test_id(2), Some(7)),
+            CanisterChangeDetails::canister_creation(vec![PrincipalId::new_user_test_id(3)]),
+        ));
+        history.add_canister_change(CanisterChange::new(
+            2,
+            1,
+            CanisterChangeOrigin::from_user(PrincipalId::new_user_test_id(3)),
+            CanisterChangeDetails::code_deployment(CanisterInstallMode::Upgrade, [4; 32]),
+        ));
+
+        let pb_history = pb::CanisterHistory::from(&history);
+        assert_eq!(CanisterHistory::try_from(pb_history).unwrap(), history);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::page_map::{Buffer, PageMap};
+use ic_protobuf::{proxy::ProxyDecodeError, state::canister_state_bits::v1 as pb};
+use ic_types::NumBytes;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::{TryFrom, TryInto};
+
+/// The maximum size of a single chunk. Every chunk takes this much space in
+/// the store, regardless of its actual length.
+pub const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// The maximum number of chunks that the store of a canister can hold.
+pub const DEFAULT_MAX_CHUNKS: usize = 100;
+
+/// The SHA-256 hash of a chunk, which is also its key in the store.
+pub type WasmChunkHash = [u8; 32];
+
+/// Where a chunk is stored in the `PageMap` of the store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkInfo {
+    /// The chunk occupies the bytes `[index * CHUNK_SIZE, index * CHUNK_SIZE
+    /// + length)` of the store.
+    index: u64,
+    length: u64,
+}
+
+impl ChunkInfo {
+    pub fn new(index: u64, length: u64) -> Self {
+        Self { index, length }
+    }
+
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    fn offset(&self) -> usize {
+        (self.index * CHUNK_SIZE) as usize
+    }
+}
+
+/// The part of the store that is persisted in the canister state bits, i.e.
+/// which chunks are stored and where.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WasmChunkStoreMetadata {
+    chunks: BTreeMap<WasmChunkHash, ChunkInfo>,
+}
+
+impl WasmChunkStoreMetadata {
+    pub fn new(chunks: BTreeMap<WasmChunkHash, ChunkInfo>) -> Self {
+        Self { chunks }
+    }
+
+    pub fn chunks(&self) -> &BTreeMap<WasmChunkHash, ChunkInfo> {
+        &self.chunks
+    }
+
+    // Returns the smallest index that is not used by any chunk.
+    fn next_free_index(&self) -> u64 {
+        let used: BTreeSet<u64> = self.chunks.values().map(|info| info.index).collect();
+        (0..).find(|index| !used.contains(index)).unwrap()
+    }
+}
+
+/// The chunks uploaded to a canister via `upload_chunk`, from which a Wasm
+/// module can be assembled with `install_chunked_code`.
+///
+/// The contents of the chunks are kept in a `PageMap` so that large stores
+/// don't have to be serialized as part of the canister state bits.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WasmChunkStore {
+    data: PageMap,
+    metadata: WasmChunkStoreMetadata,
+}
+
+impl WasmChunkStore {
+    pub fn new_from_checkpoint(data: PageMap, metadata: WasmChunkStoreMetadata) -> Self {
+        Self { data, metadata }
+    }
+
+    pub fn page_map(&self) -> &PageMap {
+        &self.data
+    }
+
+    pub fn page_map_mut(&mut self) -> &mut PageMap {
+        &mut self.data
+    }
+
+    pub fn metadata(&self) -> &WasmChunkStoreMetadata {
+        &self.metadata
+    }
+
+    /// Returns the hashes of the stored chunks, in increasing order.
+    pub fn keys(&self) -> impl Iterator<Item = &WasmChunkHash> {
+        self.metadata.chunks.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.metadata.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metadata.chunks.is_empty()
+    }
+
+    /// Returns the memory taken by the store, which counts towards the memory
+    /// usage of the canister.
+    pub fn memory_usage(&self) -> NumBytes {
+        NumBytes::from(self.len() as u64 * CHUNK_SIZE)
+    }
+
+    /// Returns the contents of the chunk with the given hash, if it exists.
+    pub fn get_chunk_data(&self, hash: &WasmChunkHash) -> Option<Vec<u8>> {
+        self.metadata.chunks.get(hash).map(|info| {
+            let mut bytes = vec![0; info.length as usize];
+            Buffer::new(self.data.clone()).read(&mut bytes, info.offset());
+            bytes
+        })
+    }
+
+    /// Returns true if a chunk with the same contents is already stored.
+    pub fn contains_chunk(&self, chunk: &[u8]) -> bool {
+        self.metadata
+            .chunks
+            .contains_key(&ic_crypto_sha::Sha256::hash(chunk))
+    }
+
+    /// Checks whether `chunk` can be added to a store that holds at most
+    /// `max_chunks` chunks. Returns a description of the problem otherwise.
+    pub fn can_insert_chunk(&self, max_chunks: usize, chunk: &[u8]) -> Result<(), String> {
+        if chunk.len() as u64 > CHUNK_SIZE {
+            return Err(format!(
+                "Wasm chunk size {} exceeds the maximum chunk size of {}",
+                chunk.len(),
+                CHUNK_SIZE
+            ));
+        }
+        if self.len() >= max_chunks {
+            return Err(format!(
+                "Wasm chunk store has already reached the maximum of {} chunks",
+                max_chunks
+            ));
+        }
+        Ok(())
+    }
+
+    /// Adds `chunk` to the store and returns its hash. Storing a chunk that
+    /// is already in the store has no effect.
+    ///
+    /// The caller must check `can_insert_chunk()` first.
+    pub fn insert_chunk(&mut self, chunk: &[u8]) -> WasmChunkHash {
+        let hash = ic_crypto_sha::Sha256::hash(chunk);
+        if self.metadata.chunks.contains_key(&hash) {
+            return hash;
+        }
+        let info = ChunkInfo::new(self.metadata.next_free_index(), chunk.len() as u64);
+        let mut buffer = Buffer::new(self.data.clone());
+        buffer.write(chunk, info.offset());
+        self.data = buffer.into_page_map();
+        self.metadata.chunks.insert(hash, info);
+        hash
+    }
+}
+
+impl From<&WasmChunkStoreMetadata> for pb::WasmChunkStoreMetadata {
+    fn from(item: &WasmChunkStoreMetadata) -> Self {
+        Self {
+            chunks: item
+                .chunks
+                .iter()
+                .map(|(hash, info)| pb::WasmChunkData {
+                    hash: hash.to_vec(),
+                    index: info.index,
+                    length: info.length,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<pb::WasmChunkStoreMetadata> for WasmChunkStoreMetadata {
+    type Error = ProxyDecodeError;
+
+    fn try_from(value: pb::WasmChunkStoreMetadata) -> Result<Self, Self::Error> {
+        let mut chunks = BTreeMap::new();
+        for chunk in value.chunks {
+            let hash: WasmChunkHash = chunk.hash.try_into().map_err(|hash: Vec<u8>| {
+                ProxyDecodeError::ValueOutOfRange {
+                    typ: "WasmChunkHash",
+                    err: format!("Expected a 32-byte long hash, got {} bytes", hash.len()),
+                }
+            })?;
+            chunks.insert(hash, ChunkInfo::new(chunk.index, chunk.length));
+        }
+        Ok(Self { chunks })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserted_chunks_can_be_read_back() {
+        let mut store = WasmChunkStore::default();
+        let first = vec![1; 10];
+        let second = vec![2; CHUNK_SIZE as usize];
+        let first_hash = store.insert_chunk(&first);
+        let second_hash = store.insert_chunk(&second);
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get_chunk_data(&first_hash), Some(first));
+        assert_eq!(store.get_chunk_data(&second_hash), Some(second));
+        assert_eq!(store.get_chunk_data(&[0; 32]), None);
+        assert_eq!(store.memory_usage(), NumBytes::from(2 * CHUNK_SIZE));
+    }
+
+    #[test]
+    fn inserting_a_chunk_twice_stores_it_once() {
+        let mut store = WasmChunkStore::default();
+        let hash = store.insert_chunk(b"chunk");
+        assert!(store.contains_chunk(b"chunk"));
+        assert_eq!(store.insert_chunk(b"chunk"), hash);
+        assert_eq!(store.keys().collect::<Vec<_>>(), vec![&hash]);
+    }
+
+    #[test]
+    fn chunks_above_limits_are_rejected() {
+        let mut store = WasmChunkStore::default();
+        assert!(store
+            .can_insert_chunk(1, &vec![0; CHUNK_SIZE as usize + 1])
+            .is_err());
+        assert!(store.can_insert_chunk(1, b"chunk").is_ok());
+        store.insert_chunk(b"chunk");
+        assert!(store.can_insert_chunk(1, b"other").is_err());
+    }
+
+    #[test]
+    fn metadata_roundtrips_through_protobuf() {
+        let mut store = WasmChunkStore::default();
+        store.insert_chunk(b"first");
+        store.insert_chunk(b"second");
+
+        let pb_metadata = pb::WasmChunkStoreMetadata::from(store.metadata());
+        let metadata = WasmChunkStoreMetadata::try_from(pb_metadata).unwrap();
+        assert_eq!(&metadata, store.metadata());
+    }
+}
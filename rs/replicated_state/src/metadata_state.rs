@@ -1,8 +1,11 @@
+pub mod query_stats;
 pub mod subnet_call_context_manager;
 #[cfg(test)]
 mod tests;
 
-use crate::metadata_state::subnet_call_context_manager::SubnetCallContextManager;
+use crate::metadata_state::{
+    query_stats::EpochQueryStats, subnet_call_context_manager::SubnetCallContextManager,
+};
 use ic_base_types::CanisterId;
 use ic_btc_types::Network as BitcoinNetwork;
 use ic_certification_version::{CertificationVersion, CURRENT_CERTIFICATION_VERSION};
@@ -63,6 +66,13 @@ pub struct SystemMetadata {
     /// Used for canister creation.
     pub generated_id_counter: u64,
 
+    /// A counter used for generating new canister snapshot ids.
+    pub next_snapshot_id: u64,
+
+    /// The query statistics delivered by consensus for the query stats epoch
+    /// that is currently being aggregated.
+    pub epoch_query_stats: EpochQueryStats,
+
     /// The canister ID ranges from which this subnet generates canister IDs.
     canister_allocation_ranges: CanisterIdRanges,
     /// The last generated canister ID; or `None` if this subnet has not
@@ -425,6 +435,7 @@ impl From<&SystemMetadata> for pb_metadata::SystemMetadata {
         Self {
             own_subnet_id: Some(subnet_id_into_protobuf(item.own_subnet_id)),
             generated_id_counter: item.generated_id_counter,
+            next_snapshot_id: item.next_snapshot_id,
             canister_allocation_ranges: Some(item.canister_allocation_ranges.clone().into()),
             last_generated_canister_id: item.last_generated_canister_id.map(Into::into),
             prev_state_hash: item
@@ -453,6 +464,7 @@ impl From<&SystemMetadata> for pb_metadata::SystemMetadata {
                     .as_nanos_since_unix_epoch(),
             }),
             subnet_metrics: Some((&item.subnet_metrics).into()),
+            epoch_query_stats: Some((&item.epoch_query_stats).into()),
         }
     }
 }
@@ -505,6 +517,11 @@ impl TryFrom<pb_metadata::SystemMetadata> for SystemMetadata {
             own_subnet_type: SubnetType::default(),
             own_subnet_features: item.own_subnet_features.unwrap_or_default().into(),
             generated_id_counter: item.generated_id_counter,
+            next_snapshot_id: item.next_snapshot_id,
+            epoch_query_stats: match item.epoch_query_stats {
+                Some(epoch_query_stats) => epoch_query_stats.try_into()?,
+                None => EpochQueryStats::default(),
+            },
             canister_allocation_ranges,
             last_generated_canister_id,
             prev_state_hash: item.prev_state_hash.map(|b| CryptoHash(b).into()),
@@ -555,6 +572,8 @@ impl SystemMetadata {
             ingress_history: Default::default(),
             streams: Default::default(),
             generated_id_counter: Default::default(),
+            next_snapshot_id: Default::default(),
+            epoch_query_stats: Default::default(),
             canister_allocation_ranges: Default::default(),
             last_generated_canister_id: None,
             batch_time: UNIX_EPOCH,
@@ -0,0 +1,191 @@
+use ic_protobuf::{
+    proxy::{try_from_option_field, ProxyDecodeError},
+    state::system_metadata::v1 as pb_metadata,
+    types::v1 as pb_types,
+};
+use ic_types::{
+    batch::{QueryStats, QueryStatsEpoch, QueryStatsPayload},
+    node_id_into_protobuf, node_id_try_from_protobuf, CanisterId, NodeId,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::{From, TryFrom},
+};
+
+/// The query statistics that were delivered through consensus for the epoch
+/// that is currently being aggregated.
+///
+/// The statistics of each proposer are kept separately, because a single
+/// faulty replica must not be able to skew the statistics of the subnet. They
+/// are only combined into a robust aggregate, see `aggregate_stats()`, once
+/// the epoch is completed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpochQueryStats {
+    /// The epoch being aggregated; `None` if no statistics were delivered yet.
+    pub epoch: Option<QueryStatsEpoch>,
+    /// The replicas that already delivered their statistics for `epoch`.
+    pub proposers: BTreeSet<NodeId>,
+    /// The statistics delivered for `epoch` by each proposer that have not
+    /// been added to the canisters yet.
+    pub stats: BTreeMap<NodeId, BTreeMap<CanisterId, QueryStats>>,
+}
+
+impl EpochQueryStats {
+    /// Adds the statistics of `payload` to the aggregation.
+    ///
+    /// Returns the aggregated statistics of the previous epoch if `payload`
+    /// starts a new epoch. Payloads for earlier epochs and repeated payloads
+    /// of the same proposer are ignored.
+    pub fn aggregate(&mut self, payload: QueryStatsPayload) -> BTreeMap<CanisterId, QueryStats> {
+        let mut completed = BTreeMap::new();
+        match self.epoch {
+            Some(epoch) if payload.epoch < epoch => return completed,
+            Some(epoch) if payload.epoch == epoch => {}
+            _ => {
+                completed = self.take_stats();
+                self.proposers.clear();
+                self.epoch = Some(payload.epoch);
+            }
+        }
+
+        if !self.proposers.insert(payload.proposer) {
+            return completed;
+        }
+        let proposer_stats = self.stats.entry(payload.proposer).or_default();
+        for canister_stats in payload.stats {
+            proposer_stats
+                .entry(canister_stats.canister_id)
+                .or_default()
+                .saturating_accumulate(&canister_stats.stats);
+        }
+        completed
+    }
+
+    /// Returns the statistics aggregated so far, leaving the epoch and the
+    /// proposers untouched so that late payloads of the same proposers are
+    /// still recognized as duplicates.
+    pub fn take_stats(&mut self) -> BTreeMap<CanisterId, QueryStats> {
+        let stats = aggregate_stats(&self.proposers, &self.stats);
+        self.stats.clear();
+        stats
+    }
+}
+
+/// Combines the statistics of the given proposers into an estimate of the
+/// statistics of the whole subnet.
+///
+/// For each canister and each field, the median of the values reported by the
+/// proposers is taken, where a proposer that did not report the canister
+/// counts as zero. The median is multiplied by the number of proposers, so
+/// that the result still counts the queries executed by all of them. Unless
+/// at least half of the proposers are faulty, the result is bounded by the
+/// values that honest proposers reported.
+fn aggregate_stats(
+    proposers: &BTreeSet<NodeId>,
+    stats: &BTreeMap<NodeId, BTreeMap<CanisterId, QueryStats>>,
+) -> BTreeMap<CanisterId, QueryStats> {
+    let canister_ids: BTreeSet<CanisterId> = stats
+        .values()
+        .flat_map(|proposer_stats| proposer_stats.keys().cloned())
+        .collect();
+    let num_proposers = proposers.len() as u64;
+
+    let mut result = BTreeMap::new();
+    for canister_id in canister_ids {
+        let reported: Vec<QueryStats> = proposers
+            .iter()
+            .map(|proposer| {
+                stats
+                    .get(proposer)
+                    .and_then(|proposer_stats| proposer_stats.get(&canister_id))
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let total = |field: fn(&QueryStats) -> u64| {
+            median(reported.iter().map(field).collect()).saturating_mul(num_proposers)
+        };
+        result.insert(
+            canister_id,
+            QueryStats {
+                num_calls: total(|stats| stats.num_calls),
+                num_instructions: total(|stats| stats.num_instructions),
+                ingress_payload_size: total(|stats| stats.ingress_payload_size),
+                egress_payload_size: total(|stats| stats.egress_payload_size),
+            },
+        );
+    }
+    result
+}
+
+/// Returns the median of the given values, or the lower of the two middle
+/// values if their number is even.
+fn median(mut values: Vec<u64>) -> u64 {
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    values[(values.len() - 1) / 2]
+}
+
+impl From<&EpochQueryStats> for pb_metadata::EpochQueryStats {
+    fn from(item: &EpochQueryStats) -> Self {
+        Self {
+            epoch: item.epoch.map(|epoch| epoch.get()),
+            proposers: item
+                .proposers
+                .iter()
+                .map(|node_id| node_id_into_protobuf(*node_id))
+                .collect(),
+            stats: item
+                .stats
+                .iter()
+                .flat_map(|(proposer, proposer_stats)| {
+                    proposer_stats.iter().map(move |(canister_id, stats)| {
+                        pb_metadata::EpochQueryStatsEntry {
+                            canister_id: Some(pb_types::CanisterId::from(*canister_id)),
+                            num_calls: stats.num_calls,
+                            num_instructions: stats.num_instructions,
+                            ingress_payload_size: stats.ingress_payload_size,
+                            egress_payload_size: stats.egress_payload_size,
+                            proposer: Some(node_id_into_protobuf(*proposer)),
+                        }
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<pb_metadata::EpochQueryStats> for EpochQueryStats {
+    type Error = ProxyDecodeError;
+
+    fn try_from(item: pb_metadata::EpochQueryStats) -> Result<Self, Self::Error> {
+        let mut proposers = BTreeSet::new();
+        for node_id in item.proposers {
+            proposers.insert(node_id_try_from_protobuf(node_id)?);
+        }
+        let mut stats: BTreeMap<NodeId, BTreeMap<CanisterId, QueryStats>> = BTreeMap::new();
+        for entry in item.stats {
+            let canister_id: CanisterId =
+                try_from_option_field(entry.canister_id, "EpochQueryStatsEntry::canister_id")?;
+            let proposer = node_id_try_from_protobuf(entry.proposer.ok_or(
+                ProxyDecodeError::MissingField("EpochQueryStatsEntry::proposer"),
+            )?)?;
+            stats.entry(proposer).or_default().insert(
+                canister_id,
+                QueryStats {
+                    num_calls: entry.num_calls,
+                    num_instructions: entry.num_instructions,
+                    ingress_payload_size: entry.ingress_payload_size,
+                    egress_payload_size: entry.egress_payload_size,
+                },
+            );
+        }
+        Ok(Self {
+            epoch: item.epoch.map(QueryStatsEpoch::from),
+            proposers,
+            stats,
+        })
+    }
+}
@@ -411,6 +411,54 @@ impl PageMap {
         self.persist_to_file(&self.round_delta, dst)
     }
 
+    /// Persists all pages of this page map, including the ones backed by the
+    /// checkpoint file, to the specified destination and fsyncs the file.
+    ///
+    /// Unlike `persist_and_sync_delta()`, this does not rely on `dst` already
+    /// containing the pages of the checkpoint.
+    pub fn persist_all_and_sync(&self, dst: &Path) -> Result<(), PersistenceError> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(dst)
+            .map_err(|err| PersistenceError::FileSystemError {
+                path: dst.display().to_string(),
+                context: "Failed to open file".to_string(),
+                internal_error: err.to_string(),
+            })?;
+        if self.num_host_pages() > 0 {
+            let mut buffer = WriteBuffer {
+                content: self.host_pages_iter().map(|(_, page)| &page[..]).collect(),
+                start_index: PageIndex::new(0),
+            };
+            buffer.apply_to_file(&mut file, dst)?;
+        }
+        file.sync_all()
+            .map_err(|err| PersistenceError::FileSystemError {
+                path: dst.display().to_string(),
+                context: "Failed to sync file".to_string(),
+                internal_error: err.to_string(),
+            })?;
+        Ok(())
+    }
+
+    /// Returns a page map with the same contents as this one in which all
+    /// pages are part of the page and round deltas.
+    ///
+    /// This is needed when the file the result is persisted to does not hold
+    /// the checkpointed pages of this page map, e.g. after it was truncated.
+    pub fn copy_with_full_delta(&self) -> PageMap {
+        let pages: Vec<_> = (0..self.num_host_pages())
+            .map(|i| {
+                let index = PageIndex::new(i as u64);
+                (index, self.get_page(index))
+            })
+            .collect();
+        let mut page_map = PageMap::new();
+        page_map.update(&pages);
+        page_map
+    }
+
     /// Returns the iterator over host pages managed by this `PageMap`.
     pub fn host_pages_iter(&self) -> impl Iterator<Item = (PageIndex, &PageBytes)> + '_ {
         (0..self.num_host_pages()).map(move |i| {
@@ -4,6 +4,7 @@ use super::{
 };
 use crate::{
     bitcoin_state::{BitcoinState, BitcoinStateError},
+    canister_snapshots::{CanisterSnapshot, CanisterSnapshots, SnapshotId},
     canister_state::queues::CanisterQueuesLoopDetector,
     canister_state::system_state::{push_input, CanisterOutputQueuesIterator},
     metadata_state::StreamMap,
@@ -20,6 +21,7 @@ use ic_registry_subnet_features::BitcoinFeatureStatus;
 use ic_registry_subnet_type::SubnetType;
 use ic_types::messages::Ingress;
 use ic_types::{
+    batch::{QueryStats, QueryStatsPayload},
     ingress::IngressStatus,
     messages::{CallbackId, MessageId, RequestOrResponse, Response},
     xnet::QueueId,
@@ -315,6 +317,9 @@ pub struct ReplicatedState {
     /// States of all canisters, indexed by canister ids.
     pub canister_states: BTreeMap<CanisterId, CanisterState>,
 
+    /// Snapshots of canister states, taken via the management canister.
+    pub canister_snapshots: CanisterSnapshots,
+
     /// Deterministic processing metadata.
     pub metadata: SystemMetadata,
 
@@ -340,12 +345,14 @@ impl PartialEq for ReplicatedState {
         (
             &self.bitcoin,
             &self.canister_states,
+            &self.canister_snapshots,
             &self.metadata,
             &self.subnet_queues,
             &self.consensus_queue,
         ) == (
             &rhs.bitcoin,
             &rhs.canister_states,
+            &rhs.canister_snapshots,
             &rhs.metadata,
             &rhs.subnet_queues,
             &rhs.consensus_queue,
@@ -363,6 +370,7 @@ impl ReplicatedState {
         ReplicatedState {
             root,
             canister_states: BTreeMap::new(),
+            canister_snapshots: CanisterSnapshots::default(),
             metadata: SystemMetadata::new(own_subnet_id, own_subnet_type),
             subnet_queues: CanisterQueues::default(),
             consensus_queue: Vec::new(),
@@ -372,6 +380,7 @@ impl ReplicatedState {
 
     pub fn new_from_checkpoint(
         canister_states: BTreeMap<CanisterId, CanisterState>,
+        canister_snapshots: CanisterSnapshots,
         metadata: SystemMetadata,
         subnet_queues: CanisterQueues,
         consensus_queue: Vec<Response>,
@@ -380,6 +389,7 @@ impl ReplicatedState {
     ) -> Self {
         let mut res = Self {
             canister_states,
+            canister_snapshots,
             metadata,
             subnet_queues,
             consensus_queue,
@@ -387,6 +397,10 @@ impl ReplicatedState {
             bitcoin,
         };
         res.update_stream_responses_size_bytes();
+        let canister_ids: Vec<_> = res.canister_states.keys().copied().collect();
+        for canister_id in canister_ids {
+            res.update_snapshots_memory_usage(canister_id);
+        }
         res
     }
 
@@ -424,6 +438,32 @@ impl ReplicatedState {
             .insert(canister_state.canister_id(), canister_state);
     }
 
+    /// Inserts the given snapshot of a canister, updating the memory usage of
+    /// the canister.
+    pub fn put_canister_snapshot(&mut self, snapshot_id: SnapshotId, snapshot: CanisterSnapshot) {
+        let canister_id = snapshot.canister_id();
+        self.canister_snapshots.insert(snapshot_id, snapshot);
+        self.update_snapshots_memory_usage(canister_id);
+    }
+
+    /// Removes the snapshot with the given id, updating the memory usage of
+    /// its canister.
+    pub fn remove_canister_snapshot(
+        &mut self,
+        snapshot_id: SnapshotId,
+    ) -> Option<Arc<CanisterSnapshot>> {
+        let snapshot = self.canister_snapshots.remove(snapshot_id)?;
+        self.update_snapshots_memory_usage(snapshot.canister_id());
+        Some(snapshot)
+    }
+
+    fn update_snapshots_memory_usage(&mut self, canister_id: CanisterId) {
+        let memory_taken = self.canister_snapshots.memory_taken(canister_id);
+        if let Some(canister) = self.canister_states.get_mut(&canister_id) {
+            canister.system_state.snapshots_memory_usage = memory_taken;
+        }
+    }
+
     /// Replaces the content of `self.canister_states` with the provided `canisters`.
     ///
     /// Panics if `self.canister_states` was not empty. The intended use is to
@@ -767,6 +807,50 @@ impl ReplicatedState {
         self.canister_states.len()
     }
 
+    /// Adds the query statistics delivered in a block to the aggregation of
+    /// their epoch.
+    ///
+    /// The aggregated statistics of an epoch are added to the
+    /// `total_query_stats` of the canisters once every node of the subnet has
+    /// delivered its statistics or once statistics for a later epoch arrive.
+    /// Statistics proposed by nodes outside of the subnet are ignored.
+    pub fn apply_query_stats(&mut self, payload: QueryStatsPayload) {
+        let subnet_nodes = self
+            .metadata
+            .network_topology
+            .subnets
+            .get(&self.metadata.own_subnet_id)
+            .map(|subnet| subnet.nodes.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        if !subnet_nodes.contains(&payload.proposer) {
+            return;
+        }
+
+        let epoch_query_stats = &mut self.metadata.epoch_query_stats;
+        let completed = epoch_query_stats.aggregate(payload);
+        self.add_query_stats_to_canisters(completed);
+
+        let epoch_query_stats = &mut self.metadata.epoch_query_stats;
+        if subnet_nodes
+            .iter()
+            .all(|node_id| epoch_query_stats.proposers.contains(node_id))
+        {
+            let stats = epoch_query_stats.take_stats();
+            self.add_query_stats_to_canisters(stats);
+        }
+    }
+
+    fn add_query_stats_to_canisters(&mut self, stats: BTreeMap<CanisterId, QueryStats>) {
+        for (canister_id, stats) in stats {
+            if let Some(canister) = self.canister_states.get_mut(&canister_id) {
+                canister
+                    .system_state
+                    .total_query_stats
+                    .saturating_accumulate(&stats);
+            }
+        }
+    }
+
     /// Returns a reference to the `BitcoinState`.
     pub fn bitcoin(&self) -> &BitcoinState {
         &self.bitcoin
@@ -10,7 +10,7 @@ use ic_replicated_state::testing::{CanisterQueuesTesting, SystemStateTesting};
 use ic_replicated_state::{
     replicated_state::PeekableOutputIterator, replicated_state::ReplicatedStateMessageRouting,
     BitcoinStateError, CanisterState, InputQueueType, ReplicatedState, SchedulerState, StateError,
-    SystemState,
+    SubnetTopology, SystemState,
 };
 use ic_test_utilities::mock_time;
 use ic_test_utilities::state::{
@@ -18,12 +18,13 @@ use ic_test_utilities::state::{
 };
 use ic_test_utilities::types::ids::canister_test_id;
 use ic_test_utilities::types::{
-    ids::{subnet_test_id, user_test_id},
+    ids::{node_test_id, subnet_test_id, user_test_id},
     messages::{RequestBuilder, ResponseBuilder},
 };
 use ic_types::{
+    batch::{CanisterQueryStats, QueryStats, QueryStatsEpoch, QueryStatsPayload},
     messages::{CallbackId, RequestOrResponse, MAX_RESPONSE_COUNT_BYTES},
-    CountBytes, Cycles, QueueIndex,
+    CountBytes, Cycles, NodeId, QueueIndex,
 };
 use proptest::prelude::*;
 use std::str::FromStr;
@@ -779,6 +780,115 @@ fn state_equality_with_bitcoin() {
     assert_ne!(original_state, state);
 }
 
+fn query_stats_payload(proposer: NodeId, epoch: u64, num_calls: u64) -> QueryStatsPayload {
+    QueryStatsPayload {
+        proposer,
+        epoch: QueryStatsEpoch::from(epoch),
+        stats: vec![CanisterQueryStats {
+            canister_id: CANISTER_ID,
+            stats: QueryStats {
+                num_calls,
+                num_instructions: 10 * num_calls,
+                ingress_payload_size: 0,
+                egress_payload_size: 0,
+            },
+        }],
+    }
+}
+
+fn total_num_calls(state: &ReplicatedState) -> u64 {
+    state
+        .canister_state(&CANISTER_ID)
+        .unwrap()
+        .system_state
+        .total_query_stats
+        .num_calls
+}
+
+#[test]
+fn query_stats_are_applied_once_all_nodes_delivered() {
+    replicated_state_test(|mut state| {
+        state.metadata.network_topology.subnets.insert(
+            SUBNET_ID,
+            SubnetTopology {
+                nodes: (1..=2)
+                    .map(|i| (node_test_id(i), Default::default()))
+                    .collect(),
+                ..Default::default()
+            },
+        );
+
+        state.apply_query_stats(query_stats_payload(node_test_id(1), 0, 1));
+        assert_eq!(total_num_calls(&state), 0);
+
+        // Repeated payloads and payloads of nodes outside of the subnet are ignored.
+        state.apply_query_stats(query_stats_payload(node_test_id(1), 0, 1));
+        state.apply_query_stats(query_stats_payload(node_test_id(3), 0, 1));
+        assert_eq!(total_num_calls(&state), 0);
+
+        // The lower median of `[1, 2]` times the number of proposers.
+        state.apply_query_stats(query_stats_payload(node_test_id(2), 0, 2));
+        assert_eq!(total_num_calls(&state), 2);
+        assert_eq!(
+            state
+                .canister_state(&CANISTER_ID)
+                .unwrap()
+                .system_state
+                .total_query_stats
+                .num_instructions,
+            20
+        );
+    })
+}
+
+#[test]
+fn query_stats_are_applied_when_next_epoch_starts() {
+    replicated_state_test(|mut state| {
+        state.metadata.network_topology.subnets.insert(
+            SUBNET_ID,
+            SubnetTopology {
+                nodes: (1..=2)
+                    .map(|i| (node_test_id(i), Default::default()))
+                    .collect(),
+                ..Default::default()
+            },
+        );
+
+        state.apply_query_stats(query_stats_payload(node_test_id(1), 0, 1));
+        state.apply_query_stats(query_stats_payload(node_test_id(1), 1, 4));
+        assert_eq!(total_num_calls(&state), 1);
+
+        // Late payloads of completed epochs are dropped.
+        state.apply_query_stats(query_stats_payload(node_test_id(2), 0, 2));
+        assert_eq!(total_num_calls(&state), 1);
+
+        state.apply_query_stats(query_stats_payload(node_test_id(2), 1, 2));
+        assert_eq!(total_num_calls(&state), 1 + 2 * 2);
+    })
+}
+
+#[test]
+fn query_stats_are_robust_against_a_faulty_node() {
+    replicated_state_test(|mut state| {
+        state.metadata.network_topology.subnets.insert(
+            SUBNET_ID,
+            SubnetTopology {
+                nodes: (1..=3)
+                    .map(|i| (node_test_id(i), Default::default()))
+                    .collect(),
+                ..Default::default()
+            },
+        );
+
+        state.apply_query_stats(query_stats_payload(node_test_id(1), 0, 1));
+        state.apply_query_stats(query_stats_payload(node_test_id(2), 0, 2));
+        state.apply_query_stats(query_stats_payload(node_test_id(3), 0, 1_000_000));
+
+        // The median of `[1, 2, 1_000_000]` times the number of proposers.
+        assert_eq!(total_num_calls(&state), 2 * 3);
+    })
+}
+
 proptest! {
     #[test]
     fn peek_and_next_consistent(
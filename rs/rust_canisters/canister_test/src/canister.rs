@@ -647,6 +647,8 @@ impl<'a> Canister<'a> {
                         compute_allocation: None,
                         memory_allocation: None,
                         freezing_threshold: None,
+                        log_visibility: None,
+                        wasm_memory_limit: None,
                     },
                 },),
             )
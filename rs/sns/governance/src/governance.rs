@@ -4359,6 +4359,8 @@ mod tests {
             Some(0),
             0,
             0,
+            None,
+            Default::default(),
         )
     }
 
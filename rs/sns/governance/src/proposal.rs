@@ -1420,6 +1420,8 @@ mod tests {
             Some(0),
             0,
             0,
+            None,
+            Default::default(),
         )
     }
 
@@ -617,6 +617,8 @@ impl SnsRootCanister {
                     compute_allocation: None,
                     memory_allocation: None,
                     freezing_threshold: None,
+                    log_visibility: None,
+                    wasm_memory_limit: None,
                 },
             };
 
@@ -1207,6 +1209,8 @@ mod tests {
                             compute_allocation: None,
                             memory_allocation: None,
                             freezing_threshold: None,
+                            log_visibility: None,
+                            wasm_memory_limit: None,
                         },
                     },
                     result: Ok(EmptyBlob {}),
@@ -1327,6 +1331,8 @@ mod tests {
                             compute_allocation: None,
                             memory_allocation: None,
                             freezing_threshold: None,
+                            log_visibility: None,
+                            wasm_memory_limit: None,
                         },
                     },
                     result: Ok(EmptyBlob {}),
@@ -523,6 +523,8 @@ mod tests {
             None,
             0,
             0,
+            None,
+            Default::default(),
         )
     }
 
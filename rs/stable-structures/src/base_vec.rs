@@ -0,0 +1,353 @@
+//! This module implements the storage shared by [`StableVec`](crate::StableVec) and
+//! [`StableMinHeap`](crate::StableMinHeap): a growable array of elements stored in slots of
+//! equal size, which provides constant-time access to any element. The trade-off is that the
+//! maximum size of an element must be known in advance.
+//!
+//! # V1 layout
+//!
+//! ```text
+//! ---------------------------------------- <- Address 0
+//! Magic                   ↕ 3 bytes
+//! ----------------------------------------
+//! Layout version          ↕ 1 byte
+//! ----------------------------------------
+//! Max element size = S    ↕ 4 bytes
+//! ----------------------------------------
+//! Number of entries = L   ↕ 8 bytes
+//! ----------------------------------------
+//! Reserved space          ↕ 48 bytes
+//! ---------------------------------------- <- Address 64
+//! Size of E_0             ↕ 4 bytes
+//! ----------------------------------------
+//! E_0 bytes               ↕ S bytes
+//! ----------------------------------------
+//! ...
+//! ----------------------------------------
+//! Size of E_(L-1)         ↕ 4 bytes
+//! ----------------------------------------
+//! E_(L-1) bytes           ↕ S bytes
+//! ----------------------------------------
+//! Unallocated space
+//! ```
+//!
+//! The magic identifies the data structure stored in the memory.
+use crate::storable::Storable;
+use crate::{
+    read_u32, read_u64, safe_write, types::Address, write_u32, write_u64, GrowFailed, Memory,
+};
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+
+/// The current version of the layout.
+const LAYOUT_VERSION: u8 = 1;
+
+/// The size of the V1 layout header.
+const HEADER_V1_SIZE: u64 = 16;
+
+/// The number of header bytes reserved for future extensions.
+const RESERVED_SIZE: u64 = 48;
+
+/// The offset at which the first element is stored.
+const DATA_OFFSET: u64 = HEADER_V1_SIZE + RESERVED_SIZE;
+
+/// The size of the length prefix of each element.
+const LENGTH_PREFIX_SIZE: u64 = 4;
+
+/// The address of the number of entries in the header.
+const LEN_OFFSET: u64 = 8;
+
+struct HeaderV1 {
+    magic: [u8; 3],
+    version: u8,
+    max_element_size: u32,
+    len: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum InitError {
+    IncompatibleVersion {
+        last_supported_version: u8,
+        decoded_version: u8,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WriteError {
+    ValueTooLarge { given: usize, max: usize },
+    GrowFailed { current_size: u64, delta: u64 },
+}
+
+impl From<GrowFailed> for WriteError {
+    fn from(
+        GrowFailed {
+            current_size,
+            delta,
+        }: GrowFailed,
+    ) -> Self {
+        Self::GrowFailed {
+            current_size,
+            delta,
+        }
+    }
+}
+
+/// A growable array of values stored in memory with constant-time access to all elements.
+///
+/// Each element occupies a slot of `max_element_size` bytes (plus a 4-byte length prefix)
+/// regardless of its actual encoded size, so `max_element_size` must be known in advance.
+pub(crate) struct BaseVec<T: Storable, M: Memory> {
+    max_element_size: u32,
+    memory: M,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Storable, M: Memory> BaseVec<T, M> {
+    /// Creates a new empty vector in the specified memory, overwriting the previous contents of
+    /// the memory.
+    pub fn new(memory: M, max_element_size: u32, magic: [u8; 3]) -> Self {
+        Self::write_header(
+            &memory,
+            &HeaderV1 {
+                magic,
+                version: LAYOUT_VERSION,
+                max_element_size,
+                len: 0,
+            },
+        );
+
+        Self {
+            max_element_size,
+            memory,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Initializes a vector based on the contents of the memory.
+    /// If the memory already contains a vector with the given magic, this function recovers it
+    /// from the stable memory, ignoring the `max_element_size` argument. Otherwise, this function
+    /// allocates a new empty vector in the memory.
+    pub fn init(memory: M, max_element_size: u32, magic: [u8; 3]) -> Result<Self, InitError> {
+        if memory.size() == 0 {
+            return Ok(Self::new(memory, max_element_size, magic));
+        }
+
+        let header = Self::read_header(&memory);
+        if header.magic != magic {
+            return Ok(Self::new(memory, max_element_size, magic));
+        }
+
+        if header.version != LAYOUT_VERSION {
+            return Err(InitError::IncompatibleVersion {
+                last_supported_version: LAYOUT_VERSION,
+                decoded_version: header.version,
+            });
+        }
+
+        Ok(Self {
+            max_element_size: header.max_element_size,
+            memory,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Writes the vector header to the memory.
+    fn write_header(memory: &M, header: &HeaderV1) {
+        if memory.size() < 1 {
+            assert!(
+                memory.grow(1) != -1,
+                "failed to allocate the first memory page"
+            );
+        }
+        memory.write(0, &header.magic);
+        memory.write(3, &[header.version]);
+        write_u32(memory, Address::from(4), header.max_element_size);
+        write_u64(memory, Address::from(LEN_OFFSET), header.len);
+    }
+
+    /// Reads the vector header from the memory.
+    /// PRECONDITION: memory.size() > 0
+    fn read_header(memory: &M) -> HeaderV1 {
+        let mut magic = [0u8; 3];
+        let mut version = [0u8; 1];
+        memory.read(0, &mut magic);
+        memory.read(3, &mut version);
+        HeaderV1 {
+            magic,
+            version: version[0],
+            max_element_size: read_u32(memory, Address::from(4)),
+            len: read_u64(memory, Address::from(LEN_OFFSET)),
+        }
+    }
+
+    /// Returns the underlying memory of the vector.
+    pub fn forget(self) -> M {
+        self.memory
+    }
+
+    /// Returns true iff this vector does not have any elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of elements in the vector.
+    pub fn len(&self) -> u64 {
+        read_u64(&self.memory, Address::from(LEN_OFFSET))
+    }
+
+    /// Returns the max size of an element this vector can hold.
+    pub fn max_element_size(&self) -> u32 {
+        self.max_element_size
+    }
+
+    /// Returns the element at the specified index.
+    /// Returns None if the index is out of bounds.
+    pub fn get(&self, index: u64) -> Option<T> {
+        if index < self.len() {
+            Some(self.read_element(index))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the element at the specified index.
+    /// If the new value is too large to fit into a slot, the vector does not change.
+    ///
+    /// PRECONDITION: index < self.len()
+    pub fn set(&self, index: u64, item: &T) -> Result<(), WriteError> {
+        assert!(
+            index < self.len(),
+            "index out of bounds: the len is {} but the index is {}",
+            self.len(),
+            index
+        );
+
+        self.write_element(index, item)
+    }
+
+    /// Appends a new element to the end of the vector.
+    /// If the element does not fit, the vector does not change.
+    pub fn push(&self, item: &T) -> Result<(), WriteError> {
+        let index = self.len();
+
+        // NB. the element is written first so that a failure doesn't require undoing any change
+        // to the length.
+        self.write_element(index, item)?;
+        self.set_len(index + 1);
+
+        Ok(())
+    }
+
+    /// Removes the last element from the vector and returns it.
+    /// Returns None if the vector is empty.
+    pub fn pop(&self) -> Option<T> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+
+        let last = self.read_element(len - 1);
+        self.set_len(len - 1);
+        Some(last)
+    }
+
+    /// Returns an iterator over the elements of the vector.
+    pub fn iter(&self) -> Iter<'_, T, M> {
+        Iter {
+            vec: self,
+            index: 0,
+            end: self.len(),
+        }
+    }
+
+    /// Reads and decodes the element stored at the specified index.
+    ///
+    /// PRECONDITION: index < self.len()
+    fn read_element(&self, index: u64) -> T {
+        let offset = self.slot_offset(index);
+        let len = read_u32(&self.memory, Address::from(offset));
+
+        debug_assert!(len <= self.max_element_size);
+
+        let mut buf = vec![0; len as usize];
+        self.memory.read(offset + LENGTH_PREFIX_SIZE, &mut buf);
+        T::from_bytes(buf)
+    }
+
+    /// Encodes the element and writes it into the slot with the specified index, growing the
+    /// memory if needed.
+    fn write_element(&self, index: u64, item: &T) -> Result<(), WriteError> {
+        let encoded = item.to_bytes();
+        let bytes: &[u8] = encoded.borrow();
+        if bytes.len() > self.max_element_size as usize {
+            return Err(WriteError::ValueTooLarge {
+                given: bytes.len(),
+                max: self.max_element_size as usize,
+            });
+        }
+
+        let offset = self.slot_offset(index);
+
+        // NB. we write the data first: a successful write allocates space for the length prefix
+        // because the data lives at higher addresses.
+        safe_write(&self.memory, offset + LENGTH_PREFIX_SIZE, bytes)?;
+        write_u32(&self.memory, Address::from(offset), bytes.len() as u32);
+
+        Ok(())
+    }
+
+    fn set_len(&self, len: u64) {
+        write_u64(&self.memory, Address::from(LEN_OFFSET), len);
+    }
+
+    /// Returns the absolute offset of the slot with the specified index in memory.
+    fn slot_offset(&self, index: u64) -> u64 {
+        let slot_size = LENGTH_PREFIX_SIZE + self.max_element_size as u64;
+        index
+            .checked_mul(slot_size)
+            .and_then(|offset| offset.checked_add(DATA_OFFSET))
+            .expect("address overflow")
+    }
+}
+
+/// An iterator over the elements of a [`StableVec`](crate::StableVec) or a
+/// [`StableMinHeap`](crate::StableMinHeap).
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct Iter<'a, T: Storable, M: Memory> {
+    vec: &'a BaseVec<T, M>,
+    // The index of the next element to return from the front.
+    index: u64,
+    // The index one past the next element to return from the back.
+    end: u64,
+}
+
+impl<T: Storable, M: Memory> Iterator for Iter<'_, T, M> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.index >= self.end {
+            return None;
+        }
+
+        let item = self.vec.get(self.index);
+        self.index += 1;
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end.saturating_sub(self.index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T: Storable, M: Memory> DoubleEndedIterator for Iter<'_, T, M> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.index >= self.end {
+            return None;
+        }
+
+        self.end -= 1;
+        self.vec.get(self.end)
+    }
+}
+
+impl<T: Storable, M: Memory> ExactSizeIterator for Iter<'_, T, M> {}
@@ -1,6 +1,7 @@
 mod allocator;
 mod iter;
 mod node;
+mod overflow;
 use crate::{
     read_struct,
     types::{Address, Bytes, NULL},
@@ -8,26 +9,37 @@ use crate::{
 };
 use allocator::Allocator;
 pub use iter::Iter;
-use iter::{Cursor, Index};
 use node::{Entry, Node, NodeType, B};
+use std::convert::TryInto;
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
 
-const LAYOUT_VERSION: u8 = 1;
+const LAYOUT_VERSION: u8 = 2;
+// In the V1 layout, all values are stored inline in the nodes. A V1 map is migrated to V2 when
+// it's loaded.
+const LAYOUT_VERSION_1: u8 = 1;
 const MAGIC: &[u8; 3] = b"BTR";
 
 /// A "stable" map based on a B-tree.
 ///
 /// The implementation is based on the algorithm outlined in "Introduction to Algorithms"
 /// by Cormen et al.
+///
+/// Keys and values up to the `max_key_size` and `max_value_size` given on creation are stored
+/// inline in the nodes, while larger ones are stored separately in "overflow" chunks, so
+/// `max_key_size` and `max_value_size` should be set to the sizes of a typical key and value
+/// rather than the sizes of the largest ones.
 pub struct StableBTreeMap<M: Memory, K: Storable, V: Storable> {
     // The address of the root node. If a root node doesn't exist, the address
     // is set to NULL.
     root_addr: Address,
 
-    // The maximum size a key can have.
+    // The maximum size of a key stored inline in a node.
+    // Larger keys are stored in overflow chunks.
     max_key_size: u32,
 
-    // The maximum size a value can have.
+    // The maximum size of a value stored inline in a node.
+    // Larger values are stored in overflow chunks.
     max_value_size: u32,
 
     // An allocator used for managing memory and allocating nodes.
@@ -94,7 +106,14 @@ impl<M: Memory + Clone, K: Storable, V: Storable> StableBTreeMap<M, K, V> {
     ///    |  BTreeHeader  |  Allocator | ... free memory for nodes |
     ///
     /// See [`Allocator`] for more details on its own memory layout.
+    ///
+    /// Keys larger than `max_key_size` and values larger than `max_value_size` are stored in
+    /// overflow chunks. To be able to reference an overflow chunk, at least 8 bytes are reserved
+    /// for each key and value in a node.
     pub fn new(memory: M, max_key_size: u32, max_value_size: u32) -> Self {
+        let max_key_size = std::cmp::max(max_key_size, Address::size().get() as u32);
+        let max_value_size = std::cmp::max(max_value_size, Address::size().get() as u32);
+
         // Because we assume that we have exclusive access to the memory,
         // we can store the `BTreeHeader` at address zero, and the allocator is
         // stored directly after the `BTreeHeader`.
@@ -119,14 +138,21 @@ impl<M: Memory + Clone, K: Storable, V: Storable> StableBTreeMap<M, K, V> {
     }
 
     /// Loads the map from memory.
+    ///
+    /// A map in the V1 layout is migrated to the current layout. Note that if such a map was
+    /// created with a `max_key_size` or `max_value_size` smaller than 8 bytes, larger keys or
+    /// values cannot be stored in overflow chunks and are rejected on insertion.
     pub fn load(memory: M) -> Self {
         // Read the header from memory.
         let header: BTreeHeader = read_struct(Address::from(0), &memory);
         assert_eq!(&header.magic, MAGIC, "Bad magic.");
-        assert_eq!(header.version, LAYOUT_VERSION, "Unsupported version.");
+        assert!(
+            header.version == LAYOUT_VERSION || header.version == LAYOUT_VERSION_1,
+            "Unsupported version."
+        );
 
         let allocator_addr = Address::from(0) + BTreeHeader::size();
-        Self {
+        let btree = Self {
             memory: memory.clone(),
             root_addr: header.root_addr,
             allocator: Allocator::load(memory, allocator_addr),
@@ -134,37 +160,26 @@ impl<M: Memory + Clone, K: Storable, V: Storable> StableBTreeMap<M, K, V> {
             max_value_size: header.max_value_size,
             length: header.length,
             _phantom: PhantomData,
+        };
+
+        if header.version == LAYOUT_VERSION_1 {
+            // A V1 map stores all its values inline, which is also valid in the V2 layout.
+            // The nodes are migrated individually the next time they are saved.
+            btree.save();
         }
+
+        btree
     }
 
     /// Inserts a key-value pair into the map.
     ///
     /// The previous value of the key, if present, is returned.
     ///
-    /// The size of the key/value must be <= the max key/value sizes configured
-    /// for the map. Otherwise, an `InsertError` is returned.
+    /// An `InsertError` is only returned for keys and values that cannot be stored in overflow
+    /// chunks, see [`StableBTreeMap::load`].
     pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, InsertError> {
-        let key = key.to_bytes();
-        let value = value.to_bytes();
-
-        // Verify the size of the key.
-        if key.len() > self.max_key_size as usize {
-            return Err(InsertError::KeyTooLarge {
-                given: key.len(),
-                max: self.max_key_size as usize,
-            });
-        }
-
-        // Verify the size of the value.
-        if value.len() > self.max_value_size as usize {
-            return Err(InsertError::ValueTooLarge {
-                given: value.len(),
-                max: self.max_value_size as usize,
-            });
-        }
-
-        let key = key.to_vec();
-        let value = value.to_vec();
+        let key = key.to_bytes().to_vec();
+        let value = self.store_value(&key, &value.to_bytes())?;
 
         let root = if self.root_addr == NULL {
             // No root present. Allocate one.
@@ -179,9 +194,9 @@ impl<M: Memory + Clone, K: Storable, V: Storable> StableBTreeMap<M, K, V> {
             // Check if the key already exists in the root.
             if let Ok(idx) = root.get_key_idx(&key) {
                 // The key exists. Overwrite it and return the previous value.
-                let (_, previous_value) = root.swap_entry(idx, (key, value));
+                let (key, previous_value) = root.swap_entry(idx, (key, value));
                 root.save(&self.memory);
-                return Ok(Some(V::from_bytes(previous_value)));
+                return Ok(Some(V::from_bytes(self.take_value(&key, previous_value))));
             }
 
             // If the root is full, we need to introduce a new node as the root.
@@ -209,7 +224,9 @@ impl<M: Memory + Clone, K: Storable, V: Storable> StableBTreeMap<M, K, V> {
             }
         };
 
-        Ok(self.insert_nonfull(root, key, value).map(V::from_bytes))
+        Ok(self
+            .insert_nonfull(root, key.clone(), value)
+            .map(|previous_value| V::from_bytes(self.take_value(&key, previous_value))))
     }
 
     // Inserts an entry into a node that is *not full*.
@@ -334,8 +351,9 @@ impl<M: Memory + Clone, K: Storable, V: Storable> StableBTreeMap<M, K, V> {
             return None;
         }
 
-        self.get_helper(self.root_addr, &key.to_bytes())
-            .map(V::from_bytes)
+        let key = key.to_bytes();
+        self.get_helper(self.root_addr, &key)
+            .map(|value| V::from_bytes(self.load_value(&key, value)))
     }
 
     fn get_helper(&self, node_addr: Address, key: &[u8]) -> Option<Vec<u8>> {
@@ -380,8 +398,9 @@ impl<M: Memory + Clone, K: Storable, V: Storable> StableBTreeMap<M, K, V> {
             return None;
         }
 
-        self.remove_helper(self.root_addr, &key.to_bytes())
-            .map(V::from_bytes)
+        let key = key.to_bytes();
+        self.remove_helper(self.root_addr, &key)
+            .map(|value| V::from_bytes(self.take_value(&key, value)))
     }
 
     // A helper method for recursively removing a key from the B-tree.
@@ -738,71 +757,93 @@ impl<M: Memory + Clone, K: Storable, V: Storable> StableBTreeMap<M, K, V> {
         Iter::new(self)
     }
 
+    /// Returns an iterator over the entries in the map whose keys are within the given range,
+    /// sorted by key.
+    ///
+    /// NOTE: keys are ordered by their encoding (see [`Storable::to_bytes`]), which may be
+    /// different from the ordering of `K` itself.
+    pub fn range(&self, key_range: impl RangeBounds<K>) -> Iter<M, K, V> {
+        Iter::new_in_range(
+            self,
+            (
+                encode_bound(key_range.start_bound()),
+                encode_bound(key_range.end_bound()),
+            ),
+        )
+    }
+
     /// Returns an iterator over the entries in the map where keys begin with the given `prefix`.
     /// If the optional `offset` is set, the iterator returned will start from the entry that
     /// contains this `offset` (while still iterating over all remaining entries that begin
     /// with the given `prefix`).
-    pub fn range(&self, prefix: Vec<u8>, offset: Option<Vec<u8>>) -> Iter<M, K, V> {
+    pub fn range_with_prefix(&self, prefix: Vec<u8>, offset: Option<Vec<u8>>) -> Iter<M, K, V> {
+        // All the keys that begin with `prefix` are smaller than the smallest key that is larger
+        // than `prefix` but doesn't begin with it.
+        let end = match prefix_upper_bound(&prefix) {
+            Some(upper_bound) => Bound::Excluded(upper_bound),
+            None => Bound::Unbounded,
+        };
+
+        let mut start = prefix;
+        if let Some(offset) = offset {
+            start.extend_from_slice(&offset);
+        }
+
+        Iter::new_in_range(self, (Bound::Included(start), end))
+    }
+
+    /// Returns the first key-value pair in the map. The key in this pair is the minimum key in
+    /// the map.
+    pub fn first_key_value(&self) -> Option<(K, V)> {
         if self.root_addr == NULL {
-            // Map is empty.
-            return Iter::null(self);
+            return None;
         }
 
-        let mut node = self.load_node(self.root_addr);
-        let mut cursors = vec![];
-        loop {
-            // Look for the prefix in the node.
-            let mut pivot = prefix.clone();
-            if let Some(offset) = &offset {
-                pivot.extend_from_slice(offset);
-            }
-            match node.entries.binary_search_by(|e| e.0.cmp(&pivot)) {
-                Ok(idx) | Err(idx) => {
-                    // If `prefix` is a key in the node, then `idx` would return its
-                    // location. Otherwise, `idx` is the location of the next key in
-                    // lexicographical order.
-
-                    // Load the next child of the node to visit if it exists.
-                    // This is done first to avoid cloning the node.
-                    let child = match node.node_type {
-                        NodeType::Internal => {
-                            // Note that loading a child node cannot fail since
-                            // len(children) = len(entries) + 1
-                            Some(self.load_node(node.children[idx]))
-                        }
-                        NodeType::Leaf => None,
-                    };
-
-                    // If the prefix is found in the node, then add a cursor starting from its index.
-                    if idx < node.entries.len() && node.entries[idx].0.starts_with(&prefix) {
-                        cursors.push(Cursor::Node {
-                            node,
-                            next: Index::Entry(idx),
-                        });
-                    }
+        let (key, value) = self.load_node(self.root_addr).get_min(&self.memory);
+        let value = self.load_value(&key, value);
+        Some((K::from_bytes(key), V::from_bytes(value)))
+    }
 
-                    match child {
-                        None => {
-                            // Leaf node. Return an iterator with the found cursors.
-                            match offset {
-                                Some(offset) => {
-                                    return Iter::new_with_prefix_and_offset(
-                                        self, prefix, offset, cursors,
-                                    );
-                                }
-                                None => {
-                                    return Iter::new_with_prefix(self, prefix, cursors);
-                                }
-                            }
-                        }
-                        Some(child) => {
-                            // Iterate over the child node.
-                            node = child;
-                        }
-                    }
-                }
-            }
+    /// Returns the last key-value pair in the map. The key in this pair is the maximum key in
+    /// the map.
+    pub fn last_key_value(&self) -> Option<(K, V)> {
+        if self.root_addr == NULL {
+            return None;
+        }
+
+        let (key, value) = self.load_node(self.root_addr).get_max(&self.memory);
+        let value = self.load_value(&key, value);
+        Some((K::from_bytes(key), V::from_bytes(value)))
+    }
+
+    /// Removes and returns the first element in the map. The key of this element is the minimum
+    /// key that was in the map.
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        if self.root_addr == NULL {
+            return None;
+        }
+
+        let (key, _) = self.load_node(self.root_addr).get_min(&self.memory);
+        let value = self
+            .remove_helper(self.root_addr, &key)
+            .expect("the minimum key must exist");
+        let value = self.take_value(&key, value);
+        Some((K::from_bytes(key), V::from_bytes(value)))
+    }
+
+    /// Removes and returns the last element in the map. The key of this element is the maximum
+    /// key that was in the map.
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        if self.root_addr == NULL {
+            return None;
         }
+
+        let (key, _) = self.load_node(self.root_addr).get_max(&self.memory);
+        let value = self
+            .remove_helper(self.root_addr, &key)
+            .expect("the maximum key must exist");
+        let value = self.take_value(&key, value);
+        Some((K::from_bytes(key), V::from_bytes(value)))
     }
 
     // Merges one node (`source`) into another (`into`), along with a median entry.
@@ -847,6 +888,99 @@ impl<M: Memory + Clone, K: Storable, V: Storable> StableBTreeMap<M, K, V> {
         lower
     }
 
+    // Returns the representation of the value to store in a node along with `key`. Keys and
+    // values that are too large to be stored inline are written into overflow chunks. A large
+    // value is replaced by a reference to its chunks, and the reference to the chunks of a large
+    // key is prefixed to the value.
+    fn store_value(&mut self, key: &[u8], value: &[u8]) -> Result<Vec<u8>, InsertError> {
+        let key_is_large = key.len() > self.max_key_size as usize;
+        let value_is_large = value.len() > self.max_value_size as usize;
+
+        // Verify the sizes before allocating any chunks.
+        if key_is_large {
+            let max = max_overflow_size(self.max_key_size);
+            if key.len() > max as usize {
+                return Err(InsertError::KeyTooLarge {
+                    given: key.len(),
+                    max: max as usize,
+                });
+            }
+        }
+
+        if value_is_large {
+            let max = max_overflow_size(self.max_value_size);
+            if value.len() > max as usize {
+                return Err(InsertError::ValueTooLarge {
+                    given: value.len(),
+                    max: max as usize,
+                });
+            }
+        }
+
+        let mut stored_value = vec![];
+        if key_is_large {
+            let address = overflow::write_value(&mut self.allocator, &self.memory, key);
+            stored_value.extend_from_slice(&address.get().to_le_bytes());
+        }
+
+        if value_is_large {
+            let address = overflow::write_value(&mut self.allocator, &self.memory, value);
+            stored_value.extend(overflow::encode_ref(
+                address,
+                value.len() as u32,
+                self.max_value_size,
+            ));
+        } else {
+            stored_value.extend_from_slice(value);
+        }
+
+        Ok(stored_value)
+    }
+
+    // Splits the representation of the value stored along with `key` into the address of the
+    // key's overflow chunks, if any, and the representation of the value itself.
+    fn split_stored_value<'a>(
+        &self,
+        key: &[u8],
+        stored_value: &'a [u8],
+    ) -> (Option<Address>, &'a [u8]) {
+        if key.len() > self.max_key_size as usize {
+            let (key_ref, value) = stored_value.split_at(overflow::KEY_REF_SIZE);
+            let address = u64::from_le_bytes(key_ref.try_into().unwrap());
+            (Some(Address::from(address)), value)
+        } else {
+            (None, stored_value)
+        }
+    }
+
+    // Returns the value given its representation in a node.
+    fn load_value(&self, key: &[u8], stored_value: Vec<u8>) -> Vec<u8> {
+        let (_, value) = self.split_stored_value(key, &stored_value);
+        if overflow::is_ref(value, self.max_value_size) {
+            let (address, len) = overflow::decode_ref(value);
+            overflow::read_value(&self.memory, self.allocator.allocation_size(), address, len)
+        } else {
+            value.to_vec()
+        }
+    }
+
+    // Returns the value given its representation in a node for an entry that is no longer
+    // referenced by the map, deallocating the overflow chunks of its key and value, if any.
+    fn take_value(&mut self, key: &[u8], stored_value: Vec<u8>) -> Vec<u8> {
+        let value = self.load_value(key, stored_value.clone());
+
+        let (key_address, stored_value) = self.split_stored_value(key, &stored_value);
+        if let Some(address) = key_address {
+            overflow::free_value(&mut self.allocator, &self.memory, address);
+        }
+        if overflow::is_ref(stored_value, self.max_value_size) {
+            let (address, _) = overflow::decode_ref(stored_value);
+            overflow::free_value(&mut self.allocator, &self.memory, address);
+        }
+
+        value
+    }
+
     fn allocate_node(&mut self, node_type: NodeType) -> Node {
         Node {
             address: self.allocator.allocate(),
@@ -883,6 +1017,39 @@ impl<M: Memory + Clone, K: Storable, V: Storable> StableBTreeMap<M, K, V> {
     }
 }
 
+// Converts a bound on a key into a bound on its encoding.
+fn encode_bound<K: Storable>(bound: Bound<&K>) -> Bound<Vec<u8>> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.to_bytes().to_vec()),
+        Bound::Excluded(key) => Bound::Excluded(key.to_bytes().to_vec()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+// Returns the smallest byte string that is larger than all the byte strings beginning with
+// `prefix`, or `None` if no such byte string exists (i.e. the prefix consists only of 0xFF bytes).
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut upper_bound = prefix.to_vec();
+    while let Some(last) = upper_bound.pop() {
+        if last < u8::MAX {
+            upper_bound.push(last + 1);
+            return Some(upper_bound);
+        }
+    }
+    None
+}
+
+// Returns the maximum size of a key or value that can be stored in a map whose nodes reserve
+// `inline_size` bytes for it. Maps migrated from the V1 layout may not have enough space in their
+// nodes to store a reference to overflow chunks.
+fn max_overflow_size(inline_size: u32) -> u32 {
+    if (inline_size as u64) < Address::size().get() {
+        inline_size
+    } else {
+        overflow::MAX_OVERFLOW_VALUE_SIZE
+    }
+}
+
 /// An error returned when inserting entries into the map.
 #[derive(Debug, PartialEq)]
 pub enum InsertError {
@@ -1739,9 +1906,14 @@ mod test {
         let btree = StableBTreeMap::<_, Vec<u8>, Vec<u8>>::new(mem, 5, 5);
 
         // Test prefixes that don't exist in the map.
-        assert_eq!(btree.range(vec![0], None).collect::<Vec<_>>(), vec![]);
         assert_eq!(
-            btree.range(vec![1, 2, 3, 4], None).collect::<Vec<_>>(),
+            btree.range_with_prefix(vec![0], None).collect::<Vec<_>>(),
+            vec![]
+        );
+        assert_eq!(
+            btree
+                .range_with_prefix(vec![1, 2, 3, 4], None)
+                .collect::<Vec<_>>(),
             vec![]
         );
     }
@@ -1755,7 +1927,10 @@ mod test {
         btree.insert(vec![0], vec![]).unwrap();
 
         // Test a prefix that's larger than the value in the leaf node. Should be empty.
-        assert_eq!(btree.range(vec![1], None).collect::<Vec<_>>(), vec![]);
+        assert_eq!(
+            btree.range_with_prefix(vec![1], None).collect::<Vec<_>>(),
+            vec![]
+        );
     }
 
     // Tests the case where the prefix is larger than all the entries in an internal node.
@@ -1775,7 +1950,7 @@ mod test {
 
         // Test a prefix that's larger than the value in the internal node.
         assert_eq!(
-            btree.range(vec![7], None).collect::<Vec<_>>(),
+            btree.range_with_prefix(vec![7], None).collect::<Vec<_>>(),
             vec![(vec![7], vec![])]
         );
     }
@@ -1810,7 +1985,7 @@ mod test {
 
         // Tests a prefix that's smaller than the value in the internal node.
         assert_eq!(
-            btree.range(vec![0], None).collect::<Vec<_>>(),
+            btree.range_with_prefix(vec![0], None).collect::<Vec<_>>(),
             vec![
                 (vec![0, 1], vec![]),
                 (vec![0, 2], vec![]),
@@ -1821,7 +1996,7 @@ mod test {
 
         // Tests a prefix that crosses several nodes.
         assert_eq!(
-            btree.range(vec![1], None).collect::<Vec<_>>(),
+            btree.range_with_prefix(vec![1], None).collect::<Vec<_>>(),
             vec![
                 (vec![1, 1], vec![]),
                 (vec![1, 2], vec![]),
@@ -1832,7 +2007,7 @@ mod test {
 
         // Tests a prefix that's larger than the value in the internal node.
         assert_eq!(
-            btree.range(vec![2], None).collect::<Vec<_>>(),
+            btree.range_with_prefix(vec![2], None).collect::<Vec<_>>(),
             vec![
                 (vec![2, 1], vec![]),
                 (vec![2, 2], vec![]),
@@ -1920,11 +2095,16 @@ mod test {
         );
 
         // Tests a prefix that doesn't exist, but is in the middle of the root node.
-        assert_eq!(btree.range(vec![1, 5], None).collect::<Vec<_>>(), vec![]);
+        assert_eq!(
+            btree
+                .range_with_prefix(vec![1, 5], None)
+                .collect::<Vec<_>>(),
+            vec![]
+        );
 
         // Tests a prefix that crosses several nodes.
         assert_eq!(
-            btree.range(vec![1], None).collect::<Vec<_>>(),
+            btree.range_with_prefix(vec![1], None).collect::<Vec<_>>(),
             vec![
                 (vec![1, 2], vec![]),
                 (vec![1, 4], vec![]),
@@ -1937,7 +2117,7 @@ mod test {
         // Tests a prefix that starts from a leaf node, then iterates through the root and right
         // sibling.
         assert_eq!(
-            btree.range(vec![2], None).collect::<Vec<_>>(),
+            btree.range_with_prefix(vec![2], None).collect::<Vec<_>>(),
             vec![
                 (vec![2, 1], vec![]),
                 (vec![2, 2], vec![]),
@@ -1979,7 +2159,7 @@ mod test {
         // Getting the range with a prefix should return all 1000 elements with that prefix.
         for prefix in 0..=1 {
             let mut i: u32 = 0;
-            for (key, _) in btree.range(vec![prefix], None) {
+            for (key, _) in btree.range_with_prefix(vec![prefix], None) {
                 assert_eq!(
                     key,
                     vec![vec![prefix], i.to_be_bytes().to_vec()]
@@ -2023,7 +2203,9 @@ mod test {
 
         // Tests a offset that's smaller than the value in the internal node.
         assert_eq!(
-            btree.range(vec![0], Some(vec![0])).collect::<Vec<_>>(),
+            btree
+                .range_with_prefix(vec![0], Some(vec![0]))
+                .collect::<Vec<_>>(),
             vec![
                 (vec![0, 1], vec![]),
                 (vec![0, 2], vec![]),
@@ -2034,13 +2216,17 @@ mod test {
 
         // Tests a offset that has a value somewhere in the range of values of an internal node.
         assert_eq!(
-            btree.range(vec![1], Some(vec![3])).collect::<Vec<_>>(),
+            btree
+                .range_with_prefix(vec![1], Some(vec![3]))
+                .collect::<Vec<_>>(),
             vec![(vec![1, 3], vec![]), (vec![1, 4], vec![]),]
         );
 
         // Tests a offset that's larger than the value in the internal node.
         assert_eq!(
-            btree.range(vec![2], Some(vec![5])).collect::<Vec<_>>(),
+            btree
+                .range_with_prefix(vec![2], Some(vec![5]))
+                .collect::<Vec<_>>(),
             vec![],
         );
     }
@@ -2124,7 +2310,9 @@ mod test {
 
         // Tests a offset that crosses several nodes.
         assert_eq!(
-            btree.range(vec![1], Some(vec![4])).collect::<Vec<_>>(),
+            btree
+                .range_with_prefix(vec![1], Some(vec![4]))
+                .collect::<Vec<_>>(),
             vec![
                 (vec![1, 4], vec![]),
                 (vec![1, 6], vec![]),
@@ -2136,7 +2324,9 @@ mod test {
         // Tests a offset that starts from a leaf node, then iterates through the root and right
         // sibling.
         assert_eq!(
-            btree.range(vec![2], Some(vec![2])).collect::<Vec<_>>(),
+            btree
+                .range_with_prefix(vec![2], Some(vec![2]))
+                .collect::<Vec<_>>(),
             vec![
                 (vec![2, 2], vec![]),
                 (vec![2, 3], vec![]),
@@ -2149,4 +2339,343 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn range_with_bounds() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem, 4, 0);
+        let mut std_btree = std::collections::BTreeMap::new();
+
+        // Insert the even numbers so that the bounds hit both existing and missing keys.
+        // The keys are big-endian encoded so that their byte representation is sorted.
+        for i in (0..200u32).step_by(2) {
+            btree.insert(i.to_be_bytes().to_vec(), vec![]).unwrap();
+            std_btree.insert(i.to_be_bytes().to_vec(), vec![]);
+        }
+
+        let key = |i: u32| i.to_be_bytes().to_vec();
+        let bounds = |i: u32| {
+            vec![
+                Bound::Included(key(i)),
+                Bound::Excluded(key(i)),
+                Bound::Unbounded,
+            ]
+        };
+
+        for start in (0..205).step_by(7) {
+            for end in (0..205).step_by(11) {
+                for start_bound in bounds(start) {
+                    for end_bound in bounds(end) {
+                        let range = (start_bound.clone(), end_bound);
+                        let expected: Vec<_> = std_btree
+                            .iter()
+                            .filter(|(k, _)| range.contains(*k))
+                            .map(|(k, v)| (k.clone(), v.clone()))
+                            .collect();
+                        assert_eq!(btree.range(range.clone()).collect::<Vec<_>>(), expected);
+
+                        let expected_rev: Vec<_> = expected.iter().cloned().rev().collect();
+                        assert_eq!(btree.range(range).rev().collect::<Vec<_>>(), expected_rev);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn range_with_std_range_syntax() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem, 1, 0);
+
+        for i in 0..50u8 {
+            btree.insert(vec![i], vec![]).unwrap();
+        }
+
+        fn keys(iter: impl Iterator<Item = (Vec<u8>, Vec<u8>)>) -> Vec<u8> {
+            iter.map(|(k, _)| k[0]).collect()
+        }
+
+        assert_eq!(
+            keys(btree.range(vec![10]..vec![15])),
+            vec![10, 11, 12, 13, 14]
+        );
+        assert_eq!(keys(btree.range(vec![10]..=vec![12])), vec![10, 11, 12]);
+        assert_eq!(keys(btree.range(vec![47]..)), vec![47, 48, 49]);
+        assert_eq!(keys(btree.range(..vec![3])), vec![0, 1, 2]);
+        assert_eq!(keys(btree.range(..vec![3]).rev()), vec![2, 1, 0]);
+        assert_eq!(keys(btree.range(..)).len(), 50);
+    }
+
+    #[test]
+    fn range_with_prefix_of_max_bytes() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem, 2, 0);
+
+        btree.insert(vec![254, 255], vec![]).unwrap();
+        btree.insert(vec![255], vec![]).unwrap();
+        btree.insert(vec![255, 0], vec![]).unwrap();
+        btree.insert(vec![255, 255], vec![]).unwrap();
+
+        assert_eq!(
+            btree.range_with_prefix(vec![255], None).collect::<Vec<_>>(),
+            vec![
+                (vec![255], vec![]),
+                (vec![255, 0], vec![]),
+                (vec![255, 255], vec![])
+            ]
+        );
+        assert_eq!(
+            btree
+                .range_with_prefix(vec![254], None)
+                .rev()
+                .collect::<Vec<_>>(),
+            vec![(vec![254, 255], vec![])]
+        );
+    }
+
+    #[test]
+    fn first_and_last_key_value() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem, 1, 1);
+
+        assert_eq!(btree.first_key_value(), None);
+        assert_eq!(btree.last_key_value(), None);
+
+        for i in (0..100).rev() {
+            btree.insert(vec![i], vec![i + 1]).unwrap();
+            assert_eq!(btree.first_key_value(), Some((vec![i], vec![i + 1])));
+            assert_eq!(btree.last_key_value(), Some((vec![99], vec![100])));
+        }
+    }
+
+    #[test]
+    fn pop_first_and_last() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem, 1, 1);
+
+        assert_eq!(btree.pop_first(), None);
+        assert_eq!(btree.pop_last(), None);
+
+        for i in 0..100 {
+            btree.insert(vec![i], vec![i + 1]).unwrap();
+        }
+
+        for i in 0..50 {
+            assert_eq!(btree.pop_first(), Some((vec![i], vec![i + 1])));
+            assert_eq!(btree.pop_last(), Some((vec![99 - i], vec![100 - i])));
+            assert_eq!(btree.len(), 98 - 2 * i as u64);
+        }
+
+        assert!(btree.is_empty());
+        assert_eq!(btree.pop_first(), None);
+        assert_eq!(btree.pop_last(), None);
+        assert_eq!(btree.allocator.num_allocated_chunks(), 0);
+    }
+
+    #[test]
+    fn large_values_are_stored_in_overflow_chunks() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem.clone(), 5, 10);
+
+        // A mix of inline values and values that span several overflow chunks.
+        let value = |i: u8| vec![i; i as usize * 100];
+        for i in 0..50u8 {
+            assert_eq!(btree.insert(vec![i], value(i)), Ok(None));
+        }
+
+        for i in 0..50u8 {
+            assert_eq!(btree.get(&vec![i]), Some(value(i)));
+        }
+        assert_eq!(
+            btree.iter().collect::<Vec<_>>(),
+            (0..50u8).map(|i| (vec![i], value(i))).collect::<Vec<_>>()
+        );
+
+        // The values survive a reload.
+        let btree: StableBTreeMap<_, Vec<u8>, Vec<u8>> = StableBTreeMap::load(mem);
+        for i in 0..50u8 {
+            assert_eq!(btree.get(&vec![i]), Some(value(i)));
+        }
+    }
+
+    #[test]
+    fn overflow_chunks_are_deallocated() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem, 5, 10);
+
+        let large_value = vec![1; 10_000];
+        assert_eq!(btree.insert(vec![1], large_value.clone()), Ok(None));
+        assert!(btree.allocator.num_allocated_chunks() > 1);
+
+        // Overwriting a large value with a small one frees its overflow chunks.
+        assert_eq!(
+            btree.insert(vec![1], vec![2]),
+            Ok(Some(large_value.clone()))
+        );
+        assert_eq!(btree.allocator.num_allocated_chunks(), 1);
+
+        // Removing a large value frees its overflow chunks.
+        assert_eq!(
+            btree.insert(vec![1], large_value.clone()),
+            Ok(Some(vec![2]))
+        );
+        assert_eq!(btree.remove(&vec![1]), Some(large_value));
+        assert_eq!(btree.allocator.num_allocated_chunks(), 0);
+    }
+
+    #[test]
+    fn large_values_move_between_nodes() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem, 5, 8);
+
+        // Enough entries to cause splits and merges, which move entries across nodes.
+        let value = |i: u8| vec![i; 50];
+        for i in 0..=255u8 {
+            assert_eq!(btree.insert(vec![i], value(i)), Ok(None));
+        }
+
+        for i in (0..=255u8).step_by(2) {
+            assert_eq!(btree.remove(&vec![i]), Some(value(i)));
+        }
+
+        for i in 0..=255u8 {
+            let expected = if i % 2 == 0 { None } else { Some(value(i)) };
+            assert_eq!(btree.get(&vec![i]), expected);
+        }
+
+        while btree.pop_first().is_some() {}
+        assert_eq!(btree.allocator.num_allocated_chunks(), 0);
+    }
+
+    // Rewrites a map with a single node as if it was written with the V1 layout.
+    fn downgrade_to_v1<M: Memory + Clone>(btree: &StableBTreeMap<M, Vec<u8>, Vec<u8>>) {
+        let root = btree.load_node(btree.root_addr);
+        assert_eq!(root.node_type, NodeType::Leaf);
+
+        // The version is the fourth byte of both the map's and the node's header.
+        btree.memory.write(3, &[LAYOUT_VERSION_1]);
+        btree
+            .memory
+            .write(btree.root_addr.get() + 3, &[LAYOUT_VERSION_1]);
+    }
+
+    #[test]
+    fn v1_layout_is_migrated_on_load() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem.clone(), 5, 10);
+        for i in 0..5u8 {
+            assert_eq!(btree.insert(vec![i], vec![i; 10]), Ok(None));
+        }
+        downgrade_to_v1(&btree);
+
+        let mut btree: StableBTreeMap<_, Vec<u8>, Vec<u8>> =
+            StableBTreeMap::init(mem.clone(), 5, 10);
+        let header: BTreeHeader = read_struct(Address::from(0), &mem);
+        assert_eq!(header.version, LAYOUT_VERSION);
+
+        for i in 0..5u8 {
+            assert_eq!(btree.get(&vec![i]), Some(vec![i; 10]));
+        }
+
+        // Large values can now be stored.
+        assert_eq!(btree.insert(vec![5], vec![5; 1000]), Ok(None));
+        assert_eq!(btree.get(&vec![5]), Some(vec![5; 1000]));
+    }
+
+    #[test]
+    fn v1_layout_with_small_values_rejects_large_values() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem.clone(), 5, 8);
+        assert_eq!(btree.insert(vec![1], vec![1]), Ok(None));
+        downgrade_to_v1(&btree);
+
+        // Simulate a V1 map that was created with values of at most 4 bytes.
+        let mut header: BTreeHeader = read_struct(Address::from(0), &mem);
+        header.max_value_size = 4;
+        write_struct(&header, Address::from(0), &mem);
+
+        let mut btree: StableBTreeMap<_, Vec<u8>, Vec<u8>> = StableBTreeMap::load(mem);
+        assert_eq!(btree.insert(vec![2], vec![2; 4]), Ok(None));
+        assert_eq!(
+            btree.insert(vec![3], vec![3; 5]),
+            Err(InsertError::ValueTooLarge { given: 5, max: 4 })
+        );
+    }
+
+    #[test]
+    fn large_keys_are_stored_in_overflow_chunks() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem.clone(), 8, 8);
+        let mut std_btree = std::collections::BTreeMap::new();
+
+        // Keys of varying lengths that share long prefixes, so that comparisons need the keys
+        // stored in overflow chunks, with both inline and large values.
+        let key = |i: u32| vec![(i % 3) as u8; (i * 37 % 500) as usize + 1];
+        let value = |i: u32| vec![i as u8; (i * 13 % 40) as usize];
+        for i in 0..200 {
+            assert_eq!(
+                btree.insert(key(i), value(i)),
+                Ok(std_btree.insert(key(i), value(i)))
+            );
+        }
+
+        assert_eq!(
+            btree.iter().collect::<Vec<_>>(),
+            std_btree.clone().into_iter().collect::<Vec<_>>()
+        );
+
+        // The keys survive a reload.
+        let mut btree: StableBTreeMap<_, Vec<u8>, Vec<u8>> = StableBTreeMap::load(mem);
+        for i in 0..200 {
+            assert_eq!(btree.get(&key(i)), std_btree.get(&key(i)).cloned());
+        }
+
+        // Removing entries moves the remaining ones across nodes.
+        for i in (0..200).step_by(2) {
+            assert_eq!(btree.remove(&key(i)), std_btree.remove(&key(i)));
+        }
+        assert_eq!(
+            btree.iter().collect::<Vec<_>>(),
+            std_btree.into_iter().collect::<Vec<_>>()
+        );
+
+        while btree.pop_last().is_some() {}
+        assert_eq!(btree.allocator.num_allocated_chunks(), 0);
+    }
+
+    #[test]
+    fn overwriting_large_keys_deallocates_overflow_chunks() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem, 8, 8);
+
+        let large_key = vec![1; 1000];
+        assert_eq!(btree.insert(large_key.clone(), vec![1]), Ok(None));
+        let num_chunks = btree.allocator.num_allocated_chunks();
+        assert!(num_chunks > 1);
+
+        assert_eq!(btree.insert(large_key.clone(), vec![2]), Ok(Some(vec![1])));
+        assert_eq!(btree.allocator.num_allocated_chunks(), num_chunks);
+
+        assert_eq!(btree.remove(&large_key), Some(vec![2]));
+        assert_eq!(btree.allocator.num_allocated_chunks(), 0);
+    }
+
+    #[test]
+    fn v1_layout_with_small_keys_rejects_large_keys() {
+        let mem = make_memory();
+        let _btree: StableBTreeMap<_, Vec<u8>, Vec<u8>> = StableBTreeMap::new(mem.clone(), 8, 8);
+
+        // Simulate an empty V1 map that was created with keys of at most 4 bytes.
+        let mut header: BTreeHeader = read_struct(Address::from(0), &mem);
+        header.version = LAYOUT_VERSION_1;
+        header.max_key_size = 4;
+        write_struct(&header, Address::from(0), &mem);
+
+        let mut btree: StableBTreeMap<_, Vec<u8>, Vec<u8>> = StableBTreeMap::load(mem);
+        assert_eq!(btree.insert(vec![1; 4], vec![1]), Ok(None));
+        assert_eq!(
+            btree.insert(vec![2; 5], vec![2]),
+            Err(InsertError::KeyTooLarge { given: 5, max: 4 })
+        );
+    }
 }
@@ -218,6 +218,11 @@ impl<M: Memory> Allocator<M> {
         write_struct(&header, self.header_addr, &self.memory);
     }
 
+    /// Returns the size of the chunks available to the user.
+    pub fn allocation_size(&self) -> Bytes {
+        self.allocation_size
+    }
+
     #[cfg(test)]
     pub fn num_allocated_chunks(&self) -> u64 {
         self.num_allocated_chunks
@@ -3,96 +3,200 @@ use super::{
     StableBTreeMap,
 };
 use crate::{types::NULL, Address, Memory, Storable};
+use std::ops::{Bound, RangeBounds};
 
 /// An indicator of the current position in the map.
-pub(crate) enum Cursor {
+enum Cursor {
     Address(Address),
     Node { node: Node, next: Index },
 }
 
 /// An index into a node's child or entry.
-pub(crate) enum Index {
+enum Index {
     Child(usize),
     Entry(usize),
 }
 
 /// An iterator over the entries of a [`StableBTreeMap`].
+///
+/// The iterator is double-ended: entries can be consumed in ascending order with `next` and in
+/// descending order with `next_back`, and the two ends never yield the same entry.
 #[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct Iter<'a, M: Memory, K: Storable, V: Storable> {
     // A reference to the map being iterated on.
     map: &'a StableBTreeMap<M, K, V>,
 
-    // A stack of cursors indicating the current position in the tree.
-    cursors: Vec<Cursor>,
+    // A flag indicating if the cursors have been initialized yet.
+    cursors_initialized: bool,
 
-    // An optional prefix that the keys of all the entries returned must have.
-    // Iteration stops as soon as it runs into a key that doesn't have this prefix.
-    prefix: Option<Vec<u8>>,
+    // A stack of cursors indicating the current position in the tree when iterating forward.
+    forward_cursors: Vec<Cursor>,
 
-    // An optional offset to begin iterating from in the keys with the same prefix.
-    // Used only in the case that prefix is also set.
-    offset: Option<Vec<u8>>,
+    // A stack of cursors indicating the current position in the tree when iterating backward.
+    backward_cursors: Vec<Cursor>,
+
+    // The range of keys (in their encoded form) that the iterator still has to return.
+    // The range shrinks as entries are consumed from either end so that the forward and the
+    // backward iteration stop once they meet.
+    range: (Bound<Vec<u8>>, Bound<Vec<u8>>),
 }
 
-impl<'a, M: Memory, K: Storable, V: Storable> Iter<'a, M, K, V> {
+impl<'a, M: Memory + Clone, K: Storable, V: Storable> Iter<'a, M, K, V> {
     pub(crate) fn new(map: &'a StableBTreeMap<M, K, V>) -> Self {
-        Self {
-            map,
-            // Initialize the cursors with the address of the root of the map.
-            cursors: vec![Cursor::Address(map.root_addr)],
-            prefix: None,
-            offset: None,
-        }
+        Self::new_in_range(map, (Bound::Unbounded, Bound::Unbounded))
     }
 
-    /// Returns an empty iterator.
-    pub(crate) fn null(map: &'a StableBTreeMap<M, K, V>) -> Self {
+    /// Returns an iterator over the entries with keys in the given range.
+    pub(crate) fn new_in_range(
+        map: &'a StableBTreeMap<M, K, V>,
+        range: (Bound<Vec<u8>>, Bound<Vec<u8>>),
+    ) -> Self {
         Self {
             map,
-            cursors: vec![],
-            prefix: None,
-            offset: None,
+            cursors_initialized: false,
+            forward_cursors: vec![],
+            backward_cursors: vec![],
+            range,
         }
     }
 
-    pub(crate) fn new_with_prefix(
-        map: &'a StableBTreeMap<M, K, V>,
-        prefix: Vec<u8>,
-        cursors: Vec<Cursor>,
-    ) -> Self {
-        Self {
-            map,
-            cursors,
-            prefix: Some(prefix),
-            offset: None,
+    // Positions the cursors at both ends of the range. This is done lazily on the first call to
+    // `next` or `next_back` to avoid any work for iterators that are never consumed.
+    fn initialize_cursors(&mut self) {
+        debug_assert!(!self.cursors_initialized);
+        self.cursors_initialized = true;
+
+        if self.map.root_addr == NULL {
+            // Map is empty.
+            return;
         }
+
+        self.forward_cursors = self.start_cursors();
+        self.backward_cursors = self.end_cursors();
     }
 
-    pub(crate) fn new_with_prefix_and_offset(
-        map: &'a StableBTreeMap<M, K, V>,
-        prefix: Vec<u8>,
-        offset: Vec<u8>,
-        cursors: Vec<Cursor>,
-    ) -> Self {
-        Self {
-            map,
-            cursors,
-            prefix: Some(prefix),
-            offset: Some(offset),
+    // Returns the cursors pointing to the first entry within the range.
+    fn start_cursors(&self) -> Vec<Cursor> {
+        let start = match &self.range.0 {
+            Bound::Unbounded => return vec![Cursor::Address(self.map.root_addr)],
+            Bound::Included(key) | Bound::Excluded(key) => key.clone(),
+        };
+        let included = matches!(self.range.0, Bound::Included(_));
+
+        let mut cursors = vec![];
+        let mut node = self.map.load_node(self.map.root_addr);
+        loop {
+            match node.entries.binary_search_by(|e| e.0.cmp(&start)) {
+                Ok(idx) => {
+                    // The start key is in this node. Iteration starts either from this entry or,
+                    // if it's excluded, from whatever comes right after it.
+                    let next = if included {
+                        Index::Entry(idx)
+                    } else {
+                        match node.node_type {
+                            NodeType::Internal => Index::Child(idx + 1),
+                            NodeType::Leaf => Index::Entry(idx + 1),
+                        }
+                    };
+                    cursors.push(Cursor::Node { node, next });
+                    return cursors;
+                }
+                Err(idx) => {
+                    // The start key isn't in the node. `idx` is the location of the next key in
+                    // lexicographical order, which is visited after the subtree at `idx`.
+
+                    // Load the next child of the node to visit if it exists.
+                    // This is done first to avoid cloning the node.
+                    let child = match node.node_type {
+                        // Note that loading a child node cannot fail since
+                        // len(children) = len(entries) + 1
+                        NodeType::Internal => Some(self.map.load_node(node.children[idx])),
+                        NodeType::Leaf => None,
+                    };
+
+                    if idx < node.entries.len() {
+                        cursors.push(Cursor::Node {
+                            node,
+                            next: Index::Entry(idx),
+                        });
+                    }
+
+                    match child {
+                        None => return cursors,
+                        Some(child) => node = child,
+                    }
+                }
+            }
         }
     }
-}
 
-impl<M: Memory + Clone, K: Storable, V: Storable> Iterator for Iter<'_, M, K, V> {
-    type Item = (K, V);
+    // Returns the cursors pointing to the last entry within the range.
+    fn end_cursors(&self) -> Vec<Cursor> {
+        let end = match &self.range.1 {
+            Bound::Unbounded => return vec![Cursor::Address(self.map.root_addr)],
+            Bound::Included(key) | Bound::Excluded(key) => key.clone(),
+        };
+        let included = matches!(self.range.1, Bound::Included(_));
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.cursors.pop() {
-            Some(Cursor::Address(address)) => {
+        let mut cursors = vec![];
+        let mut node = self.map.load_node(self.map.root_addr);
+        loop {
+            match node.entries.binary_search_by(|e| e.0.cmp(&end)) {
+                Ok(idx) => {
+                    // The end key is in this node. Iteration starts either from this entry or,
+                    // if it's excluded, from whatever comes right before it.
+                    if included {
+                        cursors.push(Cursor::Node {
+                            node,
+                            next: Index::Entry(idx),
+                        });
+                    } else {
+                        match node.node_type {
+                            NodeType::Internal => cursors.push(Cursor::Node {
+                                node,
+                                next: Index::Child(idx),
+                            }),
+                            NodeType::Leaf if idx > 0 => cursors.push(Cursor::Node {
+                                node,
+                                next: Index::Entry(idx - 1),
+                            }),
+                            NodeType::Leaf => {}
+                        }
+                    }
+                    return cursors;
+                }
+                Err(idx) => {
+                    // The end key isn't in the node. `idx - 1` is the location of the previous
+                    // key in lexicographical order, which is visited after the subtree at `idx`.
+                    let child = match node.node_type {
+                        NodeType::Internal => Some(self.map.load_node(node.children[idx])),
+                        NodeType::Leaf => None,
+                    };
+
+                    if idx > 0 {
+                        cursors.push(Cursor::Node {
+                            node,
+                            next: Index::Entry(idx - 1),
+                        });
+                    }
+
+                    match child {
+                        None => return cursors,
+                        Some(child) => node = child,
+                    }
+                }
+            }
+        }
+    }
+
+    // Returns the entry at the top of the forward cursors, if any, and advances them.
+    fn next_forward(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        match self.forward_cursors.pop()? {
+            Cursor::Address(address) => {
                 if address != NULL {
                     // Load the node at the given address, and add it to the cursors.
                     let node = self.map.load_node(address);
-                    self.cursors.push(Cursor::Node {
+                    self.forward_cursors.push(Cursor::Node {
                         next: match node.node_type {
                             // Iterate on internal nodes starting from the first child.
                             NodeType::Internal => Index::Child(0),
@@ -102,13 +206,13 @@ impl<M: Memory + Clone, K: Storable, V: Storable> Iterator for Iter<'_, M, K, V>
                         node,
                     });
                 }
-                self.next()
+                self.next_forward()
             }
 
-            Some(Cursor::Node {
+            Cursor::Node {
                 node,
                 next: Index::Child(child_idx),
-            }) => {
+            } => {
                 let child_address = *node
                     .children
                     .get(child_idx)
@@ -116,24 +220,24 @@ impl<M: Memory + Clone, K: Storable, V: Storable> Iterator for Iter<'_, M, K, V>
 
                 // After iterating on the child, iterate on the next _entry_ in this node.
                 // The entry immediately after the child has the same index as the child's.
-                self.cursors.push(Cursor::Node {
+                self.forward_cursors.push(Cursor::Node {
                     node,
                     next: Index::Entry(child_idx),
                 });
 
                 // Add the child to the top of the cursors to be iterated on first.
-                self.cursors.push(Cursor::Address(child_address));
+                self.forward_cursors.push(Cursor::Address(child_address));
 
-                self.next()
+                self.next_forward()
             }
 
-            Some(Cursor::Node {
+            Cursor::Node {
                 mut node,
                 next: Index::Entry(entry_idx),
-            }) => {
+            } => {
                 if entry_idx >= node.entries.len() {
                     // No more entries to iterate on in this node.
-                    return self.next();
+                    return self.next_forward();
                 }
 
                 // Take the entry from the node. It's swapped with an empty element to
@@ -141,7 +245,7 @@ impl<M: Memory + Clone, K: Storable, V: Storable> Iterator for Iter<'_, M, K, V>
                 let entry = node.swap_entry(entry_idx, (vec![], vec![]));
 
                 // Add to the cursors the next element to be traversed.
-                self.cursors.push(Cursor::Node {
+                self.forward_cursors.push(Cursor::Node {
                     next: match node.node_type {
                         // If this is an internal node, add the next child to the cursors.
                         NodeType::Internal => Index::Child(entry_idx + 1),
@@ -151,34 +255,134 @@ impl<M: Memory + Clone, K: Storable, V: Storable> Iterator for Iter<'_, M, K, V>
                     node,
                 });
 
-                // If there's a prefix, verify that the key has that given prefix.
-                // Otherwise iteration is stopped.
-                if let Some(prefix) = &self.prefix {
-                    if !entry.0.starts_with(prefix) {
-                        // Clear all cursors to avoid needless work in subsequent calls.
-                        self.cursors = vec![];
-                        return None;
-                    } else if let Some(offset) = &self.offset {
-                        let mut prefix_with_offset = prefix.clone();
-                        prefix_with_offset.extend_from_slice(offset);
-                        // Clear all cursors to avoid needless work in subsequent calls.
-                        if entry.0 < prefix_with_offset {
-                            self.cursors = vec![];
-                            return None;
-                        }
+                Some(entry)
+            }
+        }
+    }
+
+    // Returns the entry at the top of the backward cursors, if any, and advances them.
+    fn next_backward(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        match self.backward_cursors.pop()? {
+            Cursor::Address(address) => {
+                if address != NULL {
+                    // Load the node at the given address, and add it to the cursors.
+                    let node = self.map.load_node(address);
+                    let next = match node.node_type {
+                        // Iterate on internal nodes starting from the last child.
+                        NodeType::Internal => Some(Index::Child(node.children.len() - 1)),
+                        // Iterate on leaf nodes starting from the last entry.
+                        NodeType::Leaf => node.entries.len().checked_sub(1).map(Index::Entry),
+                    };
+                    if let Some(next) = next {
+                        self.backward_cursors.push(Cursor::Node { node, next });
                     }
                 }
+                self.next_backward()
+            }
 
-                Some((K::from_bytes(entry.0), V::from_bytes(entry.1)))
+            Cursor::Node {
+                node,
+                next: Index::Child(child_idx),
+            } => {
+                let child_address = *node
+                    .children
+                    .get(child_idx)
+                    .expect("Iterating over children went out of bounds.");
+
+                // After iterating on the child, iterate on the previous _entry_ in this node.
+                // The entry immediately before the child has the child's index minus one.
+                if child_idx > 0 {
+                    self.backward_cursors.push(Cursor::Node {
+                        node,
+                        next: Index::Entry(child_idx - 1),
+                    });
+                }
+
+                // Add the child to the top of the cursors to be iterated on first.
+                self.backward_cursors.push(Cursor::Address(child_address));
+
+                self.next_backward()
+            }
+
+            Cursor::Node {
+                mut node,
+                next: Index::Entry(entry_idx),
+            } => {
+                // Take the entry from the node. It's swapped with an empty element to
+                // avoid cloning.
+                let entry = node.swap_entry(entry_idx, (vec![], vec![]));
+
+                // Add to the cursors the previous element to be traversed.
+                match node.node_type {
+                    // If this is an internal node, add the previous child to the cursors.
+                    NodeType::Internal => self.backward_cursors.push(Cursor::Node {
+                        next: Index::Child(entry_idx),
+                        node,
+                    }),
+                    // If this is a leaf node, add the previous entry to the cursors.
+                    NodeType::Leaf if entry_idx > 0 => self.backward_cursors.push(Cursor::Node {
+                        next: Index::Entry(entry_idx - 1),
+                        node,
+                    }),
+                    NodeType::Leaf => {}
+                }
+
+                Some(entry)
             }
-            None => {
-                // The cursors are empty. Iteration is complete.
+        }
+    }
+
+    // Verifies that the entry is still within the range. If it is, the range is shrunk so that
+    // the entry isn't returned again from the other end. Otherwise, iteration is complete.
+    fn take_if_in_range(
+        &mut self,
+        entry: Option<(Vec<u8>, Vec<u8>)>,
+        from_front: bool,
+    ) -> Option<(K, V)> {
+        match entry {
+            Some((key, value)) if self.range.contains(&key) => {
+                if from_front {
+                    self.range.0 = Bound::Excluded(key.clone());
+                } else {
+                    self.range.1 = Bound::Excluded(key.clone());
+                }
+                let value = self.map.load_value(&key, value);
+                Some((K::from_bytes(key), V::from_bytes(value)))
+            }
+            _ => {
+                // Clear all cursors to avoid needless work in subsequent calls.
+                self.forward_cursors = vec![];
+                self.backward_cursors = vec![];
                 None
             }
         }
     }
 }
 
+impl<M: Memory + Clone, K: Storable, V: Storable> Iterator for Iter<'_, M, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.cursors_initialized {
+            self.initialize_cursors();
+        }
+
+        let entry = self.next_forward();
+        self.take_if_in_range(entry, true)
+    }
+}
+
+impl<M: Memory + Clone, K: Storable, V: Storable> DoubleEndedIterator for Iter<'_, M, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if !self.cursors_initialized {
+            self.initialize_cursors();
+        }
+
+        let entry = self.next_backward();
+        self.take_if_in_range(entry, false)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -229,4 +433,63 @@ mod test {
 
         assert_eq!(i, 100);
     }
+
+    #[test]
+    fn iterate_reverse() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem, 1, 1);
+
+        for i in 0..100 {
+            btree.insert(vec![i], vec![i + 1]).unwrap();
+        }
+
+        // Reverse iteration should be in descending order.
+        let mut i = 100;
+        for (key, value) in btree.iter().rev() {
+            i -= 1;
+            assert_eq!(key, vec![i]);
+            assert_eq!(value, vec![i + 1]);
+        }
+
+        assert_eq!(i, 0);
+    }
+
+    #[test]
+    fn iterate_from_both_ends() {
+        let mem = make_memory();
+        let mut btree = StableBTreeMap::new(mem, 1, 1);
+
+        for i in 0..100 {
+            btree.insert(vec![i], vec![]).unwrap();
+        }
+
+        // Alternate between both ends of the iterator. Every entry must be returned exactly once.
+        let mut iter = btree.iter();
+        let mut front = vec![];
+        let mut back = vec![];
+        loop {
+            match iter.next() {
+                Some((key, _)) => front.push(key[0]),
+                None => break,
+            }
+            match iter.next_back() {
+                Some((key, _)) => back.push(key[0]),
+                None => break,
+            }
+        }
+
+        assert_eq!(front, (0..50).collect::<Vec<_>>());
+        assert_eq!(back, (50..100).rev().collect::<Vec<_>>());
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn iterate_empty() {
+        let mem = make_memory();
+        let btree = StableBTreeMap::<_, Vec<u8>, Vec<u8>>::new(mem, 1, 1);
+
+        assert_eq!(btree.iter().next(), None);
+        assert_eq!(btree.iter().next_back(), None);
+    }
 }
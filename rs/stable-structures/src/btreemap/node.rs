@@ -1,3 +1,4 @@
+use super::overflow::{decode_ref, encode_ref, is_ref, read_value, KEY_REF_SIZE, OVERFLOW_FLAG};
 use crate::{
     read_struct, read_u32, read_u64,
     types::{Address, Bytes},
@@ -9,7 +10,10 @@ use crate::{
 pub const B: u64 = 6;
 /// The maximum number of entries per node.
 pub const CAPACITY: u64 = 2 * B - 1;
-const LAYOUT_VERSION: u8 = 1;
+const LAYOUT_VERSION: u8 = 2;
+// Nodes in the V1 layout cannot contain references to overflow values, but are otherwise
+// identical to V2 nodes and are migrated when they are saved.
+const LAYOUT_VERSION_1: u8 = 1;
 const MAGIC: &[u8; 3] = b"BTN";
 const LEAF_NODE_TYPE: u8 = 0;
 const INTERNAL_NODE_TYPE: u8 = 1;
@@ -37,6 +41,10 @@ pub enum NodeType {
 ///     - size of value (4 bytes)
 ///     - value (`max_value_size` bytes)
 ///
+/// If the highest bit of the size of a key or value is set, the key or value is stored in
+/// overflow chunks and its bytes in the node contain the address of the first overflow chunk.
+/// See [`super::overflow`] for more details.
+///
 /// Each node can contain up to `CAPACITY + 1` children, each child is 8 bytes.
 #[derive(Debug, PartialEq)]
 pub struct Node {
@@ -59,7 +67,10 @@ impl Node {
         // Load the header.
         let header: NodeHeader = read_struct(address, memory);
         assert_eq!(&header.magic, MAGIC, "Bad magic.");
-        assert_eq!(header.version, LAYOUT_VERSION, "Unsupported version.");
+        assert!(
+            header.version == LAYOUT_VERSION || header.version == LAYOUT_VERSION_1,
+            "Unsupported version."
+        );
 
         // Load the entries.
         let mut entries = vec![];
@@ -69,20 +80,52 @@ impl Node {
             let key_size = read_u32(memory, address + offset);
             offset += U32_SIZE;
 
-            // Read the key.
-            let mut key = vec![0; key_size as usize];
-            memory.read((address + offset).get(), &mut key);
+            // Read the key, along with the reference to it if it's in overflow chunks.
+            let (key, key_ref) = if key_size & OVERFLOW_FLAG != 0 {
+                let overflow_address = Address::from(read_u64(memory, address + offset));
+                let key = read_value(
+                    memory,
+                    Self::size(max_key_size, max_value_size),
+                    overflow_address,
+                    key_size & !OVERFLOW_FLAG,
+                );
+                (key, Some(overflow_address))
+            } else {
+                let mut key = vec![0; key_size as usize];
+                memory.read((address + offset).get(), &mut key);
+                (key, None)
+            };
             offset += Bytes::from(max_key_size as u64);
 
             // Read the value's size.
             let value_size = read_u32(memory, address + offset);
             offset += U32_SIZE;
 
-            // Read the value.
-            let mut value = vec![0; value_size as usize];
-            memory.read((address + offset).get(), &mut value);
+            // Read the value, or the reference to the value if it's in overflow chunks.
+            let value = if value_size & OVERFLOW_FLAG != 0 {
+                let overflow_address = Address::from(read_u64(memory, address + offset));
+                encode_ref(
+                    overflow_address,
+                    value_size & !OVERFLOW_FLAG,
+                    max_value_size,
+                )
+            } else {
+                let mut value = vec![0; value_size as usize];
+                memory.read((address + offset).get(), &mut value);
+                value
+            };
             offset += Bytes::from(max_value_size as u64);
 
+            // The reference to a key in overflow chunks is kept as a prefix of the value.
+            let value = match key_ref {
+                Some(overflow_address) => {
+                    let mut stored_value = overflow_address.get().to_le_bytes().to_vec();
+                    stored_value.extend(value);
+                    stored_value
+                }
+                None => value,
+            };
+
             entries.push((key, value));
         }
 
@@ -146,21 +189,55 @@ impl Node {
 
         // Write the entries.
         for (key, value) in self.entries.iter() {
-            // Write the size of the key.
-            write_u32(memory, self.address + offset, key.len() as u32);
-            offset += U32_SIZE;
-
-            // Write the key.
-            write(memory, (self.address + offset).get(), key);
-            offset += Bytes::from(self.max_key_size);
-
-            // Write the size of the value.
-            write_u32(memory, self.address + offset, value.len() as u32);
-            offset += U32_SIZE;
-
-            // Write the value.
-            write(memory, (self.address + offset).get(), value);
-            offset += Bytes::from(self.max_value_size);
+            let value = if key.len() > self.max_key_size as usize {
+                // Write the size of the key, flagged as stored in overflow chunks.
+                write_u32(
+                    memory,
+                    self.address + offset,
+                    key.len() as u32 | OVERFLOW_FLAG,
+                );
+                offset += U32_SIZE;
+
+                // Write the address of the key's first overflow chunk, which prefixes the value.
+                debug_assert!(self.max_key_size as u64 >= Address::size().get());
+                let (key_ref, value) = value.split_at(KEY_REF_SIZE);
+                write(memory, (self.address + offset).get(), key_ref);
+                offset += Bytes::from(self.max_key_size);
+                value
+            } else {
+                // Write the size of the key.
+                write_u32(memory, self.address + offset, key.len() as u32);
+                offset += U32_SIZE;
+
+                // Write the key.
+                write(memory, (self.address + offset).get(), key);
+                offset += Bytes::from(self.max_key_size);
+                value.as_slice()
+            };
+
+            if is_ref(value, self.max_value_size) {
+                // Write the size of the value, flagged as stored in overflow chunks.
+                let (overflow_address, value_size) = decode_ref(value);
+                write_u32(memory, self.address + offset, value_size | OVERFLOW_FLAG);
+                offset += U32_SIZE;
+
+                // Write the address of the first overflow chunk.
+                debug_assert!(self.max_value_size as u64 >= Address::size().get());
+                write(
+                    memory,
+                    (self.address + offset).get(),
+                    &overflow_address.get().to_le_bytes(),
+                );
+                offset += Bytes::from(self.max_value_size);
+            } else {
+                // Write the size of the value.
+                write_u32(memory, self.address + offset, value.len() as u32);
+                offset += U32_SIZE;
+
+                // Write the value.
+                write(memory, (self.address + offset).get(), value);
+                offset += Bytes::from(self.max_value_size);
+            }
         }
 
         // Write the children
@@ -0,0 +1,174 @@
+//! Storage for keys and values that are too large to be stored inline in a node.
+//!
+//! A key or value larger than the map's inline key or value size is stored in a linked list of
+//! "overflow" chunks obtained from the map's [`Allocator`], and the node only stores a reference
+//! to the first chunk of the list along with the length of the key or value.
+//!
+//! Each overflow chunk has the following layout:
+//!
+//!    |  next (8 bytes)  |  data (`allocation_size` - 8 bytes)  |
+//!
+//! where `next` is the address of the following chunk, or NULL in the last chunk.
+//!
+//! In a node, a reference is distinguished from an inline value by setting the highest bit of
+//! the value's size (see [`OVERFLOW_FLAG`]). The address of the first chunk is stored where the
+//! inline value would otherwise be, hence overflow values are only supported if the inline value
+//! size is at least 8 bytes.
+//!
+//! Within the map, a reference is represented as a byte vector that is longer than the inline
+//! value size, which makes it distinguishable from any inline value without having to change the
+//! representation of entries.
+//!
+//! Keys are flagged the same way in a node, but they must be compared when searching the map,
+//! so a node loads large keys entirely from their overflow chunks. The address of the first
+//! chunk of a large key is instead prefixed to the entry's value (see [`KEY_REF_SIZE`]), so that
+//! it moves along with the entry between nodes. Entries with large keys are recognized by the
+//! length of their key.
+use super::allocator::Allocator;
+use crate::{
+    read_u64,
+    types::{Address, Bytes, NULL},
+    write, Memory,
+};
+use std::convert::TryInto;
+
+/// The bit set in the size of a value in a node to indicate that the value is stored in
+/// overflow chunks.
+pub const OVERFLOW_FLAG: u32 = 1 << 31;
+
+/// The maximum size of a key or value that can be stored in overflow chunks.
+pub const MAX_OVERFLOW_VALUE_SIZE: u32 = OVERFLOW_FLAG - 1;
+
+/// The size of the address of a large key's first overflow chunk, which prefixes the value of
+/// the entry within the map.
+pub const KEY_REF_SIZE: usize = 8;
+
+// The size of an encoded reference: the address of the first chunk and the length of the value.
+const REF_SIZE: usize = 12;
+
+/// Returns true if the given value, as stored in a node, is a reference to a value stored in
+/// overflow chunks.
+pub fn is_ref(stored_value: &[u8], inline_value_size: u32) -> bool {
+    stored_value.len() > inline_value_size as usize
+}
+
+/// Encodes a reference to a value of length `len` whose first overflow chunk is at `address`.
+pub fn encode_ref(address: Address, len: u32, inline_value_size: u32) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(REF_SIZE);
+    buf.extend_from_slice(&address.get().to_le_bytes());
+    buf.extend_from_slice(&len.to_le_bytes());
+
+    // Pad the reference so that it's longer than any inline value.
+    buf.resize(std::cmp::max(REF_SIZE, inline_value_size as usize + 1), 0);
+    buf
+}
+
+/// Decodes a reference previously encoded with [`encode_ref`].
+pub fn decode_ref(stored_value: &[u8]) -> (Address, u32) {
+    let address = u64::from_le_bytes(stored_value[0..8].try_into().unwrap());
+    let len = u32::from_le_bytes(stored_value[8..REF_SIZE].try_into().unwrap());
+    (Address::from(address), len)
+}
+
+/// Writes the value into newly allocated overflow chunks and returns the address of the first
+/// chunk.
+///
+/// PRECONDITION: !value.is_empty()
+pub fn write_value<M: Memory>(allocator: &mut Allocator<M>, memory: &M, value: &[u8]) -> Address {
+    debug_assert!(!value.is_empty());
+
+    let chunks: Vec<&[u8]> = value
+        .chunks(data_size(allocator.allocation_size()))
+        .collect();
+    let addresses: Vec<Address> = chunks.iter().map(|_| allocator.allocate()).collect();
+
+    for (i, data) in chunks.iter().enumerate() {
+        let next = addresses.get(i + 1).copied().unwrap_or(NULL);
+        write(memory, addresses[i].get(), &next.get().to_le_bytes());
+        write(memory, (addresses[i] + Address::size()).get(), data);
+    }
+
+    addresses[0]
+}
+
+/// Reads a value of length `len` from the overflow chunks starting at `address`, where
+/// `allocation_size` is the size of the chunks of the map's allocator.
+pub fn read_value<M: Memory>(
+    memory: &M,
+    allocation_size: Bytes,
+    mut address: Address,
+    len: u32,
+) -> Vec<u8> {
+    let data_size = data_size(allocation_size);
+    let mut value = vec![0; len as usize];
+    for data in value.chunks_mut(data_size) {
+        assert!(address != NULL, "Overflow value is shorter than expected.");
+        memory.read((address + Address::size()).get(), data);
+        address = Address::from(read_u64(memory, address));
+    }
+    value
+}
+
+/// Deallocates all the overflow chunks starting at `address`.
+pub fn free_value<M: Memory>(allocator: &mut Allocator<M>, memory: &M, mut address: Address) {
+    while address != NULL {
+        let next = Address::from(read_u64(memory, address));
+        allocator.deallocate(address);
+        address = next;
+    }
+}
+
+// The number of bytes of the value stored in each overflow chunk.
+fn data_size(allocation_size: Bytes) -> usize {
+    let allocation_size: usize = allocation_size.into();
+    let data_size = allocation_size - Into::<usize>::into(Address::size());
+    assert!(
+        data_size > 0,
+        "Allocation size is too small for overflow chunks."
+    );
+    data_size
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn make_memory() -> Rc<RefCell<Vec<u8>>> {
+        Rc::new(RefCell::new(Vec::new()))
+    }
+
+    #[test]
+    fn refs_are_longer_than_inline_values() {
+        for inline_value_size in [0, 8, 11, 12, 100] {
+            let r = encode_ref(Address::from(1234), 5678, inline_value_size);
+            assert!(is_ref(&r, inline_value_size));
+            assert_eq!(decode_ref(&r), (Address::from(1234), 5678));
+        }
+    }
+
+    #[test]
+    fn write_read_free() {
+        let mem = make_memory();
+        let mut allocator = Allocator::new(mem.clone(), Address::from(0), Bytes::from(16u64));
+
+        for len in [1, 7, 8, 9, 100, 1000] {
+            let value: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let address = write_value(&mut allocator, &mem, &value);
+            assert_eq!(
+                allocator.num_allocated_chunks(),
+                (len as u64 + 7) / 8,
+                "unexpected number of chunks for a value of length {}",
+                len
+            );
+            assert_eq!(
+                read_value(&mem, allocator.allocation_size(), address, len),
+                value
+            );
+
+            free_value(&mut allocator, &mem, address);
+            assert_eq!(allocator.num_allocated_chunks(), 0);
+        }
+    }
+}
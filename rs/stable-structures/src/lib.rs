@@ -1,17 +1,23 @@
+mod base_vec;
 pub mod btreemap;
 pub mod cell;
 #[cfg(target_arch = "wasm32")]
 mod ic0_memory; // Memory API for canisters.
 pub mod log;
+pub mod memory_manager;
+pub mod min_heap;
 pub mod storable;
 mod types;
+pub mod vec;
 pub mod vec_mem;
 
 pub use btreemap::StableBTreeMap;
 #[cfg(target_arch = "wasm32")]
 pub use ic0_memory::Ic0StableMemory;
+pub use min_heap::StableMinHeap;
 pub use storable::Storable;
 use types::Address;
+pub use vec::StableVec;
 pub use vec_mem::VectorMemory;
 
 #[cfg(target_arch = "wasm32")]
@@ -0,0 +1,735 @@
+//! A module for simulating multiple memories within a single memory.
+//!
+//! The typical way for a canister to have multiple stable structures is by dividing the memory
+//! into distinct ranges with [`RestrictedMemory`](crate::RestrictedMemory), dedicating each range
+//! to a stable structure. This approach has two problems:
+//!
+//! 1. The developer needs to decide in advance on an upper bound for the size of each structure.
+//! 2. Memory is wasted, as each range is sized for the worst case.
+//!
+//! The [`MemoryManager`] solves both problems by simulating up to [`MAX_NUM_MEMORIES`] virtual
+//! memories within a single memory. Each virtual memory can grow independently of the others.
+//!
+//! The underlying memory is divided into "buckets" of a fixed number of WebAssembly pages. When a
+//! virtual memory grows, the manager assigns it new buckets. A virtual memory is then the
+//! concatenation of all the buckets assigned to it, in the order they were assigned.
+//!
+//! # Example
+//!
+//! ```
+//! use stable_structures::memory_manager::{MemoryId, MemoryManager};
+//! use stable_structures::{DefaultMemoryImpl, Memory};
+//!
+//! let mem_mgr = MemoryManager::init(DefaultMemoryImpl::default());
+//! let memory_0 = mem_mgr.get(MemoryId::new(0));
+//! let memory_1 = mem_mgr.get(MemoryId::new(1));
+//!
+//! // Both memories can grow independently of each other.
+//! assert_eq!(memory_0.grow(1), 0);
+//! assert_eq!(memory_1.grow(1), 0);
+//!
+//! memory_0.write(0, &[1, 2, 3]);
+//! memory_1.write(0, &[4, 5, 6]);
+//!
+//! let mut bytes = vec![0; 3];
+//! memory_0.read(0, &mut bytes);
+//! assert_eq!(bytes, vec![1, 2, 3]);
+//!
+//! memory_1.read(0, &mut bytes);
+//! assert_eq!(bytes, vec![4, 5, 6]);
+//! ```
+//!
+//! # V1 layout
+//!
+//! ```text
+//! -------------------------------------------------- <- Address 0
+//! Magic "MGR"                           ↕ 3 bytes
+//! --------------------------------------------------
+//! Layout version                        ↕ 1 byte
+//! --------------------------------------------------
+//! Number of allocated buckets           ↕ 2 bytes
+//! --------------------------------------------------
+//! Bucket size (in pages) = N            ↕ 2 bytes
+//! --------------------------------------------------
+//! Reserved space                        ↕ 32 bytes
+//! --------------------------------------------------
+//! Size of memory 0 (in pages)           ↕ 8 bytes
+//! --------------------------------------------------
+//! Size of memory 1 (in pages)           ↕ 8 bytes
+//! --------------------------------------------------
+//! ...
+//! --------------------------------------------------
+//! Size of memory 254 (in pages)         ↕ 8 bytes
+//! -------------------------------------------------- <- Bucket allocations
+//! Memory ID of bucket 0                 ↕ 1 byte
+//! --------------------------------------------------
+//! Memory ID of bucket 1                 ↕ 1 byte
+//! --------------------------------------------------
+//! ...
+//! --------------------------------------------------
+//! Memory ID of bucket MAX_NUM_BUCKETS-1 ↕ 1 byte
+//! --------------------------------------------------
+//! Unallocated space
+//! -------------------------------------------------- <- Buckets (page 1)
+//! Bucket 0                              ↕ N pages
+//! --------------------------------------------------
+//! Bucket 1                              ↕ N pages
+//! --------------------------------------------------
+//! ...
+//! --------------------------------------------------
+//! Bucket MAX_NUM_BUCKETS-1              ↕ N pages
+//! ```
+use crate::{
+    read_struct,
+    types::{Address, Bytes},
+    write, write_struct, Memory, WASM_PAGE_SIZE,
+};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+const MAGIC: &[u8; 3] = b"MGR";
+const LAYOUT_VERSION: u8 = 1;
+
+/// The maximum number of memories that can be created.
+pub const MAX_NUM_MEMORIES: u8 = 255;
+
+/// The maximum number of buckets the memory manager can handle.
+/// With a bucket size of 128 pages this can support up to 256GiB of memory.
+const MAX_NUM_BUCKETS: u64 = 32768;
+
+const BUCKET_SIZE_IN_PAGES: u64 = 128;
+
+/// A marker in the bucket allocation table for buckets that are not allocated to any memory.
+const UNALLOCATED_BUCKET_MARKER: u8 = MAX_NUM_MEMORIES;
+
+/// The offset where buckets are in memory. The first page is reserved for the header.
+const BUCKETS_OFFSET_IN_PAGES: u64 = 1;
+const BUCKETS_OFFSET_IN_BYTES: u64 = BUCKETS_OFFSET_IN_PAGES * WASM_PAGE_SIZE;
+
+/// Reserved bytes in the header for future extensions.
+const HEADER_RESERVED_BYTES: usize = 32;
+
+/// The identifier of a virtual memory managed by the [`MemoryManager`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MemoryId(u8);
+
+impl MemoryId {
+    /// Creates a new memory identifier.
+    ///
+    /// PRECONDITION: `id` < [`MAX_NUM_MEMORIES`]
+    pub const fn new(id: u8) -> Self {
+        // Any ID can be used except the special value that's used internally to
+        // mark a bucket as unallocated.
+        assert!(id != UNALLOCATED_BUCKET_MARKER);
+
+        Self(id)
+    }
+}
+
+// The ID of a bucket in the underlying memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct BucketId(u16);
+
+/// A memory manager simulates multiple memories within a single memory.
+///
+/// The memory manager can return up to [`MAX_NUM_MEMORIES`] unique instances of
+/// [`VirtualMemory`], and each can be used independently and can grow up to the bounds of the
+/// underlying memory.
+///
+/// The memory manager stores its layout in the first page of the underlying memory, so that a
+/// manager (and all of its memories) can be restored with [`MemoryManager::init`] after an
+/// upgrade.
+pub struct MemoryManager<M: Memory> {
+    inner: Rc<RefCell<MemoryManagerInner<M>>>,
+}
+
+impl<M: Memory> MemoryManager<M> {
+    /// Initializes a `MemoryManager` with the given memory.
+    ///
+    /// If the memory already contains a memory manager, its layout is loaded.
+    /// Otherwise, a new memory manager is created with the default bucket size.
+    pub fn init(memory: M) -> Self {
+        Self::init_with_bucket_size(memory, BUCKET_SIZE_IN_PAGES as u16)
+    }
+
+    /// Initializes a `MemoryManager` with the given memory and bucket size in pages.
+    ///
+    /// The bucket size is ignored if the memory already contains a memory manager.
+    pub fn init_with_bucket_size(memory: M, bucket_size_in_pages: u16) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(MemoryManagerInner::init(
+                memory,
+                bucket_size_in_pages,
+            ))),
+        }
+    }
+
+    /// Returns the virtual memory associated with the given ID.
+    pub fn get(&self, id: MemoryId) -> VirtualMemory<M> {
+        VirtualMemory {
+            id,
+            memory_manager: self.inner.clone(),
+        }
+    }
+}
+
+#[repr(packed)]
+struct Header {
+    magic: [u8; 3],
+    version: u8,
+    // The number of buckets allocated by the memory manager.
+    num_allocated_buckets: u16,
+    // The size of a bucket in Wasm pages.
+    bucket_size_in_pages: u16,
+    // Additional space reserved to add new fields without breaking backward-compatibility.
+    _reserved: [u8; HEADER_RESERVED_BYTES],
+    // The size of each individual memory that can be created by the memory manager.
+    memory_sizes_in_pages: [u64; MAX_NUM_MEMORIES as usize],
+}
+
+impl Header {
+    fn size() -> Bytes {
+        Bytes::from(core::mem::size_of::<Self>() as u64)
+    }
+}
+
+/// A virtual memory handed out by a [`MemoryManager`].
+#[derive(Clone)]
+pub struct VirtualMemory<M: Memory> {
+    id: MemoryId,
+    memory_manager: Rc<RefCell<MemoryManagerInner<M>>>,
+}
+
+impl<M: Memory> Memory for VirtualMemory<M> {
+    fn size(&self) -> u64 {
+        self.memory_manager.borrow().memory_size(self.id)
+    }
+
+    fn grow(&self, pages: u64) -> i64 {
+        self.memory_manager.borrow_mut().grow(self.id, pages)
+    }
+
+    fn read(&self, offset: u64, dst: &mut [u8]) {
+        self.memory_manager.borrow().read(self.id, offset, dst)
+    }
+
+    fn write(&self, offset: u64, src: &[u8]) {
+        self.memory_manager.borrow().write(self.id, offset, src)
+    }
+}
+
+struct MemoryManagerInner<M: Memory> {
+    memory: M,
+
+    // The number of buckets that have been allocated.
+    allocated_buckets: u16,
+
+    bucket_size_in_pages: u16,
+
+    // An array storing the size (in pages) of each of the managed memories.
+    memory_sizes_in_pages: [u64; MAX_NUM_MEMORIES as usize],
+
+    // A map mapping each managed memory to the buckets it owns, in allocation order.
+    memory_buckets: BTreeMap<MemoryId, Vec<BucketId>>,
+}
+
+impl<M: Memory> MemoryManagerInner<M> {
+    fn init(memory: M, bucket_size_in_pages: u16) -> Self {
+        if memory.size() == 0 {
+            // Memory is empty. Create a new memory manager.
+            return Self::new(memory, bucket_size_in_pages);
+        }
+
+        // Check if the magic in the memory corresponds to this object.
+        let mut dst = vec![0; 3];
+        memory.read(0, &mut dst);
+        if dst != MAGIC {
+            // No memory manager found. Create a new instance.
+            Self::new(memory, bucket_size_in_pages)
+        } else {
+            // The memory already contains a memory manager. Load it.
+            Self::load(memory)
+        }
+    }
+
+    fn new(memory: M, bucket_size_in_pages: u16) -> Self {
+        assert!(bucket_size_in_pages > 0, "Bucket size must be positive.");
+
+        let mem_mgr = Self {
+            memory,
+            allocated_buckets: 0,
+            memory_sizes_in_pages: [0; MAX_NUM_MEMORIES as usize],
+            memory_buckets: BTreeMap::new(),
+            bucket_size_in_pages,
+        };
+
+        mem_mgr.save_header();
+
+        // Mark all the buckets as unallocated.
+        write(
+            &mem_mgr.memory,
+            bucket_allocations_address(BucketId(0)).get(),
+            &[UNALLOCATED_BUCKET_MARKER; MAX_NUM_BUCKETS as usize],
+        );
+
+        mem_mgr
+    }
+
+    fn load(memory: M) -> Self {
+        // Read the header from memory.
+        let header: Header = read_struct(Address::from(0), &memory);
+        assert_eq!(&header.magic, MAGIC, "Bad magic.");
+        assert_eq!(header.version, LAYOUT_VERSION, "Unsupported version.");
+
+        let mut buckets = vec![0; MAX_NUM_BUCKETS as usize];
+        memory.read(bucket_allocations_address(BucketId(0)).get(), &mut buckets);
+
+        let mut memory_buckets = BTreeMap::new();
+        for (bucket_idx, memory) in buckets.into_iter().enumerate() {
+            if memory != UNALLOCATED_BUCKET_MARKER {
+                memory_buckets
+                    .entry(MemoryId(memory))
+                    .or_insert_with(Vec::new)
+                    .push(BucketId(bucket_idx as u16));
+            }
+        }
+
+        Self {
+            memory,
+            allocated_buckets: header.num_allocated_buckets,
+            bucket_size_in_pages: header.bucket_size_in_pages,
+            memory_sizes_in_pages: header.memory_sizes_in_pages,
+            memory_buckets,
+        }
+    }
+
+    fn save_header(&self) {
+        let header = Header {
+            magic: *MAGIC,
+            version: LAYOUT_VERSION,
+            num_allocated_buckets: self.allocated_buckets,
+            bucket_size_in_pages: self.bucket_size_in_pages,
+            _reserved: [0; HEADER_RESERVED_BYTES],
+            memory_sizes_in_pages: self.memory_sizes_in_pages,
+        };
+
+        write_struct(&header, Address::from(0), &self.memory);
+    }
+
+    // Returns the size of a memory (in pages).
+    fn memory_size(&self, id: MemoryId) -> u64 {
+        self.memory_sizes_in_pages[id.0 as usize]
+    }
+
+    // Grows the memory with the given id by the given number of pages.
+    // Returns the previous size of the memory on success and -1 otherwise.
+    fn grow(&mut self, id: MemoryId, pages: u64) -> i64 {
+        // Compute how many additional buckets are needed.
+        let old_size = self.memory_size(id);
+        let new_size = match old_size.checked_add(pages) {
+            Some(new_size) => new_size,
+            None => return -1,
+        };
+        let current_buckets = self.num_buckets_needed(old_size);
+        let required_buckets = self.num_buckets_needed(new_size);
+        let new_buckets_needed = required_buckets - current_buckets;
+
+        if new_buckets_needed + self.allocated_buckets as u64 > MAX_NUM_BUCKETS {
+            // Exceeded the memory that can be managed.
+            return -1;
+        }
+
+        // Grow the underlying memory if necessary, before touching any of the bookkeeping,
+        // so that a failed grow leaves the memory manager unchanged.
+        let required_pages = BUCKETS_OFFSET_IN_PAGES
+            + (self.allocated_buckets as u64 + new_buckets_needed)
+                * self.bucket_size_in_pages as u64;
+        let underlying_size = self.memory.size();
+        if underlying_size < required_pages
+            && self.memory.grow(required_pages - underlying_size) == -1
+        {
+            return -1;
+        }
+
+        // Allocate new buckets as needed.
+        for _ in 0..new_buckets_needed {
+            let new_bucket_id = BucketId(self.allocated_buckets);
+
+            self.memory_buckets
+                .entry(id)
+                .or_insert_with(Vec::new)
+                .push(new_bucket_id);
+
+            // Write in stable store that this bucket belongs to the memory with the provided `id`.
+            write(
+                &self.memory,
+                bucket_allocations_address(new_bucket_id).get(),
+                &[id.0],
+            );
+
+            self.allocated_buckets += 1;
+        }
+
+        // Update the memory with the new size.
+        self.memory_sizes_in_pages[id.0 as usize] = new_size;
+
+        // Update the header and return the old size.
+        self.save_header();
+        old_size as i64
+    }
+
+    fn write(&self, id: MemoryId, offset: u64, src: &[u8]) {
+        self.for_each_segment(id, offset, src.len() as u64, |src_offset, address, len| {
+            self.memory.write(
+                address.get(),
+                &src[src_offset as usize..(src_offset + len) as usize],
+            );
+        });
+    }
+
+    fn read(&self, id: MemoryId, offset: u64, dst: &mut [u8]) {
+        self.for_each_segment(id, offset, dst.len() as u64, |dst_offset, address, len| {
+            self.memory.read(
+                address.get(),
+                &mut dst[dst_offset as usize..(dst_offset + len) as usize],
+            );
+        });
+    }
+
+    // Splits the range [offset, offset + length) of the given virtual memory into contiguous
+    // segments of the underlying memory and calls `func` on each of them with the offset of the
+    // segment relative to `offset`, the address of the segment in the underlying memory and the
+    // length of the segment.
+    //
+    // Panics if the range is out of the bounds of the virtual memory.
+    fn for_each_segment(
+        &self,
+        id: MemoryId,
+        offset: u64,
+        length: u64,
+        mut func: impl FnMut(u64, Address, u64),
+    ) {
+        let end = offset.checked_add(length).expect("out of bounds");
+        if end > self.memory_size(id) * WASM_PAGE_SIZE {
+            panic!("{:?}: out of bounds", id);
+        }
+
+        let buckets = match self.memory_buckets.get(&id) {
+            Some(buckets) => buckets,
+            // The memory is empty, so only empty ranges are in bounds.
+            None => return,
+        };
+
+        let bucket_size_in_bytes = self.bucket_size_in_bytes().get();
+        let mut processed = 0;
+        while processed < length {
+            let virtual_offset = offset + processed;
+            let bucket = buckets[(virtual_offset / bucket_size_in_bytes) as usize];
+            let offset_in_bucket = virtual_offset % bucket_size_in_bytes;
+            let segment_len = (bucket_size_in_bytes - offset_in_bucket).min(length - processed);
+
+            func(
+                processed,
+                self.bucket_address(bucket) + Bytes::from(offset_in_bucket),
+                segment_len,
+            );
+
+            processed += segment_len;
+        }
+    }
+
+    fn bucket_size_in_bytes(&self) -> Bytes {
+        Bytes::from(self.bucket_size_in_pages as u64 * WASM_PAGE_SIZE)
+    }
+
+    // Returns the number of buckets needed to accommodate the given number of pages.
+    fn num_buckets_needed(&self, num_pages: u64) -> u64 {
+        // Ceiling division.
+        let bucket_size = self.bucket_size_in_pages as u64;
+        num_pages / bucket_size + if num_pages % bucket_size == 0 { 0 } else { 1 }
+    }
+
+    // Returns the address of the given bucket in the underlying memory.
+    fn bucket_address(&self, id: BucketId) -> Address {
+        Address::from(BUCKETS_OFFSET_IN_BYTES) + self.bucket_size_in_bytes() * id.0 as u64
+    }
+}
+
+// Returns the address of the entry of the given bucket in the bucket allocation table.
+fn bucket_allocations_address(id: BucketId) -> Address {
+    Address::from(0) + Header::size() + Bytes::from(id.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::btreemap::StableBTreeMap;
+    use crate::vec_mem::VectorMemory;
+    use crate::RestrictedMemory;
+
+    const MAX_MEMORY_IN_PAGES: u64 = MAX_NUM_BUCKETS * BUCKET_SIZE_IN_PAGES;
+
+    fn make_memory() -> VectorMemory {
+        VectorMemory::default()
+    }
+
+    #[test]
+    fn header_fits_in_the_first_page() {
+        assert!(
+            (bucket_allocations_address(BucketId(0)) + Bytes::from(MAX_NUM_BUCKETS)).get()
+                <= BUCKETS_OFFSET_IN_BYTES
+        );
+    }
+
+    #[test]
+    fn can_get_memory() {
+        let mem_mgr = MemoryManager::init(make_memory());
+        let memory = mem_mgr.get(MemoryId(0));
+        assert_eq!(memory.size(), 0);
+    }
+
+    #[test]
+    fn can_allocate_and_use_memory() {
+        let mem = make_memory();
+        let mem_mgr = MemoryManager::init(mem.clone());
+        let memory = mem_mgr.get(MemoryId(0));
+        assert_eq!(memory.grow(1), 0);
+        assert_eq!(memory.size(), 1);
+
+        memory.write(0, &[1, 2, 3]);
+
+        let mut bytes = vec![0; 3];
+        memory.read(0, &mut bytes);
+        assert_eq!(bytes, vec![1, 2, 3]);
+
+        // The first bucket is allocated to memory 0.
+        let mut marker = [0; 1];
+        mem.read(bucket_allocations_address(BucketId(0)).get(), &mut marker);
+        assert_eq!(marker, [0]);
+
+        // The data is stored in the first bucket.
+        let mut bytes = vec![0; 3];
+        mem.read(BUCKETS_OFFSET_IN_BYTES, &mut bytes);
+        assert_eq!(bytes, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn can_allocate_and_use_multiple_memories() {
+        let mem = make_memory();
+        let mem_mgr = MemoryManager::init(mem.clone());
+        let memory_0 = mem_mgr.get(MemoryId(0));
+        let memory_1 = mem_mgr.get(MemoryId(1));
+
+        assert_eq!(memory_0.grow(1), 0);
+        assert_eq!(memory_1.grow(1), 0);
+
+        assert_eq!(memory_0.size(), 1);
+        assert_eq!(memory_1.size(), 1);
+
+        memory_0.write(0, &[1, 2, 3]);
+        memory_0.write(0, &[1, 2, 3]);
+        memory_1.write(0, &[4, 5, 6]);
+
+        let mut bytes = vec![0; 3];
+        memory_0.read(0, &mut bytes);
+        assert_eq!(bytes, vec![1, 2, 3]);
+
+        let mut bytes = vec![0; 3];
+        memory_1.read(0, &mut bytes);
+        assert_eq!(bytes, vec![4, 5, 6]);
+
+        // The underlying memory has the header page and two buckets.
+        assert_eq!(
+            mem.size(),
+            BUCKETS_OFFSET_IN_PAGES + 2 * BUCKET_SIZE_IN_PAGES
+        );
+    }
+
+    #[test]
+    fn can_be_reinitialized_from_memory() {
+        let mem = make_memory();
+        let mem_mgr = MemoryManager::init(mem.clone());
+        let memory_0 = mem_mgr.get(MemoryId(0));
+        let memory_1 = mem_mgr.get(MemoryId(1));
+
+        assert_eq!(memory_0.grow(1), 0);
+        assert_eq!(memory_1.grow(1), 0);
+
+        memory_0.write(0, &[1, 2, 3]);
+        memory_1.write(0, &[4, 5, 6]);
+
+        let mem_mgr = MemoryManager::init(mem);
+        let memory_0 = mem_mgr.get(MemoryId(0));
+        let memory_1 = mem_mgr.get(MemoryId(1));
+
+        assert_eq!(memory_0.size(), 1);
+        assert_eq!(memory_1.size(), 1);
+
+        let mut bytes = vec![0; 3];
+        memory_0.read(0, &mut bytes);
+        assert_eq!(bytes, vec![1, 2, 3]);
+
+        memory_1.read(0, &mut bytes);
+        assert_eq!(bytes, vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn init_overwrites_memory_without_a_memory_manager() {
+        let mem = make_memory();
+        assert_eq!(mem.grow(1), 0);
+        mem.write(0, b"WAS");
+
+        let mem_mgr = MemoryManager::init(mem);
+        let memory = mem_mgr.get(MemoryId(0));
+        assert_eq!(memory.size(), 0);
+    }
+
+    #[test]
+    fn reads_and_writes_span_multiple_buckets() {
+        let mem_mgr = MemoryManager::init_with_bucket_size(make_memory(), 1);
+        let memory_0 = mem_mgr.get(MemoryId(0));
+        let memory_1 = mem_mgr.get(MemoryId(1));
+
+        // Interleave the buckets of the two memories.
+        for _ in 0..3 {
+            assert_ne!(memory_0.grow(1), -1);
+            assert_ne!(memory_1.grow(1), -1);
+        }
+
+        let bytes: Vec<u8> = (0..3 * WASM_PAGE_SIZE).map(|i| (i % 251) as u8).collect();
+        memory_0.write(0, &bytes);
+        memory_1.write(0, &vec![0xff; bytes.len()]);
+
+        let mut read_bytes = vec![0; bytes.len()];
+        memory_0.read(0, &mut read_bytes);
+        assert_eq!(read_bytes, bytes);
+
+        // Read a range crossing a bucket boundary.
+        let mut read_bytes = vec![0; 10];
+        memory_0.read(WASM_PAGE_SIZE - 5, &mut read_bytes);
+        assert_eq!(
+            read_bytes,
+            bytes[(WASM_PAGE_SIZE - 5) as usize..(WASM_PAGE_SIZE + 5) as usize]
+        );
+    }
+
+    #[test]
+    fn growing_same_memory_multiple_times_doesnt_increase_underlying_allocation() {
+        let mem = make_memory();
+        let mem_mgr = MemoryManager::init(mem.clone());
+        let memory_0 = mem_mgr.get(MemoryId(0));
+
+        // Grow the memory by 1 page. This should increase the underlying allocation
+        // by `BUCKET_SIZE_IN_PAGES` pages.
+        assert_eq!(memory_0.grow(1), 0);
+        assert_eq!(mem.size(), 1 + BUCKET_SIZE_IN_PAGES);
+
+        // Grow the memory again. This should NOT increase the underlying allocation.
+        assert_eq!(memory_0.grow(1), 1);
+        assert_eq!(memory_0.size(), 2);
+        assert_eq!(mem.size(), 1 + BUCKET_SIZE_IN_PAGES);
+
+        // Grow the memory up to the BUCKET_SIZE_IN_PAGES.
+        // This should NOT increase the underlying allocation.
+        assert_eq!(memory_0.grow(BUCKET_SIZE_IN_PAGES - 2), 2);
+        assert_eq!(memory_0.size(), BUCKET_SIZE_IN_PAGES);
+        assert_eq!(mem.size(), 1 + BUCKET_SIZE_IN_PAGES);
+
+        // Grow the memory by one more page.
+        // This should increase the underlying allocation.
+        assert_eq!(memory_0.grow(1), BUCKET_SIZE_IN_PAGES as i64);
+        assert_eq!(memory_0.size(), BUCKET_SIZE_IN_PAGES + 1);
+        assert_eq!(mem.size(), 1 + 2 * BUCKET_SIZE_IN_PAGES);
+    }
+
+    #[test]
+    fn does_not_grow_memory_unnecessarily() {
+        let mem = make_memory();
+        let initial_size = BUCKET_SIZE_IN_PAGES * 2;
+
+        // Grow the memory manually before passing it into the memory manager.
+        mem.grow(initial_size);
+
+        let mem_mgr = MemoryManager::init(mem.clone());
+        let memory_0 = mem_mgr.get(MemoryId(0));
+
+        // Grow the memory by 1 page.
+        assert_eq!(memory_0.grow(1), 0);
+        assert_eq!(mem.size(), initial_size);
+
+        // Grow the memory by BUCKET_SIZE_IN_PAGES more pages, which will cause the underlying
+        // allocation to increase.
+        assert_eq!(memory_0.grow(BUCKET_SIZE_IN_PAGES), 1);
+        assert_eq!(mem.size(), 1 + BUCKET_SIZE_IN_PAGES * 2);
+    }
+
+    #[test]
+    fn growing_beyond_capacity_fails() {
+        let mem = make_memory();
+        let mem_mgr = MemoryManager::init(mem);
+        let memory_0 = mem_mgr.get(MemoryId(0));
+
+        assert_eq!(memory_0.grow(MAX_MEMORY_IN_PAGES + 1), -1);
+        assert_eq!(memory_0.grow(u64::MAX), -1);
+
+        // Try to grow the memory by MAX_MEMORY_IN_PAGES + 1 in two steps.
+        assert_eq!(memory_0.grow(1), 0);
+        assert_eq!(memory_0.grow(MAX_MEMORY_IN_PAGES), -1);
+        assert_eq!(memory_0.size(), 1);
+    }
+
+    #[test]
+    fn failed_underlying_grow_leaves_memory_unchanged() {
+        // The underlying memory only fits the header page and a single bucket.
+        let mem = RestrictedMemory::new(make_memory(), 0..1 + BUCKET_SIZE_IN_PAGES);
+        let mem_mgr = MemoryManager::init(mem);
+        let memory_0 = mem_mgr.get(MemoryId(0));
+        let memory_1 = mem_mgr.get(MemoryId(1));
+
+        assert_eq!(memory_0.grow(1), 0);
+        assert_eq!(memory_1.grow(1), -1);
+        assert_eq!(memory_1.size(), 0);
+
+        // Memory 0 can still use its bucket.
+        assert_eq!(memory_0.grow(BUCKET_SIZE_IN_PAGES - 1), 1);
+        assert_eq!(memory_0.grow(1), -1);
+        assert_eq!(memory_0.size(), BUCKET_SIZE_IN_PAGES);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn read_out_of_bounds_panics() {
+        let mem_mgr = MemoryManager::init(make_memory());
+        let memory = mem_mgr.get(MemoryId(0));
+        assert_eq!(memory.grow(1), 0);
+
+        let mut bytes = vec![0; 2];
+        memory.read(WASM_PAGE_SIZE - 1, &mut bytes);
+    }
+
+    #[test]
+    fn stable_structures_share_one_memory() {
+        let mem = make_memory();
+        let mem_mgr = MemoryManager::init(mem.clone());
+        let mut btree_0 = StableBTreeMap::init(mem_mgr.get(MemoryId(0)), 1, 1);
+        let mut btree_1 = StableBTreeMap::init(mem_mgr.get(MemoryId(1)), 1, 1);
+
+        for i in 0..100u8 {
+            assert_eq!(btree_0.insert(vec![i], vec![i]), Ok(None));
+            assert_eq!(btree_1.insert(vec![i], vec![i + 1]), Ok(None));
+        }
+
+        // Reload the memory manager and the maps.
+        let mem_mgr = MemoryManager::init(mem);
+        let btree_0: StableBTreeMap<_, Vec<u8>, Vec<u8>> =
+            StableBTreeMap::init(mem_mgr.get(MemoryId(0)), 1, 1);
+        let btree_1: StableBTreeMap<_, Vec<u8>, Vec<u8>> =
+            StableBTreeMap::init(mem_mgr.get(MemoryId(1)), 1, 1);
+
+        for i in 0..100u8 {
+            assert_eq!(btree_0.get(&vec![i]), Some(vec![i]));
+            assert_eq!(btree_1.get(&vec![i]), Some(vec![i + 1]));
+        }
+    }
+}
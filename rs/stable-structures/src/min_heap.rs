@@ -0,0 +1,151 @@
+//! This module implements a priority queue in stable memory.
+//! The queue is a binary min-heap stored in an array of equally-sized slots, so the maximum size
+//! of an element must be known in advance.
+//!
+//! The memory layout is described in `base_vec.rs`; the magic of a stable min-heap is "SMH".
+use crate::base_vec::BaseVec;
+pub use crate::base_vec::{InitError, Iter, WriteError};
+use crate::storable::Storable;
+use crate::Memory;
+
+#[cfg(test)]
+mod tests;
+
+/// The magic number: Stable Min-Heap.
+const MAGIC: [u8; 3] = *b"SMH";
+
+/// A priority queue of values stored in memory, where the smallest value is at the top.
+///
+/// Push and pop take logarithmic time, peek takes constant time.
+///
+/// Each element occupies a slot of `max_element_size` bytes (plus a 4-byte length prefix)
+/// regardless of its actual encoded size, so `max_element_size` must be known in advance.
+pub struct StableMinHeap<T: Storable + Ord, M: Memory>(BaseVec<T, M>);
+
+impl<T: Storable + Ord, M: Memory> StableMinHeap<T, M> {
+    /// Creates a new empty heap in the specified memory, overwriting the previous contents of
+    /// the memory.
+    pub fn new(memory: M, max_element_size: u32) -> Self {
+        Self(BaseVec::new(memory, max_element_size, MAGIC))
+    }
+
+    /// Initializes a heap based on the contents of the memory.
+    /// If the memory already contains a stable heap, this function recovers it from the stable
+    /// memory, ignoring the `max_element_size` argument. Otherwise, this function allocates a new
+    /// empty heap in the memory.
+    pub fn init(memory: M, max_element_size: u32) -> Result<Self, InitError> {
+        BaseVec::init(memory, max_element_size, MAGIC).map(Self)
+    }
+
+    /// Returns the underlying memory of the heap.
+    pub fn forget(self) -> M {
+        self.0.forget()
+    }
+
+    /// Returns true iff this heap does not have any elements.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of elements in the heap.
+    pub fn len(&self) -> u64 {
+        self.0.len()
+    }
+
+    /// Returns the max size of an element this heap can hold.
+    pub fn max_element_size(&self) -> u32 {
+        self.0.max_element_size()
+    }
+
+    /// Pushes an element onto the heap.
+    /// If the element does not fit, the heap does not change.
+    pub fn push(&self, item: &T) -> Result<(), WriteError> {
+        self.0.push(item)?;
+        self.bubble_up(self.0.len() - 1, item);
+        Ok(())
+    }
+
+    /// Removes the smallest element from the heap and returns it.
+    /// Returns None if the heap is empty.
+    pub fn pop(&self) -> Option<T> {
+        let n = self.0.len();
+        match n {
+            0 => None,
+            1 => self.0.pop(),
+            _ => {
+                let smallest = self.0.get(0).unwrap();
+                let last = self.0.pop().unwrap();
+                self.set(0, &last);
+                self.bubble_down(0, n - 1, &last);
+                Some(smallest)
+            }
+        }
+    }
+
+    /// Returns the smallest element in the heap without removing it.
+    /// Returns None if the heap is empty.
+    pub fn peek(&self) -> Option<T> {
+        self.0.get(0)
+    }
+
+    /// Returns an iterator over the elements of the heap in arbitrary order.
+    pub fn iter(&self) -> Iter<'_, T, M> {
+        self.0.iter()
+    }
+
+    /// Moves the element at index `i` up the tree until the heap property is restored.
+    fn bubble_up(&self, mut i: u64, x: &T) {
+        while i > 0 {
+            let p = (i - 1) / 2;
+            let parent = self.0.get(p).unwrap();
+            if *x >= parent {
+                break;
+            }
+            self.set(i, &parent);
+            self.set(p, x);
+            i = p;
+        }
+    }
+
+    /// Moves the element at index `i` down the tree until the heap property is restored.
+    ///
+    /// PRECONDITION: `len` is the number of elements in the heap and `x` is the element at `i`.
+    fn bubble_down(&self, mut i: u64, len: u64, x: &T) {
+        loop {
+            let l = 2 * i + 1;
+            if l >= len {
+                break;
+            }
+
+            // Find the smallest child.
+            let r = l + 1;
+            let left = self.0.get(l).unwrap();
+            let (c, child) = if r < len {
+                let right = self.0.get(r).unwrap();
+                if right < left {
+                    (r, right)
+                } else {
+                    (l, left)
+                }
+            } else {
+                (l, left)
+            };
+
+            if *x <= child {
+                break;
+            }
+            self.set(i, &child);
+            self.set(c, x);
+            i = c;
+        }
+    }
+
+    /// Overwrites the element at the specified index.
+    ///
+    /// NOTE: this never fails because all the elements in the heap are known to fit into a slot.
+    fn set(&self, index: u64, item: &T) {
+        self.0
+            .set(index, item)
+            .expect("bug: an element already in the heap must fit into a slot");
+    }
+}
@@ -0,0 +1,167 @@
+use crate::min_heap::{InitError, StableMinHeap, WriteError};
+use crate::vec_mem::VectorMemory;
+use crate::{Memory, RestrictedMemory, WASM_PAGE_SIZE};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// A simple deterministic pseudo-random number generator (xorshift64).
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[test]
+fn test_heap_construct() {
+    let h = StableMinHeap::<u64, _>::new(VectorMemory::default(), 8);
+
+    assert!(h.is_empty());
+    assert_eq!(h.len(), 0);
+    assert_eq!(h.max_element_size(), 8);
+    assert_eq!(h.peek(), None);
+
+    let h = StableMinHeap::<u64, _>::init(h.forget(), 100).expect("failed to init heap");
+    assert!(h.is_empty());
+    assert_eq!(h.max_element_size(), 8);
+}
+
+#[test]
+fn test_heap_init_with_different_magic() {
+    let mem = VectorMemory::default();
+    assert_eq!(mem.grow(1), 0);
+    mem.write(0, b"SVC\x01");
+    let h = StableMinHeap::<u64, _>::init(mem, 8).expect("failed to init heap");
+    assert_eq!(h.len(), 0);
+}
+
+#[test]
+fn test_heap_load_bad_version() {
+    let mem = VectorMemory::default();
+    assert_eq!(mem.grow(1), 0);
+    mem.write(0, b"SMH\x02");
+
+    assert_eq!(
+        StableMinHeap::<u64, _>::init(mem, 8)
+            .map(|_| ())
+            .unwrap_err(),
+        InitError::IncompatibleVersion {
+            last_supported_version: 1,
+            decoded_version: 2
+        },
+    );
+}
+
+#[test]
+fn test_heap_push_pop() {
+    let h = StableMinHeap::new(VectorMemory::default(), 8);
+
+    for i in [5u64, 3, 8, 1, 9, 2, 7, 1] {
+        h.push(&i).unwrap();
+    }
+    assert_eq!(h.len(), 8);
+    assert_eq!(h.peek(), Some(1));
+
+    let mut popped = vec![];
+    while let Some(x) = h.pop() {
+        popped.push(x);
+    }
+    assert_eq!(popped, vec![1, 1, 2, 3, 5, 7, 8, 9]);
+    assert!(h.is_empty());
+    assert_eq!(h.peek(), None);
+}
+
+#[test]
+fn test_heap_persistence() {
+    let h = StableMinHeap::new(VectorMemory::default(), 8);
+    for i in (0..10u64).rev() {
+        h.push(&i).unwrap();
+    }
+
+    let h = StableMinHeap::<u64, _>::init(h.forget(), 16).unwrap();
+    assert_eq!(h.len(), 10);
+    assert_eq!(h.max_element_size(), 8);
+    for i in 0..10u64 {
+        assert_eq!(h.pop(), Some(i));
+    }
+    assert_eq!(h.pop(), None);
+}
+
+#[test]
+fn test_heap_iter() {
+    let h = StableMinHeap::new(VectorMemory::default(), 8);
+    assert_eq!(h.iter().next(), None);
+
+    for i in (0..100u64).rev() {
+        h.push(&i).unwrap();
+    }
+
+    let mut elements: Vec<u64> = h.iter().collect();
+    elements.sort_unstable();
+    assert_eq!(elements, (0..100u64).collect::<Vec<_>>());
+    assert_eq!(h.iter().len(), 100);
+}
+
+#[test]
+fn test_heap_value_too_large() {
+    let h = StableMinHeap::new(VectorMemory::default(), 5);
+    h.push(&b"abc".to_vec()).unwrap();
+
+    assert_eq!(
+        h.push(&b"abcdef".to_vec()),
+        Err(WriteError::ValueTooLarge { given: 6, max: 5 })
+    );
+    assert_eq!(h.len(), 1);
+    assert_eq!(h.peek(), Some(b"abc".to_vec()));
+}
+
+#[test]
+fn test_heap_push_out_of_memory() {
+    let h = StableMinHeap::new(
+        RestrictedMemory::new(VectorMemory::default(), 0..1),
+        WASM_PAGE_SIZE as u32 / 2,
+    );
+
+    assert_eq!(Ok(()), h.push(&b"b".to_vec()));
+    assert_eq!(
+        Err(WriteError::GrowFailed {
+            current_size: 1,
+            delta: 1
+        }),
+        h.push(&vec![b'a'; WASM_PAGE_SIZE as usize / 2])
+    );
+    assert_eq!(1, h.len());
+    assert_eq!(h.peek(), Some(b"b".to_vec()));
+}
+
+#[test]
+fn test_heap_matches_binary_heap() {
+    for seed in 1..=20u64 {
+        let mut rng = Rng(seed);
+        let h = StableMinHeap::new(VectorMemory::default(), 8);
+        let mut expected = BinaryHeap::new();
+
+        for _ in 0..1000 {
+            // Push twice as often as pop so that the heap grows over time.
+            if rng.next() % 3 == 0 {
+                assert_eq!(h.pop(), expected.pop().map(|Reverse(x)| x));
+            } else {
+                // Use a small range of values to exercise duplicates.
+                let x = rng.next() % 100;
+                h.push(&x).unwrap();
+                expected.push(Reverse(x));
+            }
+            assert_eq!(h.len(), expected.len() as u64);
+            assert_eq!(h.peek(), expected.peek().map(|Reverse(x)| *x));
+        }
+
+        while let Some(Reverse(x)) = expected.pop() {
+            assert_eq!(h.pop(), Some(x));
+        }
+        assert_eq!(h.pop(), None);
+    }
+}
@@ -98,4 +98,8 @@ impl Bytes {
     pub const fn new(val: u64) -> Self {
         Self(val)
     }
+
+    pub const fn get(&self) -> u64 {
+        self.0
+    }
 }
@@ -0,0 +1,88 @@
+//! This module implements a growable array in stable memory.
+//! All elements are stored in slots of equal size, which provides constant-time access to any
+//! element. The trade-off is that the maximum size of an element must be known in advance.
+//!
+//! The memory layout is described in `base_vec.rs`; the magic of a stable vector is "SVC".
+use crate::base_vec::BaseVec;
+pub use crate::base_vec::{InitError, Iter, WriteError};
+use crate::storable::Storable;
+use crate::Memory;
+
+#[cfg(test)]
+mod tests;
+
+/// The magic number: Stable VeC.
+const MAGIC: [u8; 3] = *b"SVC";
+
+/// A growable array of values stored in memory with constant-time access to all elements.
+///
+/// Each element occupies a slot of `max_element_size` bytes (plus a 4-byte length prefix)
+/// regardless of its actual encoded size, so `max_element_size` must be known in advance.
+pub struct StableVec<T: Storable, M: Memory>(BaseVec<T, M>);
+
+impl<T: Storable, M: Memory> StableVec<T, M> {
+    /// Creates a new empty vector in the specified memory, overwriting the previous contents of
+    /// the memory.
+    pub fn new(memory: M, max_element_size: u32) -> Self {
+        Self(BaseVec::new(memory, max_element_size, MAGIC))
+    }
+
+    /// Initializes a vector based on the contents of the memory.
+    /// If the memory already contains a stable vector, this function recovers it from the stable
+    /// memory, ignoring the `max_element_size` argument. Otherwise, this function allocates a new
+    /// empty vector in the memory.
+    pub fn init(memory: M, max_element_size: u32) -> Result<Self, InitError> {
+        BaseVec::init(memory, max_element_size, MAGIC).map(Self)
+    }
+
+    /// Returns the underlying memory of the vector.
+    pub fn forget(self) -> M {
+        self.0.forget()
+    }
+
+    /// Returns true iff this vector does not have any elements.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of elements in the vector.
+    pub fn len(&self) -> u64 {
+        self.0.len()
+    }
+
+    /// Returns the max size of an element this vector can hold.
+    pub fn max_element_size(&self) -> u32 {
+        self.0.max_element_size()
+    }
+
+    /// Returns the element at the specified index.
+    /// Returns None if the index is out of bounds.
+    pub fn get(&self, index: u64) -> Option<T> {
+        self.0.get(index)
+    }
+
+    /// Sets the element at the specified index.
+    /// If the new value is too large to fit into a slot, the vector does not change.
+    ///
+    /// PRECONDITION: index < self.len()
+    pub fn set(&self, index: u64, item: &T) -> Result<(), WriteError> {
+        self.0.set(index, item)
+    }
+
+    /// Appends a new element to the end of the vector.
+    /// If the element does not fit, the vector does not change.
+    pub fn push(&self, item: &T) -> Result<(), WriteError> {
+        self.0.push(item)
+    }
+
+    /// Removes the last element from the vector and returns it.
+    /// Returns None if the vector is empty.
+    pub fn pop(&self) -> Option<T> {
+        self.0.pop()
+    }
+
+    /// Returns an iterator over the elements of the vector.
+    pub fn iter(&self) -> Iter<'_, T, M> {
+        self.0.iter()
+    }
+}
@@ -0,0 +1,191 @@
+use crate::vec::{InitError, StableVec, WriteError};
+use crate::vec_mem::VectorMemory;
+use crate::{Memory, RestrictedMemory, WASM_PAGE_SIZE};
+
+#[test]
+fn test_vec_construct() {
+    let vec = StableVec::<u64, _>::new(VectorMemory::default(), 8);
+
+    assert!(vec.is_empty());
+    assert_eq!(vec.len(), 0);
+    assert_eq!(vec.max_element_size(), 8);
+
+    let vec = StableVec::<u64, _>::init(vec.forget(), 100).expect("failed to init vec");
+    assert!(vec.is_empty());
+    assert_eq!(vec.max_element_size(), 8);
+}
+
+#[test]
+fn test_new_overwrites() {
+    let vec = StableVec::new(VectorMemory::default(), 8);
+    vec.push(&1u64).expect("failed to push element");
+    assert_eq!(vec.len(), 1);
+
+    let vec = StableVec::<u64, _>::new(vec.forget(), 16);
+    assert_eq!(vec.len(), 0);
+    assert_eq!(vec.max_element_size(), 16);
+}
+
+#[test]
+fn test_vec_init_with_different_magic() {
+    let mem = VectorMemory::default();
+    assert_eq!(mem.grow(1), 0);
+    mem.write(0, b"WAS");
+    let vec = StableVec::<u64, _>::init(mem, 8).expect("failed to init vec");
+    assert_eq!(vec.len(), 0);
+    assert_eq!(vec.max_element_size(), 8);
+}
+
+#[test]
+fn test_vec_load_bad_version() {
+    let mem = VectorMemory::default();
+    assert_eq!(mem.grow(1), 0);
+    mem.write(0, b"SVC\x02");
+
+    assert_eq!(
+        StableVec::<u64, _>::init(mem, 8).map(|_| ()).unwrap_err(),
+        InitError::IncompatibleVersion {
+            last_supported_version: 1,
+            decoded_version: 2
+        },
+    );
+}
+
+#[test]
+fn test_vec_push_pop() {
+    let vec = StableVec::new(VectorMemory::default(), 8);
+
+    for i in 0..100u64 {
+        vec.push(&i).unwrap();
+        assert_eq!(vec.len(), i + 1);
+    }
+
+    for i in (0..100u64).rev() {
+        assert_eq!(vec.pop(), Some(i));
+        assert_eq!(vec.len(), i);
+    }
+
+    assert_eq!(vec.pop(), None);
+    assert!(vec.is_empty());
+}
+
+#[test]
+fn test_vec_get_set() {
+    let vec = StableVec::new(VectorMemory::default(), 8);
+    for i in 0..10u64 {
+        vec.push(&i).unwrap();
+    }
+
+    for i in 0..10u64 {
+        vec.set(i, &(i * 10)).unwrap();
+    }
+
+    for i in 0..10u64 {
+        assert_eq!(vec.get(i), Some(i * 10));
+    }
+    assert_eq!(vec.get(10), None);
+    assert_eq!(vec.get(u64::MAX), None);
+}
+
+#[test]
+#[should_panic(expected = "index out of bounds")]
+fn test_vec_set_out_of_bounds() {
+    let vec = StableVec::new(VectorMemory::default(), 8);
+    vec.push(&1u64).unwrap();
+    let _ = vec.set(1, &2u64);
+}
+
+#[test]
+fn test_vec_variable_size_elements() {
+    let vec = StableVec::new(VectorMemory::default(), 5);
+    vec.push(&b"abcde".to_vec()).unwrap();
+    vec.push(&vec![]).unwrap();
+    vec.push(&b"xy".to_vec()).unwrap();
+
+    assert_eq!(vec.get(0), Some(b"abcde".to_vec()));
+    assert_eq!(vec.get(1), Some(vec![]));
+    assert_eq!(vec.get(2), Some(b"xy".to_vec()));
+
+    vec.set(0, &b"z".to_vec()).unwrap();
+    assert_eq!(vec.get(0), Some(b"z".to_vec()));
+}
+
+#[test]
+fn test_vec_value_too_large() {
+    let vec = StableVec::new(VectorMemory::default(), 5);
+    vec.push(&b"abc".to_vec()).unwrap();
+
+    assert_eq!(
+        vec.push(&b"abcdef".to_vec()),
+        Err(WriteError::ValueTooLarge { given: 6, max: 5 })
+    );
+    assert_eq!(
+        vec.set(0, &b"abcdef".to_vec()),
+        Err(WriteError::ValueTooLarge { given: 6, max: 5 })
+    );
+
+    assert_eq!(vec.len(), 1);
+    assert_eq!(vec.get(0), Some(b"abc".to_vec()));
+}
+
+#[test]
+fn test_vec_persistence() {
+    let vec = StableVec::new(VectorMemory::default(), 8);
+    for i in 0..10u64 {
+        vec.push(&i).unwrap();
+    }
+
+    let vec = StableVec::<u64, _>::init(vec.forget(), 16).unwrap();
+    assert_eq!(vec.len(), 10);
+    assert_eq!(vec.max_element_size(), 8);
+    for i in 0..10u64 {
+        assert_eq!(vec.get(i), Some(i));
+    }
+
+    // The reloaded vector is mutable.
+    vec.set(0, &42).unwrap();
+    vec.push(&10).unwrap();
+    assert_eq!(vec.get(0), Some(42));
+    assert_eq!(vec.get(10), Some(10));
+}
+
+#[test]
+fn test_vec_iter() {
+    let vec = StableVec::new(VectorMemory::default(), 8);
+    assert_eq!(vec.iter().next(), None);
+
+    for i in 0..100u64 {
+        vec.push(&i).unwrap();
+    }
+
+    assert_eq!(
+        vec.iter().collect::<Vec<_>>(),
+        (0..100u64).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec.iter().rev().collect::<Vec<_>>(),
+        (0..100u64).rev().collect::<Vec<_>>()
+    );
+    assert_eq!(vec.iter().len(), 100);
+}
+
+#[test]
+fn test_vec_push_out_of_memory() {
+    let vec = StableVec::new(
+        RestrictedMemory::new(VectorMemory::default(), 0..1),
+        WASM_PAGE_SIZE as u32 / 2,
+    );
+
+    assert_eq!(
+        Ok(()),
+        vec.push(&b"small entry that fits into one page".to_vec())
+    );
+    assert_eq!(
+        Err(WriteError::GrowFailed {
+            current_size: 1,
+            delta: 1
+        }),
+        vec.push(&vec![1; WASM_PAGE_SIZE as usize / 2])
+    );
+    assert_eq!(1, vec.len());
+}
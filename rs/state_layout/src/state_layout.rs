@@ -3,6 +3,7 @@ use crate::error::LayoutError;
 
 use bitcoin::{hashes::Hash, Network, OutPoint, Script, TxOut, Txid};
 use ic_base_types::{NumBytes, NumSeconds};
+use ic_ic00_types::{CanisterLogRecord, LogVisibility};
 use ic_logger::ReplicaLogger;
 use ic_protobuf::{
     bitcoin::v1 as pb_bitcoin,
@@ -13,13 +14,19 @@ use ic_protobuf::{
     },
 };
 use ic_replicated_state::{
-    bitcoin_state, canister_state::execution_state::WasmMetadata, CallContextManager,
-    CanisterStatus, ExecutionTask, ExportedFunctions, Global, NumWasmPages,
+    bitcoin_state,
+    canister_state::{
+        execution_state::WasmMetadata,
+        system_state::{CanisterHistory, WasmChunkStoreMetadata},
+    },
+    CallContextManager, CanisterStatus, CanisterTimer, ExecutionTask, ExportedFunctions, Global,
+    NumWasmPages, SnapshotId,
 };
 use ic_sys::mmap::ScopedMmap;
 use ic_types::{
-    nominal_cycles::NominalCycles, AccumulatedPriority, CanisterId, ComputeAllocation, Cycles,
-    ExecutionRound, Height, MemoryAllocation, NumInstructions, PrincipalId,
+    batch::QueryStats, canister_log::CanisterLog, nominal_cycles::NominalCycles,
+    AccumulatedPriority, CanisterId, ComputeAllocation, Cycles, ExecutionRound, Height,
+    MemoryAllocation, NumInstructions, PrincipalId, Time,
 };
 use ic_wasm_types::{CanisterModule, WasmHash};
 use std::convert::{From, TryFrom, TryInto};
@@ -189,6 +196,29 @@ pub struct CanisterStateBits {
     pub heap_delta_debit: NumBytes,
     pub install_code_debit: NumInstructions,
     pub task_queue: Vec<ExecutionTask>,
+    pub global_timer: CanisterTimer,
+    pub log_visibility: LogVisibility,
+    pub canister_log: CanisterLog,
+    pub wasm_chunk_store_metadata: WasmChunkStoreMetadata,
+    pub canister_version: u64,
+    pub canister_history: CanisterHistory,
+    pub wasm_memory_limit: Option<NumBytes>,
+    pub total_query_stats: QueryStats,
+}
+
+/// This struct contains bits of a `CanisterSnapshot` that are not already
+/// covered somewhere else and are too small to be serialized separately.
+#[derive(Debug)]
+pub struct CanisterSnapshotBits {
+    pub canister_id: CanisterId,
+    pub taken_at_timestamp: Time,
+    pub exported_globals: Vec<Global>,
+    pub heap_size: NumWasmPages,
+    pub stable_memory_size: NumWasmPages,
+    pub exports: ExportedFunctions,
+    pub metadata: WasmMetadata,
+    pub certified_data: Vec<u8>,
+    pub binary_hash: Option<WasmHash>,
 }
 
 /// This struct contains bits of the `BitcoinState` that are not already
@@ -200,6 +230,7 @@ pub struct BitcoinStateBits {
     pub stable_height: u32,
     pub network: Network,
     pub utxos_large: BTreeMap<OutPoint, (TxOut, u32)>,
+    pub address_transactions_start_height: Option<u32>,
 }
 
 impl Default for BitcoinStateBits {
@@ -210,6 +241,7 @@ impl Default for BitcoinStateBits {
             unstable_blocks: bitcoin_state::UnstableBlocks::default(),
             stable_height: 0,
             utxos_large: BTreeMap::default(),
+            address_transactions_start_height: None,
         }
     }
 }
@@ -236,12 +268,21 @@ impl Default for BitcoinStateBits {
 /// |   |       └── utxos_small.bin
 /// |   |       └── utxos_medium.bin
 /// |   |       └── address_outpoints.bin
-/// │   └── canister_states
-/// │       └── <hex(canister_id)>
-/// │           ├── queues.pbuf
+/// |   |       └── block_headers.bin
+/// |   |       └── address_transactions.bin
+/// │   ├── canister_states
+/// │   │   └── <hex(canister_id)>
+/// │   │       ├── queues.pbuf
+/// │   │       ├── vmemory_0.bin
+/// │   │       ├── canister.pbuf
+/// │   │       ├── stable_memory.(pbuf|bin)
+/// │   │       ├── wasm_chunk_store.bin
+/// │   │       └── software.wasm
+/// │   └── snapshots
+/// │       └── <hex(snapshot_id)>
+/// │           ├── snapshot.pbuf
 /// │           ├── vmemory_0.bin
-/// │           ├── canister.pbuf
-/// │           ├── stable_memory.(pbuf|bin)
+/// │           ├── stable_memory.bin
 /// │           └── software.wasm
 /// │
 /// ├── [checkpoints] {owned and varies by checkpoint manager}
@@ -254,12 +295,21 @@ impl Default for BitcoinStateBits {
 /// |      |       └── utxos_small.bin
 /// |      |       └── utxos_medium.bin
 /// |      |       └── address_outpoints.bin
-/// │      └── canister_states
-/// │          └── <hex(canister_id)>
-/// │              ├── queues.pbuf
+/// |      |       └── block_headers.bin
+/// |      |       └── address_transactions.bin
+/// │      ├── canister_states
+/// │      │   └── <hex(canister_id)>
+/// │      │       ├── queues.pbuf
+/// │      │       ├── vmemory_0.bin
+/// │      │       ├── canister.pbuf
+/// │      │       ├── stable_memory.(pbuf|bin)
+/// │      │       ├── wasm_chunk_store.bin
+/// │      │       └── software.wasm
+/// │      └── snapshots
+/// │          └── <hex(snapshot_id)>
+/// │              ├── snapshot.pbuf
 /// │              ├── vmemory_0.bin
-/// │              ├── canister.pbuf
-/// │              ├── stable_memory.(pbuf|bin)
+/// │              ├── stable_memory.bin
 /// │              └── software.wasm
 /// │
 /// └── tmp
@@ -735,6 +785,32 @@ impl<Permissions: AccessPolicy> CheckpointLayout<Permissions> {
         )
     }
 
+    pub fn snapshot_ids(&self) -> Result<Vec<SnapshotId>, LayoutError> {
+        let snapshots_dir = self.root.join("snapshots");
+        Permissions::check_dir(&snapshots_dir)?;
+        collect_subdirs(snapshots_dir.as_path(), |p| {
+            let blob = hex::decode(p).unwrap_or_else(|err| {
+                panic!(
+                    "Failed to convert directory name {} into a snapshot id: {}",
+                    p, err
+                )
+            });
+
+            SnapshotId::from_bytes(&blob).expect("failed to parse snapshot id")
+        })
+    }
+
+    pub fn snapshot(
+        &self,
+        snapshot_id: &SnapshotId,
+    ) -> Result<SnapshotLayout<Permissions>, LayoutError> {
+        SnapshotLayout::new(
+            self.root
+                .join("snapshots")
+                .join(hex::encode(snapshot_id.to_bytes())),
+        )
+    }
+
     pub fn bitcoin(&self) -> Result<BitcoinStateLayout<Permissions>, LayoutError> {
         // TODO(EXC-1113): Rename this path to "bitcoin", as it stores data for either network.
         BitcoinStateLayout::new(self.root.join("bitcoin").join("testnet"))
@@ -789,6 +865,10 @@ impl<Permissions: AccessPolicy> CanisterLayout<Permissions> {
         self.canister_root.join("stable_memory.bin")
     }
 
+    pub fn wasm_chunk_store(&self) -> PathBuf {
+        self.canister_root.join("wasm_chunk_store.bin")
+    }
+
     pub fn tombstone(&self) -> PathBuf {
         self.canister_root.join("tombstone")
     }
@@ -811,6 +891,43 @@ impl<Permissions: AccessPolicy> CanisterLayout<Permissions> {
     }
 }
 
+pub struct SnapshotLayout<Permissions: AccessPolicy> {
+    snapshot_root: PathBuf,
+    permissions_tag: PhantomData<Permissions>,
+}
+
+impl<Permissions: AccessPolicy> SnapshotLayout<Permissions> {
+    pub fn new(snapshot_root: PathBuf) -> Result<Self, LayoutError> {
+        Permissions::check_dir(&snapshot_root)?;
+        Ok(Self {
+            snapshot_root,
+            permissions_tag: PhantomData,
+        })
+    }
+
+    pub fn raw_path(&self) -> PathBuf {
+        self.snapshot_root.clone()
+    }
+
+    pub fn snapshot(
+        &self,
+    ) -> ProtoFileWith<pb_canister_state_bits::CanisterSnapshotBits, Permissions> {
+        self.snapshot_root.join("snapshot.pbuf").into()
+    }
+
+    pub fn wasm(&self) -> WasmFile<Permissions> {
+        self.snapshot_root.join("software.wasm").into()
+    }
+
+    pub fn vmemory_0(&self) -> PathBuf {
+        self.snapshot_root.join("vmemory_0.bin")
+    }
+
+    pub fn stable_memory_blob(&self) -> PathBuf {
+        self.snapshot_root.join("stable_memory.bin")
+    }
+}
+
 pub struct BitcoinStateLayout<Permissions: AccessPolicy> {
     bitcoin_root: PathBuf,
     permissions_tag: PhantomData<Permissions>,
@@ -844,6 +961,14 @@ impl<Permissions: AccessPolicy> BitcoinStateLayout<Permissions> {
     pub fn address_outpoints(&self) -> PathBuf {
         self.bitcoin_root.join("address_outpoints.bin")
     }
+
+    pub fn block_headers(&self) -> PathBuf {
+        self.bitcoin_root.join("block_headers.bin")
+    }
+
+    pub fn address_transactions(&self) -> PathBuf {
+        self.bitcoin_root.join("address_transactions.bin")
+    }
 }
 
 fn open_for_write(path: &Path) -> Result<std::fs::File, LayoutError> {
@@ -1073,6 +1198,35 @@ impl From<CanisterStateBits> for pb_canister_state_bits::CanisterStateBits {
             heap_delta_debit: item.heap_delta_debit.get(),
             install_code_debit: item.install_code_debit.get(),
             task_queue: item.task_queue.iter().map(|v| v.into()).collect(),
+            global_timer_nanos: match item.global_timer {
+                CanisterTimer::Inactive => None,
+                CanisterTimer::Active(time) => Some(time.as_nanos_since_unix_epoch()),
+            },
+            log_visibility: match item.log_visibility {
+                LogVisibility::Controllers => pb_canister_state_bits::LogVisibility::Controllers,
+                LogVisibility::Public => pb_canister_state_bits::LogVisibility::Public,
+            } as i32,
+            canister_log_records: item
+                .canister_log
+                .records()
+                .iter()
+                .map(|record| pb_canister_state_bits::CanisterLogRecord {
+                    idx: record.idx,
+                    timestamp_nanos: record.timestamp_nanos,
+                    content: record.content.clone(),
+                })
+                .collect(),
+            next_canister_log_record_idx: item.canister_log.next_idx(),
+            wasm_chunk_store_metadata: Some((&item.wasm_chunk_store_metadata).into()),
+            canister_version: item.canister_version,
+            canister_history: Some((&item.canister_history).into()),
+            wasm_memory_limit: item.wasm_memory_limit.map(|limit| limit.get()),
+            total_query_stats: Some(pb_canister_state_bits::TotalQueryStats {
+                num_calls: item.total_query_stats.num_calls,
+                num_instructions: item.total_query_stats.num_instructions,
+                ingress_payload_size: item.total_query_stats.ingress_payload_size,
+                egress_payload_size: item.total_query_stats.egress_payload_size,
+            }),
         }
     }
 }
@@ -1112,6 +1266,19 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
             .map(|v| v.try_into())
             .collect::<Result<_, _>>()?;
 
+        let wasm_chunk_store_metadata = value
+            .wasm_chunk_store_metadata
+            .map(|m| m.try_into())
+            .transpose()?
+            .unwrap_or_default();
+
+        // Checkpoints written before the field existed have an empty history.
+        let canister_history = value
+            .canister_history
+            .map(|h| h.try_into())
+            .transpose()?
+            .unwrap_or_default();
+
         Ok(Self {
             controllers,
             last_full_execution_round: value.last_full_execution_round.into(),
@@ -1145,6 +1312,45 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
             heap_delta_debit: NumBytes::from(value.heap_delta_debit),
             install_code_debit: NumInstructions::from(value.install_code_debit),
             task_queue,
+            global_timer: match value.global_timer_nanos {
+                Some(nanos) => CanisterTimer::Active(Time::from_nanos_since_unix_epoch(nanos)),
+                None => CanisterTimer::Inactive,
+            },
+            log_visibility: match pb_canister_state_bits::LogVisibility::from_i32(
+                value.log_visibility,
+            )
+            .unwrap_or_default()
+            {
+                pb_canister_state_bits::LogVisibility::Public => LogVisibility::Public,
+                // Checkpoints written before the field existed use the default.
+                pb_canister_state_bits::LogVisibility::Controllers
+                | pb_canister_state_bits::LogVisibility::Unspecified => LogVisibility::Controllers,
+            },
+            canister_log: CanisterLog::new(
+                value.next_canister_log_record_idx,
+                value
+                    .canister_log_records
+                    .into_iter()
+                    .map(|record| CanisterLogRecord {
+                        idx: record.idx,
+                        timestamp_nanos: record.timestamp_nanos,
+                        content: record.content,
+                    })
+                    .collect(),
+            ),
+            wasm_chunk_store_metadata,
+            canister_version: value.canister_version,
+            canister_history,
+            wasm_memory_limit: value.wasm_memory_limit.map(NumBytes::from),
+            total_query_stats: value
+                .total_query_stats
+                .map(|stats| QueryStats {
+                    num_calls: stats.num_calls,
+                    num_instructions: stats.num_instructions,
+                    ingress_payload_size: stats.ingress_payload_size,
+                    egress_payload_size: stats.egress_payload_size,
+                })
+                .unwrap_or_default(),
         })
     }
 }
@@ -1202,6 +1408,68 @@ impl TryFrom<pb_canister_state_bits::ExecutionStateBits> for ExecutionStateBits
     }
 }
 
+impl From<&CanisterSnapshotBits> for pb_canister_state_bits::CanisterSnapshotBits {
+    fn from(item: &CanisterSnapshotBits) -> Self {
+        Self {
+            canister_id: Some(item.canister_id.into()),
+            taken_at_timestamp_nanos: item.taken_at_timestamp.as_nanos_since_unix_epoch(),
+            exported_globals: item
+                .exported_globals
+                .iter()
+                .map(|global| global.into())
+                .collect(),
+            heap_size: item
+                .heap_size
+                .get()
+                .try_into()
+                .expect("Snapshot heap size didn't fit into 32 bits"),
+            stable_memory_size: item.stable_memory_size.get() as u64,
+            exports: (&item.exports).into(),
+            metadata: Some((&item.metadata).into()),
+            certified_data: item.certified_data.clone(),
+            binary_hash: item.binary_hash.as_ref().map(|h| h.to_vec()),
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::CanisterSnapshotBits> for CanisterSnapshotBits {
+    type Error = ProxyDecodeError;
+    fn try_from(value: pb_canister_state_bits::CanisterSnapshotBits) -> Result<Self, Self::Error> {
+        let mut globals = Vec::with_capacity(value.exported_globals.len());
+        for g in value.exported_globals.into_iter() {
+            globals.push(g.try_into()?);
+        }
+        let binary_hash = match value.binary_hash {
+            Some(hash) => {
+                let hash: [u8; 32] =
+                    hash.try_into()
+                        .map_err(|e| ProxyDecodeError::ValueOutOfRange {
+                            typ: "BinaryHash",
+                            err: format!("Expected a 32-byte long module hash, got {:?}", e),
+                        })?;
+                Some(hash.into())
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            canister_id: try_from_option_field(
+                value.canister_id,
+                "CanisterSnapshotBits::canister_id",
+            )?,
+            taken_at_timestamp: Time::from_nanos_since_unix_epoch(value.taken_at_timestamp_nanos),
+            exported_globals: globals,
+            heap_size: (value.heap_size as usize).into(),
+            stable_memory_size: NumWasmPages::from(value.stable_memory_size as usize),
+            exports: value.exports.try_into()?,
+            metadata: try_from_option_field(value.metadata, "CanisterSnapshotBits::metadata")
+                .unwrap_or_default(),
+            certified_data: value.certified_data,
+            binary_hash,
+        })
+    }
+}
+
 impl From<&BitcoinStateBits> for pb_bitcoin::BitcoinStateBits {
     fn from(item: &BitcoinStateBits) -> Self {
         pb_bitcoin::BitcoinStateBits {
@@ -1230,6 +1498,7 @@ impl From<&BitcoinStateBits> for pb_bitcoin::BitcoinStateBits {
                     height: *height,
                 })
                 .collect(),
+            address_transactions_start_height: item.address_transactions_start_height,
         }
     }
 }
@@ -1296,6 +1565,7 @@ impl TryFrom<pb_bitcoin::BitcoinStateBits> for BitcoinStateBits {
                     (outpoint, (tx_out, utxo.height))
                 })
                 .collect(),
+            address_transactions_start_height: value.address_transactions_start_height,
         })
     }
 }
@@ -1304,10 +1574,10 @@ impl TryFrom<pb_bitcoin::BitcoinStateBits> for BitcoinStateBits {
 mod test {
     use super::*;
 
-    use ic_ic00_types::IC_00;
+    use ic_ic00_types::{CanisterChange, CanisterChangeDetails, CanisterChangeOrigin, IC_00};
     use ic_interfaces::messages::{CanisterInputMessage, RequestOrIngress};
     use ic_test_utilities::types::{
-        ids::canister_test_id,
+        ids::{canister_test_id, user_test_id},
         messages::{IngressBuilder, RequestBuilder, ResponseBuilder},
     };
 
@@ -1333,6 +1603,14 @@ mod test {
             heap_delta_debit: NumBytes::from(0),
             install_code_debit: NumInstructions::from(0),
             task_queue: vec![],
+            global_timer: CanisterTimer::Inactive,
+            log_visibility: LogVisibility::default(),
+            canister_log: CanisterLog::default(),
+            wasm_chunk_store_metadata: WasmChunkStoreMetadata::default(),
+            canister_version: 0,
+            canister_history: CanisterHistory::default(),
+            wasm_memory_limit: None,
+            total_query_stats: QueryStats::default(),
         }
     }
 
@@ -1394,4 +1672,128 @@ mod test {
         let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
         assert_eq!(canister_state_bits.task_queue, task_queue);
     }
+
+    #[test]
+    fn test_encode_decode_global_timer() {
+        for global_timer in [
+            CanisterTimer::Inactive,
+            CanisterTimer::Active(Time::from_nanos_since_unix_epoch(1_000_000)),
+        ] {
+            let canister_state_bits = CanisterStateBits {
+                global_timer,
+                ..default_canister_state_bits()
+            };
+
+            let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+            let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+            assert_eq!(canister_state_bits.global_timer, global_timer);
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_canister_log() {
+        let mut canister_log = CanisterLog::default();
+        canister_log.add_record(10, b"first".to_vec());
+        canister_log.add_record(20, b"second".to_vec());
+        let canister_state_bits = CanisterStateBits {
+            log_visibility: LogVisibility::Public,
+            canister_log: canister_log.clone(),
+            ..default_canister_state_bits()
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+        assert_eq!(canister_state_bits.log_visibility, LogVisibility::Public);
+        assert_eq!(canister_state_bits.canister_log, canister_log);
+    }
+
+    #[test]
+    fn test_encode_decode_canister_version() {
+        let canister_state_bits = CanisterStateBits {
+            canister_version: 42,
+            ..default_canister_state_bits()
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+        assert_eq!(canister_state_bits.canister_version, 42);
+    }
+
+    #[test]
+    fn test_encode_decode_canister_history() {
+        let mut canister_history = CanisterHistory::default();
+        canister_history.add_canister_change(CanisterChange::new(
+            10,
+            1,
+            CanisterChangeOrigin::from_user(user_test_id(1).get()),
+            CanisterChangeDetails::controllers_change(vec![user_test_id(2).get()]),
+        ));
+        let canister_state_bits = CanisterStateBits {
+            canister_history: canister_history.clone(),
+            ..default_canister_state_bits()
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+        assert_eq!(canister_state_bits.canister_history, canister_history);
+    }
+
+    #[test]
+    fn test_encode_decode_wasm_memory_limit() {
+        for wasm_memory_limit in [None, Some(NumBytes::from(1 << 30))] {
+            let canister_state_bits = CanisterStateBits {
+                wasm_memory_limit,
+                ..default_canister_state_bits()
+            };
+
+            let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+            let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+            assert_eq!(canister_state_bits.wasm_memory_limit, wasm_memory_limit);
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_total_query_stats() {
+        let total_query_stats = QueryStats {
+            num_calls: 3,
+            num_instructions: 1_000_000,
+            ingress_payload_size: 100,
+            egress_payload_size: 200,
+        };
+        let canister_state_bits = CanisterStateBits {
+            total_query_stats,
+            ..default_canister_state_bits()
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+        assert_eq!(canister_state_bits.total_query_stats, total_query_stats);
+    }
+
+    #[test]
+    fn test_encode_decode_canister_snapshot_bits() {
+        let snapshot_bits = CanisterSnapshotBits {
+            canister_id: canister_test_id(7),
+            taken_at_timestamp: Time::from_nanos_since_unix_epoch(1_000_000),
+            exported_globals: vec![Global::I32(1), Global::I64(2)],
+            heap_size: NumWasmPages::from(3),
+            stable_memory_size: NumWasmPages::from(4),
+            exports: ExportedFunctions::new(BTreeSet::new()),
+            metadata: WasmMetadata::default(),
+            certified_data: vec![5, 6, 7],
+            binary_hash: Some([8; 32].into()),
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterSnapshotBits::from(&snapshot_bits);
+        let decoded = CanisterSnapshotBits::try_from(pb_bits).unwrap();
+        assert_eq!(decoded.canister_id, snapshot_bits.canister_id);
+        assert_eq!(decoded.taken_at_timestamp, snapshot_bits.taken_at_timestamp);
+        assert_eq!(decoded.exported_globals, snapshot_bits.exported_globals);
+        assert_eq!(decoded.heap_size, snapshot_bits.heap_size);
+        assert_eq!(decoded.stable_memory_size, snapshot_bits.stable_memory_size);
+        assert_eq!(decoded.exports, snapshot_bits.exports);
+        assert_eq!(decoded.metadata, snapshot_bits.metadata);
+        assert_eq!(decoded.certified_data, snapshot_bits.certified_data);
+        assert_eq!(decoded.binary_hash, snapshot_bits.binary_hash);
+    }
 }
@@ -10,12 +10,12 @@ use ic_replicated_state::{
     bitcoin_state::{BitcoinState, UtxoSet},
     canister_state::execution_state::WasmBinary,
     page_map::PageMap,
-    CanisterMetrics, CanisterState, ExecutionState, NumWasmPages, ReplicatedState, SchedulerState,
-    SystemState,
+    CanisterMetrics, CanisterSnapshot, CanisterSnapshots, CanisterState, ExecutionState,
+    NumWasmPages, ReplicatedState, SchedulerState, SnapshotId, SystemState, WasmChunkStore,
 };
 use ic_state_layout::{
-    BitcoinStateBits, BitcoinStateLayout, CanisterLayout, CanisterStateBits, CheckpointLayout,
-    ExecutionStateBits, ReadPolicy, RwPolicy, StateLayout,
+    BitcoinStateBits, BitcoinStateLayout, CanisterLayout, CanisterSnapshotBits, CanisterStateBits,
+    CheckpointLayout, ExecutionStateBits, ReadPolicy, RwPolicy, SnapshotLayout, StateLayout,
 };
 use ic_types::Height;
 use ic_utils::fs::defrag_file_partially;
@@ -25,6 +25,7 @@ use rand::{Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
 use std::collections::BTreeMap;
 use std::os::unix::prelude::MetadataExt;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::{
     convert::{From, TryFrom},
@@ -115,9 +116,77 @@ fn serialize_to_tip(
 
     serialize_bitcoin_state_to_tip(state.bitcoin(), &tip.bitcoin()?)?;
 
+    serialize_snapshots_to_tip(&state.canister_snapshots, tip)?;
+
+    Ok(())
+}
+
+/// Writes the canister snapshots that are not yet in the tip and removes the
+/// ones that were deleted since the last checkpoint.
+///
+/// Snapshots are immutable, so a snapshot that is already in the tip (i.e. it
+/// was part of an earlier checkpoint) does not need to be written again.
+fn serialize_snapshots_to_tip(
+    snapshots: &CanisterSnapshots,
+    tip: &CheckpointLayout<RwPolicy>,
+) -> Result<(), CheckpointError> {
+    for snapshot_id in tip.snapshot_ids()? {
+        if snapshots.get(snapshot_id).is_none() {
+            let path = tip.snapshot(&snapshot_id)?.raw_path();
+            std::fs::remove_dir_all(&path).map_err(|err| CheckpointError::IoError {
+                path,
+                message: "failed to remove deleted snapshot".to_string(),
+                io_err: err.to_string(),
+            })?;
+        }
+    }
+
+    for (snapshot_id, snapshot) in snapshots.iter() {
+        let snapshot_layout = tip.snapshot(snapshot_id)?;
+        // The snapshot bits are written last, so their presence indicates
+        // that the snapshot has been fully serialized.
+        if snapshot_layout.snapshot().raw_path().exists() {
+            continue;
+        }
+        serialize_snapshot_to_tip(snapshot, &snapshot_layout)?;
+    }
+
     Ok(())
 }
 
+fn serialize_snapshot_to_tip(
+    snapshot: &CanisterSnapshot,
+    layout: &SnapshotLayout<RwPolicy>,
+) -> Result<(), CheckpointError> {
+    layout.wasm().serialize(&snapshot.wasm_binary().binary)?;
+    snapshot
+        .wasm_memory()
+        .page_map
+        .persist_all_and_sync(&layout.vmemory_0())?;
+    snapshot
+        .stable_memory()
+        .page_map
+        .persist_all_and_sync(&layout.stable_memory_blob())?;
+
+    layout
+        .snapshot()
+        .serialize(
+            (&CanisterSnapshotBits {
+                canister_id: snapshot.canister_id(),
+                taken_at_timestamp: snapshot.taken_at_timestamp(),
+                exported_globals: snapshot.exported_globals().to_vec(),
+                heap_size: snapshot.wasm_memory().size,
+                stable_memory_size: snapshot.stable_memory().size,
+                exports: snapshot.exports().clone(),
+                metadata: snapshot.metadata().clone(),
+                certified_data: snapshot.certified_data().to_vec(),
+                binary_hash: Some(snapshot.wasm_binary().binary.module_hash().into()),
+            })
+                .into(),
+        )
+        .map_err(CheckpointError::from)
+}
+
 fn serialize_canister_to_tip(
     log: &ReplicaLogger,
     canister_state: &CanisterState,
@@ -171,6 +240,15 @@ fn serialize_canister_to_tip(
         }
         None => None,
     };
+    // Most canisters never use the chunk store, so its file is only created
+    // once the first chunk is uploaded.
+    let wasm_chunk_store = &canister_state.system_state.wasm_chunk_store;
+    let wasm_chunk_store_path = canister_layout.wasm_chunk_store();
+    if !wasm_chunk_store.page_map().page_delta_is_empty() || wasm_chunk_store_path.exists() {
+        wasm_chunk_store
+            .page_map()
+            .persist_and_sync_delta(&wasm_chunk_store_path)?;
+    }
     // As the long executions get aborted at the checkpoint, the `priority_credit`
     // and the `long_execution_progress` must be zeros.
     assert_eq!(canister_state.scheduler_state.priority_credit, 0.into());
@@ -223,6 +301,14 @@ fn serialize_canister_to_tip(
                     .clone()
                     .into_iter()
                     .collect(),
+                global_timer: canister_state.system_state.global_timer,
+                log_visibility: canister_state.system_state.log_visibility,
+                canister_log: canister_state.system_state.canister_log.clone(),
+                wasm_chunk_store_metadata: wasm_chunk_store.metadata().clone(),
+                canister_version: canister_state.system_state.canister_version,
+                canister_history: canister_state.system_state.canister_history.clone(),
+                wasm_memory_limit: canister_state.system_state.wasm_memory_limit,
+                total_query_stats: canister_state.system_state.total_query_stats,
             }
             .into(),
         )
@@ -248,6 +334,14 @@ fn serialize_bitcoin_state_to_tip(
         .address_outpoints
         .persist_and_sync_delta(&layout.address_outpoints())?;
 
+    state
+        .block_headers
+        .persist_and_sync_delta(&layout.block_headers())?;
+
+    state
+        .address_transactions
+        .persist_and_sync_delta(&layout.address_transactions())?;
+
     layout
         .bitcoin_state()
         .serialize(
@@ -258,6 +352,7 @@ fn serialize_bitcoin_state_to_tip(
                 stable_height: state.stable_height,
                 network: state.utxo_set.network,
                 utxos_large: state.utxo_set.utxos_large.clone(),
+                address_transactions_start_height: state.address_transactions_start_height,
             })
                 .into(),
         )
@@ -293,6 +388,8 @@ fn defrag_tip(
         PageMapType::Bitcoin(BitcoinPageMap::UtxosSmall),
         PageMapType::Bitcoin(BitcoinPageMap::UtxosMedium),
         PageMapType::Bitcoin(BitcoinPageMap::AddressOutpoints),
+        PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders),
+        PageMapType::Bitcoin(BitcoinPageMap::AddressTransactions),
     ];
     let path_with_sizes: Vec<(PathBuf, u64)> = bitcoin_files
         .iter()
@@ -427,8 +524,23 @@ pub fn load_checkpoint<P: ReadPolicy + Send + Sync>(
         load_bitcoin_state(checkpoint_layout)?
     };
 
+    let canister_snapshots = {
+        let _timer = metrics
+            .load_checkpoint_step_duration
+            .with_label_values(&["canister_snapshots"])
+            .start_timer();
+
+        let mut snapshots = BTreeMap::new();
+        for snapshot_id in checkpoint_layout.snapshot_ids()? {
+            let snapshot = load_snapshot(checkpoint_layout, &snapshot_id)?;
+            snapshots.insert(snapshot_id, Arc::new(snapshot));
+        }
+        CanisterSnapshots::new(snapshots)
+    };
+
     let state = ReplicatedState::new_from_checkpoint(
         canister_states,
+        canister_snapshots,
         metadata,
         subnet_queues,
         // Consensus queue needs to be empty at the end of every round.
@@ -552,6 +664,17 @@ pub fn load_canister_state<P: ReadPolicy>(
         canister_metrics,
         canister_state_bits.cycles_balance,
         canister_state_bits.task_queue.into_iter().collect(),
+        canister_state_bits.global_timer,
+        canister_state_bits.log_visibility,
+        canister_state_bits.canister_log,
+        WasmChunkStore::new_from_checkpoint(
+            load_or_create_pagemap(&canister_layout.wasm_chunk_store(), Some(height))?,
+            canister_state_bits.wasm_chunk_store_metadata,
+        ),
+        canister_state_bits.canister_version,
+        canister_state_bits.canister_history,
+        canister_state_bits.wasm_memory_limit,
+        canister_state_bits.total_query_stats,
     );
 
     let canister_state = CanisterState {
@@ -583,6 +706,45 @@ fn load_canister_state_from_checkpoint<P: ReadPolicy>(
     load_canister_state::<P>(&canister_layout, canister_id, checkpoint_layout.height())
 }
 
+fn load_snapshot<P: ReadPolicy>(
+    checkpoint_layout: &CheckpointLayout<P>,
+    snapshot_id: &SnapshotId,
+) -> Result<CanisterSnapshot, CheckpointError> {
+    let layout = checkpoint_layout.snapshot(snapshot_id)?;
+    let height = checkpoint_layout.height();
+
+    let snapshot_bits =
+        CanisterSnapshotBits::try_from(layout.snapshot().deserialize()?).map_err(|err| {
+            CheckpointError::ProtoError {
+                path: layout.raw_path(),
+                field: format!("snapshots[{}]::snapshot_bits", snapshot_id),
+                proto_err: err.to_string(),
+            }
+        })?;
+
+    let wasm_memory = Memory::new(
+        PageMap::open(&layout.vmemory_0(), Some(height))?,
+        snapshot_bits.heap_size,
+    );
+    let stable_memory = Memory::new(
+        PageMap::open(&layout.stable_memory_blob(), Some(height))?,
+        snapshot_bits.stable_memory_size,
+    );
+    let wasm_binary = WasmBinary::new(layout.wasm().deserialize(snapshot_bits.binary_hash)?);
+
+    Ok(CanisterSnapshot::new(
+        snapshot_bits.canister_id,
+        snapshot_bits.taken_at_timestamp,
+        wasm_binary,
+        snapshot_bits.exports,
+        snapshot_bits.metadata,
+        snapshot_bits.exported_globals,
+        wasm_memory,
+        stable_memory,
+        snapshot_bits.certified_data,
+    ))
+}
+
 fn load_bitcoin_state<P: ReadPolicy>(
     checkpoint_layout: &CheckpointLayout<P>,
 ) -> Result<BitcoinState, CheckpointError> {
@@ -605,6 +767,9 @@ fn load_bitcoin_state<P: ReadPolicy>(
     let utxos_small = load_or_create_pagemap(&layout.utxos_small(), Some(height))?;
     let utxos_medium = load_or_create_pagemap(&layout.utxos_medium(), Some(height))?;
     let address_outpoints = load_or_create_pagemap(&layout.address_outpoints(), Some(height))?;
+    let block_headers = load_or_create_pagemap(&layout.block_headers(), Some(height))?;
+    let address_transactions =
+        load_or_create_pagemap(&layout.address_transactions(), Some(height))?;
 
     Ok(BitcoinState {
         adapter_queues: bitcoin_state_bits.adapter_queues,
@@ -618,6 +783,9 @@ fn load_bitcoin_state<P: ReadPolicy>(
             address_outpoints,
         },
         fee_percentiles_cache: None,
+        block_headers,
+        address_transactions,
+        address_transactions_start_height: bitcoin_state_bits.address_transactions_start_height,
     })
 }
 
@@ -1268,6 +1436,9 @@ mod tests {
             state.bitcoin_mut().utxo_set.utxos_small = PageMap::from(&[1, 2, 3, 4][..]);
             state.bitcoin_mut().utxo_set.utxos_medium = PageMap::from(&[5, 6, 7, 8][..]);
             state.bitcoin_mut().utxo_set.address_outpoints = PageMap::from(&[9, 10, 11, 12][..]);
+            state.bitcoin_mut().block_headers = PageMap::from(&[17, 18, 19, 20][..]);
+            state.bitcoin_mut().address_transactions = PageMap::from(&[13, 14, 15, 16][..]);
+            state.bitcoin_mut().address_transactions_start_height = Some(7);
 
             let original_state = state.clone();
             let _state = make_checkpoint_and_get_state(&log, &state, HEIGHT, &layout);
@@ -1297,6 +1468,8 @@ mod tests {
                 BitcoinPageMap::AddressOutpoints,
                 BitcoinPageMap::UtxosSmall,
                 BitcoinPageMap::UtxosMedium,
+                BitcoinPageMap::BlockHeaders,
+                BitcoinPageMap::AddressTransactions,
             ]
             .drain(..)
             .map(|inner| PageMapType::Bitcoin(inner).path(&tip).unwrap())
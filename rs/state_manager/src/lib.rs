@@ -801,6 +801,7 @@ struct PopulatedMetadata {
 pub enum PageMapType {
     WasmMemory(CanisterId),
     StableMemory(CanisterId),
+    WasmChunkStore(CanisterId),
     Bitcoin(BitcoinPageMap),
 }
 
@@ -810,6 +811,8 @@ pub enum BitcoinPageMap {
     UtxosSmall,
     UtxosMedium,
     AddressOutpoints,
+    BlockHeaders,
+    AddressTransactions,
 }
 
 impl PageMapType {
@@ -821,11 +824,15 @@ impl PageMapType {
                 result.push(Self::WasmMemory(id.to_owned()));
                 result.push(Self::StableMemory(id.to_owned()));
             }
+            // The chunk store exists independently of the execution state.
+            result.push(Self::WasmChunkStore(id.to_owned()));
         }
 
         result.push(Self::Bitcoin(BitcoinPageMap::UtxosSmall));
         result.push(Self::Bitcoin(BitcoinPageMap::UtxosMedium));
         result.push(Self::Bitcoin(BitcoinPageMap::AddressOutpoints));
+        result.push(Self::Bitcoin(BitcoinPageMap::BlockHeaders));
+        result.push(Self::Bitcoin(BitcoinPageMap::AddressTransactions));
 
         result
     }
@@ -838,6 +845,7 @@ impl PageMapType {
         match &self {
             PageMapType::WasmMemory(id) => Ok(layout.canister(id)?.vmemory_0()),
             PageMapType::StableMemory(id) => Ok(layout.canister(id)?.stable_memory_blob()),
+            PageMapType::WasmChunkStore(id) => Ok(layout.canister(id)?.wasm_chunk_store()),
             PageMapType::Bitcoin(BitcoinPageMap::UtxosSmall) => Ok(layout.bitcoin()?.utxos_small()),
             PageMapType::Bitcoin(BitcoinPageMap::UtxosMedium) => {
                 Ok(layout.bitcoin()?.utxos_medium())
@@ -845,6 +853,12 @@ impl PageMapType {
             PageMapType::Bitcoin(BitcoinPageMap::AddressOutpoints) => {
                 Ok(layout.bitcoin()?.address_outpoints())
             }
+            PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders) => {
+                Ok(layout.bitcoin()?.block_headers())
+            }
+            PageMapType::Bitcoin(BitcoinPageMap::AddressTransactions) => {
+                Ok(layout.bitcoin()?.address_transactions())
+            }
         }
     }
 
@@ -861,6 +875,9 @@ impl PageMapType {
                     .as_ref()
                     .map(|ex| &ex.stable_memory.page_map)
             }),
+            PageMapType::WasmChunkStore(id) => state
+                .canister_state(id)
+                .map(|can| can.system_state.wasm_chunk_store.page_map()),
             PageMapType::Bitcoin(BitcoinPageMap::UtxosSmall) => {
                 Some(&state.bitcoin().utxo_set.utxos_small)
             }
@@ -870,6 +887,12 @@ impl PageMapType {
             PageMapType::Bitcoin(BitcoinPageMap::AddressOutpoints) => {
                 Some(&state.bitcoin().utxo_set.address_outpoints)
             }
+            PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders) => {
+                Some(&state.bitcoin().block_headers)
+            }
+            PageMapType::Bitcoin(BitcoinPageMap::AddressTransactions) => {
+                Some(&state.bitcoin().address_transactions)
+            }
         }
     }
 
@@ -886,6 +909,9 @@ impl PageMapType {
                     .as_mut()
                     .map(|ex| &mut ex.stable_memory.page_map)
             }),
+            PageMapType::WasmChunkStore(id) => state
+                .canister_state_mut(id)
+                .map(|can| can.system_state.wasm_chunk_store.page_map_mut()),
             PageMapType::Bitcoin(BitcoinPageMap::UtxosSmall) => {
                 Some(&mut state.bitcoin_mut().utxo_set.utxos_small)
             }
@@ -895,6 +921,12 @@ impl PageMapType {
             PageMapType::Bitcoin(BitcoinPageMap::AddressOutpoints) => {
                 Some(&mut state.bitcoin_mut().utxo_set.address_outpoints)
             }
+            PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders) => {
+                Some(&mut state.bitcoin_mut().block_headers)
+            }
+            PageMapType::Bitcoin(BitcoinPageMap::AddressTransactions) => {
+                Some(&mut state.bitcoin_mut().address_transactions)
+            }
         }
     }
 }
@@ -1052,6 +1084,20 @@ fn switch_to_checkpoint(tip: &mut ReplicatedState, src: &ReplicatedState) {
             tip_state.stable_memory.sandbox_memory = SandboxMemory::new();
         }
     }
+
+    // Snapshots are immutable, so the ones in `src` have the same contents as
+    // the ones in `tip`, but are backed by the checkpoint files.
+    debug_assert_eq!(
+        tip.canister_snapshots
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>(),
+        src.canister_snapshots
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>()
+    );
+    tip.canister_snapshots = src.canister_snapshots.clone();
 }
 
 /// Persist the metadata of `StateManagerImpl` to disk
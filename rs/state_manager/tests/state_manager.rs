@@ -10,8 +10,8 @@ use ic_interfaces_state_manager::*;
 use ic_logger::replica_logger::no_op_logger;
 use ic_metrics::MetricsRegistry;
 use ic_replicated_state::{
-    page_map::PageIndex, testing::ReplicatedStateTesting, NumWasmPages, PageMap, ReplicatedState,
-    Stream,
+    page_map::PageIndex, testing::ReplicatedStateTesting, CanisterSnapshot, NumWasmPages, PageMap,
+    ReplicatedState, SnapshotId, Stream,
 };
 use ic_state_manager::{BitcoinPageMap, DirtyPageMap, FileType, PageMapType, StateManagerImpl};
 use ic_sys::PAGE_SIZE;
@@ -362,6 +362,71 @@ fn stable_memory_is_persisted() {
     });
 }
 
+#[test]
+fn canister_snapshots_are_persisted() {
+    state_manager_restart_test(|state_manager, restart_fn| {
+        let (_height, mut state) = state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(100));
+        let canister_state = state.canister_state_mut(&canister_test_id(100)).unwrap();
+        let execution_state = canister_state.execution_state.as_mut().unwrap();
+        execution_state.wasm_memory.size = NumWasmPages::new(1);
+        execution_state.wasm_memory.page_map = PageMap::from(&[1; 100][..]);
+        execution_state.stable_memory.size = NumWasmPages::new(2);
+        execution_state.stable_memory.page_map = PageMap::from(&[2; 100][..]);
+        let snapshot = CanisterSnapshot::from_execution_state(
+            canister_test_id(100),
+            mock_time(),
+            execution_state,
+            vec![3; 32],
+        );
+        state.put_canister_snapshot(SnapshotId::new(0), snapshot.clone());
+
+        // The canister keeps on changing after the snapshot is taken.
+        let canister_state = state.canister_state_mut(&canister_test_id(100)).unwrap();
+        canister_state
+            .execution_state
+            .as_mut()
+            .unwrap()
+            .wasm_memory
+            .page_map = PageMap::from(&[4; 100][..]);
+        state_manager.commit_and_certify(state, height(1), CertificationScope::Full);
+
+        let state_manager = restart_fn(state_manager, None);
+
+        let recovered = state_manager.get_latest_state();
+        assert_eq!(height(1), recovered.height());
+        let state = recovered.take();
+        assert_eq!(
+            state
+                .canister_snapshots
+                .get(SnapshotId::new(0))
+                .unwrap()
+                .as_ref(),
+            &snapshot
+        );
+        // The memory taken by the snapshot is derived from the snapshots.
+        assert_eq!(
+            state
+                .canister_state(&canister_test_id(100))
+                .unwrap()
+                .system_state
+                .snapshots_memory_usage,
+            snapshot.size()
+        );
+
+        // Deleted snapshots are removed from subsequent checkpoints.
+        let (_height, mut state) = state_manager.take_tip();
+        assert!(state.remove_canister_snapshot(SnapshotId::new(0)).is_some());
+        state_manager.commit_and_certify(state, height(2), CertificationScope::Full);
+
+        let state_manager = restart_fn(state_manager, None);
+
+        let recovered = state_manager.get_latest_state();
+        assert_eq!(height(2), recovered.height());
+        assert!(recovered.take().canister_snapshots.is_empty());
+    });
+}
+
 #[test]
 fn missing_stable_memory_file_is_handled() {
     use ic_state_layout::{CheckpointLayout, RwPolicy};
@@ -1500,7 +1565,7 @@ fn state_sync_message_contains_manifest() {
             .get_validated_by_identifier(&id)
             .expect("failed to get state sync messages");
 
-        // Expecting 6 files, as we don't have canisters in the default state.
+        // Expecting 8 files, as we don't have canisters in the default state.
         //
         // 1. "system_metadata.pbuf"
         // 2. "subnet_queues.pbuf"
@@ -1508,7 +1573,9 @@ fn state_sync_message_contains_manifest() {
         // 4. "bitcoin/testnet/utxos_small.pbuf"
         // 5. "bitcoin/testnet/utxos_medium.pbuf"
         // 6. "bitcoin/testnet/address_outpoints.pbuf"
-        assert_eq!(6, msg.manifest.file_table.len());
+        // 7. "bitcoin/testnet/block_headers.pbuf"
+        // 8. "bitcoin/testnet/address_transactions.pbuf"
+        assert_eq!(8, msg.manifest.file_table.len());
 
         // Check that all the files are accessible
         for file_info in msg.manifest.file_table.iter() {
@@ -2177,6 +2244,16 @@ fn can_get_dirty_pages() {
             (PageIndex::new(3), &[99u8; PAGE_SIZE]),
             (PageIndex::new(300), &[99u8; PAGE_SIZE]),
         ]);
+
+        state.bitcoin_mut().block_headers.update(&[
+            (PageIndex::new(5), &[99u8; PAGE_SIZE]),
+            (PageIndex::new(500), &[99u8; PAGE_SIZE]),
+        ]);
+
+        state.bitcoin_mut().address_transactions.update(&[
+            (PageIndex::new(4), &[99u8; PAGE_SIZE]),
+            (PageIndex::new(400), &[99u8; PAGE_SIZE]),
+        ]);
     }
 
     fn drop_page_map(state: &mut ReplicatedState, canister_id: CanisterId) {
@@ -2260,6 +2337,18 @@ fn can_get_dirty_pages() {
                 )),
                 page_delta_indices: vec![PageIndex::new(3), PageIndex::new(300)],
             },
+            DirtyPageMap {
+                height: height(1),
+                file_type: FileType::PageMap(PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders)),
+                page_delta_indices: vec![PageIndex::new(5), PageIndex::new(500)],
+            },
+            DirtyPageMap {
+                height: height(1),
+                file_type: FileType::PageMap(PageMapType::Bitcoin(
+                    BitcoinPageMap::AddressTransactions,
+                )),
+                page_delta_indices: vec![PageIndex::new(4), PageIndex::new(400)],
+            },
             DirtyPageMap {
                 height: height(1),
                 file_type: FileType::WasmBinary(canister_test_id(80)),
@@ -2338,6 +2427,18 @@ fn can_get_dirty_pages() {
                 )),
                 page_delta_indices: vec![],
             },
+            DirtyPageMap {
+                height: height(2),
+                file_type: FileType::PageMap(PageMapType::Bitcoin(BitcoinPageMap::BlockHeaders)),
+                page_delta_indices: vec![],
+            },
+            DirtyPageMap {
+                height: height(2),
+                file_type: FileType::PageMap(PageMapType::Bitcoin(
+                    BitcoinPageMap::AddressTransactions,
+                )),
+                page_delta_indices: vec![],
+            },
             DirtyPageMap {
                 height: height(2),
                 file_type: FileType::WasmBinary(canister_test_id(80)),
@@ -2,7 +2,9 @@
 pub mod cdiff;
 pub mod chash;
 pub mod decode;
+pub mod extract_canister;
 pub mod import_state;
 pub mod list;
 pub mod manifest;
 mod utils;
+pub mod verify;
@@ -0,0 +1,265 @@
+//! Extracts the state of a single canister from a checkpoint.
+
+use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::{
+    canister_state::{execution_state::Memory, WASM_PAGE_SIZE_IN_BYTES},
+    page_map::{PageIndex, PAGE_SIZE},
+    CanisterState, SystemMetadata,
+};
+use ic_state_layout::CompleteCheckpointLayout;
+use ic_state_manager::{checkpoint::load_checkpoint, CheckpointMetrics};
+use ic_types::{CanisterId, Height};
+use std::convert::TryFrom;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes `contents` to the file `name` in the directory `dir`.
+fn write_file(dir: &Path, name: &str, contents: &[u8]) -> Result<(), String> {
+    let path = dir.join(name);
+    fs::write(&path, contents).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+/// Writes the contents of `memory` to the file `name` in the directory `dir`,
+/// including the pages the canister has access to but never wrote to.
+///
+/// The memory is written one page at a time, so that it never has to be held
+/// in RAM as a whole.
+fn write_memory(dir: &Path, name: &str, memory: &Memory) -> Result<(), String> {
+    let path = dir.join(name);
+    let write_err = |e: std::io::Error| format!("failed to write {}: {}", path.display(), e);
+
+    let mut file = BufWriter::new(File::create(&path).map_err(write_err)?);
+    let num_pages = memory.size.get() * WASM_PAGE_SIZE_IN_BYTES / PAGE_SIZE;
+    for page in 0..num_pages {
+        file.write_all(memory.page_map.get_page(PageIndex::new(page as u64)))
+            .map_err(write_err)?;
+    }
+    file.flush().map_err(write_err)
+}
+
+/// Returns the type of the subnet the checkpoint was taken on, as recorded
+/// in the network topology of the checkpoint.
+///
+/// Falls back to the default subnet type if the topology does not contain
+/// the subnet, e.g. for states created by `drun`.
+fn own_subnet_type(cp_layout: &CompleteCheckpointLayout) -> Result<SubnetType, String> {
+    let metadata = cp_layout
+        .system_metadata()
+        .deserialize()
+        .map_err(|e| format!("failed to read system metadata: {}", e))?;
+    let metadata = SystemMetadata::try_from(metadata)
+        .map_err(|e| format!("failed to decode system metadata: {}", e))?;
+    Ok(metadata
+        .network_topology
+        .subnets
+        .get(&metadata.own_subnet_id)
+        .map(|subnet| subnet.subnet_type)
+        .unwrap_or_default())
+}
+
+/// Returns a human-readable description of the canister's metadata.
+fn describe_canister(canister: &CanisterState) -> String {
+    let system_state = &canister.system_state;
+    let mut out = String::new();
+    // Writing to a `String` never fails.
+    let mut line = |key: &str, value: String| writeln!(out, "{}: {}", key, value).unwrap();
+
+    line("canister_id", system_state.canister_id.to_string());
+    line("status", system_state.status_string().to_string());
+    line(
+        "controllers",
+        system_state
+            .controllers
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    line("cycles_balance", system_state.balance().get().to_string());
+    line(
+        "freeze_threshold",
+        system_state.freeze_threshold.to_string(),
+    );
+    line(
+        "compute_allocation",
+        canister.scheduler_state.compute_allocation.to_string(),
+    );
+    line(
+        "memory_allocation",
+        format!("{:?}", system_state.memory_allocation),
+    );
+    line("certified_data", hex::encode(&system_state.certified_data));
+    match &canister.execution_state {
+        Some(execution_state) => {
+            line(
+                "module_hash",
+                hex::encode(execution_state.wasm_binary.binary.module_hash()),
+            );
+            line(
+                "wasm_memory_pages",
+                execution_state.wasm_memory.size.to_string(),
+            );
+            line(
+                "stable_memory_pages",
+                execution_state.stable_memory.size.to_string(),
+            );
+        }
+        None => line("module_hash", "none (empty canister)".to_string()),
+    }
+    out
+}
+
+/// Loads the checkpoint at `path` and writes the module, memories and
+/// metadata of the canister `canister_id` into the directory `output`.
+pub fn do_extract_canister(
+    path: PathBuf,
+    canister_id: CanisterId,
+    output: PathBuf,
+) -> Result<(), String> {
+    let cp_layout = CompleteCheckpointLayout::new(path.clone(), Height::new(0))
+        .map_err(|e| format!("failed to create checkpoint layout: {}", e))?;
+
+    let dummy_metrics_registry = ic_metrics::MetricsRegistry::new();
+    let dummy_metrics = CheckpointMetrics::new(&dummy_metrics_registry);
+
+    let subnet_type = own_subnet_type(&cp_layout)?;
+    let state = load_checkpoint(&cp_layout, subnet_type, &dummy_metrics, None)
+        .map_err(|e| format!("failed to load checkpoint at {}: {}", path.display(), e))?;
+
+    let canister = state.canister_state(&canister_id).ok_or_else(|| {
+        format!(
+            "canister {} not found in checkpoint at {}",
+            canister_id,
+            path.display()
+        )
+    })?;
+
+    fs::create_dir_all(&output)
+        .map_err(|e| format!("failed to create directory {}: {}", output.display(), e))?;
+
+    write_file(
+        &output,
+        "metadata.txt",
+        describe_canister(canister).as_bytes(),
+    )?;
+    write_file(
+        &output,
+        "certified_data.bin",
+        &canister.system_state.certified_data,
+    )?;
+    if let Some(execution_state) = &canister.execution_state {
+        write_file(
+            &output,
+            "canister.wasm",
+            execution_state.wasm_binary.binary.as_slice(),
+        )?;
+        write_memory(&output, "wasm_memory.bin", &execution_state.wasm_memory)?;
+        write_memory(&output, "stable_memory.bin", &execution_state.stable_memory)?;
+    }
+
+    println!("Extracted canister {} to {}", canister_id, output.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_base_types::NumSeconds;
+    use ic_replicated_state::{
+        canister_state::execution_state::{WasmBinary, WasmMetadata},
+        ExecutionState, ExportedFunctions, NumWasmPages, PageMap, ReplicatedState, SubnetTopology,
+    };
+    use ic_state_layout::StateLayout;
+    use ic_state_manager::checkpoint::make_checkpoint;
+    use ic_test_utilities::{
+        state::new_canister_state,
+        types::ids::{canister_test_id, subnet_test_id, user_test_id},
+        with_test_replica_logger,
+    };
+    use ic_types::{Cycles, ExecutionRound};
+    use ic_wasm_types::CanisterModule;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn extracts_canister_from_checkpoint() {
+        with_test_replica_logger(|log| {
+            let tmp = tempfile::tempdir().unwrap();
+            let root = tmp.path().join("state");
+            let layout = StateLayout::new(log.clone(), root.clone());
+            let canister_id = canister_test_id(10);
+            let subnet_id = subnet_test_id(1);
+
+            let wasm = CanisterModule::new(vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00]);
+            // Only the second OS page of the wasm memory is written to.
+            let mut wasm_page_map = PageMap::new();
+            wasm_page_map.update(&[(PageIndex::new(1), &[7; PAGE_SIZE])]);
+            let mut canister_state = new_canister_state(
+                canister_id,
+                user_test_id(24).get(),
+                Cycles::new(1 << 36),
+                NumSeconds::from(100_000),
+            );
+            canister_state.execution_state = Some(ExecutionState {
+                canister_root: root.clone(),
+                session_nonce: None,
+                wasm_binary: WasmBinary::new(wasm.clone()),
+                wasm_memory: Memory::new(wasm_page_map, NumWasmPages::new(1)),
+                stable_memory: Memory::new(PageMap::from(&[1, 2, 3, 4][..]), NumWasmPages::new(1)),
+                exported_globals: vec![],
+                exports: ExportedFunctions::new(BTreeSet::new()),
+                metadata: WasmMetadata::default(),
+                last_executed_round: ExecutionRound::from(0),
+            });
+
+            let mut state =
+                ReplicatedState::new_rooted_at(subnet_id, SubnetType::System, root.clone());
+            state.metadata.network_topology.subnets.insert(
+                subnet_id,
+                SubnetTopology {
+                    subnet_type: SubnetType::System,
+                    ..Default::default()
+                },
+            );
+            state.put_canister_state(canister_state);
+            make_checkpoint(
+                &state,
+                Height::new(42),
+                &layout,
+                &log,
+                &CheckpointMetrics::new(&ic_metrics::MetricsRegistry::new()),
+                &mut scoped_threadpool::Pool::new(1),
+            )
+            .unwrap();
+            let checkpoint = layout.checkpoint(Height::new(42)).unwrap();
+            assert_eq!(own_subnet_type(&checkpoint).unwrap(), SubnetType::System);
+
+            let output = tmp.path().join("canister");
+            do_extract_canister(
+                checkpoint.raw_path().to_path_buf(),
+                canister_id,
+                output.clone(),
+            )
+            .unwrap();
+
+            assert_eq!(
+                fs::read(output.join("canister.wasm")).unwrap(),
+                wasm.as_slice()
+            );
+            let wasm_memory = fs::read(output.join("wasm_memory.bin")).unwrap();
+            assert_eq!(wasm_memory.len(), WASM_PAGE_SIZE_IN_BYTES);
+            assert!(wasm_memory[..PAGE_SIZE].iter().all(|b| *b == 0));
+            assert!(wasm_memory[PAGE_SIZE..2 * PAGE_SIZE]
+                .iter()
+                .all(|b| *b == 7));
+            assert!(wasm_memory[2 * PAGE_SIZE..].iter().all(|b| *b == 0));
+            let stable_memory = fs::read(output.join("stable_memory.bin")).unwrap();
+            assert_eq!(stable_memory.len(), WASM_PAGE_SIZE_IN_BYTES);
+            assert_eq!(&stable_memory[..4], &[1, 2, 3, 4]);
+            let metadata = fs::read_to_string(output.join("metadata.txt")).unwrap();
+            assert!(metadata.contains(&format!("canister_id: {}", canister_id)));
+        });
+    }
+}
@@ -0,0 +1,336 @@
+//! Verifies the integrity of a checkpoint.
+
+use crate::commands::manifest::compute_checkpoint_manifest;
+use ic_logger::replica_logger::no_op_logger;
+use ic_protobuf::state::v1 as pb_metadata;
+use ic_protobuf::types::v1 as pb_types;
+use ic_state_layout::StateLayout;
+use ic_state_manager::manifest::{file_chunk_range, manifest_hash};
+use ic_types::state_sync::Manifest;
+use prost::Message;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Loads the manifest stored in the state root for the checkpoint at `path`,
+/// if the checkpoint is located in a state root and its manifest was
+/// computed.
+fn load_stored_manifest(path: &Path) -> Result<Option<Manifest>, String> {
+    let height = match path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| u64::from_str_radix(name, 16).ok())
+    {
+        Some(height) => height,
+        None => return Ok(None),
+    };
+    let state_root = match path.parent().and_then(|checkpoints| checkpoints.parent()) {
+        Some(state_root) => state_root,
+        None => return Ok(None),
+    };
+
+    let metadata_path =
+        StateLayout::new(no_op_logger(), state_root.to_path_buf()).states_metadata();
+    if !metadata_path.exists() {
+        return Ok(None);
+    }
+    let bytes = fs::read(&metadata_path)
+        .map_err(|e| format!("failed to read {}: {}", metadata_path.display(), e))?;
+    let states_metadata = pb_metadata::StatesMetadata::decode(&bytes[..])
+        .map_err(|e| format!("failed to decode {}: {}", metadata_path.display(), e))?;
+
+    match states_metadata
+        .by_height
+        .get(&height)
+        .and_then(|metadata| metadata.manifest.clone())
+    {
+        Some(manifest) => Manifest::try_from(manifest)
+            .map(Some)
+            .map_err(|e| format!("failed to decode stored manifest @{}: {}", height, e)),
+        None => Ok(None),
+    }
+}
+
+/// Extracts the state hash from the catch-up package stored at `path`.
+fn load_cup_state_hash(path: &Path) -> Result<Vec<u8>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let cup = pb_types::CatchUpPackage::decode(&bytes[..])
+        .map_err(|e| format!("failed to decode CUP {}: {}", path.display(), e))?;
+    let content = pb_types::CatchUpContent::decode(&cup.content[..])
+        .map_err(|e| format!("failed to decode content of CUP {}: {}", path.display(), e))?;
+    Ok(content.state_hash)
+}
+
+/// Compares the file and chunk tables of two manifests, returning a
+/// description of each mismatch.
+fn diff_manifests(expected: &Manifest, actual: &Manifest) -> Vec<String> {
+    let mut mismatches = vec![];
+    if expected.version != actual.version {
+        mismatches.push(format!(
+            "manifest version: expected {}, got {}",
+            expected.version, actual.version
+        ));
+    }
+
+    let actual_files: BTreeMap<_, _> = actual
+        .file_table
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (&f.relative_path, i))
+        .collect();
+
+    for (expected_index, expected_file) in expected.file_table.iter().enumerate() {
+        let path = expected_file.relative_path.display();
+        let actual_index = match actual_files.get(&expected_file.relative_path) {
+            Some(i) => *i,
+            None => {
+                mismatches.push(format!("{}: file is missing", path));
+                continue;
+            }
+        };
+        let actual_file = &actual.file_table[actual_index];
+        if expected_file.size_bytes != actual_file.size_bytes {
+            mismatches.push(format!(
+                "{}: expected size {}, got {}",
+                path, expected_file.size_bytes, actual_file.size_bytes
+            ));
+        }
+        if expected_file.hash == actual_file.hash {
+            continue;
+        }
+        mismatches.push(format!("{}: file hash mismatch", path));
+
+        let expected_chunks = file_chunk_range(&expected.chunk_table, expected_index);
+        let actual_chunks = file_chunk_range(&actual.chunk_table, actual_index);
+        for (expected_chunk, actual_chunk) in expected_chunks.zip(actual_chunks) {
+            let (e, a) = (
+                &expected.chunk_table[expected_chunk],
+                &actual.chunk_table[actual_chunk],
+            );
+            if e.hash != a.hash || e.size_bytes != a.size_bytes {
+                // Chunk indices are displayed starting from 1, as in the
+                // output of the `manifest` command.
+                mismatches.push(format!(
+                    "{}: chunk [{}] at offset {} (size {}) mismatch",
+                    path,
+                    actual_chunk + 1,
+                    a.offset,
+                    a.size_bytes
+                ));
+            }
+        }
+    }
+
+    let expected_paths: Vec<_> = expected
+        .file_table
+        .iter()
+        .map(|f| &f.relative_path)
+        .collect();
+    for file in actual.file_table.iter() {
+        if !expected_paths.contains(&&file.relative_path) {
+            mismatches.push(format!("{}: unexpected file", file.relative_path.display()));
+        }
+    }
+
+    mismatches
+}
+
+/// `verify` command entry point.
+///
+/// Recomputes the manifest of the checkpoint at `path` and compares it with
+/// the manifest stored in the state root, and its root hash with the state
+/// hash of the given CUP and the given root hash.
+pub fn do_verify(
+    path: PathBuf,
+    cup: Option<PathBuf>,
+    root_hash: Option<String>,
+) -> Result<(), String> {
+    let mut expected_hashes = vec![];
+    if let Some(cup) = cup {
+        expected_hashes.push(("CUP state hash", load_cup_state_hash(&cup)?));
+    }
+    if let Some(root_hash) = root_hash {
+        let root_hash = hex::decode(root_hash.trim_start_matches("0x"))
+            .map_err(|e| format!("failed to decode root hash {}: {}", root_hash, e))?;
+        expected_hashes.push(("given root hash", root_hash));
+    }
+
+    let stored_manifest = load_stored_manifest(&path)?;
+    if stored_manifest.is_none() && expected_hashes.is_empty() {
+        return Err(format!(
+            "✗ Nothing to verify against: no stored manifest found for {}, \
+             and neither --cup nor --root-hash given",
+            path.display()
+        ));
+    }
+
+    let manifest = compute_checkpoint_manifest(path.clone())?;
+    let computed_hash = manifest_hash(&manifest).to_vec();
+    println!("ROOT HASH: {}", hex::encode(&computed_hash));
+
+    let mut corrupted = false;
+    match stored_manifest {
+        Some(stored_manifest) => {
+            let mismatches = diff_manifests(&stored_manifest, &manifest);
+            if mismatches.is_empty() {
+                println!("✓ Manifest matches the stored manifest");
+            } else {
+                corrupted = true;
+                println!("✗ Manifest does not match the stored manifest:");
+                for mismatch in mismatches {
+                    println!("\t{}", mismatch);
+                }
+            }
+            expected_hashes.push(("stored root hash", manifest_hash(&stored_manifest).to_vec()));
+        }
+        None => println!("No stored manifest found for {}", path.display()),
+    }
+
+    for (name, expected_hash) in expected_hashes {
+        if expected_hash == computed_hash {
+            println!("✓ Root hash matches the {}", name);
+        } else {
+            corrupted = true;
+            println!(
+                "✗ Root hash does not match the {} {}",
+                name,
+                hex::encode(&expected_hash)
+            );
+        }
+    }
+
+    if corrupted {
+        return Err(format!("✗ Checkpoint at {} is corrupted", path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_base_types::NumSeconds;
+    use ic_logger::ReplicaLogger;
+    use ic_metrics::MetricsRegistry;
+    use ic_registry_subnet_type::SubnetType;
+    use ic_replicated_state::ReplicatedState;
+    use ic_state_manager::{checkpoint::make_checkpoint, CheckpointMetrics};
+    use ic_test_utilities::{
+        state::new_canister_state,
+        types::ids::{canister_test_id, subnet_test_id, user_test_id},
+        with_test_replica_logger,
+    };
+    use ic_types::{Cycles, Height};
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    const HEIGHT: Height = Height::new(42);
+
+    // Writes a checkpoint of a state with a single canister to the state root
+    // of `layout` and stores its manifest, as the state manager does.
+    fn write_checkpoint(layout: &StateLayout, log: &ReplicaLogger) -> PathBuf {
+        let mut state = ReplicatedState::new_rooted_at(
+            subnet_test_id(1),
+            SubnetType::Application,
+            layout.raw_path().to_path_buf(),
+        );
+        state.put_canister_state(new_canister_state(
+            canister_test_id(10),
+            user_test_id(24).get(),
+            Cycles::new(1 << 36),
+            NumSeconds::from(100_000),
+        ));
+        make_checkpoint(
+            &state,
+            HEIGHT,
+            layout,
+            log,
+            &CheckpointMetrics::new(&MetricsRegistry::new()),
+            &mut scoped_threadpool::Pool::new(1),
+        )
+        .unwrap();
+        let path = layout.checkpoint(HEIGHT).unwrap().raw_path().to_path_buf();
+
+        let manifest = compute_checkpoint_manifest(path.clone()).unwrap();
+        let mut states_metadata = pb_metadata::StatesMetadata::default();
+        states_metadata.by_height.insert(
+            HEIGHT.get(),
+            pb_metadata::StateMetadata {
+                manifest: Some(manifest.into()),
+            },
+        );
+        fs::write(layout.states_metadata(), states_metadata.encode_to_vec()).unwrap();
+        path
+    }
+
+    // Appends a byte to the `canister.pbuf` file of the canister in the
+    // checkpoint, returning the path of the file relative to the checkpoint.
+    fn corrupt_canister_file(layout: &StateLayout) -> PathBuf {
+        let checkpoint = layout.checkpoint(HEIGHT).unwrap();
+        let file = checkpoint
+            .canister(&canister_test_id(10))
+            .unwrap()
+            .canister()
+            .raw_path()
+            .to_path_buf();
+        let mut permissions = fs::metadata(&file).unwrap().permissions();
+        permissions.set_readonly(false);
+        fs::set_permissions(&file, permissions).unwrap();
+        OpenOptions::new()
+            .append(true)
+            .open(&file)
+            .unwrap()
+            .write_all(&[0])
+            .unwrap();
+        file.strip_prefix(checkpoint.raw_path())
+            .unwrap()
+            .to_path_buf()
+    }
+
+    #[test]
+    fn valid_checkpoint_passes() {
+        with_test_replica_logger(|log| {
+            let tmp = tempfile::tempdir().unwrap();
+            let layout = StateLayout::new(log.clone(), tmp.path().to_path_buf());
+            let path = write_checkpoint(&layout, &log);
+            let root_hash = hex::encode(manifest_hash(
+                &compute_checkpoint_manifest(path.clone()).unwrap(),
+            ));
+
+            do_verify(path.clone(), None, None).unwrap();
+            do_verify(path, None, Some(root_hash)).unwrap();
+        });
+    }
+
+    #[test]
+    fn corrupted_file_is_reported() {
+        with_test_replica_logger(|log| {
+            let tmp = tempfile::tempdir().unwrap();
+            let layout = StateLayout::new(log.clone(), tmp.path().to_path_buf());
+            let path = write_checkpoint(&layout, &log);
+            let stored_manifest = load_stored_manifest(&path).unwrap().unwrap();
+
+            let corrupted_file = corrupt_canister_file(&layout);
+
+            let manifest = compute_checkpoint_manifest(path.clone()).unwrap();
+            let mismatches = diff_manifests(&stored_manifest, &manifest);
+            assert!(
+                mismatches.contains(&format!("{}: file hash mismatch", corrupted_file.display()))
+            );
+            assert!(do_verify(path, None, None).is_err());
+        });
+    }
+
+    #[test]
+    fn root_hash_mismatch_is_reported() {
+        with_test_replica_logger(|log| {
+            let tmp = tempfile::tempdir().unwrap();
+            let layout = StateLayout::new(log.clone(), tmp.path().to_path_buf());
+            let path = write_checkpoint(&layout, &log);
+            fs::remove_file(layout.states_metadata()).unwrap();
+
+            assert!(do_verify(path.clone(), None, Some(hex::encode([0u8; 32]))).is_err());
+            assert!(do_verify(path, None, None).is_err());
+        });
+    }
+}
@@ -2,9 +2,11 @@
 //!
 //! A command-line tool to manage Internet Computer replicated states (decode
 //! persisted state files, diff checkpoints, compute partial state hashes and
-//! checkpoint manifests, import state trees).
+//! checkpoint manifests, import state trees, extract canisters, verify
+//! checkpoints).
 
 use clap::Parser;
+use ic_types::CanisterId;
 use std::path::PathBuf;
 
 mod commands;
@@ -64,6 +66,39 @@ enum Opt {
         #[clap(long = "file")]
         file: PathBuf,
     },
+
+    /// Verifies the integrity of a checkpoint by recomputing its manifest.
+    #[clap(name = "verify")]
+    Verify {
+        /// Path to a checkpoint.
+        #[clap(long = "state")]
+        path: PathBuf,
+
+        /// Path to a catch-up package to compare the root hash with.
+        #[clap(long = "cup")]
+        cup: Option<PathBuf>,
+
+        /// Hex-encoded root hash to compare with.
+        #[clap(long = "root-hash")]
+        root_hash: Option<String>,
+    },
+
+    /// Extracts the module, memories and metadata of a canister from a
+    /// checkpoint.
+    #[clap(name = "extract-canister")]
+    ExtractCanister {
+        /// Path to a checkpoint.
+        #[clap(long = "state")]
+        path: PathBuf,
+
+        /// The ID of the canister to extract.
+        #[clap(long = "canister")]
+        canister_id: CanisterId,
+
+        /// The directory to write the canister's state to.
+        #[clap(long = "output", default_value = ".")]
+        output: PathBuf,
+    },
 }
 
 fn main() {
@@ -79,6 +114,16 @@ fn main() {
         Opt::Manifest { path } => commands::manifest::do_compute_manifest(path),
         Opt::ListStates { config } => commands::list::do_list(config),
         Opt::Decode { file } => commands::decode::do_decode(file),
+        Opt::Verify {
+            path,
+            cup,
+            root_hash,
+        } => commands::verify::do_verify(path, cup, root_hash),
+        Opt::ExtractCanister {
+            path,
+            canister_id,
+            output,
+        } => commands::extract_canister::do_extract_canister(path, canister_id, output),
     };
 
     if let Err(e) = result {
@@ -16,12 +16,15 @@ use ic_interfaces::execution_environment::{
 };
 use ic_logger::{error, ReplicaLogger};
 use ic_registry_subnet_type::SubnetType;
-use ic_replicated_state::{memory_required_to_push_request, Memory, NumWasmPages, PageIndex};
+use ic_replicated_state::{
+    memory_required_to_push_request, CanisterTimer, Memory, NumWasmPages, PageIndex,
+};
 use ic_sys::PageBytes;
 use ic_types::{
+    canister_log::{CanisterLog, MAX_CANISTER_LOG_BUFFER_SIZE},
     ingress::WasmResult,
     messages::{CallContextId, RejectContext, Request, MAX_INTER_CANISTER_PAYLOAD_IN_BYTES},
-    methods::{Callback, WasmClosure},
+    methods::{Callback, SystemMethod, WasmClosure},
     CanisterId, ComputeAllocation, Cycles, NumBytes, NumInstructions, PrincipalId, SubnetId, Time,
 };
 use ic_utils::deterministic_operations::deterministic_copy_from_slice;
@@ -301,8 +304,10 @@ pub enum ApiType {
         message_accepted: bool,
     },
 
-    // For executing the `canister_heartbeat` method
-    Heartbeat {
+    // For executing system tasks: the `canister_heartbeat` and
+    // `canister_global_timer` methods.
+    SystemTask {
+        system_task: SystemMethod,
         time: Time,
         call_context_id: CallContextId,
         /// Optional outgoing request under construction. If `None` no outgoing
@@ -334,8 +339,13 @@ impl ApiType {
         }
     }
 
-    pub fn heartbeat(time: Time, call_context_id: CallContextId) -> Self {
-        Self::Heartbeat {
+    pub fn system_task(
+        system_task: SystemMethod,
+        time: Time,
+        call_context_id: CallContextId,
+    ) -> Self {
+        Self::SystemTask {
+            system_task,
             time,
             call_context_id,
             outgoing_request: None,
@@ -493,7 +503,7 @@ impl ApiType {
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. }
             | ApiType::PreUpgrade { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. } => ModificationTracking::Track,
         }
     }
@@ -504,7 +514,10 @@ impl ApiType {
         match self {
             ApiType::Start { .. } => "start",
             ApiType::Init { .. } => "init",
-            ApiType::Heartbeat { .. } => "heartbeat",
+            ApiType::SystemTask { system_task, .. } => match system_task {
+                SystemMethod::CanisterGlobalTimer => "global timer",
+                _ => "heartbeat",
+            },
             ApiType::Update { .. } => "update",
             ApiType::ReplicatedQuery { .. } => "replicated query",
             ApiType::NonReplicatedQuery { .. } => "non replicated query",
@@ -716,6 +729,10 @@ pub struct SystemApiImpl {
 
     /// Tracks the total execution complexity.
     total_execution_complexity: ExecutionComplexity,
+
+    /// The log records produced by the current message. They are appended
+    /// to the canister log even if the message fails.
+    canister_log: CanisterLog,
 }
 
 impl SystemApiImpl {
@@ -751,7 +768,38 @@ impl SystemApiImpl {
             current_slice_instruction_limit: i64::try_from(slice_limit).unwrap_or(i64::MAX),
             instructions_executed_before_current_slice: 0,
             total_execution_complexity: ExecutionComplexity::new(),
+            canister_log: CanisterLog::default(),
+        }
+    }
+
+    /// Returns an error if the Wasm memory of the given size exceeds the
+    /// `wasm_memory_limit` of the canister. The limit is enforced only for
+    /// messages that can be retried safely, so that queries, upgrades, and
+    /// cleanup callbacks keep working even if the limit has been reached.
+    fn check_wasm_memory_limit(&self, wasm_memory_size: NumWasmPages) -> HypervisorResult<()> {
+        let limit = match self.sandbox_safe_system_state.wasm_memory_limit {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        match self.api_type {
+            ApiType::Update { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. } => {}
+            ApiType::Start { .. }
+            | ApiType::Init { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::InspectMessage { .. } => return Ok(()),
         }
+        let bytes = ic_replicated_state::num_bytes_try_from(wasm_memory_size)
+            .map_err(|_| HypervisorError::OutOfMemory)?;
+        if bytes > limit {
+            return Err(HypervisorError::WasmMemoryLimitExceeded { bytes, limit });
+        }
+        Ok(())
     }
 
     /// Gets the result of execution, assuming there is no error from
@@ -765,7 +813,7 @@ impl SystemApiImpl {
         match &mut self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::PreUpgrade { .. }
@@ -802,7 +850,7 @@ impl SystemApiImpl {
             | ApiType::Init { .. }
             | ApiType::PreUpgrade { .. }
             | ApiType::Cleanup { .. }
-            | ApiType::Heartbeat { .. } => Ok(None),
+            | ApiType::SystemTask { .. } => Ok(None),
             ApiType::InspectMessage {
                 message_accepted, ..
             } => {
@@ -859,7 +907,7 @@ impl SystemApiImpl {
     fn get_msg_caller_id(&self, method_name: &str) -> Result<PrincipalId, HypervisorError> {
         match &self.api_type {
             ApiType::Start { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. } => Err(self.error_for(method_name)),
@@ -877,7 +925,7 @@ impl SystemApiImpl {
             ApiType::Start { .. }
             | ApiType::Init { .. }
             | ApiType::PreUpgrade { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::InspectMessage { .. } => None,
             ApiType::Update {
@@ -917,7 +965,7 @@ impl SystemApiImpl {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
@@ -933,7 +981,7 @@ impl SystemApiImpl {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
@@ -961,7 +1009,7 @@ impl SystemApiImpl {
             ApiType::Update {
                 outgoing_request, ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 outgoing_request, ..
             }
             | ApiType::ReplyCallback {
@@ -992,7 +1040,7 @@ impl SystemApiImpl {
         match &self.api_type {
             ApiType::Start {} => Err(self.error_for(method_name)),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1011,7 +1059,7 @@ impl SystemApiImpl {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::PreUpgrade { .. }
@@ -1035,7 +1083,7 @@ impl SystemApiImpl {
         match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
@@ -1067,7 +1115,7 @@ impl SystemApiImpl {
             } => Ok(Cycles::new(0)),
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::PreUpgrade { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1095,6 +1143,29 @@ impl SystemApiImpl {
         self.sandbox_safe_system_state.take_changes()
     }
 
+    /// Returns the log records produced so far, adding a record with the
+    /// message of the given execution error if it is a trap.
+    pub fn take_canister_log(&mut self, error: Option<&HypervisorError>) -> CanisterLog {
+        let trap_message = match error {
+            Some(HypervisorError::CalledTrap(message)) => Some(message.clone()),
+            Some(HypervisorError::Trapped(trap_code)) => Some(trap_code.to_string()),
+            _ => None,
+        };
+        if let Some(trap_message) = trap_message {
+            let content = format!("[TRAP]: {}", trap_message).into_bytes();
+            self.canister_log
+                .add_record(self.log_timestamp_nanos(), content);
+        }
+        std::mem::take(&mut self.canister_log)
+    }
+
+    // The time of the current message, used as the timestamp of its log
+    // records. `canister_start` runs without a time, so its records get 0.
+    fn log_timestamp_nanos(&self) -> u64 {
+        self.ic0_time()
+            .map_or(0, |time| time.as_nanos_since_unix_epoch())
+    }
+
     pub fn stable_memory_size(&self) -> NumWasmPages {
         self.stable_memory.stable_memory_size
     }
@@ -1250,7 +1321,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Cleanup { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::RejectCallback { .. }
             | ApiType::PreUpgrade { .. } => Err(self.error_for("ic0_msg_arg_data_size")),
             ApiType::Init {
@@ -1285,7 +1356,7 @@ impl SystemApi for SystemApiImpl {
     ) -> HypervisorResult<()> {
         let result = match &self.api_type {
             ApiType::Start { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::RejectCallback { .. }
             | ApiType::PreUpgrade { .. } => Err(self.error_for("ic0_msg_arg_data_copy")),
@@ -1339,7 +1410,7 @@ impl SystemApi for SystemApiImpl {
             | ApiType::Cleanup { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::Init { .. } => Err(self.error_for("ic0_msg_method_name_size")),
@@ -1363,7 +1434,7 @@ impl SystemApi for SystemApiImpl {
             | ApiType::PreUpgrade { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::Init { .. } => Err(self.error_for("ic0_msg_method_name_copy")),
@@ -1400,7 +1471,7 @@ impl SystemApi for SystemApiImpl {
             | ApiType::Cleanup { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::ReplicatedQuery { .. }
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::Init { .. } => Err(self.error_for("ic0_accept_message")),
@@ -1572,7 +1643,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_canister_self_size")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::Update { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1601,7 +1672,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_canister_self_copy")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Cleanup { .. }
             | ApiType::Update { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1635,7 +1706,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_controller_size")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1661,7 +1732,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_controller_copy")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -1718,7 +1789,7 @@ impl SystemApi for SystemApiImpl {
                     },
                 ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 call_context_id, ..
             }
             | ApiType::ReplyCallback {
@@ -1830,7 +1901,7 @@ impl SystemApi for SystemApiImpl {
                     },
                 ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 outgoing_request, ..
             }
             | ApiType::ReplyCallback {
@@ -1899,7 +1970,7 @@ impl SystemApi for SystemApiImpl {
                     },
                 ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 outgoing_request, ..
             }
             | ApiType::ReplyCallback {
@@ -1946,7 +2017,7 @@ impl SystemApi for SystemApiImpl {
                     },
                 ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 outgoing_request, ..
             }
             | ApiType::ReplyCallback {
@@ -2003,7 +2074,7 @@ impl SystemApi for SystemApiImpl {
                 outgoing_request,
                 ..
             }
-            | ApiType::Heartbeat {
+            | ApiType::SystemTask {
                 call_context_id,
                 outgoing_request,
                 ..
@@ -2065,7 +2136,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable_size")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2083,7 +2154,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable_grow")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2121,7 +2192,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable_read")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2155,7 +2226,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable_write")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2183,7 +2254,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable64_size")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2201,7 +2272,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable64_grow")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2239,7 +2310,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable64_read")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2273,7 +2344,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start {} => Err(self.error_for("ic0_stable64_write")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::Cleanup { .. }
             | ApiType::ReplicatedQuery { .. }
@@ -2301,7 +2372,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_time")),
             ApiType::Init { time, .. }
-            | ApiType::Heartbeat { time, .. }
+            | ApiType::SystemTask { time, .. }
             | ApiType::Update { time, .. }
             | ApiType::Cleanup { time, .. }
             | ApiType::NonReplicatedQuery { time, .. }
@@ -2315,6 +2386,27 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
+    fn ic0_global_timer_set(&mut self, time: Time) -> HypervisorResult<Time> {
+        let result = match &self.api_type {
+            ApiType::Start { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::InspectMessage { .. } => Err(self.error_for("ic0_global_timer_set")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. } => Ok(self
+                .sandbox_safe_system_state
+                .set_global_timer(CanisterTimer::from_time(time))
+                .to_time()),
+        };
+        trace_syscall!(self, ic0_global_timer_set, result, time);
+        result
+    }
+
     fn ic0_performance_counter(
         &self,
         performance_counter_type: PerformanceCounterType,
@@ -2354,6 +2446,9 @@ impl SystemApi for SystemApiImpl {
             if native_memory_grow_res == -1 {
                 return Ok(-1);
             }
+            // A successful `memory.grow` returns the previous size in pages.
+            let new_wasm_memory_pages = native_memory_grow_res as usize + additional_pages as usize;
+            self.check_wasm_memory_limit(NumWasmPages::from(new_wasm_memory_pages))?;
             match self.memory_usage.allocate_pages(additional_pages as usize) {
                 Ok(()) => Ok(native_memory_grow_res),
                 Err(_err) => Err(HypervisorError::OutOfMemory),
@@ -2500,7 +2595,7 @@ impl SystemApi for SystemApiImpl {
             | ApiType::PreUpgrade { .. }
             | ApiType::InspectMessage { .. }
             | ApiType::Update { .. }
-            | ApiType::Heartbeat { .. } => Ok(0),
+            | ApiType::SystemTask { .. } => Ok(0),
             ApiType::ReplicatedQuery {
                 data_certificate, ..
             }
@@ -2519,7 +2614,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. }
@@ -2550,7 +2645,7 @@ impl SystemApi for SystemApiImpl {
         let result = match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. }
@@ -2619,7 +2714,7 @@ impl SystemApi for SystemApiImpl {
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::InspectMessage { .. } => Err(self.error_for("ic0_certified_data_set")),
             ApiType::Init { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. }
@@ -2670,7 +2765,7 @@ impl SystemApi for SystemApiImpl {
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::Init { .. }
             | ApiType::Cleanup { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::Update { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. }
@@ -2685,6 +2780,51 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
+    fn ic0_canister_version(&self) -> HypervisorResult<u64> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_canister_version")),
+            ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::Init { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => Ok(self.sandbox_safe_system_state.canister_version),
+        };
+        trace_syscall!(self, ic0_canister_version, result);
+        result
+    }
+
+    fn ic0_is_controller(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<u32> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_is_controller")),
+            ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::Init { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                let bytes = valid_subslice("ic0.is_controller", src, size, heap)?;
+                PrincipalId::try_from(bytes)
+                    .map_err(HypervisorError::InvalidPrincipalId)
+                    .map(|principal_id| {
+                        self.sandbox_safe_system_state
+                            .controllers
+                            .contains(&principal_id) as u32
+                    })
+            }
+        };
+        trace_syscall!(self, ic0_is_controller, result, src, size);
+        result
+    }
+
     fn ic0_mint_cycles(&mut self, amount: u64) -> HypervisorResult<u64> {
         let result = match self.api_type {
             ApiType::Start { .. }
@@ -2695,7 +2835,7 @@ impl SystemApi for SystemApiImpl {
             | ApiType::NonReplicatedQuery { .. }
             | ApiType::InspectMessage { .. } => Err(self.error_for("ic0_mint_cycles")),
             ApiType::Update { .. }
-            | ApiType::Heartbeat { .. }
+            | ApiType::SystemTask { .. }
             | ApiType::ReplyCallback { .. }
             | ApiType::RejectCallback { .. } => {
                 self.sandbox_safe_system_state
@@ -2727,6 +2867,17 @@ impl SystemApi for SystemApiImpl {
         Ok(())
     }
 
+    fn save_log_message(&mut self, src: u32, size: u32, heap: &[u8]) {
+        let size = size.min(MAX_CANISTER_LOG_BUFFER_SIZE as u32);
+        let content = match valid_subslice("save_log_message", src, size, heap) {
+            Ok(bytes) => bytes.to_vec(),
+            // Like `ic0.debug_print`, logging never traps.
+            Err(_) => b"(debug message out of memory bounds)".to_vec(),
+        };
+        let timestamp_nanos = self.log_timestamp_nanos();
+        self.canister_log.add_record(timestamp_nanos, content);
+    }
+
     fn ic0_trap(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<()> {
         const MAX_ERROR_MESSAGE_SIZE: u32 = 16 * 1024;
         let size = size.min(MAX_ERROR_MESSAGE_SIZE);
@@ -3,9 +3,10 @@ use std::str::FromStr;
 use candid::Decode;
 use ic_base_types::{CanisterId, SubnetId};
 use ic_ic00_types::{
-    CanisterIdRecord, ComputeInitialEcdsaDealingsArgs, ECDSAPublicKeyArgs, EcdsaKeyId,
-    InstallCodeArgs, Method as Ic00Method, Payload, ProvisionalTopUpCanisterArgs,
-    SetControllerArgs, SignWithECDSAArgs, UpdateSettingsArgs,
+    CanisterIdRecord, CanisterInfoRequest, CanisterSnapshotArgs, ComputeInitialEcdsaDealingsArgs,
+    ECDSAPublicKeyArgs, EcdsaKeyId, InstallChunkedCodeArgs, InstallCodeArgs, Method as Ic00Method,
+    Payload, ProvisionalTopUpCanisterArgs, SetControllerArgs, SignWithECDSAArgs,
+    TakeCanisterSnapshotArgs, UpdateSettingsArgs, UploadChunkArgs,
 };
 use ic_replicated_state::NetworkTopology;
 
@@ -81,6 +82,10 @@ pub(super) fn resolve_destination(
         | Ok(Ic00Method::StopCanister)
         | Ok(Ic00Method::DeleteCanister)
         | Ok(Ic00Method::UninstallCode)
+        | Ok(Ic00Method::ListCanisterSnapshots)
+        | Ok(Ic00Method::FetchCanisterLogs)
+        | Ok(Ic00Method::ClearChunkStore)
+        | Ok(Ic00Method::StoredChunks)
         | Ok(Ic00Method::DepositCycles) => {
             let args = Decode!(payload, CanisterIdRecord)?;
             let canister_id = args.get_canister_id();
@@ -91,6 +96,62 @@ pub(super) fn resolve_destination(
                     ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
                 })
         }
+        Ok(Ic00Method::CanisterInfo) => {
+            let args = CanisterInfoRequest::decode(payload)?;
+            let canister_id = args.canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(canister_id, Ic00Method::CanisterInfo)
+                })
+        }
+        Ok(Ic00Method::TakeCanisterSnapshot) => {
+            let args = TakeCanisterSnapshotArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(
+                        canister_id,
+                        Ic00Method::TakeCanisterSnapshot,
+                    )
+                })
+        }
+        Ok(Ic00Method::LoadCanisterSnapshot) | Ok(Ic00Method::DeleteCanisterSnapshot) => {
+            let args = CanisterSnapshotArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .ok_or_else(|| {
+                    ResolveDestinationError::SubnetNotFound(canister_id, method.unwrap())
+                })
+        }
+        Ok(Ic00Method::UploadChunk) => {
+            let args = UploadChunkArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(canister_id, Ic00Method::UploadChunk)
+                })
+        }
+        Ok(Ic00Method::InstallChunkedCode) => {
+            let args = InstallChunkedCodeArgs::decode(payload)?;
+            let canister_id = args.get_canister_id();
+            network_topology
+                .routing_table
+                .route(canister_id.get())
+                .ok_or({
+                    ResolveDestinationError::SubnetNotFound(
+                        canister_id,
+                        Ic00Method::InstallChunkedCode,
+                    )
+                })
+        }
         Ok(Ic00Method::ProvisionalTopUpCanister) => {
             let args = ProvisionalTopUpCanisterArgs::decode(payload)?;
             let canister_id = args.get_canister_id();
@@ -107,7 +168,10 @@ pub(super) fn resolve_destination(
         Ok(Ic00Method::BitcoinGetBalance)
         | Ok(Ic00Method::BitcoinGetUtxos)
         | Ok(Ic00Method::BitcoinSendTransaction)
-        | Ok(Ic00Method::BitcoinGetCurrentFeePercentiles) => {
+        | Ok(Ic00Method::BitcoinGetCurrentFeePercentiles)
+        | Ok(Ic00Method::BitcoinGetBlockHeaders)
+        | Ok(Ic00Method::BitcoinGetBlockchainInfo)
+        | Ok(Ic00Method::BitcoinGetTransactions) => {
             // TODO(EXC-939): Route requests across all the bitcoin subnets, not only
             // the first subnet.
             Ok(*network_topology
@@ -9,7 +9,8 @@ use ic_logger::{info, ReplicaLogger};
 use ic_nns_constants::CYCLES_MINTING_CANISTER_ID;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    canister_state::DEFAULT_QUEUE_CAPACITY, CanisterStatus, NetworkTopology, SystemState,
+    canister_state::DEFAULT_QUEUE_CAPACITY, CanisterStatus, CanisterTimer, NetworkTopology,
+    SystemState,
 };
 use ic_types::{
     messages::{CallContextId, CallbackId, Request},
@@ -55,6 +56,7 @@ pub struct SystemStateChanges {
     call_context_balance_taken: BTreeMap<CallContextId, Cycles>,
     request_slots_used: BTreeMap<CanisterId, usize>,
     requests: Vec<Request>,
+    pub(super) new_global_timer: Option<CanisterTimer>,
 }
 
 impl Default for SystemStateChanges {
@@ -67,6 +69,7 @@ impl Default for SystemStateChanges {
             call_context_balance_taken: BTreeMap::new(),
             request_slots_used: BTreeMap::new(),
             requests: vec![],
+            new_global_timer: None,
         }
     }
 }
@@ -189,6 +192,11 @@ impl SystemStateChanges {
             assert!(certified_data.len() <= CERTIFIED_DATA_MAX_LENGTH as usize);
             system_state.certified_data = certified_data.clone();
         }
+
+        // Update the global timer.
+        if let Some(new_global_timer) = self.new_global_timer {
+            system_state.global_timer = new_global_timer;
+        }
     }
 }
 
@@ -217,6 +225,10 @@ pub struct SandboxSafeSystemState {
     available_request_slots: BTreeMap<CanisterId, usize>,
     ic00_available_request_slots: usize,
     ic00_aliases: BTreeSet<CanisterId>,
+    global_timer: CanisterTimer,
+    pub(super) canister_version: u64,
+    pub(super) controllers: BTreeSet<PrincipalId>,
+    pub(super) wasm_memory_limit: Option<NumBytes>,
 }
 
 impl SandboxSafeSystemState {
@@ -237,6 +249,10 @@ impl SandboxSafeSystemState {
         ic00_available_request_slots: usize,
         ic00_aliases: BTreeSet<CanisterId>,
         subnet_size: usize,
+        global_timer: CanisterTimer,
+        canister_version: u64,
+        controllers: BTreeSet<PrincipalId>,
+        wasm_memory_limit: Option<NumBytes>,
     ) -> Self {
         Self {
             canister_id,
@@ -254,6 +270,10 @@ impl SandboxSafeSystemState {
             available_request_slots,
             ic00_available_request_slots,
             ic00_aliases,
+            global_timer,
+            canister_version,
+            controllers,
+            wasm_memory_limit,
         }
     }
 
@@ -310,6 +330,10 @@ impl SandboxSafeSystemState {
             ic00_available_request_slots,
             ic00_aliases,
             subnet_size,
+            system_state.global_timer,
+            system_state.canister_version,
+            system_state.controllers.clone(),
+            system_state.wasm_memory_limit,
         )
     }
 
@@ -388,6 +412,16 @@ impl SandboxSafeSystemState {
         self.update_balance_change(new_balance);
     }
 
+    /// Sets the global timer and returns its previous value.
+    pub(super) fn set_global_timer(&mut self, timer: CanisterTimer) -> CanisterTimer {
+        let old_timer = self
+            .system_state_changes
+            .new_global_timer
+            .unwrap_or(self.global_timer);
+        self.system_state_changes.new_global_timer = Some(timer);
+        old_timer
+    }
+
     pub(super) fn mint_cycles(&mut self, amount_to_mint: Cycles) -> HypervisorResult<()> {
         let mut new_balance = self.cycles_balance();
         let result = self
@@ -8,8 +8,8 @@ use ic_interfaces::execution_environment::{
 use ic_logger::replica_logger::no_op_logger;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    testing::CanisterQueuesTesting, CallOrigin, Memory, NetworkTopology, NumWasmPages, PageMap,
-    SystemState,
+    testing::CanisterQueuesTesting, CallOrigin, CanisterTimer, Memory, NetworkTopology,
+    NumWasmPages, PageMap, SystemState,
 };
 use ic_system_api::{
     sandbox_safe_system_state::SandboxSafeSystemState, ApiType, DefaultOutOfInstructionsHandler,
@@ -107,6 +107,9 @@ fn test_canister_init_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -170,6 +173,9 @@ fn test_canister_update_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -233,6 +239,9 @@ fn test_canister_replicated_query_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_not_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -296,6 +305,9 @@ fn test_canister_pure_query_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_not_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -369,6 +381,9 @@ fn test_canister_stateful_query_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_not_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -432,6 +447,9 @@ fn test_reply_api_support_on_nns() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -495,6 +513,9 @@ fn test_reply_api_support_non_nns() {
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -561,6 +582,9 @@ fn test_reject_api_support_on_nns() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -627,6 +651,9 @@ fn test_reject_api_support_non_nns() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -690,6 +717,9 @@ fn test_pre_upgrade_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -753,6 +783,9 @@ fn test_start_support() {
     assert_api_not_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_not_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_not_supported(api.ic0_time());
+    assert_api_not_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_not_supported(api.ic0_canister_version());
+    assert_api_not_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -816,6 +849,9 @@ fn test_cleanup_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -884,6 +920,9 @@ fn test_inspect_message_support() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_not_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -948,6 +987,9 @@ fn test_canister_heartbeat_support() {
     assert_api_supported(api.ic0_stable64_grow(1));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -1011,6 +1053,9 @@ fn test_canister_heartbeat_support_nns() {
     assert_api_supported(api.ic0_stable64_read(0, 0, 0, &mut []));
     assert_api_supported(api.ic0_stable64_write(0, 0, 0, &[]));
     assert_api_supported(api.ic0_time());
+    assert_api_supported(api.ic0_global_timer_set(mock_time()));
+    assert_api_supported(api.ic0_canister_version());
+    assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(
         api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
     );
@@ -1317,6 +1362,44 @@ fn certified_data_set() {
     assert_eq!(system_state.certified_data, vec![10; 32])
 }
 
+#[test]
+fn global_timer_set_returns_previous_value() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let mut system_state = SystemStateBuilder::default().build();
+    system_state.global_timer = CanisterTimer::Active(Time::from_nanos_since_unix_epoch(1_000));
+    let mut api = get_system_api(
+        ApiTypeBuilder::build_global_timer_api(),
+        &system_state,
+        cycles_account_manager,
+    );
+
+    assert_eq!(
+        api.ic0_global_timer_set(Time::from_nanos_since_unix_epoch(2_000)),
+        Ok(Time::from_nanos_since_unix_epoch(1_000))
+    );
+    assert_eq!(
+        api.ic0_global_timer_set(Time::from_nanos_since_unix_epoch(0)),
+        Ok(Time::from_nanos_since_unix_epoch(2_000))
+    );
+    assert_eq!(
+        api.ic0_global_timer_set(Time::from_nanos_since_unix_epoch(3_000)),
+        Ok(Time::from_nanos_since_unix_epoch(0))
+    );
+
+    let system_state_changes = api.into_system_state_changes();
+    system_state_changes.apply_changes(
+        mock_time(),
+        &mut system_state,
+        &default_network_topology(),
+        subnet_test_id(1),
+        &no_op_logger(),
+    );
+    assert_eq!(
+        system_state.global_timer,
+        CanisterTimer::Active(Time::from_nanos_since_unix_epoch(3_000))
+    );
+}
+
 #[test]
 fn data_certificate_copy() {
     let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
@@ -16,7 +16,7 @@ use ic_execution_environment::{
     as_num_instructions, execute_canister, util::process_stopping_canisters,
     CanisterHeartbeatError, CompilationCostHandling, ExecuteMessageResult, ExecutionEnvironment,
     ExecutionResponse, Hypervisor, IngressHistoryWriterImpl, InternalHttpQueryHandler,
-    RoundInstructions, RoundLimits,
+    QueryStatsCollector, RoundInstructions, RoundLimits,
 };
 use ic_ic00_types::{
     CanisterIdRecord, CanisterInstallMode, CanisterSettingsArgs, CanisterStatusType, EcdsaKeyId,
@@ -49,6 +49,7 @@ use ic_types::Time;
 use ic_types::{
     ingress::{IngressState, IngressStatus, WasmResult},
     messages::{AnonymousQuery, CallbackId, MessageId, RequestOrResponse, Response, UserQuery},
+    methods::SystemMethod,
     CanisterId, Cycles, NumInstructions, UserId,
 };
 use ic_types_test_utils::ids::{subnet_test_id, user_test_id};
@@ -586,6 +587,19 @@ impl ExecutionTest {
 
     /// Executes the heartbeat method of the given canister.
     pub fn heartbeat(&mut self, canister_id: CanisterId) -> Result<(), CanisterHeartbeatError> {
+        self.system_task(SystemMethod::CanisterHeartbeat, canister_id)
+    }
+
+    /// Executes the global timer method of the given canister.
+    pub fn global_timer(&mut self, canister_id: CanisterId) -> Result<(), CanisterHeartbeatError> {
+        self.system_task(SystemMethod::CanisterGlobalTimer, canister_id)
+    }
+
+    fn system_task(
+        &mut self,
+        system_task: SystemMethod,
+        canister_id: CanisterId,
+    ) -> Result<(), CanisterHeartbeatError> {
         let mut state = self.state.take().unwrap();
         let canister = state.take_canister_state(&canister_id).unwrap();
         let network_topology = Arc::new(state.metadata.network_topology.clone());
@@ -594,7 +608,8 @@ impl ExecutionTest {
             subnet_available_memory: self.subnet_available_memory.get().into(),
         };
         let instructions_before = round_limits.instructions;
-        let (canister, result) = self.exec_env.execute_canister_heartbeat(
+        let (canister, result) = self.exec_env.execute_canister_system_task(
+            system_task,
             canister,
             self.instruction_limits.clone(),
             network_topology,
@@ -1108,6 +1123,8 @@ pub struct ExecutionTestBuilder {
     deterministic_time_slicing: bool,
     allocatable_compute_capacity_in_percent: usize,
     subnet_features: String,
+    max_query_call_graph_depth: usize,
+    max_query_call_graph_instructions: NumInstructions,
 }
 
 impl Default for ExecutionTestBuilder {
@@ -1116,9 +1133,8 @@ impl Default for ExecutionTestBuilder {
         let config = SubnetConfigs::default()
             .own_subnet_config(subnet_type)
             .scheduler_config;
-        let subnet_total_memory = ic_config::execution_environment::Config::default()
-            .subnet_memory_capacity
-            .get() as i64;
+        let execution_config = ic_config::execution_environment::Config::default();
+        let subnet_total_memory = execution_config.subnet_memory_capacity.get() as i64;
         Self {
             nns_subnet_id: subnet_test_id(2),
             own_subnet_id: subnet_test_id(1),
@@ -1140,6 +1156,8 @@ impl Default for ExecutionTestBuilder {
             deterministic_time_slicing: false,
             allocatable_compute_capacity_in_percent: 100,
             subnet_features: String::default(),
+            max_query_call_graph_depth: execution_config.max_query_call_graph_depth,
+            max_query_call_graph_instructions: execution_config.max_query_call_graph_instructions,
         }
     }
 }
@@ -1288,6 +1306,25 @@ impl ExecutionTestBuilder {
         }
     }
 
+    pub fn with_max_query_call_graph_depth(self, max_query_call_graph_depth: usize) -> Self {
+        Self {
+            max_query_call_graph_depth,
+            ..self
+        }
+    }
+
+    pub fn with_max_query_call_graph_instructions(
+        self,
+        max_query_call_graph_instructions: u64,
+    ) -> Self {
+        Self {
+            max_query_call_graph_instructions: NumInstructions::from(
+                max_query_call_graph_instructions,
+            ),
+            ..self
+        }
+    }
+
     pub fn with_provisional_whitelist_all(mut self) -> Self {
         self.registry_settings.provisional_whitelist = ProvisionalWhitelist::All;
         self
@@ -1394,6 +1431,8 @@ impl ExecutionTestBuilder {
             rate_limiting_of_instructions,
             deterministic_time_slicing,
             allocatable_compute_capacity_in_percent: self.allocatable_compute_capacity_in_percent,
+            max_query_call_graph_depth: self.max_query_call_graph_depth,
+            max_query_call_graph_instructions: self.max_query_call_graph_instructions,
             ..Config::default()
         };
         let hypervisor = Hypervisor::new(
@@ -1417,17 +1456,18 @@ impl ExecutionTestBuilder {
             self.own_subnet_id,
             self.subnet_type,
             1,
-            config,
+            config.clone(),
             Arc::clone(&cycles_account_manager),
         );
         let query_handler = InternalHttpQueryHandler::new(
             self.log,
             hypervisor,
             self.subnet_type,
-            Config::default(),
+            config,
             &metrics_registry,
             self.instruction_limit,
             Arc::clone(&cycles_account_manager),
+            Arc::new(QueryStatsCollector::new()),
         );
         ExecutionTest {
             state: Some(state),
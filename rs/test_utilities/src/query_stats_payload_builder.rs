@@ -0,0 +1,40 @@
+use ic_interfaces::query_stats::{QueryStatsPayloadBuilder, QueryStatsPayloadValidationError};
+use ic_types::{
+    batch::{QueryStatsPayload, ValidationContext},
+    CountBytes, NodeId, NumBytes,
+};
+
+#[derive(Default)]
+pub struct FakeQueryStatsPayloadBuilder(Option<QueryStatsPayload>);
+
+impl FakeQueryStatsPayloadBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_payload(mut self, payload: QueryStatsPayload) -> Self {
+        self.0 = Some(payload);
+        self
+    }
+}
+
+impl QueryStatsPayloadBuilder for FakeQueryStatsPayloadBuilder {
+    fn get_query_stats_payload(
+        &self,
+        _validation_context: &ValidationContext,
+        _past_payloads: &[&QueryStatsPayload],
+        _byte_limit: NumBytes,
+    ) -> Option<QueryStatsPayload> {
+        self.0.clone()
+    }
+
+    fn validate_query_stats_payload(
+        &self,
+        payload: &QueryStatsPayload,
+        _block_maker: NodeId,
+        _validation_context: &ValidationContext,
+        _past_payloads: &[&QueryStatsPayload],
+    ) -> Result<NumBytes, QueryStatsPayloadValidationError> {
+        Ok(NumBytes::new(payload.count_bytes() as u64))
+    }
+}
@@ -70,6 +70,7 @@ impl From<ErrorCode> for RejectCode {
             CanisterWasmModuleNotFound => DestinationInvalid,
             CanisterAlreadyInstalled => DestinationInvalid,
             CanisterEmpty => DestinationInvalid,
+            CanisterSnapshotNotFound => DestinationInvalid,
             CanisterNonEmpty => CanisterError,
             CanisterOutOfCycles => CanisterError,
             CanisterTrapped => CanisterError,
@@ -97,6 +98,10 @@ impl From<ErrorCode> for RejectCode {
             CanisterWasmEngineError => CanisterError,
             CanisterInstructionLimitExceeded => CanisterError,
             CanisterInstallCodeRateLimited => SysTransient,
+            CompositeQueryCalledInReplicatedMode => CanisterError,
+            QueryCallGraphTooDeep => CanisterError,
+            QueryCallGraphTotalInstructionLimitExceeded => CanisterError,
+            CanisterSnapshotLimitExceeded => CanisterError,
         }
     }
 }
@@ -118,6 +123,7 @@ pub enum ErrorCode {
     CanisterAlreadyInstalled = 303,
     CanisterWasmModuleNotFound = 304,
     CanisterEmpty = 305,
+    CanisterSnapshotNotFound = 306,
     InsufficientTransferFunds = 401,
     InsufficientMemoryAllocation = 402,
     InsufficientCyclesForCreateCanister = 403,
@@ -145,6 +151,10 @@ pub enum ErrorCode {
     CanisterWasmEngineError = 521,
     CanisterInstructionLimitExceeded = 522,
     CanisterInstallCodeRateLimited = 523,
+    CompositeQueryCalledInReplicatedMode = 524,
+    QueryCallGraphTooDeep = 525,
+    QueryCallGraphTotalInstructionLimitExceeded = 526,
+    CanisterSnapshotLimitExceeded = 527,
 }
 
 impl TryFrom<u64> for ErrorCode {
@@ -160,6 +170,7 @@ impl TryFrom<u64> for ErrorCode {
             303 => Ok(ErrorCode::CanisterAlreadyInstalled),
             304 => Ok(ErrorCode::CanisterWasmModuleNotFound),
             305 => Ok(ErrorCode::CanisterEmpty),
+            306 => Ok(ErrorCode::CanisterSnapshotNotFound),
             401 => Ok(ErrorCode::InsufficientTransferFunds),
             402 => Ok(ErrorCode::InsufficientMemoryAllocation),
             403 => Ok(ErrorCode::InsufficientCyclesForCreateCanister),
@@ -187,6 +198,10 @@ impl TryFrom<u64> for ErrorCode {
             521 => Ok(ErrorCode::CanisterWasmEngineError),
             522 => Ok(ErrorCode::CanisterInstructionLimitExceeded),
             523 => Ok(ErrorCode::CanisterInstallCodeRateLimited),
+            524 => Ok(ErrorCode::CompositeQueryCalledInReplicatedMode),
+            525 => Ok(ErrorCode::QueryCallGraphTooDeep),
+            526 => Ok(ErrorCode::QueryCallGraphTotalInstructionLimitExceeded),
+            527 => Ok(ErrorCode::CanisterSnapshotLimitExceeded),
             _ => Err(TryFromError::ValueOutOfRange(err)),
         }
     }
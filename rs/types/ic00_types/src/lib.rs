@@ -7,7 +7,11 @@ use ic_base_types::{CanisterId, NodeId, NumBytes, PrincipalId, RegistryVersion,
 use ic_error_types::{ErrorCode, UserError};
 use ic_protobuf::registry::crypto::v1::PublicKey;
 use ic_protobuf::registry::subnet::v1::{InitialIDkgDealings, InitialNiDkgTranscriptRecord};
-use ic_protobuf::{proxy::ProxyDecodeError, registry::crypto::v1 as pb_registry_crypto};
+use ic_protobuf::state::canister_state_bits::v1 as pb_canister_state_bits;
+use ic_protobuf::{
+    proxy::{try_from_option_field, ProxyDecodeError},
+    registry::crypto::v1 as pb_registry_crypto,
+};
 use num_traits::cast::ToPrimitive;
 use serde::Serialize;
 use std::{collections::BTreeSet, convert::TryFrom, fmt, slice::Iter, str::FromStr};
@@ -40,11 +44,32 @@ pub enum Method {
     UpdateSettings,
     ComputeInitialEcdsaDealings,
 
+    // Canister snapshots.
+    TakeCanisterSnapshot,
+    LoadCanisterSnapshot,
+    ListCanisterSnapshots,
+    DeleteCanisterSnapshot,
+
+    // Canister logging.
+    FetchCanisterLogs,
+
+    // Chunked Wasm upload.
+    UploadChunk,
+    ClearChunkStore,
+    StoredChunks,
+    InstallChunkedCode,
+
+    // Canister history.
+    CanisterInfo,
+
     // Bitcoin Interface.
     BitcoinGetBalance,
     BitcoinGetUtxos,
     BitcoinSendTransaction,
     BitcoinGetCurrentFeePercentiles,
+    BitcoinGetBlockHeaders,
+    BitcoinGetBlockchainInfo,
+    BitcoinGetTransactions,
 
     // These methods are added for the Mercury I release.
     // They should be removed afterwards.
@@ -93,6 +118,7 @@ impl From<CanisterId> for CanisterIdRecord {
 ///     controller : principal;
 ///     compute_allocation: nat;
 ///     memory_allocation: opt nat;
+///     wasm_memory_limit: nat;
 /// })`
 #[derive(CandidType, Deserialize, Debug, Eq, PartialEq)]
 pub struct DefiniteCanisterSettingsArgs {
@@ -101,6 +127,7 @@ pub struct DefiniteCanisterSettingsArgs {
     compute_allocation: candid::Nat,
     memory_allocation: candid::Nat,
     freezing_threshold: candid::Nat,
+    wasm_memory_limit: candid::Nat,
 }
 
 impl DefiniteCanisterSettingsArgs {
@@ -110,6 +137,7 @@ impl DefiniteCanisterSettingsArgs {
         compute_allocation: u64,
         memory_allocation: Option<u64>,
         freezing_threshold: u64,
+        wasm_memory_limit: Option<u64>,
     ) -> Self {
         let memory_allocation = match memory_allocation {
             None => candid::Nat::from(0),
@@ -121,12 +149,21 @@ impl DefiniteCanisterSettingsArgs {
             compute_allocation: candid::Nat::from(compute_allocation),
             memory_allocation,
             freezing_threshold: candid::Nat::from(freezing_threshold),
+            wasm_memory_limit: candid::Nat::from(wasm_memory_limit.unwrap_or(0)),
         }
     }
 
     pub fn controllers(&self) -> Vec<PrincipalId> {
         self.controllers.clone()
     }
+
+    /// Returns the Wasm memory limit in bytes or `None` if there is no limit.
+    pub fn wasm_memory_limit(&self) -> Option<u64> {
+        match self.wasm_memory_limit.0.to_u64().unwrap() {
+            0 => None,
+            limit => Some(limit),
+        }
+    }
 }
 
 impl Payload<'_> for DefiniteCanisterSettingsArgs {}
@@ -196,6 +233,7 @@ impl Payload<'_> for CanisterStatusResult {}
 ///     memory_size: nat;
 ///     cycles: nat;
 ///     idle_cycles_burned_per_day: nat;
+///     query_stats: query_stats;
 /// })`
 #[derive(CandidType, Debug, Deserialize, Eq, PartialEq)]
 pub struct CanisterStatusResultV2 {
@@ -209,6 +247,54 @@ pub struct CanisterStatusResultV2 {
     balance: Vec<(Vec<u8>, candid::Nat)>,
     freezing_threshold: candid::Nat,
     idle_cycles_burned_per_day: candid::Nat,
+    query_stats: QueryStatsRecord,
+}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     num_calls_total: nat;
+///     num_instructions_total: nat;
+///     request_payload_bytes_total: nat;
+///     response_payload_bytes_total: nat;
+/// })`
+#[derive(CandidType, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct QueryStatsRecord {
+    num_calls_total: candid::Nat,
+    num_instructions_total: candid::Nat,
+    request_payload_bytes_total: candid::Nat,
+    response_payload_bytes_total: candid::Nat,
+}
+
+impl QueryStatsRecord {
+    pub fn new(
+        num_calls_total: u64,
+        num_instructions_total: u64,
+        request_payload_bytes_total: u64,
+        response_payload_bytes_total: u64,
+    ) -> Self {
+        Self {
+            num_calls_total: candid::Nat::from(num_calls_total),
+            num_instructions_total: candid::Nat::from(num_instructions_total),
+            request_payload_bytes_total: candid::Nat::from(request_payload_bytes_total),
+            response_payload_bytes_total: candid::Nat::from(response_payload_bytes_total),
+        }
+    }
+
+    pub fn num_calls_total(&self) -> u64 {
+        self.num_calls_total.0.to_u64().unwrap()
+    }
+
+    pub fn num_instructions_total(&self) -> u64 {
+        self.num_instructions_total.0.to_u64().unwrap()
+    }
+
+    pub fn request_payload_bytes_total(&self) -> u64 {
+        self.request_payload_bytes_total.0.to_u64().unwrap()
+    }
+
+    pub fn response_payload_bytes_total(&self) -> u64 {
+        self.response_payload_bytes_total.0.to_u64().unwrap()
+    }
 }
 
 impl CanisterStatusResultV2 {
@@ -224,6 +310,8 @@ impl CanisterStatusResultV2 {
         memory_allocation: Option<u64>,
         freezing_threshold: u64,
         idle_cycles_burned_per_day: u128,
+        wasm_memory_limit: Option<u64>,
+        query_stats: QueryStatsRecord,
     ) -> Self {
         Self {
             status,
@@ -240,9 +328,11 @@ impl CanisterStatusResultV2 {
                 compute_allocation,
                 memory_allocation,
                 freezing_threshold,
+                wasm_memory_limit,
             ),
             freezing_threshold: candid::Nat::from(freezing_threshold),
             idle_cycles_burned_per_day: candid::Nat::from(idle_cycles_burned_per_day),
+            query_stats,
         }
     }
 
@@ -277,6 +367,14 @@ impl CanisterStatusResultV2 {
     pub fn idle_cycles_burned_per_day(&self) -> u128 {
         self.idle_cycles_burned_per_day.0.to_u128().unwrap()
     }
+
+    pub fn wasm_memory_limit(&self) -> Option<u64> {
+        self.settings.wasm_memory_limit()
+    }
+
+    pub fn query_stats(&self) -> &QueryStatsRecord {
+        &self.query_stats
+    }
 }
 
 /// Indicates whether the canister is running, stopping, or stopped.
@@ -492,12 +590,34 @@ impl UpdateSettingsArgs {
 
 impl Payload<'_> for UpdateSettingsArgs {}
 
+/// Determines who can fetch the logs of a canister.
+/// `variant {
+///     controllers;
+///     public;
+/// }`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, CandidType)]
+pub enum LogVisibility {
+    #[serde(rename = "controllers")]
+    Controllers,
+    #[serde(rename = "public")]
+    Public,
+}
+
+impl Default for LogVisibility {
+    fn default() -> Self {
+        LogVisibility::Controllers
+    }
+}
+
 /// Struct used for encoding/decoding
 /// `(record {
 ///     controller : opt principal;
 ///     controllers: opt vec principal;
 ///     compute_allocation: opt nat;
 ///     memory_allocation: opt nat;
+///     freezing_threshold: opt nat;
+///     log_visibility: opt log_visibility;
+///     wasm_memory_limit: opt nat;
 /// })`
 #[derive(Default, Clone, CandidType, Deserialize, Debug)]
 pub struct CanisterSettingsArgs {
@@ -506,6 +626,10 @@ pub struct CanisterSettingsArgs {
     pub compute_allocation: Option<candid::Nat>,
     pub memory_allocation: Option<candid::Nat>,
     pub freezing_threshold: Option<candid::Nat>,
+    pub log_visibility: Option<LogVisibility>,
+    /// The upper bound on the Wasm heap of the canister in bytes. Zero means
+    /// no limit.
+    pub wasm_memory_limit: Option<candid::Nat>,
 }
 
 impl Payload<'_> for CanisterSettingsArgs {}
@@ -524,6 +648,8 @@ impl CanisterSettingsArgs {
             compute_allocation: compute_allocation.map(candid::Nat::from),
             memory_allocation: memory_allocation.map(candid::Nat::from),
             freezing_threshold: freezing_threshold.map(candid::Nat::from),
+            log_visibility: None,
+            wasm_memory_limit: None,
         }
     }
 }
@@ -588,6 +714,543 @@ impl SetControllerArgs {
 
 impl Payload<'_> for SetControllerArgs {}
 
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id : principal;
+///     replace_snapshot : opt blob;
+/// })`
+#[derive(CandidType, Deserialize, Debug)]
+pub struct TakeCanisterSnapshotArgs {
+    canister_id: PrincipalId,
+    replace_snapshot: Option<serde_bytes::ByteBuf>,
+}
+
+impl TakeCanisterSnapshotArgs {
+    pub fn new(canister_id: CanisterId, replace_snapshot: Option<Vec<u8>>) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            replace_snapshot: replace_snapshot.map(serde_bytes::ByteBuf::from),
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+
+    pub fn replace_snapshot(&self) -> Option<&[u8]> {
+        self.replace_snapshot.as_ref().map(|id| id.as_slice())
+    }
+}
+
+impl Payload<'_> for TakeCanisterSnapshotArgs {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id : principal;
+///     snapshot_id : blob;
+/// })`
+///
+/// Used by both `load_canister_snapshot` and `delete_canister_snapshot`.
+#[derive(CandidType, Deserialize, Debug)]
+pub struct CanisterSnapshotArgs {
+    canister_id: PrincipalId,
+    #[serde(with = "serde_bytes")]
+    snapshot_id: Vec<u8>,
+}
+
+impl CanisterSnapshotArgs {
+    pub fn new(canister_id: CanisterId, snapshot_id: Vec<u8>) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            snapshot_id,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+
+    pub fn snapshot_id(&self) -> &[u8] {
+        &self.snapshot_id
+    }
+}
+
+impl Payload<'_> for CanisterSnapshotArgs {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     id : blob;
+///     taken_at_timestamp : nat64;
+///     total_size : nat64;
+/// })`
+#[derive(CandidType, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct CanisterSnapshotResponse {
+    #[serde(with = "serde_bytes")]
+    pub id: Vec<u8>,
+    pub taken_at_timestamp: u64,
+    pub total_size: u64,
+}
+
+impl Payload<'_> for CanisterSnapshotResponse {}
+
+/// Struct used for encoding/decoding the reply of `list_canister_snapshots`:
+/// `(vec snapshot)`.
+#[derive(CandidType, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct ListCanisterSnapshotsResponse(pub Vec<CanisterSnapshotResponse>);
+
+impl Payload<'_> for ListCanisterSnapshotsResponse {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     idx : nat64;
+///     timestamp_nanos : nat64;
+///     content : blob;
+/// })`
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct CanisterLogRecord {
+    pub idx: u64,
+    pub timestamp_nanos: u64,
+    #[serde(with = "serde_bytes")]
+    pub content: Vec<u8>,
+}
+
+impl CanisterLogRecord {
+    /// Returns the number of bytes a record takes in the canister log.
+    pub fn data_size(&self) -> usize {
+        std::mem::size_of::<u64>() * 2 + self.content.len()
+    }
+}
+
+/// Struct used for encoding/decoding the reply of `fetch_canister_logs`:
+/// `(record {
+///     canister_log_records : vec canister_log_record;
+/// })`
+#[derive(CandidType, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct FetchCanisterLogsResponse {
+    pub canister_log_records: Vec<CanisterLogRecord>,
+}
+
+impl Payload<'_> for FetchCanisterLogsResponse {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id : principal;
+///     chunk : blob;
+/// })`
+#[derive(CandidType, Deserialize, Debug)]
+pub struct UploadChunkArgs {
+    canister_id: PrincipalId,
+    #[serde(with = "serde_bytes")]
+    chunk: Vec<u8>,
+}
+
+impl UploadChunkArgs {
+    pub fn new(canister_id: CanisterId, chunk: Vec<u8>) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            chunk,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+
+    pub fn chunk(&self) -> &[u8] {
+        &self.chunk
+    }
+}
+
+impl Payload<'_> for UploadChunkArgs {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     hash : blob;
+/// })`
+///
+/// It is the reply of `upload_chunk` and an element of the reply of
+/// `stored_chunks`.
+#[derive(CandidType, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct ChunkHash {
+    #[serde(with = "serde_bytes")]
+    pub hash: Vec<u8>,
+}
+
+impl Payload<'_> for ChunkHash {}
+
+/// Struct used for encoding/decoding the reply of `stored_chunks`:
+/// `(vec chunk_hash)`.
+#[derive(CandidType, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct StoredChunksReply(pub Vec<ChunkHash>);
+
+impl Payload<'_> for StoredChunksReply {}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     mode : variant { install; reinstall; upgrade };
+///     target_canister : principal;
+///     chunk_hashes_list : vec chunk_hash;
+///     wasm_module_hash : blob;
+///     arg : blob;
+/// })`
+///
+/// The Wasm module is the concatenation of the chunks listed in
+/// `chunk_hashes_list`, which must be in the chunk store of
+/// `target_canister`.
+#[derive(Clone, CandidType, Deserialize, Debug)]
+pub struct InstallChunkedCodeArgs {
+    pub mode: CanisterInstallMode,
+    pub target_canister: PrincipalId,
+    pub chunk_hashes_list: Vec<ChunkHash>,
+    #[serde(with = "serde_bytes")]
+    pub wasm_module_hash: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub arg: Vec<u8>,
+}
+
+impl InstallChunkedCodeArgs {
+    pub fn new(
+        mode: CanisterInstallMode,
+        target_canister: CanisterId,
+        chunk_hashes_list: Vec<Vec<u8>>,
+        wasm_module_hash: Vec<u8>,
+        arg: Vec<u8>,
+    ) -> Self {
+        Self {
+            mode,
+            target_canister: target_canister.into(),
+            chunk_hashes_list: chunk_hashes_list
+                .into_iter()
+                .map(|hash| ChunkHash { hash })
+                .collect(),
+            wasm_module_hash,
+            arg,
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.target_canister).unwrap()
+    }
+}
+
+impl Payload<'_> for InstallChunkedCodeArgs {}
+
+/// The principal that triggered a change of a canister:
+/// `variant {
+///     from_user : record { user_id : principal };
+///     from_canister : record {
+///         canister_id : principal;
+///         canister_version : opt nat64;
+///     };
+/// }`
+#[derive(Clone, CandidType, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub enum CanisterChangeOrigin {
+    #[serde(rename = "from_user")]
+    FromUser { user_id: PrincipalId },
+    #[serde(rename = "from_canister")]
+    FromCanister {
+        canister_id: PrincipalId,
+        canister_version: Option<u64>,
+    },
+}
+
+impl CanisterChangeOrigin {
+    pub fn from_user(user_id: PrincipalId) -> Self {
+        Self::FromUser { user_id }
+    }
+
+    pub fn from_canister(canister_id: PrincipalId, canister_version: Option<u64>) -> Self {
+        Self::FromCanister {
+            canister_id,
+            canister_version,
+        }
+    }
+
+    /// Returns the principal that triggered the change.
+    pub fn origin(&self) -> PrincipalId {
+        match self {
+            Self::FromUser { user_id } => *user_id,
+            Self::FromCanister { canister_id, .. } => *canister_id,
+        }
+    }
+}
+
+/// The kind of a change of a canister:
+/// `variant {
+///     creation : record { controllers : vec principal };
+///     code_uninstall;
+///     code_deployment : record {
+///         mode : variant { install; reinstall; upgrade };
+///         module_hash : blob;
+///     };
+///     controllers_change : record { controllers : vec principal };
+/// }`
+#[derive(Clone, CandidType, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub enum CanisterChangeDetails {
+    #[serde(rename = "creation")]
+    Creation { controllers: Vec<PrincipalId> },
+    #[serde(rename = "code_uninstall")]
+    CodeUninstall,
+    #[serde(rename = "code_deployment")]
+    CodeDeployment {
+        mode: CanisterInstallMode,
+        #[serde(with = "serde_bytes")]
+        module_hash: Vec<u8>,
+    },
+    #[serde(rename = "controllers_change")]
+    ControllersChange { controllers: Vec<PrincipalId> },
+}
+
+impl CanisterChangeDetails {
+    pub fn canister_creation(controllers: Vec<PrincipalId>) -> Self {
+        Self::Creation { controllers }
+    }
+
+    pub fn code_deployment(mode: CanisterInstallMode, module_hash: [u8; 32]) -> Self {
+        Self::CodeDeployment {
+            mode,
+            module_hash: module_hash.to_vec(),
+        }
+    }
+
+    pub fn controllers_change(controllers: Vec<PrincipalId>) -> Self {
+        Self::ControllersChange { controllers }
+    }
+}
+
+/// An entry of the history of a canister:
+/// `(record {
+///     timestamp_nanos : nat64;
+///     canister_version : nat64;
+///     origin : change_origin;
+///     details : change_details;
+/// })`
+#[derive(Clone, CandidType, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct CanisterChange {
+    pub timestamp_nanos: u64,
+    pub canister_version: u64,
+    pub origin: CanisterChangeOrigin,
+    pub details: CanisterChangeDetails,
+}
+
+impl CanisterChange {
+    pub fn new(
+        timestamp_nanos: u64,
+        canister_version: u64,
+        origin: CanisterChangeOrigin,
+        details: CanisterChangeDetails,
+    ) -> Self {
+        Self {
+            timestamp_nanos,
+            canister_version,
+            origin,
+            details,
+        }
+    }
+}
+
+impl From<CanisterInstallMode> for pb_canister_state_bits::CanisterInstallMode {
+    fn from(item: CanisterInstallMode) -> Self {
+        match item {
+            CanisterInstallMode::Install => pb_canister_state_bits::CanisterInstallMode::Install,
+            CanisterInstallMode::Reinstall => {
+                pb_canister_state_bits::CanisterInstallMode::Reinstall
+            }
+            CanisterInstallMode::Upgrade => pb_canister_state_bits::CanisterInstallMode::Upgrade,
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::CanisterInstallMode> for CanisterInstallMode {
+    type Error = ProxyDecodeError;
+
+    fn try_from(item: pb_canister_state_bits::CanisterInstallMode) -> Result<Self, Self::Error> {
+        match item {
+            pb_canister_state_bits::CanisterInstallMode::Install => {
+                Ok(CanisterInstallMode::Install)
+            }
+            pb_canister_state_bits::CanisterInstallMode::Reinstall => {
+                Ok(CanisterInstallMode::Reinstall)
+            }
+            pb_canister_state_bits::CanisterInstallMode::Upgrade => {
+                Ok(CanisterInstallMode::Upgrade)
+            }
+            pb_canister_state_bits::CanisterInstallMode::Unspecified => {
+                Err(ProxyDecodeError::ValueOutOfRange {
+                    typ: "CanisterInstallMode",
+                    err: format!("Unable to convert {:?} to a CanisterInstallMode", item),
+                })
+            }
+        }
+    }
+}
+
+impl From<&CanisterChange> for pb_canister_state_bits::CanisterChange {
+    fn from(item: &CanisterChange) -> Self {
+        use pb_canister_state_bits::canister_change::{ChangeDetails, ChangeOrigin};
+
+        let change_origin = match &item.origin {
+            CanisterChangeOrigin::FromUser { user_id } => ChangeOrigin::CanisterChangeFromUser(
+                pb_canister_state_bits::CanisterChangeFromUser {
+                    user_id: Some((*user_id).into()),
+                },
+            ),
+            CanisterChangeOrigin::FromCanister {
+                canister_id,
+                canister_version,
+            } => ChangeOrigin::CanisterChangeFromCanister(
+                pb_canister_state_bits::CanisterChangeFromCanister {
+                    canister_id: Some((*canister_id).into()),
+                    canister_version: *canister_version,
+                },
+            ),
+        };
+        let change_details = match &item.details {
+            CanisterChangeDetails::Creation { controllers } => {
+                ChangeDetails::CanisterCreation(pb_canister_state_bits::CanisterCreation {
+                    controllers: controllers.iter().map(|c| (*c).into()).collect(),
+                })
+            }
+            CanisterChangeDetails::CodeUninstall => ChangeDetails::CanisterCodeUninstall(
+                pb_canister_state_bits::CanisterCodeUninstall {},
+            ),
+            CanisterChangeDetails::CodeDeployment { mode, module_hash } => {
+                ChangeDetails::CanisterCodeDeployment(
+                    pb_canister_state_bits::CanisterCodeDeployment {
+                        mode: pb_canister_state_bits::CanisterInstallMode::from(*mode) as i32,
+                        module_hash: module_hash.clone(),
+                    },
+                )
+            }
+            CanisterChangeDetails::ControllersChange { controllers } => {
+                ChangeDetails::CanisterControllersChange(
+                    pb_canister_state_bits::CanisterControllersChange {
+                        controllers: controllers.iter().map(|c| (*c).into()).collect(),
+                    },
+                )
+            }
+        };
+        Self {
+            timestamp_nanos: item.timestamp_nanos,
+            canister_version: item.canister_version,
+            change_origin: Some(change_origin),
+            change_details: Some(change_details),
+        }
+    }
+}
+
+impl TryFrom<pb_canister_state_bits::CanisterChange> for CanisterChange {
+    type Error = ProxyDecodeError;
+
+    fn try_from(item: pb_canister_state_bits::CanisterChange) -> Result<Self, Self::Error> {
+        use pb_canister_state_bits::canister_change::{ChangeDetails, ChangeOrigin};
+
+        let origin = match item.change_origin.ok_or(ProxyDecodeError::MissingField(
+            "CanisterChange::change_origin",
+        ))? {
+            ChangeOrigin::CanisterChangeFromUser(from_user) => CanisterChangeOrigin::from_user(
+                try_from_option_field(from_user.user_id, "CanisterChangeFromUser::user_id")?,
+            ),
+            ChangeOrigin::CanisterChangeFromCanister(from_canister) => {
+                CanisterChangeOrigin::from_canister(
+                    try_from_option_field(
+                        from_canister.canister_id,
+                        "CanisterChangeFromCanister::canister_id",
+                    )?,
+                    from_canister.canister_version,
+                )
+            }
+        };
+        let details = match item.change_details.ok_or(ProxyDecodeError::MissingField(
+            "CanisterChange::change_details",
+        ))? {
+            ChangeDetails::CanisterCreation(creation) => CanisterChangeDetails::Creation {
+                controllers: creation
+                    .controllers
+                    .into_iter()
+                    .map(PrincipalId::try_from)
+                    .collect::<Result<_, _>>()?,
+            },
+            ChangeDetails::CanisterCodeUninstall(_) => CanisterChangeDetails::CodeUninstall,
+            ChangeDetails::CanisterCodeDeployment(deployment) => {
+                let mode = pb_canister_state_bits::CanisterInstallMode::from_i32(deployment.mode)
+                    .unwrap_or_default();
+                CanisterChangeDetails::CodeDeployment {
+                    mode: CanisterInstallMode::try_from(mode)?,
+                    module_hash: deployment.module_hash,
+                }
+            }
+            ChangeDetails::CanisterControllersChange(change) => {
+                CanisterChangeDetails::ControllersChange {
+                    controllers: change
+                        .controllers
+                        .into_iter()
+                        .map(PrincipalId::try_from)
+                        .collect::<Result<_, _>>()?,
+                }
+            }
+        };
+        Ok(Self {
+            timestamp_nanos: item.timestamp_nanos,
+            canister_version: item.canister_version,
+            origin,
+            details,
+        })
+    }
+}
+
+/// Struct used for encoding/decoding
+/// `(record {
+///     canister_id : principal;
+///     num_requested_changes : opt nat64;
+/// })`
+#[derive(CandidType, Deserialize, Debug)]
+pub struct CanisterInfoRequest {
+    canister_id: PrincipalId,
+    num_requested_changes: Option<u64>,
+}
+
+impl CanisterInfoRequest {
+    pub fn new(canister_id: CanisterId, num_requested_changes: Option<u64>) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            num_requested_changes,
+        }
+    }
+
+    pub fn canister_id(&self) -> CanisterId {
+        // Safe as this was converted from CanisterId when Self was constructed.
+        CanisterId::new(self.canister_id).unwrap()
+    }
+
+    pub fn num_requested_changes(&self) -> Option<u64> {
+        self.num_requested_changes
+    }
+}
+
+impl Payload<'_> for CanisterInfoRequest {}
+
+/// Struct used for encoding/decoding the reply of `canister_info`:
+/// `(record {
+///     total_num_changes : nat64;
+///     recent_changes : vec change;
+///     module_hash : opt blob;
+///     controllers : vec principal;
+/// })`
+#[derive(CandidType, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct CanisterInfoResponse {
+    pub total_num_changes: u64,
+    pub recent_changes: Vec<CanisterChange>,
+    pub module_hash: Option<Vec<u8>>,
+    pub controllers: Vec<PrincipalId>,
+}
+
+impl Payload<'_> for CanisterInfoResponse {}
+
 /// Struct used for encoding/decoding
 /// `(record {
 ///     node_ids : vec principal;
@@ -969,12 +1632,17 @@ impl ComputeInitialEcdsaDealingsResponse {
 // Export the bitcoin types.
 pub use ic_btc_types::{
     GetBalanceRequest as BitcoinGetBalanceArgs,
+    GetBlockHeadersRequest as BitcoinGetBlockHeadersArgs,
+    GetBlockchainInfoRequest as BitcoinGetBlockchainInfoArgs,
     GetCurrentFeePercentilesRequest as BitcoinGetCurrentFeePercentilesArgs,
-    GetUtxosRequest as BitcoinGetUtxosArgs, Network as BitcoinNetwork,
-    SendTransactionRequest as BitcoinSendTransactionArgs,
+    GetTransactionsRequest as BitcoinGetTransactionsArgs, GetUtxosRequest as BitcoinGetUtxosArgs,
+    Network as BitcoinNetwork, SendTransactionRequest as BitcoinSendTransactionArgs,
 };
 
 impl Payload<'_> for BitcoinGetBalanceArgs {}
 impl Payload<'_> for BitcoinGetUtxosArgs {}
 impl Payload<'_> for BitcoinSendTransactionArgs {}
 impl Payload<'_> for BitcoinGetCurrentFeePercentilesArgs {}
+impl Payload<'_> for BitcoinGetBlockHeadersArgs {}
+impl Payload<'_> for BitcoinGetBlockchainInfoArgs {}
+impl Payload<'_> for BitcoinGetTransactionsArgs {}
@@ -0,0 +1,171 @@
+use crate::{
+    node_id_into_protobuf, node_id_try_from_protobuf, CanisterId, CountBytes, Height, NodeId,
+};
+use ic_protobuf::types::v1 as pb;
+use phantom_newtype::AmountOf;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+pub const MAX_QUERY_STATS_PAYLOAD_SIZE: usize = 1024 * 1024; // 1 MiB
+
+/// The number of consecutive batch heights that make up a query stats epoch.
+///
+/// Every replica collects the statistics of the non-replicated queries it
+/// executes against states within an epoch. Once the epoch is over, the
+/// replica proposes its statistics for inclusion in a block.
+pub const QUERY_STATS_EPOCH_LENGTH: u64 = 2000;
+
+pub struct QueryStatsEpochTag {}
+/// The index of a query stats epoch.
+pub type QueryStatsEpoch = AmountOf<QueryStatsEpochTag, u64>;
+
+/// Returns the query stats epoch that the state at the given height belongs to.
+pub fn epoch_from_height(height: Height) -> QueryStatsEpoch {
+    QueryStatsEpoch::from(height.get() / QUERY_STATS_EPOCH_LENGTH)
+}
+
+/// Statistics about the non-replicated queries executed on a canister.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QueryStats {
+    pub num_calls: u64,
+    pub num_instructions: u64,
+    pub ingress_payload_size: u64,
+    pub egress_payload_size: u64,
+}
+
+impl QueryStats {
+    /// Adds the given statistics to `self`, saturating on overflow.
+    pub fn saturating_accumulate(&mut self, other: &QueryStats) {
+        self.num_calls = self.num_calls.saturating_add(other.num_calls);
+        self.num_instructions = self.num_instructions.saturating_add(other.num_instructions);
+        self.ingress_payload_size = self
+            .ingress_payload_size
+            .saturating_add(other.ingress_payload_size);
+        self.egress_payload_size = self
+            .egress_payload_size
+            .saturating_add(other.egress_payload_size);
+    }
+}
+
+/// The query statistics collected by a single replica for a single canister.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanisterQueryStats {
+    pub canister_id: CanisterId,
+    pub stats: QueryStats,
+}
+
+/// Payload that contains the query statistics that the block proposer
+/// collected during a completed epoch.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QueryStatsPayload {
+    pub proposer: NodeId,
+    pub epoch: QueryStatsEpoch,
+    pub stats: Vec<CanisterQueryStats>,
+}
+
+impl CountBytes for QueryStatsPayload {
+    fn count_bytes(&self) -> usize {
+        std::mem::size_of::<NodeId>()
+            + std::mem::size_of::<QueryStatsEpoch>()
+            + self.stats.len() * std::mem::size_of::<CanisterQueryStats>()
+    }
+}
+
+impl From<&QueryStatsPayload> for pb::QueryStatsPayload {
+    fn from(payload: &QueryStatsPayload) -> Self {
+        Self {
+            proposer: Some(node_id_into_protobuf(payload.proposer)),
+            epoch: payload.epoch.get(),
+            canister_stats: payload
+                .stats
+                .iter()
+                .map(|canister_stats| pb::CanisterQueryStats {
+                    canister_id: Some(pb::CanisterId::from(canister_stats.canister_id)),
+                    num_calls: canister_stats.stats.num_calls,
+                    num_instructions: canister_stats.stats.num_instructions,
+                    ingress_payload_size: canister_stats.stats.ingress_payload_size,
+                    egress_payload_size: canister_stats.stats.egress_payload_size,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<pb::QueryStatsPayload> for QueryStatsPayload {
+    type Error = String;
+
+    fn try_from(payload: pb::QueryStatsPayload) -> Result<Self, Self::Error> {
+        let proposer = node_id_try_from_protobuf(
+            payload
+                .proposer
+                .ok_or("Error: query_stats_payload does not contain a proposer")?,
+        )
+        .map_err(|err| format!("{:?}", err))?;
+
+        Ok(QueryStatsPayload {
+            proposer,
+            epoch: QueryStatsEpoch::from(payload.epoch),
+            stats: payload
+                .canister_stats
+                .into_iter()
+                .map(|canister_stats| -> Result<CanisterQueryStats, String> {
+                    let canister_id = canister_stats
+                        .canister_id
+                        .ok_or_else(|| "No canister id on canister query stats".to_string())
+                        .and_then(|canister_id| {
+                            CanisterId::try_from(canister_id)
+                                .map_err(|e| format!("Proxy decode error {:?}", e))
+                        })?;
+                    Ok(CanisterQueryStats {
+                        canister_id,
+                        stats: QueryStats {
+                            num_calls: canister_stats.num_calls,
+                            num_instructions: canister_stats.num_instructions,
+                            ingress_payload_size: canister_stats.ingress_payload_size,
+                            egress_payload_size: canister_stats.egress_payload_size,
+                        },
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_base_types::PrincipalId;
+
+    #[test]
+    fn query_stats_payload_proto_round_trip() {
+        let payload = QueryStatsPayload {
+            proposer: NodeId::from(PrincipalId::new_node_test_id(1)),
+            epoch: QueryStatsEpoch::from(7),
+            stats: vec![CanisterQueryStats {
+                canister_id: CanisterId::from_u64(42),
+                stats: QueryStats {
+                    num_calls: 3,
+                    num_instructions: 1000,
+                    ingress_payload_size: 20,
+                    egress_payload_size: 40,
+                },
+            }],
+        };
+
+        let proto = pb::QueryStatsPayload::from(&payload);
+        assert_eq!(QueryStatsPayload::try_from(proto).unwrap(), payload);
+    }
+
+    #[test]
+    fn epoch_from_height_rounds_down() {
+        assert_eq!(epoch_from_height(Height::from(0)), QueryStatsEpoch::from(0));
+        assert_eq!(
+            epoch_from_height(Height::from(QUERY_STATS_EPOCH_LENGTH - 1)),
+            QueryStatsEpoch::from(0)
+        );
+        assert_eq!(
+            epoch_from_height(Height::from(QUERY_STATS_EPOCH_LENGTH)),
+            QueryStatsEpoch::from(1)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! The log of a canister, populated by `ic0.debug_print` and by traps.
+use ic_ic00_types::CanisterLogRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// The maximum number of bytes that the log of a canister can take. When the
+/// limit is exceeded, the oldest records are dropped.
+pub const MAX_CANISTER_LOG_BUFFER_SIZE: usize = 4 * 1024;
+
+/// The maximum size of the content of a single record. Longer contents are
+/// truncated.
+const MAX_RECORD_CONTENT_SIZE: usize =
+    MAX_CANISTER_LOG_BUFFER_SIZE - 2 * std::mem::size_of::<u64>();
+
+/// A bounded ring buffer of log records.
+///
+/// Record indices are assigned in increasing order and are never reused, so
+/// readers can tell whether records were dropped in between two fetches.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanisterLog {
+    next_idx: u64,
+    records: VecDeque<CanisterLogRecord>,
+    used_space: usize,
+}
+
+impl CanisterLog {
+    /// Restores a log from its records, e.g. when loading a checkpoint.
+    pub fn new(next_idx: u64, records: Vec<CanisterLogRecord>) -> Self {
+        let mut log = Self {
+            next_idx,
+            records: VecDeque::new(),
+            used_space: 0,
+        };
+        for record in records {
+            log.push_record(record);
+        }
+        log
+    }
+
+    /// Returns the index that will be assigned to the next record.
+    pub fn next_idx(&self) -> u64 {
+        self.next_idx
+    }
+
+    /// Returns the records of the log, from the oldest to the newest.
+    pub fn records(&self) -> &VecDeque<CanisterLogRecord> {
+        &self.records
+    }
+
+    /// Returns the number of bytes taken by the records of the log.
+    pub fn used_space(&self) -> usize {
+        self.used_space
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Adds a new record to the log, dropping the oldest records if the log
+    /// exceeds its capacity.
+    pub fn add_record(&mut self, timestamp_nanos: u64, mut content: Vec<u8>) {
+        content.truncate(MAX_RECORD_CONTENT_SIZE);
+        let record = CanisterLogRecord {
+            idx: self.next_idx,
+            timestamp_nanos,
+            content,
+        };
+        self.next_idx += 1;
+        self.push_record(record);
+    }
+
+    /// Moves all records of `delta` to the end of this log, assigning them
+    /// new indices.
+    pub fn append(&mut self, delta: &mut CanisterLog) {
+        for record in delta.records.drain(..) {
+            self.add_record(record.timestamp_nanos, record.content);
+        }
+        delta.used_space = 0;
+    }
+
+    fn push_record(&mut self, record: CanisterLogRecord) {
+        self.used_space += record.data_size();
+        self.records.push_back(record);
+        while self.used_space > MAX_CANISTER_LOG_BUFFER_SIZE {
+            let dropped = self.records.pop_front().unwrap();
+            self.used_space -= dropped.data_size();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_get_increasing_indices() {
+        let mut log = CanisterLog::default();
+        log.add_record(10, b"a".to_vec());
+        log.add_record(20, b"b".to_vec());
+
+        let records: Vec<_> = log.records().iter().cloned().collect();
+        assert_eq!(
+            records,
+            vec![
+                CanisterLogRecord {
+                    idx: 0,
+                    timestamp_nanos: 10,
+                    content: b"a".to_vec(),
+                },
+                CanisterLogRecord {
+                    idx: 1,
+                    timestamp_nanos: 20,
+                    content: b"b".to_vec(),
+                },
+            ]
+        );
+        assert_eq!(log.next_idx(), 2);
+    }
+
+    #[test]
+    fn oldest_records_are_dropped_when_full() {
+        let mut log = CanisterLog::default();
+        let content = vec![0; 1000];
+        for i in 0..10 {
+            log.add_record(i, content.clone());
+        }
+        assert!(log.used_space() <= MAX_CANISTER_LOG_BUFFER_SIZE);
+        assert_eq!(log.records().back().unwrap().idx, 9);
+        assert_eq!(log.records().front().unwrap().idx, 6);
+    }
+
+    #[test]
+    fn long_records_are_truncated() {
+        let mut log = CanisterLog::default();
+        log.add_record(0, vec![0; 2 * MAX_CANISTER_LOG_BUFFER_SIZE]);
+        assert_eq!(log.records().len(), 1);
+        assert_eq!(log.used_space(), MAX_CANISTER_LOG_BUFFER_SIZE);
+    }
+
+    #[test]
+    fn append_reindexes_records() {
+        let mut log = CanisterLog::default();
+        log.add_record(0, b"a".to_vec());
+
+        let mut delta = CanisterLog::default();
+        delta.add_record(1, b"b".to_vec());
+        log.append(&mut delta);
+
+        assert!(delta.is_empty());
+        assert_eq!(delta.used_space(), 0);
+        let last = log.records().back().unwrap();
+        assert_eq!((last.idx, last.timestamp_nanos), (1, 1));
+        assert_eq!(log.next_idx(), 2);
+    }
+}